@@ -0,0 +1,13 @@
+//! Generates the gRPC service code from `proto/ghrust.proto` for builds with
+//! the `grpc` feature, using the vendored `protoc` so no system install is
+//! needed. Builds without the feature skip code generation.
+
+fn main() {
+    #[cfg(feature = "grpc")]
+    {
+        println!("cargo:rerun-if-changed=proto/ghrust.proto");
+        let protoc = protoc_bin_vendored::protoc_bin_path().expect("vendored protoc not found");
+        std::env::set_var("PROTOC", protoc);
+        tonic_build::compile_protos("proto/ghrust.proto").expect("failed to compile protos");
+    }
+}
@@ -0,0 +1,61 @@
+//! # gRPC Server
+//!
+//! This binary serves the `MetricsService` of `proto/ghrust.proto`, so
+//! internal platform services can fetch Copilot metrics over gRPC instead of
+//! invoking the Lambda.
+//!
+//! ## RPCs
+//!
+//! - `FetchEnterpriseMetrics` - The days of a configured enterprise
+//! - `FetchTeamMetrics` - The days of a team of a configured enterprise
+//!
+//! ## Usage
+//!
+//! 1. Set the environment variables of the Lambda (see the README);
+//!    `GHRUST_GRPC_ADDR` sets the listen address (default: 0.0.0.0:50051)
+//!
+//! 2. Run the binary: `cargo run --features grpc --bin grpc_server`
+//!
+//! The configuration is loaded once at startup.
+
+use std::env;
+
+use anyhow::{Context, Result};
+use tonic::transport::Server;
+use tracing::info;
+
+use ghrust::config::logging::log_filter;
+use ghrust::config::Config;
+use ghrust::grpc::MetricsGrpcService;
+
+/// Address the server listens on unless `GHRUST_GRPC_ADDR` is set
+const DEFAULT_ADDR: &str = "0.0.0.0:50051";
+
+/// Main entry point for the gRPC server
+///
+/// # Returns
+///
+/// A Result indicating success or containing an error if the server fails
+#[tokio::main]
+async fn main() -> Result<()> {
+    dotenvy::dotenv().ok();
+
+    tracing_subscriber::fmt()
+        .with_env_filter(log_filter())
+        .with_target(false)
+        .init();
+
+    let config = Config::from_env()?;
+    let addr = env::var("GHRUST_GRPC_ADDR").unwrap_or_else(|_| DEFAULT_ADDR.to_string());
+    let socket = addr
+        .parse()
+        .with_context(|| format!("Invalid GHRUST_GRPC_ADDR: {}", addr))?;
+
+    info!("Serving gRPC on {}", addr);
+    Server::builder()
+        .add_service(MetricsGrpcService::from_config(&config).into_server())
+        .serve(socket)
+        .await?;
+
+    Ok(())
+}
@@ -0,0 +1,243 @@
+//! # Mock Datadog Server
+//!
+//! This binary accepts Datadog v2 series submissions on a local port,
+//! validates them and writes every payload to disk, so developers can run the
+//! whole pipeline locally and inspect exactly what would have been submitted.
+//!
+//! ## Usage
+//!
+//! 1. Start the server: `cargo run --bin mock_datadog -- [--addr <host:port>] [--out <dir>]`
+//!
+//! 2. Point the collector at it by setting the Datadog site to its URL, e.g.
+//!    `DATADOG_SITE=http://127.0.0.1:8126`, and run the collector.
+//!
+//! Each accepted request is written to `<dir>/payload-0001.json`,
+//! `payload-0002.json`, ... (default directory: `mock-datadog`). Requests
+//! without an API key or with an invalid payload are answered with HTTP 400
+//! (403 for a missing key) and the problems found, like Datadog does; they
+//! are logged but not written.
+
+use anyhow::{anyhow, bail, Context, Result};
+use serde_json::{json, Value};
+use std::env;
+use std::fs;
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::path::{Path, PathBuf};
+
+/// Address the server listens on unless `--addr` is given
+const DEFAULT_ADDR: &str = "127.0.0.1:8126";
+
+/// Directory payloads are written to unless `--out` is given
+const DEFAULT_OUT: &str = "mock-datadog";
+
+/// Path of the series endpoint
+const SERIES_PATH: &str = "/api/v2/series";
+
+/// Metric types accepted by the v2 series endpoint
+const METRIC_TYPES: [&str; 4] = ["UNSPECIFIED", "COUNT", "RATE", "GAUGE"];
+
+/// Main entry point for the mock server
+///
+/// # Returns
+///
+/// A Result indicating success or containing an error if the server can't start
+fn main() -> Result<()> {
+    let mut addr = DEFAULT_ADDR.to_string();
+    let mut out = PathBuf::from(DEFAULT_OUT);
+    let mut args = env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--addr" => {
+                addr = args
+                    .next()
+                    .ok_or_else(|| anyhow!("--addr needs an address"))?
+            }
+            "--out" => {
+                out = args
+                    .next()
+                    .ok_or_else(|| anyhow!("--out needs a directory"))?
+                    .into()
+            }
+            other => bail!("Unknown argument: {}", other),
+        }
+    }
+
+    fs::create_dir_all(&out).with_context(|| format!("Failed to create {}", out.display()))?;
+    let listener = TcpListener::bind(&addr).with_context(|| format!("Failed to bind {}", addr))?;
+    println!(
+        "Mock Datadog listening on http://{} (payloads in {})",
+        addr,
+        out.display()
+    );
+
+    let mut accepted = 0;
+    for stream in listener.incoming() {
+        let mut stream = match stream {
+            Ok(stream) => stream,
+            Err(e) => {
+                eprintln!("Connection failed: {}", e);
+                continue;
+            }
+        };
+        if let Err(e) = handle(&mut stream, &out, &mut accepted) {
+            eprintln!("Request failed: {:#}", e);
+        }
+    }
+
+    Ok(())
+}
+
+/// Reads one request from the connection and answers it
+///
+/// # Arguments
+///
+/// * `stream` - The client connection
+/// * `out` - Directory accepted payloads are written to
+/// * `accepted` - Number of payloads accepted so far
+fn handle(stream: &mut TcpStream, out: &Path, accepted: &mut usize) -> Result<()> {
+    let mut reader = BufReader::new(stream.try_clone()?);
+
+    let mut request_line = String::new();
+    reader.read_line(&mut request_line)?;
+    let mut parts = request_line.split_whitespace();
+    let method = parts.next().unwrap_or_default().to_string();
+    let path = parts.next().unwrap_or_default().to_string();
+
+    let mut content_length = None;
+    let mut api_key = None;
+    loop {
+        let mut line = String::new();
+        reader.read_line(&mut line)?;
+        let line = line.trim_end();
+        if line.is_empty() {
+            break;
+        }
+        if let Some((name, value)) = line.split_once(':') {
+            let value = value.trim();
+            if name.eq_ignore_ascii_case("content-length") {
+                content_length = value.parse::<usize>().ok();
+            } else if name.eq_ignore_ascii_case("dd-api-key") && !value.is_empty() {
+                api_key = Some(value.to_string());
+            }
+        }
+    }
+
+    if method != "POST" || path != SERIES_PATH {
+        return respond(
+            stream,
+            "404 Not Found",
+            &[format!("No route for {} {}", method, path)],
+        );
+    }
+    let Some(content_length) = content_length else {
+        return respond(
+            stream,
+            "411 Length Required",
+            &["Content-Length is required".to_string()],
+        );
+    };
+
+    let mut body = vec![0; content_length];
+    reader.read_exact(&mut body)?;
+
+    if api_key.is_none() {
+        return respond(
+            stream,
+            "403 Forbidden",
+            &["DD-API-KEY header is missing".to_string()],
+        );
+    }
+
+    let payload: Value = match serde_json::from_slice(&body) {
+        Ok(payload) => payload,
+        Err(e) => return respond(stream, "400 Bad Request", &[format!("Invalid JSON: {}", e)]),
+    };
+
+    let problems = validate(&payload);
+    if !problems.is_empty() {
+        eprintln!("Rejected payload: {}", problems.join("; "));
+        return respond(stream, "400 Bad Request", &problems);
+    }
+
+    *accepted += 1;
+    let file = out.join(format!("payload-{:04}.json", accepted));
+    fs::write(&file, serde_json::to_string_pretty(&payload)?)?;
+    println!(
+        "Accepted {} series -> {}",
+        payload["series"].as_array().map_or(0, Vec::len),
+        file.display()
+    );
+
+    respond(stream, "202 Accepted", &[])
+}
+
+/// Validates a v2 series payload
+///
+/// # Arguments
+///
+/// * `payload` - The parsed request body
+///
+/// # Returns
+///
+/// * `Vec<String>` - The problems found, empty if the payload is valid
+fn validate(payload: &Value) -> Vec<String> {
+    let Some(series) = payload["series"].as_array() else {
+        return vec!["`series` must be an array".to_string()];
+    };
+
+    let mut problems = Vec::new();
+    for (i, s) in series.iter().enumerate() {
+        let mut problem = |message: &str| problems.push(format!("series[{}]: {}", i, message));
+
+        match s["metric"].as_str() {
+            Some(name) if !name.is_empty() => {}
+            _ => problem("`metric` must be a non-empty string"),
+        }
+        match &s["type"] {
+            Value::Null => {}
+            Value::String(t) if METRIC_TYPES.contains(&t.as_str()) => {}
+            Value::Number(n) if n.as_u64().is_some_and(|n| n <= 3) => {}
+            other => problem(&format!("unknown `type` {}", other)),
+        }
+        match s["points"].as_array() {
+            Some(points) if !points.is_empty() => {
+                for point in points {
+                    if !point["timestamp"].is_i64() {
+                        problem("every point needs an integer `timestamp`");
+                    }
+                    if !point["value"].is_number() {
+                        problem("every point needs a numeric `value`");
+                    }
+                }
+            }
+            _ => problem("`points` must be a non-empty array"),
+        }
+        match &s["tags"] {
+            Value::Null => {}
+            Value::Array(tags) if tags.iter().all(Value::is_string) => {}
+            _ => problem("`tags` must be an array of strings"),
+        }
+    }
+
+    problems
+}
+
+/// Writes a response with Datadog's `{"errors": [...]}` body
+///
+/// # Arguments
+///
+/// * `stream` - The client connection
+/// * `status` - HTTP status line, e.g. "202 Accepted"
+/// * `errors` - Errors reported in the body
+fn respond(stream: &mut TcpStream, status: &str, errors: &[String]) -> Result<()> {
+    let body = json!({ "errors": errors }).to_string();
+    write!(
+        stream,
+        "HTTP/1.1 {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        status,
+        body.len(),
+        body
+    )?;
+    Ok(())
+}
@@ -0,0 +1,264 @@
+//! # Collection Server
+//!
+//! This binary runs the metrics pipeline behind an HTTP endpoint instead of
+//! the Lambda runtime, so internal tooling can trigger collections and
+//! operators can re-run parts of a collection by hand.
+//!
+//! ## Endpoints
+//!
+//! - `POST /collect` - Runs the pipeline and returns the run report. The
+//!   optional JSON body narrows the run, e.g.
+//!   `{"enterprise_id": "acme", "team_slug": "platform", "since": "2025-06-01", "until": "2025-06-07"}`.
+//!   Only one run executes at a time; concurrent requests get `409 Conflict`.
+//! - `GET /metrics/enterprise?from=&to=` - The stored days of the enterprise
+//!   within the optional date range (YYYY-MM-DD, inclusive).
+//! - `GET /metrics/teams/{slug}?from=&to=` - The stored days of a team.
+//!
+//! The read endpoints are served from the metrics store (`METRICS_STORE_PATH`)
+//! and answer `503 Service Unavailable` without one. With several enterprises
+//! configured, `enterprise_id=` selects one; the first is queried otherwise.
+//!
+//! ## Usage
+//!
+//! 1. Set the environment variables of the Lambda (see the README);
+//!    `GHRUST_SERVER_ADDR` sets the listen address (default: 0.0.0.0:8080)
+//!
+//! 2. Run the binary: `cargo run --features server --bin server`
+//!
+//! The configuration is loaded for every request, and each run opens its own
+//! CA bundle, metrics store, ETag cache, raw archive and exporters from it, so
+//! changes to the configuration file (`GHRUST_CONFIG_FILE`) and rotated Vault
+//! secrets apply to the next request without a restart. Environment variables
+//! are the process's own, and the listen address and log filter are only read
+//! at startup.
+
+use std::env;
+use std::sync::Arc;
+
+use anyhow::Result;
+use axum::body::Bytes;
+use axum::extract::rejection::QueryRejection;
+use axum::extract::{Path, Query, State};
+use axum::http::StatusCode;
+use axum::routing::{get, post};
+use axum::{Json, Router};
+use chrono::NaiveDate;
+use serde::Deserialize;
+use serde_json::{json, Value};
+use tokio::sync::Mutex;
+use tracing::{error, info};
+
+use ghrust::config::logging::log_filter;
+use ghrust::config::Config;
+use ghrust::error::GhrustError;
+use ghrust::pipeline::{MetricsPipeline, RunScope};
+use ghrust::services::store::{self, Store};
+
+/// Address the server listens on unless `GHRUST_SERVER_ADDR` is set
+const DEFAULT_ADDR: &str = "0.0.0.0:8080";
+
+/// Held while a run is in progress
+type RunLock = Arc<Mutex<()>>;
+
+/// Query parameters of the read endpoints
+#[derive(Debug, Default, Deserialize)]
+struct MetricsQuery {
+    /// Configured enterprise to query, the first one if unset
+    enterprise_id: Option<String>,
+    /// First day to return (inclusive)
+    from: Option<NaiveDate>,
+    /// Last day to return (inclusive)
+    to: Option<NaiveDate>,
+}
+
+/// Main entry point for the collection server
+///
+/// # Returns
+///
+/// A Result indicating success or containing an error if the server fails
+#[tokio::main]
+async fn main() -> Result<()> {
+    dotenvy::dotenv().ok();
+
+    tracing_subscriber::fmt()
+        .with_env_filter(log_filter())
+        .with_target(false)
+        .init();
+
+    let addr = env::var("GHRUST_SERVER_ADDR").unwrap_or_else(|_| DEFAULT_ADDR.to_string());
+    let app = Router::new()
+        .route("/collect", post(collect))
+        .route("/metrics/enterprise", get(enterprise_metrics))
+        .route("/metrics/teams/:slug", get(team_metrics))
+        .with_state(RunLock::default());
+
+    let listener = tokio::net::TcpListener::bind(&addr).await?;
+    info!("Listening on {}", addr);
+    axum::serve(listener, app).await?;
+
+    Ok(())
+}
+
+/// Handles `POST /collect`
+///
+/// # Arguments
+///
+/// * `lock` - Lock serializing runs
+/// * `body` - Optional JSON `RunScope` narrowing the run
+///
+/// # Returns
+///
+/// * `(StatusCode, Json<Value>)` - The run report, or an error with its kind
+async fn collect(State(lock): State<RunLock>, body: Bytes) -> (StatusCode, Json<Value>) {
+    let scope = if body.is_empty() {
+        RunScope::default()
+    } else {
+        match serde_json::from_slice::<RunScope>(&body) {
+            Ok(scope) => scope,
+            Err(e) => {
+                return (
+                    StatusCode::BAD_REQUEST,
+                    Json(json!({ "error": format!("Invalid request body: {}", e) })),
+                )
+            }
+        }
+    };
+
+    let Ok(_guard) = lock.try_lock() else {
+        return (
+            StatusCode::CONFLICT,
+            Json(json!({ "error": "A run is already in progress" })),
+        );
+    };
+
+    info!("Starting collection for {:?}", scope);
+    let result = tokio::task::spawn_blocking(move || {
+        let config = Config::from_env()
+            .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, GhrustError::from(e)))?;
+        let pipeline = MetricsPipeline::new(config)
+            .with_scope(scope)
+            .map_err(|e| (StatusCode::BAD_REQUEST, e))?;
+        pipeline
+            .run_blocking()
+            .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e))
+    })
+    .await;
+
+    match result {
+        Ok(Ok(report)) => (StatusCode::OK, Json(json!(report))),
+        Ok(Err((status, e))) => {
+            error!("Collection failed: {}", e);
+            (
+                status,
+                Json(json!({ "error": e.to_string(), "kind": e.kind() })),
+            )
+        }
+        Err(e) => {
+            error!("Collection panicked: {}", e);
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(json!({ "error": "The run panicked" })),
+            )
+        }
+    }
+}
+
+/// Handles `GET /metrics/enterprise`
+async fn enterprise_metrics(
+    query: std::result::Result<Query<MetricsQuery>, QueryRejection>,
+) -> (StatusCode, Json<Value>) {
+    query_store(query, None).await
+}
+
+/// Handles `GET /metrics/teams/{slug}`
+async fn team_metrics(
+    Path(slug): Path<String>,
+    query: std::result::Result<Query<MetricsQuery>, QueryRejection>,
+) -> (StatusCode, Json<Value>) {
+    query_store(query, Some(slug)).await
+}
+
+/// Answers a read request from the metrics store
+///
+/// # Arguments
+///
+/// * `query` - The parsed query parameters, or why they couldn't be parsed
+/// * `team_slug` - Team to return the days of, the enterprise's if `None`
+///
+/// # Returns
+///
+/// * `(StatusCode, Json<Value>)` - The scope and its stored days, or an error
+async fn query_store(
+    query: std::result::Result<Query<MetricsQuery>, QueryRejection>,
+    team_slug: Option<String>,
+) -> (StatusCode, Json<Value>) {
+    let Query(query) = match query {
+        Ok(query) => query,
+        Err(e) => {
+            return (
+                StatusCode::BAD_REQUEST,
+                Json(json!({ "error": e.body_text() })),
+            )
+        }
+    };
+
+    match tokio::task::spawn_blocking(move || load_days(&query, team_slug.as_deref())).await {
+        Ok(Ok(body)) => (StatusCode::OK, Json(body)),
+        Ok(Err((status, message))) => (status, Json(json!({ "error": message }))),
+        Err(e) => {
+            error!("Query panicked: {}", e);
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(json!({ "error": "The query panicked" })),
+            )
+        }
+    }
+}
+
+/// Loads the stored days a read request asks for
+fn load_days(
+    query: &MetricsQuery,
+    team_slug: Option<&str>,
+) -> std::result::Result<Value, (StatusCode, String)> {
+    if let (Some(from), Some(to)) = (query.from, query.to) {
+        if from > to {
+            return Err((
+                StatusCode::BAD_REQUEST,
+                format!("from ({}) is after to ({})", from, to),
+            ));
+        }
+    }
+
+    let internal = |e: &dyn std::fmt::Display| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string());
+    let config = Config::from_env().map_err(|e| internal(&e))?;
+    let enterprise = match &query.enterprise_id {
+        Some(id) => config.enterprises.iter().find(|e| &e.id == id),
+        None => config.enterprises.first(),
+    }
+    .ok_or_else(|| {
+        (
+            StatusCode::NOT_FOUND,
+            format!(
+                "Enterprise {} is not configured",
+                query.enterprise_id.as_deref().unwrap_or_default()
+            ),
+        )
+    })?;
+
+    let metrics_store = Store::open(config.store_path.as_deref(), false, &config.agent)
+        .map_err(|e| internal(&e))?;
+    let metrics_store = metrics_store.backend().ok_or_else(|| {
+        (
+            StatusCode::SERVICE_UNAVAILABLE,
+            "No metrics store is configured (METRICS_STORE_PATH)".to_string(),
+        )
+    })?;
+
+    let scope = match team_slug {
+        Some(slug) => format!("team:{}/{}", enterprise.id, slug),
+        None => format!("enterprise:{}", enterprise.id),
+    };
+    let days =
+        store::load_range(metrics_store, &scope, query.from, query.to).map_err(|e| internal(&e))?;
+    Ok(json!({ "scope": scope, "days": days }))
+}
@@ -0,0 +1,204 @@
+//! # Enterprise Metrics Test Tool
+//!
+//! This binary provides a command-line utility for testing the GitHub enterprise
+//! metrics functionality. It fetches the enterprise-wide metrics and prints
+//! them without sending anything to Datadog, which is useful for:
+//!
+//! - Verifying that the GitHub API integration is working correctly
+//! - Debugging enterprise metrics issues
+//! - Viewing the per-language, per-editor and per-model breakdowns of a day
+//!
+//! ## Usage
+//!
+//! 1. Set the required environment variables:
+//!    - GITHUB_TOKEN: A valid GitHub personal access token
+//!    - GITHUB_ENTERPRISE_ID: ID of the GitHub Enterprise organization
+//!
+//! 2. Run the binary:
+//!    `cargo run --bin test_enterprise_metrics -- [--since YYYY-MM-DD] [--until YYYY-MM-DD] [--breakdowns] [--arrow PATH]`
+//!
+//! Without `--since` the last 30 days are fetched. `--until` drops later days,
+//! and `--breakdowns` adds the breakdowns of every day to its summary.
+//! `--arrow` additionally writes the days to an Arrow IPC (Feather) file; it
+//! needs a build with the `arrow` feature.
+
+use anyhow::{anyhow, bail, Context, Result};
+use chrono::{Duration, NaiveDate, Utc};
+use std::env;
+
+use ghrust::models::github::CopilotMetrics;
+use ghrust::services::github::{GitHubClient, GitHubSettings, REPORTING_WINDOW_DAYS};
+use ghrust::services::tls::AgentConfig;
+
+/// Main entry point for the enterprise metrics test tool
+///
+/// This function:
+/// 1. Loads environment variables and parses the command-line arguments
+/// 2. Fetches the enterprise metrics since the requested date
+/// 3. Prints a summary of every day, optionally with its breakdowns
+///
+/// # Returns
+///
+/// A Result indicating success or containing an error if any step fails
+fn main() -> Result<()> {
+    // Load environment variables
+    dotenvy::dotenv().ok();
+
+    let github_token = env::var("GITHUB_TOKEN").expect("GITHUB_TOKEN environment variable not set");
+    let enterprise_id = env::var("GITHUB_ENTERPRISE_ID")
+        .expect("GITHUB_ENTERPRISE_ID environment variable not set");
+
+    let mut since = Utc::now().date_naive() - Duration::days(REPORTING_WINDOW_DAYS);
+    let mut until = None;
+    let mut breakdowns = false;
+    let mut arrow_path = None;
+    let mut args = env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--since" => since = parse_date(args.next(), "--since")?,
+            "--until" => until = Some(parse_date(args.next(), "--until")?),
+            "--breakdowns" => breakdowns = true,
+            "--arrow" => {
+                arrow_path = Some(
+                    args.next()
+                        .ok_or_else(|| anyhow!("--arrow needs a file path"))?,
+                )
+            }
+            other => bail!("Unknown argument: {}", other),
+        }
+    }
+
+    let client = GitHubClient::builder()
+        .token(&github_token)
+        .settings(GitHubSettings {
+            agent: AgentConfig::from_env()?,
+            ..GitHubSettings::from_env()
+        })
+        .build()?;
+
+    println!(
+        "Fetching enterprise metrics for {} since {}",
+        enterprise_id, since
+    );
+    let mut metrics =
+        client.fetch_enterprise_metrics(&enterprise_id, &since.format("%Y-%m-%d").to_string())?;
+    if let Some(until) = until {
+        let until = until.format("%Y-%m-%d").to_string();
+        metrics.retain(|m| m.date <= until);
+    }
+    metrics.sort_by(|a, b| a.date.cmp(&b.date));
+
+    println!("Fetched {} days of metrics", metrics.len());
+    for metric in &metrics {
+        print_summary(metric);
+        if breakdowns {
+            print_breakdowns(metric);
+        }
+        println!("-------------------------");
+    }
+
+    if let Some(path) = arrow_path {
+        write_arrow(&path, &enterprise_id, &metrics)?;
+    }
+
+    Ok(())
+}
+
+/// Writes the days to an Arrow IPC file
+#[cfg(feature = "arrow")]
+fn write_arrow(path: &str, enterprise_id: &str, metrics: &[CopilotMetrics]) -> Result<()> {
+    let file = std::fs::File::create(path).with_context(|| format!("Cannot create {}", path))?;
+    let scope = format!("enterprise:{}", enterprise_id);
+    let rows = ghrust::services::export::arrow::write_metrics(file, &scope, metrics)?;
+    println!("Wrote {} rows to {}", rows, path);
+    Ok(())
+}
+
+/// Fails: writing Arrow files needs the `arrow` feature
+#[cfg(not(feature = "arrow"))]
+fn write_arrow(_path: &str, _enterprise_id: &str, _metrics: &[CopilotMetrics]) -> Result<()> {
+    bail!("--arrow requires a build with the arrow feature (cargo run --features arrow ...)")
+}
+
+/// Parses the date following a flag
+fn parse_date(value: Option<String>, flag: &str) -> Result<NaiveDate> {
+    let value = value.ok_or_else(|| anyhow!("{} needs a date (YYYY-MM-DD)", flag))?;
+    NaiveDate::parse_from_str(&value, "%Y-%m-%d")
+        .with_context(|| format!("Invalid date for {}: {}", flag, value))
+}
+
+/// Prints the totals of a day
+fn print_summary(metric: &CopilotMetrics) {
+    println!("Date: {}", metric.date);
+    if let Some(active) = metric.total_active_users {
+        println!("Total Active Users: {}", active);
+    }
+    if let Some(engaged) = metric.total_engaged_users {
+        println!("Total Engaged Users: {}", engaged);
+    }
+    if let Some(completions) = &metric.copilot_ide_code_completions {
+        println!(
+            "IDE Code Completions Engaged Users: {}",
+            completions.total_engaged_users
+        );
+    }
+    if let Some(chat) = &metric.copilot_ide_chat {
+        println!("IDE Chat Engaged Users: {}", chat.total_engaged_users);
+    }
+    if let Some(chat) = &metric.copilot_dotcom_chat {
+        println!(
+            "GitHub.com Chat Engaged Users: {}",
+            chat.total_engaged_users
+        );
+    }
+    if let Some(pr) = &metric.copilot_dotcom_pull_requests {
+        println!("Pull Request Engaged Users: {}", pr.total_engaged_users);
+    }
+    if let Some(review) = &metric.copilot_code_review {
+        println!("Code Review Engaged Users: {}", review.total_engaged_users);
+    }
+}
+
+/// Prints the per-language, per-editor and per-model breakdowns of a day
+fn print_breakdowns(metric: &CopilotMetrics) {
+    if let Some(completions) = &metric.copilot_ide_code_completions {
+        for language in completions.languages.iter().flatten() {
+            println!(
+                "  Language {}: {} engaged, {} suggestions, {} acceptances",
+                language.name,
+                language.total_engaged_users,
+                language.total_code_suggestions.unwrap_or(0),
+                language.total_code_acceptances.unwrap_or(0)
+            );
+        }
+        for editor in completions.editors.iter().flatten() {
+            println!(
+                "  Completions editor {}: {} engaged",
+                editor.name, editor.total_engaged_users
+            );
+        }
+    }
+
+    if let Some(chat) = &metric.copilot_ide_chat {
+        for editor in chat.editors.iter().flatten() {
+            for model in editor.models.iter().flatten() {
+                println!(
+                    "  Chat {} / {}: {} engaged, {} chats",
+                    editor.name,
+                    model.name,
+                    model.total_engaged_users,
+                    model.total_chats.unwrap_or(0)
+                );
+            }
+        }
+    }
+
+    if let Some(pr) = &metric.copilot_dotcom_pull_requests {
+        for repository in pr.repositories.iter().flatten() {
+            println!(
+                "  Repository {}: {} engaged",
+                repository.name, repository.total_engaged_users
+            );
+        }
+    }
+}
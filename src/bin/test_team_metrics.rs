@@ -25,7 +25,8 @@ use anyhow::Result;
 use std::env;
 
 // Import only what we need
-use ghrust::services::github::{get_team_metrics, GitHubClient};
+use ghrust::services::github::{get_team_metrics, GitHubClient, GitHubSettings};
+use ghrust::services::tls::AgentConfig;
 
 /// Main entry point for the team metrics test tool
 ///
@@ -39,8 +40,7 @@ use ghrust::services::github::{get_team_metrics, GitHubClient};
 /// # Returns
 ///
 /// A Result indicating success or containing an error if any step fails
-#[tokio::main]
-async fn main() -> Result<()> {
+fn main() -> Result<()> {
     // Load environment variables
     dotenvy::dotenv().ok();
 
@@ -49,7 +49,13 @@ async fn main() -> Result<()> {
         .expect("GITHUB_ENTERPRISE_ID environment variable not set");
 
     // Create a GitHub client
-    let client = GitHubClient::new(&github_token);
+    let client = GitHubClient::builder()
+        .token(&github_token)
+        .settings(GitHubSettings {
+            agent: AgentConfig::from_env()?,
+            ..GitHubSettings::from_env()
+        })
+        .build()?;
 
     // Process specific team(s)
     let teams_env =
@@ -0,0 +1,416 @@
+//! # Live Checks
+//!
+//! `ghrust validate` only looks at the configuration; `ghrust doctor` tries it
+//! against the services it points at and prints an actionable fix for every
+//! check that fails:
+//!
+//! * TLS: the configured CA bundle loads
+//! * GitHub: the token is accepted and has a scope that can read Copilot
+//!   metrics
+//! * GitHub: every configured enterprise ID is an enterprise slug (or an
+//!   organization login)
+//! * GitHub: the metrics endpoints of every configured enterprise and team
+//!   answer, requesting only yesterday to keep the checks cheap
+//! * Datadog: the API key (and secondary key) of every destination is valid
+//!
+//! Network failures are classified (certificate, DNS, connection) so the fix
+//! names the setting to change. The collector's HTTP agents connect directly,
+//! so a proxy set in the environment is pointed out when a host can't be
+//! reached.
+
+use std::env;
+use std::fmt;
+
+use chrono::{Duration, Utc};
+
+use super::Config;
+use crate::processors::enterprise;
+use crate::services::datadog::{DatadogClient, DatadogError};
+use crate::services::github::{AccountKind, GitHubClient, GitHubError};
+use crate::services::tls;
+
+/// Token scopes any of which grants read access to the Copilot metrics
+pub const METRICS_SCOPES: &[&str] = &[
+    "manage_billing:copilot",
+    "read:enterprise",
+    "admin:enterprise",
+];
+
+/// Environment variables holding a proxy, which the HTTP agents don't use
+const PROXY_VARIABLES: &[&str] = &["HTTPS_PROXY", "https_proxy", "ALL_PROXY", "all_proxy"];
+
+/// Outcome of a check
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CheckStatus {
+    /// The check passed
+    Pass,
+    /// The check passed with a caveat, or couldn't be run
+    Warn,
+    /// The check failed; the collector won't work as configured
+    Fail,
+}
+
+/// Result of a single check
+#[derive(Debug, Clone, PartialEq)]
+pub struct Check {
+    /// What was checked, e.g. "GitHub token"
+    pub name: String,
+    /// Outcome of the check
+    pub status: CheckStatus,
+    /// What was found
+    pub detail: String,
+    /// How to fix a failure or warning, if known
+    pub fix: Option<String>,
+}
+
+impl Check {
+    /// Creates a passed check
+    fn pass(name: impl Into<String>, detail: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            status: CheckStatus::Pass,
+            detail: detail.into(),
+            fix: None,
+        }
+    }
+
+    /// Creates a check that passed with a caveat
+    fn warn(name: impl Into<String>, detail: impl Into<String>, fix: Option<String>) -> Self {
+        Self {
+            name: name.into(),
+            status: CheckStatus::Warn,
+            detail: detail.into(),
+            fix,
+        }
+    }
+
+    /// Creates a failed check
+    fn fail(name: impl Into<String>, detail: impl Into<String>, fix: String) -> Self {
+        Self {
+            name: name.into(),
+            status: CheckStatus::Fail,
+            detail: detail.into(),
+            fix: Some(fix),
+        }
+    }
+}
+
+/// Results of every check of a `ghrust doctor` run
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Report {
+    /// The checks, in the order they ran
+    pub checks: Vec<Check>,
+}
+
+impl Report {
+    /// Returns whether no check failed
+    pub fn is_ok(&self) -> bool {
+        self.failures() == 0
+    }
+
+    /// Returns the number of failed checks
+    pub fn failures(&self) -> usize {
+        self.checks
+            .iter()
+            .filter(|check| check.status == CheckStatus::Fail)
+            .count()
+    }
+}
+
+impl fmt::Display for Report {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for check in &self.checks {
+            let label = match check.status {
+                CheckStatus::Pass => "ok  ",
+                CheckStatus::Warn => "warn",
+                CheckStatus::Fail => "FAIL",
+            };
+            writeln!(f, "[{}] {}: {}", label, check.name, check.detail)?;
+            if let Some(fix) = &check.fix {
+                writeln!(f, "       fix: {}", fix)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Runs every check against the public GitHub API
+///
+/// The GitHub client trusts the configured CA bundle, like a collector run.
+///
+/// # Arguments
+///
+/// * `config` - The loaded configuration
+///
+/// # Returns
+///
+/// * `Report` - The result of every check
+pub fn run(config: &Config) -> Report {
+    let github = config.github_client().build();
+    match github {
+        Ok(github) => run_with_client(config, &github),
+        Err(e) => Report {
+            checks: vec![Check::fail(
+                "GitHub client",
+                e.to_string(),
+                "Check GITHUB_TOKEN and the GitHub client settings".to_string(),
+            )],
+        },
+    }
+}
+
+/// Runs every check with a given GitHub client, e.g. one for a mock server
+///
+/// The endpoint checks are skipped if the token is rejected or GitHub can't
+/// be reached, as they would fail for the same reason.
+///
+/// # Arguments
+///
+/// * `config` - The loaded configuration
+/// * `github` - Client the GitHub checks are run with
+///
+/// # Returns
+///
+/// * `Report` - The result of every check
+pub fn run_with_client(config: &Config, github: &GitHubClient) -> Report {
+    let mut checks = Vec::new();
+    if let Some(ca_bundle) = config.agent.ca_bundle() {
+        checks.push(check_ca_bundle(ca_bundle));
+    }
+
+    let token = check_token(github);
+    let reachable = token.status != CheckStatus::Fail;
+    checks.push(token);
+
+    if reachable {
+        let day = (Utc::now() - Duration::days(1))
+            .format("%Y-%m-%d")
+            .to_string();
+        for enterprise in &config.enterprises {
+            checks.push(check_enterprise_id(github, &enterprise.id));
+            if !config.skip_enterprise {
+                let result = enterprise::with_org_fallback(
+                    github,
+                    &enterprise.id,
+                    || github.fetch_enterprise_metrics_between(&enterprise.id, &day, &day),
+                    || github.fetch_org_metrics_between(&enterprise.id, &day, &day),
+                );
+                checks.push(endpoint_check(
+                    format!("Enterprise metrics ({})", enterprise.id),
+                    result.map(|_| ()),
+                ));
+            }
+            for team in &enterprise.team_slugs {
+                let result = enterprise::with_org_fallback(
+                    github,
+                    &enterprise.id,
+                    || github.fetch_team_metrics_between(&enterprise.id, team, &day, &day),
+                    || github.fetch_org_team_metrics_between(&enterprise.id, team, &day, &day),
+                );
+                checks.push(endpoint_check(
+                    format!("Team metrics ({}/{})", enterprise.id, team),
+                    result.map(|_| ()),
+                ));
+            }
+        }
+    }
+
+    for destination in &config.datadog_destinations {
+        checks.push(check_datadog(
+            format!("Datadog API key ({})", destination.name),
+            &destination.api_key,
+            &destination.site,
+        ));
+        if let Some(api_key) = &destination.secondary_api_key {
+            checks.push(check_datadog(
+                format!("Datadog secondary API key ({})", destination.name),
+                api_key,
+                &destination.site,
+            ));
+        }
+    }
+    Report { checks }
+}
+
+/// Checks that an enterprise ID resolves to an enterprise or organization
+fn check_enterprise_id(github: &GitHubClient, enterprise_id: &str) -> Check {
+    let name = format!("Enterprise ID ({})", enterprise_id);
+    match github.resolve_account(enterprise_id) {
+        Ok(AccountKind::Enterprise) => Check::pass(name, "enterprise slug"),
+        Ok(AccountKind::Organization) => Check::pass(
+            name,
+            "organization login; metrics are read from the organization endpoint",
+        ),
+        Ok(AccountKind::Unknown) => Check::fail(
+            name,
+            "not found",
+            enterprise::enterprise_id_hint(enterprise_id),
+        ),
+        Err(e) => Check::warn(
+            name,
+            format!("could not be resolved: {}", e),
+            Some("Grant the token read:enterprise to verify the enterprise slug".to_string()),
+        ),
+    }
+}
+
+/// Checks that the CA bundle loads and contains a certificate
+fn check_ca_bundle(path: &str) -> Check {
+    match tls::load_client_config(path) {
+        Ok(_) => Check::pass("CA bundle", format!("{} loaded", path)),
+        Err(e) => Check::fail(
+            "CA bundle",
+            e.to_string(),
+            "Point GHRUST_CA_BUNDLE (or ca_bundle) at a readable PEM file with the internal \
+             CA certificate(s)"
+                .to_string(),
+        ),
+    }
+}
+
+/// Checks that GitHub accepts the token and that it can read the metrics
+fn check_token(github: &GitHubClient) -> Check {
+    let name = "GitHub token";
+    match github.fetch_token_scopes() {
+        Ok(Some(scopes)) => {
+            if scopes
+                .iter()
+                .any(|scope| METRICS_SCOPES.contains(&scope.as_str()))
+            {
+                Check::pass(name, format!("valid, scopes: {}", scopes.join(", ")))
+            } else {
+                let granted = if scopes.is_empty() {
+                    "none".to_string()
+                } else {
+                    scopes.join(", ")
+                };
+                Check::fail(
+                    name,
+                    format!(
+                        "valid, but no scope can read Copilot metrics (scopes: {})",
+                        granted
+                    ),
+                    format!(
+                        "Regenerate the token with one of the scopes {}",
+                        METRICS_SCOPES.join(", ")
+                    ),
+                )
+            }
+        }
+        Ok(None) => Check::warn(
+            name,
+            "valid; fine-grained and GitHub App tokens don't list their scopes",
+            Some(
+                "Make sure the token has the \"GitHub Copilot Business\" (read) permission; \
+                 the endpoint checks below show whether it does"
+                    .to_string(),
+            ),
+        ),
+        Err(e) => Check::fail(name, e.to_string(), github_fix(&e, "api.github.com")),
+    }
+}
+
+/// Turns the result of a metrics endpoint request into a check
+fn endpoint_check(name: String, result: Result<(), GitHubError>) -> Check {
+    match result {
+        Ok(()) => Check::pass(name, "reachable"),
+        Err(e) => {
+            let fix = github_fix(&e, "api.github.com");
+            Check::fail(name, e.to_string(), fix)
+        }
+    }
+}
+
+/// Checks that Datadog accepts an API key of a destination
+fn check_datadog(name: String, api_key: &str, site: &str) -> Check {
+    let client = DatadogClient::from_settings(api_key.to_string(), site, Default::default());
+    match client.validate_api_key() {
+        Ok(()) => Check::pass(name, format!("valid for {}", site)),
+        Err(e) => {
+            let fix = datadog_fix(&e, site);
+            Check::fail(name, e.to_string(), fix)
+        }
+    }
+}
+
+/// Returns the fix for a failed GitHub request
+///
+/// # Arguments
+///
+/// * `error` - Error of the request
+/// * `host` - Host the request went to, named in network fixes
+pub fn github_fix(error: &GitHubError, host: &str) -> String {
+    match error {
+        GitHubError::Authentication(_) => {
+            "The token is invalid or expired; create a new one and update GITHUB_TOKEN".to_string()
+        }
+        GitHubError::Authorization(_) => format!(
+            "The token lacks access; grant one of the scopes {} and make sure its owner is \
+             an enterprise owner or billing manager",
+            METRICS_SCOPES.join(", ")
+        ),
+        GitHubError::NotFound(_) => "Check the enterprise slug (GITHUB_ENTERPRISE_ID) and team \
+            slugs (GITHUB_TEAM_SLUGS), and that the Copilot metrics API access policy is enabled \
+            for the enterprise"
+            .to_string(),
+        GitHubError::Validation(_) => {
+            "Enable the Copilot metrics API access policy in the enterprise settings".to_string()
+        }
+        GitHubError::RateLimit(_) => {
+            "The token's rate limit is exhausted; wait for the reset or use another token"
+                .to_string()
+        }
+        GitHubError::Network(message) => network_fix(message, host),
+        _ => "Retry later; if it persists, check githubstatus.com".to_string(),
+    }
+}
+
+/// Returns the fix for a failed Datadog request
+///
+/// # Arguments
+///
+/// * `error` - Error of the request
+/// * `site` - Datadog site of the destination
+pub fn datadog_fix(error: &DatadogError, site: &str) -> String {
+    match error {
+        DatadogError::HttpError(401 | 403, _) => format!(
+            "The API key was rejected by {}; check DATADOG_API_KEY and that DATADOG_SITE is \
+             the site of the key's organization",
+            site
+        ),
+        DatadogError::Network(message) => network_fix(message, site),
+        _ => "Retry later; if it persists, check status.datadoghq.com".to_string(),
+    }
+}
+
+/// Returns the fix for a transport error
+///
+/// # Arguments
+///
+/// * `message` - The transport error
+/// * `host` - Host that couldn't be reached
+pub fn network_fix(message: &str, host: &str) -> String {
+    let message = message.to_ascii_lowercase();
+    let mut fix = if message.contains("certificate") || message.contains("unknownissuer") {
+        "The server's certificate isn't trusted; if a proxy or appliance re-signs TLS traffic, \
+         set GHRUST_CA_BUNDLE to a PEM file with its CA certificate"
+            .to_string()
+    } else if message.contains("dns") {
+        format!(
+            "{} could not be resolved; check the DNS settings of this host",
+            host
+        )
+    } else {
+        format!(
+            "{} could not be reached; check firewall rules and outbound access from this host",
+            host
+        )
+    };
+    if let Some(proxy) = PROXY_VARIABLES.iter().find(|name| env::var(name).is_ok()) {
+        fix.push_str(&format!(
+            " ({} is set, but the collector connects directly; allow direct outbound HTTPS)",
+            proxy
+        ));
+    }
+    fix
+}
@@ -0,0 +1,31 @@
+//! # Configuration Error Types
+//!
+//! This module defines structured error types for loading the collector's
+//! configuration from environment variables and configuration files.
+
+use thiserror::Error;
+
+use crate::services::vault::VaultError;
+
+/// Errors that can occur while loading the configuration
+#[derive(Error, Debug)]
+pub enum ConfigError {
+    /// A required setting was not provided
+    #[error("{0} environment variable not set")]
+    Missing(String),
+
+    /// A setting was provided but its value is invalid
+    #[error("Invalid value for {0}: {1}")]
+    Invalid(String, String),
+
+    /// The configuration file could not be read or parsed
+    #[error("Error reading configuration file {0}: {1}")]
+    File(String, String),
+
+    /// A secret could not be read from Vault
+    #[error("Error reading secrets from Vault: {0}")]
+    Vault(#[from] VaultError),
+}
+
+/// A specialized Result type for configuration loading
+pub type Result<T> = std::result::Result<T, ConfigError>;
@@ -0,0 +1,238 @@
+//! # Configuration File
+//!
+//! Data structures for the optional JSON configuration file. Every section is
+//! optional; settings missing from the file fall back to environment variables.
+//!
+//! ```json
+//! {
+//!   "enterprises": [
+//!     { "id": "acme", "namespace": "github.copilot.acme", "team_slugs": ["platform"] },
+//!     { "id": "acme-labs" },
+//!     { "id": "globex", "namespace": "globex.copilot",
+//!       "github_token_env": "GLOBEX_GITHUB_TOKEN", "datadog_api_key_env": "GLOBEX_DD_API_KEY" }
+//!   ],
+//!   "datadog_destinations": [
+//!     { "name": "security", "site": "datadoghq.com", "api_key_env": "SECURITY_DD_API_KEY",
+//!       "namespace": "security.copilot" }
+//!   ],
+//!   "team_mappings": {
+//!     "platform": { "namespace": "platform.copilot", "tags": ["cost_center:42"] }
+//!   },
+//!   "ca_bundle": "/opt/certs/internal-ca.pem",
+//!   "digest": { "sender": "copilot@example.com", "recipients": ["leads@example.com"] },
+//!   "store_path": "/mnt/efs/ghrust/metrics.db",
+//!   "alert_rules": [{ "metric": "active_users", "drop_percent": 20 }],
+//!   "language_groups": { "jvm": ["java", "kotlin", "scala"] },
+//!   "exports": [{ "type": "google_sheets", "spreadsheet_id": "1AbC",
+//!     "credentials_file": "/opt/ghrust/service-account.json" }]
+//! }
+//! ```
+
+use std::collections::{BTreeMap, HashMap};
+
+use serde::Deserialize;
+
+use super::error::{ConfigError, Result};
+use crate::processors::alerts::AlertRule;
+use crate::services::export::ExportConfig;
+
+/// An enterprise entry in the configuration file
+///
+/// An entry with its own GitHub token or Datadog API key is a tenant: its
+/// metrics are fetched with its token and sent only to its own Datadog
+/// organization, in isolation from the other enterprises. A tenant always
+/// needs its own Datadog API key. Credentials named by the `_env` fields are
+/// read like `GITHUB_TOKEN`, from the variable, `{name}_FILE` or Vault.
+#[derive(Debug, Clone, Default, Deserialize, PartialEq)]
+pub struct EnterpriseEntry {
+    /// ID (slug) of the GitHub Enterprise account
+    pub id: String,
+
+    /// Namespace for this enterprise's metrics (defaults to `{namespace}.{id}`
+    /// when several enterprises are configured)
+    #[serde(default)]
+    pub namespace: Option<String>,
+
+    /// Team slugs for this enterprise (defaults to `GITHUB_TEAM_SLUGS`)
+    #[serde(default)]
+    pub team_slugs: Option<Vec<String>>,
+
+    /// GitHub token of a tenant, replacing `GITHUB_TOKEN`
+    #[serde(default)]
+    pub github_token: Option<String>,
+
+    /// Name of an environment variable holding the tenant's GitHub token
+    #[serde(default)]
+    pub github_token_env: Option<String>,
+
+    /// API key of the tenant's own Datadog organization
+    #[serde(default)]
+    pub datadog_api_key: Option<String>,
+
+    /// Name of an environment variable holding the tenant's Datadog API key
+    #[serde(default)]
+    pub datadog_api_key_env: Option<String>,
+
+    /// Application key of the tenant's own Datadog organization, for
+    /// dashboards and metric metadata
+    #[serde(default)]
+    pub datadog_app_key: Option<String>,
+
+    /// Name of an environment variable holding the tenant's Datadog
+    /// application key
+    #[serde(default)]
+    pub datadog_app_key_env: Option<String>,
+
+    /// Datadog site of the tenant's organization (defaults to "datadoghq.eu")
+    #[serde(default)]
+    pub datadog_site: Option<String>,
+}
+
+impl EnterpriseEntry {
+    /// Returns whether the entry has credentials of its own
+    pub fn is_tenant(&self) -> bool {
+        self.github_token.is_some()
+            || self.github_token_env.is_some()
+            || self.datadog_api_key.is_some()
+            || self.datadog_api_key_env.is_some()
+            || self.datadog_app_key.is_some()
+            || self.datadog_app_key_env.is_some()
+    }
+}
+
+/// An additional Datadog destination in the configuration file
+#[derive(Debug, Clone, Deserialize, PartialEq)]
+pub struct DatadogDestinationEntry {
+    /// Name used to identify the destination in logs and errors
+    pub name: String,
+
+    /// Datadog site (defaults to "datadoghq.eu")
+    #[serde(default)]
+    pub site: Option<String>,
+
+    /// API key for the destination's organization
+    #[serde(default)]
+    pub api_key: Option<String>,
+
+    /// Name of an environment variable holding the API key, so the key
+    /// doesn't have to be stored in the file; `{name}_FILE` and Vault are
+    /// read as for `DATADOG_API_KEY`
+    #[serde(default)]
+    pub api_key_env: Option<String>,
+
+    /// Name of an environment variable holding a second API key, retried
+    /// when the organization rejects the first one
+    #[serde(default)]
+    pub secondary_api_key_env: Option<String>,
+
+    /// Namespace replacing the base namespace for this destination
+    #[serde(default)]
+    pub namespace: Option<String>,
+}
+
+/// Overrides for where and how a team's metrics are reported
+#[derive(Debug, Clone, Default, Deserialize, PartialEq)]
+pub struct TeamMapping {
+    /// Namespace replacing `{namespace}.team.{slug}` for this team
+    #[serde(default)]
+    pub namespace: Option<String>,
+
+    /// Tags added to every series of this team, e.g. "cost_center:42"
+    #[serde(default)]
+    pub tags: Vec<String>,
+
+    /// Slug of the parent team the metrics of this team are aggregated into
+    #[serde(default)]
+    pub parent: Option<String>,
+
+    /// Login of the organization owning the team; enables adoption rates
+    /// relative to the team's member count
+    #[serde(default)]
+    pub org: Option<String>,
+}
+
+/// Weekly email digest settings in the configuration file
+#[derive(Debug, Clone, Default, Deserialize, PartialEq)]
+pub struct DigestEntry {
+    /// Verified SES sender address
+    #[serde(default)]
+    pub sender: Option<String>,
+
+    /// Recipient addresses
+    #[serde(default)]
+    pub recipients: Vec<String>,
+
+    /// Day of the week the digest is sent on, e.g. "Mon" (default)
+    #[serde(default)]
+    pub weekday: Option<String>,
+}
+
+/// Contents of the JSON configuration file
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct ConfigFile {
+    /// Enterprises to collect metrics for
+    #[serde(default)]
+    pub enterprises: Vec<EnterpriseEntry>,
+
+    /// Datadog organizations to send to in addition to the primary one
+    #[serde(default)]
+    pub datadog_destinations: Vec<DatadogDestinationEntry>,
+
+    /// Namespace and tag overrides keyed by team slug
+    #[serde(default)]
+    pub team_mappings: HashMap<String, TeamMapping>,
+
+    /// Path of a PEM CA bundle trusted in addition to the default roots
+    #[serde(default)]
+    pub ca_bundle: Option<String>,
+
+    /// Weekly email digest settings
+    #[serde(default)]
+    pub digest: Option<DigestEntry>,
+
+    /// Path of the SQLite database fetched metrics are stored in
+    #[serde(default)]
+    pub store_path: Option<String>,
+
+    /// Threshold rules evaluated against the latest day of each enterprise
+    #[serde(default)]
+    pub alert_rules: Vec<AlertRule>,
+
+    /// Normalized language names keyed by the group they are summed into
+    #[serde(default)]
+    pub language_groups: BTreeMap<String, Vec<String>>,
+
+    /// Destinations the fetched days are exported to as rows
+    #[serde(default)]
+    pub exports: Vec<ExportConfig>,
+}
+
+impl ConfigFile {
+    /// Reads and parses the configuration file at the given path
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - Path of the JSON configuration file
+    ///
+    /// # Returns
+    ///
+    /// * `Result<ConfigFile>` - The parsed file or a `File` error
+    pub fn load(path: &str) -> Result<Self> {
+        let contents = std::fs::read_to_string(path)
+            .map_err(|e| ConfigError::File(path.to_string(), e.to_string()))?;
+        Self::parse(&contents).map_err(|e| match e {
+            ConfigError::File(_, msg) => ConfigError::File(path.to_string(), msg),
+            other => other,
+        })
+    }
+
+    /// Parses configuration file contents
+    ///
+    /// # Arguments
+    ///
+    /// * `contents` - The JSON configuration
+    pub fn parse(contents: &str) -> Result<Self> {
+        serde_json::from_str(contents)
+            .map_err(|e| ConfigError::File("<inline>".to_string(), e.to_string()))
+    }
+}
@@ -0,0 +1,40 @@
+//! # Log Filtering
+//!
+//! The binaries log at `info` unless `RUST_LOG` or `LOG_LEVEL` holds filter
+//! directives, e.g. `info,ghrust::services::github=debug` to debug the GitHub
+//! client in production without a code change. `RUST_LOG` takes precedence;
+//! `LOG_LEVEL` is the conventional name on Lambda.
+
+use std::env;
+
+use tracing_subscriber::EnvFilter;
+
+/// Directives used when neither variable is set or the set one is invalid
+pub const DEFAULT_LOG_FILTER: &str = "info";
+
+/// Returns the log filter configured by `RUST_LOG` or `LOG_LEVEL`
+///
+/// Invalid directives are reported on stderr, since logging isn't set up
+/// yet, and replaced by the default filter.
+///
+/// # Returns
+///
+/// * `EnvFilter` - The filter for the tracing subscriber
+pub fn log_filter() -> EnvFilter {
+    let Some((name, directives)) = ["RUST_LOG", "LOG_LEVEL"].iter().find_map(|name| {
+        env::var(name)
+            .ok()
+            .filter(|value| !value.trim().is_empty())
+            .map(|value| (*name, value))
+    }) else {
+        return EnvFilter::new(DEFAULT_LOG_FILTER);
+    };
+
+    EnvFilter::try_new(&directives).unwrap_or_else(|e| {
+        eprintln!(
+            "Ignoring invalid {} \"{}\" ({}); logging at {}",
+            name, directives, e, DEFAULT_LOG_FILTER
+        );
+        EnvFilter::new(DEFAULT_LOG_FILTER)
+    })
+}
@@ -0,0 +1,1215 @@
+//! # Configuration
+//!
+//! This module loads the collector's runtime configuration from environment
+//! variables and an optional JSON configuration file.
+//!
+//! Environment variables cover the common single-enterprise deployment. The
+//! configuration file (path given by `GHRUST_CONFIG_FILE`) is used for settings
+//! that do not fit comfortably into a single variable, such as a list of
+//! enterprises with their own namespaces and team slugs.
+//!
+//! Secrets can also be mounted as files (Kubernetes secrets, ECS): for
+//! `GITHUB_TOKEN` and `DATADOG_API_KEY`, a path in `GITHUB_TOKEN_FILE` or
+//! `DATADOG_API_KEY_FILE` is read when the variable itself is not set.
+//! Without either, the secrets are read from HashiCorp Vault if it is
+//! configured (see `services::vault`).
+//!
+//! ## Submodules
+//!
+//! * `doctor` - Live checks of the configured credentials and endpoints, for
+//!   `ghrust doctor`
+//! * `error` - Structured error types for configuration loading
+//! * `file` - Data structures for the JSON configuration file
+//! * `logging` - Log filter directives from `RUST_LOG` or `LOG_LEVEL`
+//! * `validate` - Checks and masked summary of a loaded configuration, for
+//!   `ghrust validate`
+
+pub mod doctor;
+mod error;
+mod file;
+pub mod logging;
+pub mod validate;
+
+#[cfg(test)]
+mod tests;
+
+use std::collections::{BTreeMap, HashMap};
+use std::env;
+use std::fs;
+use std::time::Duration;
+
+use chrono::Weekday;
+use tracing::info;
+
+pub use error::{ConfigError, Result};
+pub use file::{ConfigFile, DatadogDestinationEntry, DigestEntry, EnterpriseEntry, TeamMapping};
+
+use crate::processors::alerts::AlertRule;
+use crate::services::datadog::breakdown::parse_limit;
+use crate::services::datadog::client::{parse_concurrency, DEFAULT_SEND_CONCURRENCY, DEFAULT_SITE};
+use crate::services::datadog::languages::parse_aliases;
+use crate::services::datadog::names::NamespaceTemplate;
+use crate::services::datadog::repos::parse_patterns;
+use crate::services::datadog::tag_templates::TagTemplates;
+use crate::services::datadog::tags::parse_keys;
+use crate::services::datadog::throttle::parse_rate;
+use crate::services::datadog::{
+    BreakdownLimits, DatadogDestination, DatadogSettings, DayWindow, Failover, LanguageNormalizer,
+    RepositoryFilter, TagFilter, Throttle,
+};
+use crate::services::export::ExportConfig;
+use crate::services::github::{
+    parse_count, GitHubClient, GitHubClientBuilder, GitHubSettings, MetricsSource, PendingPolicy,
+    RetryPolicy, SchemaMode,
+};
+use crate::services::tls::AgentConfig;
+use crate::services::vault;
+
+/// Default namespace prefix for Datadog metrics
+pub const DEFAULT_NAMESPACE: &str = "github.copilot";
+
+/// Default key of the baseline file in the `BASELINE_S3_BUCKET` bucket
+pub const DEFAULT_BASELINE_KEY: &str = "ghrust/baseline.json";
+
+/// Hours a discovered team list is cached for unless configured otherwise
+pub const DEFAULT_TEAM_CACHE_TTL_HOURS: u64 = 24;
+
+/// A GitHub Enterprise account to collect metrics for
+#[derive(Debug, Clone, PartialEq)]
+pub struct EnterpriseConfig {
+    /// ID (slug) of the GitHub Enterprise account
+    pub id: String,
+
+    /// Namespace prefix for this enterprise's Datadog metrics
+    pub namespace: String,
+
+    /// Slugs of the teams to collect team-specific metrics for
+    pub team_slugs: Vec<String>,
+
+    /// Credentials of a tenant enterprise, if it has its own
+    pub tenant: Option<TenantConfig>,
+}
+
+/// Credentials of an enterprise that is processed in isolation
+///
+/// Credentials the configured stage doesn't need are not read: a fetch has
+/// no Datadog destination and a send no GitHub token.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TenantConfig {
+    /// GitHub token replacing `GITHUB_TOKEN`, if the tenant has its own
+    pub github_token: Option<String>,
+
+    /// The tenant's own Datadog organization, replacing all configured
+    /// destinations
+    pub datadog: Option<DatadogDestination>,
+
+    /// Application key of the tenant's Datadog organization, replacing
+    /// `DATADOG_APP_KEY`, if it has one
+    pub datadog_app_key: Option<String>,
+}
+
+/// Weekly adoption digest emailed through Amazon SES
+#[derive(Debug, Clone, PartialEq)]
+pub struct DigestConfig {
+    /// Verified SES sender address
+    pub sender: String,
+
+    /// Recipient addresses
+    pub recipients: Vec<String>,
+
+    /// Day of the week (UTC) the digest is sent on
+    pub weekday: Weekday,
+}
+
+/// Team leaderboard settings
+#[derive(Debug, Clone, PartialEq)]
+pub struct LeaderboardConfig {
+    /// Day of the week (UTC) the leaderboard is built on
+    pub weekday: Weekday,
+
+    /// Local path or `s3://<bucket>/<key>` location the leaderboard is
+    /// written to, if configured
+    pub output: Option<String>,
+}
+
+/// Location of the weekly baseline file in S3
+#[derive(Debug, Clone, PartialEq)]
+pub struct BaselineConfig {
+    /// Bucket of the baseline file
+    pub bucket: String,
+
+    /// Key of the baseline file
+    pub key: String,
+}
+
+/// Opsgenie integration threshold alerts are sent to
+#[derive(Debug, Clone, PartialEq)]
+pub struct OpsgenieConfig {
+    /// API key of an Opsgenie API integration
+    pub api_key: String,
+
+    /// Base URL of the Opsgenie API, e.g. the EU instance
+    pub api_url: Option<String>,
+}
+
+/// Privacy mode replacing team slugs in namespaces with salted hashes
+#[derive(Debug, Clone, PartialEq)]
+pub struct TeamHashingConfig {
+    /// Secret salt of the hashes
+    pub salt: String,
+
+    /// Path of the local file recording the slug of every hash, if any
+    pub mapping_file: Option<String>,
+}
+
+/// Discovery of the teams of every enterprise through the API
+#[derive(Debug, Clone, PartialEq)]
+pub struct TeamDiscoveryConfig {
+    /// Path of the JSON file the discovered team lists are cached in, if any
+    pub cache_path: Option<String>,
+
+    /// How long a cached team list is used before the teams are listed again
+    pub cache_ttl: Duration,
+}
+
+/// Fully resolved collector configuration
+#[derive(Debug, Clone)]
+pub struct Config {
+    /// GitHub personal access token with admin:enterprise permissions
+    pub github_token: String,
+
+    /// Datadog organizations to send metrics to; the primary destination
+    /// (`DATADOG_API_KEY`) is always first
+    pub datadog_destinations: Vec<DatadogDestination>,
+
+    /// Whether the series of every scope are queued and submitted in one
+    /// chunked pass at the end of the run
+    pub datadog_batch_send: bool,
+
+    /// Base namespace prefix for Datadog metrics
+    pub datadog_namespace: String,
+
+    /// Template the namespaces of teams and organizations are built from
+    pub namespace_template: NamespaceTemplate,
+
+    /// Filters and limits the Datadog clients apply to the sent series
+    pub datadog_settings: DatadogSettings,
+
+    /// Request policies and identification of the GitHub clients
+    pub github_settings: GitHubSettings,
+
+    /// Enterprises to process, in order
+    pub enterprises: Vec<EnterpriseConfig>,
+
+    /// Whether enterprise-wide metrics processing is skipped
+    pub skip_enterprise: bool,
+
+    /// Whether premium request usage is collected for each enterprise
+    pub collect_premium_requests: bool,
+
+    /// Whether the metrics of every organization of each enterprise are collected
+    pub collect_org_metrics: bool,
+
+    /// Whether seat utilization is collected for each enterprise
+    pub collect_seat_metrics: bool,
+
+    /// Whether every unused seat is reported as a series tagged with its holder
+    pub report_unused_seat_users: bool,
+
+    /// Whether seats are reported per days-since-last-activity bucket
+    pub report_seat_activity: bool,
+
+    /// Monthly price of a Copilot seat, used to estimate spend, if configured
+    pub seat_price: Option<f64>,
+
+    /// Namespace and tag overrides keyed by team slug
+    pub team_mappings: HashMap<String, TeamMapping>,
+
+    /// Settings of the HTTP agents: the PEM CA bundle they trust, if any, and
+    /// whether requests and responses are logged with credentials redacted
+    pub agent: AgentConfig,
+
+    /// Whether the handler fails the invocation when processing fails
+    pub fail_on_error: bool,
+
+    /// Share of teams (0.0 to 1.0) allowed to fail before the invocation
+    /// fails in `fail_on_error` mode
+    pub team_failure_threshold: f64,
+
+    /// Weekly email digest, if recipients are configured
+    pub digest: Option<DigestConfig>,
+
+    /// Path of the SQLite database (or `s3://` location) fetched metrics are
+    /// stored in, if any
+    pub store_path: Option<String>,
+
+    /// Whether stored days are served instead of being fetched again
+    pub reuse_stored_days: bool,
+
+    /// Path of the file ETags of metrics responses are kept in between runs,
+    /// if conditional requests are enabled
+    pub etag_cache_path: Option<String>,
+
+    /// Directory or S3 location raw metrics responses are archived in for
+    /// `ghrust replay`, if archiving is enabled
+    pub raw_archive: Option<String>,
+
+    /// Whether the summed metrics of all teams are sent as a rollup
+    pub team_rollup: bool,
+
+    /// Datadog application key for the primary destination, if configured
+    pub datadog_app_key: Option<String>,
+
+    /// Whether the standard dashboard of each enterprise namespace is created
+    /// or updated in the primary Datadog organization
+    pub provision_dashboards: bool,
+
+    /// Whether units and descriptions of the standard metrics of each
+    /// enterprise namespace are kept in sync in the primary Datadog organization
+    pub update_metric_metadata: bool,
+
+    /// Threshold rules evaluated against the latest day of each enterprise
+    pub alert_rules: Vec<AlertRule>,
+
+    /// Normalized language names keyed by the group they are summed into
+    pub language_groups: BTreeMap<String, Vec<String>>,
+
+    /// Incoming webhook alerts are posted to, if configured
+    pub alert_webhook_url: Option<String>,
+
+    /// Opsgenie integration alerts are created in, if configured
+    pub opsgenie: Option<OpsgenieConfig>,
+
+    /// Dead man's switch pinged at the start and end of every run, if
+    /// configured
+    pub healthcheck_url: Option<String>,
+
+    /// Whether collector metrics carry a `run_id` tag
+    pub collector_run_id_tag: bool,
+
+    /// S3 location of the long-term baseline file, if configured
+    pub baseline: Option<BaselineConfig>,
+
+    /// Z-score beyond which the latest day of a key metric is anomalous;
+    /// anomaly detection is disabled if unset
+    pub anomaly_sigma: Option<f64>,
+
+    /// Whether complete ISO weeks of the enterprise window are reported as
+    /// `weekly.*` rollups
+    pub weekly_rollups: bool,
+
+    /// Whether complete calendar months of the enterprise's stored days are
+    /// reported as `monthly.*` rollups
+    pub monthly_rollups: bool,
+
+    /// Whether percentiles of the key metrics across teams are reported
+    pub team_distribution: bool,
+
+    /// Team leaderboard settings, if enabled
+    pub leaderboard: Option<LeaderboardConfig>,
+
+    /// Destinations the fetched days are exported to as rows
+    pub exports: Vec<ExportConfig>,
+
+    /// Privacy mode for team namespaces, if a salt is configured
+    pub team_hashing: Option<TeamHashingConfig>,
+
+    /// Discovery of the enterprises' teams, in addition to the configured slugs
+    pub team_discovery: Option<TeamDiscoveryConfig>,
+}
+
+/// Part of a run a configuration is loaded for
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Stage {
+    /// Fetching from GitHub and sending to Datadog, e.g. a Lambda invocation
+    Full,
+    /// Only fetching from GitHub (`ghrust fetch`); no Datadog key is needed
+    Fetch,
+    /// Only sending to Datadog (`ghrust send`); no GitHub token is needed
+    Send,
+    /// Only reading local data, e.g. `ghrust diff --store`; neither a GitHub
+    /// token nor a Datadog key is needed
+    Local,
+}
+
+impl Config {
+    /// Loads the configuration from environment variables and the optional
+    /// configuration file
+    ///
+    /// Enterprises are resolved in this order:
+    /// 1. The `enterprises` list of the configuration file, if present
+    /// 2. `GITHUB_ENTERPRISE_IDS` (comma-separated)
+    /// 3. `GITHUB_ENTERPRISE_ID`
+    ///
+    /// When a single enterprise is configured it uses the base namespace, so
+    /// existing dashboards keep working. With several enterprises each one
+    /// defaults to `{namespace}.{enterprise_id}` unless the configuration file
+    /// sets an explicit namespace.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<Config>` - The resolved configuration or an error describing
+    ///   the missing or invalid setting
+    pub fn from_env() -> Result<Self> {
+        Self::from_env_for(Stage::Full)
+    }
+
+    /// Loads the configuration for one stage of a split run
+    ///
+    /// Like `from_env`, but the credentials of the service the stage doesn't
+    /// talk to are optional: without a GitHub token the token is empty, and
+    /// a fetch or a local command has no Datadog destinations.
+    ///
+    /// # Arguments
+    ///
+    /// * `stage` - The part of the run the configuration is used for
+    pub fn from_env_for(stage: Stage) -> Result<Self> {
+        let file = match env::var("GHRUST_CONFIG_FILE") {
+            Ok(path) => Some(ConfigFile::load(&path)?),
+            Err(_) => None,
+        };
+
+        // Loaded before any secret is read, as Vault may need the bundle
+        let agent = agent_config(file.as_ref())?;
+        let github_token = match stage {
+            Stage::Send | Stage::Local => {
+                optional_secret_var("GITHUB_TOKEN", &agent)?.unwrap_or_default()
+            }
+            _ => secret_var("GITHUB_TOKEN", &agent)?,
+        };
+        let datadog_destinations = match stage {
+            Stage::Fetch | Stage::Local => Vec::new(),
+            _ => datadog_destinations(file.as_ref(), &agent)?,
+        };
+
+        let datadog_namespace = env::var("DATADOG_METRIC_NAMESPACE").unwrap_or_else(|_| {
+            info!(
+                "DATADOG_METRIC_NAMESPACE not set, using default: {}",
+                DEFAULT_NAMESPACE
+            );
+            DEFAULT_NAMESPACE.to_string()
+        });
+        let namespace_template = namespace_template()?;
+
+        let team_hashing = env::var("TEAM_HASH_SALT")
+            .ok()
+            .filter(|salt| !salt.is_empty())
+            .map(|salt| TeamHashingConfig {
+                salt,
+                mapping_file: env::var("TEAM_HASH_MAPPING_FILE").ok(),
+            });
+
+        let schema_mode = schema_mode()?;
+        let datadog_settings = DatadogSettings {
+            concurrency: send_concurrency()?,
+            report_schema_drift: schema_mode == SchemaMode::Warn,
+            tag_templates: tag_templates(team_hashing.is_some())?,
+            tag_filter: tag_filter(),
+            throttle: throttle()?,
+            day_window: day_window(),
+            breakdown_limits: breakdown_limits()?,
+            language_normalizer: language_normalizer()?,
+            repository_filter: repository_filter(),
+            agent: agent.clone(),
+        };
+
+        let default_teams = env::var("GITHUB_TEAM_SLUGS")
+            .map(|slugs| split_list(&slugs))
+            .unwrap_or_default();
+
+        let entries = match file.as_ref().map(|f| f.enterprises.clone()) {
+            Some(entries) if !entries.is_empty() => entries,
+            _ => enterprise_entries_from_env()?,
+        };
+
+        let enterprises =
+            resolve_enterprises(entries, &datadog_namespace, &default_teams, stage, &agent)?;
+        let team_mappings = team_mappings(file.as_ref())?;
+        let team_failure_threshold = team_failure_threshold()?;
+        let seat_price = seat_price()?;
+        let digest = digest(file.as_ref())?;
+        let store_path = file
+            .as_ref()
+            .and_then(|f| f.store_path.clone())
+            .or_else(|| env::var("METRICS_STORE_PATH").ok());
+        let datadog_app_key = env::var("DATADOG_APP_KEY").ok();
+        let provision_dashboards = env::var("PROVISION_DASHBOARDS").is_ok();
+        let update_metric_metadata = env::var("UPDATE_METRIC_METADATA").is_ok();
+        if provision_dashboards || update_metric_metadata {
+            if datadog_app_key.is_none() {
+                return Err(ConfigError::Missing("DATADOG_APP_KEY".to_string()));
+            }
+            // Tenants can't use the operator's application key with their API key
+            let without_app_key = enterprises.iter().find(|e| {
+                e.tenant
+                    .as_ref()
+                    .is_some_and(|t| t.datadog.is_some() && t.datadog_app_key.is_none())
+            });
+            if let Some(enterprise) = without_app_key {
+                return Err(ConfigError::Invalid(
+                    format!("enterprises[{}]", enterprise.id),
+                    "a tenant needs datadog_app_key or datadog_app_key_env with \
+                     PROVISION_DASHBOARDS or UPDATE_METRIC_METADATA"
+                        .to_string(),
+                ));
+            }
+        }
+
+        let alert_rules = alert_rules(file.as_ref())?;
+        let language_groups = file
+            .as_ref()
+            .map(|f| f.language_groups.clone())
+            .unwrap_or_default();
+        let anomaly_sigma = anomaly_sigma()?;
+        let exports = exports(file.as_ref())?;
+        let baseline = env::var("BASELINE_S3_BUCKET")
+            .ok()
+            .map(|bucket| BaselineConfig {
+                bucket,
+                key: env::var("BASELINE_S3_KEY")
+                    .unwrap_or_else(|_| DEFAULT_BASELINE_KEY.to_string()),
+            });
+        let opsgenie = env::var("OPSGENIE_API_KEY")
+            .ok()
+            .filter(|key| !key.is_empty())
+            .map(|api_key| OpsgenieConfig {
+                api_key,
+                api_url: env::var("OPSGENIE_API_URL").ok(),
+            });
+
+        Ok(Self {
+            github_token,
+            datadog_destinations,
+            datadog_batch_send: env::var("DATADOG_BATCH_SEND").is_ok(),
+            datadog_namespace,
+            namespace_template,
+            datadog_settings,
+            github_settings: github_settings(schema_mode, &agent)?,
+            enterprises,
+            skip_enterprise: env::var("SKIP_ENTERPRISE_METRICS").is_ok(),
+            collect_premium_requests: env::var("COLLECT_PREMIUM_REQUESTS").is_ok(),
+            collect_org_metrics: env::var("COLLECT_ORG_METRICS").is_ok(),
+            collect_seat_metrics: env::var("COLLECT_SEAT_METRICS").is_ok(),
+            report_unused_seat_users: env::var("REPORT_UNUSED_SEAT_USERS").is_ok(),
+            report_seat_activity: env::var("REPORT_SEAT_ACTIVITY").is_ok(),
+            seat_price,
+            team_mappings,
+            agent,
+            fail_on_error: env::var("FAIL_ON_ERROR").is_ok(),
+            team_failure_threshold,
+            digest,
+            store_path,
+            reuse_stored_days: env::var("METRICS_STORE_REUSE").is_ok(),
+            etag_cache_path: env::var("GITHUB_ETAG_CACHE").ok(),
+            raw_archive: env::var("GITHUB_RAW_ARCHIVE").ok(),
+            team_rollup: env::var("TEAM_ROLLUP").is_ok(),
+            datadog_app_key,
+            provision_dashboards,
+            update_metric_metadata,
+            alert_rules,
+            language_groups,
+            alert_webhook_url: env::var("ALERT_WEBHOOK_URL").ok(),
+            opsgenie,
+            healthcheck_url: env::var("HEALTHCHECK_URL").ok(),
+            collector_run_id_tag: env::var("COLLECTOR_RUN_ID_TAG").is_ok(),
+            anomaly_sigma,
+            weekly_rollups: env::var("WEEKLY_ROLLUPS").is_ok(),
+            monthly_rollups: env::var("MONTHLY_ROLLUPS").is_ok(),
+            team_distribution: env::var("TEAM_DISTRIBUTION").is_ok(),
+            leaderboard: leaderboard()?,
+            exports,
+            baseline,
+            team_hashing,
+            team_discovery: team_discovery()?,
+        })
+    }
+
+    /// Returns a GitHub client builder with the configured token and settings
+    pub fn github_client(&self) -> GitHubClientBuilder {
+        GitHubClient::builder()
+            .token(&self.github_token)
+            .settings(self.github_settings.clone())
+    }
+}
+
+/// Loads the HTTP agent settings with the configured CA bundle
+///
+/// The configuration file's `ca_bundle` takes precedence over
+/// `GHRUST_CA_BUNDLE`; `HTTP_DEBUG_LOG` enables logging.
+fn agent_config(file: Option<&ConfigFile>) -> Result<AgentConfig> {
+    let (name, ca_bundle) = match file.and_then(|f| f.ca_bundle.clone()) {
+        Some(path) => ("ca_bundle", Some(path)),
+        None => ("GHRUST_CA_BUNDLE", env::var("GHRUST_CA_BUNDLE").ok()),
+    };
+    let agent = AgentConfig::load(ca_bundle.as_deref())
+        .map_err(|e| ConfigError::Invalid(name.to_string(), e.to_string()))?;
+    Ok(agent.with_debug_log(env::var("HTTP_DEBUG_LOG").is_ok()))
+}
+
+/// Reads a required environment variable
+fn required_var(name: &str) -> Result<String> {
+    env::var(name).map_err(|_| ConfigError::Missing(name.to_string()))
+}
+
+/// Reads a required secret from an environment variable or a mounted file
+///
+/// The variable itself takes precedence; otherwise the file named by
+/// `{name}_FILE` is read and its contents trimmed, so trailing newlines of
+/// mounted secrets are ignored. Without either, the lowercased name (e.g.
+/// `github_token`) is read from the Vault secret, if Vault is configured.
+///
+/// # Arguments
+///
+/// * `name` - Name of the environment variable, e.g. "GITHUB_TOKEN"
+///
+/// # Returns
+///
+/// * `Result<String>` - The secret, `Missing` if none is set, `Invalid` if
+///   the file can't be read or is empty, or `Vault` if Vault fails
+fn secret_var(name: &str, agent: &AgentConfig) -> Result<String> {
+    if let Ok(value) = env::var(name) {
+        return Ok(value);
+    }
+
+    let file_var = format!("{}_FILE", name);
+    let Ok(path) = env::var(&file_var) else {
+        return vault::secret(&name.to_lowercase(), agent)?
+            .ok_or_else(|| ConfigError::Missing(name.to_string()));
+    };
+    let contents = fs::read_to_string(&path)
+        .map_err(|e| ConfigError::Invalid(file_var.clone(), format!("{}: {}", path, e)))?;
+    let secret = contents.trim();
+    if secret.is_empty() {
+        return Err(ConfigError::Invalid(file_var, format!("{} is empty", path)));
+    }
+    Ok(secret.to_string())
+}
+
+/// Reads a secret like `secret_var`, returning `None` if it isn't set anywhere
+fn optional_secret_var(name: &str, agent: &AgentConfig) -> Result<Option<String>> {
+    match secret_var(name, agent) {
+        Ok(secret) => Ok(Some(secret)),
+        Err(ConfigError::Missing(_)) => Ok(None),
+        Err(e) => Err(e),
+    }
+}
+
+/// Splits a comma-separated list, trimming entries and dropping empty ones
+pub fn split_list(value: &str) -> Vec<String> {
+    value
+        .split(',')
+        .map(|s| s.trim().to_string())
+        .filter(|s| !s.is_empty())
+        .collect()
+}
+
+/// Resolves the primary Datadog destination and any additional ones
+///
+/// The primary destination uses `DATADOG_API_KEY` and `DATADOG_SITE`. Additional
+/// destinations come from the configuration file's `datadog_destinations`
+/// or, if the file has none, from the `DATADOG_DESTINATIONS` JSON array. Keys
+/// named by `api_key_env` are read like `DATADOG_API_KEY`, so they can also
+/// come from a `_FILE` or Vault.
+fn datadog_destinations(
+    file: Option<&ConfigFile>,
+    agent: &AgentConfig,
+) -> Result<Vec<DatadogDestination>> {
+    let mut destinations = vec![DatadogDestination {
+        name: "primary".to_string(),
+        site: env::var("DATADOG_SITE").unwrap_or_else(|_| DEFAULT_SITE.to_string()),
+        api_key: secret_var("DATADOG_API_KEY", agent)?,
+        namespace: None,
+        secondary_api_key: optional_secret_var("DATADOG_API_KEY_SECONDARY", agent)?,
+        failover: failover(agent)?,
+    }];
+
+    let entries = match file.map(|f| f.datadog_destinations.clone()) {
+        Some(entries) if !entries.is_empty() => entries,
+        _ => match env::var("DATADOG_DESTINATIONS") {
+            Ok(json) => serde_json::from_str(&json).map_err(|e| {
+                ConfigError::Invalid("DATADOG_DESTINATIONS".to_string(), e.to_string())
+            })?,
+            Err(_) => Vec::new(),
+        },
+    };
+
+    for entry in entries {
+        let api_key = match (&entry.api_key, &entry.api_key_env) {
+            (Some(key), _) => key.clone(),
+            (None, Some(var)) => secret_var(var, agent)?,
+            (None, None) => {
+                return Err(ConfigError::Invalid(
+                    format!("datadog_destinations[{}]", entry.name),
+                    "either api_key or api_key_env is required".to_string(),
+                ))
+            }
+        };
+
+        destinations.push(DatadogDestination {
+            name: entry.name,
+            site: entry.site.unwrap_or_else(|| DEFAULT_SITE.to_string()),
+            api_key,
+            namespace: entry.namespace,
+            secondary_api_key: entry
+                .secondary_api_key_env
+                .as_deref()
+                .map(|var| secret_var(var, agent))
+                .transpose()?,
+            failover: None,
+        });
+    }
+
+    Ok(destinations)
+}
+
+/// Reads the secondary site the primary destination fails over to
+///
+/// `DATADOG_FAILOVER_SITE` enables the failover and requires
+/// `DATADOG_FAILOVER_API_KEY`; `DATADOG_FAILOVER_THRESHOLD` sets the number of
+/// consecutive server errors that trigger it.
+fn failover(agent: &AgentConfig) -> Result<Option<Failover>> {
+    let Ok(site) = env::var("DATADOG_FAILOVER_SITE") else {
+        return Ok(None);
+    };
+
+    let mut failover = Failover::new(site, secret_var("DATADOG_FAILOVER_API_KEY", agent)?);
+    if let Ok(value) = env::var("DATADOG_FAILOVER_THRESHOLD") {
+        failover.threshold = match value.trim().parse::<u32>() {
+            Ok(threshold) if threshold > 0 => threshold,
+            _ => {
+                return Err(ConfigError::Invalid(
+                    "DATADOG_FAILOVER_THRESHOLD".to_string(),
+                    format!("expected a positive number, got {}", value),
+                ))
+            }
+        };
+    }
+    Ok(Some(failover))
+}
+
+/// Reads the per-team namespace and tag overrides
+///
+/// Mappings come from the configuration file's `team_mappings` or, if the file
+/// has none, from the `GITHUB_TEAM_MAPPINGS` JSON object keyed by team slug.
+fn team_mappings(file: Option<&ConfigFile>) -> Result<HashMap<String, TeamMapping>> {
+    let mappings = match file.map(|f| f.team_mappings.clone()) {
+        Some(mappings) if !mappings.is_empty() => mappings,
+        _ => match env::var("GITHUB_TEAM_MAPPINGS") {
+            Ok(json) => serde_json::from_str(&json).map_err(|e| {
+                ConfigError::Invalid("GITHUB_TEAM_MAPPINGS".to_string(), e.to_string())
+            })?,
+            Err(_) => HashMap::new(),
+        },
+    };
+
+    if let Some((slug, _)) = mappings.iter().find(|(_, m)| {
+        m.namespace
+            .as_deref()
+            .is_some_and(|ns| ns.trim().is_empty())
+    }) {
+        return Err(ConfigError::Invalid(
+            format!("team_mappings[{}]", slug),
+            "namespace must not be empty".to_string(),
+        ));
+    }
+
+    // A team can't be its own ancestor, or its metrics would be counted twice
+    for slug in mappings.keys() {
+        let mut seen = vec![slug.as_str()];
+        let mut current = slug.as_str();
+        while let Some(parent) = mappings.get(current).and_then(|m| m.parent.as_deref()) {
+            if seen.contains(&parent) {
+                return Err(ConfigError::Invalid(
+                    format!("team_mappings[{}]", slug),
+                    format!("parent teams form a cycle through {}", parent),
+                ));
+            }
+            seen.push(parent);
+            current = parent;
+        }
+    }
+
+    Ok(mappings)
+}
+
+/// Reads `TEAM_FAILURE_THRESHOLD`, the share of teams allowed to fail
+///
+/// Defaults to 0.0, i.e. any failed team fails the run in `FAIL_ON_ERROR` mode.
+fn team_failure_threshold() -> Result<f64> {
+    let value = match env::var("TEAM_FAILURE_THRESHOLD") {
+        Ok(value) => value,
+        Err(_) => return Ok(0.0),
+    };
+
+    match value.trim().parse::<f64>() {
+        Ok(threshold) if (0.0..=1.0).contains(&threshold) => Ok(threshold),
+        _ => Err(ConfigError::Invalid(
+            "TEAM_FAILURE_THRESHOLD".to_string(),
+            format!("expected a number between 0 and 1, got {}", value),
+        )),
+    }
+}
+
+/// Reads the team discovery settings, if `GITHUB_DISCOVER_TEAMS` is set
+///
+/// Discovered team lists are cached in `GITHUB_TEAM_CACHE`, if set, for
+/// `GITHUB_TEAM_CACHE_TTL_HOURS` (default: 24).
+fn team_discovery() -> Result<Option<TeamDiscoveryConfig>> {
+    if env::var("GITHUB_DISCOVER_TEAMS").is_err() {
+        return Ok(None);
+    }
+
+    let hours = match env::var("GITHUB_TEAM_CACHE_TTL_HOURS") {
+        Ok(value) => value.trim().parse::<u64>().map_err(|_| {
+            ConfigError::Invalid(
+                "GITHUB_TEAM_CACHE_TTL_HOURS".to_string(),
+                format!("expected a number of hours, got {}", value),
+            )
+        })?,
+        Err(_) => DEFAULT_TEAM_CACHE_TTL_HOURS,
+    };
+    Ok(Some(TeamDiscoveryConfig {
+        cache_path: env::var("GITHUB_TEAM_CACHE").ok(),
+        cache_ttl: Duration::from_secs(hours * 3600),
+    }))
+}
+
+/// Reads `COPILOT_SEAT_PRICE`, the monthly price of a Copilot seat
+fn seat_price() -> Result<Option<f64>> {
+    let value = match env::var("COPILOT_SEAT_PRICE") {
+        Ok(value) => value,
+        Err(_) => return Ok(None),
+    };
+
+    match value.trim().parse::<f64>() {
+        Ok(price) if price.is_finite() && price >= 0.0 => Ok(Some(price)),
+        _ => Err(ConfigError::Invalid(
+            "COPILOT_SEAT_PRICE".to_string(),
+            format!("expected a non-negative number, got {}", value),
+        )),
+    }
+}
+
+/// Reads the threshold alert rules
+///
+/// Rules come from the configuration file's `alert_rules` or, if the file has
+/// none, from the `ALERT_RULES` JSON array. Every rule needs exactly one
+/// condition with a non-negative threshold.
+fn alert_rules(file: Option<&ConfigFile>) -> Result<Vec<AlertRule>> {
+    let rules = match file.map(|f| f.alert_rules.clone()) {
+        Some(rules) if !rules.is_empty() => rules,
+        _ => match env::var("ALERT_RULES") {
+            Ok(json) => serde_json::from_str(&json)
+                .map_err(|e| ConfigError::Invalid("ALERT_RULES".to_string(), e.to_string()))?,
+            Err(_) => Vec::new(),
+        },
+    };
+
+    for (i, rule) in rules.iter().enumerate() {
+        let problem = match (rule.drop_percent, rule.below) {
+            (Some(_), Some(_)) => Some("set either drop_percent or below, not both"),
+            (None, None) => Some("either drop_percent or below is required"),
+            (Some(t), None) | (None, Some(t)) if !t.is_finite() || t < 0.0 => {
+                Some("thresholds must be non-negative numbers")
+            }
+            _ => None,
+        };
+        if let Some(problem) = problem {
+            return Err(ConfigError::Invalid(
+                format!("alert_rules[{}]", i),
+                problem.to_string(),
+            ));
+        }
+    }
+
+    Ok(rules)
+}
+
+/// Reads the export destinations
+///
+/// Destinations come from the configuration file's `exports` or, if the file
+/// has none, from the `EXPORT_SINKS` JSON array.
+fn exports(file: Option<&ConfigFile>) -> Result<Vec<ExportConfig>> {
+    match file.map(|f| f.exports.clone()) {
+        Some(exports) if !exports.is_empty() => Ok(exports),
+        _ => match env::var("EXPORT_SINKS") {
+            Ok(json) => serde_json::from_str(&json)
+                .map_err(|e| ConfigError::Invalid("EXPORT_SINKS".to_string(), e.to_string())),
+            Err(_) => Ok(Vec::new()),
+        },
+    }
+}
+
+/// Reads `DATADOG_NAMESPACE_TEMPLATE`, falling back to the default template
+fn namespace_template() -> Result<NamespaceTemplate> {
+    match env::var("DATADOG_NAMESPACE_TEMPLATE") {
+        Ok(template) if !template.trim().is_empty() => NamespaceTemplate::parse(&template)
+            .map_err(|e| ConfigError::Invalid("DATADOG_NAMESPACE_TEMPLATE".to_string(), e)),
+        _ => Ok(NamespaceTemplate::default()),
+    }
+}
+
+/// Reads `DATADOG_SEND_CONCURRENCY`, the number of chunks submitted at the
+/// same time
+fn send_concurrency() -> Result<usize> {
+    match env::var("DATADOG_SEND_CONCURRENCY") {
+        Ok(value) => parse_concurrency(&value)
+            .map_err(|e| ConfigError::Invalid("DATADOG_SEND_CONCURRENCY".to_string(), e)),
+        Err(_) => Ok(DEFAULT_SEND_CONCURRENCY),
+    }
+}
+
+/// Reads `DATADOG_TAG_TEMPLATES` and `DD_ENV`, the tags with placeholders
+/// added to every series
+///
+/// # Arguments
+///
+/// * `team_hashing` - Whether `TEAM_HASH_SALT` is set, which rules out the
+///   `{team_slug}` placeholder
+fn tag_templates(team_hashing: bool) -> Result<TagTemplates> {
+    let Ok(list) = env::var("DATADOG_TAG_TEMPLATES") else {
+        return Ok(TagTemplates::default());
+    };
+    let invalid = |e| ConfigError::Invalid("DATADOG_TAG_TEMPLATES".to_string(), e);
+    let templates = TagTemplates::parse(&list).map_err(invalid)?;
+    if team_hashing && templates.uses("team_slug") {
+        return Err(invalid(
+            "{team_slug} would reveal the teams hashed by TEAM_HASH_SALT".to_string(),
+        ));
+    }
+    Ok(templates.with_env(env::var("DD_ENV").ok()))
+}
+
+/// Reads an optional environment variable with a parser, rejecting values the
+/// parser doesn't accept
+///
+/// # Arguments
+///
+/// * `name` - Name of the variable
+/// * `parse` - Parses the value or describes why it is invalid
+fn parsed_var<T>(
+    name: &str,
+    parse: impl FnOnce(&str) -> std::result::Result<T, String>,
+) -> Result<Option<T>> {
+    match env::var(name) {
+        Ok(value) => parse(&value)
+            .map(Some)
+            .map_err(|e| ConfigError::Invalid(name.to_string(), e)),
+        Err(_) => Ok(None),
+    }
+}
+
+/// Reads the GitHub request policies and identification
+///
+/// # Arguments
+///
+/// * `schema_mode` - The mode read from `STRICT_SCHEMA`
+/// * `agent` - Settings of the HTTP agent
+fn github_settings(schema_mode: SchemaMode, agent: &AgentConfig) -> Result<GitHubSettings> {
+    let defaults = GitHubSettings::default();
+    let retry_policy = RetryPolicy::new(
+        parsed_var("GITHUB_MAX_RETRIES", parse_count)?.unwrap_or(defaults.retry_policy.max_retries),
+        parsed_var("GITHUB_RETRY_BACKOFF_MS", parse_count)?
+            .map_or(defaults.retry_policy.backoff, |ms| {
+                Duration::from_millis(ms.into())
+            }),
+    );
+    let pending_policy = PendingPolicy::new(
+        parsed_var("GITHUB_PENDING_RETRIES", parse_count)?
+            .unwrap_or(defaults.pending_policy.max_retries),
+        parsed_var("GITHUB_PENDING_DELAY_SECS", parse_count)?
+            .map_or(defaults.pending_policy.delay, |secs| {
+                Duration::from_secs(secs.into())
+            }),
+    )
+    .with_max_total_retries(
+        parsed_var("GITHUB_PENDING_RETRY_BUDGET", parse_count)?
+            .unwrap_or(defaults.pending_policy.max_total_retries),
+    );
+
+    Ok(GitHubSettings {
+        retry_policy,
+        pending_policy,
+        schema_mode,
+        metrics_source: env::var("GITHUB_METRICS_SOURCE")
+            .map(|source| MetricsSource::parse(&source))
+            .unwrap_or_default(),
+        user_agent: env::var("GITHUB_USER_AGENT").unwrap_or(defaults.user_agent),
+        api_version: env::var("GITHUB_API_VERSION").unwrap_or(defaults.api_version),
+        agent: agent.clone(),
+    })
+}
+
+/// Reads `DATADOG_TAG_ALLOWLIST` and `DATADOG_TAG_DENYLIST`
+fn tag_filter() -> TagFilter {
+    let allow = env::var("DATADOG_TAG_ALLOWLIST")
+        .ok()
+        .map(|keys| parse_keys(&keys));
+    let deny = env::var("DATADOG_TAG_DENYLIST")
+        .map(|keys| parse_keys(&keys))
+        .unwrap_or_default();
+    TagFilter::new(allow, deny)
+}
+
+/// Reads `DATADOG_MAX_CHUNKS_PER_SECOND`, `DATADOG_MAX_POINTS_PER_SECOND` and
+/// `DATADOG_MAX_RATE_LIMIT_WAIT_SECS`
+fn throttle() -> Result<Throttle> {
+    let throttle = Throttle::new(
+        parsed_var("DATADOG_MAX_CHUNKS_PER_SECOND", parse_rate)?,
+        parsed_var("DATADOG_MAX_POINTS_PER_SECOND", parse_rate)?,
+    );
+    Ok(
+        match parsed_var("DATADOG_MAX_RATE_LIMIT_WAIT_SECS", parse_rate)? {
+            Some(secs) => throttle.with_max_rate_limit_wait(Duration::from_secs_f64(secs)),
+            None => throttle,
+        },
+    )
+}
+
+/// Reads `SEND_LATEST_DAY_ONLY`, the fetched days that are sent
+fn day_window() -> DayWindow {
+    if env::var("SEND_LATEST_DAY_ONLY").is_ok() {
+        DayWindow::LatestCompleteDay
+    } else {
+        DayWindow::All
+    }
+}
+
+/// Reads `MIN_LANGUAGE_ENGAGED_USERS` and `TOP_N_BREAKDOWNS`
+fn breakdown_limits() -> Result<BreakdownLimits> {
+    Ok(
+        BreakdownLimits::new(parsed_var("MIN_LANGUAGE_ENGAGED_USERS", parse_limit)?)
+            .with_top_n(parsed_var("TOP_N_BREAKDOWNS", parse_limit)?),
+    )
+}
+
+/// Reads `LANGUAGE_ALIASES` and `LANGUAGE_RAW_TAG`
+fn language_normalizer() -> Result<LanguageNormalizer> {
+    let aliases = parsed_var("LANGUAGE_ALIASES", parse_aliases)?.unwrap_or_default();
+    Ok(LanguageNormalizer::new(aliases).with_raw_tag(env::var("LANGUAGE_RAW_TAG").is_ok()))
+}
+
+/// Reads `DATADOG_REPOSITORY_ALLOWLIST`, `DATADOG_REPOSITORY_DENYLIST` and
+/// `DATADOG_REPOSITORY_HASH_SALT`
+fn repository_filter() -> RepositoryFilter {
+    let allow = env::var("DATADOG_REPOSITORY_ALLOWLIST")
+        .ok()
+        .map(|patterns| parse_patterns(&patterns));
+    let deny = env::var("DATADOG_REPOSITORY_DENYLIST")
+        .map(|patterns| parse_patterns(&patterns))
+        .unwrap_or_default();
+    RepositoryFilter::new(allow, deny).with_hash_salt(env::var("DATADOG_REPOSITORY_HASH_SALT").ok())
+}
+
+/// Reads `STRICT_SCHEMA`, how strictly GitHub responses are validated
+fn schema_mode() -> Result<SchemaMode> {
+    match env::var("STRICT_SCHEMA") {
+        Ok(value) => SchemaMode::parse(&value).ok_or_else(|| {
+            ConfigError::Invalid(
+                "STRICT_SCHEMA".to_string(),
+                format!(
+                    "expected 1, true, strict, warn, 0, false or off, got {}",
+                    value
+                ),
+            )
+        }),
+        Err(_) => Ok(SchemaMode::default()),
+    }
+}
+
+/// Reads `ANOMALY_SIGMA`, the z-score beyond which a day is anomalous
+fn anomaly_sigma() -> Result<Option<f64>> {
+    let value = match env::var("ANOMALY_SIGMA") {
+        Ok(value) => value,
+        Err(_) => return Ok(None),
+    };
+
+    match value.trim().parse::<f64>() {
+        Ok(sigma) if sigma.is_finite() && sigma > 0.0 => Ok(Some(sigma)),
+        _ => Err(ConfigError::Invalid(
+            "ANOMALY_SIGMA".to_string(),
+            format!("expected a positive number, got {}", value),
+        )),
+    }
+}
+
+/// Reads `LEADERBOARD_WEEKDAY` and `LEADERBOARD_OUTPUT`
+///
+/// The leaderboard is disabled unless a weekday is set.
+fn leaderboard() -> Result<Option<LeaderboardConfig>> {
+    let day = match env::var("LEADERBOARD_WEEKDAY") {
+        Ok(day) => day,
+        Err(_) => return Ok(None),
+    };
+
+    let weekday = day.trim().parse::<Weekday>().map_err(|_| {
+        ConfigError::Invalid(
+            "LEADERBOARD_WEEKDAY".to_string(),
+            format!("unknown day {}", day),
+        )
+    })?;
+    Ok(Some(LeaderboardConfig {
+        weekday,
+        output: env::var("LEADERBOARD_OUTPUT").ok(),
+    }))
+}
+
+/// Resolves the weekly digest settings
+///
+/// Settings come from the configuration file's `digest` section or, if the
+/// file has none, from `DIGEST_SENDER`, `DIGEST_RECIPIENTS` (comma-separated)
+/// and `DIGEST_WEEKDAY`. The digest is disabled without recipients.
+fn digest(file: Option<&ConfigFile>) -> Result<Option<DigestConfig>> {
+    let entry = match file.and_then(|f| f.digest.clone()) {
+        Some(entry) => entry,
+        None => DigestEntry {
+            sender: env::var("DIGEST_SENDER").ok(),
+            recipients: env::var("DIGEST_RECIPIENTS")
+                .map(|r| split_list(&r))
+                .unwrap_or_default(),
+            weekday: env::var("DIGEST_WEEKDAY").ok(),
+        },
+    };
+
+    if entry.recipients.is_empty() {
+        return Ok(None);
+    }
+
+    let sender = entry.sender.ok_or_else(|| {
+        ConfigError::Invalid(
+            "DIGEST_SENDER".to_string(),
+            "a sender is required when digest recipients are configured".to_string(),
+        )
+    })?;
+
+    let weekday = match entry.weekday {
+        Some(day) => day.trim().parse::<Weekday>().map_err(|_| {
+            ConfigError::Invalid("DIGEST_WEEKDAY".to_string(), format!("unknown day {}", day))
+        })?,
+        None => Weekday::Mon,
+    };
+
+    Ok(Some(DigestConfig {
+        sender,
+        recipients: entry.recipients,
+        weekday,
+    }))
+}
+
+/// Reads the enterprise list from `GITHUB_ENTERPRISE_IDS` or `GITHUB_ENTERPRISE_ID`
+fn enterprise_entries_from_env() -> Result<Vec<EnterpriseEntry>> {
+    let ids = match env::var("GITHUB_ENTERPRISE_IDS") {
+        Ok(ids) => split_list(&ids),
+        Err(_) => vec![required_var("GITHUB_ENTERPRISE_ID")?],
+    };
+
+    if ids.is_empty() {
+        return Err(ConfigError::Invalid(
+            "GITHUB_ENTERPRISE_IDS".to_string(),
+            "no enterprise IDs listed".to_string(),
+        ));
+    }
+
+    Ok(ids
+        .into_iter()
+        .map(|id| EnterpriseEntry {
+            id,
+            ..EnterpriseEntry::default()
+        })
+        .collect())
+}
+
+/// Applies namespace and team defaults to the configured enterprise entries
+/// and reads the credentials of tenants
+fn resolve_enterprises(
+    entries: Vec<EnterpriseEntry>,
+    base_namespace: &str,
+    default_teams: &[String],
+    stage: Stage,
+    agent: &AgentConfig,
+) -> Result<Vec<EnterpriseConfig>> {
+    let multiple = entries.len() > 1;
+
+    entries
+        .into_iter()
+        .map(|entry| {
+            let tenant = tenant(&entry, stage, agent)?;
+            let namespace = entry.namespace.unwrap_or_else(|| {
+                if multiple {
+                    format!("{}.{}", base_namespace, entry.id)
+                } else {
+                    base_namespace.to_string()
+                }
+            });
+
+            Ok(EnterpriseConfig {
+                id: entry.id,
+                namespace,
+                team_slugs: entry.team_slugs.unwrap_or_else(|| default_teams.to_vec()),
+                tenant,
+            })
+        })
+        .collect()
+}
+
+/// Reads the credentials of a tenant enterprise entry
+///
+/// # Arguments
+///
+/// * `entry` - The enterprise entry
+/// * `stage` - The part of the run the configuration is used for
+/// * `agent` - HTTP agent settings, for reading credentials from Vault
+///
+/// # Returns
+///
+/// * `Result<Option<TenantConfig>>` - The credentials, `None` if the entry
+///   has none of its own, or an error if the tenant has no Datadog API key
+///   or a named secret isn't set
+fn tenant(
+    entry: &EnterpriseEntry,
+    stage: Stage,
+    agent: &AgentConfig,
+) -> Result<Option<TenantConfig>> {
+    if !entry.is_tenant() {
+        return Ok(None);
+    }
+    // Without a key of its own, the tenant's series would reach the
+    // operator's organizations
+    if entry.datadog_api_key.is_none() && entry.datadog_api_key_env.is_none() {
+        return Err(ConfigError::Invalid(
+            format!("enterprises[{}]", entry.id),
+            "a tenant needs datadog_api_key or datadog_api_key_env".to_string(),
+        ));
+    }
+    let credential = |value: &Option<String>, var: &Option<String>| match (value, var) {
+        (Some(value), _) => Ok(Some(value.clone())),
+        (None, Some(var)) => secret_var(var, agent).map(Some),
+        (None, None) => Ok(None),
+    };
+
+    let github_token = match stage {
+        Stage::Send | Stage::Local => None,
+        _ => credential(&entry.github_token, &entry.github_token_env)?,
+    };
+    let datadog = match stage {
+        Stage::Fetch | Stage::Local => None,
+        _ => credential(&entry.datadog_api_key, &entry.datadog_api_key_env)?.map(|api_key| {
+            DatadogDestination {
+                site: entry
+                    .datadog_site
+                    .clone()
+                    .unwrap_or_else(|| DEFAULT_SITE.to_string()),
+                ..DatadogDestination::new(format!("tenant:{}", entry.id), api_key)
+            }
+        }),
+    };
+    let datadog_app_key = match stage {
+        Stage::Fetch | Stage::Local => None,
+        _ => credential(&entry.datadog_app_key, &entry.datadog_app_key_env)?,
+    };
+    Ok(Some(TenantConfig {
+        github_token,
+        datadog,
+        datadog_app_key,
+    }))
+}
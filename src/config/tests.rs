@@ -0,0 +1,1605 @@
+//! # Configuration Tests
+//!
+//! Tests for resolving the collector configuration from environment variables
+//! and the JSON configuration file. Environment variables are set with
+//! `temp_env` so tests don't interfere with each other.
+
+use std::io::Write;
+
+use super::{
+    Config, ConfigError, ConfigFile, DigestConfig, EnterpriseConfig, OpsgenieConfig, Stage,
+    TeamMapping,
+};
+use crate::processors::alerts::{AlertMetric, AlertRule};
+use crate::services::datadog::names::NamespaceTemplate;
+use crate::services::datadog::{BreakdownLimits, TagContext, Throttle};
+use crate::services::export::ExportConfig;
+use crate::services::github::{MetricsSource, SchemaMode};
+use crate::services::tls::{self, TlsError};
+use crate::tests::mock_server::{self, MockRequest, MockResponse};
+
+/// Environment variables read by `Config::from_env`, with only credentials set
+const CLEAN_ENV: &[(&str, Option<&str>)] = &[
+    ("GITHUB_TOKEN", Some("ghp_test")),
+    ("DATADOG_API_KEY", Some("dd_test")),
+    ("GITHUB_TOKEN_FILE", None),
+    ("DATADOG_API_KEY_FILE", None),
+    ("GHRUST_CONFIG_FILE", None),
+    ("GITHUB_ENTERPRISE_ID", None),
+    ("GITHUB_ENTERPRISE_IDS", None),
+    ("GITHUB_TEAM_SLUGS", None),
+    ("DATADOG_METRIC_NAMESPACE", None),
+    ("DATADOG_NAMESPACE_TEMPLATE", None),
+    ("DATADOG_TAG_TEMPLATES", None),
+    ("DD_ENV", None),
+    ("DATADOG_SEND_CONCURRENCY", None),
+    ("DATADOG_MAX_CHUNKS_PER_SECOND", None),
+    ("DATADOG_MAX_POINTS_PER_SECOND", None),
+    ("DATADOG_MAX_RATE_LIMIT_WAIT_SECS", None),
+    ("MIN_LANGUAGE_ENGAGED_USERS", None),
+    ("TOP_N_BREAKDOWNS", None),
+    ("LANGUAGE_ALIASES", None),
+    ("SKIP_ENTERPRISE_METRICS", None),
+    ("COLLECT_PREMIUM_REQUESTS", None),
+    ("COLLECT_ORG_METRICS", None),
+    ("COLLECT_SEAT_METRICS", None),
+    ("REPORT_UNUSED_SEAT_USERS", None),
+    ("REPORT_SEAT_ACTIVITY", None),
+    ("COPILOT_SEAT_PRICE", None),
+    ("DATADOG_SITE", None),
+    ("DATADOG_DESTINATIONS", None),
+    ("GITHUB_TEAM_MAPPINGS", None),
+    ("GHRUST_CA_BUNDLE", None),
+    ("FAIL_ON_ERROR", None),
+    ("TEAM_FAILURE_THRESHOLD", None),
+    ("DIGEST_SENDER", None),
+    ("DIGEST_RECIPIENTS", None),
+    ("DIGEST_WEEKDAY", None),
+    ("METRICS_STORE_PATH", None),
+    ("METRICS_STORE_REUSE", None),
+    ("TEAM_ROLLUP", None),
+    ("DATADOG_APP_KEY", None),
+    ("PROVISION_DASHBOARDS", None),
+    ("UPDATE_METRIC_METADATA", None),
+    ("ALERT_RULES", None),
+    ("ALERT_WEBHOOK_URL", None),
+    ("HEALTHCHECK_URL", None),
+    ("COLLECTOR_RUN_ID_TAG", None),
+    ("OPSGENIE_API_KEY", None),
+    ("OPSGENIE_API_URL", None),
+    ("ANOMALY_SIGMA", None),
+    ("WEEKLY_ROLLUPS", None),
+    ("MONTHLY_ROLLUPS", None),
+    ("TEAM_DISTRIBUTION", None),
+    ("LEADERBOARD_WEEKDAY", None),
+    ("LEADERBOARD_OUTPUT", None),
+    ("EXPORT_SINKS", None),
+    ("BASELINE_S3_BUCKET", None),
+    ("BASELINE_S3_KEY", None),
+    ("TEAM_HASH_SALT", None),
+    ("TEAM_HASH_MAPPING_FILE", None),
+    ("GITHUB_ETAG_CACHE", None),
+    ("GITHUB_RAW_ARCHIVE", None),
+    ("GITHUB_DISCOVER_TEAMS", None),
+    ("GITHUB_TEAM_CACHE", None),
+    ("GITHUB_TEAM_CACHE_TTL_HOURS", None),
+    ("GITHUB_USER_AGENT", None),
+    ("GITHUB_MAX_RETRIES", None),
+    ("GITHUB_RETRY_BACKOFF_MS", None),
+    ("GITHUB_PENDING_RETRIES", None),
+    ("GITHUB_PENDING_DELAY_SECS", None),
+    ("GITHUB_PENDING_RETRY_BUDGET", None),
+    ("GITHUB_METRICS_SOURCE", None),
+    ("STRICT_SCHEMA", None),
+    ("HTTP_DEBUG_LOG", None),
+    ("DATADOG_API_KEY_SECONDARY", None),
+    ("DATADOG_BATCH_SEND", None),
+    ("DATADOG_API_KEY_SECONDARY_FILE", None),
+    ("DATADOG_FAILOVER_SITE", None),
+    ("DATADOG_FAILOVER_API_KEY", None),
+    ("DATADOG_FAILOVER_API_KEY_FILE", None),
+    ("DATADOG_FAILOVER_THRESHOLD", None),
+];
+
+/// Runs `f` with the clean environment plus the given overrides
+fn with_env<F: FnOnce()>(overrides: &[(&str, Option<&str>)], f: F) {
+    let vars: Vec<(String, Option<String>)> = CLEAN_ENV
+        .iter()
+        .filter(|(key, _)| !overrides.iter().any(|(k, _)| k == key))
+        .chain(overrides.iter())
+        .map(|(key, value)| (key.to_string(), value.map(str::to_string)))
+        .collect();
+    temp_env::with_vars(vars, f);
+}
+
+#[test]
+fn test_single_enterprise_uses_base_namespace() {
+    with_env(
+        &[
+            ("GITHUB_ENTERPRISE_ID", Some("acme")),
+            ("GITHUB_TEAM_SLUGS", Some("platform, ,web")),
+        ],
+        || {
+            let config = Config::from_env().unwrap();
+            assert_eq!(config.datadog_namespace, "github.copilot");
+            assert_eq!(
+                config.enterprises,
+                vec![EnterpriseConfig {
+                    id: "acme".to_string(),
+                    namespace: "github.copilot".to_string(),
+                    team_slugs: vec!["platform".to_string(), "web".to_string()],
+                    tenant: None,
+                }]
+            );
+        },
+    );
+}
+
+#[test]
+fn test_multiple_enterprises_get_own_namespaces() {
+    with_env(
+        &[
+            ("GITHUB_ENTERPRISE_IDS", Some("acme, acme-labs")),
+            ("GITHUB_ENTERPRISE_ID", Some("ignored")),
+            ("DATADOG_METRIC_NAMESPACE", Some("gh")),
+        ],
+        || {
+            let config = Config::from_env().unwrap();
+            let namespaces: Vec<_> = config
+                .enterprises
+                .iter()
+                .map(|e| (e.id.as_str(), e.namespace.as_str()))
+                .collect();
+            assert_eq!(
+                namespaces,
+                vec![("acme", "gh.acme"), ("acme-labs", "gh.acme-labs")]
+            );
+        },
+    );
+}
+
+#[test]
+fn test_secrets_from_files() {
+    let mut token = tempfile();
+    writeln!(token.1, "  ghp_from_file").unwrap();
+    let mut api_key = tempfile();
+    writeln!(api_key.1, "dd_from_file").unwrap();
+    let empty = tempfile();
+
+    with_env(
+        &[
+            ("GITHUB_ENTERPRISE_ID", Some("acme")),
+            ("GITHUB_TOKEN", None),
+            ("GITHUB_TOKEN_FILE", Some(token.0.as_str())),
+            ("DATADOG_API_KEY", None),
+            ("DATADOG_API_KEY_FILE", Some(api_key.0.as_str())),
+        ],
+        || {
+            let config = Config::from_env().unwrap();
+            assert_eq!(config.github_token, "ghp_from_file");
+            assert_eq!(config.datadog_destinations[0].api_key, "dd_from_file");
+        },
+    );
+
+    // The variable itself takes precedence over the file
+    with_env(
+        &[
+            ("GITHUB_ENTERPRISE_ID", Some("acme")),
+            ("GITHUB_TOKEN_FILE", Some(token.0.as_str())),
+        ],
+        || assert_eq!(Config::from_env().unwrap().github_token, "ghp_test"),
+    );
+
+    with_env(
+        &[
+            ("GITHUB_ENTERPRISE_ID", Some("acme")),
+            ("GITHUB_TOKEN", None),
+            ("GITHUB_TOKEN_FILE", Some(empty.0.as_str())),
+        ],
+        || match Config::from_env() {
+            Err(ConfigError::Invalid(name, _)) => assert_eq!(name, "GITHUB_TOKEN_FILE"),
+            other => panic!("Expected invalid GITHUB_TOKEN_FILE, got {:?}", other),
+        },
+    );
+
+    for file in [token, api_key, empty] {
+        std::fs::remove_file(&file.0).ok();
+    }
+}
+
+#[test]
+fn test_enterprises_from_config_file() {
+    let mut file = tempfile();
+    write!(
+        file.1,
+        r#"{{"enterprises": [
+            {{"id": "acme", "namespace": "custom.acme", "team_slugs": ["core"]}},
+            {{"id": "labs"}}
+        ]}}"#
+    )
+    .unwrap();
+    with_env(
+        &[
+            ("GHRUST_CONFIG_FILE", Some(file.0.as_str())),
+            ("GITHUB_TEAM_SLUGS", Some("web")),
+        ],
+        || {
+            let config = Config::from_env().unwrap();
+            assert_eq!(config.enterprises.len(), 2);
+            assert_eq!(config.enterprises[0].namespace, "custom.acme");
+            assert_eq!(config.enterprises[0].team_slugs, vec!["core".to_string()]);
+            assert_eq!(config.enterprises[1].namespace, "github.copilot.labs");
+            assert_eq!(config.enterprises[1].team_slugs, vec!["web".to_string()]);
+        },
+    );
+
+    std::fs::remove_file(&file.0).ok();
+}
+
+#[test]
+fn test_tenant_from_config_file() {
+    let mut file = tempfile();
+    write!(
+        file.1,
+        r#"{{"enterprises": [
+            {{"id": "acme"}},
+            {{"id": "globex", "github_token_env": "GLOBEX_GITHUB_TOKEN",
+              "datadog_api_key_env": "GLOBEX_DD_API_KEY", "datadog_site": "datadoghq.com"}}
+        ]}}"#
+    )
+    .unwrap();
+    with_env(
+        &[
+            ("GHRUST_CONFIG_FILE", Some(file.0.as_str())),
+            ("GLOBEX_GITHUB_TOKEN", Some("globex-token")),
+            ("GLOBEX_DD_API_KEY", Some("globex-key")),
+        ],
+        || {
+            let config = Config::from_env().unwrap();
+            assert_eq!(config.enterprises[0].tenant, None);
+            let tenant = config.enterprises[1].tenant.clone().unwrap();
+            assert_eq!(tenant.github_token.as_deref(), Some("globex-token"));
+            let datadog = tenant.datadog.unwrap();
+            assert_eq!(datadog.name, "tenant:globex");
+            assert_eq!(datadog.api_key, "globex-key");
+            assert_eq!(datadog.site, "datadoghq.com");
+
+            // A send doesn't read the tenant's GitHub token
+            temp_env::with_var_unset("GLOBEX_GITHUB_TOKEN", || {
+                let config = Config::from_env_for(Stage::Send).unwrap();
+                let tenant = config.enterprises[1].tenant.clone().unwrap();
+                assert_eq!(tenant.github_token, None);
+                assert!(tenant.datadog.is_some());
+                assert!(matches!(
+                    Config::from_env(),
+                    Err(ConfigError::Missing(name)) if name == "GLOBEX_GITHUB_TOKEN"
+                ));
+            });
+        },
+    );
+
+    std::fs::remove_file(&file.0).ok();
+}
+
+#[test]
+fn test_tenant_needs_its_own_datadog_keys() {
+    let mut file = tempfile();
+    write!(
+        file.1,
+        r#"{{"enterprises": [{{"id": "globex", "github_token": "globex-token"}}]}}"#
+    )
+    .unwrap();
+    with_env(&[("GHRUST_CONFIG_FILE", Some(file.0.as_str()))], || {
+        assert!(matches!(
+            Config::from_env(),
+            Err(ConfigError::Invalid(name, _)) if name == "enterprises[globex]"
+        ));
+    });
+
+    // Dashboards need the tenant's application key, not the operator's
+    std::fs::write(
+        &file.0,
+        r#"{"enterprises": [{"id": "globex", "datadog_api_key": "globex-key"}]}"#,
+    )
+    .unwrap();
+    with_env(
+        &[
+            ("GHRUST_CONFIG_FILE", Some(file.0.as_str())),
+            ("PROVISION_DASHBOARDS", Some("1")),
+            ("DATADOG_APP_KEY", Some("operator-app-key")),
+        ],
+        || {
+            assert!(matches!(
+                Config::from_env(),
+                Err(ConfigError::Invalid(name, _)) if name == "enterprises[globex]"
+            ));
+
+            std::fs::write(
+                &file.0,
+                r#"{"enterprises": [{"id": "globex", "datadog_api_key": "globex-key",
+                    "datadog_app_key": "globex-app-key"}]}"#,
+            )
+            .unwrap();
+            let config = Config::from_env().unwrap();
+            let tenant = config.enterprises[0].tenant.clone().unwrap();
+            assert_eq!(tenant.datadog_app_key.as_deref(), Some("globex-app-key"));
+        },
+    );
+
+    std::fs::remove_file(&file.0).ok();
+}
+#[test]
+fn test_stage_credentials() {
+    // A fetch needs no Datadog key, a send no GitHub token
+    with_env(
+        &[
+            ("GITHUB_ENTERPRISE_ID", Some("acme")),
+            ("DATADOG_API_KEY", None),
+        ],
+        || {
+            let config = Config::from_env_for(Stage::Fetch).unwrap();
+            assert!(config.datadog_destinations.is_empty());
+            assert!(matches!(
+                Config::from_env_for(Stage::Send),
+                Err(ConfigError::Missing(name)) if name == "DATADOG_API_KEY"
+            ));
+        },
+    );
+    with_env(&[("GITHUB_ENTERPRISE_ID", Some("acme"))], || {
+        temp_env::with_var_unset("GITHUB_TOKEN", || {
+            let config = Config::from_env_for(Stage::Send).unwrap();
+            assert_eq!(config.github_token, "");
+            assert_eq!(config.datadog_destinations.len(), 1);
+            assert!(matches!(
+                Config::from_env_for(Stage::Fetch),
+                Err(ConfigError::Missing(name)) if name == "GITHUB_TOKEN"
+            ));
+        });
+    });
+    // A local command needs neither
+    with_env(
+        &[
+            ("GITHUB_ENTERPRISE_ID", Some("acme")),
+            ("GITHUB_TOKEN", None),
+            ("DATADOG_API_KEY", None),
+        ],
+        || {
+            let config = Config::from_env_for(Stage::Local).unwrap();
+            assert_eq!(config.github_token, "");
+            assert!(config.datadog_destinations.is_empty());
+        },
+    );
+}
+
+#[test]
+fn test_missing_required_variables() {
+    with_env(&[("GITHUB_ENTERPRISE_ID", Some("acme"))], || {
+        temp_env::with_var_unset("GITHUB_TOKEN", || match Config::from_env() {
+            Err(ConfigError::Missing(name)) => assert_eq!(name, "GITHUB_TOKEN"),
+            other => panic!("Expected missing GITHUB_TOKEN, got {:?}", other),
+        });
+    });
+
+    with_env(&[], || match Config::from_env() {
+        Err(ConfigError::Missing(name)) => assert_eq!(name, "GITHUB_ENTERPRISE_ID"),
+        other => panic!("Expected missing GITHUB_ENTERPRISE_ID, got {:?}", other),
+    });
+}
+
+#[test]
+fn test_additional_datadog_destinations_from_env() {
+    with_env(
+        &[
+            ("GITHUB_ENTERPRISE_ID", Some("acme")),
+            ("DATADOG_SITE", Some("datadoghq.com")),
+            ("SECURITY_DD_KEY", Some("dd_security")),
+            ("SECURITY_DD_KEY_NEW", Some("dd_security_new")),
+            ("DATADOG_API_KEY_SECONDARY", Some("dd_test_new")),
+            (
+                "DATADOG_DESTINATIONS",
+                Some(
+                    r#"[{"name": "security", "site": "us5.datadoghq.com",
+                         "api_key_env": "SECURITY_DD_KEY", "namespace": "sec.copilot",
+                         "secondary_api_key_env": "SECURITY_DD_KEY_NEW"},
+                        {"name": "eng", "api_key": "dd_eng"}]"#,
+                ),
+            ),
+        ],
+        || {
+            let config = Config::from_env().unwrap();
+            let destinations = &config.datadog_destinations;
+            assert_eq!(destinations.len(), 3);
+            assert_eq!(destinations[0].name, "primary");
+            assert_eq!(destinations[0].site, "datadoghq.com");
+            assert_eq!(destinations[0].api_key, "dd_test");
+            assert_eq!(
+                destinations[0].secondary_api_key.as_deref(),
+                Some("dd_test_new")
+            );
+            assert_eq!(destinations[1].site, "us5.datadoghq.com");
+            assert_eq!(destinations[1].api_key, "dd_security");
+            assert_eq!(
+                destinations[1].secondary_api_key.as_deref(),
+                Some("dd_security_new")
+            );
+            assert_eq!(destinations[2].secondary_api_key, None);
+            assert_eq!(destinations[1].namespace.as_deref(), Some("sec.copilot"));
+            assert_eq!(destinations[2].site, "datadoghq.eu");
+            assert_eq!(destinations[2].api_key, "dd_eng");
+        },
+    );
+}
+
+#[test]
+fn test_named_secrets_from_files() {
+    let mut security_key = tempfile();
+    writeln!(security_key.1, "dd_security").unwrap();
+    let mut globex_key = tempfile();
+    writeln!(globex_key.1, "globex-key").unwrap();
+    let mut file = tempfile();
+    write!(
+        file.1,
+        r#"{{"enterprises": [
+            {{"id": "globex", "datadog_api_key_env": "GLOBEX_DD_API_KEY"}}
+        ],
+        "datadog_destinations": [
+            {{"name": "security", "api_key_env": "SECURITY_DD_KEY"}}
+        ]}}"#
+    )
+    .unwrap();
+
+    with_env(
+        &[
+            ("GHRUST_CONFIG_FILE", Some(file.0.as_str())),
+            ("SECURITY_DD_KEY_FILE", Some(security_key.0.as_str())),
+            ("GLOBEX_DD_API_KEY_FILE", Some(globex_key.0.as_str())),
+        ],
+        || {
+            let config = Config::from_env().unwrap();
+            assert_eq!(config.datadog_destinations[1].api_key, "dd_security");
+            let tenant = config.enterprises[0].tenant.clone().unwrap();
+            assert_eq!(tenant.datadog.unwrap().api_key, "globex-key");
+        },
+    );
+
+    for path in [&security_key.0, &globex_key.0, &file.0] {
+        std::fs::remove_file(path).ok();
+    }
+}
+
+#[test]
+fn test_datadog_destination_requires_api_key() {
+    with_env(
+        &[
+            ("GITHUB_ENTERPRISE_ID", Some("acme")),
+            ("DATADOG_DESTINATIONS", Some(r#"[{"name": "security"}]"#)),
+        ],
+        || {
+            assert!(matches!(
+                Config::from_env(),
+                Err(ConfigError::Invalid(_, _))
+            ));
+        },
+    );
+}
+
+#[test]
+fn test_datadog_failover_from_env() {
+    with_env(&[("GITHUB_ENTERPRISE_ID", Some("acme"))], || {
+        let config = Config::from_env().unwrap();
+        assert_eq!(config.datadog_destinations[0].failover, None);
+    });
+
+    with_env(
+        &[
+            ("GITHUB_ENTERPRISE_ID", Some("acme")),
+            ("DATADOG_FAILOVER_SITE", Some("us5.datadoghq.com")),
+            ("DATADOG_FAILOVER_API_KEY", Some("dd_us5")),
+            ("DATADOG_FAILOVER_THRESHOLD", Some("5")),
+        ],
+        || {
+            let config = Config::from_env().unwrap();
+            let failover = config.datadog_destinations[0].failover.as_ref().unwrap();
+            assert_eq!(failover.site, "us5.datadoghq.com");
+            assert_eq!(failover.api_key, "dd_us5");
+            assert_eq!(failover.threshold, 5);
+        },
+    );
+
+    with_env(
+        &[
+            ("GITHUB_ENTERPRISE_ID", Some("acme")),
+            ("DATADOG_FAILOVER_SITE", Some("us5.datadoghq.com")),
+        ],
+        || match Config::from_env() {
+            Err(ConfigError::Missing(name)) => assert_eq!(name, "DATADOG_FAILOVER_API_KEY"),
+            other => panic!("Expected missing DATADOG_FAILOVER_API_KEY, got {:?}", other),
+        },
+    );
+
+    with_env(
+        &[
+            ("GITHUB_ENTERPRISE_ID", Some("acme")),
+            ("DATADOG_FAILOVER_SITE", Some("us5.datadoghq.com")),
+            ("DATADOG_FAILOVER_API_KEY", Some("dd_us5")),
+            ("DATADOG_FAILOVER_THRESHOLD", Some("0")),
+        ],
+        || {
+            assert!(matches!(
+                Config::from_env(),
+                Err(ConfigError::Invalid(name, _)) if name == "DATADOG_FAILOVER_THRESHOLD"
+            ));
+        },
+    );
+}
+
+#[test]
+fn test_team_mappings_from_env() {
+    with_env(
+        &[
+            ("GITHUB_ENTERPRISE_ID", Some("acme")),
+            (
+                "GITHUB_TEAM_MAPPINGS",
+                Some(
+                    r#"{"platform": {"namespace": "platform.copilot", "tags": ["cost_center:42"]},
+                        "web": {"tags": ["org:frontend"]}}"#,
+                ),
+            ),
+        ],
+        || {
+            let config = Config::from_env().unwrap();
+            assert_eq!(
+                config.team_mappings.get("platform"),
+                Some(&TeamMapping {
+                    namespace: Some("platform.copilot".to_string()),
+                    tags: vec!["cost_center:42".to_string()],
+                    ..TeamMapping::default()
+                })
+            );
+            assert_eq!(config.team_mappings["web"].namespace, None);
+            assert_eq!(config.team_mappings["web"].tags, vec!["org:frontend"]);
+        },
+    );
+}
+
+#[test]
+fn test_team_mapping_rejects_parent_cycles() {
+    with_env(
+        &[
+            ("GITHUB_ENTERPRISE_ID", Some("acme")),
+            (
+                "GITHUB_TEAM_MAPPINGS",
+                Some(r#"{"squad": {"parent": "tribe"}, "tribe": {"parent": "squad"}}"#),
+            ),
+        ],
+        || {
+            assert!(matches!(
+                Config::from_env(),
+                Err(ConfigError::Invalid(_, _))
+            ));
+        },
+    );
+}
+
+#[test]
+fn test_team_mapping_rejects_empty_namespace() {
+    with_env(
+        &[
+            ("GITHUB_ENTERPRISE_ID", Some("acme")),
+            (
+                "GITHUB_TEAM_MAPPINGS",
+                Some(r#"{"web": {"namespace": " "}}"#),
+            ),
+        ],
+        || {
+            assert!(matches!(
+                Config::from_env(),
+                Err(ConfigError::Invalid(_, _))
+            ));
+        },
+    );
+}
+
+/// A self-signed CA certificate
+const TEST_CA_PEM: &str = "-----BEGIN CERTIFICATE-----
+MIIBiTCCAS+gAwIBAgIUbCfUR+M+X30OkFgR3P6SIbZHO/AwCgYIKoZIzj0EAwIw
+GTEXMBUGA1UEAwwOZ2hydXN0IHRlc3QgQ0EwIBcNMjYxMDE2MTQ0NjA4WhgPMjEy
+NjA5MjIxNDQ2MDhaMBkxFzAVBgNVBAMMDmdocnVzdCB0ZXN0IENBMFkwEwYHKoZI
+zj0CAQYIKoZIzj0DAQcDQgAEx3zX9H5ERZTZYgWIl4AyICRy4J/jdLdARbHIA/Dd
+g0bRlHSWCYRi8VDR9n3UawitvARBRZYFZRu9BsGDVYdamKNTMFEwHQYDVR0OBBYE
+FCeXURG4BifSUzPmGdFWgvpR9h5ZMB8GA1UdIwQYMBaAFCeXURG4BifSUzPmGdFW
+gvpR9h5ZMA8GA1UdEwEB/wQFMAMBAf8wCgYIKoZIzj0EAwIDSAAwRQIhAM+zAVqE
+5/Ev+GnWqtntnevNAQpGHrEqU2tY+i99aOnnAiBqTwfyOlkmD2LqM8IZrkDwVAST
+KPbq3PvexNoox4MhTA==
+-----END CERTIFICATE-----
+";
+
+#[test]
+fn test_ca_bundle_from_config_file_overrides_env() {
+    let mut env_bundle = tempfile();
+    write!(env_bundle.1, "{}", TEST_CA_PEM).unwrap();
+    let mut file_bundle = tempfile();
+    write!(file_bundle.1, "{}", TEST_CA_PEM).unwrap();
+
+    with_env(
+        &[
+            ("GITHUB_ENTERPRISE_ID", Some("acme")),
+            ("GHRUST_CA_BUNDLE", Some(env_bundle.0.as_str())),
+        ],
+        || {
+            let config = Config::from_env().unwrap();
+            assert_eq!(config.agent.ca_bundle(), Some(env_bundle.0.as_str()));
+        },
+    );
+
+    let mut file = tempfile();
+    write!(file.1, r#"{{"ca_bundle": "{}"}}"#, file_bundle.0).unwrap();
+    with_env(
+        &[
+            ("GHRUST_CONFIG_FILE", Some(file.0.as_str())),
+            ("GITHUB_ENTERPRISE_ID", Some("acme")),
+            ("GHRUST_CA_BUNDLE", Some(env_bundle.0.as_str())),
+        ],
+        || {
+            let config = Config::from_env().unwrap();
+            assert_eq!(config.agent.ca_bundle(), Some(file_bundle.0.as_str()));
+            // Every client of the configuration trusts the file's bundle
+            assert_eq!(config.github_settings.agent, config.agent);
+            assert_eq!(config.datadog_settings.agent, config.agent);
+        },
+    );
+
+    for path in [&file.0, &env_bundle.0, &file_bundle.0] {
+        std::fs::remove_file(path).ok();
+    }
+}
+
+#[test]
+fn test_http_debug_log_is_read_per_configuration() {
+    with_env(
+        &[
+            ("GITHUB_ENTERPRISE_ID", Some("acme")),
+            ("HTTP_DEBUG_LOG", Some("1")),
+        ],
+        || {
+            let config = Config::from_env().unwrap();
+            assert!(config.agent.debug_log());
+            assert!(config.github_settings.agent.debug_log());
+            assert!(config.datadog_settings.agent.debug_log());
+        },
+    );
+    // A later load without the variable logs nothing
+    with_env(&[("GITHUB_ENTERPRISE_ID", Some("acme"))], || {
+        assert!(!Config::from_env().unwrap().agent.debug_log());
+    });
+}
+
+#[test]
+fn test_unreadable_ca_bundle_is_rejected_before_secrets_are_read() {
+    let mut file = tempfile();
+    write!(file.1, r#"{{"ca_bundle": "/nonexistent/ca.pem"}}"#).unwrap();
+    with_env(
+        &[
+            ("GHRUST_CONFIG_FILE", Some(file.0.as_str())),
+            ("GITHUB_ENTERPRISE_ID", Some("acme")),
+            ("GITHUB_TOKEN", None),
+            // Vault would be asked for the token without the bundle
+            ("VAULT_ADDR", Some("http://127.0.0.1:9")),
+            ("VAULT_SECRET_PATH", Some("secret/data/ghrust")),
+            ("VAULT_TOKEN", Some("s.token")),
+        ],
+        || match Config::from_env() {
+            Err(ConfigError::Invalid(name, _)) => assert_eq!(name, "ca_bundle"),
+            other => panic!("expected an invalid ca_bundle, got {:?}", other.err()),
+        },
+    );
+
+    std::fs::remove_file(&file.0).ok();
+}
+
+#[test]
+fn test_ca_bundle_without_certificates_is_rejected() {
+    let mut file = tempfile();
+    write!(file.1, "not a certificate").unwrap();
+
+    assert!(matches!(
+        tls::load_client_config(&file.0),
+        Err(TlsError::Bundle(_, _))
+    ));
+    assert!(tls::load_client_config("/nonexistent/ca.pem").is_err());
+
+    std::fs::remove_file(&file.0).ok();
+}
+
+#[test]
+fn test_dashboard_provisioning_requires_app_key() {
+    with_env(
+        &[
+            ("GITHUB_ENTERPRISE_ID", Some("acme")),
+            ("PROVISION_DASHBOARDS", Some("1")),
+        ],
+        || {
+            assert!(matches!(
+                Config::from_env(),
+                Err(ConfigError::Missing(var)) if var == "DATADOG_APP_KEY"
+            ));
+        },
+    );
+
+    with_env(
+        &[
+            ("GITHUB_ENTERPRISE_ID", Some("acme")),
+            ("PROVISION_DASHBOARDS", Some("1")),
+            ("DATADOG_APP_KEY", Some("dd_app")),
+        ],
+        || {
+            let config = Config::from_env().unwrap();
+            assert!(config.provision_dashboards);
+            assert_eq!(config.datadog_app_key.as_deref(), Some("dd_app"));
+        },
+    );
+
+    with_env(
+        &[
+            ("GITHUB_ENTERPRISE_ID", Some("acme")),
+            ("UPDATE_METRIC_METADATA", Some("1")),
+        ],
+        || {
+            assert!(matches!(
+                Config::from_env(),
+                Err(ConfigError::Missing(var)) if var == "DATADOG_APP_KEY"
+            ));
+        },
+    );
+}
+
+#[test]
+fn test_alert_rules_from_env() {
+    with_env(
+        &[
+            ("GITHUB_ENTERPRISE_ID", Some("acme")),
+            (
+                "ALERT_RULES",
+                Some(
+                    r#"[{"metric": "active_users", "drop_percent": 20}, {"metric": "acceptance_rate", "below": 15}]"#,
+                ),
+            ),
+            ("ALERT_WEBHOOK_URL", Some("https://hooks.example.com/x")),
+            ("OPSGENIE_API_KEY", Some("og-key")),
+            ("OPSGENIE_API_URL", Some("https://api.eu.opsgenie.com")),
+        ],
+        || {
+            let config = Config::from_env().unwrap();
+            assert_eq!(
+                config.opsgenie,
+                Some(OpsgenieConfig {
+                    api_key: "og-key".to_string(),
+                    api_url: Some("https://api.eu.opsgenie.com".to_string()),
+                })
+            );
+            assert_eq!(
+                config.alert_rules,
+                vec![
+                    AlertRule {
+                        metric: AlertMetric::ActiveUsers,
+                        drop_percent: Some(20.0),
+                        below: None,
+                    },
+                    AlertRule {
+                        metric: AlertMetric::AcceptanceRate,
+                        drop_percent: None,
+                        below: Some(15.0),
+                    },
+                ]
+            );
+            assert_eq!(
+                config.alert_webhook_url.as_deref(),
+                Some("https://hooks.example.com/x")
+            );
+        },
+    );
+
+    for rules in [
+        r#"[{"metric": "active_users"}]"#,
+        r#"[{"metric": "active_users", "drop_percent": 20, "below": 5}]"#,
+        r#"[{"metric": "active_users", "below": -1}]"#,
+        r#"[{"metric": "daily_mood", "below": 1}]"#,
+    ] {
+        with_env(
+            &[
+                ("GITHUB_ENTERPRISE_ID", Some("acme")),
+                ("ALERT_RULES", Some(rules)),
+            ],
+            || {
+                assert!(
+                    matches!(Config::from_env(), Err(ConfigError::Invalid(_, _))),
+                    "{} should be rejected",
+                    rules
+                );
+            },
+        );
+    }
+}
+
+#[test]
+fn test_anomaly_sigma_from_env() {
+    with_env(&[("GITHUB_ENTERPRISE_ID", Some("acme"))], || {
+        assert_eq!(Config::from_env().unwrap().anomaly_sigma, None);
+    });
+
+    with_env(
+        &[
+            ("GITHUB_ENTERPRISE_ID", Some("acme")),
+            ("ANOMALY_SIGMA", Some("2.5")),
+        ],
+        || {
+            assert_eq!(Config::from_env().unwrap().anomaly_sigma, Some(2.5));
+        },
+    );
+
+    with_env(
+        &[
+            ("GITHUB_ENTERPRISE_ID", Some("acme")),
+            ("ANOMALY_SIGMA", Some("0")),
+        ],
+        || {
+            assert!(matches!(
+                Config::from_env(),
+                Err(ConfigError::Invalid(_, _))
+            ));
+        },
+    );
+}
+
+#[test]
+fn test_leaderboard_from_env() {
+    with_env(&[("GITHUB_ENTERPRISE_ID", Some("acme"))], || {
+        assert_eq!(Config::from_env().unwrap().leaderboard, None);
+    });
+
+    with_env(
+        &[
+            ("GITHUB_ENTERPRISE_ID", Some("acme")),
+            ("LEADERBOARD_WEEKDAY", Some("Fri")),
+            ("LEADERBOARD_OUTPUT", Some("s3://reports/leaderboard.md")),
+        ],
+        || {
+            let leaderboard = Config::from_env().unwrap().leaderboard.unwrap();
+            assert_eq!(leaderboard.weekday, chrono::Weekday::Fri);
+            assert_eq!(
+                leaderboard.output.as_deref(),
+                Some("s3://reports/leaderboard.md")
+            );
+        },
+    );
+
+    with_env(
+        &[
+            ("GITHUB_ENTERPRISE_ID", Some("acme")),
+            ("LEADERBOARD_WEEKDAY", Some("someday")),
+        ],
+        || {
+            assert!(matches!(
+                Config::from_env(),
+                Err(ConfigError::Invalid(_, _))
+            ));
+        },
+    );
+}
+
+#[test]
+fn test_export_sinks_from_env() {
+    with_env(
+        &[
+            ("GITHUB_ENTERPRISE_ID", Some("acme")),
+            (
+                "EXPORT_SINKS",
+                Some(
+                    r#"[{"type": "google_sheets", "spreadsheet_id": "1AbC",
+                         "sheet": "Adoption", "credentials_file": "/opt/sa.json"}]"#,
+                ),
+            ),
+        ],
+        || {
+            let config = Config::from_env().unwrap();
+            assert_eq!(
+                config.exports,
+                vec![ExportConfig::GoogleSheets {
+                    spreadsheet_id: "1AbC".to_string(),
+                    sheet: Some("Adoption".to_string()),
+                    credentials_file: "/opt/sa.json".to_string(),
+                }]
+            );
+        },
+    );
+
+    with_env(
+        &[
+            ("GITHUB_ENTERPRISE_ID", Some("acme")),
+            ("EXPORT_SINKS", Some(r#"[{"type": "google_sheets"}]"#)),
+        ],
+        || {
+            assert!(matches!(
+                Config::from_env(),
+                Err(ConfigError::Invalid(_, _))
+            ));
+        },
+    );
+}
+
+#[test]
+fn test_fail_on_error_settings() {
+    with_env(&[("GITHUB_ENTERPRISE_ID", Some("acme"))], || {
+        let config = Config::from_env().unwrap();
+        assert!(!config.fail_on_error);
+        assert_eq!(config.team_failure_threshold, 0.0);
+    });
+
+    with_env(
+        &[
+            ("GITHUB_ENTERPRISE_ID", Some("acme")),
+            ("FAIL_ON_ERROR", Some("1")),
+            ("TEAM_FAILURE_THRESHOLD", Some("0.25")),
+        ],
+        || {
+            let config = Config::from_env().unwrap();
+            assert!(config.fail_on_error);
+            assert_eq!(config.team_failure_threshold, 0.25);
+        },
+    );
+
+    with_env(
+        &[
+            ("GITHUB_ENTERPRISE_ID", Some("acme")),
+            ("TEAM_FAILURE_THRESHOLD", Some("25%")),
+        ],
+        || {
+            assert!(matches!(
+                Config::from_env(),
+                Err(ConfigError::Invalid(_, _))
+            ));
+        },
+    );
+}
+
+#[test]
+fn test_seat_price_from_env() {
+    with_env(&[("GITHUB_ENTERPRISE_ID", Some("acme"))], || {
+        assert_eq!(Config::from_env().unwrap().seat_price, None);
+    });
+
+    with_env(
+        &[
+            ("GITHUB_ENTERPRISE_ID", Some("acme")),
+            ("COPILOT_SEAT_PRICE", Some("39")),
+        ],
+        || {
+            assert_eq!(Config::from_env().unwrap().seat_price, Some(39.0));
+        },
+    );
+
+    with_env(
+        &[
+            ("GITHUB_ENTERPRISE_ID", Some("acme")),
+            ("COPILOT_SEAT_PRICE", Some("$19")),
+        ],
+        || {
+            assert!(matches!(
+                Config::from_env(),
+                Err(ConfigError::Invalid(_, _))
+            ));
+        },
+    );
+}
+
+#[test]
+fn test_team_discovery_from_env() {
+    use super::TeamDiscoveryConfig;
+    use std::time::Duration;
+
+    with_env(&[("GITHUB_ENTERPRISE_ID", Some("acme"))], || {
+        assert_eq!(Config::from_env().unwrap().team_discovery, None);
+    });
+
+    with_env(
+        &[
+            ("GITHUB_ENTERPRISE_ID", Some("acme")),
+            ("GITHUB_DISCOVER_TEAMS", Some("1")),
+        ],
+        || {
+            assert_eq!(
+                Config::from_env().unwrap().team_discovery,
+                Some(TeamDiscoveryConfig {
+                    cache_path: None,
+                    cache_ttl: Duration::from_secs(24 * 3600),
+                })
+            );
+        },
+    );
+
+    with_env(
+        &[
+            ("GITHUB_ENTERPRISE_ID", Some("acme")),
+            ("GITHUB_DISCOVER_TEAMS", Some("1")),
+            ("GITHUB_TEAM_CACHE", Some("/tmp/teams.json")),
+            ("GITHUB_TEAM_CACHE_TTL_HOURS", Some("6")),
+        ],
+        || {
+            let discovery = Config::from_env().unwrap().team_discovery.unwrap();
+            assert_eq!(discovery.cache_path.as_deref(), Some("/tmp/teams.json"));
+            assert_eq!(discovery.cache_ttl, Duration::from_secs(6 * 3600));
+        },
+    );
+
+    with_env(
+        &[
+            ("GITHUB_ENTERPRISE_ID", Some("acme")),
+            ("GITHUB_DISCOVER_TEAMS", Some("1")),
+            ("GITHUB_TEAM_CACHE_TTL_HOURS", Some("a day")),
+        ],
+        || {
+            assert!(matches!(
+                Config::from_env(),
+                Err(ConfigError::Invalid(_, _))
+            ));
+        },
+    );
+}
+
+#[test]
+fn test_digest_settings_from_env() {
+    with_env(&[("GITHUB_ENTERPRISE_ID", Some("acme"))], || {
+        assert_eq!(Config::from_env().unwrap().digest, None);
+    });
+
+    with_env(
+        &[
+            ("GITHUB_ENTERPRISE_ID", Some("acme")),
+            ("DIGEST_SENDER", Some("copilot@example.com")),
+            ("DIGEST_RECIPIENTS", Some("a@example.com, b@example.com")),
+            ("DIGEST_WEEKDAY", Some("friday")),
+        ],
+        || {
+            assert_eq!(
+                Config::from_env().unwrap().digest,
+                Some(DigestConfig {
+                    sender: "copilot@example.com".to_string(),
+                    recipients: vec!["a@example.com".to_string(), "b@example.com".to_string()],
+                    weekday: chrono::Weekday::Fri,
+                })
+            );
+        },
+    );
+
+    with_env(
+        &[
+            ("GITHUB_ENTERPRISE_ID", Some("acme")),
+            ("DIGEST_RECIPIENTS", Some("a@example.com")),
+        ],
+        || {
+            assert!(matches!(
+                Config::from_env(),
+                Err(ConfigError::Invalid(_, _))
+            ));
+        },
+    );
+}
+
+#[test]
+fn test_invalid_namespace_template_rejected() {
+    with_env(
+        &[
+            ("GITHUB_ENTERPRISE_ID", Some("acme")),
+            ("DATADOG_NAMESPACE_TEMPLATE", Some("{base}.{team}")),
+        ],
+        || {
+            assert!(matches!(
+                Config::from_env(),
+                Err(ConfigError::Invalid(name, _)) if name == "DATADOG_NAMESPACE_TEMPLATE"
+            ));
+        },
+    );
+}
+
+#[test]
+fn test_tag_templates_rejected_when_invalid_or_revealing_hashed_teams() {
+    with_env(
+        &[
+            ("GITHUB_ENTERPRISE_ID", Some("acme")),
+            ("DATADOG_TAG_TEMPLATES", Some("enterprise:{enterprise}")),
+        ],
+        || {
+            assert!(matches!(
+                Config::from_env(),
+                Err(ConfigError::Invalid(name, _)) if name == "DATADOG_TAG_TEMPLATES"
+            ));
+        },
+    );
+    with_env(
+        &[
+            ("GITHUB_ENTERPRISE_ID", Some("acme")),
+            ("DATADOG_TAG_TEMPLATES", Some("team:{team_slug}")),
+            ("TEAM_HASH_SALT", Some("salt")),
+        ],
+        || {
+            assert!(matches!(
+                Config::from_env(),
+                Err(ConfigError::Invalid(name, _)) if name == "DATADOG_TAG_TEMPLATES"
+            ));
+        },
+    );
+}
+
+#[test]
+fn test_datadog_settings_kept_in_config() {
+    with_env(
+        &[
+            ("GITHUB_ENTERPRISE_ID", Some("acme")),
+            ("DATADOG_SEND_CONCURRENCY", Some("8")),
+            ("DATADOG_NAMESPACE_TEMPLATE", Some("{base}.{scope}")),
+            (
+                "DATADOG_TAG_TEMPLATES",
+                Some("enterprise:{enterprise_id},env:{env}"),
+            ),
+            ("DD_ENV", Some("prod")),
+        ],
+        || {
+            let config = Config::from_env().unwrap();
+            assert_eq!(config.datadog_settings.concurrency, 8);
+            assert_eq!(
+                config
+                    .datadog_settings
+                    .tag_templates
+                    .resolve(&TagContext::enterprise("acme")),
+                vec!["enterprise:acme", "env:prod"]
+            );
+            assert_eq!(
+                config.namespace_template,
+                NamespaceTemplate::parse("{base}.{scope}").unwrap()
+            );
+        },
+    );
+}
+
+#[test]
+fn test_github_settings_kept_in_config() {
+    with_env(
+        &[
+            ("GITHUB_ENTERPRISE_ID", Some("acme")),
+            ("GITHUB_USER_AGENT", Some("acme-gateway/2.0")),
+            ("GITHUB_MAX_RETRIES", Some("5")),
+            ("GITHUB_PENDING_RETRY_BUDGET", Some("1")),
+            ("GITHUB_METRICS_SOURCE", Some("usage")),
+        ],
+        || {
+            let config = Config::from_env().unwrap();
+            let settings = &config.github_settings;
+            assert_eq!(settings.user_agent, "acme-gateway/2.0");
+            assert_eq!(settings.retry_policy.max_retries, 5);
+            assert_eq!(settings.pending_policy.max_total_retries, 1);
+            assert_eq!(settings.metrics_source, MetricsSource::Usage);
+        },
+    );
+}
+
+#[test]
+fn test_invalid_send_concurrency_rejected() {
+    with_env(
+        &[
+            ("GITHUB_ENTERPRISE_ID", Some("acme")),
+            ("DATADOG_SEND_CONCURRENCY", Some("0")),
+        ],
+        || {
+            assert!(matches!(
+                Config::from_env(),
+                Err(ConfigError::Invalid(name, _)) if name == "DATADOG_SEND_CONCURRENCY"
+            ));
+        },
+    );
+}
+
+#[test]
+fn test_invalid_client_settings_rejected() {
+    for (name, value) in [
+        ("TOP_N_BREAKDOWNS", "ten"),
+        ("MIN_LANGUAGE_ENGAGED_USERS", "-5"),
+        ("DATADOG_MAX_CHUNKS_PER_SECOND", "x"),
+        ("DATADOG_MAX_RATE_LIMIT_WAIT_SECS", "0"),
+        ("LANGUAGE_ALIASES", "hcl=terraform,svelte"),
+        ("GITHUB_MAX_RETRIES", "-1"),
+        ("GITHUB_PENDING_DELAY_SECS", "soon"),
+    ] {
+        with_env(
+            &[("GITHUB_ENTERPRISE_ID", Some("acme")), (name, Some(value))],
+            || match Config::from_env() {
+                Err(ConfigError::Invalid(invalid, _)) => assert_eq!(invalid, name),
+                other => panic!("{}={} was not rejected: {:?}", name, value, other.err()),
+            },
+        );
+    }
+
+    // Valid values are parsed into the settings
+    with_env(
+        &[
+            ("GITHUB_ENTERPRISE_ID", Some("acme")),
+            ("TOP_N_BREAKDOWNS", Some("10")),
+            ("DATADOG_MAX_CHUNKS_PER_SECOND", Some("2.5")),
+            ("LANGUAGE_ALIASES", Some("hcl=terraform")),
+            ("GITHUB_PENDING_DELAY_SECS", Some("0")),
+        ],
+        || {
+            let config = Config::from_env().unwrap();
+            let settings = &config.datadog_settings;
+            assert_eq!(
+                settings.breakdown_limits,
+                BreakdownLimits::new(None).with_top_n(Some(10))
+            );
+            assert_eq!(settings.throttle, Throttle::new(Some(2.5), None));
+            assert_eq!(settings.language_normalizer.normalize("hcl"), "terraform");
+            assert_eq!(
+                config.github_settings.pending_policy.delay,
+                std::time::Duration::ZERO
+            );
+        },
+    );
+}
+
+#[test]
+fn test_schema_mode_read_once() {
+    with_env(
+        &[
+            ("GITHUB_ENTERPRISE_ID", Some("acme")),
+            ("STRICT_SCHEMA", Some("warn")),
+        ],
+        || {
+            let config = Config::from_env().unwrap();
+            assert_eq!(config.github_settings.schema_mode, SchemaMode::Warn);
+            assert!(config.datadog_settings.report_schema_drift);
+        },
+    );
+}
+
+#[test]
+fn test_invalid_schema_mode_rejected() {
+    with_env(
+        &[
+            ("GITHUB_ENTERPRISE_ID", Some("acme")),
+            ("STRICT_SCHEMA", Some("warning")),
+        ],
+        || {
+            assert!(matches!(
+                Config::from_env(),
+                Err(ConfigError::Invalid(name, _)) if name == "STRICT_SCHEMA"
+            ));
+        },
+    );
+}
+
+#[test]
+fn test_invalid_config_file() {
+    assert!(matches!(
+        ConfigFile::parse("{not json"),
+        Err(ConfigError::File(_, _))
+    ));
+}
+
+/// Creates a uniquely named temporary file, returning its path and handle
+fn tempfile() -> (String, std::fs::File) {
+    let path = std::env::temp_dir().join(format!(
+        "ghrust-config-{}-{}.json",
+        std::process::id(),
+        std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap()
+            .as_nanos()
+    ));
+    let file = std::fs::File::create(&path).unwrap();
+    (path.to_string_lossy().into_owned(), file)
+}
+
+#[test]
+fn test_validate_reports_invalid_slugs_and_namespaces() {
+    use super::validate::validate;
+
+    with_env(
+        &[
+            ("GITHUB_ENTERPRISE_IDS", Some("acme,Acme Corp")),
+            ("GITHUB_TEAM_SLUGS", Some("platform,Web Team")),
+            ("DATADOG_METRIC_NAMESPACE", Some("github..copilot")),
+        ],
+        || {
+            let config = Config::from_env().unwrap();
+            let validation = validate(&config, &[], None);
+            assert!(!validation.is_ok());
+            let errors = validation.errors.join("\n");
+            assert!(errors.contains("\"Acme Corp\" is not an enterprise slug"));
+            assert!(errors.contains("\"Web Team\" is not a team slug"));
+            assert!(errors.contains("DATADOG_METRIC_NAMESPACE \"github..copilot\""));
+            assert!(!errors.contains("\"platform\""));
+        },
+    );
+
+    with_env(
+        &[
+            ("GITHUB_ENTERPRISE_ID", Some("acme")),
+            ("GITHUB_TEAM_SLUGS", Some("platform,data_eng")),
+        ],
+        || {
+            let config = Config::from_env().unwrap();
+            assert_eq!(validate(&config, &[], None), Default::default());
+        },
+    );
+}
+
+#[test]
+fn test_validate_requires_store_for_weekly_deliveries() {
+    use super::validate::validate;
+
+    let weekly = [
+        ("GITHUB_ENTERPRISE_ID", Some("acme")),
+        ("DIGEST_SENDER", Some("copilot@acme.com")),
+        ("DIGEST_RECIPIENTS", Some("cto@acme.com")),
+        ("LEADERBOARD_WEEKDAY", Some("Fri")),
+    ];
+    with_env(&weekly, || {
+        let config = Config::from_env().unwrap();
+        let errors = validate(&config, &[], None).errors.join("\n");
+        assert!(
+            errors.contains("weekly digest needs a metrics store"),
+            "{}",
+            errors
+        );
+        assert!(
+            errors.contains("LEADERBOARD_WEEKDAY needs a metrics store"),
+            "{}",
+            errors
+        );
+    });
+
+    let with_store = [
+        &weekly[..],
+        &[("METRICS_STORE_PATH", Some("/tmp/metrics.db"))],
+    ]
+    .concat();
+    with_env(&with_store, || {
+        let config = Config::from_env().unwrap();
+        assert!(validate(&config, &[], None).is_ok());
+    });
+}
+
+#[test]
+fn test_validate_warns_about_typos() {
+    use super::validate::validate;
+
+    with_env(&[("GITHUB_ENTERPRISE_ID", Some("acme"))], || {
+        let config = Config::from_env().unwrap();
+        let variables: Vec<String> = [
+            "GITHUB_TOKEN",
+            "GITHUB_TEAM_SLUG",
+            "DATADOG_STIE",
+            "GHRUST_VERBOSE",
+            "PATH",
+            "GITHUB_SHA",
+        ]
+        .iter()
+        .map(|name| name.to_string())
+        .collect();
+        let file = serde_json::json!({
+            "enterprise": [{ "id": "acme" }],
+            "team_mappings": { "platform": { "namepsace": "platform.copilot" } },
+            "digest": { "recipients": [], "weekday": "Mon" }
+        });
+
+        let validation = validate(&config, &variables, Some(&file));
+        assert!(validation.is_ok());
+        assert_eq!(
+            validation.warnings[..5],
+            [
+                "Unknown environment variable GITHUB_TEAM_SLUG (did you mean GITHUB_TEAM_SLUGS?)",
+                "Unknown environment variable DATADOG_STIE (did you mean DATADOG_SITE?)",
+                "Unknown environment variable GHRUST_VERBOSE",
+                "Unknown key enterprise in the configuration file, ignored (did you mean enterprises?)",
+                "Unknown key namepsace in team_mappings[platform], ignored (did you mean namespace?)",
+            ]
+        );
+        assert_eq!(validation.warnings.len(), 5);
+    });
+}
+
+#[test]
+fn test_summary_masks_secrets() {
+    use super::validate::{mask, summary};
+
+    with_env(
+        &[
+            ("GITHUB_TOKEN", Some("ghp_abcdefghijkl1234")),
+            ("GITHUB_ENTERPRISE_ID", Some("acme")),
+            (
+                "ALERT_WEBHOOK_URL",
+                Some("https://hooks.slack.com/services/T000/B000/secret"),
+            ),
+        ],
+        || {
+            let config = Config::from_env().unwrap();
+            let summary = summary(&config);
+            assert!(summary.contains("GitHub token: ****1234"));
+            assert!(!summary.contains("ghp_abcdefghijkl1234"));
+            assert!(summary.contains("API key ****"));
+            assert!(!summary.contains("dd_test"));
+            assert!(summary.contains("Alert webhook: https://hooks.slack.com/****"));
+            assert!(summary.contains("Enterprise acme: namespace github.copilot"));
+        },
+    );
+    assert_eq!(mask("short"), "****");
+}
+
+/// Serves `requests` connections, answering each with the response of the
+/// first route whose path prefix matches the request path
+fn mock_services(
+    routes: Vec<(&'static str, MockResponse)>,
+    requests: usize,
+) -> (String, std::thread::JoinHandle<Vec<MockRequest>>) {
+    mock_server::serve_with(requests, move |_, request| {
+        routes
+            .iter()
+            .find(|(prefix, _)| request.path.starts_with(prefix))
+            .map_or_else(
+                || MockResponse::new("404 Not Found", "[]"),
+                |(_, response)| response.clone(),
+            )
+    })
+}
+
+#[test]
+fn test_doctor_reports_fixes() {
+    use super::doctor::{run_with_client, CheckStatus};
+    use crate::services::github::GitHubClient;
+
+    // A token without a metrics scope skips the endpoint checks
+    let (base_url, server) = mock_services(
+        vec![
+            (
+                "/user",
+                MockResponse::new("200 OK", "[]").with_header("X-OAuth-Scopes", "repo, read:org"),
+            ),
+            ("/api/v1/validate", MockResponse::new("403 Forbidden", "[]")),
+        ],
+        2,
+    );
+    with_env(
+        &[
+            ("GITHUB_ENTERPRISE_ID", Some("acme")),
+            ("DATADOG_SITE", Some(&base_url)),
+        ],
+        || {
+            let config = Config::from_env().unwrap();
+            let github = GitHubClient::builder()
+                .token("ghp_test")
+                .base_url(&base_url)
+                .build()
+                .unwrap();
+            let report = run_with_client(&config, &github);
+            assert_eq!(report.failures(), 2);
+            assert_eq!(report.checks.len(), 2);
+            assert!(report.checks[0].detail.contains("scopes: repo, read:org"));
+            assert!(report.checks[0]
+                .fix
+                .as_deref()
+                .unwrap()
+                .contains("manage_billing:copilot"));
+            assert!(report.checks[1]
+                .fix
+                .as_deref()
+                .unwrap()
+                .contains("API key was rejected"));
+        },
+    );
+    let paths: Vec<String> = server
+        .join()
+        .unwrap()
+        .into_iter()
+        .map(|request| request.path)
+        .collect();
+    assert_eq!(paths, vec!["/user", "/api/v1/validate"]);
+
+    // With a metrics scope, every enterprise and team endpoint is checked; an
+    // enterprise ID that can't be resolved is only a warning
+    let (base_url, server) = mock_services(
+        vec![
+            (
+                "/user",
+                MockResponse::new("200 OK", "[]")
+                    .with_header("X-OAuth-Scopes", "manage_billing:copilot"),
+            ),
+            (
+                "/enterprises/acme/copilot/metrics",
+                MockResponse::new("200 OK", "[]"),
+            ),
+            ("/api/v1/validate", MockResponse::new("200 OK", "[]")),
+        ],
+        // The unknown team is looked up in the organization as well
+        6,
+    );
+    with_env(
+        &[
+            ("GITHUB_ENTERPRISE_ID", Some("acme")),
+            ("GITHUB_TEAM_SLUGS", Some("platform")),
+            ("DATADOG_SITE", Some(&base_url)),
+        ],
+        || {
+            let config = Config::from_env().unwrap();
+            let github = GitHubClient::builder()
+                .token("ghp_test")
+                .base_url(&base_url)
+                .build()
+                .unwrap();
+            let report = run_with_client(&config, &github);
+            let statuses: Vec<_> = report.checks.iter().map(|c| c.status).collect();
+            assert_eq!(
+                statuses,
+                vec![
+                    CheckStatus::Pass,
+                    CheckStatus::Warn,
+                    CheckStatus::Pass,
+                    CheckStatus::Fail,
+                    CheckStatus::Pass
+                ]
+            );
+            assert_eq!(report.checks[1].name, "Enterprise ID (acme)");
+            assert_eq!(report.checks[3].name, "Team metrics (acme/platform)");
+            assert!(report.checks[3]
+                .fix
+                .as_deref()
+                .unwrap()
+                .contains("GITHUB_TEAM_SLUGS"));
+            assert!(report.to_string().contains("[FAIL] Team metrics"));
+        },
+    );
+    server.join().unwrap();
+}
+
+#[test]
+fn test_doctor_classifies_network_errors() {
+    use super::doctor::network_fix;
+
+    temp_env::with_vars(
+        [
+            ("HTTPS_PROXY", None::<&str>),
+            ("https_proxy", None),
+            ("ALL_PROXY", None),
+            ("all_proxy", None),
+        ],
+        || {
+            let fix = network_fix("invalid peer certificate: UnknownIssuer", "api.github.com");
+            assert!(fix.contains("GHRUST_CA_BUNDLE"));
+            let fix = network_fix("Dns Failed: resolve dns name", "api.github.com");
+            assert!(fix.starts_with("api.github.com could not be resolved"));
+            let fix = network_fix("Connection Failed: Connect error", "datadoghq.eu");
+            assert!(fix.starts_with("datadoghq.eu could not be reached"));
+            assert!(!fix.contains("HTTPS_PROXY"));
+        },
+    );
+    temp_env::with_var("HTTPS_PROXY", Some("http://proxy:3128"), || {
+        let fix = network_fix("Connection Failed: Connect error", "datadoghq.eu");
+        assert!(fix.contains("HTTPS_PROXY is set"));
+    });
+}
+
+#[test]
+fn test_log_filter_directives() {
+    use super::logging::log_filter;
+
+    let filter = |rust_log: Option<&str>, log_level: Option<&str>| {
+        temp_env::with_vars([("RUST_LOG", rust_log), ("LOG_LEVEL", log_level)], || {
+            log_filter().to_string()
+        })
+    };
+    assert_eq!(filter(None, None), "info");
+    assert_eq!(filter(None, Some("debug")), "debug");
+    assert_eq!(
+        filter(Some("info,ghrust::services::github=debug"), Some("warn")),
+        "ghrust::services::github=debug,info"
+    );
+    assert_eq!(filter(Some(" "), Some("warn")), "warn");
+    assert_eq!(filter(Some("info,=[{"), None), "info");
+}
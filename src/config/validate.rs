@@ -0,0 +1,592 @@
+//! # Configuration Validation
+//!
+//! Checks of a loaded configuration that loading itself doesn't enforce, for
+//! `ghrust validate`. Loading fails on missing required settings and values
+//! that can't be parsed (numbers, weekdays, JSON); validation additionally
+//! catches settings that load fine but break at runtime or are silently
+//! ignored:
+//!
+//! * Namespaces that aren't valid Datadog metric name prefixes
+//! * Enterprise IDs and team slugs GitHub can't resolve
+//! * Environment variables and configuration file keys that look like typos
+//!   of known ones
+//!
+//! `summary` renders the resolved configuration with secrets masked.
+
+use std::collections::BTreeSet;
+use std::env;
+use std::fmt::Write;
+use std::fs;
+
+use serde_json::Value;
+
+use super::Config;
+
+/// Environment variables read by the collector and its binaries
+pub const KNOWN_VARIABLES: &[&str] = &[
+    "ALERT_RULES",
+    "ALERT_WEBHOOK_URL",
+    "ANOMALY_SIGMA",
+    "BASELINE_S3_BUCKET",
+    "BASELINE_S3_KEY",
+    "COLLECTOR_RUN_ID_TAG",
+    "COLLECT_ORG_METRICS",
+    "COLLECT_PREMIUM_REQUESTS",
+    "COLLECT_SEAT_METRICS",
+    "COPILOT_SEAT_PRICE",
+    "DATADOG_API_KEY",
+    "DATADOG_API_KEY_FILE",
+    "DATADOG_API_KEY_SECONDARY",
+    "DATADOG_API_KEY_SECONDARY_FILE",
+    "DATADOG_APP_KEY",
+    "DATADOG_BATCH_SEND",
+    "DATADOG_DESTINATIONS",
+    "DATADOG_FAILOVER_API_KEY",
+    "DATADOG_FAILOVER_API_KEY_FILE",
+    "DATADOG_FAILOVER_SITE",
+    "DATADOG_FAILOVER_THRESHOLD",
+    "DATADOG_MAX_CHUNKS_PER_SECOND",
+    "DATADOG_MAX_POINTS_PER_SECOND",
+    "DATADOG_MAX_RATE_LIMIT_WAIT_SECS",
+    "DATADOG_METRIC_NAMESPACE",
+    "DATADOG_NAMESPACE_P7S1",
+    "DATADOG_NAMESPACE_TEMPLATE",
+    "DATADOG_REPOSITORY_ALLOWLIST",
+    "DATADOG_REPOSITORY_DENYLIST",
+    "DATADOG_REPOSITORY_HASH_SALT",
+    "DATADOG_SEND_CONCURRENCY",
+    "DATADOG_SITE",
+    "DATADOG_TAG_ALLOWLIST",
+    "DATADOG_TAG_DENYLIST",
+    "DATADOG_TAG_TEMPLATES",
+    "DD_ENV",
+    "DIGEST_RECIPIENTS",
+    "DIGEST_SENDER",
+    "DIGEST_WEEKDAY",
+    "EXPORT_SINKS",
+    "FAIL_ON_ERROR",
+    "GHRUST_CA_BUNDLE",
+    "GHRUST_CONFIG_FILE",
+    "GHRUST_GRPC_ADDR",
+    "GHRUST_SERVER_ADDR",
+    "GITHUB_API_VERSION",
+    "GITHUB_DISCOVER_TEAMS",
+    "GITHUB_ENTERPRISE_ID",
+    "GITHUB_ENTERPRISE_IDS",
+    "GITHUB_ETAG_CACHE",
+    "GITHUB_MAX_RETRIES",
+    "GITHUB_METRICS_SOURCE",
+    "GITHUB_PENDING_DELAY_SECS",
+    "GITHUB_PENDING_RETRIES",
+    "GITHUB_PENDING_RETRY_BUDGET",
+    "GITHUB_RAW_ARCHIVE",
+    "GITHUB_RETRY_BACKOFF_MS",
+    "GITHUB_TEAM_CACHE",
+    "GITHUB_TEAM_CACHE_TTL_HOURS",
+    "GITHUB_TEAM_MAPPINGS",
+    "GITHUB_TEAM_SLUGS",
+    "GITHUB_TOKEN",
+    "GITHUB_TOKEN_FILE",
+    "GITHUB_USER_AGENT",
+    "HEALTHCHECK_URL",
+    "HTTP_DEBUG_LOG",
+    "LANGUAGE_ALIASES",
+    "LANGUAGE_RAW_TAG",
+    "LEADERBOARD_OUTPUT",
+    "LEADERBOARD_WEEKDAY",
+    "LOG_LEVEL",
+    "METRICS_STORE_PATH",
+    "METRICS_STORE_REUSE",
+    "MIN_LANGUAGE_ENGAGED_USERS",
+    "MOCK_GITHUB_API",
+    "MONTHLY_ROLLUPS",
+    "OPSGENIE_API_KEY",
+    "OPSGENIE_API_URL",
+    "PROVISION_DASHBOARDS",
+    "REPORT_SEAT_ACTIVITY",
+    "REPORT_UNKNOWN_FIELDS",
+    "REPORT_UNUSED_SEAT_USERS",
+    "RUST_LOG",
+    "SEND_LATEST_DAY_ONLY",
+    "SKIP_DATADOG_TESTS",
+    "SKIP_ENTERPRISE_METRICS",
+    "STRICT_SCHEMA",
+    "TEAM_DISTRIBUTION",
+    "TEAM_FAILURE_THRESHOLD",
+    "TEAM_HASH_MAPPING_FILE",
+    "TEAM_HASH_SALT",
+    "TEAM_ROLLUP",
+    "TOP_N_BREAKDOWNS",
+    "UPDATE_METRIC_METADATA",
+    "VAULT_ADDR",
+    "VAULT_NAMESPACE",
+    "VAULT_ROLE_ID",
+    "VAULT_SECRET_ID",
+    "VAULT_SECRET_PATH",
+    "VAULT_TOKEN",
+    "WEEKLY_ROLLUPS",
+];
+
+/// Keys of the configuration file and of its entries, by section
+const FILE_KEYS: &[(&str, &[&str])] = &[
+    (
+        "",
+        &[
+            "enterprises",
+            "datadog_destinations",
+            "team_mappings",
+            "ca_bundle",
+            "digest",
+            "store_path",
+            "alert_rules",
+            "language_groups",
+            "exports",
+        ],
+    ),
+    (
+        "enterprises",
+        &[
+            "id",
+            "namespace",
+            "team_slugs",
+            "github_token",
+            "github_token_env",
+            "datadog_api_key",
+            "datadog_api_key_env",
+            "datadog_app_key",
+            "datadog_app_key_env",
+            "datadog_site",
+        ],
+    ),
+    (
+        "datadog_destinations",
+        &[
+            "name",
+            "site",
+            "api_key",
+            "api_key_env",
+            "secondary_api_key_env",
+            "namespace",
+        ],
+    ),
+    ("team_mappings", &["namespace", "tags", "parent", "org"]),
+    ("digest", &["sender", "recipients", "weekday"]),
+];
+
+/// Prefix of the variables only this collector reads; unknown ones are
+/// always reported
+const OWN_PREFIX: &str = "GHRUST_";
+
+/// Edit distance up to which an unknown name is reported as a typo
+const MAX_TYPO_DISTANCE: usize = 2;
+
+/// Outcome of validating a configuration
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct Validation {
+    /// Problems that break runs
+    pub errors: Vec<String>,
+    /// Settings that are likely mistakes but don't break runs
+    pub warnings: Vec<String>,
+}
+
+impl Validation {
+    /// Returns whether no errors were found
+    pub fn is_ok(&self) -> bool {
+        self.errors.is_empty()
+    }
+}
+
+/// Validates a configuration against the current environment
+///
+/// Reads the environment's variable names and, if `GHRUST_CONFIG_FILE` is
+/// set, the configuration file's keys.
+pub fn validate_env(config: &Config) -> Validation {
+    let variables: Vec<String> = env::vars_os()
+        .filter_map(|(name, _)| name.into_string().ok())
+        .collect();
+    let file = env::var("GHRUST_CONFIG_FILE")
+        .ok()
+        .and_then(|path| fs::read_to_string(path).ok())
+        .and_then(|contents| serde_json::from_str::<Value>(&contents).ok());
+    validate(config, &variables, file.as_ref())
+}
+
+/// Validates a configuration
+///
+/// # Arguments
+///
+/// * `config` - The loaded configuration
+/// * `variables` - Names of the environment variables that are set
+/// * `file` - Contents of the configuration file, if one is used
+///
+/// # Returns
+///
+/// * `Validation` - The errors and warnings found
+pub fn validate(config: &Config, variables: &[String], file: Option<&Value>) -> Validation {
+    let mut validation = Validation::default();
+    let errors = &mut validation.errors;
+
+    check_namespace(
+        errors,
+        "DATADOG_METRIC_NAMESPACE",
+        &config.datadog_namespace,
+    );
+    for destination in &config.datadog_destinations {
+        if let Some(namespace) = &destination.namespace {
+            let field = format!("datadog_destinations[{}].namespace", destination.name);
+            check_namespace(errors, &field, namespace);
+        }
+    }
+    for enterprise in &config.enterprises {
+        if !is_slug(&enterprise.id, false) {
+            errors.push(format!(
+                "Enterprise ID {:?} is not an enterprise slug (lowercase letters, digits \
+                 and hyphens, as in github.com/enterprises/<slug>)",
+                enterprise.id
+            ));
+        }
+        let field = format!("enterprises[{}].namespace", enterprise.id);
+        check_namespace(errors, &field, &enterprise.namespace);
+        for slug in &enterprise.team_slugs {
+            check_team_slug(errors, &format!("team slug of {}", enterprise.id), slug);
+        }
+    }
+    let mut mapped: Vec<&String> = config.team_mappings.keys().collect();
+    mapped.sort();
+    for slug in mapped {
+        let mapping = &config.team_mappings[slug];
+        check_team_slug(errors, "team_mappings key", slug);
+        if let Some(namespace) = &mapping.namespace {
+            check_namespace(
+                errors,
+                &format!("team_mappings[{}].namespace", slug),
+                namespace,
+            );
+        }
+        if let Some(parent) = &mapping.parent {
+            check_team_slug(errors, &format!("team_mappings[{}].parent", slug), parent);
+        }
+    }
+
+    // Weekly deliveries record the week they were sent in the store
+    if config.store_path.is_none() {
+        if config.digest.is_some() {
+            errors.push(
+                "The weekly digest needs a metrics store (METRICS_STORE_PATH or store_path) \
+                 to be sent once per week"
+                    .to_string(),
+            );
+        }
+        if config.leaderboard.is_some() {
+            errors.push(
+                "LEADERBOARD_WEEKDAY needs a metrics store (METRICS_STORE_PATH or store_path) \
+                 to be posted once per week"
+                    .to_string(),
+            );
+        }
+    }
+
+    let warnings = &mut validation.warnings;
+    for name in variables {
+        if KNOWN_VARIABLES.contains(&name.as_str()) {
+            continue;
+        }
+        match closest(name, KNOWN_VARIABLES) {
+            Some(known) => warnings.push(format!(
+                "Unknown environment variable {} (did you mean {}?)",
+                name, known
+            )),
+            None if name.starts_with(OWN_PREFIX) => {
+                warnings.push(format!("Unknown environment variable {}", name))
+            }
+            None => {}
+        }
+    }
+    if let Some(file) = file {
+        check_file_keys(warnings, file);
+    }
+
+    let teams: BTreeSet<&str> = config
+        .enterprises
+        .iter()
+        .flat_map(|e| e.team_slugs.iter().map(String::as_str))
+        .collect();
+    let parents: BTreeSet<&str> = config
+        .team_mappings
+        .values()
+        .filter_map(|m| m.parent.as_deref())
+        .collect();
+    let mut unused: Vec<&String> = config
+        .team_mappings
+        .keys()
+        .filter(|slug| !teams.contains(slug.as_str()) && !parents.contains(slug.as_str()))
+        .collect();
+    unused.sort();
+    for slug in unused {
+        warnings.push(format!(
+            "team_mappings[{}] maps a team that isn't collected",
+            slug
+        ));
+    }
+    if config.skip_enterprise && teams.is_empty() {
+        warnings.push(
+            "SKIP_ENTERPRISE_METRICS is set and no team slugs are configured, \
+             so runs collect nothing"
+                .to_string(),
+        );
+    }
+
+    validation
+}
+
+/// Renders the resolved configuration with secrets masked
+pub fn summary(config: &Config) -> String {
+    let mut out = String::new();
+    let flag = |enabled: bool| if enabled { "yes" } else { "no" };
+
+    let _ = writeln!(out, "GitHub token: {}", mask(&config.github_token));
+    let _ = writeln!(out, "Datadog namespace: {}", config.datadog_namespace);
+    for destination in &config.datadog_destinations {
+        let _ = writeln!(
+            out,
+            "Datadog destination {}: site {}, API key {}{}",
+            destination.name,
+            destination.site,
+            mask(&destination.api_key),
+            destination
+                .namespace
+                .as_ref()
+                .map(|ns| format!(", namespace {}", ns))
+                .unwrap_or_default()
+        );
+    }
+    if let Some(app_key) = &config.datadog_app_key {
+        let _ = writeln!(out, "Datadog application key: {}", mask(app_key));
+    }
+    for enterprise in &config.enterprises {
+        let _ = writeln!(
+            out,
+            "Enterprise {}: namespace {}, teams [{}]{}",
+            enterprise.id,
+            enterprise.namespace,
+            enterprise.team_slugs.join(", "),
+            enterprise
+                .tenant
+                .as_ref()
+                .map(|tenant| format!(
+                    ", tenant (GitHub token {}, Datadog {})",
+                    tenant
+                        .github_token
+                        .as_deref()
+                        .map(mask)
+                        .unwrap_or("shared".into()),
+                    tenant
+                        .datadog
+                        .as_ref()
+                        .map(|d| format!("{} key {}", d.site, mask(&d.api_key)))
+                        .unwrap_or("not read".into())
+                ))
+                .unwrap_or_default()
+        );
+    }
+    if !config.team_mappings.is_empty() {
+        let mut slugs: Vec<&str> = config.team_mappings.keys().map(String::as_str).collect();
+        slugs.sort();
+        let _ = writeln!(out, "Team mappings: {}", slugs.join(", "));
+    }
+
+    let features = [
+        ("Enterprise metrics", !config.skip_enterprise),
+        ("Premium requests", config.collect_premium_requests),
+        ("Organization metrics", config.collect_org_metrics),
+        ("Seat metrics", config.collect_seat_metrics),
+        ("Team rollup", config.team_rollup),
+        ("Weekly rollups", config.weekly_rollups),
+        ("Monthly rollups", config.monthly_rollups),
+        ("Team distribution", config.team_distribution),
+        ("Dashboards", config.provision_dashboards),
+        ("Metric metadata", config.update_metric_metadata),
+        ("Fail on error", config.fail_on_error),
+    ];
+    for (name, enabled) in features {
+        let _ = writeln!(out, "{}: {}", name, flag(enabled));
+    }
+
+    if let Some(path) = &config.store_path {
+        let _ = writeln!(
+            out,
+            "Metrics store: {} (reuse stored days: {})",
+            path,
+            flag(config.reuse_stored_days)
+        );
+    }
+    if let Some(path) = config.agent.ca_bundle() {
+        let _ = writeln!(out, "CA bundle: {}", path);
+    }
+    if let Some(digest) = &config.digest {
+        let _ = writeln!(
+            out,
+            "Digest: {} to {} on {}",
+            digest.sender,
+            digest.recipients.join(", "),
+            digest.weekday
+        );
+    }
+    if !config.alert_rules.is_empty() {
+        let _ = writeln!(out, "Alert rules: {}", config.alert_rules.len());
+    }
+    if let Some(url) = &config.alert_webhook_url {
+        let _ = writeln!(out, "Alert webhook: {}", mask_url(url));
+    }
+    if let Some(opsgenie) = &config.opsgenie {
+        let _ = writeln!(
+            out,
+            "Opsgenie: API key {}{}",
+            mask(&opsgenie.api_key),
+            opsgenie
+                .api_url
+                .as_deref()
+                .map(|url| format!(" ({})", url))
+                .unwrap_or_default()
+        );
+    }
+    if let Some(url) = &config.healthcheck_url {
+        let _ = writeln!(out, "Healthcheck: {}", mask_url(url));
+    }
+    if !config.exports.is_empty() {
+        let kinds: Vec<&str> = config.exports.iter().map(|e| e.kind()).collect();
+        let _ = writeln!(out, "Exports: {}", kinds.join(", "));
+    }
+    if let Some(hashing) = &config.team_hashing {
+        let _ = writeln!(out, "Team hashing salt: {}", mask(&hashing.salt));
+    }
+    out
+}
+
+/// Masks a secret, keeping its last four characters of long secrets so
+/// operators can tell which one is configured
+pub fn mask(secret: &str) -> String {
+    let chars: Vec<char> = secret.chars().collect();
+    if chars.len() <= 8 {
+        return "****".to_string();
+    }
+    let tail: String = chars[chars.len() - 4..].iter().collect();
+    format!("****{}", tail)
+}
+
+/// Masks the path and query of a URL, which carry the secret of webhook URLs
+fn mask_url(url: &str) -> String {
+    let Some((scheme, rest)) = url.split_once("://") else {
+        return mask(url);
+    };
+    let host = rest.split('/').next().unwrap_or_default();
+    format!("{}://{}/****", scheme, host)
+}
+
+/// Reports a namespace that isn't a valid Datadog metric name prefix
+fn check_namespace(errors: &mut Vec<String>, field: &str, namespace: &str) {
+    let valid = namespace.starts_with(|c: char| c.is_ascii_alphabetic())
+        && namespace
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '.')
+        && !namespace.split('.').any(str::is_empty);
+    if !valid {
+        errors.push(format!(
+            "{} {:?} is not a valid Datadog namespace (start with a letter, use letters, \
+             digits, underscores and dots, no empty segments)",
+            field, namespace
+        ));
+    }
+}
+
+/// Reports a team slug that GitHub can't resolve
+fn check_team_slug(errors: &mut Vec<String>, field: &str, slug: &str) {
+    if !is_slug(slug, true) {
+        errors.push(format!(
+            "{} {:?} is not a team slug (lowercase letters, digits, hyphens and \
+             underscores, as in the team's URL)",
+            field, slug
+        ));
+    }
+}
+
+/// Returns whether a value is a GitHub slug
+///
+/// # Arguments
+///
+/// * `value` - The value
+/// * `underscores` - Whether underscores are allowed (team slugs)
+pub fn is_slug(value: &str, underscores: bool) -> bool {
+    !value.is_empty()
+        && value.chars().all(|c| {
+            c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-' || (underscores && c == '_')
+        })
+}
+
+/// Reports unknown keys of the configuration file and its entries
+fn check_file_keys(warnings: &mut Vec<String>, file: &Value) {
+    for (section, keys) in FILE_KEYS {
+        let objects: Vec<(String, &Value)> = match (*section, file.get(section)) {
+            ("", _) => vec![(String::new(), file)],
+            (_, Some(Value::Array(entries))) => entries
+                .iter()
+                .enumerate()
+                .map(|(i, entry)| (format!("{}[{}]", section, i), entry))
+                .collect(),
+            ("team_mappings", Some(Value::Object(mappings))) => mappings
+                .iter()
+                .map(|(slug, entry)| (format!("{}[{}]", section, slug), entry))
+                .collect(),
+            (_, Some(entry @ Value::Object(_))) => vec![(section.to_string(), entry)],
+            _ => Vec::new(),
+        };
+
+        for (path, object) in objects {
+            let Some(object) = object.as_object() else {
+                continue;
+            };
+            for key in object.keys() {
+                if keys.contains(&key.as_str()) {
+                    continue;
+                }
+                let location = if path.is_empty() {
+                    "the configuration file".to_string()
+                } else {
+                    path.clone()
+                };
+                let hint = closest(key, keys)
+                    .map(|known| format!(" (did you mean {}?)", known))
+                    .unwrap_or_default();
+                warnings.push(format!(
+                    "Unknown key {} in {}, ignored{}",
+                    key, location, hint
+                ));
+            }
+        }
+    }
+}
+
+/// Returns the known name closest to an unknown one, if it is a likely typo
+fn closest<'a>(name: &str, known: &[&'a str]) -> Option<&'a str> {
+    known
+        .iter()
+        .map(|candidate| (distance(name, candidate), *candidate))
+        .filter(|(distance, _)| *distance <= MAX_TYPO_DISTANCE)
+        .min()
+        .map(|(_, candidate)| candidate)
+}
+
+/// Levenshtein distance between two names
+fn distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.chars().enumerate() {
+        let mut current = vec![i + 1];
+        for (j, cb) in b.iter().enumerate() {
+            let substitution = previous[j] + usize::from(ca != *cb);
+            current.push(substitution.min(previous[j + 1] + 1).min(current[j] + 1));
+        }
+        previous = current;
+    }
+    previous[b.len()]
+}
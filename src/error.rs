@@ -0,0 +1,174 @@
+//! # Crate Error Type
+//!
+//! Every service module has its own error enum (`GitHubError`, `DatadogError`,
+//! `AwsError`, ...). This module defines `GhrustError`, which wraps them with
+//! `From` impls so processors and the Lambda handler can use `?` without
+//! flattening errors into strings. The original error stays available as the
+//! `source`, and `kind` and `is_retryable` let callers decide about retries
+//! and alerting.
+
+use thiserror::Error;
+
+use crate::config::ConfigError;
+use crate::services::aws::AwsError;
+use crate::services::datadog::DatadogError;
+use crate::services::export::ExportError;
+use crate::services::github::GitHubError;
+use crate::services::notify::NotifyError;
+use crate::services::store::StoreError;
+use crate::services::tls::TlsError;
+
+/// Errors returned by the processors and the Lambda handler
+#[derive(Error, Debug)]
+pub enum GhrustError {
+    /// The configuration is missing or invalid
+    #[error(transparent)]
+    Config(#[from] ConfigError),
+
+    /// The TLS configuration could not be loaded
+    #[error(transparent)]
+    Tls(#[from] TlsError),
+
+    /// The metrics store failed
+    #[error(transparent)]
+    Store(#[from] StoreError),
+
+    /// An export destination could not be set up
+    #[error(transparent)]
+    Export(#[from] ExportError),
+
+    /// A GitHub API request failed
+    #[error("{context}: {source}")]
+    GitHub {
+        /// What was being fetched, e.g. "Failed to fetch team metrics"
+        context: String,
+        /// The underlying GitHub error
+        #[source]
+        source: GitHubError,
+    },
+
+    /// Sending to Datadog failed
+    #[error(transparent)]
+    Datadog(#[from] DatadogError),
+
+    /// An AWS API request failed
+    #[error(transparent)]
+    Aws(#[from] AwsError),
+
+    /// A notification could not be delivered
+    #[error(transparent)]
+    Notify(#[from] NotifyError),
+
+    /// The progress of a backfill could not be recorded
+    #[error("Error writing backfill checkpoint {0}: {1}")]
+    Checkpoint(String, #[source] std::io::Error),
+
+    /// A generated artifact, such as the team leaderboard, could not be written
+    #[error("Error writing {0}: {1}")]
+    Artifact(String, #[source] std::io::Error),
+
+    /// The metrics file handed from `ghrust fetch` to `ghrust send` could not
+    /// be read or written
+    #[error("Error reading or writing metrics file {0}: {1}")]
+    Bundle(String, #[source] std::io::Error),
+
+    /// A team's namespace is shared with other teams
+    #[error("Namespace {0} is shared with other teams")]
+    NamespaceCollision(String),
+
+    /// A run moved to a blocking thread panicked or was cancelled
+    #[error("The run did not complete: {0}")]
+    Interrupted(String),
+
+    /// One or more scopes failed in `FAIL_ON_ERROR` mode
+    #[error("GitHub Copilot metrics processing failed: {}", .0.join("; "))]
+    ScopesFailed(Vec<String>),
+}
+
+/// A specialized Result type for processors and the Lambda handler
+pub type Result<T> = std::result::Result<T, GhrustError>;
+
+impl From<GitHubError> for GhrustError {
+    fn from(source: GitHubError) -> Self {
+        Self::GitHub {
+            context: "GitHub API request failed".to_string(),
+            source,
+        }
+    }
+}
+
+impl GhrustError {
+    /// Returns a closure wrapping a `GitHubError` with context, for `map_err`
+    ///
+    /// # Arguments
+    ///
+    /// * `context` - What was being fetched, e.g. "Failed to fetch team metrics"
+    pub fn github(context: &str) -> impl FnOnce(GitHubError) -> Self + '_ {
+        move |source| Self::GitHub {
+            context: context.to_string(),
+            source,
+        }
+    }
+
+    /// Short, stable name of the error kind, for summaries and alerting
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Self::Config(_) => "config",
+            Self::Tls(_) => "tls",
+            Self::Store(_) => "store",
+            Self::Export(_) => "export",
+            Self::GitHub { source, .. } => match source {
+                GitHubError::Authentication(_) | GitHubError::Authorization(_) => "github_auth",
+                GitHubError::Config(_) => "config",
+                GitHubError::RateLimit(_) => "github_rate_limit",
+                GitHubError::NoDataYet(_) => "github_no_data_yet",
+                GitHubError::NotModified(_) => "github_not_modified",
+                GitHubError::ParseError(..) | GitHubError::SchemaDrift(..) => "github_schema",
+                _ => "github",
+            },
+            Self::Datadog(_) => "datadog",
+            Self::Aws(_) => "aws",
+            Self::Notify(_) => "notify",
+            Self::Checkpoint(..) => "checkpoint",
+            Self::Artifact(..) => "artifact",
+            Self::Bundle(..) => "bundle",
+            Self::NamespaceCollision(_) => "namespace_collision",
+            Self::Interrupted(_) => "interrupted",
+            Self::ScopesFailed(_) => "scopes_failed",
+        }
+    }
+
+    /// Returns whether GitHub was still generating the requested data
+    pub fn is_pending(&self) -> bool {
+        matches!(
+            self,
+            Self::GitHub {
+                source: GitHubError::NoDataYet(_),
+                ..
+            }
+        )
+    }
+
+    /// Returns whether retrying the operation later may succeed
+    ///
+    /// Network errors, rate limits and server errors are transient;
+    /// configuration, authentication and parsing errors are not.
+    pub fn is_retryable(&self) -> bool {
+        match self {
+            Self::GitHub { source, .. } => source.is_retryable(),
+            Self::Datadog(source) => source.is_retryable(),
+            Self::Export(source) => source.is_retryable(),
+            Self::Aws(source) => match source {
+                AwsError::Network(_) => true,
+                AwsError::HttpError(status, _) => *status >= 500,
+                AwsError::Config(_) => false,
+            },
+            Self::Notify(source) => match source {
+                NotifyError::Network(_) => true,
+                NotifyError::HttpError(status, _) => *status >= 500,
+                NotifyError::Notifiers(_) => false,
+            },
+            _ => false,
+        }
+    }
+}
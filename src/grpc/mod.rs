@@ -0,0 +1,263 @@
+//! # gRPC Service
+//!
+//! Optional tonic service (the `grpc` feature) for internal platform services
+//! that prefer gRPC over invoking the Lambda. `FetchEnterpriseMetrics` and
+//! `FetchTeamMetrics` fetch the days of a configured enterprise or one of its
+//! teams from GitHub and return them without sending anything to Datadog.
+//! The interface is defined in `proto/ghrust.proto`; `build.rs` generates
+//! the `proto` module from it.
+//!
+//! Every day carries its totals (the columns of `MetricsRow`) and the full
+//! day as returned by the metrics API, as JSON, for callers that need the
+//! breakdowns.
+//!
+//! ## Core Components
+//!
+//! * `MetricsGrpcService` - Implementation of the `MetricsService` RPCs
+//! * `proto` - Messages, client and server generated from the proto file
+//! * `metrics_day` - Conversion of a fetched day into its message
+
+// Handlers return tonic's `Status`, which is larger than clippy likes
+#![allow(clippy::result_large_err)]
+
+#[cfg(test)]
+mod tests;
+
+use chrono::NaiveDate;
+use tonic::{Request, Response, Status};
+use tracing::{error, info};
+
+use crate::config::Config;
+use crate::models::github::CopilotMetrics;
+use crate::services::export::MetricsRow;
+use crate::services::github::{
+    calculate_default_since_date, GitHubClient, GitHubError, GitHubSettings,
+};
+use proto::metrics_service_server::{MetricsService, MetricsServiceServer};
+use proto::{FetchEnterpriseMetricsRequest, FetchTeamMetricsRequest, MetricsDay, MetricsResponse};
+
+/// Messages and service definitions generated from `proto/ghrust.proto`
+pub mod proto {
+    tonic::include_proto!("ghrust.v1");
+}
+
+/// Serves the `MetricsService` RPCs for the configured enterprises
+#[derive(Debug, Clone)]
+pub struct MetricsGrpcService {
+    /// GitHub token the metrics are fetched with
+    github_token: String,
+    /// IDs of the enterprises callers may query, in configuration order
+    enterprises: Vec<String>,
+    /// GitHub API base URL, the client's default if `None`
+    base_url: Option<String>,
+    /// Request policies and identification of the GitHub client
+    settings: GitHubSettings,
+}
+
+impl MetricsGrpcService {
+    /// Creates a service for enterprises
+    ///
+    /// # Arguments
+    ///
+    /// * `github_token` - GitHub token the metrics are fetched with
+    /// * `enterprises` - IDs of the enterprises callers may query; requests
+    ///   without an enterprise use the first
+    pub fn new(github_token: &str, enterprises: Vec<String>) -> Self {
+        Self {
+            github_token: github_token.to_string(),
+            enterprises,
+            base_url: None,
+            settings: GitHubSettings::default(),
+        }
+    }
+
+    /// Creates a service for the enterprises of a configuration
+    pub fn from_config(config: &Config) -> Self {
+        Self::new(
+            &config.github_token,
+            config.enterprises.iter().map(|e| e.id.clone()).collect(),
+        )
+        .with_settings(config.github_settings.clone())
+    }
+
+    /// Uses request policies and identification other than the defaults
+    pub fn with_settings(mut self, settings: GitHubSettings) -> Self {
+        self.settings = settings;
+        self
+    }
+
+    /// Fetches from another GitHub API base URL, e.g. a mock server
+    pub fn with_base_url(mut self, base_url: &str) -> Self {
+        self.base_url = Some(base_url.to_string());
+        self
+    }
+
+    /// Wraps the service in a tonic server, to be added to a `Server`
+    pub fn into_server(self) -> MetricsServiceServer<Self> {
+        MetricsServiceServer::new(self)
+    }
+
+    /// Returns the requested enterprise, or the first one if none is given
+    fn enterprise(&self, enterprise_id: &str) -> Result<String, Status> {
+        let enterprise = if enterprise_id.is_empty() {
+            self.enterprises.first()
+        } else {
+            self.enterprises.iter().find(|id| *id == enterprise_id)
+        };
+        enterprise.cloned().ok_or_else(|| {
+            Status::not_found(format!("Enterprise {} is not configured", enterprise_id))
+        })
+    }
+
+    /// Builds the GitHub client of a request
+    fn client(&self) -> Result<GitHubClient, Status> {
+        let mut builder = GitHubClient::builder()
+            .token(&self.github_token)
+            .settings(self.settings.clone());
+        if let Some(base_url) = &self.base_url {
+            builder = builder.base_url(base_url);
+        }
+        builder.build().map_err(status)
+    }
+}
+
+#[tonic::async_trait]
+impl MetricsService for MetricsGrpcService {
+    async fn fetch_enterprise_metrics(
+        &self,
+        request: Request<FetchEnterpriseMetricsRequest>,
+    ) -> Result<Response<MetricsResponse>, Status> {
+        let request = request.into_inner();
+        let enterprise = self.enterprise(&request.enterprise_id)?;
+        let (since, until) = date_range(&request.since, &request.until)?;
+        let client = self.client()?;
+        let scope = format!("enterprise:{}", enterprise);
+
+        info!("gRPC fetch of {} since {}", scope, since);
+        let metrics = run_blocking(move || match until {
+            Some(until) => client.fetch_enterprise_metrics_between(&enterprise, &since, &until),
+            None => client.fetch_enterprise_metrics(&enterprise, &since),
+        })
+        .await?;
+        Ok(Response::new(metrics_response(scope, metrics)))
+    }
+
+    async fn fetch_team_metrics(
+        &self,
+        request: Request<FetchTeamMetricsRequest>,
+    ) -> Result<Response<MetricsResponse>, Status> {
+        let request = request.into_inner();
+        if request.team_slug.is_empty() {
+            return Err(Status::invalid_argument("team_slug is required"));
+        }
+        let enterprise = self.enterprise(&request.enterprise_id)?;
+        let (since, until) = date_range(&request.since, &request.until)?;
+        let client = self.client()?;
+        let team_slug = request.team_slug;
+        let scope = format!("team:{}/{}", enterprise, team_slug);
+
+        info!("gRPC fetch of {} since {}", scope, since);
+        let metrics = run_blocking(move || match until {
+            Some(until) => {
+                client.fetch_team_metrics_between(&enterprise, &team_slug, &since, &until)
+            }
+            None => client.fetch_team_metrics(&enterprise, &team_slug, &since),
+        })
+        .await?;
+        Ok(Response::new(metrics_response(scope, metrics)))
+    }
+}
+
+/// Converts a fetched day into its message
+///
+/// # Arguments
+///
+/// * `scope` - Scope the day belongs to, e.g. "enterprise:acme"
+/// * `metric` - The day's metrics
+pub fn metrics_day(scope: &str, metric: &CopilotMetrics) -> MetricsDay {
+    let row = MetricsRow::new(scope, metric);
+    MetricsDay {
+        date: row.date,
+        total_active_users: row.total_active_users,
+        total_engaged_users: row.total_engaged_users,
+        code_completions_engaged_users: row.code_completions_engaged_users,
+        ide_chat_engaged_users: row.ide_chat_engaged_users,
+        dotcom_chat_engaged_users: row.dotcom_chat_engaged_users,
+        pull_requests_engaged_users: row.pull_requests_engaged_users,
+        code_suggestions: row.code_suggestions,
+        code_acceptances: row.code_acceptances,
+        code_lines_suggested: row.code_lines_suggested,
+        code_lines_accepted: row.code_lines_accepted,
+        payload_json: serde_json::to_string(metric).unwrap_or_default(),
+    }
+}
+
+/// Builds the response of a scope, with its days oldest first
+fn metrics_response(scope: String, mut metrics: Vec<CopilotMetrics>) -> MetricsResponse {
+    metrics.sort_by(|a, b| a.date.cmp(&b.date));
+    MetricsResponse {
+        days: metrics.iter().map(|m| metrics_day(&scope, m)).collect(),
+        scope,
+    }
+}
+
+/// Validates the date range of a request
+///
+/// # Returns
+///
+/// * `Result<(String, Option<String>), Status>` - The first day, the start of
+///   the reporting window if `since` is empty, and the last day if given
+pub(crate) fn date_range(since: &str, until: &str) -> Result<(String, Option<String>), Status> {
+    let parse = |value: &str, field: &str| {
+        NaiveDate::parse_from_str(value, "%Y-%m-%d").map_err(|_| {
+            Status::invalid_argument(format!("{} must be a YYYY-MM-DD date: {}", field, value))
+        })
+    };
+
+    let since = if since.is_empty() {
+        calculate_default_since_date()
+    } else {
+        parse(since, "since")?.format("%Y-%m-%d").to_string()
+    };
+    if until.is_empty() {
+        return Ok((since, None));
+    }
+    let until = parse(until, "until")?.format("%Y-%m-%d").to_string();
+    if until < since {
+        return Err(Status::invalid_argument(format!(
+            "until ({}) is before since ({})",
+            until, since
+        )));
+    }
+    Ok((since, Some(until)))
+}
+
+/// Runs a blocking GitHub request off the async executor
+async fn run_blocking(
+    fetch: impl FnOnce() -> Result<Vec<CopilotMetrics>, GitHubError> + Send + 'static,
+) -> Result<Vec<CopilotMetrics>, Status> {
+    match tokio::task::spawn_blocking(fetch).await {
+        Ok(result) => result.map_err(status),
+        Err(e) => {
+            error!("gRPC fetch panicked: {}", e);
+            Err(Status::internal("The fetch panicked"))
+        }
+    }
+}
+
+/// Converts a GitHub error into the gRPC status callers see
+pub(crate) fn status(e: GitHubError) -> Status {
+    let message = e.to_string();
+    match e {
+        GitHubError::Authentication(_) => Status::unauthenticated(message),
+        GitHubError::Authorization(_) => Status::permission_denied(message),
+        GitHubError::NotFound(_) => Status::not_found(message),
+        GitHubError::Validation(_) => Status::invalid_argument(message),
+        GitHubError::RateLimit(_) => Status::resource_exhausted(message),
+        GitHubError::Network(_) => Status::unavailable(message),
+        GitHubError::Config(_) => Status::failed_precondition(message),
+        GitHubError::ServerError(..) => Status::unavailable(message),
+        GitHubError::HttpError(status, _) if status >= 500 => Status::unavailable(message),
+        _ => Status::internal(message),
+    }
+}
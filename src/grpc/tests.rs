@@ -0,0 +1,112 @@
+//! # gRPC Service Tests
+
+use tonic::{Code, Request};
+
+use super::proto::metrics_service_server::MetricsService;
+use super::proto::{FetchEnterpriseMetricsRequest, FetchTeamMetricsRequest};
+use super::{date_range, metrics_day, metrics_response, status, MetricsGrpcService};
+use crate::models::github::CopilotMetrics;
+use crate::services::github::{create_mock_metrics, GitHubError};
+
+fn service() -> MetricsGrpcService {
+    // Requests that fail validation never reach this address
+    MetricsGrpcService::new("token", vec!["acme".to_string(), "globex".to_string()])
+        .with_base_url("http://127.0.0.1:9")
+}
+
+#[test]
+fn test_metrics_day_carries_totals_and_payload() {
+    let metric = create_mock_metrics(120, 90);
+    let day = metrics_day("enterprise:acme", &metric);
+
+    assert_eq!(day.date, metric.date);
+    assert_eq!(day.total_active_users, 120);
+    assert_eq!(day.total_engaged_users, 90);
+    let payload: CopilotMetrics = serde_json::from_str(&day.payload_json).unwrap();
+    assert_eq!(payload.date, metric.date);
+    assert_eq!(payload.total_active_users, Some(120));
+}
+
+#[test]
+fn test_response_sorts_days() {
+    let mut later = create_mock_metrics(2, 1);
+    later.date = "2025-06-02".to_string();
+    let mut earlier = create_mock_metrics(1, 1);
+    earlier.date = "2025-06-01".to_string();
+
+    let response = metrics_response("enterprise:acme".to_string(), vec![later, earlier]);
+    assert_eq!(response.scope, "enterprise:acme");
+    let dates: Vec<&str> = response.days.iter().map(|d| d.date.as_str()).collect();
+    assert_eq!(dates, vec!["2025-06-01", "2025-06-02"]);
+}
+
+#[test]
+fn test_date_range_validation() {
+    let (since, until) = date_range("2025-06-01", "2025-06-07").unwrap();
+    assert_eq!(since, "2025-06-01");
+    assert_eq!(until.as_deref(), Some("2025-06-07"));
+
+    let (since, until) = date_range("", "").unwrap();
+    assert_eq!(since.len(), 10);
+    assert_eq!(until, None);
+
+    assert_eq!(
+        date_range("06/01/2025", "").unwrap_err().code(),
+        Code::InvalidArgument
+    );
+    assert_eq!(
+        date_range("2025-06-07", "2025-06-01").unwrap_err().code(),
+        Code::InvalidArgument
+    );
+}
+
+#[test]
+fn test_github_errors_map_to_status_codes() {
+    let code = |e: GitHubError| status(e).code();
+    assert_eq!(
+        code(GitHubError::Authentication("bad".into())),
+        Code::Unauthenticated
+    );
+    assert_eq!(code(GitHubError::NotFound("team".into())), Code::NotFound);
+    assert_eq!(
+        code(GitHubError::RateLimit("slow down".into())),
+        Code::ResourceExhausted
+    );
+    assert_eq!(
+        code(GitHubError::HttpError(502, "bad gateway".into())),
+        Code::Unavailable
+    );
+    assert_eq!(
+        code(GitHubError::ParseError("team".into(), "eof".into())),
+        Code::Internal
+    );
+}
+
+#[tokio::test]
+async fn test_requests_are_validated_before_fetching() {
+    let service = service();
+
+    let unknown = service
+        .fetch_enterprise_metrics(Request::new(FetchEnterpriseMetricsRequest {
+            enterprise_id: "initech".to_string(),
+            ..Default::default()
+        }))
+        .await
+        .unwrap_err();
+    assert_eq!(unknown.code(), Code::NotFound);
+
+    let invalid = service
+        .fetch_enterprise_metrics(Request::new(FetchEnterpriseMetricsRequest {
+            since: "yesterday".to_string(),
+            ..Default::default()
+        }))
+        .await
+        .unwrap_err();
+    assert_eq!(invalid.code(), Code::InvalidArgument);
+
+    let no_team = service
+        .fetch_team_metrics(Request::new(FetchTeamMetricsRequest::default()))
+        .await
+        .unwrap_err();
+    assert_eq!(no_team.code(), Code::InvalidArgument);
+}
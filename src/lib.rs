@@ -4,11 +4,25 @@
 //! GitHub Copilot usage metrics. It's designed to work both as a standalone binary
 //! and as a Lambda function.
 //!
+//! The Lambda entry point and its runtime dependencies (`lambda_runtime`,
+//! `tokio`) are behind the default `lambda` feature. Build with
+//! `default-features = false` to embed the library without them.
+//!
 //! The library exposes modules for working with GitHub and Datadog APIs,
-//! as well as processors for different types of metrics.
+//! as well as processors for different types of metrics. Processors return the
+//! crate-wide `GhrustError`, which wraps the error types of the services.
+//! `MetricsPipeline` runs a complete collection for a configuration.
+//!
+//! With the `grpc` feature, `grpc::MetricsGrpcService` serves metrics
+//! fetches over gRPC.
 
 // Public modules that can be used by external crates
+pub mod config;
+pub mod error;
+#[cfg(feature = "grpc")]
+pub mod grpc;
 pub mod models;
+pub mod pipeline;
 pub mod processors;
 pub mod services;
 
@@ -16,4 +30,6 @@ pub mod services;
 #[cfg(test)]
 pub mod tests;
 
+pub use error::GhrustError;
 pub use models::github::CopilotMetrics;
+pub use pipeline::{MetricsPipeline, RunReport, RunScope};
@@ -4,141 +4,753 @@
 //! GitHub Copilot metrics and reports them to Datadog.
 //!
 //! ## Workflow
-//! 1. Collects environment variables for configuration
-//! 2. Processes enterprise-wide Copilot metrics (if not skipped)
-//! 3. Processes team-specific Copilot metrics (if team slugs provided)
-//! 4. Reports all metrics to Datadog
+//! 1. Loads the configuration from environment variables and the config file
+//! 2. For each configured enterprise:
+//!    - Processes enterprise-wide Copilot metrics (if not skipped)
+//!    - Processes team-specific Copilot metrics (if team slugs provided)
+//! 3. Reports all metrics to Datadog and returns a per-enterprise status
+//!    together with a per-scope run summary
+//! 4. In `FAIL_ON_ERROR` mode, fails the invocation if any enterprise scope or
+//!    too many teams failed, so Lambda error alarms fire
 //!
 //! ## Environment Variables
 //! - `GITHUB_TOKEN`: Personal access token with admin:enterprise permissions
+//! - `GITHUB_TOKEN_FILE`: File containing the token, read if `GITHUB_TOKEN` is not set
 //! - `GITHUB_ENTERPRISE_ID`: ID of the GitHub Enterprise organization
+//! - `GITHUB_ENTERPRISE_IDS`: Comma-separated list of enterprise IDs (optional,
+//!   overrides `GITHUB_ENTERPRISE_ID`; each enterprise gets its own namespace)
+//! - `GHRUST_CONFIG_FILE`: Path to a JSON configuration file (optional)
 //! - `GITHUB_TEAM_SLUGS`: Comma-separated list of team slugs (optional)
+//! - `GITHUB_TEAM_MAPPINGS`: JSON object of per-team namespace and tag overrides (optional)
+//! - `GHRUST_CA_BUNDLE`: Path of a PEM CA bundle for internal certificate authorities (optional)
 //! - `DATADOG_API_KEY`: Datadog API key
+//! - `DATADOG_API_KEY_FILE`: File containing the API key, read if `DATADOG_API_KEY` is not set
+//! - `DATADOG_SITE`: Datadog site for the primary API key (default: datadoghq.eu)
+//! - `DATADOG_DESTINATIONS`: JSON list of additional Datadog organizations (optional)
 //! - `DATADOG_METRIC_NAMESPACE`: Namespace prefix for metrics (default: github.copilot)
+//! - `DATADOG_SEND_CONCURRENCY`: Number of chunks submitted at the same time (default: 4)
 //! - `SKIP_ENTERPRISE_METRICS`: If set, skips enterprise metrics processing
-
-// Module declarations for project organization
-mod models; // Contains data structures for GitHub and Datadog
-mod processors; // Contains business logic for processing metrics
-mod services; // Contains API clients for external services
-#[cfg(test)] // Test module only included in test builds
-mod tests;
+//! - `COLLECT_PREMIUM_REQUESTS`: If set, also reports premium request usage
+//! - `COLLECT_ORG_METRICS`: If set, also reports the metrics of every
+//!   organization of the enterprise under `{namespace}.org.{slug}`
+//! - `COLLECT_SEAT_METRICS`: If set, also reports assigned and unused Copilot
+//!   seats under `{namespace}.seats.*`
+//! - `REPORT_UNUSED_SEAT_USERS`: If set, also reports every unused seat tagged
+//!   with its holder
+//! - `COPILOT_SEAT_PRICE`: Monthly price of a seat; if set, estimated spend and
+//!   wasted spend are reported with the seat metrics
+//! - `TEAM_ROLLUP`: If set, also reports the summed metrics of all teams under
+//!   `{namespace}.teams.all`
+//! - `DIGEST_RECIPIENTS`: Comma-separated recipients of the weekly SES digest (optional)
+//! - `DIGEST_SENDER`: Verified SES sender address of the digest
+//! - `DIGEST_WEEKDAY`: Day of the week the digest is sent on (default: Mon)
+//! - `METRICS_STORE_PATH`: SQLite database fetched metrics are stored in
+//!   (optional, requires the `sqlite` feature)
+//! - `MIN_LANGUAGE_ENGAGED_USERS`: Languages with fewer engaged users are
+//!   reported as `other` (optional)
+//! - `TOP_N_BREAKDOWNS`: Keeps the N most used languages, editors and models
+//!   per breakdown and reports the rest as `other` (optional)
+//! - `SEND_LATEST_DAY_ONLY`: If set, only the most recent complete day is sent
+//! - `FAIL_ON_ERROR`: If set, returns a Lambda error when processing fails
+//! - `TEAM_FAILURE_THRESHOLD`: Share of teams allowed to fail in `FAIL_ON_ERROR`
+//!   mode (default: 0.0)
+//!
+//! ## Commands
+//! - `ghrust validate`: Loads the configuration, prints a summary with secrets
+//!   masked and the problems found, and exits non-zero if it is invalid,
+//!   without starting the Lambda runtime
+//! - `ghrust doctor`: Checks the GitHub token and its scopes, the enterprise
+//!   and team metrics endpoints, the Datadog API keys and the CA bundle against
+//!   the live services, prints a fix for every failure and exits non-zero if
+//!   any check failed
+//! - `ghrust fetch --out <file>`: Fetches the enterprise and team metrics with
+//!   the GitHub token only and writes them to a JSON file
+//! - `ghrust send --in <file> [--sink datadog]`: Sends a file written by
+//!   `ghrust fetch` with the Datadog keys only
+//! - `ghrust replay --from <dir|s3://bucket/prefix> --since <date> [--until <date>]`:
+//!   Sends the raw responses archived on the given fetch days (`--until`
+//!   defaults to `--since`) through the current transformation to the
+//!   configured Datadog destinations, with the Datadog keys only
+//! - `ghrust report [--enterprise <id>] [--team <slug>] [--output <file>]`:
+//!   Renders the metrics of an enterprise or a team into a standalone HTML
+//!   report (default: `copilot-report.html`), with the GitHub token only
+//! - `ghrust diff [--enterprise <id>] [--team <slug>] [--days <n>] [--store <path>] [--json]`:
+//!   Compares the aggregated metrics of the last `--days` days (default: 7)
+//!   with the days before and prints the change of every metric; with
+//!   `--store` the days are read from a local metrics store instead of GitHub
+//! - `ghrust compare-teams [--enterprise <id>] [--by <metric>] [--json]`:
+//!   Ranks the configured teams of an enterprise on their adoption over the
+//!   last 30 days by `engaged_users_rate` (default), `acceptance_rate` or
+//!   `chats_per_user`
+//! - `ghrust backfill --since <date> --until <date> [--enterprise <id>] [--team <slug>] [--checkpoint <file>] [--day-delay-ms <n>] [--refresh-teams]`:
+//!   Backfills the range one day at a time, recording every completed day in
+//!   the checkpoint file (default: `backfill-checkpoint.json`) so a failed
+//!   backfill resumes where it stopped when run again
+//!
+//! Commands log to stderr, so their output on stdout can be piped.
 
 // Import necessary dependencies, modules and types
 use anyhow::Result;
+use chrono::NaiveDate;
 use lambda_runtime::{service_fn, Error, LambdaEvent};
 use serde_json::{json, Value};
-use std::env;
-use tracing;
+use std::path::Path;
+use std::time::Duration;
+use tracing::{info, info_span, warn, Instrument};
+
+// Import configuration and the pipeline from the library crate
+use ghrust::config::doctor;
+use ghrust::config::logging::log_filter;
+use ghrust::config::validate::{summary, validate_env};
+use ghrust::config::{Config, EnterpriseConfig, Stage};
+use ghrust::error::GhrustError;
+use ghrust::models::github::CopilotMetrics;
+use ghrust::pipeline::{MetricsPipeline, RunScope};
+use ghrust::processors::backfill::BackfillOptions;
+use ghrust::processors::diff::PeriodComparison;
+use ghrust::processors::handoff::MetricsBundle;
+use ghrust::processors::ranking::{RankBy, TeamRanking, TeamStanding};
+use ghrust::processors::report::MetricsReport;
+use ghrust::services::github::{get_enterprise_metrics, get_team_metrics, RawArchive};
+use ghrust::services::store::Store;
+
+/// Default path of the report written by `ghrust report`
+const DEFAULT_REPORT_OUTPUT: &str = "copilot-report.html";
 
-// Import processor modules for enterprise and team metrics
-use crate::processors::enterprise;
-use crate::processors::team;
+/// Default length of each period compared by `ghrust diff`, in days
+const DEFAULT_DIFF_DAYS: usize = 7;
+
+/// Default checkpoint file of `ghrust backfill`
+const DEFAULT_BACKFILL_CHECKPOINT: &str = "backfill-checkpoint.json";
 
 /// Handler function for AWS Lambda
 ///
-/// Processes GitHub Copilot metrics for an enterprise and/or specific teams
-/// and sends the metrics to Datadog.
+/// Processes GitHub Copilot metrics for one or more enterprises and/or
+/// specific teams and sends the metrics to Datadog.
 ///
 /// # Arguments
 ///
-/// * `_event` - Lambda event payload (not used in current implementation)
+/// * `event` - Lambda event; only its request ID is used, to tag the logs and
+///   collector metrics of the invocation
 ///
 /// # Returns
 ///
-/// * `Result<Value, Error>` - JSON response indicating success or failure
-async fn function_handler(_event: LambdaEvent<Value>) -> Result<Value, Error> {
-    println!("Starting lambda function execution...");
+/// * `Result<Value, Error>` - JSON response indicating success or failure,
+///   including the processing status of each enterprise. In `FAIL_ON_ERROR`
+///   mode an error is returned instead when any scope failed.
+async fn function_handler(event: LambdaEvent<Value>) -> Result<Value, Error> {
+    let request_id = event.context.request_id;
+    info!(request_id = %request_id, "Starting lambda function execution");
 
-    // Get required environment variables for GitHub API authentication
-    let github_token = env::var("GITHUB_TOKEN")
-        .map_err(|_| Error::from("GITHUB_TOKEN environment variable not set"))?;
+    // Load configuration from environment variables and the optional config file
+    let config = Config::from_env().map_err(GhrustError::from)?;
+    let fail_on_error = config.fail_on_error;
 
-    // Get the enterprise ID to identify which GitHub Enterprise instance to query
-    let enterprise_id = env::var("GITHUB_ENTERPRISE_ID")
-        .map_err(|_| Error::from("GITHUB_ENTERPRISE_ID environment variable not set"))?;
+    // Tag the logs and collector metrics of the run with the invocation, so
+    // they can be traced back to its CloudWatch logs
+    let span = info_span!("invocation", request_id = %request_id);
+    let report = MetricsPipeline::new(config)
+        .with_collector_tags(vec![format!("lambda_request_id:{}", request_id)])
+        .run()
+        .instrument(span)
+        .await?;
 
-    // Get Datadog API key for sending metrics
-    let datadog_api_key = env::var("DATADOG_API_KEY")
-        .map_err(|_| Error::from("DATADOG_API_KEY environment variable not set"))?;
+    // In strict mode, surface failures to the Lambda runtime so alerting fires
+    if fail_on_error && report.has_failures() {
+        warn!(
+            request_id = %request_id,
+            run_id = %report.run_id,
+            failures = report.failures.len(),
+            "Failing invocation: {}",
+            report.failures.join("; ")
+        );
+        return Err(GhrustError::ScopesFailed(report.failures).into());
+    }
 
-    // Get namespace for Datadog metrics or use default if not provided
-    // This determines the prefix for all metrics sent to Datadog
-    let datadog_namespace = env::var("DATADOG_METRIC_NAMESPACE").unwrap_or_else(|_| {
-        println!("DATADOG_METRIC_NAMESPACE not set, using default: github.copilot");
-        "github.copilot".to_string()
+    // Return success response to Lambda runtime
+    // Unless FAIL_ON_ERROR is set, the workflow completes successfully even if
+    // some metrics processing failed
+    let mut response = json!({
+        "statusCode": 200,
+        "message": "GitHub Copilot metrics processing completed",
+        "run_id": report.run_id,
+        "enterprises": report.enterprises,
+        "summary": report.summary
     });
+    if !report.failovers.is_empty() {
+        response["failovers"] = json!(report.failovers);
+    }
+    Ok(response)
+}
 
-    // Check if enterprise metrics processing should be skipped
-    // This is useful for cases where only team metrics are needed
-    let skip_enterprise = env::var("SKIP_ENTERPRISE_METRICS").is_ok();
-
-    // Parse comma-separated team slugs into a vector of strings
-    // These identify which teams to collect metrics for
-    let team_slugs = env::var("GITHUB_TEAM_SLUGS").ok().map(|slugs| {
-        slugs
-            .split(',')
-            .map(|s| s.trim().to_string())
-            .filter(|s| !s.is_empty())
-            .collect::<Vec<String>>()
+/// Runs `ghrust validate` and exits
+///
+/// Loads the configuration like an invocation does (environment, `.env`,
+/// configuration file, mounted and Vault secrets), prints the resolved
+/// settings with secrets masked followed by the warnings and errors found,
+/// and exits with status 1 if the configuration can't be loaded or has errors.
+fn validate_config() -> ! {
+    dotenvy::dotenv().ok();
+
+    let config = match Config::from_env() {
+        Ok(config) => config,
+        Err(e) => {
+            eprintln!("error: {}", e);
+            std::process::exit(1);
+        }
+    };
+    print!("{}", summary(&config));
+
+    let validation = validate_env(&config);
+    for warning in &validation.warnings {
+        eprintln!("warning: {}", warning);
+    }
+    for error in &validation.errors {
+        eprintln!("error: {}", error);
+    }
+    if !validation.is_ok() {
+        std::process::exit(1);
+    }
+    println!("Configuration is valid");
+    std::process::exit(0);
+}
+
+/// Runs `ghrust doctor` and exits
+///
+/// Loads the configuration like `ghrust validate`, runs the live checks,
+/// prints each result with its fix and exits with status 1 if the
+/// configuration can't be loaded or a check failed.
+fn run_doctor() -> ! {
+    dotenvy::dotenv().ok();
+
+    let config = match Config::from_env() {
+        Ok(config) => config,
+        Err(e) => {
+            eprintln!("error: {}", e);
+            std::process::exit(1);
+        }
+    };
+    let report = doctor::run(&config);
+    print!("{}", report);
+    if !report.is_ok() {
+        eprintln!("{} check(s) failed", report.failures());
+        std::process::exit(1);
+    }
+    println!("All checks passed");
+    std::process::exit(0);
+}
+
+/// Returns the value of a command's only option, exiting on other arguments
+///
+/// # Arguments
+///
+/// * `command` - The command, for error messages
+/// * `option` - The required option, e.g. "--out"
+/// * `sink` - Whether `--sink datadog` is accepted as well
+fn path_option(command: &str, option: &str, sink: bool) -> String {
+    let mut path = None;
+    let mut args = std::env::args().skip(2);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            a if a == option => path = args.next(),
+            "--sink" if sink => match args.next().as_deref() {
+                Some("datadog") => {}
+                other => {
+                    eprintln!(
+                        "error: unsupported sink {}, only datadog is supported",
+                        other.unwrap_or("(none)")
+                    );
+                    std::process::exit(2);
+                }
+            },
+            other => {
+                eprintln!("error: unknown argument {} for ghrust {}", other, command);
+                std::process::exit(2);
+            }
+        }
+    }
+    path.unwrap_or_else(|| {
+        eprintln!("error: ghrust {} needs {} <file>", command, option);
+        std::process::exit(2);
+    })
+}
+
+/// Loads the configuration of a stage, exiting if it can't be loaded
+fn stage_config(stage: Stage) -> Config {
+    dotenvy::dotenv().ok();
+    Config::from_env_for(stage).unwrap_or_else(|e| {
+        eprintln!("error: {}", e);
+        std::process::exit(1);
+    })
+}
+
+/// Runs `ghrust fetch` and exits
+///
+/// Fetches the metrics of every configured scope, writes the ones that were
+/// fetched to the `--out` file and exits with status 1 if any scope failed,
+/// so the fetch can be retried before sending.
+fn run_fetch() -> ! {
+    let out = path_option("fetch", "--out", false);
+    let config = stage_config(Stage::Fetch);
+
+    let (bundle, report) = match MetricsPipeline::new(config).fetch_bundle() {
+        Ok(fetched) => fetched,
+        Err(e) => {
+            eprintln!("error: {}", e);
+            std::process::exit(1);
+        }
+    };
+    if let Err(e) = bundle.write(std::path::Path::new(&out)) {
+        eprintln!("error: {}", e);
+        std::process::exit(1);
+    }
+    println!("Wrote {} scope(s) to {}", bundle.scopes.len(), out);
+    if report.has_failures() {
+        eprintln!("Failed scopes: {}", report.failures.join("; "));
+        std::process::exit(1);
+    }
+    std::process::exit(0);
+}
+
+/// Runs `ghrust send` and exits
+///
+/// Sends the `--in` file written by `ghrust fetch` to the configured Datadog
+/// destinations and exits with status 1 if any scope failed to send.
+fn run_send() -> ! {
+    let input = path_option("send", "--in", true);
+    let config = stage_config(Stage::Send);
+
+    let report = MetricsBundle::read(std::path::Path::new(&input))
+        .and_then(|bundle| MetricsPipeline::new(config).send_bundle(&bundle));
+    match report {
+        Ok(report) if report.has_failures() => {
+            eprintln!("Failed scopes: {}", report.failures.join("; "));
+            std::process::exit(1);
+        }
+        Ok(report) => {
+            println!(
+                "Sent {} scope(s) from {}",
+                report.summary.scopes.len(),
+                input
+            );
+            std::process::exit(0);
+        }
+        Err(e) => {
+            eprintln!("error: {}", e);
+            std::process::exit(1);
+        }
+    }
+}
+
+/// Runs `ghrust replay` and exits
+///
+/// Reads the raw responses archived on every day from `--since` to `--until`
+/// at `--from`, sends them to the configured Datadog destinations and exits
+/// with status 1 if any scope failed.
+fn run_replay() -> ! {
+    let usage = || -> ! {
+        eprintln!(
+            "error: ghrust replay needs --from <dir|s3://bucket/prefix> --since <YYYY-MM-DD> [--until <YYYY-MM-DD>]"
+        );
+        std::process::exit(2);
+    };
+    let date = |value: Option<String>| {
+        value
+            .and_then(|value| NaiveDate::parse_from_str(&value, "%Y-%m-%d").ok())
+            .unwrap_or_else(|| usage())
+    };
+
+    let (mut from, mut since, mut until) = (None, None, None);
+    let mut args = std::env::args().skip(2);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--from" => from = Some(args.next().unwrap_or_else(|| usage())),
+            "--since" => since = Some(date(args.next())),
+            "--until" => until = Some(date(args.next())),
+            other => {
+                eprintln!("error: unknown argument {} for ghrust replay", other);
+                std::process::exit(2);
+            }
+        }
+    }
+    let (Some(from), Some(since)) = (from, since) else {
+        usage()
+    };
+    let until = until.unwrap_or(since);
+    if until < since {
+        usage();
+    }
+    let days: Vec<NaiveDate> = since.iter_days().take_while(|day| *day <= until).collect();
+
+    let config = stage_config(Stage::Send);
+    let archive = RawArchive::open(&from, &config.agent).unwrap_or_else(|e| {
+        eprintln!("error: could not open the archive {}: {}", from, e);
+        std::process::exit(1);
     });
+    match MetricsPipeline::new(config).replay(&archive, &days) {
+        Ok(report) if report.has_failures() => {
+            eprintln!("Failed scopes: {}", report.failures.join("; "));
+            std::process::exit(1);
+        }
+        Ok(report) => {
+            println!(
+                "Replayed {} scope(s) archived {} to {} from {}",
+                report.summary.scopes.len(),
+                since,
+                until,
+                from
+            );
+            std::process::exit(0);
+        }
+        Err(e) => {
+            eprintln!("error: {}", e);
+            std::process::exit(1);
+        }
+    }
+}
+
+/// Returns the value following an option, exiting if it is missing
+///
+/// # Arguments
+///
+/// * `command` - The command, for error messages
+/// * `option` - The option the value belongs to, e.g. "--team"
+/// * `value` - The next command-line argument
+fn option_value(command: &str, option: &str, value: Option<String>) -> String {
+    value.unwrap_or_else(|| {
+        eprintln!("error: {} of ghrust {} needs a value", option, command);
+        std::process::exit(2);
+    })
+}
+
+/// Returns the configured enterprise a command reads, exiting if it is unknown
+///
+/// # Arguments
+///
+/// * `config` - The loaded configuration
+/// * `id` - The `--enterprise` option; the first configured enterprise if `None`
+fn command_enterprise(config: &Config, id: Option<String>) -> &EnterpriseConfig {
+    let Some(id) = id else {
+        return &config.enterprises[0];
+    };
+    config
+        .enterprises
+        .iter()
+        .find(|e| e.id == id)
+        .unwrap_or_else(|| {
+            eprintln!("error: enterprise {} is not configured", id);
+            std::process::exit(1);
+        })
+}
+
+/// Fetches the metrics of an enterprise or one of its teams, exiting on failure
+///
+/// # Arguments
+///
+/// * `config` - The loaded configuration, providing the token and settings
+/// * `enterprise_id` - The enterprise
+/// * `team_slug` - The team, or `None` for the enterprise-wide metrics
+fn fetch_metrics(
+    config: &Config,
+    enterprise_id: &str,
+    team_slug: Option<&str>,
+) -> Vec<CopilotMetrics> {
+    let fetched = config
+        .github_client()
+        .build()
+        .and_then(|client| match team_slug {
+            Some(slug) => get_team_metrics(&client, enterprise_id, slug),
+            None => get_enterprise_metrics(&client, enterprise_id),
+        });
+    fetched.unwrap_or_else(|e| {
+        eprintln!("error: {}", e);
+        std::process::exit(1);
+    })
+}
 
-    // WORKFLOW STEP 1: Process enterprise-wide metrics if not explicitly skipped
-    // These metrics cover all Copilot usage across the entire enterprise
-    if !skip_enterprise {
-        match enterprise::process_enterprise_metrics(
-            &github_token,
-            &enterprise_id,
-            &datadog_api_key,
-            &datadog_namespace,
-        ) {
-            Ok(_) => {
-                println!("Successfully processed enterprise metrics");
+/// Runs `ghrust report` and exits
+///
+/// Fetches the metrics of the enterprise or `--team`, renders them into an
+/// HTML report and writes it to `--output`, with the GitHub token only.
+fn run_report() -> ! {
+    let (mut enterprise, mut team, mut output) = (None, None, None);
+    let mut args = std::env::args().skip(2);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--enterprise" => enterprise = Some(option_value("report", &arg, args.next())),
+            "--team" => team = Some(option_value("report", &arg, args.next())),
+            "--output" => output = Some(option_value("report", &arg, args.next())),
+            other => {
+                eprintln!("error: unknown argument {} for ghrust report", other);
+                std::process::exit(2);
             }
+        }
+    }
+    let output = output.unwrap_or_else(|| DEFAULT_REPORT_OUTPUT.to_string());
+
+    let config = stage_config(Stage::Fetch);
+    let enterprise_id = command_enterprise(&config, enterprise).id.clone();
+    let metrics = fetch_metrics(&config, &enterprise_id, team.as_deref());
+    let title = match &team {
+        Some(slug) => format!("{} / {}", enterprise_id, slug),
+        None => enterprise_id,
+    };
+    info!("Fetched {} days of metrics for {}", metrics.len(), title);
+
+    let report = MetricsReport::from_metrics(&title, &metrics);
+    if let Err(e) = std::fs::write(&output, report.to_html()) {
+        eprintln!("error: could not write {}: {}", output, e);
+        std::process::exit(1);
+    }
+    println!("Report written to {}", output);
+    std::process::exit(0);
+}
+
+/// Runs `ghrust diff` and exits
+///
+/// Loads the days of the enterprise or `--team` from the `--store` metrics
+/// store or GitHub, compares the two most recent periods of `--days` days and
+/// prints the changes as a table or, with `--json`, as JSON. Exits with
+/// status 1 if there aren't enough days to compare.
+fn run_diff() -> ! {
+    let (mut enterprise, mut team, mut store_path) = (None, None, None);
+    let (mut days, mut json) = (DEFAULT_DIFF_DAYS, false);
+    let mut args = std::env::args().skip(2);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--enterprise" => enterprise = Some(option_value("diff", &arg, args.next())),
+            "--team" => team = Some(option_value("diff", &arg, args.next())),
+            "--store" => store_path = Some(option_value("diff", &arg, args.next())),
+            "--days" => {
+                days = match option_value("diff", &arg, args.next()).parse() {
+                    Ok(days) if days > 0 => days,
+                    _ => {
+                        eprintln!("error: --days of ghrust diff needs a positive number");
+                        std::process::exit(2);
+                    }
+                }
+            }
+            "--json" => json = true,
+            other => {
+                eprintln!("error: unknown argument {} for ghrust diff", other);
+                std::process::exit(2);
+            }
+        }
+    }
+
+    // Reading the store needs no credentials at all
+    let stage = match store_path {
+        Some(_) => Stage::Local,
+        None => Stage::Fetch,
+    };
+    let config = stage_config(stage);
+    let enterprise_id = command_enterprise(&config, enterprise).id.clone();
+    let scope = match &team {
+        Some(slug) => format!("team:{}/{}", enterprise_id, slug),
+        None => format!("enterprise:{}", enterprise_id),
+    };
+
+    let metrics = match &store_path {
+        Some(path) => Store::open(Some(path), false, &config.agent)
+            .and_then(|store| store.backend().map_or(Ok(Vec::new()), |s| s.load(&scope)))
+            .unwrap_or_else(|e| {
+                eprintln!("error: could not read {} from {}: {}", scope, path, e);
+                std::process::exit(1);
+            }),
+        None => fetch_metrics(&config, &enterprise_id, team.as_deref()),
+    };
+
+    let Some(comparison) = PeriodComparison::from_metrics(&metrics, days) else {
+        eprintln!(
+            "error: {} has {} days of metrics, {} are needed to compare two periods",
+            scope,
+            metrics.len(),
+            2 * days
+        );
+        std::process::exit(1);
+    };
+    if json {
+        match serde_json::to_string_pretty(&comparison) {
+            Ok(json) => println!("{}", json),
             Err(e) => {
-                // Log error but continue execution to process team metrics
-                // This follows a partial success pattern instead of failing completely
-                println!("Error processing enterprise metrics: {}", e);
+                eprintln!("error: {}", e);
+                std::process::exit(1);
             }
         }
     } else {
-        println!("Skipping enterprise metrics due to SKIP_ENTERPRISE_METRICS flag");
-    }
-
-    // WORKFLOW STEP 2: Process team-specific metrics if team slugs are provided
-    // These metrics are scoped to individual teams for more granular reporting
-    if let Some(slugs) = team_slugs {
-        if !slugs.is_empty() {
-            match team::process_all_teams(
-                &github_token,
-                &enterprise_id,
-                &slugs,
-                &datadog_api_key,
-                &datadog_namespace,
-            ) {
-                Ok(_) => {
-                    println!(
-                        "Successfully processed team metrics for {} teams",
-                        slugs.len()
-                    );
-                }
+        println!(
+            "{}: {}..{} vs. {}..{}\n",
+            scope,
+            comparison.current.from,
+            comparison.current.to,
+            comparison.previous.from,
+            comparison.previous.to
+        );
+        print!("{}", comparison.to_table());
+    }
+    std::process::exit(0);
+}
+
+/// Runs `ghrust compare-teams` and exits
+///
+/// Fetches the metrics and member count of every configured team of the
+/// enterprise, ranks the teams `--by` a metric and prints the ranking as a
+/// table or, with `--json`, as JSON. Teams that fail to fetch are skipped.
+fn run_compare_teams() -> ! {
+    let (mut enterprise, mut rank_by, mut json) = (None, RankBy::default(), false);
+    let mut args = std::env::args().skip(2);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--enterprise" => enterprise = Some(option_value("compare-teams", &arg, args.next())),
+            "--by" => {
+                rank_by = option_value("compare-teams", &arg, args.next())
+                    .parse()
+                    .unwrap_or_else(|e: String| {
+                        eprintln!("error: {}", e);
+                        std::process::exit(2);
+                    })
+            }
+            "--json" => json = true,
+            other => {
+                eprintln!("error: unknown argument {} for ghrust compare-teams", other);
+                std::process::exit(2);
+            }
+        }
+    }
+
+    let config = stage_config(Stage::Fetch);
+    let enterprise = command_enterprise(&config, enterprise);
+    if enterprise.team_slugs.is_empty() {
+        eprintln!("error: no teams configured for {}", enterprise.id);
+        std::process::exit(1);
+    }
+    let client = config.github_client().build().unwrap_or_else(|e| {
+        eprintln!("error: {}", e);
+        std::process::exit(1);
+    });
+
+    let mut standings = Vec::new();
+    for slug in &enterprise.team_slugs {
+        let metrics = match get_team_metrics(&client, &enterprise.id, slug) {
+            Ok(metrics) => metrics,
+            Err(e) => {
+                warn!("Skipping team {}: {}", slug, e);
+                continue;
+            }
+        };
+        let members = config
+            .team_mappings
+            .get(slug)
+            .and_then(|m| m.org.as_deref())
+            .and_then(|org| match client.fetch_team_member_count(org, slug) {
+                Ok(count) => Some(count),
                 Err(e) => {
-                    println!("Error processing team metrics: {}", e);
+                    warn!("No member count for team {}: {}", slug, e);
+                    None
                 }
+            });
+        standings.extend(TeamStanding::from_metrics(slug, &metrics, members));
+    }
+
+    let ranking = TeamRanking::new(standings, rank_by);
+    if json {
+        match serde_json::to_string_pretty(&ranking) {
+            Ok(json) => println!("{}", json),
+            Err(e) => {
+                eprintln!("error: {}", e);
+                std::process::exit(1);
             }
-        } else {
-            println!("No team slugs provided, skipping team metrics");
         }
     } else {
-        println!("GITHUB_TEAM_SLUGS not set, skipping team metrics");
+        print!("{}", ranking.to_table());
     }
+    std::process::exit(0);
+}
 
-    // Return success response to Lambda runtime
-    // The workflow completes successfully even if some metrics processing failed
-    Ok(json!({
-        "statusCode": 200,
-        "message": "GitHub Copilot metrics processing completed"
-    }))
+/// Runs `ghrust backfill` and exits
+///
+/// Backfills every configured scope, or the `--enterprise` and `--team` ones,
+/// from `--since` to `--until` one day at a time, prints the report and exits
+/// with status 1 if any scope failed, so the backfill can be run again to
+/// resume from the checkpoint.
+fn run_backfill() -> ! {
+    let usage = || -> ! {
+        eprintln!(
+            "error: ghrust backfill needs --since <YYYY-MM-DD> --until <YYYY-MM-DD>, with --since not after --until"
+        );
+        std::process::exit(2);
+    };
+    let date = |value: Option<String>| {
+        value
+            .and_then(|value| NaiveDate::parse_from_str(&value, "%Y-%m-%d").ok())
+            .unwrap_or_else(|| usage())
+    };
+
+    let (mut since, mut until, mut day_delay) = (None, None, None);
+    let mut scope = RunScope::default();
+    let mut checkpoint = DEFAULT_BACKFILL_CHECKPOINT.to_string();
+    let mut args = std::env::args().skip(2);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--since" => since = Some(date(args.next())),
+            "--until" => until = Some(date(args.next())),
+            "--enterprise" => {
+                scope.enterprise_id = Some(option_value("backfill", &arg, args.next()))
+            }
+            "--team" => scope.team_slug = Some(option_value("backfill", &arg, args.next())),
+            "--checkpoint" => checkpoint = option_value("backfill", &arg, args.next()),
+            "--day-delay-ms" => {
+                day_delay = match option_value("backfill", &arg, args.next()).parse() {
+                    Ok(ms) => Some(Duration::from_millis(ms)),
+                    Err(_) => {
+                        eprintln!("error: --day-delay-ms of ghrust backfill needs a number");
+                        std::process::exit(2);
+                    }
+                }
+            }
+            "--refresh-teams" => scope.refresh_teams = true,
+            other => {
+                eprintln!("error: unknown argument {} for ghrust backfill", other);
+                std::process::exit(2);
+            }
+        }
+    }
+    let (Some(since), Some(until)) = (since, until) else {
+        usage()
+    };
+    if since > until {
+        usage();
+    }
+    let mut options = BackfillOptions::new(since, until);
+    if let Some(day_delay) = day_delay {
+        options = options.with_day_delay(day_delay);
+    }
+
+    let config = stage_config(Stage::Full);
+    let report = MetricsPipeline::new(config)
+        .with_scope(scope)
+        .and_then(|pipeline| pipeline.run_backfill(options, Path::new(&checkpoint)));
+    match report {
+        Ok(report) => {
+            match serde_json::to_string_pretty(&report) {
+                Ok(json) => println!("{}", json),
+                Err(e) => eprintln!("error: {}", e),
+            }
+            if report.has_failures() {
+                eprintln!(
+                    "Backfill incomplete, run again to resume from {}: {}",
+                    checkpoint,
+                    report.failures.join("; ")
+                );
+                std::process::exit(1);
+            }
+            std::process::exit(0);
+        }
+        Err(e) => {
+            eprintln!("error: {}", e);
+            std::process::exit(1);
+        }
+    }
 }
 
 /// Initializes the Lambda runtime and starts the service
@@ -148,12 +760,30 @@ async fn function_handler(_event: LambdaEvent<Value>) -> Result<Value, Error> {
 #[tokio::main]
 async fn main() -> Result<(), Error> {
     // Initialize tracing for better observability in AWS Lambda environment
-    // This configures the logging format and level
-    tracing_subscriber::fmt()
-        .with_max_level(tracing::Level::INFO)
+    // This configures the logging format and the RUST_LOG/LOG_LEVEL filter.
+    // Commands log to stderr, keeping their output on stdout clean.
+    let command = std::env::args().nth(1);
+    let logging = tracing_subscriber::fmt()
+        .with_env_filter(log_filter())
         .with_target(false)
-        .without_time()
-        .init();
+        .without_time();
+    match command {
+        Some(_) => logging.with_writer(std::io::stderr).init(),
+        None => logging.init(),
+    }
+
+    match command.as_deref() {
+        Some("validate") => validate_config(),
+        Some("doctor") => run_doctor(),
+        Some("fetch") => run_fetch(),
+        Some("send") => run_send(),
+        Some("replay") => run_replay(),
+        Some("report") => run_report(),
+        Some("diff") => run_diff(),
+        Some("compare-teams") => run_compare_teams(),
+        Some("backfill") => run_backfill(),
+        _ => {}
+    }
 
     // Start the Lambda runtime with our handler function
     // This creates an event loop that processes incoming Lambda events
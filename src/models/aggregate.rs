@@ -0,0 +1,234 @@
+//! # Metrics Aggregation
+//!
+//! Adds Copilot metrics of several scopes together, e.g. the metrics of all
+//! teams into a rollup, or long-tail breakdown entries into `other`.
+//!
+//! Counters are summed, and breakdowns (languages, editors, models and
+//! repositories) are merged by name. User counts are summed as well, so a user
+//! who belongs to several aggregated scopes is counted once per scope; the sums
+//! are upper bounds of the unique users.
+
+use std::collections::BTreeMap;
+
+use super::github::{
+    CodeReviewRepository, CopilotCodeReview, CopilotDotcomChat, CopilotDotcomPullRequests,
+    CopilotIdeChat, CopilotIdeCodeCompletions, CopilotMetrics, Editor, EditorVersion, Language,
+    Model, Repository,
+};
+
+/// Adds the counters of another value of the same type to this one
+pub trait Absorb {
+    /// Adds `other` to `self`
+    fn absorb(&mut self, other: Self);
+}
+
+/// A named entry of a breakdown, merged with entries of the same name
+pub trait Named {
+    /// Name of the entry, e.g. the language or editor
+    fn name(&self) -> &str;
+}
+
+/// Sums metrics days of several scopes into one day per date
+///
+/// # Arguments
+///
+/// * `metrics` - Days of any number of scopes, in any order
+///
+/// # Returns
+///
+/// * `Vec<CopilotMetrics>` - One summed day per date, oldest first
+pub fn sum_by_date(metrics: impl IntoIterator<Item = CopilotMetrics>) -> Vec<CopilotMetrics> {
+    let mut days: BTreeMap<String, CopilotMetrics> = BTreeMap::new();
+    for metric in metrics {
+        match days.get_mut(&metric.date) {
+            Some(day) => day.absorb(metric),
+            None => {
+                days.insert(metric.date.clone(), metric);
+            }
+        }
+    }
+    days.into_values().collect()
+}
+
+/// Merges a breakdown into another, combining entries by name
+pub fn merge_by_name<T: Named + Absorb>(target: &mut Vec<T>, source: Vec<T>) {
+    for entry in source {
+        match target.iter_mut().find(|t| t.name() == entry.name()) {
+            Some(existing) => existing.absorb(entry),
+            None => target.push(entry),
+        }
+    }
+}
+
+impl<T: Named + Absorb> Absorb for Vec<T> {
+    fn absorb(&mut self, other: Self) {
+        merge_by_name(self, other);
+    }
+}
+
+impl<T: Absorb> Absorb for Option<T> {
+    fn absorb(&mut self, other: Self) {
+        match (self.as_mut(), other) {
+            (Some(target), Some(other)) => target.absorb(other),
+            (None, Some(other)) => *self = Some(other),
+            (_, None) => {}
+        }
+    }
+}
+
+impl Absorb for i64 {
+    fn absorb(&mut self, other: Self) {
+        *self += other;
+    }
+}
+
+impl Absorb for CopilotMetrics {
+    fn absorb(&mut self, other: Self) {
+        self.total_active_users.absorb(other.total_active_users);
+        self.total_engaged_users.absorb(other.total_engaged_users);
+        self.copilot_ide_code_completions
+            .absorb(other.copilot_ide_code_completions);
+        self.copilot_ide_chat.absorb(other.copilot_ide_chat);
+        self.copilot_dotcom_chat.absorb(other.copilot_dotcom_chat);
+        self.copilot_dotcom_pull_requests
+            .absorb(other.copilot_dotcom_pull_requests);
+        self.copilot_code_review.absorb(other.copilot_code_review);
+    }
+}
+
+impl Absorb for CopilotIdeCodeCompletions {
+    fn absorb(&mut self, other: Self) {
+        self.total_engaged_users += other.total_engaged_users;
+        self.languages.absorb(other.languages);
+        self.editors.absorb(other.editors);
+    }
+}
+
+impl Absorb for CopilotIdeChat {
+    fn absorb(&mut self, other: Self) {
+        self.total_engaged_users += other.total_engaged_users;
+        self.editors.absorb(other.editors);
+    }
+}
+
+impl Absorb for CopilotDotcomChat {
+    fn absorb(&mut self, other: Self) {
+        self.total_engaged_users += other.total_engaged_users;
+        self.models.absorb(other.models);
+    }
+}
+
+impl Absorb for CopilotDotcomPullRequests {
+    fn absorb(&mut self, other: Self) {
+        self.total_engaged_users += other.total_engaged_users;
+        self.repositories.absorb(other.repositories);
+    }
+}
+
+impl Absorb for CopilotCodeReview {
+    fn absorb(&mut self, other: Self) {
+        self.total_engaged_users += other.total_engaged_users;
+        self.total_reviews.absorb(other.total_reviews);
+        self.total_review_comments
+            .absorb(other.total_review_comments);
+        self.total_review_comments_applied
+            .absorb(other.total_review_comments_applied);
+        self.repositories.absorb(other.repositories);
+    }
+}
+
+impl Named for CodeReviewRepository {
+    fn name(&self) -> &str {
+        &self.name
+    }
+}
+
+impl Absorb for CodeReviewRepository {
+    fn absorb(&mut self, other: Self) {
+        self.total_engaged_users += other.total_engaged_users;
+        self.total_reviews.absorb(other.total_reviews);
+        self.total_review_comments
+            .absorb(other.total_review_comments);
+        self.total_review_comments_applied
+            .absorb(other.total_review_comments_applied);
+    }
+}
+
+impl Named for Repository {
+    fn name(&self) -> &str {
+        &self.name
+    }
+}
+
+impl Absorb for Repository {
+    fn absorb(&mut self, other: Self) {
+        self.total_engaged_users += other.total_engaged_users;
+        self.models.absorb(other.models);
+    }
+}
+
+impl Named for Editor {
+    fn name(&self) -> &str {
+        &self.name
+    }
+}
+
+impl Absorb for Editor {
+    fn absorb(&mut self, other: Self) {
+        self.total_engaged_users += other.total_engaged_users;
+        self.models.absorb(other.models);
+        self.versions.absorb(other.versions);
+    }
+}
+
+impl Named for EditorVersion {
+    fn name(&self) -> &str {
+        &self.version
+    }
+}
+
+impl Absorb for EditorVersion {
+    fn absorb(&mut self, other: Self) {
+        self.total_engaged_users += other.total_engaged_users;
+    }
+}
+
+impl Named for Model {
+    fn name(&self) -> &str {
+        &self.name
+    }
+}
+
+impl Absorb for Model {
+    fn absorb(&mut self, other: Self) {
+        self.total_engaged_users += other.total_engaged_users;
+        self.languages.absorb(other.languages);
+        self.total_chats.absorb(other.total_chats);
+        self.total_chat_insertion_events
+            .absorb(other.total_chat_insertion_events);
+        self.total_chat_copy_events
+            .absorb(other.total_chat_copy_events);
+        self.total_pr_summaries_created
+            .absorb(other.total_pr_summaries_created);
+    }
+}
+
+impl Named for Language {
+    fn name(&self) -> &str {
+        &self.name
+    }
+}
+
+impl Absorb for Language {
+    fn absorb(&mut self, other: Self) {
+        self.total_engaged_users += other.total_engaged_users;
+        self.total_code_suggestions
+            .absorb(other.total_code_suggestions);
+        self.total_code_acceptances
+            .absorb(other.total_code_acceptances);
+        self.total_code_lines_suggested
+            .absorb(other.total_code_lines_suggested);
+        self.total_code_lines_accepted
+            .absorb(other.total_code_lines_accepted);
+    }
+}
@@ -0,0 +1,151 @@
+//! # GitHub Billing Models
+//!
+//! This module defines the data structures returned by GitHub's billing APIs
+//! that are relevant to Copilot consumption tracking.
+//!
+//! The primary structures include:
+//! - `PremiumRequestUsage`: A premium request usage report for a billing period
+//! - `PremiumRequestUsageItem`: Request counts and amounts for one model/SKU
+//! - `CopilotSeat`: A Copilot seat assignment with the seat holder's last activity
+//! - `CopilotBilling`: An organization's Copilot billing settings and seat breakdown
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use super::github::ExtraFields;
+
+/// The billing period a usage report covers
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct TimePeriod {
+    pub year: i32,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub month: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub day: Option<u32>,
+}
+
+impl TimePeriod {
+    /// Formats the period as `YYYY`, `YYYY-MM` or `YYYY-MM-DD`
+    pub fn label(&self) -> String {
+        match (self.month, self.day) {
+            (Some(month), Some(day)) => format!("{:04}-{:02}-{:02}", self.year, month, day),
+            (Some(month), None) => format!("{:04}-{:02}", self.year, month),
+            _ => format!("{:04}", self.year),
+        }
+    }
+}
+
+/// Premium request consumption for a single model and SKU
+///
+/// Premium models consume requests at a model-specific multiplier (e.g. a model
+/// with a multiplier of 10 counts every request as ten premium requests).
+/// Quantities are expressed in premium requests after the multiplier is applied.
+#[derive(Debug, Deserialize, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct PremiumRequestUsageItem {
+    pub product: String,
+    pub sku: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub model: Option<String>,
+    pub unit_type: String,
+    #[serde(default)]
+    pub price_per_unit: f64,
+    #[serde(default)]
+    pub gross_quantity: f64,
+    #[serde(default)]
+    pub gross_amount: f64,
+    #[serde(default)]
+    pub discount_quantity: f64,
+    #[serde(default)]
+    pub discount_amount: f64,
+    #[serde(default)]
+    pub net_quantity: f64,
+    #[serde(default)]
+    pub net_amount: f64,
+    /// Request multiplier of the model, when reported by the API
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub multiplier: Option<f64>,
+    #[serde(flatten)]
+    pub extra: ExtraFields,
+}
+
+/// A premium request usage report for a billing period
+#[derive(Debug, Deserialize, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct PremiumRequestUsage {
+    pub time_period: TimePeriod,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub enterprise: Option<String>,
+    #[serde(default)]
+    pub usage_items: Vec<PremiumRequestUsageItem>,
+    #[serde(flatten)]
+    pub extra: ExtraFields,
+}
+
+/// The user a Copilot seat is assigned to
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct SeatAssignee {
+    pub login: String,
+    #[serde(flatten)]
+    pub extra: ExtraFields,
+}
+
+/// A Copilot seat assignment
+///
+/// `last_activity_at` is the most recent Copilot activity of the seat holder,
+/// or `None` if the seat was never used.
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct CopilotSeat {
+    pub assignee: SeatAssignee,
+    pub created_at: DateTime<Utc>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub last_activity_at: Option<DateTime<Utc>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub last_activity_editor: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pending_cancellation_date: Option<String>,
+    #[serde(flatten)]
+    pub extra: ExtraFields,
+}
+
+/// A page of the seat assignments of an enterprise
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct CopilotSeats {
+    pub total_seats: usize,
+    #[serde(default)]
+    pub seats: Vec<CopilotSeat>,
+}
+
+/// Seats of an organization by their state in the current billing cycle
+#[derive(Debug, Deserialize, Serialize, Clone, Default, PartialEq)]
+pub struct SeatBreakdown {
+    /// Seats billed in the current cycle
+    #[serde(default)]
+    pub total: i64,
+    /// Seats added in the current cycle
+    #[serde(default)]
+    pub added_this_cycle: i64,
+    /// Seats that are cancelled at the end of the cycle
+    #[serde(default)]
+    pub pending_cancellation: i64,
+    /// Seats assigned to users who haven't accepted their invitation yet
+    #[serde(default)]
+    pub pending_invitation: i64,
+    /// Seats used in the current cycle
+    #[serde(default)]
+    pub active_this_cycle: i64,
+    /// Seats not used in the current cycle
+    #[serde(default)]
+    pub inactive_this_cycle: i64,
+}
+
+/// Copilot billing information of an organization
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct CopilotBilling {
+    #[serde(default)]
+    pub seat_breakdown: SeatBreakdown,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub seat_management_setting: Option<String>,
+    #[serde(flatten)]
+    pub extra: ExtraFields,
+}
@@ -1,93 +1,277 @@
 // Generated by Github Copilot
+//! # GitHub Data Models
+//!
+//! This module defines the data structures that represent GitHub Copilot metrics.
+//!
+//! The primary structures include:
+//! - `CopilotMetrics`: The top-level container for all metrics
+//! - `CopilotIdeCodeCompletions`: Metrics for IDE code completions
+//! - `CopilotIdeChat`: Metrics for IDE chat interactions
+//! - `CopilotDotcomChat`: Metrics for GitHub.com chat interactions
+//! - `CopilotDotcomPullRequests`: Metrics for GitHub.com pull request interactions
+//! - `CopilotCodeReview`: Metrics for Copilot code review on GitHub.com
+
+use std::collections::BTreeSet;
+
 use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+
+use super::lenient;
+
+/// Fields present in an API response that the models do not know about
+///
+/// GitHub regularly adds new fields to the Copilot metrics API. Rather than
+/// silently dropping them, every model keeps unrecognized fields in a flattened
+/// map so they survive a serialization round-trip and can be reported.
+pub type ExtraFields = Map<String, Value>;
 
 #[derive(Debug, Deserialize, Serialize, Clone)]
 pub struct Language {
+    #[serde(default, deserialize_with = "lenient::string")]
     pub name: String,
+    #[serde(default, deserialize_with = "lenient::count")]
     pub total_engaged_users: i64,
-    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(
+        default,
+        deserialize_with = "lenient::optional_count",
+        skip_serializing_if = "Option::is_none"
+    )]
     pub total_code_suggestions: Option<i64>,
-    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(
+        default,
+        deserialize_with = "lenient::optional_count",
+        skip_serializing_if = "Option::is_none"
+    )]
     pub total_code_acceptances: Option<i64>,
-    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(
+        default,
+        deserialize_with = "lenient::optional_count",
+        skip_serializing_if = "Option::is_none"
+    )]
     pub total_code_lines_suggested: Option<i64>,
-    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(
+        default,
+        deserialize_with = "lenient::optional_count",
+        skip_serializing_if = "Option::is_none"
+    )]
     pub total_code_lines_accepted: Option<i64>,
+    #[serde(flatten)]
+    pub extra: ExtraFields,
 }
 
 #[derive(Debug, Deserialize, Serialize, Clone)]
 pub struct Model {
+    #[serde(default, deserialize_with = "lenient::string")]
     pub name: String,
+    #[serde(default, deserialize_with = "lenient::flag")]
     pub is_custom_model: bool,
+    #[serde(default, deserialize_with = "lenient::optional_string")]
     pub custom_model_training_date: Option<String>,
+    #[serde(default, deserialize_with = "lenient::count")]
     pub total_engaged_users: i64,
-    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(
+        default,
+        deserialize_with = "lenient::optional_breakdown",
+        skip_serializing_if = "Option::is_none"
+    )]
     pub languages: Option<Vec<Language>>,
-    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(
+        default,
+        deserialize_with = "lenient::optional_count",
+        skip_serializing_if = "Option::is_none"
+    )]
     pub total_chats: Option<i64>,
-    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(
+        default,
+        deserialize_with = "lenient::optional_count",
+        skip_serializing_if = "Option::is_none"
+    )]
     pub total_chat_insertion_events: Option<i64>,
-    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(
+        default,
+        deserialize_with = "lenient::optional_count",
+        skip_serializing_if = "Option::is_none"
+    )]
     pub total_chat_copy_events: Option<i64>,
-    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(
+        default,
+        deserialize_with = "lenient::optional_count",
+        skip_serializing_if = "Option::is_none"
+    )]
     pub total_pr_summaries_created: Option<i64>,
+    #[serde(flatten)]
+    pub extra: ExtraFields,
+}
+
+/// Engaged users of one client version of an editor's Copilot plugin
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct EditorVersion {
+    #[serde(default, deserialize_with = "lenient::string")]
+    pub version: String,
+    #[serde(default, deserialize_with = "lenient::count")]
+    pub total_engaged_users: i64,
+    #[serde(flatten)]
+    pub extra: ExtraFields,
 }
 
 #[derive(Debug, Deserialize, Serialize, Clone)]
 pub struct Editor {
+    #[serde(default, deserialize_with = "lenient::string")]
     pub name: String,
+    #[serde(default, deserialize_with = "lenient::count")]
     pub total_engaged_users: i64,
-    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(
+        default,
+        deserialize_with = "lenient::optional_breakdown",
+        skip_serializing_if = "Option::is_none"
+    )]
     pub models: Option<Vec<Model>>,
+    /// Breakdown by client version, only present in newer responses
+    #[serde(
+        default,
+        deserialize_with = "lenient::optional_breakdown",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub versions: Option<Vec<EditorVersion>>,
+    #[serde(flatten)]
+    pub extra: ExtraFields,
 }
 
 #[derive(Debug, Deserialize, Serialize, Clone)]
 pub struct Repository {
+    #[serde(default, deserialize_with = "lenient::string")]
     pub name: String,
+    #[serde(default, deserialize_with = "lenient::count")]
     pub total_engaged_users: i64,
+    #[serde(default, deserialize_with = "lenient::breakdown")]
     pub models: Vec<Model>,
+    #[serde(flatten)]
+    pub extra: ExtraFields,
 }
 
 #[derive(Debug, Deserialize, Serialize, Clone)]
 pub struct CopilotIdeCodeCompletions {
+    #[serde(default, deserialize_with = "lenient::count")]
     pub total_engaged_users: i64,
-    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(
+        default,
+        deserialize_with = "lenient::optional_breakdown",
+        skip_serializing_if = "Option::is_none"
+    )]
     pub languages: Option<Vec<Language>>,
-    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(
+        default,
+        deserialize_with = "lenient::optional_breakdown",
+        skip_serializing_if = "Option::is_none"
+    )]
     pub editors: Option<Vec<Editor>>,
+    #[serde(flatten)]
+    pub extra: ExtraFields,
 }
 
 #[derive(Debug, Deserialize, Serialize, Clone)]
 pub struct CopilotIdeChat {
+    #[serde(default, deserialize_with = "lenient::count")]
     pub total_engaged_users: i64,
-    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(
+        default,
+        deserialize_with = "lenient::optional_breakdown",
+        skip_serializing_if = "Option::is_none"
+    )]
     pub editors: Option<Vec<Editor>>,
+    #[serde(flatten)]
+    pub extra: ExtraFields,
 }
 
 #[derive(Debug, Deserialize, Serialize, Clone)]
 pub struct CopilotDotcomChat {
+    #[serde(default, deserialize_with = "lenient::count")]
     pub total_engaged_users: i64,
-    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(
+        default,
+        deserialize_with = "lenient::optional_breakdown",
+        skip_serializing_if = "Option::is_none"
+    )]
     pub models: Option<Vec<Model>>,
+    #[serde(flatten)]
+    pub extra: ExtraFields,
 }
 
 #[derive(Debug, Deserialize, Serialize, Clone)]
 pub struct CopilotDotcomPullRequests {
+    #[serde(default, deserialize_with = "lenient::count")]
     pub total_engaged_users: i64,
-    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(
+        default,
+        deserialize_with = "lenient::optional_breakdown",
+        skip_serializing_if = "Option::is_none"
+    )]
     pub repositories: Option<Vec<Repository>>,
+    #[serde(flatten)]
+    pub extra: ExtraFields,
 }
 
-/// GitHub Data Models
-///
-/// This module defines the data structures that represent GitHub Copilot metrics.
-///
-/// The primary structures include:
-/// - `CopilotMetrics`: The top-level container for all metrics
-/// - `CopilotIdeCodeCompletions`: Metrics for IDE code completions
-/// - `CopilotIdeChat`: Metrics for IDE chat interactions
-/// - `CopilotDotcomChat`: Metrics for GitHub.com chat interactions
-/// - `CopilotDotcomPullRequests`: Metrics for GitHub.com pull request interactions
+/// Per-repository Copilot code review activity
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct CodeReviewRepository {
+    #[serde(default, deserialize_with = "lenient::string")]
+    pub name: String,
+    #[serde(default, deserialize_with = "lenient::count")]
+    pub total_engaged_users: i64,
+    #[serde(
+        default,
+        deserialize_with = "lenient::optional_count",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub total_reviews: Option<i64>,
+    #[serde(
+        default,
+        deserialize_with = "lenient::optional_count",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub total_review_comments: Option<i64>,
+    #[serde(
+        default,
+        deserialize_with = "lenient::optional_count",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub total_review_comments_applied: Option<i64>,
+    #[serde(flatten)]
+    pub extra: ExtraFields,
+}
+
+/// Copilot code review usage on GitHub.com pull requests
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct CopilotCodeReview {
+    #[serde(default, deserialize_with = "lenient::count")]
+    pub total_engaged_users: i64,
+    #[serde(
+        default,
+        deserialize_with = "lenient::optional_count",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub total_reviews: Option<i64>,
+    #[serde(
+        default,
+        deserialize_with = "lenient::optional_count",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub total_review_comments: Option<i64>,
+    #[serde(
+        default,
+        deserialize_with = "lenient::optional_count",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub total_review_comments_applied: Option<i64>,
+    #[serde(
+        default,
+        deserialize_with = "lenient::optional_breakdown",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub repositories: Option<Vec<CodeReviewRepository>>,
+    #[serde(flatten)]
+    pub extra: ExtraFields,
+}
 
 /// Represents a complete set of GitHub Copilot metrics
 ///
@@ -99,22 +283,142 @@ pub struct CopilotMetrics {
     pub date: String,
 
     /// Total number of users who have activated Copilot
+    #[serde(default, deserialize_with = "lenient::optional_count")]
     pub total_active_users: Option<i64>,
 
     /// Total number of users who have used Copilot
+    #[serde(default, deserialize_with = "lenient::optional_count")]
     pub total_engaged_users: Option<i64>,
 
     /// Metrics related to code completions in IDEs
+    #[serde(default, deserialize_with = "lenient::optional")]
     pub copilot_ide_code_completions: Option<CopilotIdeCodeCompletions>,
 
     /// Metrics related to chat in IDEs
+    #[serde(default, deserialize_with = "lenient::optional")]
     pub copilot_ide_chat: Option<CopilotIdeChat>,
 
     /// Metrics related to chat on GitHub.com
+    #[serde(default, deserialize_with = "lenient::optional")]
     pub copilot_dotcom_chat: Option<CopilotDotcomChat>,
 
     /// Metrics related to pull requests on GitHub.com
+    #[serde(default, deserialize_with = "lenient::optional")]
     pub copilot_dotcom_pull_requests: Option<CopilotDotcomPullRequests>,
+
+    /// Metrics related to Copilot code review on GitHub.com
+    #[serde(
+        default,
+        deserialize_with = "lenient::optional",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub copilot_code_review: Option<CopilotCodeReview>,
+
+    /// Fields returned by the API that are not (yet) modelled explicitly
+    #[serde(flatten)]
+    pub extra: ExtraFields,
+}
+
+impl CopilotMetrics {
+    /// Lists the paths of all unknown fields found in this data point
+    ///
+    /// Paths use dot notation with `[]` marking array elements, e.g.
+    /// `copilot_ide_chat.editors[].models[].new_field`. Duplicates across
+    /// array elements are collapsed, and the result is sorted.
+    ///
+    /// # Returns
+    ///
+    /// A sorted list of unique unknown field paths; empty when the response
+    /// matches the models exactly
+    pub fn unknown_field_paths(&self) -> Vec<String> {
+        let mut paths = BTreeSet::new();
+        collect_extra("", &self.extra, &mut paths);
+
+        if let Some(completions) = &self.copilot_ide_code_completions {
+            let prefix = "copilot_ide_code_completions";
+            collect_extra(prefix, &completions.extra, &mut paths);
+            for language in completions.languages.iter().flatten() {
+                collect_language(&format!("{}.languages[]", prefix), language, &mut paths);
+            }
+            for editor in completions.editors.iter().flatten() {
+                collect_editor(&format!("{}.editors[]", prefix), editor, &mut paths);
+            }
+        }
+
+        if let Some(chat) = &self.copilot_ide_chat {
+            let prefix = "copilot_ide_chat";
+            collect_extra(prefix, &chat.extra, &mut paths);
+            for editor in chat.editors.iter().flatten() {
+                collect_editor(&format!("{}.editors[]", prefix), editor, &mut paths);
+            }
+        }
+
+        if let Some(chat) = &self.copilot_dotcom_chat {
+            let prefix = "copilot_dotcom_chat";
+            collect_extra(prefix, &chat.extra, &mut paths);
+            for model in chat.models.iter().flatten() {
+                collect_model(&format!("{}.models[]", prefix), model, &mut paths);
+            }
+        }
+
+        if let Some(prs) = &self.copilot_dotcom_pull_requests {
+            let prefix = "copilot_dotcom_pull_requests";
+            collect_extra(prefix, &prs.extra, &mut paths);
+            for repo in prs.repositories.iter().flatten() {
+                let repo_prefix = format!("{}.repositories[]", prefix);
+                collect_extra(&repo_prefix, &repo.extra, &mut paths);
+                for model in &repo.models {
+                    collect_model(&format!("{}.models[]", repo_prefix), model, &mut paths);
+                }
+            }
+        }
+
+        if let Some(review) = &self.copilot_code_review {
+            let prefix = "copilot_code_review";
+            collect_extra(prefix, &review.extra, &mut paths);
+            for repo in review.repositories.iter().flatten() {
+                collect_extra(
+                    &format!("{}.repositories[]", prefix),
+                    &repo.extra,
+                    &mut paths,
+                );
+            }
+        }
+
+        paths.into_iter().collect()
+    }
+}
+
+/// Adds the keys of an `extra` map to the set of unknown paths
+fn collect_extra(prefix: &str, extra: &ExtraFields, paths: &mut BTreeSet<String>) {
+    for key in extra.keys() {
+        if prefix.is_empty() {
+            paths.insert(key.clone());
+        } else {
+            paths.insert(format!("{}.{}", prefix, key));
+        }
+    }
+}
+
+/// Collects unknown fields of a language entry
+fn collect_language(prefix: &str, language: &Language, paths: &mut BTreeSet<String>) {
+    collect_extra(prefix, &language.extra, paths);
+}
+
+/// Collects unknown fields of an editor entry and its models
+fn collect_editor(prefix: &str, editor: &Editor, paths: &mut BTreeSet<String>) {
+    collect_extra(prefix, &editor.extra, paths);
+    for model in editor.models.iter().flatten() {
+        collect_model(&format!("{}.models[]", prefix), model, paths);
+    }
+}
+
+/// Collects unknown fields of a model entry and its languages
+fn collect_model(prefix: &str, model: &Model, paths: &mut BTreeSet<String>) {
+    collect_extra(prefix, &model.extra, paths);
+    for language in model.languages.iter().flatten() {
+        collect_language(&format!("{}.languages[]", prefix), language, paths);
+    }
 }
 
 // Generated Code by Github Copilot ends here
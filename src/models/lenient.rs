@@ -0,0 +1,239 @@
+//! # Lenient Deserialization
+//!
+//! Real API responses occasionally contain `null` where the models expect a
+//! number or a list, a count sent as a string or float, or a breakdown entry
+//! of an unexpected shape. Failing the whole response on such a value would
+//! lose every other day and breakdown of the scope, so the models read their
+//! fields through these functions instead:
+//!
+//! * counts fall back to `0` (or `None` if optional) and accept integers,
+//!   floats (truncated) and numeric strings
+//! * a list that is `null` or not a list is read as empty (or `None`), and an
+//!   entry that can't be read is dropped
+//! * a breakdown entry (a language, editor, model, version or repository)
+//!   whose name is missing, `null` or empty is dropped, since it would be
+//!   reported under an empty tag value such as `language:`
+//! * a section of the wrong shape is read as absent
+//!
+//! Every value that is replaced or dropped is logged as a warning, so the
+//! degraded data can be traced back to the response.
+
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Deserializer};
+use serde_json::Value;
+use tracing::warn;
+
+use super::aggregate::Named;
+
+/// Describes a JSON value for warnings without logging its full contents
+fn describe(value: &Value) -> String {
+    match value {
+        Value::Null => "null".to_string(),
+        Value::Bool(b) => b.to_string(),
+        Value::Number(n) => n.to_string(),
+        Value::String(s) => format!("{:?}", s),
+        Value::Array(items) => format!("a list of {} entries", items.len()),
+        Value::Object(_) => "an object".to_string(),
+    }
+}
+
+/// Reads a JSON value as a count, if it represents one
+fn as_count(value: &Value) -> Option<i64> {
+    match value {
+        Value::Number(n) => n
+            .as_i64()
+            .or_else(|| n.as_u64().map(|_| i64::MAX))
+            .or_else(|| n.as_f64().filter(|f| f.is_finite()).map(|f| f as i64)),
+        Value::String(s) => {
+            let s = s.trim();
+            s.parse::<i64>().ok().or_else(|| {
+                s.parse::<f64>()
+                    .ok()
+                    .filter(|f| f.is_finite())
+                    .map(|f| f as i64)
+            })
+        }
+        _ => None,
+    }
+}
+
+/// Reads a count, falling back to `0` for `null` and unexpected values
+pub fn count<'de, D: Deserializer<'de>>(deserializer: D) -> Result<i64, D::Error> {
+    let value = Value::deserialize(deserializer)?;
+    Ok(as_count(&value).unwrap_or_else(|| {
+        warn!("Expected a count, got {}; using 0", describe(&value));
+        0
+    }))
+}
+
+/// Reads an optional count, falling back to `None` for unexpected values
+pub fn optional_count<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Option<i64>, D::Error> {
+    let value = Value::deserialize(deserializer)?;
+    if value.is_null() {
+        return Ok(None);
+    }
+    let count = as_count(&value);
+    if count.is_none() {
+        warn!("Expected a count, got {}; ignoring it", describe(&value));
+    }
+    Ok(count)
+}
+
+/// Reads a name, falling back to an empty string for `null` and reading
+/// numbers and booleans as text
+pub fn string<'de, D: Deserializer<'de>>(deserializer: D) -> Result<String, D::Error> {
+    let value = Value::deserialize(deserializer)?;
+    Ok(match value {
+        Value::String(s) => s,
+        Value::Number(n) => n.to_string(),
+        Value::Bool(b) => b.to_string(),
+        other => {
+            warn!(
+                "Expected a name, got {}; using an empty name",
+                describe(&other)
+            );
+            String::new()
+        }
+    })
+}
+
+/// Reads an optional string, falling back to `None` for unexpected values
+pub fn optional_string<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<Option<String>, D::Error> {
+    let value = Value::deserialize(deserializer)?;
+    Ok(match value {
+        Value::Null => None,
+        Value::String(s) => Some(s),
+        other => {
+            warn!("Expected a string, got {}; ignoring it", describe(&other));
+            None
+        }
+    })
+}
+
+/// Reads a flag, falling back to `false` for `null` and unexpected values
+pub fn flag<'de, D: Deserializer<'de>>(deserializer: D) -> Result<bool, D::Error> {
+    let value = Value::deserialize(deserializer)?;
+    Ok(match value {
+        Value::Bool(b) => b,
+        Value::String(s) if s.eq_ignore_ascii_case("true") => true,
+        Value::String(s) if s.eq_ignore_ascii_case("false") => false,
+        other => {
+            warn!(
+                "Expected true or false, got {}; using false",
+                describe(&other)
+            );
+            false
+        }
+    })
+}
+
+/// Reads an optional section, falling back to `None` if it has an
+/// unexpected shape
+pub fn optional<'de, D, T>(deserializer: D) -> Result<Option<T>, D::Error>
+where
+    D: Deserializer<'de>,
+    T: DeserializeOwned,
+{
+    let value = Value::deserialize(deserializer)?;
+    if value.is_null() {
+        return Ok(None);
+    }
+    let described = describe(&value);
+    match serde_json::from_value(value) {
+        Ok(section) => Ok(Some(section)),
+        Err(e) => {
+            warn!("Ignoring unreadable section ({}): {}", described, e);
+            Ok(None)
+        }
+    }
+}
+
+/// Reads an optional list, dropping entries that can't be read
+///
+/// `null` is read as `None`; any other value that isn't a list is read as
+/// `None` with a warning.
+pub fn optional_list<'de, D, T>(deserializer: D) -> Result<Option<Vec<T>>, D::Error>
+where
+    D: Deserializer<'de>,
+    T: DeserializeOwned,
+{
+    let value = Value::deserialize(deserializer)?;
+    Ok(match value {
+        Value::Null => None,
+        Value::Array(items) => Some(entries(items)),
+        other => {
+            warn!("Expected a list, got {}; ignoring it", describe(&other));
+            None
+        }
+    })
+}
+
+/// Reads a list, reading `null` and other values as empty and dropping
+/// entries that can't be read
+pub fn list<'de, D, T>(deserializer: D) -> Result<Vec<T>, D::Error>
+where
+    D: Deserializer<'de>,
+    T: DeserializeOwned,
+{
+    let value = Value::deserialize(deserializer)?;
+    Ok(match value {
+        Value::Array(items) => entries(items),
+        Value::Null => Vec::new(),
+        other => {
+            warn!(
+                "Expected a list, got {}; using an empty list",
+                describe(&other)
+            );
+            Vec::new()
+        }
+    })
+}
+
+/// Reads the entries of a list, dropping the ones that can't be read
+fn entries<T: DeserializeOwned>(items: Vec<Value>) -> Vec<T> {
+    items
+        .into_iter()
+        .filter_map(|item| {
+            let described = describe(&item);
+            serde_json::from_value(item)
+                .map_err(|e| warn!("Dropping unreadable list entry ({}): {}", described, e))
+                .ok()
+        })
+        .collect()
+}
+
+/// Reads an optional breakdown list like `optional_list`, also dropping the
+/// entries without a name
+pub fn optional_breakdown<'de, D, T>(deserializer: D) -> Result<Option<Vec<T>>, D::Error>
+where
+    D: Deserializer<'de>,
+    T: DeserializeOwned + Named,
+{
+    Ok(optional_list(deserializer)?.map(named))
+}
+
+/// Reads a breakdown list like `list`, also dropping the entries without a
+/// name
+pub fn breakdown<'de, D, T>(deserializer: D) -> Result<Vec<T>, D::Error>
+where
+    D: Deserializer<'de>,
+    T: DeserializeOwned + Named,
+{
+    Ok(named(list(deserializer)?))
+}
+
+/// Drops the breakdown entries whose name is empty
+fn named<T: Named>(entries: Vec<T>) -> Vec<T> {
+    entries
+        .into_iter()
+        .filter(|entry| {
+            let unnamed = entry.name().trim().is_empty();
+            if unnamed {
+                warn!("Dropping breakdown entry without a name");
+            }
+            !unnamed
+        })
+        .collect()
+}
@@ -16,10 +16,26 @@
 //!   including enterprise-wide and team-specific metrics for code completions,
 //!   chat interactions, and pull request activities.
 //!
+//! * `aggregate` - Adds metrics of several scopes together, merging
+//!   breakdowns by name, e.g. for team rollups.
+//!
+//! * `billing` - Contains data structures for GitHub's billing APIs, such as
+//!   premium request usage reports used to track consumption of premium models.
+//!
+//! * `lenient` - Field deserializers that read `null`s and values of an
+//!   unexpected type as defaults instead of failing the whole response.
+//!
+//! * `usage` - Contains data structures for GitHub's Copilot usage summary
+//!   API and their mapping onto the metrics models.
+//!
 //! Using these models ensures consistency in how data is represented across
 //! different parts of the application and simplifies serialization/deserialization
 //! when communicating with external APIs.
 
 // Generated by Github Copilot
+pub mod aggregate;
+pub mod billing;
 pub mod github;
+pub mod lenient;
+pub mod usage;
 // Generated Code by Github Copilot ends here
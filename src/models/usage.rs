@@ -0,0 +1,187 @@
+//! # GitHub Copilot Usage Models
+//!
+//! This module defines the data structures returned by GitHub's Copilot usage
+//! summary API (`/copilot/usage`), which predates the metrics API and is the
+//! only one some tenants have access to.
+//!
+//! The primary structures include:
+//! - `CopilotUsageDay`: The totals of one day
+//! - `CopilotUsageBreakdown`: Completion counts for one language and editor
+//!
+//! `CopilotUsageDay::to_metrics` maps a day onto `CopilotMetrics`, so usage
+//! days go through the same processors and Datadog series as metrics days.
+
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+
+use super::github::{
+    CopilotIdeChat, CopilotIdeCodeCompletions, CopilotMetrics, Editor, ExtraFields, Language, Model,
+};
+use super::lenient;
+
+/// Completion counts of one language in one editor
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct CopilotUsageBreakdown {
+    pub language: String,
+    pub editor: String,
+    #[serde(default, deserialize_with = "lenient::count")]
+    pub suggestions_count: i64,
+    #[serde(default, deserialize_with = "lenient::count")]
+    pub acceptances_count: i64,
+    #[serde(default, deserialize_with = "lenient::count")]
+    pub lines_suggested: i64,
+    #[serde(default, deserialize_with = "lenient::count")]
+    pub lines_accepted: i64,
+    #[serde(default, deserialize_with = "lenient::count")]
+    pub active_users: i64,
+    #[serde(flatten)]
+    pub extra: ExtraFields,
+}
+
+/// Copilot usage summary of one day
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct CopilotUsageDay {
+    /// ISO date of the day (YYYY-MM-DD)
+    pub day: String,
+    #[serde(default, deserialize_with = "lenient::count")]
+    pub total_suggestions_count: i64,
+    #[serde(default, deserialize_with = "lenient::count")]
+    pub total_acceptances_count: i64,
+    #[serde(default, deserialize_with = "lenient::count")]
+    pub total_lines_suggested: i64,
+    #[serde(default, deserialize_with = "lenient::count")]
+    pub total_lines_accepted: i64,
+    #[serde(default, deserialize_with = "lenient::count")]
+    pub total_active_users: i64,
+    #[serde(default, deserialize_with = "lenient::count")]
+    pub total_chat_acceptances: i64,
+    #[serde(default, deserialize_with = "lenient::count")]
+    pub total_chat_turns: i64,
+    #[serde(default, deserialize_with = "lenient::count")]
+    pub total_active_chat_users: i64,
+    /// Completion counts per language and editor
+    #[serde(default, deserialize_with = "lenient::list")]
+    pub breakdown: Vec<CopilotUsageBreakdown>,
+    #[serde(flatten)]
+    pub extra: ExtraFields,
+}
+
+impl CopilotUsageDay {
+    /// Maps the day onto the metrics model
+    ///
+    /// Completions are broken down by language and by editor, and each editor
+    /// carries its languages under a `default` model, as in the metrics API.
+    /// The usage API only counts active users per language and editor, so the
+    /// engaged users of a language (or editor) are the largest count among
+    /// its editors (or languages), a lower bound of the real number. Chat is
+    /// not broken down by editor; only its engaged users are mapped.
+    ///
+    /// # Returns
+    ///
+    /// * `CopilotMetrics` - The day in the shape of the metrics API
+    pub fn to_metrics(&self) -> CopilotMetrics {
+        let mut languages: BTreeMap<&str, Language> = BTreeMap::new();
+        let mut editors: BTreeMap<&str, Vec<Language>> = BTreeMap::new();
+        for entry in &self.breakdown {
+            let language = languages
+                .entry(&entry.language)
+                .or_insert_with(|| empty_language(&entry.language));
+            language.total_engaged_users = language.total_engaged_users.max(entry.active_users);
+            add_counts(language, entry);
+
+            let mut editor_language = empty_language(&entry.language);
+            editor_language.total_engaged_users = entry.active_users;
+            add_counts(&mut editor_language, entry);
+            editors
+                .entry(&entry.editor)
+                .or_default()
+                .push(editor_language);
+        }
+
+        let editors = editors
+            .into_iter()
+            .map(|(name, languages)| Editor {
+                name: name.to_string(),
+                total_engaged_users: languages
+                    .iter()
+                    .map(|l| l.total_engaged_users)
+                    .max()
+                    .unwrap_or(0),
+                models: Some(vec![Model {
+                    name: "default".to_string(),
+                    is_custom_model: false,
+                    custom_model_training_date: None,
+                    total_engaged_users: languages
+                        .iter()
+                        .map(|l| l.total_engaged_users)
+                        .max()
+                        .unwrap_or(0),
+                    languages: Some(languages),
+                    total_chats: None,
+                    total_chat_insertion_events: None,
+                    total_chat_copy_events: None,
+                    total_pr_summaries_created: None,
+                    extra: ExtraFields::new(),
+                }]),
+                versions: None,
+                extra: ExtraFields::new(),
+            })
+            .collect();
+
+        CopilotMetrics {
+            date: self.day.clone(),
+            total_active_users: Some(self.total_active_users),
+            total_engaged_users: Some(self.total_active_users.max(self.total_active_chat_users)),
+            copilot_ide_code_completions: Some(CopilotIdeCodeCompletions {
+                total_engaged_users: self.total_active_users,
+                languages: Some(languages.into_values().collect()),
+                editors: Some(editors),
+                extra: ExtraFields::new(),
+            }),
+            copilot_ide_chat: Some(CopilotIdeChat {
+                total_engaged_users: self.total_active_chat_users,
+                editors: None,
+                extra: ExtraFields::new(),
+            }),
+            copilot_dotcom_chat: None,
+            copilot_dotcom_pull_requests: None,
+            copilot_code_review: None,
+            extra: ExtraFields::new(),
+        }
+    }
+}
+
+/// Creates a language without counts
+fn empty_language(name: &str) -> Language {
+    Language {
+        name: name.to_string(),
+        total_engaged_users: 0,
+        total_code_suggestions: Some(0),
+        total_code_acceptances: Some(0),
+        total_code_lines_suggested: Some(0),
+        total_code_lines_accepted: Some(0),
+        extra: ExtraFields::new(),
+    }
+}
+
+/// Adds the completion counts of a breakdown entry to a language
+fn add_counts(language: &mut Language, entry: &CopilotUsageBreakdown) {
+    let add = |total: &mut Option<i64>, value: i64| *total = Some(total.unwrap_or(0) + value);
+    add(
+        &mut language.total_code_suggestions,
+        entry.suggestions_count,
+    );
+    add(
+        &mut language.total_code_acceptances,
+        entry.acceptances_count,
+    );
+    add(
+        &mut language.total_code_lines_suggested,
+        entry.lines_suggested,
+    );
+    add(
+        &mut language.total_code_lines_accepted,
+        entry.lines_accepted,
+    );
+}
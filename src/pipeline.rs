@@ -0,0 +1,1839 @@
+//! # Metrics Pipeline
+//!
+//! `MetricsPipeline` runs a complete collection: for every configured
+//! enterprise it fetches the metrics from GitHub, transforms them and sends
+//! them to Datadog, recording one summary per scope. The Lambda handler and
+//! external crates use it instead of orchestrating the processors themselves.
+//!
+//! A `RunScope` narrows a run to one enterprise, one team or a date range, e.g.
+//! for manual re-runs.
+//!
+//! The GitHub and Datadog clients are blocking, so `run_blocking` blocks the
+//! calling thread. With the `async` feature (enabled by `lambda` and
+//! `server`), `run` moves it to Tokio's blocking thread pool for async callers.
+//!
+//! ```no_run
+//! use ghrust::config::Config;
+//! use ghrust::pipeline::MetricsPipeline;
+//!
+//! # #[cfg(feature = "async")]
+//! # async fn example() -> ghrust::error::Result<()> {
+//! let config = Config::from_env()?;
+//! let report = MetricsPipeline::new(config).run().await?;
+//! if report.has_failures() {
+//!     eprintln!("Failed scopes: {}", report.failures.join("; "));
+//! }
+//! # Ok(())
+//! # }
+//! ```
+
+use std::borrow::Cow;
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::Arc;
+use std::time::Instant;
+
+use chrono::{Datelike, NaiveDate, Utc};
+use ring::rand::{SecureRandom, SystemRandom};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use tracing::{info, info_span, warn};
+
+use crate::config::{Config, ConfigError, EnterpriseConfig, TeamMapping};
+use crate::error::{GhrustError, Result};
+use crate::models::github::CopilotMetrics;
+use crate::processors::backfill::{self, BackfillCheckpoint, BackfillOptions};
+use crate::processors::baseline::{self, BaselineStore};
+use crate::processors::handoff::{self, MetricsBundle};
+use crate::processors::persistence::Persistence;
+use crate::processors::rollups::{self, RollupPeriod};
+use crate::processors::summary::{RunSummary, ScopeSummary};
+use crate::processors::{
+    alerts, anomaly, digest, distribution, enterprise, leaderboard, organization, privacy, seats,
+    team,
+};
+use crate::services::datadog::metadata::MetadataOutcome;
+use crate::services::datadog::{
+    DashboardProvisioner, DatadogSink, DayWindow, FailoverEvent, Gauge, LanguageGroups,
+    MetadataUpdater, TagContext,
+};
+use crate::services::export::ExportSink;
+use crate::services::github::{
+    teams, EtagCache, GitHubClient, RateLimitBudget, RawArchive, TeamCache,
+};
+use crate::services::notify::{
+    HealthcheckPinger, NotificationSink, OpsgenieNotifier, WebhookNotifier,
+};
+use crate::services::store::{self, Store};
+
+/// Scope the batched Datadog send is recorded under
+const BATCH_SCOPE: &str = "datadog:batch";
+
+/// Outcome of a pipeline run
+#[derive(Debug, Clone, Serialize)]
+pub struct RunReport {
+    /// Identifies the run in logs, responses and collector metrics
+    pub run_id: String,
+    /// Processing status of each enterprise, in configuration order
+    pub enterprises: Vec<Value>,
+    /// Failures that fail the run in `FAIL_ON_ERROR` mode
+    pub failures: Vec<String>,
+    /// Summary of every processed scope
+    pub summary: RunSummary,
+    /// Destinations that failed over to their secondary Datadog site
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub failovers: Vec<FailoverEvent>,
+}
+
+impl Default for RunReport {
+    fn default() -> Self {
+        Self {
+            run_id: new_run_id(),
+            enterprises: Vec::new(),
+            failures: Vec::new(),
+            summary: RunSummary::default(),
+            failovers: Vec::new(),
+        }
+    }
+}
+
+/// Generates a random run ID of 16 hex characters
+pub fn new_run_id() -> String {
+    let mut bytes = [0u8; 8];
+    if SystemRandom::new().fill(&mut bytes).is_err() {
+        // Unique enough to stitch the logs of a run together
+        let nanos = Utc::now().timestamp_nanos_opt().unwrap_or_default();
+        bytes = (nanos as u64 ^ u64::from(std::process::id())).to_be_bytes();
+    }
+    hex::encode(bytes)
+}
+
+impl RunReport {
+    /// Returns whether any scope failed in a way that fails the run
+    pub fn has_failures(&self) -> bool {
+        !self.failures.is_empty()
+    }
+
+    /// Adds the outcome of a part of the run processed on its own, e.g. a
+    /// tenant
+    ///
+    /// # Arguments
+    ///
+    /// * `other` - Report of the part
+    pub fn merge(&mut self, other: RunReport) {
+        self.enterprises.extend(other.enterprises);
+        self.failures.extend(other.failures);
+        self.summary.merge(other.summary);
+        self.failovers.extend(other.failovers);
+    }
+}
+
+/// Restricts a run to part of the configuration
+///
+/// Unset fields don't restrict the run. The date range selects days within
+/// the fetched reporting window; metrics that aren't reported per day (seats,
+/// premium requests) are not affected by it.
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+pub struct RunScope {
+    /// Only process this configured enterprise
+    pub enterprise_id: Option<String>,
+    /// Only process this team, skipping the enterprise-wide collections
+    pub team_slug: Option<String>,
+    /// First day to send (inclusive)
+    pub since: Option<NaiveDate>,
+    /// Last day to send (inclusive)
+    pub until: Option<NaiveDate>,
+    /// List the enterprises' teams again instead of using the cached lists
+    #[serde(default)]
+    pub refresh_teams: bool,
+}
+
+/// Fetches, transforms and sends the metrics of every configured enterprise
+#[derive(Debug, Clone)]
+pub struct MetricsPipeline {
+    config: Config,
+    day_window: Option<DayWindow>,
+    refresh_teams: bool,
+    collector_tags: Vec<String>,
+}
+
+impl MetricsPipeline {
+    /// Creates a pipeline for a resolved configuration
+    ///
+    /// # Arguments
+    ///
+    /// * `config` - The collector configuration, e.g. from `Config::from_env`
+    pub fn new(config: Config) -> Self {
+        Self {
+            config,
+            day_window: None,
+            refresh_teams: false,
+            collector_tags: Vec::new(),
+        }
+    }
+
+    /// Adds tags to the collector metrics (`collector.*`) of every run
+    ///
+    /// # Arguments
+    ///
+    /// * `tags` - Tags identifying the invocation, e.g.
+    ///   "lambda_request_id:<id>"
+    pub fn with_collector_tags(mut self, tags: Vec<String>) -> Self {
+        self.collector_tags.extend(tags);
+        self
+    }
+
+    /// Restricts the run to an enterprise, a team or a date range
+    ///
+    /// # Arguments
+    ///
+    /// * `scope` - The part of the configuration to run
+    ///
+    /// # Returns
+    ///
+    /// * `Result<Self>` - The narrowed pipeline, or an `Invalid` configuration
+    ///   error if the enterprise isn't configured or the range is empty
+    pub fn with_scope(mut self, scope: RunScope) -> Result<Self> {
+        if let Some(enterprise_id) = &scope.enterprise_id {
+            self.config.enterprises.retain(|e| &e.id == enterprise_id);
+            if self.config.enterprises.is_empty() {
+                return Err(ConfigError::Invalid(
+                    "enterprise_id".to_string(),
+                    format!("{} is not a configured enterprise", enterprise_id),
+                )
+                .into());
+            }
+        }
+
+        if let Some(team_slug) = scope.team_slug {
+            let config = &mut self.config;
+            for enterprise in &mut config.enterprises {
+                enterprise.team_slugs = vec![team_slug.clone()];
+            }
+            config.skip_enterprise = true;
+            config.team_discovery = None;
+            config.collect_premium_requests = false;
+            config.collect_org_metrics = false;
+            config.collect_seat_metrics = false;
+            config.team_rollup = false;
+            config.digest = None;
+            config.provision_dashboards = false;
+            config.update_metric_metadata = false;
+        }
+
+        if scope.since.is_some() || scope.until.is_some() {
+            let since = scope.since.unwrap_or(NaiveDate::MIN);
+            let until = scope.until.unwrap_or(NaiveDate::MAX);
+            if since > until {
+                return Err(ConfigError::Invalid(
+                    "since".to_string(),
+                    format!("{} is after until {}", since, until),
+                )
+                .into());
+            }
+            self.day_window = Some(DayWindow::Range { since, until });
+        }
+        self.refresh_teams = scope.refresh_teams;
+
+        Ok(self)
+    }
+
+    /// The pipeline's configuration
+    pub fn config(&self) -> &Config {
+        &self.config
+    }
+
+    /// Runs the pipeline for every configured enterprise
+    ///
+    /// Each enterprise is processed independently so one failing enterprise
+    /// doesn't prevent the others from being reported; scope failures are
+    /// recorded in the report rather than returned as errors.
+    ///
+    /// The run blocks the current thread, since the GitHub and Datadog clients
+    /// are blocking; async callers use `run` instead.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<RunReport>` - The report of the run, or an error if the
+    ///   metrics store or an exporter can't be set up
+    pub fn run_blocking(&self) -> Result<RunReport> {
+        let started = Instant::now();
+        // Tag every log line of the run with its ID
+        let report = RunReport::default();
+        let span = info_span!("run", run_id = %report.run_id);
+        let _run = span.enter();
+
+        // Ping the dead man's switch around the run, if one is configured
+        let pinger = self
+            .config
+            .healthcheck_url
+            .as_deref()
+            .map(|url| HealthcheckPinger::new(url, &self.config.agent));
+        if let Some(Err(e)) = pinger.as_ref().map(HealthcheckPinger::start) {
+            warn!("Error sending the healthcheck start ping: {}", e);
+        }
+        let result = self.collect_tenants(started, report);
+        if let Some(pinger) = &pinger {
+            let pinged = match &result {
+                Ok(report) if !report.has_failures() => pinger.success(&format!(
+                    "{} scope(s) processed",
+                    report.summary.scopes.len()
+                )),
+                Ok(report) => pinger.failure(&report.failures.join("\n")),
+                Err(e) => pinger.failure(&e.to_string()),
+            };
+            if let Err(e) = pinged {
+                warn!("Error sending the healthcheck end ping: {}", e);
+            }
+        }
+        result
+    }
+
+    /// Runs the pipeline for every configured enterprise on a blocking thread
+    ///
+    /// Like `run_blocking`, but the run is moved to Tokio's blocking thread
+    /// pool so it doesn't stall the runtime; its log lines stay in the
+    /// caller's span.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<RunReport>` - The report of the run, or an error if the
+    ///   metrics store or an exporter can't be set up, or `Interrupted` if
+    ///   the run panicked
+    #[cfg(feature = "async")]
+    pub async fn run(self) -> Result<RunReport> {
+        let span = tracing::Span::current();
+        tokio::task::spawn_blocking(move || span.in_scope(|| self.run_blocking()))
+            .await
+            .map_err(|e| GhrustError::Interrupted(e.to_string()))?
+    }
+
+    /// Collects the metrics of the shared enterprises and of every tenant
+    ///
+    /// Tenants (enterprises with credentials of their own) are processed one
+    /// after another, each with its own GitHub token and Datadog sink, so a
+    /// tenant's metrics never reach another organization and its failures
+    /// don't affect the others. The collector metrics of a tenant are sent
+    /// to its organization under its namespace. A part of the run that can't
+    /// be set up is recorded as a failure of its enterprises.
+    ///
+    /// # Arguments
+    ///
+    /// * `started` - When the run started
+    /// * `report` - Report of the run, filled in with its outcome
+    fn collect_tenants(&self, started: Instant, mut report: RunReport) -> Result<RunReport> {
+        let (tenants, shared): (Vec<_>, Vec<_>) = self
+            .config
+            .enterprises
+            .iter()
+            .cloned()
+            .partition(|e| e.tenant.is_some());
+        if tenants.is_empty() {
+            return self.collect(started, report);
+        }
+
+        let part = || RunReport {
+            run_id: report.run_id.clone(),
+            ..RunReport::default()
+        };
+        // Each part is labeled for its failure and lists its enterprises
+        let mut parts = Vec::new();
+        if !shared.is_empty() {
+            let ids: Vec<String> = shared.iter().map(|e| e.id.clone()).collect();
+            let mut pipeline = self.clone();
+            pipeline.config.enterprises = shared;
+            parts.push((
+                "shared enterprises".to_string(),
+                ids,
+                pipeline.collect(started, part()),
+            ));
+        }
+        for enterprise in tenants {
+            let id = enterprise.id.clone();
+            let span = info_span!("tenant", enterprise = %id);
+            let _tenant = span.enter();
+            parts.push((
+                format!("tenant {}", id),
+                vec![id],
+                self.for_tenant(enterprise).collect(Instant::now(), part()),
+            ));
+        }
+
+        // A part that can't be set up fails only its enterprises
+        for (label, ids, result) in parts {
+            match result {
+                Ok(part) => report.merge(part),
+                Err(e) => {
+                    warn!("Error processing {}: {}", label, e);
+                    for id in ids {
+                        report
+                            .enterprises
+                            .push(json!({ "enterprise_id": id, "error": e.to_string() }));
+                    }
+                    report.failures.push(format!("{}: {}", label, e));
+                }
+            }
+        }
+        Ok(report)
+    }
+
+    /// Returns a pipeline processing only a tenant, with its credentials
+    ///
+    /// # Arguments
+    ///
+    /// * `enterprise` - The tenant enterprise
+    fn for_tenant(&self, mut enterprise: EnterpriseConfig) -> Self {
+        let mut pipeline = self.clone();
+        let config = &mut pipeline.config;
+        if let Some(tenant) = enterprise.tenant.take() {
+            if let Some(token) = tenant.github_token {
+                config.github_token = token;
+            }
+            if let Some(datadog) = tenant.datadog {
+                config.datadog_destinations = vec![datadog];
+            }
+            // The operator's application key belongs to another organization
+            config.datadog_app_key = tenant.datadog_app_key;
+        }
+        config.datadog_namespace = enterprise.namespace.clone();
+        config.enterprises = vec![enterprise];
+        pipeline
+    }
+
+    /// Collects the metrics of every configured enterprise
+    ///
+    /// # Arguments
+    ///
+    /// * `started` - When the run started
+    /// * `report` - Report of the run, filled in with its outcome
+    fn collect(&self, started: Instant, mut report: RunReport) -> Result<RunReport> {
+        let config = &self.config;
+
+        // Send every series to all configured Datadog organizations
+        let mut datadog = DatadogSink::builder(
+            &config.datadog_destinations,
+            &config.datadog_namespace,
+            &config.datadog_settings,
+        )
+        .language_groups(LanguageGroups::new(config.language_groups.clone()));
+        if let Some(day_window) = self.day_window {
+            datadog = datadog.day_window(day_window);
+        }
+        if config.datadog_batch_send {
+            datadog = datadog.batching();
+        }
+        let datadog = datadog.build();
+        info!(
+            "Sending metrics to Datadog destination(s): {}",
+            datadog.destination_names().join(", ")
+        );
+        // Before anything else is set up, so a setup error still leaves a
+        // heartbeat
+        self.send_heartbeat(&datadog, &report.run_id);
+
+        // Open the metrics store and set up the exporters, if configured
+        let persistence = Persistence {
+            store: Store::open(
+                config.store_path.as_deref(),
+                config.reuse_stored_days,
+                &config.agent,
+            )?,
+            exports: ExportSink::open(&config.exports, &config.agent)?,
+        };
+
+        // Send the ETags of the last successful run with metrics requests
+        let etags = config
+            .etag_cache_path
+            .as_deref()
+            .map(|path| Arc::new(EtagCache::open(path)));
+
+        info!(
+            "Processing {} enterprise(s): {}",
+            config.enterprises.len(),
+            config
+                .enterprises
+                .iter()
+                .map(|e| e.id.as_str())
+                .collect::<Vec<_>>()
+                .join(", ")
+        );
+
+        let mut github = config.github_client();
+        if let Some(etags) = &etags {
+            github = github.etag_cache(Arc::clone(etags));
+        }
+        let mut github = github
+            .build()
+            .map_err(GhrustError::github("Failed to configure the GitHub client"))?;
+        // Keep the raw metrics responses for replays
+        if let Some(archive) = self.raw_archive() {
+            github = github.with_archive(archive);
+        }
+
+        // Deliver threshold alerts to the configured channels
+        let mut notifications = NotificationSink::new();
+        if let Some(url) = &config.alert_webhook_url {
+            notifications = notifications.with_notifier(WebhookNotifier::new(url, &config.agent));
+        }
+        if let Some(opsgenie) = &config.opsgenie {
+            let mut notifier = OpsgenieNotifier::new(&opsgenie.api_key, &config.agent);
+            if let Some(api_url) = &opsgenie.api_url {
+                notifier = notifier.with_api_url(api_url);
+            }
+            notifications = notifications.with_notifier(notifier);
+        }
+
+        let budget = RateLimitBudget::snapshot();
+        for enterprise_config in self.enterprises().iter() {
+            let (status, failures) = self.process_enterprise(
+                enterprise_config,
+                &github,
+                &datadog,
+                &notifications,
+                &persistence,
+                &mut report.summary,
+            );
+            report.enterprises.push(status);
+            report.failures.extend(failures);
+        }
+        self.report_budget(&budget, &datadog, &mut report);
+        self.flush_batch(&datadog, &mut report);
+        self.report_runtime(started, &datadog, &mut report);
+        self.report_freshness(&datadog, &report);
+        report.failovers = datadog.failovers();
+
+        // Log the outcome of every scope once, at the end of the run
+        report.summary.log();
+
+        // Keep the ETags only if every scope was sent, so failed windows are
+        // fetched again by the next run
+        if let Some(etags) = etags {
+            if report.summary.scopes.iter().all(|s| s.error.is_none()) {
+                if let Err(e) = etags.commit() {
+                    warn!("Error saving the ETag cache: {}", e);
+                }
+            } else {
+                info!("Not saving ETags because some scopes failed");
+                etags.discard();
+            }
+        }
+
+        Ok(report)
+    }
+
+    /// Processes all configured metrics for a single enterprise
+    ///
+    /// Runs the enterprise, premium request and team processors for the
+    /// enterprise, logging and recording failures instead of aborting.
+    ///
+    /// # Arguments
+    ///
+    /// * `enterprise_config` - The enterprise to process
+    /// * `github` - GitHub client the metrics are fetched with
+    /// * `datadog` - Datadog sink fanning out to the configured destinations
+    /// * `notifications` - Channels threshold alerts are sent to
+    /// * `persistence` - Where the fetched days are kept
+    /// * `summary` - Run summary receiving one scope summary per processed scope
+    ///
+    /// # Returns
+    ///
+    /// * `(Value, Vec<String>)` - JSON status of each processing step for this
+    ///   enterprise, and the failures that fail the run in `FAIL_ON_ERROR` mode
+    fn process_enterprise(
+        &self,
+        enterprise_config: &EnterpriseConfig,
+        github: &GitHubClient,
+        datadog: &DatadogSink,
+        notifications: &NotificationSink,
+        persistence: &Persistence,
+        summary: &mut RunSummary,
+    ) -> (Value, Vec<String>) {
+        let config = &self.config;
+        let mut failures = Vec::new();
+        let enterprise_id = &enterprise_config.id;
+        let namespace = &enterprise_config.namespace;
+        let mut status = json!({
+            "enterprise_id": enterprise_id,
+            "namespace": namespace,
+        });
+        // A numeric ID or display name fails the enterprise with an
+        // explanation instead of a 404 in every scope; other enterprises
+        // still run
+        if let Err(e) = enterprise::check_enterprise_id(github, enterprise_id) {
+            record_step(
+                &mut status,
+                &mut failures,
+                "enterprise_id",
+                "enterprise ID",
+                Err(e),
+            );
+            return (status, failures);
+        }
+
+        // Teams and organizations scope the sink further for their own series
+        let datadog = &datadog.scoped(TagContext::enterprise(enterprise_id));
+
+        // The fetched enterprise window, analyzed further below
+        let mut metrics = Vec::new();
+
+        // WORKFLOW STEP 1: Process enterprise-wide metrics if not explicitly skipped
+        // These metrics cover all Copilot usage across the entire enterprise
+        // A failure is recorded and the remaining steps still run
+        if !config.skip_enterprise {
+            let result = summary.track(
+                format!("enterprise:{}", enterprise_id),
+                namespace,
+                |scope| {
+                    enterprise::process_enterprise_metrics(
+                        github,
+                        enterprise_id,
+                        datadog,
+                        namespace,
+                        persistence,
+                        scope,
+                    )
+                },
+            );
+            let result = result.map(|fetched| {
+                metrics = fetched;
+                json!("ok")
+            });
+            record_step(
+                &mut status,
+                &mut failures,
+                "enterprise_metrics",
+                "enterprise metrics",
+                result,
+            );
+
+            // An unchanged window is served from the metrics store; without
+            // one, the analyses of the window are recorded as not run
+            let not_modified = summary.scopes.last().is_some_and(|s| s.not_modified);
+            if not_modified && metrics.is_empty() {
+                for (step, enabled) in [
+                    ("alerts", !config.alert_rules.is_empty()),
+                    ("anomalies", config.anomaly_sigma.is_some()),
+                    ("weekly_rollups", config.weekly_rollups),
+                    ("monthly_rollups", config.monthly_rollups),
+                    ("baseline", config.baseline.is_some()),
+                ] {
+                    if enabled {
+                        status[step] = json!("not_modified");
+                    }
+                }
+            }
+        } else {
+            info!("Skipping enterprise metrics due to SKIP_ENTERPRISE_METRICS flag");
+            status["enterprise_metrics"] = json!("skipped");
+        }
+
+        // Check the latest day against the configured thresholds
+        if !config.alert_rules.is_empty() && !metrics.is_empty() {
+            let scope = format!("enterprise:{}", enterprise_id);
+            let result =
+                alerts::process_alerts(&scope, &config.alert_rules, &metrics, notifications);
+            record_step(
+                &mut status,
+                &mut failures,
+                "alerts",
+                "alerts",
+                result.map(|fired| json!(fired)),
+            );
+        }
+
+        // Score the latest day against the rest of the window
+        if let Some(sigma) = config.anomaly_sigma.filter(|_| !metrics.is_empty()) {
+            let scope = format!("anomalies:{}", enterprise_id);
+            let result = summary.track(scope, namespace, |scope| {
+                anomaly::process_anomalies(&metrics, sigma, datadog, namespace, scope)
+            });
+            record_step(
+                &mut status,
+                &mut failures,
+                "anomalies",
+                "anomalies",
+                result.map(|anomalous| json!(anomalous)),
+            );
+        }
+
+        // Report the complete weeks of the window as one point each
+        if config.weekly_rollups && !metrics.is_empty() {
+            let scope = format!("weekly_rollups:{}", enterprise_id);
+            let result = summary.track(scope, namespace, |scope| {
+                rollups::process_rollups(&metrics, RollupPeriod::Week, datadog, namespace, scope)
+            });
+            record_step(
+                &mut status,
+                &mut failures,
+                "weekly_rollups",
+                "weekly rollups",
+                result.map(|weeks| json!(weeks)),
+            );
+        }
+
+        // Report the complete months of the stored days for long-term trends
+        if config.monthly_rollups && !metrics.is_empty() {
+            let scope = format!("monthly_rollups:{}", enterprise_id);
+            let result = summary.track(scope, namespace, |scope| {
+                let days = rollups::with_stored_days(
+                    &persistence.store,
+                    &format!("enterprise:{}", enterprise_id),
+                    &metrics,
+                );
+                rollups::process_rollups(&days, RollupPeriod::Month, datadog, namespace, scope)
+            });
+            record_step(
+                &mut status,
+                &mut failures,
+                "monthly_rollups",
+                "monthly rollups",
+                result.map(|months| json!(months)),
+            );
+        }
+
+        // Keep weekly aggregates beyond the 30-day window and compare with last quarter
+        if let Some(baseline_config) = config.baseline.as_ref().filter(|_| !metrics.is_empty()) {
+            let scope = format!("baseline:{}", enterprise_id);
+            let result = summary.track(scope, namespace, |scope| {
+                let store = BaselineStore::new(
+                    &baseline_config.bucket,
+                    &baseline_config.key,
+                    &config.agent,
+                )?;
+                baseline::process_baseline(
+                    &format!("enterprise:{}", enterprise_id),
+                    &metrics,
+                    &store,
+                    datadog,
+                    namespace,
+                    scope,
+                )
+            });
+            record_step(
+                &mut status,
+                &mut failures,
+                "baseline",
+                "baseline",
+                result.map(|()| json!("ok")),
+            );
+        }
+
+        // Optionally track premium request consumption alongside the usage metrics
+        if config.collect_premium_requests {
+            let scope = format!("premium_requests:{}", enterprise_id);
+            let result = summary.track(scope, namespace, |scope| {
+                enterprise::process_premium_request_usage(
+                    github,
+                    enterprise_id,
+                    datadog,
+                    namespace,
+                    scope,
+                )
+            });
+            record_step(
+                &mut status,
+                &mut failures,
+                "premium_requests",
+                "premium requests",
+                result.map(|_| json!("ok")),
+            );
+        }
+
+        // Optionally report seats that are paid for but not used
+        if config.collect_seat_metrics {
+            let scope = format!("seats:{}", enterprise_id);
+            let result = summary.track(scope, namespace, |scope| {
+                seats::process_seat_usage(
+                    github,
+                    enterprise_id,
+                    datadog,
+                    namespace,
+                    seats::SeatReporting {
+                        list_users: config.report_unused_seat_users,
+                        seat_price: config.seat_price,
+                        activity_histogram: config.report_seat_activity,
+                    },
+                    scope,
+                )
+            });
+            record_step(
+                &mut status,
+                &mut failures,
+                "seats",
+                "seats",
+                result.map(|_| json!("ok")),
+            );
+        }
+
+        // Optionally report every organization of the enterprise on its own
+        if config.collect_org_metrics {
+            status["org_metrics"] = match organization::process_all_orgs(
+                github,
+                enterprise_id,
+                datadog,
+                &organization::OrgNaming {
+                    datadog_namespace: namespace,
+                    template: &config.namespace_template,
+                },
+                config.collect_seat_metrics,
+                persistence,
+                summary,
+            ) {
+                Ok(outcome) if outcome.failed == 0 => json!("ok"),
+                Ok(outcome) => {
+                    let error = format!("Failed to process {} organizations", outcome.failed);
+                    warn!("Error processing organization metrics: {}", error);
+                    failures.push(format!(
+                        "{} organization metrics: {} of {} organizations failed",
+                        enterprise_id,
+                        outcome.failed,
+                        outcome.total()
+                    ));
+                    json!({
+                        "error": error,
+                        "failed": outcome.failed,
+                        "total": outcome.total(),
+                    })
+                }
+                Err(e) => {
+                    warn!(
+                        "Error processing organization metrics for {}: {}",
+                        enterprise_id, e
+                    );
+                    failures.push(format!("{} organization metrics: {}", enterprise_id, e));
+                    json!({ "error": e.to_string() })
+                }
+            };
+        }
+
+        // WORKFLOW STEP 2: Process team-specific metrics if team slugs are provided
+        // These metrics are scoped to individual teams for more granular reporting
+        let slugs = &enterprise_config.team_slugs;
+        status["team_metrics"] = if !slugs.is_empty() {
+            let team_mappings = self.team_mappings(namespace, slugs);
+
+            let outcome = team::process_all_teams(
+                github,
+                enterprise_id,
+                slugs,
+                datadog,
+                &team::TeamNaming {
+                    datadog_namespace: namespace,
+                    template: &config.namespace_template,
+                    mappings: &team_mappings,
+                },
+                persistence,
+                summary,
+            );
+
+            // Send the sum of all teams for comparison with the enterprise totals
+            if config.team_rollup && outcome.succeeded > 0 {
+                let scope = format!("teams:{}/all", enterprise_id);
+                let rollup_namespace = team::rollup_namespace(namespace);
+                let result = summary.track(scope, &rollup_namespace, |scope| {
+                    team::process_team_rollup(
+                        enterprise_id,
+                        &outcome.rollup,
+                        datadog,
+                        namespace,
+                        scope,
+                    )
+                });
+                record_step(
+                    &mut status,
+                    &mut failures,
+                    "team_rollup",
+                    "team rollup",
+                    result.map(|_| json!("ok")),
+                );
+            }
+
+            // Report how the teams compare with each other
+            if config.team_distribution && outcome.succeeded > 0 {
+                let scope = format!("teams:{}/distribution", enterprise_id);
+                let result = summary.track(scope, namespace, |scope| {
+                    distribution::process_team_distribution(
+                        &outcome.teams,
+                        datadog,
+                        namespace,
+                        scope,
+                    )
+                });
+                record_step(
+                    &mut status,
+                    &mut failures,
+                    "team_distribution",
+                    "team distribution",
+                    result.map(|teams| json!(teams)),
+                );
+            }
+
+            // Post the engagement growth leaderboard once on the configured day
+            let today = Utc::now().date_naive();
+            if let Some(leaderboard_config) = config
+                .leaderboard
+                .as_ref()
+                .filter(|l| l.weekday == today.weekday() && outcome.succeeded > 0)
+            {
+                let marker = format!("leaderboard:{}", enterprise_id);
+                if store::sent_this_week(persistence.store.backend(), &marker, today) {
+                    info!(
+                        "Team leaderboard for {} was already posted this week",
+                        enterprise_id
+                    );
+                    status["leaderboard"] = json!("skipped");
+                } else {
+                    let result = leaderboard::process_leaderboard(
+                        &format!("enterprise:{}", enterprise_id),
+                        &outcome.teams,
+                        leaderboard_config,
+                        notifications,
+                        &config.agent,
+                    );
+                    let result = result.map(|teams| {
+                        store::mark_sent_this_week(persistence.store.backend(), &marker, today);
+                        json!(teams)
+                    });
+                    record_step(
+                        &mut status,
+                        &mut failures,
+                        "leaderboard",
+                        "team leaderboard",
+                        result,
+                    );
+                }
+            }
+
+            if outcome.failed == 0 {
+                json!("ok")
+            } else {
+                let error = format!("Failed to process {} teams", outcome.failed);
+                warn!("Error processing team metrics: {}", error);
+                if outcome.exceeds(config.team_failure_threshold) {
+                    failures.push(format!(
+                        "{} team metrics: {} of {} teams failed",
+                        enterprise_id,
+                        outcome.failed,
+                        outcome.total()
+                    ));
+                }
+                json!({
+                    "error": error,
+                    "failed": outcome.failed,
+                    "total": outcome.total(),
+                })
+            }
+        } else {
+            info!("No team slugs provided, skipping team metrics");
+            json!("skipped")
+        };
+
+        // Keep the enterprise's dashboard in sync with the metric names
+        if config.provision_dashboards {
+            let result = self.provision_dashboard(namespace);
+            record_step(
+                &mut status,
+                &mut failures,
+                "dashboard",
+                "dashboard",
+                result.map(|id| json!({ "id": id })),
+            );
+        }
+
+        // Label the enterprise's metrics with units and descriptions
+        if config.update_metric_metadata {
+            let result = self.update_metric_metadata(namespace).map(|outcome| {
+                json!({
+                    "updated": outcome.updated,
+                    "unchanged": outcome.unchanged,
+                    "missing": outcome.missing,
+                })
+            });
+            record_step(
+                &mut status,
+                &mut failures,
+                "metric_metadata",
+                "metric metadata",
+                result,
+            );
+        }
+
+        // Email the weekly adoption digest of the window once on the configured day
+        if let Some(digest_config) = &config.digest {
+            let marker = format!("digest:{}", enterprise_id);
+            let today = Utc::now().date_naive();
+            if today.weekday() != digest_config.weekday || metrics.is_empty() {
+                status["digest"] = json!("skipped");
+            } else if store::sent_this_week(persistence.store.backend(), &marker, today) {
+                info!(
+                    "Weekly digest for {} was already sent this week",
+                    enterprise_id
+                );
+                status["digest"] = json!("skipped");
+            } else {
+                let result = digest::process_weekly_digest(
+                    enterprise_id,
+                    &metrics,
+                    digest_config,
+                    &config.agent,
+                );
+                let result = result.map(|_| {
+                    store::mark_sent_this_week(persistence.store.backend(), &marker, today);
+                    json!("ok")
+                });
+                record_step(
+                    &mut status,
+                    &mut failures,
+                    "digest",
+                    "weekly digest",
+                    result,
+                );
+            }
+        }
+
+        (status, failures)
+    }
+
+    /// Backfills a date range one day at a time
+    ///
+    /// The enterprise metrics (unless skipped) and the metrics of every
+    /// configured team are requested per day, sent, and recorded in the
+    /// checkpoint file, pausing between days. Starting the backfill again with
+    /// the same checkpoint resumes every scope after its last completed day.
+    /// Collections that aren't reported per day (seats, premium requests,
+    /// organizations, digests) are not part of a backfill.
+    ///
+    /// # Arguments
+    ///
+    /// * `options` - Days to backfill and the pause between them
+    /// * `checkpoint_path` - Path of the checkpoint file; created if missing
+    ///
+    /// # Returns
+    ///
+    /// * `Result<RunReport>` - The report of the backfill, or an error if the
+    ///   checkpoint file can't be loaded
+    pub fn run_backfill(
+        &self,
+        options: BackfillOptions,
+        checkpoint_path: &Path,
+    ) -> Result<RunReport> {
+        let mut report = RunReport::default();
+        let span = info_span!("run", run_id = %report.run_id);
+        let _run = span.enter();
+        let config = &self.config;
+
+        let mut checkpoint = BackfillCheckpoint::open(checkpoint_path)
+            .map_err(|e| GhrustError::Checkpoint(checkpoint_path.display().to_string(), e))?;
+        let datadog = DatadogSink::builder(
+            &config.datadog_destinations,
+            &config.datadog_namespace,
+            &config.datadog_settings,
+        )
+        .language_groups(LanguageGroups::new(config.language_groups.clone()))
+        .build();
+        let github_client = config
+            .github_client()
+            .build()
+            .map_err(GhrustError::github("Failed to configure the GitHub client"))?;
+        info!(
+            "Backfilling {} to {} for {} enterprise(s)",
+            options.since,
+            options.until,
+            config.enterprises.len()
+        );
+
+        let budget = RateLimitBudget::snapshot();
+        for enterprise_config in self.enterprises().iter() {
+            let enterprise_id = &enterprise_config.id;
+            let namespace = &enterprise_config.namespace;
+            let mut status = json!({
+                "enterprise_id": enterprise_id,
+                "namespace": namespace,
+            });
+            // Tags of the last fetched day: `endpoint:org` if it came from
+            // the organization endpoint
+            let fallback = RefCell::new(Vec::new());
+            let send = |metrics: &[CopilotMetrics],
+                        namespace: &str,
+                        tag_context: TagContext,
+                        tags: &[String],
+                        scope: &mut ScopeSummary|
+             -> Result<()> {
+                let datadog = datadog.scoped(tag_context);
+                let before = datadog.stats();
+                let sent = datadog.send_metrics_with_tags(metrics, namespace, tags);
+                scope.record_send(&before, &datadog.stats());
+                Ok(sent?)
+            };
+
+            if !config.skip_enterprise {
+                let result = report.summary.track(
+                    format!("enterprise:{}", enterprise_id),
+                    namespace,
+                    |scope| {
+                        backfill::backfill_scope(
+                            &options,
+                            &mut checkpoint,
+                            scope,
+                            |day| {
+                                enterprise::with_org_fallback(
+                                    &github_client,
+                                    enterprise_id,
+                                    || {
+                                        github_client.fetch_enterprise_metrics_between(
+                                            enterprise_id,
+                                            day,
+                                            day,
+                                        )
+                                    },
+                                    || {
+                                        github_client.fetch_org_metrics_between(
+                                            enterprise_id,
+                                            day,
+                                            day,
+                                        )
+                                    },
+                                )
+                                .map(|(metrics, tags)| {
+                                    *fallback.borrow_mut() = tags;
+                                    metrics
+                                })
+                                .map_err(GhrustError::github("Failed to fetch enterprise metrics"))
+                            },
+                            |metrics, scope| {
+                                let tag_context = TagContext::enterprise(enterprise_id);
+                                send(metrics, namespace, tag_context, &fallback.borrow(), scope)
+                            },
+                        )
+                    },
+                );
+                status["enterprise_metrics"] =
+                    backfill_status(result, &mut report.failures, || {
+                        format!("{} enterprise metrics", enterprise_id)
+                    });
+            }
+
+            let slugs = &enterprise_config.team_slugs;
+            let team_mappings = self.team_mappings(namespace, slugs);
+            let mut teams = serde_json::Map::new();
+            for slug in slugs {
+                let mapping = team_mappings.get(slug);
+                let team_ns =
+                    team::team_namespace(namespace, &config.namespace_template, slug, mapping);
+                let tags = team::team_tags(&config.namespace_template, slug, mapping);
+                let result = report.summary.track(
+                    format!("team:{}/{}", enterprise_id, slug),
+                    &team_ns,
+                    |scope| {
+                        backfill::backfill_scope(
+                            &options,
+                            &mut checkpoint,
+                            scope,
+                            |day| {
+                                enterprise::with_org_fallback(
+                                    &github_client,
+                                    enterprise_id,
+                                    || {
+                                        github_client.fetch_team_metrics_between(
+                                            enterprise_id,
+                                            slug,
+                                            day,
+                                            day,
+                                        )
+                                    },
+                                    || {
+                                        github_client.fetch_org_team_metrics_between(
+                                            enterprise_id,
+                                            slug,
+                                            day,
+                                            day,
+                                        )
+                                    },
+                                )
+                                .map(|(metrics, tags)| {
+                                    *fallback.borrow_mut() = tags;
+                                    metrics
+                                })
+                                .map_err(GhrustError::github("Failed to fetch team metrics"))
+                            },
+                            |metrics, scope| {
+                                let tag_context = TagContext::team(enterprise_id, slug);
+                                let tags: Vec<String> = tags
+                                    .iter()
+                                    .chain(fallback.borrow().iter())
+                                    .cloned()
+                                    .collect();
+                                send(metrics, &team_ns, tag_context, &tags, scope)
+                            },
+                        )
+                    },
+                );
+                teams.insert(
+                    slug.clone(),
+                    backfill_status(result, &mut report.failures, || {
+                        format!("{} team {}", enterprise_id, slug)
+                    }),
+                );
+            }
+            if !teams.is_empty() {
+                status["team_metrics"] = Value::Object(teams);
+            }
+            report.enterprises.push(status);
+        }
+        self.report_budget(&budget, &datadog, &mut report);
+        report.failovers = datadog.failovers();
+
+        report.summary.log();
+        Ok(report)
+    }
+
+    /// Fetches the enterprise and team metrics without sending them
+    ///
+    /// The first stage of a split run (`ghrust fetch`): every scope is fetched
+    /// from GitHub and returned with its resolved namespace and tags, for
+    /// `send_bundle` to send later, possibly elsewhere. A scope that fails is
+    /// recorded in the report and left out of the bundle.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<(MetricsBundle, RunReport)>` - The fetched scopes and the
+    ///   report of the fetch, or an error if the GitHub client can't be
+    ///   configured
+    pub fn fetch_bundle(&self) -> Result<(MetricsBundle, RunReport)> {
+        let mut report = RunReport::default();
+        let span = info_span!("run", run_id = %report.run_id);
+        let _run = span.enter();
+        let config = &self.config;
+
+        let mut github_client = config
+            .github_client()
+            .build()
+            .map_err(GhrustError::github("Failed to configure the GitHub client"))?;
+        // Keep the raw metrics responses for replays
+        if let Some(archive) = self.raw_archive() {
+            github_client = github_client.with_archive(archive);
+        }
+
+        let budget = RateLimitBudget::snapshot();
+        let mut bundle = MetricsBundle::new();
+        for enterprise_config in self.enterprises().iter() {
+            let enterprise_id = &enterprise_config.id;
+            let namespace = &enterprise_config.namespace;
+
+            if !config.skip_enterprise {
+                let result = report.summary.track(
+                    format!("enterprise:{}", enterprise_id),
+                    namespace,
+                    |scope| {
+                        handoff::fetch_enterprise(&github_client, enterprise_id, namespace, scope)
+                            .map(Some)
+                    },
+                );
+                match result {
+                    Ok(fetched) => bundle.scopes.extend(fetched),
+                    Err(e) => {
+                        warn!(
+                            "Error fetching enterprise metrics for {}: {}",
+                            enterprise_id, e
+                        );
+                        report
+                            .failures
+                            .push(format!("{} enterprise metrics: {}", enterprise_id, e));
+                    }
+                }
+            }
+
+            let slugs = &enterprise_config.team_slugs;
+            let team_mappings = self.team_mappings(namespace, slugs);
+            for slug in slugs {
+                let mapping = team_mappings.get(slug);
+                let team_ns =
+                    team::team_namespace(namespace, &config.namespace_template, slug, mapping);
+                let tags = team::team_tags(&config.namespace_template, slug, mapping);
+                let result = report.summary.track(
+                    format!("team:{}/{}", enterprise_id, slug),
+                    &team_ns,
+                    |scope| {
+                        handoff::fetch_team(
+                            &github_client,
+                            enterprise_id,
+                            slug,
+                            &team_ns,
+                            &tags,
+                            scope,
+                        )
+                        .map(Some)
+                    },
+                );
+                match result {
+                    Ok(fetched) => bundle.scopes.extend(fetched),
+                    Err(e) => {
+                        warn!(
+                            "Error fetching team metrics for {}/{}: {}",
+                            enterprise_id, slug, e
+                        );
+                        report
+                            .failures
+                            .push(format!("{} team {}: {}", enterprise_id, slug, e));
+                    }
+                }
+            }
+        }
+        report.summary.github = RateLimitBudget::snapshot().since(&budget);
+
+        report.summary.log();
+        Ok((bundle, report))
+    }
+
+    /// Sends the metrics fetched by `fetch_bundle`
+    ///
+    /// The second stage of a split run (`ghrust send`): every scope of the
+    /// bundle is sent to the configured destinations under the namespace it
+    /// was fetched for. A date range set with `with_scope` selects the days
+    /// that are sent.
+    ///
+    /// # Arguments
+    ///
+    /// * `bundle` - The metrics read from the file written by the fetch
+    ///
+    /// # Returns
+    ///
+    /// * `Result<RunReport>` - The report of the send
+    pub fn send_bundle(&self, bundle: &MetricsBundle) -> Result<RunReport> {
+        let report = RunReport::default();
+        let span = info_span!("run", run_id = %report.run_id);
+        let _run = span.enter();
+        self.send_scopes(bundle, report)
+    }
+
+    /// Sends the scopes of a bundle, adding their outcome to a report
+    fn send_scopes(&self, bundle: &MetricsBundle, mut report: RunReport) -> Result<RunReport> {
+        let started = Instant::now();
+        let config = &self.config;
+
+        let mut datadog = DatadogSink::builder(
+            &config.datadog_destinations,
+            &config.datadog_namespace,
+            &config.datadog_settings,
+        )
+        .language_groups(LanguageGroups::new(config.language_groups.clone()));
+        if let Some(day_window) = self.day_window {
+            datadog = datadog.day_window(day_window);
+        }
+        if config.datadog_batch_send {
+            datadog = datadog.batching();
+        }
+        let datadog = datadog.build();
+        info!(
+            "Sending {} scope(s) fetched at {} to Datadog destination(s): {}",
+            bundle.scopes.len(),
+            bundle.fetched_at,
+            datadog.destination_names().join(", ")
+        );
+
+        for fetched in &bundle.scopes {
+            let result =
+                report
+                    .summary
+                    .track(fetched.scope.as_str(), &fetched.namespace, |scope| {
+                        handoff::send_scope(fetched, &datadog, scope)
+                    });
+            if let Err(e) = result {
+                warn!("Error sending {}: {}", fetched.scope, e);
+                report.failures.push(format!("{}: {}", fetched.scope, e));
+            }
+        }
+        self.flush_batch(&datadog, &mut report);
+        self.report_runtime(started, &datadog, &mut report);
+        self.report_freshness(&datadog, &report);
+        report.failovers = datadog.failovers();
+
+        report.summary.log();
+        Ok(report)
+    }
+
+    /// Sends archived raw responses through the current transformation
+    ///
+    /// Disaster recovery for `ghrust replay`: the enterprise and team windows
+    /// fetched on the given days are read back from the raw response archive,
+    /// merged by metric date and sent like `send_bundle` sends a fetched
+    /// bundle, under the namespaces and tags of the current configuration. No
+    /// GitHub request is made for the metrics. A scope that can't be read is
+    /// recorded as failed; one with nothing archived is skipped.
+    ///
+    /// # Arguments
+    ///
+    /// * `archive` - Archive the responses were kept in
+    /// * `days` - Days the responses were fetched on, oldest first
+    ///
+    /// # Returns
+    ///
+    /// * `Result<RunReport>` - The report of the replay
+    pub fn replay(&self, archive: &RawArchive, days: &[NaiveDate]) -> Result<RunReport> {
+        let mut report = RunReport::default();
+        let span = info_span!("run", run_id = %report.run_id);
+        let _run = span.enter();
+        let config = &self.config;
+        // Only used to locate and parse the archived responses, so replays
+        // work without a GitHub token
+        let github_client = GitHubClient::builder()
+            .settings(config.github_settings.clone())
+            .build_offline();
+        let days: Vec<String> = days.iter().map(|day| day.to_string()).collect();
+
+        let mut bundle = MetricsBundle::new();
+        for enterprise_config in self.enterprises().iter() {
+            let enterprise_id = &enterprise_config.id;
+            let namespace = &enterprise_config.namespace;
+
+            if !config.skip_enterprise {
+                let scope_name = format!("enterprise:{}", enterprise_id);
+                let result = report
+                    .summary
+                    .track(scope_name.as_str(), namespace, |scope| {
+                        let read = |day: &str| {
+                            enterprise::archived_with_org_fallback(
+                                || {
+                                    github_client.archived_enterprise_metrics(
+                                        archive,
+                                        day,
+                                        enterprise_id,
+                                    )
+                                },
+                                || github_client.archived_org_metrics(archive, day, enterprise_id),
+                            )
+                            .map_err(GhrustError::github("Failed to read archived metrics"))
+                        };
+                        let tag_context = TagContext::enterprise(enterprise_id);
+                        handoff::replay_scope(&days, read, namespace, &[], tag_context, scope)
+                    });
+                match result {
+                    Ok(fetched) => bundle.scopes.extend(fetched),
+                    Err(e) => {
+                        warn!("Error reading the archive of {}: {}", scope_name, e);
+                        report.failures.push(format!("{}: {}", scope_name, e));
+                    }
+                }
+            }
+
+            let slugs = &enterprise_config.team_slugs;
+            let team_mappings = self.team_mappings(namespace, slugs);
+            for slug in slugs {
+                let mapping = team_mappings.get(slug);
+                let team_ns =
+                    team::team_namespace(namespace, &config.namespace_template, slug, mapping);
+                let tags = team::team_tags(&config.namespace_template, slug, mapping);
+                let scope_name = format!("team:{}/{}", enterprise_id, slug);
+                let result = report
+                    .summary
+                    .track(scope_name.as_str(), &team_ns, |scope| {
+                        let read = |day: &str| {
+                            enterprise::archived_with_org_fallback(
+                                || {
+                                    github_client.archived_team_metrics(
+                                        archive,
+                                        day,
+                                        enterprise_id,
+                                        slug,
+                                    )
+                                },
+                                || {
+                                    github_client.archived_org_team_metrics(
+                                        archive,
+                                        day,
+                                        enterprise_id,
+                                        slug,
+                                    )
+                                },
+                            )
+                            .map_err(GhrustError::github("Failed to read archived metrics"))
+                        };
+                        let tag_context = TagContext::team(enterprise_id, slug);
+                        handoff::replay_scope(&days, read, &team_ns, &tags, tag_context, scope)
+                    });
+                match result {
+                    Ok(fetched) => bundle.scopes.extend(fetched),
+                    Err(e) => {
+                        warn!("Error reading the archive of {}: {}", scope_name, e);
+                        report.failures.push(format!("{}: {}", scope_name, e));
+                    }
+                }
+            }
+        }
+        // Scopes that were read are tracked again when they are sent
+        report.summary.scopes.retain(|s| s.error.is_some());
+
+        self.send_scopes(&bundle, report)
+    }
+
+    /// Submits the series queued by every scope, if batching is enabled
+    ///
+    /// The submission is recorded as the `datadog:batch` scope; a failure
+    /// fails the run.
+    ///
+    /// # Arguments
+    ///
+    /// * `datadog` - Sink the scopes queued their series in
+    /// * `report` - Report of the run
+    fn flush_batch(&self, datadog: &DatadogSink, report: &mut RunReport) {
+        let config = &self.config;
+        if !config.datadog_batch_send {
+            return;
+        }
+
+        let sent = report
+            .summary
+            .track(BATCH_SCOPE, &config.datadog_namespace, |scope| {
+                let before = datadog.stats();
+                let sent = datadog.flush();
+                scope.record_send(&before, &datadog.stats());
+                sent?;
+                Ok(())
+            });
+        if let Err(e) = sent {
+            warn!("Error sending the batched series: {}", e);
+            report.failures.push(format!("batched Datadog send: {}", e));
+        }
+    }
+
+    /// Returns the configured enterprises with their discovered teams added
+    ///
+    /// Without team discovery the configured enterprises are returned as is.
+    /// An enterprise whose teams can't be listed keeps its configured teams.
+    /// Tenants are left out: only complete runs process them, in isolation.
+    fn enterprises(&self) -> Cow<'_, [EnterpriseConfig]> {
+        let config = &self.config;
+        let mut enterprises = Cow::Borrowed(config.enterprises.as_slice());
+        if enterprises.iter().any(|e| e.tenant.is_some()) {
+            for tenant in enterprises.iter().filter(|e| e.tenant.is_some()) {
+                warn!(
+                    "Skipping tenant {}; tenants are only processed by complete runs",
+                    tenant.id
+                );
+            }
+            enterprises = Cow::Owned(
+                config
+                    .enterprises
+                    .iter()
+                    .filter(|e| e.tenant.is_none())
+                    .cloned()
+                    .collect(),
+            );
+        }
+        let Some(discovery) = &config.team_discovery else {
+            return enterprises;
+        };
+        let client = match config.github_client().build() {
+            Ok(client) => client,
+            Err(e) => {
+                warn!("Team discovery skipped, the GitHub client failed: {}", e);
+                return enterprises;
+            }
+        };
+        let cache = discovery
+            .cache_path
+            .as_ref()
+            .map(|path| TeamCache::open(path, discovery.cache_ttl));
+
+        let enterprises = enterprises.iter().map(|enterprise| {
+            let mut enterprise = enterprise.clone();
+            match teams::discover_teams(&client, &enterprise.id, cache.as_ref(), self.refresh_teams)
+            {
+                Ok(discovered) => {
+                    for slug in discovered {
+                        if !enterprise.team_slugs.contains(&slug) {
+                            enterprise.team_slugs.push(slug);
+                        }
+                    }
+                }
+                Err(e) => warn!(
+                    "Error discovering the teams of {}, using the configured teams: {}",
+                    enterprise.id, e
+                ),
+            }
+            enterprise
+        });
+        Cow::Owned(enterprises.collect())
+    }
+
+    /// Opens the raw response archive (`GITHUB_RAW_ARCHIVE`), if configured
+    ///
+    /// Archiving is best effort, so an archive that can't be opened is logged
+    /// and the run continues without it.
+    fn raw_archive(&self) -> Option<Arc<RawArchive>> {
+        let location = self.config.raw_archive.as_deref()?;
+        match RawArchive::open(location, &self.config.agent) {
+            Ok(archive) => Some(Arc::new(archive)),
+            Err(e) => {
+                warn!("Raw response archive {} disabled: {}", location, e);
+                None
+            }
+        }
+    }
+
+    /// Records the GitHub requests of the run in its summary and sends them,
+    /// with the rate limit left, as collector metrics
+    ///
+    /// # Arguments
+    ///
+    /// * `before` - Budget snapshot taken at the start of the run
+    /// * `datadog` - Sink the collector metrics are sent to
+    /// * `report` - Report of the run
+    fn report_budget(
+        &self,
+        before: &RateLimitBudget,
+        datadog: &DatadogSink,
+        report: &mut RunReport,
+    ) {
+        let summary = &mut report.summary;
+        summary.github = RateLimitBudget::snapshot().since(before);
+        let today = Utc::now().format("%Y-%m-%d").to_string();
+        let gauges = self.collector_gauges(summary.github.gauges(&today), &report.run_id);
+        if let Err(e) = datadog.send_gauges(&gauges, &self.config.datadog_namespace) {
+            warn!("Error sending the GitHub rate limit budget: {}", e);
+        }
+    }
+
+    /// Adds the collector tags and, if configured, the `run_id` tag to
+    /// collector metrics
+    ///
+    /// # Arguments
+    ///
+    /// * `gauges` - The collector metrics
+    /// * `run_id` - ID of the run
+    fn collector_gauges(&self, gauges: Vec<Gauge>, run_id: &str) -> Vec<Gauge> {
+        let mut tags = self.collector_tags.clone();
+        if self.config.collector_run_id_tag {
+            tags.push(format!("run_id:{}", run_id));
+        }
+        if tags.is_empty() {
+            return gauges;
+        }
+        gauges
+            .into_iter()
+            .map(|gauge| gauge.with_tags(tags.clone()))
+            .collect()
+    }
+
+    /// Sends `collector.heartbeat` before anything is set up or fetched
+    ///
+    /// The series is sent even if the metrics store, an exporter or the
+    /// GitHub client can't be set up, or every GitHub request of the run
+    /// fails, so a missing heartbeat means the collector didn't run at all
+    /// rather than GitHub returning nothing.
+    ///
+    /// # Arguments
+    ///
+    /// * `datadog` - Sink the heartbeat is sent to
+    /// * `run_id` - ID of the run
+    fn send_heartbeat(&self, datadog: &DatadogSink, run_id: &str) {
+        let today = Utc::now().format("%Y-%m-%d").to_string();
+        let gauges =
+            self.collector_gauges(vec![Gauge::new("collector.heartbeat", 1.0, today)], run_id);
+        let sent = datadog
+            .send_gauges(&gauges, &self.config.datadog_namespace)
+            .and_then(|_| datadog.flush());
+        if let Err(e) = sent {
+            warn!("Error sending the collector heartbeat: {}", e);
+        }
+    }
+
+    /// Records the duration and Datadog counters of the run in its summary
+    /// and sends them, with the fetch time and duration of every scope, as
+    /// collector metrics
+    ///
+    /// Sent after the batched series were flushed, so the counters include
+    /// them; the runtime gauges themselves are flushed right away.
+    ///
+    /// # Arguments
+    ///
+    /// * `started` - When the run started
+    /// * `datadog` - Sink the collector metrics are sent to
+    /// * `report` - Report of the run
+    fn report_runtime(&self, started: Instant, datadog: &DatadogSink, report: &mut RunReport) {
+        let summary = &mut report.summary;
+        summary.duration_ms = started.elapsed().as_millis() as u64;
+        summary.datadog = datadog.stats();
+        let today = Utc::now().format("%Y-%m-%d").to_string();
+        let team_salt = self.config.team_hashing.as_ref().map(|h| h.salt.as_str());
+        let gauges =
+            self.collector_gauges(summary.runtime_gauges(&today, team_salt), &report.run_id);
+        let sent = datadog
+            .send_gauges(&gauges, &self.config.datadog_namespace)
+            .and_then(|_| datadog.flush());
+        if let Err(e) = sent {
+            warn!("Error sending the collector runtime metrics: {}", e);
+        }
+    }
+
+    /// Sends `collector.last_success_timestamp` to every namespace that
+    /// received series from a successful scope, so each dashboard can show the
+    /// freshness of its own data and alert when it goes stale
+    ///
+    /// # Arguments
+    ///
+    /// * `datadog` - Sink the markers are sent to
+    /// * `report` - Report of the run
+    fn report_freshness(&self, datadog: &DatadogSink, report: &RunReport) {
+        let summary = &report.summary;
+        let batch_failed = summary
+            .scopes
+            .iter()
+            .any(|s| s.scope == BATCH_SCOPE && s.error.is_some());
+        let now = Utc::now();
+        let today = now.format("%Y-%m-%d").to_string();
+        let marker = self.collector_gauges(
+            vec![Gauge::new(
+                "collector.last_success_timestamp",
+                now.timestamp() as f64,
+                today,
+            )],
+            &report.run_id,
+        );
+        for namespace in summary.succeeded_namespaces(batch_failed) {
+            if let Err(e) = datadog.send_gauges(&marker, namespace) {
+                warn!("Error sending the success marker of {}: {}", namespace, e);
+            }
+        }
+        if let Err(e) = datadog.flush() {
+            warn!("Error sending the success markers: {}", e);
+        }
+    }
+
+    /// Returns the team mappings used for the teams of an enterprise
+    ///
+    /// In privacy mode unmapped teams are sent under hashed namespaces, and
+    /// their hashes are recorded in the mapping file.
+    ///
+    /// # Arguments
+    ///
+    /// * `namespace` - The enterprise namespace
+    /// * `slugs` - Slugs of the processed teams
+    fn team_mappings(
+        &self,
+        namespace: &str,
+        slugs: &[String],
+    ) -> Cow<'_, HashMap<String, TeamMapping>> {
+        let config = &self.config;
+        match &config.team_hashing {
+            Some(hashing) => {
+                let hashes = privacy::team_hashes(slugs, &config.team_mappings, &hashing.salt);
+                if let Some(path) = &hashing.mapping_file {
+                    if let Err(e) = privacy::update_mapping_file(Path::new(path), &hashes) {
+                        warn!("Error updating team hash mapping file {}: {}", path, e);
+                    }
+                }
+                Cow::Owned(privacy::hash_team_namespaces(
+                    namespace,
+                    &config.namespace_template,
+                    slugs,
+                    &config.team_mappings,
+                    &hashing.salt,
+                ))
+            }
+            None => Cow::Borrowed(&config.team_mappings),
+        }
+    }
+
+    /// Creates or updates the standard dashboard of a namespace in the
+    /// primary Datadog organization
+    ///
+    /// # Arguments
+    ///
+    /// * `namespace` - The enterprise namespace
+    ///
+    /// # Returns
+    ///
+    /// * `Result<String>` - The ID of the provisioned dashboard
+    fn provision_dashboard(&self, namespace: &str) -> Result<String> {
+        let config = &self.config;
+        let primary = &config.datadog_destinations[0];
+        let app_key = config
+            .datadog_app_key
+            .as_deref()
+            .ok_or_else(|| ConfigError::Missing("DATADOG_APP_KEY".to_string()))?;
+
+        let provisioner =
+            DashboardProvisioner::new(&primary.api_key, app_key, &primary.site, &config.agent);
+        let namespace = primary.map_namespace(&config.datadog_namespace, namespace);
+        Ok(provisioner.provision(&namespace)?)
+    }
+
+    /// Brings the metadata of a namespace's standard metrics up to date in
+    /// the primary Datadog organization
+    ///
+    /// # Arguments
+    ///
+    /// * `namespace` - The enterprise namespace
+    ///
+    /// # Returns
+    ///
+    /// * `Result<MetadataOutcome>` - How many metrics were updated, already up
+    ///   to date or not received yet
+    fn update_metric_metadata(&self, namespace: &str) -> Result<MetadataOutcome> {
+        let config = &self.config;
+        let primary = &config.datadog_destinations[0];
+        let app_key = config
+            .datadog_app_key
+            .as_deref()
+            .ok_or_else(|| ConfigError::Missing("DATADOG_APP_KEY".to_string()))?;
+
+        let updater = MetadataUpdater::new(&primary.api_key, app_key, &primary.site, &config.agent);
+        let namespace = primary.map_namespace(&config.datadog_namespace, namespace);
+        Ok(updater.update(&namespace)?)
+    }
+}
+
+/// Converts the outcome of a backfilled scope into its JSON status
+///
+/// # Arguments
+///
+/// * `result` - Number of backfilled days, or the error of the scope
+/// * `failures` - Failures of the run, extended with a failed scope
+/// * `label` - Describes the scope in the failure, e.g. "acme enterprise metrics"
+fn backfill_status(
+    result: Result<usize>,
+    failures: &mut Vec<String>,
+    label: impl FnOnce() -> String,
+) -> Value {
+    match result {
+        Ok(days) => json!({ "days": days }),
+        Err(e) => {
+            let label = label();
+            warn!("Error backfilling {}: {}", label, e);
+            failures.push(format!("{}: {}", label, e));
+            json!({ "error": e.to_string() })
+        }
+    }
+}
+
+/// Records the outcome of one processing step of an enterprise
+///
+/// On success the step's value is stored under `key` of the status; on
+/// failure the error is logged, stored instead and added to the failures.
+///
+/// # Arguments
+///
+/// * `status` - JSON status of the enterprise, holding its `enterprise_id`
+/// * `failures` - Failures of the run, extended with a failed step
+/// * `key` - Key of the step in the status, e.g. "weekly_rollups"
+/// * `label` - Describes the step in logs and failures, e.g. "weekly rollups"
+/// * `result` - Status value of the step, or its error
+fn record_step<E: std::fmt::Display>(
+    status: &mut Value,
+    failures: &mut Vec<String>,
+    key: &str,
+    label: &str,
+    result: std::result::Result<Value, E>,
+) {
+    status[key] = match result {
+        Ok(value) => value,
+        Err(e) => {
+            let enterprise_id = status["enterprise_id"].as_str().unwrap_or_default();
+            warn!("Error processing {} for {}: {}", label, enterprise_id, e);
+            failures.push(format!("{} {}: {}", enterprise_id, label, e));
+            json!({ "error": e.to_string() })
+        }
+    };
+}
@@ -0,0 +1,146 @@
+//! # Adoption Rates
+//!
+//! The metrics API reports how many users are active and engaged, but not how
+//! many could be. This module combines the latest day of a scope's metrics
+//! with the member count of the team or organization (fetched through the
+//! GraphQL API) into adoption rates:
+//!
+//! - `adoption.members`: Members of the team or organization
+//! - `adoption.active_users_rate`: Active users / members
+//! - `adoption.engaged_users_rate`: Engaged users / members
+//!
+//! Adoption is supplemental: a failing member count query is logged and
+//! doesn't fail the scope.
+
+use tracing::{debug, warn};
+
+use super::summary::ScopeSummary;
+use crate::models::github::CopilotMetrics;
+use crate::services::datadog::{DatadogSink, Gauge};
+use crate::services::github::GitHubClient;
+
+/// Adoption of a team or organization on a single day
+#[derive(Debug, Clone, PartialEq)]
+pub struct Adoption {
+    /// Date (YYYY-MM-DD) of the usage numbers
+    pub date: String,
+    /// Members of the team or organization
+    pub members: i64,
+    /// Users active on the date
+    pub active_users: i64,
+    /// Users engaged on the date
+    pub engaged_users: i64,
+}
+
+impl Adoption {
+    /// Computes the adoption from the most recent day of a metrics window
+    ///
+    /// # Arguments
+    ///
+    /// * `metrics` - Daily metrics in any order
+    /// * `members` - Members of the team or organization
+    ///
+    /// # Returns
+    ///
+    /// * `Option<Adoption>` - The adoption, or `None` if there are no days or
+    ///   no members
+    pub fn from_metrics(metrics: &[CopilotMetrics], members: i64) -> Option<Self> {
+        let latest = metrics.iter().max_by(|a, b| a.date.cmp(&b.date))?;
+        if members <= 0 {
+            return None;
+        }
+
+        Some(Self {
+            date: latest.date.clone(),
+            members,
+            active_users: latest.total_active_users.unwrap_or(0),
+            engaged_users: latest.total_engaged_users.unwrap_or(0),
+        })
+    }
+
+    /// Share of members that were active (0.0 to 1.0)
+    pub fn active_users_rate(&self) -> f64 {
+        self.active_users as f64 / self.members as f64
+    }
+
+    /// Share of members that were engaged (0.0 to 1.0)
+    pub fn engaged_users_rate(&self) -> f64 {
+        self.engaged_users as f64 / self.members as f64
+    }
+
+    /// Converts the adoption to gauges below `adoption.*`
+    pub fn to_gauges(&self) -> Vec<Gauge> {
+        vec![
+            Gauge::new("adoption.members", self.members as f64, &self.date),
+            Gauge::new(
+                "adoption.active_users_rate",
+                self.active_users_rate(),
+                &self.date,
+            ),
+            Gauge::new(
+                "adoption.engaged_users_rate",
+                self.engaged_users_rate(),
+                &self.date,
+            ),
+        ]
+    }
+}
+
+/// Where the member count of a scope comes from
+#[derive(Debug, Clone, Copy)]
+pub enum Members<'a> {
+    /// A team of an organization
+    Team { org: &'a str, team_slug: &'a str },
+    /// An organization
+    Org(&'a str),
+}
+
+/// Sends the adoption rates of a scope to Datadog
+///
+/// # Arguments
+///
+/// * `client` - GitHub client used for the member count query
+/// * `members` - The team or organization whose members are counted
+/// * `metrics` - The scope's fetched metrics
+/// * `datadog` - Datadog sink fanning out to the configured destinations
+/// * `namespace` - The scope's namespace
+/// * `extra_tags` - Tags added to every gauge, e.g. the team's mapped tags
+/// * `summary` - Summary of the scope, filled in with send counts
+pub fn send_adoption(
+    client: &GitHubClient,
+    members: Members,
+    metrics: &[CopilotMetrics],
+    datadog: &DatadogSink,
+    namespace: &str,
+    extra_tags: &[String],
+    summary: &mut ScopeSummary,
+) {
+    let count = match members {
+        Members::Team { org, team_slug } => client.fetch_team_member_count(org, team_slug),
+        Members::Org(org) => client.fetch_org_member_count(org),
+    };
+    let count = match count {
+        Ok(count) => count,
+        Err(e) => {
+            warn!("Skipping adoption rates for {}: {}", namespace, e);
+            return;
+        }
+    };
+
+    let Some(adoption) = Adoption::from_metrics(metrics, count) else {
+        debug!("No adoption rates for {}", namespace);
+        return;
+    };
+    let gauges: Vec<Gauge> = adoption
+        .to_gauges()
+        .into_iter()
+        .map(|gauge| gauge.with_tags(extra_tags.to_vec()))
+        .collect();
+
+    let before = datadog.stats();
+    let sent = datadog.send_gauges(&gauges, namespace);
+    summary.record_send(&before, &datadog.stats());
+    if let Err(e) = sent {
+        warn!("Failed to send adoption rates for {}: {}", namespace, e);
+    }
+}
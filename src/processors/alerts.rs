@@ -0,0 +1,272 @@
+//! # Threshold Alerts
+//!
+//! Not every team using the collector has Datadog monitors set up. This module
+//! evaluates simple, configurable rules against the latest day of a scope's
+//! metrics, e.g. "active users dropped by more than 20% day-over-day" or
+//! "the acceptance rate is below 15%", and sends a notification for every rule
+//! that fires.
+//!
+//! Rules are configured as a JSON list in `ALERT_RULES` or the configuration
+//! file's `alert_rules`:
+//!
+//! ```json
+//! [
+//!   { "metric": "active_users", "drop_percent": 20 },
+//!   { "metric": "acceptance_rate", "below": 15 }
+//! ]
+//! ```
+
+use serde::Deserialize;
+use tracing::{info, warn};
+
+use crate::error::Result;
+use crate::models::github::CopilotMetrics;
+use crate::services::notify::{Notification, NotificationKind, NotificationSink, NotifyError};
+
+/// A metric alert rules can be defined on
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AlertMetric {
+    /// Total active users of the day
+    ActiveUsers,
+    /// Total engaged users of the day
+    EngagedUsers,
+    /// Engaged users of IDE code completions
+    CompletionsEngagedUsers,
+    /// Engaged users of IDE chat
+    ChatEngagedUsers,
+    /// Accepted code suggestions in percent of all suggestions
+    AcceptanceRate,
+}
+
+impl AlertMetric {
+    /// Name of the metric in rules and notifications
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::ActiveUsers => "active_users",
+            Self::EngagedUsers => "engaged_users",
+            Self::CompletionsEngagedUsers => "completions_engaged_users",
+            Self::ChatEngagedUsers => "chat_engaged_users",
+            Self::AcceptanceRate => "acceptance_rate",
+        }
+    }
+
+    /// Returns the metric's value for a day, if the day reports it
+    ///
+    /// # Arguments
+    ///
+    /// * `metric` - The day's metrics
+    pub fn value(&self, metric: &CopilotMetrics) -> Option<f64> {
+        match self {
+            Self::ActiveUsers => metric.total_active_users.map(|v| v as f64),
+            Self::EngagedUsers => metric.total_engaged_users.map(|v| v as f64),
+            Self::CompletionsEngagedUsers => metric
+                .copilot_ide_code_completions
+                .as_ref()
+                .map(|c| c.total_engaged_users as f64),
+            Self::ChatEngagedUsers => metric
+                .copilot_ide_chat
+                .as_ref()
+                .map(|c| c.total_engaged_users as f64),
+            Self::AcceptanceRate => {
+                let languages = metric
+                    .copilot_ide_code_completions
+                    .as_ref()?
+                    .languages
+                    .as_ref()?;
+                let suggestions: i64 = languages
+                    .iter()
+                    .map(|l| l.total_code_suggestions.unwrap_or(0))
+                    .sum();
+                let acceptances: i64 = languages
+                    .iter()
+                    .map(|l| l.total_code_acceptances.unwrap_or(0))
+                    .sum();
+                (suggestions > 0).then(|| 100.0 * acceptances as f64 / suggestions as f64)
+            }
+        }
+    }
+}
+
+/// A threshold rule on one metric
+///
+/// Exactly one condition must be set; the configuration loader rejects rules
+/// with none or both.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct AlertRule {
+    /// The metric the rule watches
+    pub metric: AlertMetric,
+
+    /// Fires when the metric dropped by more than this percentage against
+    /// the previous day
+    #[serde(default)]
+    pub drop_percent: Option<f64>,
+
+    /// Fires when the metric is below this value on the latest day
+    #[serde(default)]
+    pub below: Option<f64>,
+}
+
+/// A rule that fired on the latest day of a scope
+#[derive(Debug, Clone, PartialEq)]
+pub struct Alert {
+    /// Scope the metrics belong to, e.g. "enterprise:acme"
+    pub scope: String,
+    /// The metric that triggered the alert
+    pub metric: AlertMetric,
+    /// Date of the latest day
+    pub date: String,
+    /// Value of the metric on the latest day
+    pub value: f64,
+    /// Description of the violated condition
+    pub condition: String,
+}
+
+impl Alert {
+    /// Builds the notification announcing the alert
+    pub fn notification(&self) -> Notification {
+        Notification {
+            kind: NotificationKind::Alert,
+            title: format!("Copilot alert for {}: {}", self.scope, self.metric.name()),
+            message: format!(
+                "{} was {:.1} on {} ({})",
+                self.metric.name(),
+                self.value,
+                self.date,
+                self.condition
+            ),
+            dedup_key: dedup_key(&self.scope, self.metric),
+        }
+    }
+}
+
+/// Returns the deduplication key of the alerts on a metric of a scope
+///
+/// # Arguments
+///
+/// * `scope` - Scope the metrics belong to, e.g. "enterprise:acme"
+/// * `metric` - The metric the alerts are about
+pub fn dedup_key(scope: &str, metric: AlertMetric) -> String {
+    format!("{}:{}", scope, metric.name())
+}
+
+/// Evaluates rules against the latest day of a scope's metrics
+///
+/// Drop conditions compare the latest day with the day before it; they
+/// don't fire if either day doesn't report the metric or the previous value
+/// is zero.
+///
+/// # Arguments
+///
+/// * `scope` - Scope the metrics belong to, e.g. "enterprise:acme"
+/// * `rules` - The configured rules
+/// * `metrics` - The fetched days, in any order
+///
+/// # Returns
+///
+/// * `Vec<Alert>` - One alert per rule that fired, in rule order
+pub fn evaluate(scope: &str, rules: &[AlertRule], metrics: &[CopilotMetrics]) -> Vec<Alert> {
+    let mut days: Vec<&CopilotMetrics> = metrics.iter().collect();
+    days.sort_by(|a, b| a.date.cmp(&b.date));
+    let Some((latest, earlier)) = days.split_last() else {
+        return Vec::new();
+    };
+    let previous = earlier.last();
+
+    let mut alerts = Vec::new();
+    for rule in rules {
+        let Some(value) = rule.metric.value(latest) else {
+            continue;
+        };
+
+        let mut conditions = Vec::new();
+        if let Some(below) = rule.below {
+            if value < below {
+                conditions.push(format!("below {}", below));
+            }
+        }
+        if let Some(drop_percent) = rule.drop_percent {
+            let before = previous.and_then(|p| rule.metric.value(p));
+            if let Some(before) = before.filter(|b| *b > 0.0) {
+                let drop = 100.0 * (before - value) / before;
+                if drop > drop_percent {
+                    conditions.push(format!(
+                        "dropped {:.1}% from {:.1} the day before, more than {}%",
+                        drop, before, drop_percent
+                    ));
+                }
+            }
+        }
+
+        if !conditions.is_empty() {
+            alerts.push(Alert {
+                scope: scope.to_string(),
+                metric: rule.metric,
+                date: latest.date.clone(),
+                value,
+                condition: conditions.join(", "),
+            });
+        }
+    }
+
+    alerts
+}
+
+/// Evaluates the rules for a scope and notifies about every alert
+///
+/// Alerts are always logged; without notification channels nothing is sent.
+/// Alerts on the metrics of rules that no longer fire are resolved, so
+/// channels that keep open alerts (Opsgenie) close them.
+///
+/// # Arguments
+///
+/// * `scope` - Scope the metrics belong to, e.g. "enterprise:acme"
+/// * `rules` - The configured rules
+/// * `metrics` - The fetched days
+/// * `notifications` - Channels the alerts are sent to
+///
+/// # Returns
+///
+/// * `Result<usize>` - Number of alerts that fired, or an error if a
+///   notification could not be delivered
+pub fn process_alerts(
+    scope: &str,
+    rules: &[AlertRule],
+    metrics: &[CopilotMetrics],
+    notifications: &NotificationSink,
+) -> Result<usize> {
+    let alerts = evaluate(scope, rules, metrics);
+    if alerts.is_empty() {
+        info!("No alert rules fired for {}", scope);
+    }
+
+    let mut failures = Vec::new();
+    for alert in &alerts {
+        let notification = alert.notification();
+        warn!("{}: {}", notification.title, notification.message);
+        if !notifications.is_empty() {
+            if let Err(e) = notifications.notify(&notification) {
+                failures.push(e.to_string());
+            }
+        }
+    }
+
+    if !notifications.is_empty() {
+        let mut resolved: Vec<AlertMetric> = Vec::new();
+        for rule in rules {
+            let fired = alerts.iter().any(|a| a.metric == rule.metric);
+            if fired || resolved.contains(&rule.metric) {
+                continue;
+            }
+            resolved.push(rule.metric);
+            if let Err(e) = notifications.resolve(&dedup_key(scope, rule.metric)) {
+                failures.push(e.to_string());
+            }
+        }
+    }
+
+    if !failures.is_empty() {
+        return Err(NotifyError::Notifiers(failures).into());
+    }
+    Ok(alerts.len())
+}
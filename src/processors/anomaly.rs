@@ -0,0 +1,180 @@
+//! # Anomaly Detection
+//!
+//! Flags days whose usage deviates sharply from the rest of the reporting
+//! window, e.g. active users collapsing because of a Copilot outage or a
+//! broken license assignment. For every key metric the latest day is compared
+//! with the earlier days of the 30-day window using a z-score (the distance
+//! from the mean in standard deviations); a score beyond the configured sigma
+//! marks the metric as anomalous.
+//!
+//! Scores are sent as `{namespace}.anomaly.z_score` tagged with the metric and
+//! `anomalous:true|false`, and the number of anomalous metrics as
+//! `{namespace}.anomaly.count`, so a single monitor can watch for outages.
+
+use tracing::{info, warn};
+
+use super::alerts::AlertMetric;
+use super::summary::ScopeSummary;
+use crate::error::Result;
+use crate::models::github::CopilotMetrics;
+use crate::services::datadog::{DatadogSink, Gauge};
+
+/// Metrics scored for anomalies
+pub const KEY_METRICS: [AlertMetric; 5] = [
+    AlertMetric::ActiveUsers,
+    AlertMetric::EngagedUsers,
+    AlertMetric::CompletionsEngagedUsers,
+    AlertMetric::ChatEngagedUsers,
+    AlertMetric::AcceptanceRate,
+];
+
+/// Earlier days a metric needs before its latest day is scored
+pub const MIN_HISTORY_DAYS: usize = 7;
+
+/// Z-score of the latest day of one metric
+#[derive(Debug, Clone, PartialEq)]
+pub struct AnomalyScore {
+    /// The scored metric
+    pub metric: AlertMetric,
+    /// Date of the latest day
+    pub date: String,
+    /// Value of the metric on the latest day
+    pub value: f64,
+    /// Mean of the earlier days
+    pub mean: f64,
+    /// Standard deviation of the earlier days
+    pub std_dev: f64,
+    /// Distance of the value from the mean in standard deviations
+    pub z_score: f64,
+}
+
+impl AnomalyScore {
+    /// Returns whether the score deviates beyond `sigma` in either direction
+    ///
+    /// # Arguments
+    ///
+    /// * `sigma` - Threshold in standard deviations
+    pub fn is_anomalous(&self, sigma: f64) -> bool {
+        self.z_score.abs() > sigma
+    }
+}
+
+/// Scores the latest day of every key metric against the earlier days
+///
+/// Metrics with fewer than `MIN_HISTORY_DAYS` earlier values, or whose
+/// earlier values are all equal, are not scored.
+///
+/// # Arguments
+///
+/// * `metrics` - The fetched days, in any order
+///
+/// # Returns
+///
+/// * `Vec<AnomalyScore>` - One score per scorable key metric
+pub fn score(metrics: &[CopilotMetrics]) -> Vec<AnomalyScore> {
+    let mut days: Vec<&CopilotMetrics> = metrics.iter().collect();
+    days.sort_by(|a, b| a.date.cmp(&b.date));
+    let Some((latest, earlier)) = days.split_last() else {
+        return Vec::new();
+    };
+
+    KEY_METRICS
+        .iter()
+        .filter_map(|metric| {
+            let value = metric.value(latest)?;
+            let history: Vec<f64> = earlier.iter().filter_map(|d| metric.value(d)).collect();
+            if history.len() < MIN_HISTORY_DAYS {
+                return None;
+            }
+
+            let n = history.len() as f64;
+            let mean = history.iter().sum::<f64>() / n;
+            let variance = history.iter().map(|v| (v - mean).powi(2)).sum::<f64>() / n;
+            let std_dev = variance.sqrt();
+            if std_dev == 0.0 {
+                return None;
+            }
+
+            Some(AnomalyScore {
+                metric: *metric,
+                date: latest.date.clone(),
+                value,
+                mean,
+                std_dev,
+                z_score: (value - mean) / std_dev,
+            })
+        })
+        .collect()
+}
+
+/// Converts scores to the gauges sent to Datadog
+///
+/// # Arguments
+///
+/// * `scores` - The scores of the latest day
+/// * `sigma` - Threshold in standard deviations
+pub fn to_gauges(scores: &[AnomalyScore], sigma: f64) -> Vec<Gauge> {
+    let Some(first) = scores.first() else {
+        return Vec::new();
+    };
+
+    let mut gauges: Vec<Gauge> = scores
+        .iter()
+        .map(|s| {
+            Gauge::new("anomaly.z_score", s.z_score, &s.date).with_tags(vec![
+                format!("metric:{}", s.metric.name()),
+                format!("anomalous:{}", s.is_anomalous(sigma)),
+            ])
+        })
+        .collect();
+    let anomalous = scores.iter().filter(|s| s.is_anomalous(sigma)).count();
+    gauges.push(Gauge::new("anomaly.count", anomalous as f64, &first.date));
+    gauges
+}
+
+/// Scores the latest day of a scope and sends the scores to Datadog
+///
+/// # Arguments
+///
+/// * `metrics` - The scope's fetched days
+/// * `sigma` - Threshold in standard deviations
+/// * `datadog` - Datadog sink fanning out to the configured destinations
+/// * `namespace` - The scope's namespace
+/// * `summary` - Summary of the scope, filled in with send counts
+///
+/// # Returns
+///
+/// * `Result<usize>` - Number of anomalous metrics, or an error if the scores
+///   could not be sent
+pub fn process_anomalies(
+    metrics: &[CopilotMetrics],
+    sigma: f64,
+    datadog: &DatadogSink,
+    namespace: &str,
+    summary: &mut ScopeSummary,
+) -> Result<usize> {
+    let scores = score(metrics);
+    if scores.is_empty() {
+        info!("Not enough history to score anomalies for {}", namespace);
+        return Ok(0);
+    }
+
+    for s in scores.iter().filter(|s| s.is_anomalous(sigma)) {
+        warn!(
+            "Anomaly in {}: {} was {:.1} on {} (mean {:.1}, z-score {:.2})",
+            namespace,
+            s.metric.name(),
+            s.value,
+            s.date,
+            s.mean,
+            s.z_score
+        );
+    }
+
+    let before = datadog.stats();
+    let sent = datadog.send_gauges(&to_gauges(&scores, sigma), namespace);
+    summary.record_send(&before, &datadog.stats());
+    sent?;
+
+    Ok(scores.iter().filter(|s| s.is_anomalous(sigma)).count())
+}
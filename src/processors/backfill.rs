@@ -0,0 +1,184 @@
+//! # Per-Day Backfill
+//!
+//! Backfilling a long range in one request means one failure (a timeout, a
+//! rate limit, a Datadog outage) loses the whole range. A backfill instead
+//! requests one day at a time (`since` and `until` equal), sends it, and
+//! records the day in a checkpoint file before moving on, pausing between
+//! days to stay clear of the rate limit.
+//!
+//! The checkpoint keeps the last completed day of every scope
+//! (`{"enterprise:acme": "2025-03-10"}`), so a backfill that failed at day 40
+//! of 90 resumes at day 40 when it is started again with the same file.
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::thread;
+use std::time::Duration;
+
+use chrono::NaiveDate;
+use tracing::{debug, info};
+
+use super::summary::ScopeSummary;
+use crate::error::{GhrustError, Result};
+use crate::models::github::CopilotMetrics;
+
+/// Pause between two days unless configured otherwise
+pub const DEFAULT_DAY_DELAY: Duration = Duration::from_secs(1);
+
+/// Days to backfill and how fast
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BackfillOptions {
+    /// First day to backfill (inclusive)
+    pub since: NaiveDate,
+    /// Last day to backfill (inclusive)
+    pub until: NaiveDate,
+    /// Pause between two day requests of a scope
+    pub day_delay: Duration,
+}
+
+impl BackfillOptions {
+    /// Creates options for a range with the default pause between days
+    ///
+    /// # Arguments
+    ///
+    /// * `since` - First day to backfill (inclusive)
+    /// * `until` - Last day to backfill (inclusive)
+    pub fn new(since: NaiveDate, until: NaiveDate) -> Self {
+        Self {
+            since,
+            until,
+            day_delay: DEFAULT_DAY_DELAY,
+        }
+    }
+
+    /// Sets the pause between two day requests
+    pub fn with_day_delay(mut self, day_delay: Duration) -> Self {
+        self.day_delay = day_delay;
+        self
+    }
+}
+
+/// Last completed day of every backfilled scope, kept in a JSON file
+#[derive(Debug)]
+pub struct BackfillCheckpoint {
+    /// Path of the checkpoint file
+    path: PathBuf,
+    /// Last completed day keyed by scope
+    completed: BTreeMap<String, NaiveDate>,
+}
+
+impl BackfillCheckpoint {
+    /// Opens a checkpoint file, starting empty if it doesn't exist
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - Path of the checkpoint file
+    ///
+    /// # Returns
+    ///
+    /// * `io::Result<BackfillCheckpoint>` - The checkpoint, or an error if the
+    ///   file can't be read or parsed (rather than restarting from scratch)
+    pub fn open(path: impl Into<PathBuf>) -> io::Result<Self> {
+        let path = path.into();
+        let completed = match fs::read(&path) {
+            Ok(contents) => serde_json::from_slice(&contents)
+                .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => BTreeMap::new(),
+            Err(e) => return Err(e),
+        };
+        Ok(Self { path, completed })
+    }
+
+    /// Path of the checkpoint file
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Returns the last completed day of a scope, if any
+    pub fn last_completed(&self, scope: &str) -> Option<NaiveDate> {
+        self.completed.get(scope).copied()
+    }
+
+    /// Records a completed day and writes the file
+    ///
+    /// # Arguments
+    ///
+    /// * `scope` - Scope the day belongs to, e.g. "enterprise:acme"
+    /// * `day` - The completed day
+    pub fn record(&mut self, scope: &str, day: NaiveDate) -> io::Result<()> {
+        self.completed.insert(scope.to_string(), day);
+        let contents = serde_json::to_vec_pretty(&self.completed)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+        fs::write(&self.path, contents)
+    }
+}
+
+/// Backfills a scope one day at a time
+///
+/// Days up to the scope's last completed day in the checkpoint are skipped.
+/// Every other day is fetched, sent if GitHub reported it, and then recorded
+/// in the checkpoint; the first failure stops the scope, leaving the
+/// checkpoint at the day before.
+///
+/// # Arguments
+///
+/// * `options` - Days to backfill and the pause between them
+/// * `checkpoint` - Progress of earlier attempts, updated after every day
+/// * `summary` - Summary of the scope; its `scope` keys the checkpoint
+/// * `fetch_day` - Fetches one day (YYYY-MM-DD) from GitHub
+/// * `send` - Sends the metrics of one day to Datadog, recording the send
+///   counts in the summary
+///
+/// # Returns
+///
+/// * `Result<usize>` - Number of days backfilled in this attempt, or the
+///   error of the failed day
+pub fn backfill_scope<F, S>(
+    options: &BackfillOptions,
+    checkpoint: &mut BackfillCheckpoint,
+    summary: &mut ScopeSummary,
+    mut fetch_day: F,
+    mut send: S,
+) -> Result<usize>
+where
+    F: FnMut(&str) -> Result<Vec<CopilotMetrics>>,
+    S: FnMut(&[CopilotMetrics], &mut ScopeSummary) -> Result<()>,
+{
+    let scope = summary.scope.clone();
+    let start = match checkpoint.last_completed(&scope) {
+        Some(done) if done >= options.since => {
+            info!("Resuming backfill of {} after {}", scope, done);
+            done.succ_opt().unwrap_or(done)
+        }
+        _ => options.since,
+    };
+
+    let mut days = 0;
+    for day in start.iter_days().take_while(|day| *day <= options.until) {
+        if days > 0 && !options.day_delay.is_zero() {
+            thread::sleep(options.day_delay);
+        }
+
+        let date = day.format("%Y-%m-%d").to_string();
+        let metrics = fetch_day(&date)?;
+        if metrics.is_empty() {
+            debug!("No metrics reported for {} on {}", scope, date);
+        } else {
+            summary.dates_fetched += metrics.len();
+            send(&metrics, summary)?;
+        }
+
+        checkpoint
+            .record(&scope, day)
+            .map_err(|e| GhrustError::Checkpoint(checkpoint.path().display().to_string(), e))?;
+        days += 1;
+    }
+
+    info!(
+        "Backfilled {} days of {} up to {}",
+        days, scope, options.until
+    );
+    Ok(days)
+}
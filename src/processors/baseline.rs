@@ -0,0 +1,302 @@
+//! # Long-term Baseline
+//!
+//! GitHub only returns the last 30 days of metrics, which is too short to
+//! compare against last quarter. This module keeps weekly aggregates of every
+//! enterprise in a JSON baseline file in S3, merges the complete weeks of each
+//! run into it, and reports the latest complete week next to the same week one
+//! quarter (13 weeks) earlier:
+//!
+//! * `baseline.week.{metric}` - The latest complete week
+//! * `baseline.quarter_ago.{metric}` - The same week one quarter earlier
+//! * `baseline.change_percent.{metric}` - Change against the quarter-ago week
+//!
+//! for the metrics `active_users` and `engaged_users` (daily averages) and
+//! `acceptance_rate` (percent). The comparison gauges are only sent once the
+//! baseline reaches back far enough.
+
+use std::collections::BTreeMap;
+
+use chrono::{Datelike, Duration, NaiveDate};
+use serde::{Deserialize, Serialize};
+use tracing::{info, warn};
+
+use super::summary::ScopeSummary;
+use crate::error::Result;
+use crate::models::github::CopilotMetrics;
+use crate::services::aws::{AwsCredentials, S3Client};
+use crate::services::datadog::{DatadogSink, Gauge};
+use crate::services::store::StoreError;
+use crate::services::tls::AgentConfig;
+
+/// Weeks kept per scope in the baseline file
+pub const RETAINED_WEEKS: usize = 104;
+
+/// Distance between a week and the week it is compared with
+pub const QUARTER_WEEKS: i64 = 13;
+
+/// Aggregated metrics of one complete week (Monday to Sunday)
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct WeeklyAggregate {
+    /// Monday of the week
+    pub week_start: NaiveDate,
+    /// Average daily active users
+    pub average_active_users: f64,
+    /// Average daily engaged users
+    pub average_engaged_users: f64,
+    /// Code suggestions of the week
+    pub code_suggestions: i64,
+    /// Accepted code suggestions of the week
+    pub code_acceptances: i64,
+}
+
+impl WeeklyAggregate {
+    /// Accepted suggestions in percent of all suggestions, if there were any
+    pub fn acceptance_rate(&self) -> Option<f64> {
+        (self.code_suggestions > 0)
+            .then(|| 100.0 * self.code_acceptances as f64 / self.code_suggestions as f64)
+    }
+
+    /// The values reported for the week, by metric name
+    fn values(&self) -> Vec<(&'static str, Option<f64>)> {
+        vec![
+            ("active_users", Some(self.average_active_users)),
+            ("engaged_users", Some(self.average_engaged_users)),
+            ("acceptance_rate", self.acceptance_rate()),
+        ]
+    }
+}
+
+/// Contents of the baseline file
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Baseline {
+    /// Weekly aggregates by scope (e.g. "enterprise:acme"), oldest first
+    #[serde(default)]
+    pub scopes: BTreeMap<String, Vec<WeeklyAggregate>>,
+}
+
+impl Baseline {
+    /// Merges weeks into a scope, replacing weeks already stored
+    ///
+    /// Only the newest `RETAINED_WEEKS` weeks of the scope are kept.
+    ///
+    /// # Arguments
+    ///
+    /// * `scope` - Scope the weeks belong to
+    /// * `weeks` - The weeks to merge
+    pub fn merge(&mut self, scope: &str, weeks: &[WeeklyAggregate]) {
+        let stored = self.scopes.entry(scope.to_string()).or_default();
+        for week in weeks {
+            stored.retain(|w| w.week_start != week.week_start);
+            stored.push(week.clone());
+        }
+        stored.sort_by_key(|w| w.week_start);
+        let excess = stored.len().saturating_sub(RETAINED_WEEKS);
+        stored.drain(..excess);
+    }
+
+    /// Returns a stored week of a scope
+    ///
+    /// # Arguments
+    ///
+    /// * `scope` - Scope the week belongs to
+    /// * `week_start` - Monday of the week
+    pub fn week(&self, scope: &str, week_start: NaiveDate) -> Option<&WeeklyAggregate> {
+        self.scopes
+            .get(scope)?
+            .iter()
+            .find(|w| w.week_start == week_start)
+    }
+}
+
+/// Aggregates the complete weeks of a metrics window
+///
+/// Weeks missing any day, such as the current week, are skipped. Days with
+/// unparseable dates are ignored.
+///
+/// # Arguments
+///
+/// * `metrics` - Daily metrics in any order
+///
+/// # Returns
+///
+/// * `Vec<WeeklyAggregate>` - The complete weeks, oldest first
+pub fn weekly_aggregates(metrics: &[CopilotMetrics]) -> Vec<WeeklyAggregate> {
+    let mut weeks: BTreeMap<NaiveDate, Vec<&CopilotMetrics>> = BTreeMap::new();
+    for metric in metrics {
+        let Ok(date) = NaiveDate::parse_from_str(&metric.date, "%Y-%m-%d") else {
+            continue;
+        };
+        let week_start = date - Duration::days(date.weekday().num_days_from_monday() as i64);
+        weeks.entry(week_start).or_default().push(metric);
+    }
+
+    weeks
+        .into_iter()
+        .filter(|(_, days)| days.len() == 7)
+        .map(|(week_start, days)| {
+            let average = |f: fn(&CopilotMetrics) -> Option<i64>| -> f64 {
+                days.iter().map(|d| f(d).unwrap_or(0)).sum::<i64>() as f64 / 7.0
+            };
+            let languages = days
+                .iter()
+                .filter_map(|d| d.copilot_ide_code_completions.as_ref())
+                .flat_map(|c| c.languages.iter().flatten());
+            let (code_suggestions, code_acceptances) =
+                languages.fold((0, 0), |(suggestions, acceptances), l| {
+                    (
+                        suggestions + l.total_code_suggestions.unwrap_or(0),
+                        acceptances + l.total_code_acceptances.unwrap_or(0),
+                    )
+                });
+
+            WeeklyAggregate {
+                week_start,
+                average_active_users: average(|d| d.total_active_users),
+                average_engaged_users: average(|d| d.total_engaged_users),
+                code_suggestions,
+                code_acceptances,
+            }
+        })
+        .collect()
+}
+
+/// Builds the gauges comparing a week with the same week a quarter earlier
+///
+/// # Arguments
+///
+/// * `week` - The latest complete week
+/// * `quarter_ago` - The week 13 weeks earlier, if stored
+pub fn comparison_gauges(
+    week: &WeeklyAggregate,
+    quarter_ago: Option<&WeeklyAggregate>,
+) -> Vec<Gauge> {
+    // Report on the Sunday ending the week
+    let date = (week.week_start + Duration::days(6)).to_string();
+    let previous = quarter_ago.map(WeeklyAggregate::values);
+
+    let mut gauges = Vec::new();
+    for (i, (name, value)) in week.values().into_iter().enumerate() {
+        let Some(value) = value else {
+            continue;
+        };
+        gauges.push(Gauge::new(format!("baseline.week.{}", name), value, &date));
+
+        let Some(before) = previous.as_ref().and_then(|p| p[i].1) else {
+            continue;
+        };
+        gauges.push(Gauge::new(
+            format!("baseline.quarter_ago.{}", name),
+            before,
+            &date,
+        ));
+        if before > 0.0 {
+            gauges.push(Gauge::new(
+                format!("baseline.change_percent.{}", name),
+                100.0 * (value - before) / before,
+                &date,
+            ));
+        }
+    }
+    gauges
+}
+
+/// The baseline file in S3
+pub struct BaselineStore {
+    client: S3Client,
+    key: String,
+}
+
+impl BaselineStore {
+    /// Creates a store for the baseline file at `s3://{bucket}/{key}`
+    ///
+    /// # Arguments
+    ///
+    /// * `bucket` - Bucket of the baseline file
+    /// * `key` - Key of the baseline file
+    /// * `agent` - Settings of the HTTP agent
+    ///
+    /// # Returns
+    ///
+    /// * `Result<BaselineStore>` - The store, or an error if the AWS
+    ///   credentials are not available
+    pub fn new(bucket: &str, key: &str, agent: &AgentConfig) -> Result<Self> {
+        Ok(Self {
+            client: S3Client::new(AwsCredentials::from_env()?, bucket, agent),
+            key: key.to_string(),
+        })
+    }
+
+    /// Reads the baseline, or an empty one if the file doesn't exist yet
+    pub fn load(&self) -> Result<Baseline> {
+        match self.client.get_object(&self.key)? {
+            Some(body) => serde_json::from_slice(&body).map_err(|e| {
+                StoreError::Serialization(format!("invalid baseline file {}: {}", self.key, e))
+                    .into()
+            }),
+            None => {
+                info!("No baseline file at {} yet, starting a new one", self.key);
+                Ok(Baseline::default())
+            }
+        }
+    }
+
+    /// Writes the baseline, replacing the file
+    pub fn save(&self, baseline: &Baseline) -> Result<()> {
+        let body = serde_json::to_vec_pretty(baseline)
+            .map_err(|e| StoreError::Serialization(e.to_string()))?;
+        self.client
+            .put_object(&self.key, &body, "application/json")?;
+        Ok(())
+    }
+}
+
+/// Merges a scope's complete weeks into the baseline and sends the
+/// comparison with the same week last quarter
+///
+/// # Arguments
+///
+/// * `scope` - Scope the metrics belong to, e.g. "enterprise:acme"
+/// * `metrics` - The scope's fetched days
+/// * `store` - The baseline file
+/// * `datadog` - Datadog sink fanning out to the configured destinations
+/// * `namespace` - The scope's namespace
+/// * `summary` - Summary of the scope, filled in with send counts
+///
+/// # Returns
+///
+/// * `Result<()>` - Ok, or an error if the baseline can't be read or
+///   written, or the gauges can't be sent
+pub fn process_baseline(
+    scope: &str,
+    metrics: &[CopilotMetrics],
+    store: &BaselineStore,
+    datadog: &DatadogSink,
+    namespace: &str,
+    summary: &mut ScopeSummary,
+) -> Result<()> {
+    let weeks = weekly_aggregates(metrics);
+    let Some(latest) = weeks.last() else {
+        warn!("No complete week in the metrics of {}", scope);
+        return Ok(());
+    };
+
+    let mut baseline = store.load()?;
+    baseline.merge(scope, &weeks);
+    store.save(&baseline)?;
+
+    let quarter_ago = baseline.week(scope, latest.week_start - Duration::weeks(QUARTER_WEEKS));
+    if quarter_ago.is_none() {
+        info!(
+            "Baseline of {} doesn't reach back to the week of {} yet",
+            scope,
+            latest.week_start - Duration::weeks(QUARTER_WEEKS)
+        );
+    }
+
+    let before = datadog.stats();
+    let sent = datadog.send_gauges(&comparison_gauges(latest, quarter_ago), namespace);
+    summary.record_send(&before, &datadog.stats());
+    sent?;
+
+    Ok(())
+}
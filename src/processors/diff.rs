@@ -0,0 +1,193 @@
+//! # Period Comparison
+//!
+//! Compares aggregated metrics between two consecutive periods of a metrics
+//! window, e.g. the last seven days against the seven days before, and reports
+//! the percentage change of every metric.
+//!
+//! User counts are averaged per day, since a period's daily values can't be
+//! summed into unique users; activity counts (suggestions, acceptances, lines)
+//! are summed over the period.
+
+use serde::Serialize;
+
+use crate::models::github::CopilotMetrics;
+
+/// Change of a single metric between two periods
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct MetricChange {
+    /// Name of the metric, e.g. "average_active_users"
+    pub metric: &'static str,
+    /// Value over the previous period
+    pub previous: f64,
+    /// Value over the current period
+    pub current: f64,
+    /// Relative change (0.1 = +10%), or `None` if the previous value is zero
+    pub change: Option<f64>,
+}
+
+/// First and last date of a period
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Period {
+    /// First date (YYYY-MM-DD)
+    pub from: String,
+    /// Last date (YYYY-MM-DD)
+    pub to: String,
+}
+
+/// Comparison of two consecutive periods
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct PeriodComparison {
+    /// The earlier period
+    pub previous: Period,
+    /// The most recent period
+    pub current: Period,
+    /// Change of every metric, in a fixed order
+    pub changes: Vec<MetricChange>,
+}
+
+impl PeriodComparison {
+    /// Compares the most recent `days` of a window with the `days` before
+    ///
+    /// # Arguments
+    ///
+    /// * `metrics` - Daily metrics in any order
+    /// * `days` - Length of each period in days
+    ///
+    /// # Returns
+    ///
+    /// * `Option<PeriodComparison>` - The comparison, or `None` if the window
+    ///   doesn't contain two full periods
+    pub fn from_metrics(metrics: &[CopilotMetrics], days: usize) -> Option<Self> {
+        if days == 0 || metrics.len() < 2 * days {
+            return None;
+        }
+
+        let mut sorted: Vec<&CopilotMetrics> = metrics.iter().collect();
+        sorted.sort_by(|a, b| a.date.cmp(&b.date));
+        let current = &sorted[sorted.len() - days..];
+        let previous = &sorted[sorted.len() - 2 * days..sorted.len() - days];
+
+        let changes = period_totals(previous)
+            .into_iter()
+            .zip(period_totals(current))
+            .map(|((metric, previous), (_, current))| MetricChange {
+                metric,
+                previous,
+                current,
+                change: (previous != 0.0).then(|| (current - previous) / previous),
+            })
+            .collect();
+
+        Some(Self {
+            previous: period(previous),
+            current: period(current),
+            changes,
+        })
+    }
+
+    /// Renders the comparison as a plain-text table
+    pub fn to_table(&self) -> String {
+        let mut table = format!(
+            "{:<32} {:>14} {:>14} {:>9}\n",
+            "metric",
+            format!("{}..", self.previous.from),
+            format!("{}..", self.current.from),
+            "change"
+        );
+        for change in &self.changes {
+            let relative = match change.change {
+                Some(change) => format!("{:+.1}%", change * 100.0),
+                None => "n/a".to_string(),
+            };
+            table.push_str(&format!(
+                "{:<32} {:>14.2} {:>14.2} {:>9}\n",
+                change.metric, change.previous, change.current, relative
+            ));
+        }
+        table
+    }
+}
+
+/// Aggregates the metrics of one period, in a fixed order
+pub(crate) fn period_totals(days: &[&CopilotMetrics]) -> Vec<(&'static str, f64)> {
+    let count = days.len() as f64;
+    let average = |value: fn(&CopilotMetrics) -> i64| -> f64 {
+        days.iter().map(|m| value(m)).sum::<i64>() as f64 / count
+    };
+    let total = |value: fn(&CopilotMetrics) -> i64| -> f64 {
+        days.iter().map(|m| value(m)).sum::<i64>() as f64
+    };
+
+    let suggestions = total(|m| sum_languages(m, |l| l.total_code_suggestions));
+    let acceptances = total(|m| sum_languages(m, |l| l.total_code_acceptances));
+    let acceptance_rate = if suggestions > 0.0 {
+        acceptances / suggestions
+    } else {
+        0.0
+    };
+
+    vec![
+        (
+            "average_active_users",
+            average(|m| m.total_active_users.unwrap_or(0)),
+        ),
+        (
+            "average_engaged_users",
+            average(|m| m.total_engaged_users.unwrap_or(0)),
+        ),
+        (
+            "average_code_completion_users",
+            average(|m| {
+                m.copilot_ide_code_completions
+                    .as_ref()
+                    .map_or(0, |c| c.total_engaged_users)
+            }),
+        ),
+        (
+            "average_ide_chat_users",
+            average(|m| {
+                m.copilot_ide_chat
+                    .as_ref()
+                    .map_or(0, |c| c.total_engaged_users)
+            }),
+        ),
+        (
+            "average_dotcom_chat_users",
+            average(|m| {
+                m.copilot_dotcom_chat
+                    .as_ref()
+                    .map_or(0, |c| c.total_engaged_users)
+            }),
+        ),
+        ("total_code_suggestions", suggestions),
+        ("total_code_acceptances", acceptances),
+        ("acceptance_rate", acceptance_rate),
+        (
+            "total_code_lines_accepted",
+            total(|m| sum_languages(m, |l| l.total_code_lines_accepted)),
+        ),
+    ]
+}
+
+/// Sums a code completion language counter over all languages of a day
+fn sum_languages(
+    metric: &CopilotMetrics,
+    value: fn(&crate::models::github::Language) -> Option<i64>,
+) -> i64 {
+    metric
+        .copilot_ide_code_completions
+        .as_ref()
+        .and_then(|c| c.languages.as_ref())
+        .into_iter()
+        .flatten()
+        .map(|l| value(l).unwrap_or(0))
+        .sum()
+}
+
+/// First and last date of a non-empty, sorted period
+fn period(days: &[&CopilotMetrics]) -> Period {
+    Period {
+        from: days[0].date.clone(),
+        to: days[days.len() - 1].date.clone(),
+    }
+}
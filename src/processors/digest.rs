@@ -0,0 +1,276 @@
+//! # Weekly Adoption Digest
+//!
+//! Builds a short weekly summary of Copilot adoption from the fetched metrics
+//! window and emails it through Amazon SES, for stakeholders who don't follow
+//! the Datadog dashboards.
+//!
+//! The digest compares the last seven days with the seven days before and
+//! contains:
+//!
+//! - The daily active user trend and the change in the weekly average
+//! - The overall code suggestion acceptance rate
+//! - The top languages by accepted suggestions
+
+use std::collections::HashMap;
+
+use tracing::{debug, info};
+
+use crate::config::DigestConfig;
+use crate::error::Result;
+use crate::models::github::CopilotMetrics;
+use crate::services::aws::{AwsCredentials, SesClient};
+use crate::services::tls::AgentConfig;
+
+/// Number of days in a digest period
+const PERIOD_DAYS: usize = 7;
+
+/// Number of languages listed in the digest
+const TOP_LANGUAGES: usize = 5;
+
+/// Code completion usage of a single language over the digest period
+#[derive(Debug, Clone, PartialEq)]
+pub struct LanguageUsage {
+    /// Language name as reported by GitHub
+    pub name: String,
+    /// Suggestions shown
+    pub suggestions: i64,
+    /// Suggestions accepted
+    pub acceptances: i64,
+}
+
+/// Aggregated adoption figures for the most recent week
+#[derive(Debug, Clone, PartialEq)]
+pub struct WeeklyDigest {
+    /// Scope the digest describes, e.g. the enterprise ID
+    pub scope: String,
+    /// Active users per day of the period, oldest first
+    pub daily_active_users: Vec<(String, i64)>,
+    /// Average daily active users over the period
+    pub average_active_users: f64,
+    /// Average daily active users over the previous period, if fetched
+    pub previous_average_active_users: Option<f64>,
+    /// Share of suggestions accepted over the period, if any were shown
+    pub acceptance_rate: Option<f64>,
+    /// Languages with the most accepted suggestions, most first
+    pub top_languages: Vec<LanguageUsage>,
+}
+
+impl WeeklyDigest {
+    /// Aggregates the most recent week of a metrics window
+    ///
+    /// # Arguments
+    ///
+    /// * `scope` - Scope the digest describes, e.g. the enterprise ID
+    /// * `metrics` - Daily metrics in any order
+    ///
+    /// # Returns
+    ///
+    /// * `Option<WeeklyDigest>` - The digest, or `None` if no metrics were fetched
+    pub fn from_metrics(scope: &str, metrics: &[CopilotMetrics]) -> Option<Self> {
+        let mut days: Vec<&CopilotMetrics> = metrics.iter().collect();
+        days.sort_by(|a, b| a.date.cmp(&b.date));
+
+        let split = days.len().saturating_sub(PERIOD_DAYS);
+        let (earlier, current) = days.split_at(split);
+        if current.is_empty() {
+            return None;
+        }
+        let previous = &earlier[earlier.len().saturating_sub(PERIOD_DAYS)..];
+
+        let daily_active_users: Vec<(String, i64)> = current
+            .iter()
+            .map(|m| (m.date.clone(), m.total_active_users.unwrap_or(0)))
+            .collect();
+
+        let mut languages: HashMap<&str, LanguageUsage> = HashMap::new();
+        for metric in current {
+            let completions = metric.copilot_ide_code_completions.as_ref();
+            for language in completions
+                .and_then(|c| c.languages.as_ref())
+                .into_iter()
+                .flatten()
+            {
+                let usage =
+                    languages
+                        .entry(language.name.as_str())
+                        .or_insert_with(|| LanguageUsage {
+                            name: language.name.clone(),
+                            suggestions: 0,
+                            acceptances: 0,
+                        });
+                usage.suggestions += language.total_code_suggestions.unwrap_or(0);
+                usage.acceptances += language.total_code_acceptances.unwrap_or(0);
+            }
+        }
+
+        let suggestions: i64 = languages.values().map(|l| l.suggestions).sum();
+        let acceptances: i64 = languages.values().map(|l| l.acceptances).sum();
+
+        let mut top_languages: Vec<LanguageUsage> = languages.into_values().collect();
+        top_languages.sort_by(|a, b| {
+            b.acceptances
+                .cmp(&a.acceptances)
+                .then_with(|| a.name.cmp(&b.name))
+        });
+        top_languages.truncate(TOP_LANGUAGES);
+
+        Some(Self {
+            scope: scope.to_string(),
+            average_active_users: average_active_users(current),
+            previous_average_active_users: (!previous.is_empty())
+                .then(|| average_active_users(previous)),
+            acceptance_rate: (suggestions > 0).then(|| acceptances as f64 / suggestions as f64),
+            daily_active_users,
+            top_languages,
+        })
+    }
+
+    /// Returns the change of average active users against the previous week
+    ///
+    /// # Returns
+    ///
+    /// * `Option<f64>` - The relative change (0.1 = +10%), or `None` without a
+    ///   previous week to compare with
+    pub fn active_users_change(&self) -> Option<f64> {
+        match self.previous_average_active_users {
+            Some(previous) if previous > 0.0 => {
+                Some((self.average_active_users - previous) / previous)
+            }
+            _ => None,
+        }
+    }
+
+    /// Returns the subject line of the digest email
+    pub fn subject(&self) -> String {
+        let period = match (
+            self.daily_active_users.first(),
+            self.daily_active_users.last(),
+        ) {
+            (Some((first, _)), Some((last, _))) => format!("{} to {}", first, last),
+            _ => String::new(),
+        };
+        format!("Copilot adoption digest for {}: {}", self.scope, period)
+    }
+
+    /// Renders the digest as a standalone HTML document
+    pub fn to_html(&self) -> String {
+        let change = match self.active_users_change() {
+            Some(change) => format!(" ({:+.1}% vs. previous week)", change * 100.0),
+            None => String::new(),
+        };
+        let acceptance = match self.acceptance_rate {
+            Some(rate) => format!("{:.1}%", rate * 100.0),
+            None => "n/a".to_string(),
+        };
+
+        let trend: String = self
+            .daily_active_users
+            .iter()
+            .map(|(date, users)| {
+                format!(
+                    "<tr><td>{}</td><td style=\"text-align:right\">{}</td></tr>",
+                    escape_html(date),
+                    users
+                )
+            })
+            .collect();
+
+        let languages: String = self
+            .top_languages
+            .iter()
+            .map(|l| {
+                let rate = if l.suggestions > 0 {
+                    format!("{:.1}%", l.acceptances as f64 / l.suggestions as f64 * 100.0)
+                } else {
+                    "n/a".to_string()
+                };
+                format!(
+                    "<tr><td>{}</td><td style=\"text-align:right\">{}</td><td style=\"text-align:right\">{}</td></tr>",
+                    escape_html(&l.name),
+                    l.acceptances,
+                    rate
+                )
+            })
+            .collect();
+
+        format!(
+            "<!DOCTYPE html>\n<html><head><meta charset=\"utf-8\"><title>{title}</title></head>\
+             <body style=\"font-family:sans-serif\">\
+             <h1>{title}</h1>\
+             <p>Average daily active users: <strong>{average:.0}</strong>{change}</p>\
+             <p>Suggestion acceptance rate: <strong>{acceptance}</strong></p>\
+             <h2>Daily active users</h2><table><tr><th>Date</th><th>Active users</th></tr>{trend}</table>\
+             <h2>Top languages</h2><table><tr><th>Language</th><th>Acceptances</th><th>Acceptance rate</th></tr>{languages}</table>\
+             </body></html>\n",
+            title = escape_html(&self.subject()),
+            average = self.average_active_users,
+            change = change,
+            acceptance = acceptance,
+            trend = trend,
+            languages = languages,
+        )
+    }
+}
+
+/// Emails the weekly digest of the enterprise's metrics
+///
+/// # Arguments
+///
+/// * `enterprise_id` - ID of the GitHub Enterprise organization
+/// * `metrics` - The enterprise's window, as fetched by the enterprise step
+/// * `digest` - Sender and recipients of the digest
+/// * `agent` - Settings of the HTTP agent sending the email
+///
+/// # Returns
+///
+/// * `Result<()>` - Ok(()) if the digest was sent or there was nothing to send
+///
+/// # Errors
+///
+/// This function may return errors in the following cases:
+/// * AWS credentials are missing or SES rejects the message
+pub fn process_weekly_digest(
+    enterprise_id: &str,
+    metrics: &[CopilotMetrics],
+    digest: &DigestConfig,
+    agent: &AgentConfig,
+) -> Result<()> {
+    info!("Building weekly digest for {}", enterprise_id);
+
+    let weekly = match WeeklyDigest::from_metrics(enterprise_id, metrics) {
+        Some(weekly) => weekly,
+        None => {
+            debug!("No metrics for the weekly digest of {}", enterprise_id);
+            return Ok(());
+        }
+    };
+
+    let ses = SesClient::new(AwsCredentials::from_env()?, agent);
+    ses.send_html_email(
+        &digest.sender,
+        &digest.recipients,
+        &weekly.subject(),
+        &weekly.to_html(),
+    )?;
+
+    info!(
+        "Sent weekly digest for {} to {} recipient(s)",
+        enterprise_id,
+        digest.recipients.len()
+    );
+    Ok(())
+}
+
+/// Average of the daily active users of the given days
+fn average_active_users(days: &[&CopilotMetrics]) -> f64 {
+    let total: i64 = days.iter().map(|m| m.total_active_users.unwrap_or(0)).sum();
+    total as f64 / days.len() as f64
+}
+
+/// Escapes text for inclusion in HTML
+pub fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
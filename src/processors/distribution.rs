@@ -0,0 +1,172 @@
+//! # Cross-Team Distribution
+//!
+//! Per-team series show each team on its own, and the team rollup shows their
+//! sum; neither shows how teams compare. This module computes percentiles of
+//! the key metrics across all processed teams on the latest day and reports
+//! them under the enterprise namespace:
+//!
+//! * `teams.distribution.{metric}.p10` / `.p50` / `.p90` - Percentiles across
+//!   teams
+//! * `teams.distribution.teams` - Number of teams that reported the day
+//!
+//! for the metrics available to threshold alerts (`active_users`,
+//! `engaged_users`, `completions_engaged_users`, `chat_engaged_users` and
+//! `acceptance_rate`). A monitor comparing a team's series against the p10 or
+//! p90 spots outlier teams. Parent teams aggregated from their children are
+//! not part of the distribution.
+
+use tracing::info;
+
+use super::alerts::AlertMetric;
+use super::anomaly::KEY_METRICS;
+use super::summary::ScopeSummary;
+use crate::error::Result;
+use crate::models::github::CopilotMetrics;
+use crate::services::datadog::{DatadogSink, Gauge};
+
+/// Percentiles reported per metric, as (name, fraction)
+pub const PERCENTILES: [(&str, f64); 3] = [("p10", 0.1), ("p50", 0.5), ("p90", 0.9)];
+
+/// Teams needed before a distribution is reported
+pub const MIN_TEAMS: usize = 3;
+
+/// Percentiles of one metric across teams on one day
+#[derive(Debug, Clone, PartialEq)]
+pub struct MetricDistribution {
+    /// The metric
+    pub metric: AlertMetric,
+    /// Number of teams reporting the metric
+    pub teams: usize,
+    /// Values at `PERCENTILES`, in the same order
+    pub percentiles: Vec<f64>,
+}
+
+/// Distribution of the key metrics across teams on the latest day
+#[derive(Debug, Clone, PartialEq)]
+pub struct TeamDistribution {
+    /// Date (YYYY-MM-DD) of the day
+    pub date: String,
+    /// Number of teams that reported the day
+    pub teams: usize,
+    /// Metrics reported by at least `MIN_TEAMS` teams
+    pub metrics: Vec<MetricDistribution>,
+}
+
+impl TeamDistribution {
+    /// Computes the distribution on the latest day any team reported
+    ///
+    /// # Arguments
+    ///
+    /// * `teams` - Fetched days of every team, by slug
+    ///
+    /// # Returns
+    ///
+    /// * `Option<TeamDistribution>` - The distribution, or `None` if fewer
+    ///   than `MIN_TEAMS` teams reported the latest day
+    pub fn from_teams(teams: &[(String, Vec<CopilotMetrics>)]) -> Option<Self> {
+        let date = teams
+            .iter()
+            .flat_map(|(_, metrics)| metrics.iter().map(|m| &m.date))
+            .max()?
+            .clone();
+        let days: Vec<&CopilotMetrics> = teams
+            .iter()
+            .filter_map(|(_, metrics)| metrics.iter().find(|m| m.date == date))
+            .collect();
+        if days.len() < MIN_TEAMS {
+            return None;
+        }
+
+        let metrics = KEY_METRICS
+            .iter()
+            .filter_map(|metric| {
+                let mut values: Vec<f64> = days.iter().filter_map(|d| metric.value(d)).collect();
+                if values.len() < MIN_TEAMS {
+                    return None;
+                }
+                values.sort_by(f64::total_cmp);
+                Some(MetricDistribution {
+                    metric: *metric,
+                    teams: values.len(),
+                    percentiles: PERCENTILES
+                        .iter()
+                        .map(|(_, fraction)| percentile(&values, *fraction))
+                        .collect(),
+                })
+            })
+            .collect();
+
+        Some(Self {
+            date,
+            teams: days.len(),
+            metrics,
+        })
+    }
+
+    /// Converts the distribution to the gauges sent to Datadog
+    pub fn to_gauges(&self) -> Vec<Gauge> {
+        let mut gauges = vec![Gauge::new(
+            "teams.distribution.teams",
+            self.teams as f64,
+            &self.date,
+        )];
+        for distribution in &self.metrics {
+            for ((name, _), value) in PERCENTILES.iter().zip(&distribution.percentiles) {
+                gauges.push(Gauge::new(
+                    format!("teams.distribution.{}.{}", distribution.metric.name(), name),
+                    *value,
+                    &self.date,
+                ));
+            }
+        }
+        gauges
+    }
+}
+
+/// Returns a percentile of sorted values, interpolating between neighbors
+///
+/// # Arguments
+///
+/// * `sorted` - Non-empty values in ascending order
+/// * `fraction` - The percentile as a fraction (0.5 for the median)
+pub fn percentile(sorted: &[f64], fraction: f64) -> f64 {
+    let rank = fraction.clamp(0.0, 1.0) * (sorted.len() - 1) as f64;
+    let lower = rank.floor() as usize;
+    let upper = rank.ceil() as usize;
+    sorted[lower] + (sorted[upper] - sorted[lower]) * (rank - lower as f64)
+}
+
+/// Computes the distribution across teams and sends it to Datadog
+///
+/// # Arguments
+///
+/// * `teams` - Fetched days of every team, by slug
+/// * `datadog` - Datadog sink fanning out to the configured destinations
+/// * `namespace` - The enterprise namespace
+/// * `summary` - Summary of the scope, filled in with send counts
+///
+/// # Returns
+///
+/// * `Result<usize>` - Number of teams in the distribution, or an error if
+///   the gauges could not be sent
+pub fn process_team_distribution(
+    teams: &[(String, Vec<CopilotMetrics>)],
+    datadog: &DatadogSink,
+    namespace: &str,
+    summary: &mut ScopeSummary,
+) -> Result<usize> {
+    let Some(distribution) = TeamDistribution::from_teams(teams) else {
+        info!(
+            "Fewer than {} teams reported the latest day, skipping the team distribution",
+            MIN_TEAMS
+        );
+        return Ok(0);
+    };
+
+    let before = datadog.stats();
+    let sent = datadog.send_gauges(&distribution.to_gauges(), namespace);
+    summary.record_send(&before, &datadog.stats());
+    sent?;
+
+    Ok(distribution.teams)
+}
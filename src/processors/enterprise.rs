@@ -10,15 +10,261 @@
 //!
 //! This module serves as a key integration point between the GitHub API client
 //! and the Datadog client, managing the end-to-end flow of metrics data.
+//!
+//! Customers with Copilot Business but no enterprise account get a 404 from
+//! the enterprise endpoints. The configured ID is then used as an organization
+//! login: enterprise and team metrics, seats and premium request usage are
+//! fetched from the organization endpoints, and metrics and seats are sent
+//! tagged `endpoint:org`, so the scope doesn't fail.
+
+use std::time::Instant;
 
-use anyhow::{anyhow, Result};
-use tracing::{debug, info};
+use tracing::{debug, info, warn};
 
+use super::persistence::Persistence;
+use super::summary::ScopeSummary;
+use crate::config::ConfigError;
+use crate::error::{GhrustError, Result};
+use crate::models::github::CopilotMetrics;
 use crate::services::{
-    datadog::DatadogClient,
-    github::{get_enterprise_metrics, GitHubClient},
+    datadog::DatadogSink,
+    github::{
+        calculate_default_since_date, get_org_premium_request_usage, get_premium_request_usage,
+        AccountKind, GitHubClient, GitHubError,
+    },
 };
 
+/// Checks that a configured enterprise ID names an account the API knows
+///
+/// The enterprise endpoints take the enterprise slug (as in
+/// `github.com/enterprises/<slug>`), which is easily confused with the
+/// enterprise's numeric ID or display name. An organization login passes, as
+/// its metrics are fetched from the organization endpoint. If the lookup
+/// itself fails (e.g. the token can't query enterprises), the ID is assumed
+/// to be right. An ID that resolved once isn't looked up again by the same
+/// client (see `GitHubClient::account_kind`).
+///
+/// # Arguments
+///
+/// * `github` - Client the ID is resolved with
+/// * `enterprise_id` - The configured enterprise ID
+///
+/// # Returns
+///
+/// * `Result<()>` - Ok, or a configuration error explaining the expected form
+pub fn check_enterprise_id(github: &GitHubClient, enterprise_id: &str) -> Result<()> {
+    match github.account_kind(enterprise_id) {
+        Ok(AccountKind::Enterprise) => Ok(()),
+        Ok(AccountKind::Organization) => {
+            info!(
+                "{} is an organization, not an enterprise; using the organization endpoint",
+                enterprise_id
+            );
+            Ok(())
+        }
+        Ok(AccountKind::Unknown) => Err(ConfigError::Invalid(
+            format!("enterprise ID {:?}", enterprise_id),
+            enterprise_id_hint(enterprise_id),
+        )
+        .into()),
+        Err(e) => {
+            warn!("Could not verify enterprise ID {}: {}", enterprise_id, e);
+            Ok(())
+        }
+    }
+}
+
+/// Explains which form of enterprise ID is expected
+///
+/// # Arguments
+///
+/// * `enterprise_id` - The configured enterprise ID the API doesn't know
+pub fn enterprise_id_hint(enterprise_id: &str) -> String {
+    if !enterprise_id.is_empty() && enterprise_id.chars().all(|c| c.is_ascii_digit()) {
+        "this looks like the numeric enterprise ID, but the API expects the enterprise \
+         slug, as in github.com/enterprises/<slug>"
+            .to_string()
+    } else {
+        "no enterprise or organization with this slug is visible to the token; use the \
+         enterprise slug as in github.com/enterprises/<slug> (lowercase, with hyphens), \
+         not the display name or numeric ID"
+            .to_string()
+    }
+}
+
+/// Tag of the metrics fetched from the organization endpoint instead of the
+/// enterprise endpoint
+pub const ORG_FALLBACK_TAG: &str = "endpoint:org";
+
+/// Runs a fetch against an enterprise endpoint, falling back to the matching
+/// organization endpoint if the ID is an organization login
+///
+/// Only a `404` for an ID that resolves to an organization (see
+/// `GitHubClient::account_kind`) falls back; a `404` of an enterprise, e.g.
+/// for a team it doesn't have, is returned as is.
+///
+/// # Arguments
+///
+/// * `github` - Client the ID is resolved with
+/// * `enterprise_id` - ID of the GitHub Enterprise organization, or the login
+///   of an organization without an enterprise
+/// * `enterprise` - Fetches from the enterprise endpoint
+/// * `organization` - Fetches the same data from the organization endpoint
+///
+/// # Returns
+///
+/// * `Result<(T, Vec<String>), GitHubError>` - The fetched data and the tags
+///   to send it with: `ORG_FALLBACK_TAG` if it came from the organization
+///   endpoint. The enterprise endpoint's error is returned if the
+///   organization endpoint fails as well.
+pub fn with_org_fallback<T>(
+    github: &GitHubClient,
+    enterprise_id: &str,
+    enterprise: impl FnOnce() -> std::result::Result<T, GitHubError>,
+    organization: impl FnOnce() -> std::result::Result<T, GitHubError>,
+) -> std::result::Result<(T, Vec<String>), GitHubError> {
+    match enterprise() {
+        Err(GitHubError::NotFound(message)) => {
+            match github.account_kind(enterprise_id) {
+                Ok(AccountKind::Organization) => {}
+                Ok(_) => return Err(GitHubError::NotFound(message)),
+                Err(e) => {
+                    warn!(
+                        "Could not resolve {} ({}); not trying the organization endpoint",
+                        enterprise_id, e
+                    );
+                    return Err(GitHubError::NotFound(message));
+                }
+            }
+            debug!(
+                "Enterprise endpoint not found for organization {}; trying the organization endpoint",
+                enterprise_id
+            );
+            match organization() {
+                Ok(data) => {
+                    info!("Fetched {} from the organization endpoint", enterprise_id);
+                    Ok((data, vec![ORG_FALLBACK_TAG.to_string()]))
+                }
+                Err(e) => {
+                    debug!("Organization endpoint failed for {}: {}", enterprise_id, e);
+                    Err(GitHubError::NotFound(message))
+                }
+            }
+        }
+        result => result.map(|data| (data, Vec::new())),
+    }
+}
+
+/// Reads an archived enterprise response, falling back to the archived
+/// response of the organization endpoint
+///
+/// A run that fell back archived the organization endpoint's response, so a
+/// day without an enterprise response is looked up there.
+///
+/// # Arguments
+///
+/// * `enterprise` - Reads the enterprise endpoint's response
+/// * `organization` - Reads the organization endpoint's response
+///
+/// # Returns
+///
+/// * `Result<Option<(T, Vec<String>)>, GitHubError>` - The archived data and
+///   the tags to send it with (`ORG_FALLBACK_TAG` if it was read from the
+///   organization endpoint), `None` if neither was archived, or the first
+///   read error
+pub fn archived_with_org_fallback<T>(
+    enterprise: impl FnOnce() -> std::result::Result<Option<T>, GitHubError>,
+    organization: impl FnOnce() -> std::result::Result<Option<T>, GitHubError>,
+) -> std::result::Result<Option<(T, Vec<String>)>, GitHubError> {
+    if let Some(data) = enterprise()? {
+        return Ok(Some((data, Vec::new())));
+    }
+    Ok(organization()?.map(|data| (data, vec![ORG_FALLBACK_TAG.to_string()])))
+}
+
+/// Returns the tags of days of an ID that weren't fetched in this run
+///
+/// Days served from the store were fetched by an earlier run, from the
+/// organization endpoint if the ID is an organization login, so they are
+/// sent with the same tags a fetch would have returned.
+///
+/// # Arguments
+///
+/// * `github` - Client the ID is resolved with
+/// * `enterprise_id` - ID of the GitHub Enterprise organization, or the login
+///   of an organization without an enterprise
+///
+/// # Returns
+///
+/// * `Vec<String>` - `ORG_FALLBACK_TAG` for an organization, otherwise empty
+pub fn fallback_tags(github: &GitHubClient, enterprise_id: &str) -> Vec<String> {
+    match github.account_kind(enterprise_id) {
+        Ok(AccountKind::Organization) => vec![ORG_FALLBACK_TAG.to_string()],
+        Ok(_) => Vec::new(),
+        Err(e) => {
+            warn!("Could not resolve {}: {}", enterprise_id, e);
+            Vec::new()
+        }
+    }
+}
+
+/// Fetches enterprise metrics, falling back to the organization endpoint
+///
+/// # Arguments
+///
+/// * `github` - Client the metrics are fetched with
+/// * `enterprise_id` - ID of the GitHub Enterprise organization, or the login
+///   of an organization without an enterprise
+/// * `since` - First day to fetch (YYYY-MM-DD)
+///
+/// # Returns
+///
+/// * `Result<(Vec<CopilotMetrics>, Vec<String>), GitHubError>` - The metrics
+///   and the tags to send them with: `ORG_FALLBACK_TAG` if they came from the
+///   organization endpoint. The enterprise endpoint's error is returned if the
+///   organization endpoint fails as well.
+pub fn fetch_with_org_fallback(
+    github: &GitHubClient,
+    enterprise_id: &str,
+    since: &str,
+) -> std::result::Result<(Vec<CopilotMetrics>, Vec<String>), GitHubError> {
+    with_org_fallback(
+        github,
+        enterprise_id,
+        || github.fetch_enterprise_metrics(enterprise_id, since),
+        || github.fetch_org_metrics(enterprise_id, since),
+    )
+}
+
+/// Fetches the metrics of an enterprise team, falling back to the team of
+/// the organization with the enterprise's ID
+///
+/// # Arguments
+///
+/// * `github` - Client the metrics are fetched with
+/// * `enterprise_id` - ID of the GitHub Enterprise organization, or the login
+///   of an organization without an enterprise
+/// * `team_slug` - Slug of the team
+/// * `since` - First day to fetch (YYYY-MM-DD)
+///
+/// # Returns
+///
+/// * `Result<(Vec<CopilotMetrics>, Vec<String>), GitHubError>` - The metrics
+///   and the tags to send them with, as for `fetch_with_org_fallback`
+pub fn fetch_team_with_org_fallback(
+    github: &GitHubClient,
+    enterprise_id: &str,
+    team_slug: &str,
+    since: &str,
+) -> std::result::Result<(Vec<CopilotMetrics>, Vec<String>), GitHubError> {
+    with_org_fallback(
+        github,
+        enterprise_id,
+        || github.fetch_team_metrics(enterprise_id, team_slug, since),
+        || github.fetch_org_team_metrics(enterprise_id, team_slug, since),
+    )
+}
+
 /// Process and send enterprise-wide metrics to Datadog
 ///
 /// This function orchestrates the end-to-end process for enterprise metrics:
@@ -28,18 +274,23 @@ use crate::services::{
 /// 4. Sends the processed metrics to Datadog for monitoring
 ///
 /// If the GitHub API returns no metrics, the function will log this and return
-/// successfully without attempting to send data to Datadog.
+/// successfully without attempting to send data to Datadog. An enterprise the
+/// API doesn't know is fetched as an organization (see
+/// `fetch_with_org_fallback`).
 ///
 /// # Arguments
 ///
-/// * `github_token` - Personal access token for GitHub API authentication
+/// * `github_client` - GitHub client the metrics are fetched with
 /// * `enterprise_id` - ID of the GitHub Enterprise organization to fetch metrics for
-/// * `datadog_api_key` - API key for Datadog authentication
+/// * `datadog` - Datadog sink fanning out to the configured destinations
 /// * `datadog_namespace` - Namespace prefix for metrics in Datadog (e.g., "github.copilot")
+/// * `persistence` - Where the fetched days are kept
+/// * `summary` - Summary of the scope, filled in with fetch and send counts
 ///
 /// # Returns
 ///
-/// * `Result<()>` - Ok(()) if processing was successful, or an error with details
+/// * `Result<Vec<CopilotMetrics>>` - The fetched days (empty if GitHub
+///   returned none), or an error with details
 ///
 /// # Errors
 ///
@@ -54,31 +305,51 @@ use crate::services::{
 /// * Number of metrics data points retrieved
 /// * Completion of the metrics processing
 pub fn process_enterprise_metrics(
-    github_token: &str,
+    github_client: &GitHubClient,
     enterprise_id: &str,
-    datadog_api_key: &str,
+    datadog: &DatadogSink,
     datadog_namespace: &str,
-) -> Result<()> {
+    persistence: &Persistence,
+    summary: &mut ScopeSummary,
+) -> Result<Vec<CopilotMetrics>> {
     info!(
         "Starting enterprise metrics processing for {}",
         enterprise_id
     );
 
-    // Initialize clients
-    let github_client = GitHubClient::new(github_token);
-    let datadog_client = DatadogClient::new(datadog_api_key.to_string());
-
-    // Fetch metrics from GitHub
-    let metrics = match get_enterprise_metrics(&github_client, enterprise_id) {
+    // Fetch the days of the window that aren't stored from GitHub
+    let since = calculate_default_since_date();
+    let started = Instant::now();
+    let mut tags = None;
+    let fetched = persistence
+        .store
+        .fetch_missing(&summary.scope, &since, |since| {
+            fetch_with_org_fallback(github_client, enterprise_id, since).map(
+                |(metrics, fallback)| {
+                    tags = Some(fallback);
+                    metrics
+                },
+            )
+        });
+    summary.record_fetch(started);
+    let metrics = match fetched {
         Ok(metrics) => {
             if metrics.is_empty() {
                 debug!("No enterprise metrics returned for {}", enterprise_id);
-                return Ok(());
+                return Ok(metrics);
             }
             metrics
         }
+        Err(GitHubError::NotModified(_)) => {
+            info!(
+                "Enterprise metrics of {} not modified since the last run, not sending them",
+                enterprise_id
+            );
+            summary.not_modified = true;
+            return Ok(persistence.store.window(&summary.scope, &since));
+        }
         Err(e) => {
-            return Err(anyhow!("Failed to fetch enterprise metrics: {}", e));
+            return Err(GhrustError::github("Failed to fetch enterprise metrics")(e));
         }
     };
 
@@ -87,13 +358,82 @@ pub fn process_enterprise_metrics(
         metrics.len(),
         enterprise_id
     );
+    summary.dates_fetched = metrics.len();
+    persistence.keep(&summary.scope, &metrics);
 
     // Send metrics to Datadog
-    datadog_client.send_metrics(&metrics, datadog_namespace)?;
+    let tags = tags.unwrap_or_else(|| fallback_tags(github_client, enterprise_id));
+    let before = datadog.stats();
+    let sent = datadog.send_metrics_with_tags(&metrics, datadog_namespace, &tags);
+    summary.record_send(&before, &datadog.stats());
+    sent?;
 
     info!(
         "Enterprise metrics processing completed for {}",
         enterprise_id
     );
+    Ok(metrics)
+}
+
+/// Process and send the enterprise's premium request usage to Datadog
+///
+/// Fetches the premium request usage report for the current billing month
+/// (per-model request counts and multipliers), from the organization endpoint
+/// if the enterprise isn't known, and sends it to Datadog under
+/// `{datadog_namespace}.premium_requests.*`, so consumption of premium models
+/// can be tracked alongside the classic completion metrics.
+///
+/// # Arguments
+///
+/// * `github_client` - GitHub client the metrics are fetched with
+///   (requires access to the enterprise's billing data)
+/// * `enterprise_id` - ID of the GitHub Enterprise organization
+/// * `datadog` - Datadog sink fanning out to the configured destinations
+/// * `datadog_namespace` - Namespace prefix for metrics in Datadog (e.g., "github.copilot")
+/// * `summary` - Summary of the scope, filled in with send counts
+///
+/// # Returns
+///
+/// * `Result<()>` - Ok(()) if processing was successful, or an error with details
+///
+/// # Errors
+///
+/// This function may return errors in the following cases:
+/// * Unable to fetch the usage report from GitHub API
+/// * Unable to send metrics to Datadog API
+pub fn process_premium_request_usage(
+    github_client: &GitHubClient,
+    enterprise_id: &str,
+    datadog: &DatadogSink,
+    datadog_namespace: &str,
+    summary: &mut ScopeSummary,
+) -> Result<()> {
+    info!(
+        "Starting premium request usage processing for {}",
+        enterprise_id
+    );
+
+    let (usage, tags) = with_org_fallback(
+        github_client,
+        enterprise_id,
+        || get_premium_request_usage(github_client, enterprise_id),
+        || get_org_premium_request_usage(github_client, enterprise_id),
+    )
+    .map_err(GhrustError::github("Failed to fetch premium request usage"))?;
+
+    if usage.usage_items.is_empty() {
+        debug!("No premium request usage returned for {}", enterprise_id);
+        return Ok(());
+    }
+
+    let before = datadog.stats();
+    let sent = datadog.send_premium_request_usage(&usage, datadog_namespace, &tags);
+    summary.record_send(&before, &datadog.stats());
+    sent?;
+
+    info!(
+        "Premium request usage processing completed for {}",
+        enterprise_id
+    );
     Ok(())
 }
@@ -0,0 +1,286 @@
+//! # Fetch/Send Handoff
+//!
+//! A run can be split into two stages that run in different security
+//! contexts: `ghrust fetch` needs only the GitHub token and writes the fetched
+//! metrics of every scope to a JSON file, `ghrust send` needs only the Datadog
+//! keys and sends that file. Each stage can be retried on its own; sending the
+//! same file again overwrites the same points in Datadog.
+//!
+//! The file carries everything the send stage needs per scope: the resolved
+//! namespace (after team mappings and privacy mode) and the extra tags. Only
+//! the enterprise and team metrics are handed off; collections derived from
+//! them (rollups, alerts, anomalies, distributions) and the seat, premium
+//! request and organization reports need a complete run.
+//!
+//! `ghrust replay` builds the same bundle from the raw responses kept in the
+//! archive (`GITHUB_RAW_ARCHIVE`) instead of fetching them, resolving the
+//! namespaces and tags from the current configuration.
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::time::Instant;
+
+use serde::{Deserialize, Serialize};
+use tracing::info;
+
+use super::enterprise::{fetch_team_with_org_fallback, fetch_with_org_fallback};
+use super::summary::ScopeSummary;
+use crate::error::{GhrustError, Result};
+use crate::models::github::CopilotMetrics;
+use crate::services::datadog::{DatadogSink, TagContext};
+use crate::services::github::{calculate_default_since_date, GitHubClient};
+
+/// Version of the metrics file format written by this build
+pub const BUNDLE_VERSION: u32 = 1;
+
+/// Fetched metrics of one scope, ready to be sent
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FetchedScope {
+    /// Scope identifier, e.g. "enterprise:acme" or "team:acme/platform"
+    pub scope: String,
+    /// Namespace the metrics are sent to
+    pub namespace: String,
+    /// Tags added to every series of the scope
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tags: Vec<String>,
+    /// Enterprise and team the tag templates are resolved against when the
+    /// scope is sent
+    #[serde(default)]
+    pub tag_context: TagContext,
+    /// The fetched days, oldest first
+    pub metrics: Vec<CopilotMetrics>,
+}
+
+/// Metrics file handed from `ghrust fetch` to `ghrust send`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MetricsBundle {
+    /// Version of the file format
+    pub version: u32,
+    /// When the metrics were fetched (RFC 3339)
+    pub fetched_at: String,
+    /// The fetched scopes, in processing order
+    pub scopes: Vec<FetchedScope>,
+}
+
+impl Default for MetricsBundle {
+    fn default() -> Self {
+        Self {
+            version: BUNDLE_VERSION,
+            fetched_at: chrono::Utc::now().to_rfc3339(),
+            scopes: Vec::new(),
+        }
+    }
+}
+
+impl MetricsBundle {
+    /// Creates an empty bundle fetched now
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Reads a metrics file
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - Path of the file written by `ghrust fetch`
+    ///
+    /// # Returns
+    ///
+    /// * `Result<MetricsBundle>` - The bundle, or a `Bundle` error if the file
+    ///   can't be read or parsed or was written by a newer version
+    pub fn read(path: &Path) -> Result<Self> {
+        let error = |e| GhrustError::Bundle(path.display().to_string(), e);
+        let contents = fs::read(path).map_err(error)?;
+        let bundle: Self = serde_json::from_slice(&contents)
+            .map_err(|e| error(io::Error::new(io::ErrorKind::InvalidData, e)))?;
+        if bundle.version > BUNDLE_VERSION {
+            return Err(error(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!(
+                    "format version {} is newer than the supported version {}",
+                    bundle.version, BUNDLE_VERSION
+                ),
+            )));
+        }
+        Ok(bundle)
+    }
+
+    /// Writes the bundle as JSON
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - Path of the file to write
+    pub fn write(&self, path: &Path) -> Result<()> {
+        let error = |e| GhrustError::Bundle(path.display().to_string(), e);
+        let contents = serde_json::to_vec(self)
+            .map_err(|e| error(io::Error::new(io::ErrorKind::InvalidData, e)))?;
+        fs::write(path, contents).map_err(error)
+    }
+}
+
+/// Fetches the enterprise-wide metrics of the reporting window
+///
+/// An enterprise the API doesn't know is fetched as an organization, like in
+/// a complete run.
+///
+/// # Arguments
+///
+/// * `github` - Client the metrics are fetched with
+/// * `enterprise_id` - ID of the GitHub Enterprise organization
+/// * `namespace` - Namespace the metrics are sent to
+/// * `summary` - Summary of the scope, filled in with the fetch count
+pub fn fetch_enterprise(
+    github: &GitHubClient,
+    enterprise_id: &str,
+    namespace: &str,
+    summary: &mut ScopeSummary,
+) -> Result<FetchedScope> {
+    let started = Instant::now();
+    let fetched_metrics =
+        fetch_with_org_fallback(github, enterprise_id, &calculate_default_since_date());
+    summary.record_fetch(started);
+    let (metrics, tags) =
+        fetched_metrics.map_err(GhrustError::github("Failed to fetch enterprise metrics"))?;
+    let tag_context = TagContext::enterprise(enterprise_id);
+    Ok(fetched(summary, namespace, tags, tag_context, metrics))
+}
+
+/// Fetches the metrics of a team for the reporting window
+///
+/// Like the enterprise, a team of an enterprise the API doesn't know is
+/// fetched from the organization's team endpoint.
+///
+/// # Arguments
+///
+/// * `github` - Client the metrics are fetched with
+/// * `enterprise_id` - ID of the GitHub Enterprise organization
+/// * `team_slug` - Slug of the team
+/// * `namespace` - The team's namespace
+/// * `tags` - Tags added to every series of the team
+/// * `summary` - Summary of the scope, filled in with the fetch count
+pub fn fetch_team(
+    github: &GitHubClient,
+    enterprise_id: &str,
+    team_slug: &str,
+    namespace: &str,
+    tags: &[String],
+    summary: &mut ScopeSummary,
+) -> Result<FetchedScope> {
+    let started = Instant::now();
+    let since = calculate_default_since_date();
+    let fetched_metrics = fetch_team_with_org_fallback(github, enterprise_id, team_slug, &since);
+    summary.record_fetch(started);
+    let (metrics, fallback) =
+        fetched_metrics.map_err(GhrustError::github("Failed to fetch team metrics"))?;
+    let tag_context = TagContext::team(enterprise_id, team_slug);
+    let tags = tags.iter().cloned().chain(fallback).collect();
+    Ok(fetched(summary, namespace, tags, tag_context, metrics))
+}
+
+/// Reads the archived windows of a scope and wraps them for the bundle
+///
+/// The windows of all days are merged by metric date, a later fetch of a date
+/// replacing an earlier one.
+///
+/// # Arguments
+///
+/// * `days` - Fetch days to read, oldest first
+/// * `read` - Reads the window archived on a day and the tags it is sent
+///   with (e.g. `endpoint:org`), `None` if there is none
+/// * `namespace` - Namespace the metrics are sent to
+/// * `tags` - Tags added to every series of the scope
+/// * `tag_context` - Scope the tag templates are resolved against
+/// * `summary` - Summary of the scope, filled in with the number of days read
+///
+/// # Returns
+///
+/// * `Result<Option<FetchedScope>>` - The scope, `None` if nothing of it was
+///   archived on any of the days, or the first read error
+pub fn replay_scope(
+    days: &[String],
+    read: impl Fn(&str) -> Result<Option<(Vec<CopilotMetrics>, Vec<String>)>>,
+    namespace: &str,
+    tags: &[String],
+    tag_context: TagContext,
+    summary: &mut ScopeSummary,
+) -> Result<Option<FetchedScope>> {
+    let mut windows = Vec::new();
+    let mut tags = tags.to_vec();
+    for day in days {
+        if let Some((window, fallback)) = read(day)? {
+            windows.push(window);
+            for tag in fallback {
+                if !tags.contains(&tag) {
+                    tags.push(tag);
+                }
+            }
+        }
+    }
+    if windows.is_empty() {
+        info!("Nothing archived for {}", summary.scope);
+        return Ok(None);
+    }
+    let metrics = merge_windows(windows);
+    Ok(Some(fetched(
+        summary,
+        namespace,
+        tags,
+        tag_context,
+        metrics,
+    )))
+}
+
+/// Merges metrics windows by date, later windows replacing earlier days
+///
+/// # Returns
+///
+/// * `Vec<CopilotMetrics>` - One entry per date, oldest first
+pub fn merge_windows(windows: Vec<Vec<CopilotMetrics>>) -> Vec<CopilotMetrics> {
+    let mut days = BTreeMap::new();
+    for day in windows.into_iter().flatten() {
+        days.insert(day.date.clone(), day);
+    }
+    days.into_values().collect()
+}
+
+/// Records the fetched days of a scope and wraps them for the bundle
+fn fetched(
+    summary: &mut ScopeSummary,
+    namespace: &str,
+    tags: Vec<String>,
+    tag_context: TagContext,
+    metrics: Vec<CopilotMetrics>,
+) -> FetchedScope {
+    info!("Fetched {} days for {}", metrics.len(), summary.scope);
+    summary.dates_fetched = metrics.len();
+    FetchedScope {
+        scope: summary.scope.clone(),
+        namespace: namespace.to_string(),
+        tags,
+        tag_context,
+        metrics,
+    }
+}
+
+/// Sends the metrics of a fetched scope to every destination
+///
+/// # Arguments
+///
+/// * `fetched` - The scope read from the metrics file
+/// * `datadog` - Datadog sink fanning out to the configured destinations
+/// * `summary` - Summary of the scope, filled in with send counts
+pub fn send_scope(
+    fetched: &FetchedScope,
+    datadog: &DatadogSink,
+    summary: &mut ScopeSummary,
+) -> Result<()> {
+    summary.dates_fetched = fetched.metrics.len();
+    let datadog = datadog.scoped(fetched.tag_context.clone());
+    let before = datadog.stats();
+    let sent = datadog.send_metrics_with_tags(&fetched.metrics, &fetched.namespace, &fetched.tags);
+    summary.record_send(&before, &datadog.stats());
+    sent?;
+    Ok(())
+}
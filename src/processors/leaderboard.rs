@@ -0,0 +1,218 @@
+//! # Team Leaderboard
+//!
+//! A periodic ranking of teams by engagement growth: the average daily
+//! engaged users of the last seven days against the seven days before, taken
+//! from the team windows fetched by the run. The leaderboard is posted
+//! through the notification channels (Slack/Teams webhooks) and can be kept
+//! as an artifact, as Markdown or JSON, in a local file or S3.
+//!
+//! Teams are ordered by relative growth, best first. Teams without engaged
+//! users in the earlier period have no relative growth and come last, ordered
+//! by their absolute change.
+
+use std::cmp::Ordering;
+use std::fs;
+
+use serde::Serialize;
+use tracing::{info, warn};
+
+use super::diff::PeriodComparison;
+use crate::config::LeaderboardConfig;
+use crate::error::{GhrustError, Result};
+use crate::models::github::CopilotMetrics;
+use crate::services::aws::{AwsCredentials, S3Client};
+use crate::services::notify::{Notification, NotificationKind, NotificationSink};
+use crate::services::store::s3::parse_location;
+use crate::services::tls::AgentConfig;
+
+/// Length of each compared period in days
+pub const PERIOD_DAYS: usize = 7;
+
+/// Engagement growth of one team
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct LeaderboardEntry {
+    /// Slug of the team
+    pub team: String,
+    /// Average daily engaged users of the earlier period
+    pub previous: f64,
+    /// Average daily engaged users of the latest period
+    pub current: f64,
+    /// Relative change (0.1 = +10%), or `None` if the earlier period had no
+    /// engaged users
+    pub growth: Option<f64>,
+}
+
+/// Teams ranked by engagement growth
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Leaderboard {
+    /// Scope the teams belong to, e.g. "enterprise:acme"
+    pub scope: String,
+    /// First date of the latest period (YYYY-MM-DD)
+    pub from: String,
+    /// Last date of the latest period (YYYY-MM-DD)
+    pub to: String,
+    /// The teams, best first
+    pub entries: Vec<LeaderboardEntry>,
+}
+
+impl Leaderboard {
+    /// Ranks teams by the growth of their engaged users
+    ///
+    /// # Arguments
+    ///
+    /// * `scope` - Scope the teams belong to, e.g. "enterprise:acme"
+    /// * `teams` - Fetched days of every team, by slug
+    ///
+    /// # Returns
+    ///
+    /// * `Option<Leaderboard>` - The leaderboard, or `None` if no team has two
+    ///   full periods of metrics
+    pub fn from_teams(scope: &str, teams: &[(String, Vec<CopilotMetrics>)]) -> Option<Self> {
+        let mut period = None;
+        let mut entries: Vec<LeaderboardEntry> = teams
+            .iter()
+            .filter_map(|(team, metrics)| {
+                let comparison = PeriodComparison::from_metrics(metrics, PERIOD_DAYS)?;
+                let engaged = comparison
+                    .changes
+                    .iter()
+                    .find(|c| c.metric == "average_engaged_users")?;
+                let entry = LeaderboardEntry {
+                    team: team.clone(),
+                    previous: engaged.previous,
+                    current: engaged.current,
+                    growth: engaged.change,
+                };
+                // Report the most recent period any team covers
+                if period
+                    .as_ref()
+                    .is_none_or(|(_, to): &(String, String)| comparison.current.to > *to)
+                {
+                    period = Some((
+                        comparison.current.from.clone(),
+                        comparison.current.to.clone(),
+                    ));
+                }
+                Some(entry)
+            })
+            .collect();
+        let (from, to) = period?;
+
+        entries.sort_by(|a, b| {
+            match (a.growth, b.growth) {
+                (Some(a_growth), Some(b_growth)) => b_growth.total_cmp(&a_growth),
+                (Some(_), None) => Ordering::Less,
+                (None, Some(_)) => Ordering::Greater,
+                (None, None) => (b.current - b.previous).total_cmp(&(a.current - a.previous)),
+            }
+            .then_with(|| a.team.cmp(&b.team))
+        });
+
+        Some(Self {
+            scope: scope.to_string(),
+            from,
+            to,
+            entries,
+        })
+    }
+
+    /// Renders the leaderboard as Markdown
+    pub fn to_markdown(&self) -> String {
+        let mut markdown = format!(
+            "*Copilot team leaderboard for {}* ({} to {}, against the {} days before)\n\n",
+            self.scope, self.from, self.to, PERIOD_DAYS
+        );
+        for (rank, entry) in self.entries.iter().enumerate() {
+            let growth = match entry.growth {
+                Some(growth) => format!("{:+.1}%", growth * 100.0),
+                None => "new".to_string(),
+            };
+            markdown.push_str(&format!(
+                "{}. {}: {:.1} engaged users per day ({}, from {:.1})\n",
+                rank + 1,
+                entry.team,
+                entry.current,
+                growth,
+                entry.previous
+            ));
+        }
+        markdown
+    }
+}
+
+/// Builds the leaderboard of a scope's teams, posts it and writes the artifact
+///
+/// # Arguments
+///
+/// * `scope` - Scope the teams belong to, e.g. "enterprise:acme"
+/// * `teams` - Fetched days of every team, by slug
+/// * `config` - Where the leaderboard is written to
+/// * `notifications` - Channels the leaderboard is posted to
+/// * `agent` - Settings of the HTTP agent writing an S3 artifact
+///
+/// # Returns
+///
+/// * `Result<usize>` - Number of ranked teams, or an error if the artifact
+///   can't be written or a channel failed
+pub fn process_leaderboard(
+    scope: &str,
+    teams: &[(String, Vec<CopilotMetrics>)],
+    config: &LeaderboardConfig,
+    notifications: &NotificationSink,
+    agent: &AgentConfig,
+) -> Result<usize> {
+    let Some(leaderboard) = Leaderboard::from_teams(scope, teams) else {
+        info!(
+            "No team of {} has {} days of metrics, skipping the leaderboard",
+            scope,
+            2 * PERIOD_DAYS
+        );
+        return Ok(0);
+    };
+
+    if let Some(output) = &config.output {
+        write_artifact(&leaderboard, output, agent)?;
+        info!("Leaderboard of {} written to {}", scope, output);
+    }
+
+    if notifications.is_empty() {
+        if config.output.is_none() {
+            warn!(
+                "Leaderboard of {} has no notification channel or output",
+                scope
+            );
+        }
+    } else {
+        notifications.notify(&Notification {
+            kind: NotificationKind::Report,
+            title: format!("Copilot team leaderboard: {}", scope),
+            message: leaderboard.to_markdown(),
+            dedup_key: format!("{}:leaderboard:{}", scope, leaderboard.to),
+        })?;
+    }
+
+    Ok(leaderboard.entries.len())
+}
+
+/// Writes the leaderboard to a local path or `s3://<bucket>/<key>`
+///
+/// Outputs ending in `.json` get JSON, all others Markdown.
+fn write_artifact(leaderboard: &Leaderboard, output: &str, agent: &AgentConfig) -> Result<()> {
+    let (body, content_type) = if output.ends_with(".json") {
+        (
+            serde_json::to_vec_pretty(leaderboard).expect("leaderboard serializes to JSON"),
+            "application/json",
+        )
+    } else {
+        (leaderboard.to_markdown().into_bytes(), "text/markdown")
+    };
+
+    match parse_location(output) {
+        Some((bucket, key)) => S3Client::new(AwsCredentials::from_env()?, bucket, agent)
+            .put_object(key, &body, content_type)?,
+        None => {
+            fs::write(output, body).map_err(|e| GhrustError::Artifact(output.to_string(), e))?
+        }
+    }
+    Ok(())
+}
@@ -18,6 +18,60 @@
 //!   Handles fetching metrics for individual teams within a GitHub Enterprise
 //!   organization and sending them to Datadog with team-specific namespaces.
 //!
+//! * `organization` - Processes the metrics of every organization of an
+//!   enterprise and sends them under organization-specific namespaces.
+//!
+//! * `handoff` - The metrics file written by `ghrust fetch` and sent by
+//!   `ghrust send`, so fetching and sending can run in different security
+//!   contexts.
+//!
+//! * `backfill` - Backfills long ranges one day at a time, with a checkpoint
+//!   file so an interrupted backfill resumes where it stopped.
+//!
+//! * `alerts` - Threshold rules evaluated against the latest day of a scope,
+//!   notifying through the configured channels when they fire.
+//!
+//! * `anomaly` - Z-scores of the latest day against the reporting window,
+//!   flagging sudden drops such as outages.
+//!
+//! * `baseline` - Weekly aggregates kept in S3 beyond the 30-day window,
+//!   compared with the same week last quarter.
+//!
+//! * `rollups` - Daily metrics aggregated into complete weeks and months,
+//!   reported as one point per period.
+//!
+//! * `distribution` - Percentiles of the key metrics across teams, to spot
+//!   outlier teams.
+//!
+//! * `leaderboard` - Teams ranked by engagement growth, posted through the
+//!   notification channels and kept as a Markdown or JSON artifact.
+//!
+//! * `persistence` - The metrics store and exporters the fetched days are
+//!   kept in, passed to the processors by the pipeline.
+//!
+//! * `privacy` - Salted hashes replacing team slugs in namespaces, recorded
+//!   in a local mapping file.
+//!
+//! * `adoption` - Adoption rates of teams and organizations, relative to
+//!   their member counts.
+//!
+//! * `seats` - Reports assigned Copilot seats without activity in the
+//!   reporting window.
+//!
+//! * `digest` - Builds the weekly Copilot adoption digest and emails it
+//!   through Amazon SES.
+//!
+//! * `diff` - Compares aggregated metrics between two consecutive periods.
+//!
+//! * `ranking` - Ranks teams on engaged users per member, acceptance rate
+//!   and chats per user.
+//!
+//! * `report` - Renders a metrics window into a standalone HTML report for
+//!   stakeholders without Datadog access.
+//!
+//! * `summary` - Structured per-scope summaries of a run, logged once and
+//!   returned in the Lambda response.
+//!
 //! ## Architecture
 //!
 //! The processors follow these general steps:
@@ -28,5 +82,26 @@
 //! 5. Return success or error information
 
 // This module contains processors for different metrics
+pub mod adoption;
+pub mod alerts;
+pub mod anomaly;
+pub mod backfill;
+pub mod baseline;
+pub mod diff;
+pub mod digest;
+pub mod distribution;
 pub mod enterprise;
+pub mod handoff;
+pub mod leaderboard;
+pub mod organization;
+pub mod persistence;
+pub mod privacy;
+pub mod ranking;
+pub mod report;
+pub mod rollups;
+pub mod seats;
+pub mod summary;
 pub mod team;
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,243 @@
+//! # Organization Metrics Processing
+//!
+//! This module handles the processing of GitHub Copilot metrics for the
+//! organizations of an enterprise. Organizations usually map to business
+//! units, so their metrics sit between the enterprise totals and the team
+//! slices. It provides functionality to:
+//!
+//! - List all organizations of an enterprise
+//! - Fetch each organization's metrics from the GitHub API
+//! - Send the metrics to Datadog under `{namespace}.org.{slug}`
+//! - Report adoption rates relative to the organization's member count
+//! - Optionally report the organization's seat breakdown
+//!
+//! A failing organization is logged and counted, but doesn't stop the others.
+
+use std::time::Instant;
+
+use tracing::{debug, error, info};
+
+use super::adoption::{send_adoption, Members};
+use super::persistence::Persistence;
+use super::seats::send_seat_breakdown;
+use super::summary::{RunSummary, ScopeSummary};
+use super::team::sanitize_slug;
+use crate::error::{GhrustError, Result};
+use crate::services::{
+    datadog::{names::NamespaceTemplate, DatadogSink, TagContext},
+    github::{
+        calculate_default_since_date, get_enterprise_organizations, GitHubClient, GitHubError,
+    },
+};
+
+/// Number of organizations processed successfully and unsuccessfully in a run
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct OrgOutcome {
+    /// Organizations whose metrics were sent
+    pub succeeded: usize,
+    /// Organizations that failed
+    pub failed: usize,
+}
+
+impl OrgOutcome {
+    /// Total number of organizations attempted
+    pub fn total(&self) -> usize {
+        self.succeeded + self.failed
+    }
+}
+
+/// How the namespaces and tags of a run's organizations are resolved
+#[derive(Debug, Clone, Copy)]
+pub struct OrgNaming<'a> {
+    /// Base namespace prefix for Datadog metrics
+    pub datadog_namespace: &'a str,
+    /// Template of the organization namespaces
+    pub template: &'a NamespaceTemplate,
+}
+
+impl OrgNaming<'_> {
+    /// Returns the namespace of an organization, see [`org_namespace`]
+    pub fn namespace(&self, org: &str) -> String {
+        org_namespace(self.datadog_namespace, self.template, org)
+    }
+
+    /// Returns the tags of an organization, see [`org_tags`]
+    pub fn tags(&self, org: &str) -> Vec<String> {
+        org_tags(self.template, org)
+    }
+}
+
+/// Process and send the metrics of a single organization to Datadog
+///
+/// # Arguments
+///
+/// * `github_client` - GitHub client the metrics are fetched with
+/// * `org` - Login of the organization
+/// * `datadog` - Datadog sink fanning out to the configured destinations
+/// * `naming` - Base namespace and namespace template
+/// * `seat_breakdown` - Whether the organization's seat breakdown is sent
+/// * `persistence` - Where the fetched days are kept
+/// * `summary` - Summary of the scope, filled in with fetch and send counts
+///
+/// # Returns
+///
+/// * `Result<()>` - Ok(()) if successful, or an error if any step fails
+pub fn process_org_metrics(
+    github_client: &GitHubClient,
+    org: &str,
+    datadog: &DatadogSink,
+    naming: &OrgNaming,
+    seat_breakdown: bool,
+    persistence: &Persistence,
+    summary: &mut ScopeSummary,
+) -> Result<()> {
+    info!("Starting organization metrics processing for {}", org);
+
+    let namespace = naming.namespace(org);
+    let tags = naming.tags(org);
+    if seat_breakdown {
+        send_seat_breakdown(github_client, org, datadog, &namespace, &tags, summary);
+    }
+
+    let since = calculate_default_since_date();
+    let started = Instant::now();
+    let fetched = persistence
+        .store
+        .fetch_missing(&summary.scope, &since, |since| {
+            github_client.fetch_org_metrics(org, since)
+        });
+    summary.record_fetch(started);
+    let metrics = match fetched {
+        Ok(metrics) => metrics,
+        Err(GitHubError::NotModified(_)) => {
+            info!(
+                "Organization metrics of {} not modified since the last run, not sending them",
+                org
+            );
+            summary.not_modified = true;
+            return Ok(());
+        }
+        Err(e) => {
+            return Err(GhrustError::github("Failed to fetch organization metrics")(
+                e,
+            ))
+        }
+    };
+    if metrics.is_empty() {
+        debug!("No organization metrics returned for {}", org);
+        return Ok(());
+    }
+
+    summary.dates_fetched = metrics.len();
+    persistence.keep(&summary.scope, &metrics);
+
+    let before = datadog.stats();
+    let sent = datadog.send_metrics_with_tags(&metrics, &namespace, &tags);
+    summary.record_send(&before, &datadog.stats());
+    sent?;
+
+    send_adoption(
+        github_client,
+        Members::Org(org),
+        &metrics,
+        datadog,
+        &namespace,
+        &tags,
+        summary,
+    );
+
+    info!("Organization metrics processing completed for {}", org);
+    Ok(())
+}
+
+/// Process metrics for every organization of an enterprise
+///
+/// # Arguments
+///
+/// * `github_client` - GitHub client the metrics are fetched with
+/// * `enterprise_id` - Slug of the GitHub Enterprise whose organizations are listed
+/// * `datadog` - Datadog sink fanning out to the configured destinations
+/// * `naming` - Base namespace and namespace template
+/// * `seat_breakdown` - Whether every organization's seat breakdown is sent
+/// * `persistence` - Where the fetched days are kept
+/// * `run` - Run summary receiving one scope summary per organization
+///
+/// # Returns
+///
+/// * `Result<OrgOutcome>` - The number of organizations that succeeded and
+///   failed, or an error if the organizations can't be listed
+pub fn process_all_orgs(
+    github_client: &GitHubClient,
+    enterprise_id: &str,
+    datadog: &DatadogSink,
+    naming: &OrgNaming,
+    seat_breakdown: bool,
+    persistence: &Persistence,
+    run: &mut RunSummary,
+) -> Result<OrgOutcome> {
+    let orgs = get_enterprise_organizations(github_client, enterprise_id)
+        .map_err(GhrustError::github("Failed to list organizations"))?;
+    info!("Processing metrics for {} organizations", orgs.len());
+
+    let mut outcome = OrgOutcome::default();
+    for org in &orgs {
+        let scope = format!("org:{}/{}", enterprise_id, org);
+        let namespace = naming.namespace(org);
+        let datadog = &datadog.scoped(TagContext::org(enterprise_id, org));
+
+        match run.track(scope, &namespace, |summary| {
+            process_org_metrics(
+                github_client,
+                org,
+                datadog,
+                naming,
+                seat_breakdown,
+                persistence,
+                summary,
+            )
+        }) {
+            Ok(_) => outcome.succeeded += 1,
+            Err(e) => {
+                outcome.failed += 1;
+                error!("Error processing organization {}: {}", org, e);
+            }
+        }
+    }
+
+    info!(
+        "Organization metrics processing completed. Successful: {}, Failed: {}",
+        outcome.succeeded, outcome.failed
+    );
+    Ok(outcome)
+}
+
+/// Returns the Datadog namespace for an organization's metrics
+///
+/// # Arguments
+///
+/// * `datadog_namespace` - Base namespace prefix for Datadog metrics
+/// * `template` - Template of the organization namespaces
+/// * `org` - Login of the organization
+///
+/// # Returns
+///
+/// * `String` - `{datadog_namespace}.org.{slug}` (or the namespace template
+///   applied to the slug) with the login sanitized like a team slug
+pub fn org_namespace(datadog_namespace: &str, template: &NamespaceTemplate, org: &str) -> String {
+    template.render(datadog_namespace, "org", &sanitize_slug(org))
+}
+
+/// Returns the tags added to every series of an organization
+///
+/// # Arguments
+///
+/// * `template` - Template of the organization namespaces
+/// * `org` - Login of the organization
+///
+/// # Returns
+///
+/// * `Vec<String>` - `org:{login}` if the namespace template leaves the
+///   login out, otherwise no tags
+pub fn org_tags(template: &NamespaceTemplate, org: &str) -> Vec<String> {
+    template.slug_tag("org", org).into_iter().collect()
+}
@@ -0,0 +1,36 @@
+//! # Persistence of Fetched Days
+//!
+//! Everything a run keeps of the fetched days besides sending them to
+//! Datadog: the metrics store, which also serves stored days instead of
+//! fetching them again, and the exporters. The pipeline opens both from the
+//! configuration and passes them to the processors, so tenants and server
+//! runs each use their own.
+//!
+//! Keeping the days is best effort: failures are logged and never fail the
+//! processing of the scope.
+
+use crate::models::github::CopilotMetrics;
+use crate::services::export::ExportSink;
+use crate::services::store::Store;
+
+/// Where the processors keep the fetched days of a run
+#[derive(Default)]
+pub struct Persistence {
+    /// The metrics store, if one is configured
+    pub store: Store,
+    /// The configured exporters
+    pub exports: ExportSink,
+}
+
+impl Persistence {
+    /// Keeps the fetched days of a scope
+    ///
+    /// # Arguments
+    ///
+    /// * `scope` - Scope the days belong to, e.g. "enterprise:acme"
+    /// * `metrics` - The fetched days
+    pub fn keep(&self, scope: &str, metrics: &[CopilotMetrics]) {
+        self.store.persist(scope, metrics);
+        self.exports.export(scope, metrics);
+    }
+}
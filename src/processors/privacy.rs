@@ -0,0 +1,136 @@
+//! # Team Privacy Mode
+//!
+//! Team namespaces (`{namespace}.team.{slug}`) reveal an enterprise's
+//! organizational structure to everyone with access to the Datadog
+//! organization. In privacy mode the slug segment is replaced with a salted
+//! hash of the slug (see `services::datadog::redact`), which stays stable
+//! across runs so dashboards and monitors keep working. With a namespace
+//! template that leaves the slug out, the `team:` tag carries the hash.
+//!
+//! Every hash that is sent is recorded in a local JSON mapping file
+//! (`{"<hash>": "<slug>"}`), so operators can still tell which team a
+//! namespace belongs to. Teams with an explicit namespace mapping keep that
+//! namespace, and mapping tags are sent as configured.
+
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use tracing::info;
+
+use super::team::parent_teams;
+use crate::config::TeamMapping;
+use crate::services::datadog::{names::NamespaceTemplate, redact::salted_hash};
+
+/// Returns the team mappings with hashed namespaces for unmapped teams
+///
+/// Parent teams of the processed teams are included, so their aggregated
+/// namespaces are hashed as well.
+///
+/// # Arguments
+///
+/// * `datadog_namespace` - Base namespace prefix for Datadog metrics
+/// * `template` - Template of the namespaces of teams without a mapping
+/// * `team_slugs` - Slugs of the processed teams
+/// * `team_mappings` - Namespace and tag overrides keyed by team slug
+/// * `salt` - Secret salt of the deployment
+///
+/// # Returns
+///
+/// * `HashMap<String, TeamMapping>` - The mappings, where every team without
+///   an explicit namespace maps to `{datadog_namespace}.team.{hash}` (or the
+///   namespace template applied to the hash)
+pub fn hash_team_namespaces(
+    datadog_namespace: &str,
+    template: &NamespaceTemplate,
+    team_slugs: &[String],
+    team_mappings: &HashMap<String, TeamMapping>,
+    salt: &str,
+) -> HashMap<String, TeamMapping> {
+    let mut mappings = team_mappings.clone();
+    let parents = parent_teams(team_slugs, team_mappings);
+
+    for slug in team_slugs.iter().chain(&parents) {
+        let mapping = mappings.entry(slug.clone()).or_default();
+        if mapping.namespace.is_none() {
+            let hash = salted_hash(salt, slug);
+            mapping.namespace = Some(template.render(datadog_namespace, "team", &hash));
+            mapping.tags.extend(template.slug_tag("team", &hash));
+        }
+    }
+
+    mappings
+}
+
+/// Returns the hashes sent for the processed teams and their parents
+///
+/// Teams with an explicit namespace mapping are not hashed and are left out.
+///
+/// # Arguments
+///
+/// * `team_slugs` - Slugs of the processed teams
+/// * `team_mappings` - Namespace and tag overrides keyed by team slug
+/// * `salt` - Secret salt of the deployment
+///
+/// # Returns
+///
+/// * `BTreeMap<String, String>` - Team slugs keyed by their hash
+pub fn team_hashes(
+    team_slugs: &[String],
+    team_mappings: &HashMap<String, TeamMapping>,
+    salt: &str,
+) -> BTreeMap<String, String> {
+    let parents = parent_teams(team_slugs, team_mappings);
+    team_slugs
+        .iter()
+        .chain(&parents)
+        .filter(|slug| {
+            let mapping = team_mappings.get(slug.as_str());
+            mapping.and_then(|m| m.namespace.as_ref()).is_none()
+        })
+        .map(|slug| (salted_hash(salt, slug), slug.clone()))
+        .collect()
+}
+
+/// Records hashes in the local mapping file
+///
+/// Entries already in the file are kept, so the file decodes the hashes of
+/// every team that was ever sent, including teams that were removed since.
+///
+/// # Arguments
+///
+/// * `path` - Path of the JSON mapping file; created if it doesn't exist
+/// * `hashes` - Slugs keyed by hash, as returned by `team_hashes`
+///
+/// # Returns
+///
+/// * `io::Result<()>` - Ok, or an error if the file can't be read, parsed or
+///   written
+pub fn update_mapping_file(path: &Path, hashes: &BTreeMap<String, String>) -> io::Result<()> {
+    let (mut mapping, existed): (BTreeMap<String, String>, bool) = match fs::read(path) {
+        Ok(contents) => (
+            serde_json::from_slice(&contents)
+                .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?,
+            true,
+        ),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => (BTreeMap::new(), false),
+        Err(e) => return Err(e),
+    };
+
+    let before = mapping.len();
+    mapping.extend(hashes.iter().map(|(h, s)| (h.clone(), s.clone())));
+    if existed && mapping.len() == before {
+        return Ok(());
+    }
+
+    let contents = serde_json::to_vec_pretty(&mapping)
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+    fs::write(path, contents)?;
+    info!(
+        "Recorded {} team hashes in {}",
+        mapping.len() - before,
+        path.display()
+    );
+    Ok(())
+}
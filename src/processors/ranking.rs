@@ -0,0 +1,226 @@
+//! # Team Ranking
+//!
+//! Ranks teams on their adoption over a metrics window, for deciding where
+//! enablement effort goes. Every team gets a standing with:
+//!
+//! * `engaged_users_rate` - Average daily engaged users per team member, if
+//!   the team's owning organization is mapped (see `TeamMapping::org`)
+//! * `acceptance_rate` - Accepted code suggestions per suggestion
+//! * `chats_per_user` - IDE and GitHub.com chats per engaged chat user and day
+//!
+//! Teams are ordered by one of these metrics, best first; teams without a
+//! value for it come last, in slug order.
+
+use std::cmp::Ordering;
+use std::fmt;
+use std::str::FromStr;
+
+use serde::Serialize;
+
+use crate::models::github::{CopilotMetrics, Model};
+
+/// Metric teams are ranked by
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RankBy {
+    /// Average daily engaged users per member
+    #[default]
+    EngagedUsersRate,
+    /// Accepted suggestions per suggestion
+    AcceptanceRate,
+    /// Chats per engaged chat user and day
+    ChatsPerUser,
+}
+
+impl FromStr for RankBy {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value {
+            "engaged_users_rate" => Ok(Self::EngagedUsersRate),
+            "acceptance_rate" => Ok(Self::AcceptanceRate),
+            "chats_per_user" => Ok(Self::ChatsPerUser),
+            other => Err(format!(
+                "unknown ranking metric {} (expected engaged_users_rate, acceptance_rate or chats_per_user)",
+                other
+            )),
+        }
+    }
+}
+
+/// Adoption of one team over a metrics window
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct TeamStanding {
+    /// Slug of the team
+    pub team: String,
+    /// Members of the team, if known
+    pub members: Option<i64>,
+    /// Average daily engaged users
+    pub average_engaged_users: f64,
+    /// Average daily engaged users per member (0.0 to 1.0)
+    pub engaged_users_rate: Option<f64>,
+    /// Accepted suggestions per suggestion (0.0 to 1.0)
+    pub acceptance_rate: Option<f64>,
+    /// Chats per engaged chat user and day
+    pub chats_per_user: Option<f64>,
+}
+
+impl TeamStanding {
+    /// Computes a team's standing from its metrics window
+    ///
+    /// # Arguments
+    ///
+    /// * `team` - Slug of the team
+    /// * `metrics` - The team's daily metrics
+    /// * `members` - Members of the team, if known
+    ///
+    /// # Returns
+    ///
+    /// * `Option<TeamStanding>` - The standing, or `None` if the window is
+    ///   empty
+    pub fn from_metrics(
+        team: &str,
+        metrics: &[CopilotMetrics],
+        members: Option<i64>,
+    ) -> Option<Self> {
+        if metrics.is_empty() {
+            return None;
+        }
+
+        let average_engaged_users = metrics
+            .iter()
+            .map(|m| m.total_engaged_users.unwrap_or(0))
+            .sum::<i64>() as f64
+            / metrics.len() as f64;
+
+        let (suggestions, acceptances) = metrics
+            .iter()
+            .filter_map(|m| m.copilot_ide_code_completions.as_ref())
+            .flat_map(|c| c.languages.iter().flatten())
+            .fold((0, 0), |(suggestions, acceptances), l| {
+                (
+                    suggestions + l.total_code_suggestions.unwrap_or(0),
+                    acceptances + l.total_code_acceptances.unwrap_or(0),
+                )
+            });
+
+        let chat_user_days: i64 = metrics
+            .iter()
+            .map(|m| {
+                m.copilot_ide_chat
+                    .as_ref()
+                    .map_or(0, |c| c.total_engaged_users)
+                    + m.copilot_dotcom_chat
+                        .as_ref()
+                        .map_or(0, |c| c.total_engaged_users)
+            })
+            .sum();
+        let chats: i64 = metrics.iter().map(chats).sum();
+
+        Some(Self {
+            team: team.to_string(),
+            members,
+            average_engaged_users,
+            engaged_users_rate: members
+                .filter(|m| *m > 0)
+                .map(|m| average_engaged_users / m as f64),
+            acceptance_rate: (suggestions > 0).then(|| acceptances as f64 / suggestions as f64),
+            chats_per_user: (chat_user_days > 0).then(|| chats as f64 / chat_user_days as f64),
+        })
+    }
+
+    /// Returns the value the team is ranked by
+    pub fn value(&self, rank_by: RankBy) -> Option<f64> {
+        match rank_by {
+            RankBy::EngagedUsersRate => self.engaged_users_rate,
+            RankBy::AcceptanceRate => self.acceptance_rate,
+            RankBy::ChatsPerUser => self.chats_per_user,
+        }
+    }
+}
+
+/// Teams ordered by one adoption metric
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct TeamRanking {
+    /// The metric the teams are ordered by
+    pub rank_by: RankBy,
+    /// Standings of the teams, best first
+    pub teams: Vec<TeamStanding>,
+}
+
+impl TeamRanking {
+    /// Orders team standings by a metric
+    ///
+    /// # Arguments
+    ///
+    /// * `teams` - The standings, in any order
+    /// * `rank_by` - The metric to order by, highest first
+    pub fn new(mut teams: Vec<TeamStanding>, rank_by: RankBy) -> Self {
+        teams.sort_by(|a, b| match (a.value(rank_by), b.value(rank_by)) {
+            (Some(a_value), Some(b_value)) => b_value
+                .total_cmp(&a_value)
+                .then_with(|| a.team.cmp(&b.team)),
+            (Some(_), None) => Ordering::Less,
+            (None, Some(_)) => Ordering::Greater,
+            (None, None) => a.team.cmp(&b.team),
+        });
+        Self { rank_by, teams }
+    }
+
+    /// Renders the ranking as a plain-text table
+    pub fn to_table(&self) -> String {
+        let mut table = format!(
+            "{:>4} {:<28} {:>8} {:>10} {:>10} {:>10} {:>10}\n",
+            "rank", "team", "members", "engaged", "engaged%", "accept%", "chats/user"
+        );
+        for (rank, team) in self.teams.iter().enumerate() {
+            table.push_str(&format!(
+                "{:>4} {:<28} {:>8} {:>10.1} {:>10} {:>10} {:>10}\n",
+                rank + 1,
+                team.team,
+                team.members.map_or("-".to_string(), |m| m.to_string()),
+                team.average_engaged_users,
+                Percent(team.engaged_users_rate),
+                Percent(team.acceptance_rate),
+                team.chats_per_user
+                    .map_or("-".to_string(), |c| format!("{:.2}", c)),
+            ));
+        }
+        table
+    }
+}
+
+/// A share formatted as a percentage, or "-" if unknown
+struct Percent(Option<f64>);
+
+impl fmt::Display for Percent {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.0 {
+            Some(share) => f.pad(&format!("{:.1}%", share * 100.0)),
+            None => f.pad("-"),
+        }
+    }
+}
+
+/// Sums the IDE and GitHub.com chats of a day
+fn chats(metric: &CopilotMetrics) -> i64 {
+    let model_chats = |models: &Option<Vec<Model>>| -> i64 {
+        models
+            .iter()
+            .flatten()
+            .map(|m| m.total_chats.unwrap_or(0))
+            .sum()
+    };
+    let ide = metric.copilot_ide_chat.as_ref().map_or(0, |c| {
+        c.editors
+            .iter()
+            .flatten()
+            .map(|e| model_chats(&e.models))
+            .sum()
+    });
+    let dotcom = metric
+        .copilot_dotcom_chat
+        .as_ref()
+        .map_or(0, |c| model_chats(&c.models));
+    ide + dotcom
+}
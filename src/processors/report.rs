@@ -0,0 +1,246 @@
+//! # HTML Report
+//!
+//! Renders a metrics window into a standalone HTML report for stakeholders
+//! without Datadog access. The report has no external dependencies: charts are
+//! drawn as inline SVG so the file can be attached to an email or opened from
+//! disk.
+//!
+//! The report contains:
+//!
+//! - Daily active and engaged users over the window
+//! - Accepted code suggestions per language
+//! - Peak daily engaged users per editor
+
+use std::collections::HashMap;
+use std::fmt::Write;
+
+use super::digest::escape_html;
+use crate::models::github::CopilotMetrics;
+
+/// Width of a chart in pixels
+const CHART_WIDTH: f64 = 720.0;
+
+/// Height of the plot area of the user trend chart in pixels
+const TREND_HEIGHT: f64 = 220.0;
+
+/// Height of a single bar in a bar chart in pixels
+const BAR_HEIGHT: f64 = 22.0;
+
+/// Width reserved for labels left of the bars in pixels
+const LABEL_WIDTH: f64 = 160.0;
+
+/// Number of entries shown in a bar chart
+const MAX_BARS: usize = 10;
+
+/// Users on a single day of the window
+#[derive(Debug, Clone, PartialEq)]
+pub struct DailyUsers {
+    /// ISO date (YYYY-MM-DD)
+    pub date: String,
+    /// Users with Copilot activity
+    pub active_users: i64,
+    /// Users who engaged with a Copilot feature
+    pub engaged_users: i64,
+}
+
+/// A named value shown as one bar of a bar chart
+#[derive(Debug, Clone, PartialEq)]
+pub struct Bar {
+    /// Label of the bar
+    pub name: String,
+    /// Value of the bar
+    pub value: i64,
+}
+
+/// A metrics window prepared for rendering
+#[derive(Debug, Clone, PartialEq)]
+pub struct MetricsReport {
+    /// Title of the report, e.g. the enterprise or team
+    pub title: String,
+    /// Users per day, oldest first
+    pub days: Vec<DailyUsers>,
+    /// Accepted code suggestions per language, most first
+    pub languages: Vec<Bar>,
+    /// Peak daily engaged users per editor, most first
+    pub editors: Vec<Bar>,
+}
+
+impl MetricsReport {
+    /// Aggregates a metrics window for the report
+    ///
+    /// # Arguments
+    ///
+    /// * `title` - Title of the report, e.g. the enterprise or team
+    /// * `metrics` - Daily metrics in any order
+    ///
+    /// # Returns
+    ///
+    /// * `MetricsReport` - The aggregated report
+    pub fn from_metrics(title: &str, metrics: &[CopilotMetrics]) -> Self {
+        let mut sorted: Vec<&CopilotMetrics> = metrics.iter().collect();
+        sorted.sort_by(|a, b| a.date.cmp(&b.date));
+
+        let days = sorted
+            .iter()
+            .map(|m| DailyUsers {
+                date: m.date.clone(),
+                active_users: m.total_active_users.unwrap_or(0),
+                engaged_users: m.total_engaged_users.unwrap_or(0),
+            })
+            .collect();
+
+        let mut languages: HashMap<String, i64> = HashMap::new();
+        let mut editors: HashMap<String, i64> = HashMap::new();
+        for metric in &sorted {
+            let Some(completions) = &metric.copilot_ide_code_completions else {
+                continue;
+            };
+            for language in completions.languages.iter().flatten() {
+                *languages.entry(language.name.clone()).or_default() +=
+                    language.total_code_acceptances.unwrap_or(0);
+            }
+            for editor in completions.editors.iter().flatten() {
+                let peak = editors.entry(editor.name.clone()).or_default();
+                *peak = (*peak).max(editor.total_engaged_users);
+            }
+        }
+
+        Self {
+            title: title.to_string(),
+            days,
+            languages: ranked(languages),
+            editors: ranked(editors),
+        }
+    }
+
+    /// Renders the report as a standalone HTML document
+    pub fn to_html(&self) -> String {
+        let period = match (self.days.first(), self.days.last()) {
+            (Some(first), Some(last)) => format!("{} to {}", first.date, last.date),
+            _ => "no data".to_string(),
+        };
+
+        format!(
+            "<!DOCTYPE html>\n<html><head><meta charset=\"utf-8\"><title>{title}</title>\
+             <style>body{{font-family:sans-serif;margin:2em}}svg text{{font-size:12px}}</style>\
+             </head><body>\
+             <h1>{title}</h1><p>{period}</p>\
+             <h2>Users</h2>{users}\
+             <h2>Accepted suggestions by language</h2>{languages}\
+             <h2>Peak daily engaged users by editor</h2>{editors}\
+             </body></html>\n",
+            title = escape_html(&format!("GitHub Copilot report: {}", self.title)),
+            period = escape_html(&period),
+            users = self.users_chart(),
+            languages = bar_chart(&self.languages),
+            editors = bar_chart(&self.editors),
+        )
+    }
+
+    /// Draws active and engaged users per day as two polylines
+    fn users_chart(&self) -> String {
+        if self.days.is_empty() {
+            return "<p>No data</p>".to_string();
+        }
+
+        let max = self
+            .days
+            .iter()
+            .map(|d| d.active_users.max(d.engaged_users))
+            .max()
+            .unwrap_or(0)
+            .max(1) as f64;
+        let step = if self.days.len() > 1 {
+            CHART_WIDTH / (self.days.len() - 1) as f64
+        } else {
+            0.0
+        };
+        let points = |value: fn(&DailyUsers) -> i64| -> String {
+            self.days
+                .iter()
+                .enumerate()
+                .map(|(i, day)| {
+                    let x = i as f64 * step;
+                    let y = TREND_HEIGHT - value(day) as f64 / max * TREND_HEIGHT;
+                    format!("{:.1},{:.1}", x, y)
+                })
+                .collect::<Vec<_>>()
+                .join(" ")
+        };
+
+        let last = self.days.len() - 1;
+        format!(
+            "<svg width=\"{width}\" height=\"{height}\" viewBox=\"-40 -10 {vb_width} {height}\">\
+             <line x1=\"0\" y1=\"{plot}\" x2=\"{chart}\" y2=\"{plot}\" stroke=\"#999\"/>\
+             <text x=\"-5\" y=\"4\" text-anchor=\"end\">{max}</text>\
+             <text x=\"-5\" y=\"{plot}\" text-anchor=\"end\">0</text>\
+             <polyline fill=\"none\" stroke=\"#1f77b4\" stroke-width=\"2\" points=\"{active}\"/>\
+             <polyline fill=\"none\" stroke=\"#2ca02c\" stroke-width=\"2\" points=\"{engaged}\"/>\
+             <text x=\"0\" y=\"{label}\">{first}</text>\
+             <text x=\"{chart}\" y=\"{label}\" text-anchor=\"end\">{last}</text>\
+             <text x=\"0\" y=\"{legend}\" fill=\"#1f77b4\">Active users</text>\
+             <text x=\"120\" y=\"{legend}\" fill=\"#2ca02c\">Engaged users</text>\
+             </svg>",
+            width = CHART_WIDTH + 60.0,
+            vb_width = CHART_WIDTH + 60.0,
+            height = TREND_HEIGHT + 50.0,
+            plot = TREND_HEIGHT,
+            chart = CHART_WIDTH,
+            max = max as i64,
+            active = points(|d| d.active_users),
+            engaged = points(|d| d.engaged_users),
+            label = TREND_HEIGHT + 16.0,
+            legend = TREND_HEIGHT + 34.0,
+            first = escape_html(&self.days[0].date),
+            last = escape_html(&self.days[last].date),
+        )
+    }
+}
+
+/// Sorts values descending (then by name) and keeps the largest entries
+fn ranked(values: HashMap<String, i64>) -> Vec<Bar> {
+    let mut bars: Vec<Bar> = values
+        .into_iter()
+        .map(|(name, value)| Bar { name, value })
+        .collect();
+    bars.sort_by(|a, b| b.value.cmp(&a.value).then_with(|| a.name.cmp(&b.name)));
+    bars.truncate(MAX_BARS);
+    bars
+}
+
+/// Draws a horizontal bar chart
+fn bar_chart(bars: &[Bar]) -> String {
+    if bars.is_empty() {
+        return "<p>No data</p>".to_string();
+    }
+
+    let max = bars.iter().map(|b| b.value).max().unwrap_or(0).max(1) as f64;
+    let plot_width = CHART_WIDTH - LABEL_WIDTH - 60.0;
+
+    let mut svg = format!(
+        "<svg width=\"{}\" height=\"{}\">",
+        CHART_WIDTH,
+        bars.len() as f64 * (BAR_HEIGHT + 6.0)
+    );
+    for (i, bar) in bars.iter().enumerate() {
+        let y = i as f64 * (BAR_HEIGHT + 6.0);
+        let width = bar.value.max(0) as f64 / max * plot_width;
+        let _ = write!(
+            svg,
+            "<text x=\"{label}\" y=\"{text_y:.1}\" text-anchor=\"end\">{name}</text>\
+             <rect x=\"{x}\" y=\"{y:.1}\" width=\"{width:.1}\" height=\"{height}\" fill=\"#1f77b4\"/>\
+             <text x=\"{value_x:.1}\" y=\"{text_y:.1}\">{value}</text>",
+            label = LABEL_WIDTH - 8.0,
+            text_y = y + BAR_HEIGHT * 0.7,
+            name = escape_html(&bar.name),
+            x = LABEL_WIDTH,
+            y = y,
+            width = width,
+            height = BAR_HEIGHT,
+            value_x = LABEL_WIDTH + width + 6.0,
+            value = bar.value,
+        );
+    }
+    svg.push_str("</svg>");
+    svg
+}
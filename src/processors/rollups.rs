@@ -0,0 +1,226 @@
+//! # Period Rollups
+//!
+//! Daily Copilot numbers are noisy (weekends, holidays, release days), while
+//! leadership reviews usage per week. This module aggregates the daily window
+//! into complete calendar periods and reports every period as one point:
+//!
+//! * `weekly.{metric}` - ISO weeks (Monday to Sunday), dated on the Monday
+//! * `monthly.{metric}` - Calendar months, dated on the first of the month
+//!
+//! The metrics are those of the period comparison (`diff`): user counts are
+//! daily averages, activity counts (suggestions, acceptances, lines) are
+//! summed over the period and `acceptance_rate` is the period's ratio of
+//! acceptances to suggestions. Periods the window doesn't cover completely,
+//! such as the current week, are not reported, so a point never changes once
+//! it was sent.
+//!
+//! A 30-day window rarely covers a whole month, so monthly rollups are built
+//! from the days kept in the metrics store (`with_stored_days`), of which the
+//! newest `REPORTED_MONTHS` complete months are sent. The long-term baseline
+//! only keeps weekly aggregates, whose weeks don't align with months, so it
+//! can't serve as a source.
+
+use std::borrow::Cow;
+use std::collections::BTreeMap;
+
+use chrono::{Datelike, Duration, Months, NaiveDate};
+use tracing::{info, warn};
+
+use super::diff::period_totals;
+use super::summary::ScopeSummary;
+use crate::error::Result;
+use crate::models::github::CopilotMetrics;
+use crate::services::datadog::{DatadogSink, Gauge};
+use crate::services::store::Store;
+
+/// Complete months sent per run, newest first
+pub const REPORTED_MONTHS: usize = 13;
+
+/// Calendar period days are rolled up into
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RollupPeriod {
+    /// ISO week, Monday to Sunday
+    Week,
+    /// Calendar month
+    Month,
+}
+
+impl RollupPeriod {
+    /// Metric prefix of the period's gauges
+    pub fn prefix(&self) -> &'static str {
+        match self {
+            Self::Week => "weekly",
+            Self::Month => "monthly",
+        }
+    }
+
+    /// Returns the first day of the period containing a date
+    pub fn start(&self, date: NaiveDate) -> NaiveDate {
+        match self {
+            Self::Week => date - Duration::days(date.weekday().num_days_from_monday() as i64),
+            Self::Month => date - Duration::days(date.day0() as i64),
+        }
+    }
+
+    /// Returns the number of days of the period starting on `start`
+    pub fn days(&self, start: NaiveDate) -> usize {
+        match self {
+            Self::Week => 7,
+            Self::Month => start
+                .checked_add_months(Months::new(1))
+                .map_or(31, |next| (next - start).num_days() as usize),
+        }
+    }
+
+    /// Returns how many of the newest complete periods are sent per run
+    pub fn reported(&self) -> usize {
+        match self {
+            // The window holds at most five weeks
+            Self::Week => 5,
+            Self::Month => REPORTED_MONTHS,
+        }
+    }
+}
+
+/// Aggregated metrics of one complete period
+#[derive(Debug, Clone, PartialEq)]
+pub struct Rollup {
+    /// First day of the period
+    pub start: NaiveDate,
+    /// Aggregated metrics, in the order of the period comparison
+    pub totals: Vec<(&'static str, f64)>,
+}
+
+/// Rolls the complete periods of a metrics window up
+///
+/// Days with unparseable dates are ignored.
+///
+/// # Arguments
+///
+/// * `metrics` - Daily metrics in any order
+/// * `period` - The period to roll up into
+///
+/// # Returns
+///
+/// * `Vec<Rollup>` - The complete periods, oldest first
+pub fn rollups(metrics: &[CopilotMetrics], period: RollupPeriod) -> Vec<Rollup> {
+    let mut periods: BTreeMap<NaiveDate, Vec<&CopilotMetrics>> = BTreeMap::new();
+    for metric in metrics {
+        let Ok(date) = NaiveDate::parse_from_str(&metric.date, "%Y-%m-%d") else {
+            continue;
+        };
+        periods.entry(period.start(date)).or_default().push(metric);
+    }
+
+    periods
+        .into_iter()
+        .filter(|(start, days)| days.len() == period.days(*start))
+        .map(|(start, days)| Rollup {
+            start,
+            totals: period_totals(&days),
+        })
+        .collect()
+}
+
+/// Adds the stored days of a scope to its fetched window
+///
+/// Fetched days replace stored days of the same date. Without a configured
+/// store, or if it can't be read, the window is returned unchanged.
+///
+/// # Arguments
+///
+/// * `store` - The metrics store of the run
+/// * `scope` - Scope the days are stored under, e.g. "enterprise:acme"
+/// * `metrics` - The scope's fetched days
+///
+/// # Returns
+///
+/// * `Cow<[CopilotMetrics]>` - The stored and fetched days, oldest first,
+///   or the window itself
+pub fn with_stored_days<'a>(
+    store: &Store,
+    scope: &str,
+    metrics: &'a [CopilotMetrics],
+) -> Cow<'a, [CopilotMetrics]> {
+    let Some(store) = store.backend() else {
+        return Cow::Borrowed(metrics);
+    };
+    match store.load(scope) {
+        Ok(stored) => {
+            let mut days: BTreeMap<String, CopilotMetrics> = stored
+                .into_iter()
+                .map(|metric| (metric.date.clone(), metric))
+                .collect();
+            days.extend(
+                metrics
+                    .iter()
+                    .map(|metric| (metric.date.clone(), metric.clone())),
+            );
+            Cow::Owned(days.into_values().collect())
+        }
+        Err(e) => {
+            warn!("Failed to load the stored days of {}: {}", scope, e);
+            Cow::Borrowed(metrics)
+        }
+    }
+}
+
+/// Converts rollups to the gauges sent to Datadog
+///
+/// # Arguments
+///
+/// * `rollups` - The rolled-up periods
+/// * `period` - The period they were rolled up into
+pub fn to_gauges(rollups: &[Rollup], period: RollupPeriod) -> Vec<Gauge> {
+    rollups
+        .iter()
+        .flat_map(|rollup| {
+            let date = rollup.start.to_string();
+            rollup.totals.iter().map(move |(name, value)| {
+                Gauge::new(format!("{}.{}", period.prefix(), name), *value, &date)
+            })
+        })
+        .collect()
+}
+
+/// Rolls a scope's days up and sends the newest complete periods to Datadog
+///
+/// At most `period.reported()` periods are sent.
+///
+/// # Arguments
+///
+/// * `metrics` - The scope's days, fetched or stored
+/// * `period` - The period to roll up into
+/// * `datadog` - Datadog sink fanning out to the configured destinations
+/// * `namespace` - The scope's namespace
+/// * `summary` - Summary of the scope, filled in with send counts
+///
+/// # Returns
+///
+/// * `Result<usize>` - Number of periods sent, or an error if the gauges
+///   could not be sent
+pub fn process_rollups(
+    metrics: &[CopilotMetrics],
+    period: RollupPeriod,
+    datadog: &DatadogSink,
+    namespace: &str,
+    summary: &mut ScopeSummary,
+) -> Result<usize> {
+    let mut rollups = rollups(metrics, period);
+    rollups.drain(..rollups.len().saturating_sub(period.reported()));
+    if rollups.is_empty() {
+        info!(
+            "No complete {} period in the metrics of {}",
+            period.prefix(),
+            namespace
+        );
+        return Ok(0);
+    }
+
+    let before = datadog.stats();
+    let sent = datadog.send_gauges(&to_gauges(&rollups, period), namespace);
+    summary.record_send(&before, &datadog.stats());
+    sent?;
+
+    Ok(rollups.len())
+}
@@ -0,0 +1,320 @@
+//! # Seat Utilization
+//!
+//! Copilot is billed per assigned seat, whether the seat is used or not. This
+//! module joins the enterprise's seat assignments with the seat holders' last
+//! activity and reports seats without any activity in the reporting window:
+//!
+//! - `seats.total`: Assigned seats
+//! - `seats.unused`: Seats whose holder had no Copilot activity in the window
+//! - `seats.unused_user`: One series per unused seat tagged `user:{login}`,
+//!   only when `REPORT_UNUSED_SEAT_USERS` is set, since it adds a tag value
+//!   per user
+//!
+//! With a configured monthly seat price (`COPILOT_SEAT_PRICE`) the spend is
+//! estimated as well:
+//!
+//! - `seats.estimated_spend`: Assigned seats × price
+//! - `seats.estimated_wasted_spend`: Unused seats × price
+//!
+//! With `REPORT_SEAT_ACTIVITY` set, the seat holders' last activity is
+//! reported as a histogram instead of per user, so managers can see how many
+//! people need enablement without learning who they are:
+//!
+//! - `seats.by_last_activity`: Seats per `days_since_activity` bucket
+//!   (`0-7`, `8-14`, `15-30`, `31-90`, `91+` and `never`)
+//!
+//! With organization metrics enabled, every organization also reports its
+//! seat breakdown of the current billing cycle below `{namespace}.org.{slug}`:
+//!
+//! - `seats.total`: Seats billed in the cycle
+//! - `seats.added_this_cycle`: Seats added in the cycle
+//! - `seats.pending_cancellation`: Seats cancelled at the end of the cycle
+//! - `seats.pending_invitation`: Seats whose invitation isn't accepted yet
+
+use chrono::{DateTime, Duration, Utc};
+use tracing::{info, warn};
+
+use super::enterprise::with_org_fallback;
+use super::summary::ScopeSummary;
+use crate::error::{GhrustError, Result};
+use crate::models::billing::{CopilotSeat, SeatBreakdown};
+use crate::services::datadog::{DatadogSink, Gauge};
+use crate::services::github::{
+    get_enterprise_seats, get_org_seats, GitHubClient, REPORTING_WINDOW_DAYS,
+};
+
+/// Upper bounds (in days, inclusive) and labels of the activity buckets
+pub const ACTIVITY_BUCKETS: &[(i64, &str)] =
+    &[(7, "0-7"), (14, "8-14"), (30, "15-30"), (90, "31-90")];
+
+/// Label of seats last used longer ago than the last bucket
+pub const ACTIVITY_BUCKET_OLDER: &str = "91+";
+
+/// Label of seats that were never used
+pub const ACTIVITY_BUCKET_NEVER: &str = "never";
+
+/// Which seat series are reported besides the seat totals
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct SeatReporting {
+    /// Whether a series per unused seat is added
+    pub list_users: bool,
+    /// Monthly price of a seat, if spend is estimated
+    pub seat_price: Option<f64>,
+    /// Whether seats are counted per days-since-last-activity bucket
+    pub activity_histogram: bool,
+}
+
+/// Number of seats per days-since-last-activity bucket
+#[derive(Debug, Clone, PartialEq)]
+pub struct ActivityHistogram {
+    /// Bucket labels and seat counts, in the order of `ACTIVITY_BUCKETS`
+    /// followed by the older and never-used buckets
+    pub buckets: Vec<(&'static str, usize)>,
+}
+
+impl ActivityHistogram {
+    /// Counts seats by the days since their holder's last activity
+    ///
+    /// # Arguments
+    ///
+    /// * `seats` - The seat assignments
+    /// * `now` - Time the days are counted up to
+    pub fn from_seats(seats: &[CopilotSeat], now: DateTime<Utc>) -> Self {
+        let mut buckets: Vec<(&'static str, usize)> = ACTIVITY_BUCKETS
+            .iter()
+            .map(|(_, label)| (*label, 0))
+            .chain([(ACTIVITY_BUCKET_OLDER, 0), (ACTIVITY_BUCKET_NEVER, 0)])
+            .collect();
+
+        for seat in seats {
+            let index = match seat.last_activity_at {
+                None => buckets.len() - 1,
+                Some(at) => {
+                    let days = (now - at).num_days();
+                    ACTIVITY_BUCKETS
+                        .iter()
+                        .position(|(max, _)| days <= *max)
+                        .unwrap_or(ACTIVITY_BUCKETS.len())
+                }
+            };
+            buckets[index].1 += 1;
+        }
+
+        Self { buckets }
+    }
+
+    /// Converts the histogram to one `seats.by_last_activity` gauge per
+    /// bucket, tagged `days_since_activity:{bucket}`
+    ///
+    /// Empty buckets are sent as zero so dashboards don't keep stale values.
+    ///
+    /// # Arguments
+    ///
+    /// * `date` - Date (YYYY-MM-DD) the gauges are tagged with
+    pub fn to_gauges(&self, date: &str) -> Vec<Gauge> {
+        self.buckets
+            .iter()
+            .map(|(label, count)| {
+                Gauge::new("seats.by_last_activity", *count as f64, date)
+                    .with_tags(vec![format!("days_since_activity:{}", label)])
+            })
+            .collect()
+    }
+}
+
+/// Assigned and unused seats of an enterprise
+#[derive(Debug, Clone, PartialEq)]
+pub struct SeatUsage {
+    /// Number of assigned seats
+    pub total: usize,
+    /// Logins of seat holders without activity in the window, sorted
+    pub unused: Vec<String>,
+}
+
+impl SeatUsage {
+    /// Classifies seat assignments by their holders' last activity
+    ///
+    /// # Arguments
+    ///
+    /// * `seats` - The seat assignments
+    /// * `since` - Start of the reporting window; seats last used before it
+    ///   (or never) are unused
+    pub fn from_seats(seats: &[CopilotSeat], since: DateTime<Utc>) -> Self {
+        let mut unused: Vec<String> = seats
+            .iter()
+            .filter(|seat| seat.last_activity_at.is_none_or(|at| at < since))
+            .map(|seat| seat.assignee.login.clone())
+            .collect();
+        unused.sort();
+
+        Self {
+            total: seats.len(),
+            unused,
+        }
+    }
+
+    /// Converts the usage to gauges below `seats.*`
+    ///
+    /// # Arguments
+    ///
+    /// * `date` - Date (YYYY-MM-DD) the gauges are tagged with
+    /// * `list_users` - Whether a series per unused seat is added
+    /// * `seat_price` - Monthly price of a seat, if spend is estimated
+    pub fn to_gauges(&self, date: &str, list_users: bool, seat_price: Option<f64>) -> Vec<Gauge> {
+        let mut gauges = vec![
+            Gauge::new("seats.total", self.total as f64, date),
+            Gauge::new("seats.unused", self.unused.len() as f64, date),
+        ];
+        if let Some(price) = seat_price {
+            gauges.push(Gauge::new(
+                "seats.estimated_spend",
+                self.total as f64 * price,
+                date,
+            ));
+            gauges.push(Gauge::new(
+                "seats.estimated_wasted_spend",
+                self.unused.len() as f64 * price,
+                date,
+            ));
+        }
+        if list_users {
+            gauges.extend(self.unused.iter().map(|login| {
+                Gauge::new("seats.unused_user", 1.0, date)
+                    .with_tags(vec![format!("user:{}", login)])
+            }));
+        }
+        gauges
+    }
+}
+
+/// Converts a seat breakdown to gauges below `seats.*`
+///
+/// # Arguments
+///
+/// * `breakdown` - The organization's seat breakdown
+/// * `date` - Date (YYYY-MM-DD) the gauges are tagged with
+pub fn breakdown_gauges(breakdown: &SeatBreakdown, date: &str) -> Vec<Gauge> {
+    vec![
+        Gauge::new("seats.total", breakdown.total as f64, date),
+        Gauge::new(
+            "seats.added_this_cycle",
+            breakdown.added_this_cycle as f64,
+            date,
+        ),
+        Gauge::new(
+            "seats.pending_cancellation",
+            breakdown.pending_cancellation as f64,
+            date,
+        ),
+        Gauge::new(
+            "seats.pending_invitation",
+            breakdown.pending_invitation as f64,
+            date,
+        ),
+    ]
+}
+
+/// Sends an organization's seat breakdown to Datadog
+///
+/// Failures are logged but don't fail the organization, since its metrics
+/// are useful without the breakdown (the billing endpoint needs the
+/// `manage_billing:copilot` scope, which the metrics endpoint doesn't).
+///
+/// # Arguments
+///
+/// * `client` - GitHub client used for the billing request
+/// * `org` - Login of the organization
+/// * `datadog` - Datadog sink fanning out to the configured destinations
+/// * `namespace` - The organization's namespace
+/// * `tags` - Tags added to every gauge, see `org_tags`
+/// * `summary` - Summary of the scope, filled in with send counts
+pub fn send_seat_breakdown(
+    client: &GitHubClient,
+    org: &str,
+    datadog: &DatadogSink,
+    namespace: &str,
+    tags: &[String],
+    summary: &mut ScopeSummary,
+) {
+    let billing = match client.fetch_org_billing(org) {
+        Ok(billing) => billing,
+        Err(e) => {
+            warn!("Skipping seat breakdown for {}: {}", org, e);
+            return;
+        }
+    };
+
+    let date = Utc::now().format("%Y-%m-%d").to_string();
+    let gauges: Vec<_> = breakdown_gauges(&billing.seat_breakdown, &date)
+        .into_iter()
+        .map(|gauge| gauge.with_tags(tags.to_vec()))
+        .collect();
+    let before = datadog.stats();
+    let sent = datadog.send_gauges(&gauges, namespace);
+    summary.record_send(&before, &datadog.stats());
+    if let Err(e) = sent {
+        warn!("Failed to send seat breakdown for {}: {}", org, e);
+    }
+}
+
+/// Process and send the enterprise's seat utilization to Datadog
+///
+/// An enterprise the API doesn't know is read as an organization (see
+/// `with_org_fallback`).
+///
+/// # Arguments
+///
+/// * `github_client` - GitHub client the metrics are fetched with
+///   (requires access to the enterprise's Copilot billing data)
+/// * `enterprise_id` - ID of the GitHub Enterprise organization
+/// * `datadog` - Datadog sink fanning out to the configured destinations
+/// * `datadog_namespace` - Namespace prefix for metrics in Datadog (e.g., "github.copilot")
+/// * `reporting` - Which series are reported besides the seat totals
+/// * `summary` - Summary of the scope, filled in with send counts
+///
+/// # Returns
+///
+/// * `Result<()>` - Ok(()) if processing was successful, or an error with details
+pub fn process_seat_usage(
+    github_client: &GitHubClient,
+    enterprise_id: &str,
+    datadog: &DatadogSink,
+    datadog_namespace: &str,
+    reporting: SeatReporting,
+    summary: &mut ScopeSummary,
+) -> Result<()> {
+    info!("Starting seat utilization processing for {}", enterprise_id);
+
+    let (seats, tags) = with_org_fallback(
+        github_client,
+        enterprise_id,
+        || get_enterprise_seats(github_client, enterprise_id),
+        || get_org_seats(github_client, enterprise_id),
+    )
+    .map_err(GhrustError::github("Failed to fetch Copilot seats"))?;
+
+    let now = Utc::now();
+    let usage = SeatUsage::from_seats(&seats, now - Duration::days(REPORTING_WINDOW_DAYS));
+    info!(
+        "{} of {} Copilot seats of {} had no activity in the last {} days",
+        usage.unused.len(),
+        usage.total,
+        enterprise_id,
+        REPORTING_WINDOW_DAYS
+    );
+
+    let date = now.format("%Y-%m-%d").to_string();
+    let before = datadog.stats();
+    let mut gauges = usage.to_gauges(&date, reporting.list_users, reporting.seat_price);
+    if reporting.activity_histogram {
+        gauges.extend(ActivityHistogram::from_seats(&seats, now).to_gauges(&date));
+    }
+    for gauge in &mut gauges {
+        gauge.tags.extend(tags.iter().cloned());
+    }
+    let sent = datadog.send_gauges(&gauges, datadog_namespace);
+    summary.record_send(&before, &datadog.stats());
+    sent?;
+
+    Ok(())
+}
@@ -0,0 +1,297 @@
+//! # Run Summary
+//!
+//! Collects one structured record per processed scope (an enterprise, its
+//! premium request usage or a team) so the outcome of a run can be logged once
+//! and returned in the Lambda response, instead of being pieced together from
+//! scattered log lines.
+
+use std::time::Instant;
+
+use serde::Serialize;
+use tracing::info;
+
+use crate::error::Result;
+use crate::services::datadog::{redact::salted_hash, Gauge, SendStats};
+use crate::services::github::RateLimitBudget;
+
+/// Outcome of processing a single scope
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct ScopeSummary {
+    /// Scope identifier, e.g. "enterprise:acme" or "team:acme/platform"
+    pub scope: String,
+    /// Namespace the scope's metrics were sent to
+    pub namespace: String,
+    /// Number of days of metrics fetched from GitHub
+    pub dates_fetched: usize,
+    /// Time spent fetching the scope's metrics from GitHub in milliseconds
+    pub fetch_ms: u64,
+    /// Series prepared for Datadog (summed over destinations)
+    pub series_prepared: usize,
+    /// Series accepted by Datadog (summed over destinations)
+    pub series_sent: usize,
+    /// Chunks accepted by Datadog (summed over destinations)
+    pub chunks_sent: usize,
+    /// Chunks that could not be delivered, even after retrying them
+    pub chunks_failed: usize,
+    /// Series in the chunks that could not be delivered
+    pub series_failed: usize,
+    /// Wall-clock duration of the scope in milliseconds
+    pub duration_ms: u64,
+    /// Error message if the scope failed
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    /// Kind of the error if the scope failed, e.g. "github_rate_limit"
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error_kind: Option<&'static str>,
+    /// Whether the failed scope may succeed when retried
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub retryable: bool,
+    /// Why the scope was skipped without failing, e.g. because GitHub was
+    /// still generating its data
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub skipped: Option<String>,
+    /// Whether GitHub reported the scope's metrics as unchanged since the
+    /// last run (`304 Not Modified`), so they weren't sent again
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub not_modified: bool,
+}
+
+impl ScopeSummary {
+    /// Creates an empty summary for a scope
+    ///
+    /// # Arguments
+    ///
+    /// * `scope` - Scope identifier, e.g. "enterprise:acme"
+    /// * `namespace` - Namespace the scope's metrics are sent to
+    pub fn new(scope: impl Into<String>, namespace: impl Into<String>) -> Self {
+        Self {
+            scope: scope.into(),
+            namespace: namespace.into(),
+            ..Self::default()
+        }
+    }
+
+    /// Records the Datadog counters accumulated by a send
+    ///
+    /// # Arguments
+    ///
+    /// * `before` - Sink counters taken before the send
+    /// * `after` - Sink counters taken after the send
+    pub fn record_send(&mut self, before: &SendStats, after: &SendStats) {
+        let delta = after.since(before);
+        self.series_prepared += delta.series_prepared;
+        self.series_sent += delta.series_sent;
+        self.chunks_sent += delta.chunks_sent;
+        self.chunks_failed += delta.chunks_failed;
+        self.series_failed += delta.series_failed;
+    }
+
+    /// Records the time spent fetching the scope's metrics from GitHub
+    ///
+    /// # Arguments
+    ///
+    /// * `started` - When the fetch started
+    pub fn record_fetch(&mut self, started: Instant) {
+        self.fetch_ms += started.elapsed().as_millis() as u64;
+    }
+}
+
+/// Summaries of every scope processed in a run
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct RunSummary {
+    /// Scope summaries in processing order
+    pub scopes: Vec<ScopeSummary>,
+    /// GitHub requests made by the run and the rate limit left after it
+    pub github: RateLimitBudget,
+    /// Wall-clock duration of the run in milliseconds
+    pub duration_ms: u64,
+    /// Datadog counters of the whole run, including batched sends
+    pub datadog: SendStats,
+}
+
+impl RunSummary {
+    /// Creates an empty run summary
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds the summary of a part of the run processed on its own
+    ///
+    /// Scopes, GitHub requests, durations and Datadog counters are summed; the
+    /// rate limit is the one reported last.
+    ///
+    /// # Arguments
+    ///
+    /// * `other` - Summary of the part
+    pub fn merge(&mut self, other: RunSummary) {
+        self.scopes.extend(other.scopes);
+        self.github.requests += other.github.requests;
+        if other.github.remaining.is_some() {
+            self.github.remaining = other.github.remaining;
+            self.github.limit = other.github.limit;
+            self.github.reset = other.github.reset;
+        }
+        self.duration_ms += other.duration_ms;
+        self.datadog.add(&other.datadog);
+    }
+
+    /// Runs `process` for a scope and records its summary
+    ///
+    /// The duration and any error (with its kind) are filled in once
+    /// `process` returns. A scope GitHub is still generating
+    /// the data of is recorded as skipped rather than failed, and `process`
+    /// is treated as having returned `T::default()`.
+    ///
+    /// # Arguments
+    ///
+    /// * `scope` - Scope identifier, e.g. "enterprise:acme"
+    /// * `namespace` - Namespace the scope's metrics are sent to
+    /// * `process` - Processes the scope, filling in its summary
+    ///
+    /// # Returns
+    ///
+    /// * `Result<T>` - The result of `process`, or `T::default()` if the
+    ///   scope was skipped
+    pub fn track<T, F>(
+        &mut self,
+        scope: impl Into<String>,
+        namespace: &str,
+        process: F,
+    ) -> Result<T>
+    where
+        T: Default,
+        F: FnOnce(&mut ScopeSummary) -> Result<T>,
+    {
+        let started = Instant::now();
+        let mut summary = ScopeSummary::new(scope, namespace);
+
+        let result = match process(&mut summary) {
+            Err(e) if e.is_pending() => {
+                info!("Skipping {}: {}", summary.scope, e);
+                summary.skipped = Some(e.to_string());
+                Ok(T::default())
+            }
+            result => result,
+        };
+
+        summary.duration_ms = started.elapsed().as_millis() as u64;
+        if let Err(e) = &result {
+            summary.error = Some(e.to_string());
+            summary.error_kind = Some(e.kind());
+            summary.retryable = e.is_retryable();
+        }
+        self.scopes.push(summary);
+
+        result
+    }
+
+    /// Returns the runtime of the run as collector gauges
+    ///
+    /// The run duration, prepared series and chunks, plus the fetch time and
+    /// duration of every scope tagged with `scope:<scope>`, for sizing the
+    /// Lambda memory and timeout. In privacy mode the slug of a team scope is
+    /// replaced with its salted hash, as in the team's namespace.
+    ///
+    /// # Arguments
+    ///
+    /// * `date` - Date (YYYY-MM-DD) the gauges apply to
+    /// * `team_salt` - Salt team slugs are hashed with, if `TEAM_HASH_SALT`
+    ///   is set
+    pub fn runtime_gauges(&self, date: &str, team_salt: Option<&str>) -> Vec<Gauge> {
+        let mut gauges = vec![
+            Gauge::new("collector.run.duration_ms", self.duration_ms as f64, date),
+            Gauge::new(
+                "collector.run.series_prepared",
+                self.datadog.series_prepared as f64,
+                date,
+            ),
+            Gauge::new(
+                "collector.run.chunks_sent",
+                self.datadog.chunks_sent as f64,
+                date,
+            ),
+            Gauge::new("collector.run.scopes", self.scopes.len() as f64, date),
+        ];
+        for scope in &self.scopes {
+            let tags = vec![format!("scope:{}", scope_tag(&scope.scope, team_salt))];
+            gauges.push(
+                Gauge::new("collector.scope.fetch_ms", scope.fetch_ms as f64, date)
+                    .with_tags(tags.clone()),
+            );
+            gauges.push(
+                Gauge::new(
+                    "collector.scope.duration_ms",
+                    scope.duration_ms as f64,
+                    date,
+                )
+                .with_tags(tags),
+            );
+        }
+        gauges
+    }
+
+    /// Returns the namespaces that received series from a successful scope
+    ///
+    /// # Arguments
+    ///
+    /// * `batch_failed` - Whether the batched send of the run failed, in
+    ///   which case no scope's series reached Datadog
+    ///
+    /// # Returns
+    ///
+    /// * `Vec<&str>` - The namespaces, sorted and without duplicates
+    pub fn succeeded_namespaces(&self, batch_failed: bool) -> Vec<&str> {
+        if batch_failed {
+            return Vec::new();
+        }
+        let mut namespaces: Vec<&str> = self
+            .scopes
+            .iter()
+            .filter(|s| s.error.is_none() && s.series_prepared > 0)
+            .map(|s| s.namespace.as_str())
+            .collect();
+        namespaces.sort_unstable();
+        namespaces.dedup();
+        namespaces
+    }
+
+    /// Logs the summary as a single structured line
+    pub fn log(&self) {
+        let failed = self.scopes.iter().filter(|s| s.error.is_some()).count();
+        info!(
+            "Run summary ({} scopes, {} failed, {} ms, {} chunks sent, {} GitHub requests, rate limit remaining {}): {}",
+            self.scopes.len(),
+            failed,
+            self.duration_ms,
+            self.datadog.chunks_sent,
+            self.github.requests,
+            self.github
+                .remaining
+                .map_or_else(|| "unknown".to_string(), |r| r.to_string()),
+            serde_json::to_string(&self.scopes).unwrap_or_default()
+        );
+    }
+}
+
+/// Returns the value of the `scope` tag of a scope
+///
+/// # Arguments
+///
+/// * `scope` - Scope identifier, e.g. "team:acme/platform"
+/// * `team_salt` - Salt team slugs are hashed with, if any
+///
+/// # Returns
+///
+/// * `String` - The identifier, with the slug of a team scope hashed when a
+///   salt is given
+fn scope_tag(scope: &str, team_salt: Option<&str>) -> String {
+    let team = scope
+        .strip_prefix("team:")
+        .and_then(|team| team.split_once('/'));
+    match (team, team_salt) {
+        (Some((enterprise_id, slug)), Some(salt)) => {
+            format!("team:{}/{}", enterprise_id, salted_hash(salt, slug))
+        }
+        _ => scope.to_string(),
+    }
+}
@@ -6,16 +6,36 @@
 //! - Fetch team-specific metrics from the GitHub API
 //! - Process the metrics data
 //! - Send the metrics to Datadog with team-specific namespace
+//! - Apply per-team namespace and tag overrides from the configuration
+//! - Sanitize team slugs so they form valid Datadog metric names
+//! - Report adoption rates relative to the team size for teams mapped to
+//!   their organization
+//! - Roll the metrics of all teams up into `{namespace}.teams.all`
+//! - Aggregate teams into their configured parent teams (e.g. squads into
+//!   tribes) without fetching the parents
 //!
 //! The module is designed to work with both individual teams and multiple teams
 //! in batch processing scenarios.
 
-use anyhow::{anyhow, Result};
-use tracing::{debug, info};
+use std::collections::HashMap;
+use std::time::Instant;
 
+use tracing::{debug, error, info};
+
+use super::adoption::{send_adoption, Members};
+use super::enterprise::{fallback_tags, fetch_team_with_org_fallback};
+use super::persistence::Persistence;
+use super::summary::{RunSummary, ScopeSummary};
+use crate::config::TeamMapping;
+use crate::error::{GhrustError, Result};
+use crate::models::aggregate::sum_by_date;
+use crate::models::github::CopilotMetrics;
 use crate::services::{
-    datadog::DatadogClient,
-    github::{get_team_metrics, GitHubClient},
+    datadog::{
+        names::{self, NamespaceTemplate},
+        DatadogSink, TagContext,
+    },
+    github::{calculate_default_since_date, GitHubClient, GitHubError},
 };
 
 /// Process team-specific metrics and send to Datadog
@@ -23,17 +43,30 @@ use crate::services::{
 /// This function fetches GitHub Copilot metrics for a specific team within an enterprise,
 /// processes the data, and sends the metrics to Datadog with a team-specific namespace.
 ///
+/// A team of an enterprise the API doesn't know is fetched from the team
+/// endpoint of the organization with the enterprise's ID and tagged
+/// `endpoint:org`.
+///
+/// The namespace defaults to `{datadog_namespace}.team.{team_slug}`, with the
+/// slug sanitized by `sanitize_slug`, and follows the configured namespace
+/// template. A team
+/// mapping can replace it with a namespace of its own and add tags to every
+/// series of the team.
+///
 /// # Arguments
 ///
-/// * `github_token` - GitHub personal access token with appropriate permissions
+/// * `github_client` - GitHub client the metrics are fetched with
 /// * `enterprise_id` - ID of the GitHub Enterprise organization
 /// * `team_slug` - Slug identifier for the team (used in API paths and metrics namespacing)
-/// * `datadog_api_key` - Datadog API key for authentication
-/// * `datadog_namespace` - Base namespace prefix for Datadog metrics
+/// * `datadog` - Datadog sink fanning out to the configured destinations
+/// * `naming` - Base namespace, namespace template and team mappings
+/// * `persistence` - Where the fetched days are kept
+/// * `summary` - Summary of the scope, filled in with fetch and send counts
 ///
 /// # Returns
 ///
-/// * `Result<()>` - Ok(()) if successful, or an error if any step fails
+/// * `Result<Vec<CopilotMetrics>>` - The fetched days if successful, or an
+///   error if any step fails
 ///
 /// # Errors
 ///
@@ -41,35 +74,55 @@ use crate::services::{
 /// * Unable to fetch team metrics from GitHub
 /// * Sending metrics to Datadog fails
 pub fn process_team_metrics(
-    github_token: &str,
+    github_client: &GitHubClient,
     enterprise_id: &str,
     team_slug: &str,
-    datadog_api_key: &str,
-    datadog_namespace: &str,
-) -> Result<()> {
+    datadog: &DatadogSink,
+    naming: &TeamNaming,
+    persistence: &Persistence,
+    summary: &mut ScopeSummary,
+) -> Result<Vec<CopilotMetrics>> {
     info!(
         "Starting team metrics processing for {}/{}",
         enterprise_id, team_slug
     );
 
-    // Initialize clients
-    let github_client = GitHubClient::new(github_token);
-    let datadog_client = DatadogClient::new(datadog_api_key.to_string());
-
-    // Fetch team metrics from GitHub
-    let metrics = match get_team_metrics(&github_client, enterprise_id, team_slug) {
+    // Fetch the days of the window that aren't stored from GitHub
+    let since = calculate_default_since_date();
+    let started = Instant::now();
+    let mut fetched_tags = None;
+    let fetched = persistence
+        .store
+        .fetch_missing(&summary.scope, &since, |since| {
+            fetch_team_with_org_fallback(github_client, enterprise_id, team_slug, since).map(
+                |(metrics, fallback)| {
+                    fetched_tags = Some(fallback);
+                    metrics
+                },
+            )
+        });
+    summary.record_fetch(started);
+    let metrics = match fetched {
         Ok(metrics) => {
             if metrics.is_empty() {
                 debug!(
                     "No team metrics returned for {}/{}",
                     enterprise_id, team_slug
                 );
-                return Ok(());
+                return Ok(Vec::new());
             }
             metrics
         }
+        Err(GitHubError::NotModified(_)) => {
+            info!(
+                "Team metrics of {}/{} not modified since the last run, not sending them",
+                enterprise_id, team_slug
+            );
+            summary.not_modified = true;
+            return Ok(persistence.store.window(&summary.scope, &since));
+        }
         Err(e) => {
-            return Err(anyhow!("Failed to fetch team metrics: {}", e));
+            return Err(GhrustError::github("Failed to fetch team metrics")(e));
         }
     };
 
@@ -80,78 +133,500 @@ pub fn process_team_metrics(
         team_slug
     );
 
-    // Create team-specific namespace
-    let team_namespace = format!("{}.team.{}", datadog_namespace, team_slug);
+    summary.dates_fetched = metrics.len();
+    persistence.keep(&summary.scope, &metrics);
+
+    // Create team-specific namespace, unless the team is mapped elsewhere
+    let mapping = naming.mappings.get(team_slug);
+    let team_namespace = naming.namespace(team_slug);
+    let mut extra_tags = naming.tags(team_slug);
+    extra_tags.extend(fetched_tags.unwrap_or_else(|| fallback_tags(github_client, enterprise_id)));
 
     // Send metrics to Datadog with team-specific namespace
-    datadog_client.send_metrics(&metrics, &team_namespace)?;
+    let before = datadog.stats();
+    let sent = datadog.send_metrics_with_tags(&metrics, &team_namespace, &extra_tags);
+    summary.record_send(&before, &datadog.stats());
+    sent?;
+
+    // Report adoption relative to the team size if the owning org is known
+    if let Some(org) = mapping.and_then(|m| m.org.as_deref()) {
+        send_adoption(
+            github_client,
+            Members::Team { org, team_slug },
+            &metrics,
+            datadog,
+            &team_namespace,
+            &extra_tags,
+            summary,
+        );
+    }
 
     info!(
         "Team metrics processing completed for {}/{}",
         enterprise_id, team_slug
     );
+    Ok(metrics)
+}
+
+/// Sends the rollup of all teams' metrics to Datadog
+///
+/// The rollup sums the metrics of every successfully processed team per day
+/// and is sent to `{datadog_namespace}.teams.all`, so team coverage can be
+/// compared against the enterprise totals. Users in several teams are counted
+/// once per team.
+///
+/// # Arguments
+///
+/// * `enterprise_id` - ID of the GitHub Enterprise organization
+/// * `rollup` - The summed team metrics, one day per date
+/// * `datadog` - Datadog sink fanning out to the configured destinations
+/// * `datadog_namespace` - Base namespace prefix for Datadog metrics
+/// * `summary` - Summary of the scope, filled in with send counts
+///
+/// # Returns
+///
+/// * `Result<()>` - Ok(()) if successful, or an error if sending fails
+pub fn process_team_rollup(
+    enterprise_id: &str,
+    rollup: &[CopilotMetrics],
+    datadog: &DatadogSink,
+    datadog_namespace: &str,
+    summary: &mut ScopeSummary,
+) -> Result<()> {
+    if rollup.is_empty() {
+        debug!("No team metrics to roll up for {}", enterprise_id);
+        return Ok(());
+    }
+
+    info!(
+        "Sending rollup of {} days of team metrics for {}",
+        rollup.len(),
+        enterprise_id
+    );
+    summary.dates_fetched = rollup.len();
+
+    let before = datadog.stats();
+    let sent = datadog.send_metrics(rollup, &rollup_namespace(datadog_namespace));
+    summary.record_send(&before, &datadog.stats());
+    sent?;
+
     Ok(())
 }
 
+/// How the namespaces and tags of a run's teams are resolved
+#[derive(Debug, Clone, Copy)]
+pub struct TeamNaming<'a> {
+    /// Base namespace prefix for Datadog metrics
+    pub datadog_namespace: &'a str,
+    /// Template of the namespaces of teams without a mapping
+    pub template: &'a NamespaceTemplate,
+    /// Namespace and tag overrides keyed by team slug
+    pub mappings: &'a HashMap<String, TeamMapping>,
+}
+
+impl TeamNaming<'_> {
+    /// Returns the namespace of a team, see [`team_namespace`]
+    pub fn namespace(&self, team_slug: &str) -> String {
+        team_namespace(
+            self.datadog_namespace,
+            self.template,
+            team_slug,
+            self.mappings.get(team_slug),
+        )
+    }
+
+    /// Returns the tags of a team, see [`team_tags`]
+    pub fn tags(&self, team_slug: &str) -> Vec<String> {
+        team_tags(self.template, team_slug, self.mappings.get(team_slug))
+    }
+}
+
+/// Number of teams processed successfully and unsuccessfully in a run
+#[derive(Debug, Clone, Default)]
+pub struct TeamOutcome {
+    /// Teams whose metrics were sent
+    pub succeeded: usize,
+    /// Teams that failed or were skipped because of a namespace collision
+    pub failed: usize,
+    /// Metrics of the successful teams summed per day, oldest first
+    pub rollup: Vec<CopilotMetrics>,
+    /// Fetched days of every successful team (not parent teams), by slug
+    pub teams: Vec<(String, Vec<CopilotMetrics>)>,
+}
+
+impl TeamOutcome {
+    /// Total number of teams attempted
+    pub fn total(&self) -> usize {
+        self.succeeded + self.failed
+    }
+
+    /// Returns whether the share of failed teams is above the threshold
+    ///
+    /// # Arguments
+    ///
+    /// * `threshold` - Share of teams (0.0 to 1.0) allowed to fail
+    pub fn exceeds(&self, threshold: f64) -> bool {
+        self.failed > 0 && self.failed as f64 / self.total() as f64 > threshold
+    }
+}
+
 /// Process metrics for multiple teams
 ///
 /// This function iterates through a list of team slugs and processes metrics for each team.
-/// It tracks the success and failure count, and returns an error if any team processing fails.
+/// Failing teams are logged and counted, but don't stop the remaining teams.
 ///
 /// # Arguments
 ///
-/// * `github_token` - GitHub personal access token with appropriate permissions
+/// * `github_client` - GitHub client the metrics are fetched with
 /// * `enterprise_id` - ID of the GitHub Enterprise organization
 /// * `team_slugs` - Array of team slug identifiers to process
-/// * `datadog_api_key` - Datadog API key for authentication
-/// * `datadog_namespace` - Base namespace prefix for Datadog metrics
+/// * `datadog` - Datadog sink fanning out to the configured destinations
+/// * `naming` - Base namespace, namespace template and team mappings
+/// * `persistence` - Where the fetched days are kept
+/// * `run` - Run summary receiving one scope summary per team
 ///
 /// # Returns
 ///
-/// * `Result<()>` - Ok(()) if all teams processed successfully, or an error if any team fails
-///
-/// # Errors
-///
-/// Returns an error if one or more teams could not be processed successfully,
-/// including the count of failed teams in the error message.
+/// * `TeamOutcome` - The number of teams that succeeded and failed, the
+///   rollup of the successful teams' metrics and their fetched days
 pub fn process_all_teams(
-    github_token: &str,
+    github_client: &GitHubClient,
     enterprise_id: &str,
     team_slugs: &[String],
-    datadog_api_key: &str,
-    datadog_namespace: &str,
-) -> Result<()> {
+    datadog: &DatadogSink,
+    naming: &TeamNaming,
+    persistence: &Persistence,
+    run: &mut RunSummary,
+) -> TeamOutcome {
+    let team_mappings = naming.mappings;
     info!("Processing metrics for {} teams", team_slugs.len());
 
-    let mut success_count = 0;
-    let mut error_count = 0;
+    let mut outcome = TeamOutcome::default();
+    let mut team_metrics = Vec::new();
+
+    // Parent teams that are not processed themselves get the sum of their
+    // descendants, so they take part in the namespace collision check
+    let parents = parent_teams(team_slugs, team_mappings);
+    let all_slugs: Vec<String> = team_slugs.iter().chain(&parents).cloned().collect();
+
+    // Teams whose namespaces collide would overwrite each other's series in
+    // Datadog, so they are skipped and reported as failures instead
+    let collisions = find_namespace_collisions(
+        naming.datadog_namespace,
+        naming.template,
+        &all_slugs,
+        team_mappings,
+    );
+    for (namespace, slugs) in &collisions {
+        error!(
+            "Teams {} all map to namespace {}; skipping them. Add team_mappings to disambiguate",
+            slugs.join(", "),
+            namespace
+        );
+    }
 
     for team_slug in team_slugs {
-        match process_team_metrics(
-            github_token,
-            enterprise_id,
-            team_slug,
-            datadog_api_key,
-            datadog_namespace,
-        ) {
-            Ok(_) => {
-                success_count += 1;
+        let namespace = naming.namespace(team_slug);
+        let scope = format!("team:{}/{}", enterprise_id, team_slug);
+        let datadog = &datadog.scoped(TagContext::team(enterprise_id, team_slug));
+
+        let result = run.track(scope, &namespace, |summary| {
+            if collisions.contains_key(&namespace) {
+                return Err(GhrustError::NamespaceCollision(namespace.clone()));
+            }
+            process_team_metrics(
+                github_client,
+                enterprise_id,
+                team_slug,
+                datadog,
+                naming,
+                persistence,
+                summary,
+            )
+        });
+
+        match result {
+            Ok(metrics) => {
+                outcome.succeeded += 1;
+                team_metrics.extend(metrics.iter().cloned());
+                outcome.teams.push((team_slug.clone(), metrics));
             }
             Err(e) => {
-                error_count += 1;
-                debug!("Error processing team {}: {}", team_slug, e);
+                outcome.failed += 1;
+                error!("Error processing team {}: {}", team_slug, e);
+            }
+        }
+    }
+
+    // Aggregate the fetched teams into their parent teams
+    for parent in &parents {
+        let descendants: Vec<CopilotMetrics> = outcome
+            .teams
+            .iter()
+            .filter(|(slug, _)| ancestors(slug, team_mappings).contains(parent))
+            .flat_map(|(_, metrics)| metrics.iter().cloned())
+            .collect();
+        if descendants.is_empty() {
+            debug!("No fetched descendants for parent team {}", parent);
+            continue;
+        }
+
+        let namespace = naming.namespace(parent);
+        let scope = format!("team:{}/{}", enterprise_id, parent);
+        let datadog = &datadog.scoped(TagContext::team(enterprise_id, parent));
+
+        let result = run.track(scope, &namespace, |summary| {
+            if collisions.contains_key(&namespace) {
+                return Err(GhrustError::NamespaceCollision(namespace.clone()));
+            }
+            let tags = naming.tags(parent);
+            send_parent_team_metrics(&namespace, descendants, datadog, &tags, summary)
+        });
+
+        match result {
+            Ok(_) => outcome.succeeded += 1,
+            Err(e) => {
+                outcome.failed += 1;
+                error!("Error processing parent team {}: {}", parent, e);
             }
         }
     }
 
     info!(
         "Team metrics processing completed. Successful: {}, Failed: {}",
-        success_count, error_count
+        outcome.succeeded, outcome.failed
+    );
+
+    outcome.rollup = sum_by_date(team_metrics);
+    outcome
+}
+
+/// Sends the aggregated metrics of a parent team to Datadog
+///
+/// # Arguments
+///
+/// * `namespace` - The parent team's namespace
+/// * `descendants` - Fetched days of every descendant team
+/// * `datadog` - Datadog sink fanning out to the configured destinations
+/// * `tags` - Tags of the parent team, see `team_tags`
+/// * `summary` - Summary of the scope, filled in with send counts
+fn send_parent_team_metrics(
+    namespace: &str,
+    descendants: Vec<CopilotMetrics>,
+    datadog: &DatadogSink,
+    tags: &[String],
+    summary: &mut ScopeSummary,
+) -> Result<()> {
+    let aggregate = sum_by_date(descendants);
+    info!(
+        "Sending {} days of aggregated metrics to {}",
+        aggregate.len(),
+        namespace
     );
+    summary.dates_fetched = aggregate.len();
 
-    if error_count > 0 {
-        Err(anyhow!("Failed to process {} teams", error_count))
+    let before = datadog.stats();
+    let sent = datadog.send_metrics_with_tags(&aggregate, namespace, tags);
+    summary.record_send(&before, &datadog.stats());
+    sent?;
+
+    Ok(())
+}
+
+/// Returns the parent teams that are aggregated from their descendants
+///
+/// Every ancestor of a processed team that is not itself in `team_slugs` is
+/// returned. Parents that are processed directly report their own metrics
+/// instead of an aggregate.
+///
+/// # Arguments
+///
+/// * `team_slugs` - The teams being processed
+/// * `team_mappings` - Mappings holding each team's `parent`
+///
+/// # Returns
+///
+/// * `Vec<String>` - The parent slugs, sorted
+pub fn parent_teams(
+    team_slugs: &[String],
+    team_mappings: &HashMap<String, TeamMapping>,
+) -> Vec<String> {
+    let mut parents: Vec<String> = team_slugs
+        .iter()
+        .flat_map(|slug| ancestors(slug, team_mappings))
+        .filter(|parent| !team_slugs.contains(parent))
+        .collect();
+    parents.sort();
+    parents.dedup();
+    parents
+}
+
+/// Returns the chain of parents of a team, nearest first
+///
+/// The configuration rejects cyclic hierarchies; the chain stops at a repeated
+/// team regardless.
+fn ancestors(slug: &str, team_mappings: &HashMap<String, TeamMapping>) -> Vec<String> {
+    let mut chain: Vec<String> = Vec::new();
+    let mut current = slug;
+    while let Some(parent) = team_mappings.get(current).and_then(|m| m.parent.as_deref()) {
+        if parent == slug || chain.iter().any(|p| p == parent) {
+            break;
+        }
+        chain.push(parent.to_string());
+        current = parent;
+    }
+    chain
+}
+
+/// Returns the Datadog namespace of the rollup of all teams
+///
+/// # Arguments
+///
+/// * `datadog_namespace` - Base namespace prefix for Datadog metrics
+pub fn rollup_namespace(datadog_namespace: &str) -> String {
+    format!("{}.teams.all", datadog_namespace)
+}
+
+/// Returns the Datadog namespace for a team's metrics
+///
+/// # Arguments
+///
+/// * `datadog_namespace` - Base namespace prefix for Datadog metrics
+/// * `template` - Template of the namespaces of teams without a mapping
+/// * `team_slug` - Slug identifier for the team
+/// * `mapping` - Namespace and tag overrides for the team, if configured
+///
+/// # Returns
+///
+/// * `String` - The mapped namespace, or the namespace template (by default
+///   `{datadog_namespace}.team.{slug}`) applied to the sanitized slug
+pub fn team_namespace(
+    datadog_namespace: &str,
+    template: &NamespaceTemplate,
+    team_slug: &str,
+    mapping: Option<&TeamMapping>,
+) -> String {
+    match mapping.and_then(|m| m.namespace.as_deref()) {
+        Some(namespace) => namespace.to_string(),
+        None => template.render(datadog_namespace, "team", &sanitize_slug(team_slug)),
+    }
+}
+
+/// Returns the tags added to every series of a team
+///
+/// # Arguments
+///
+/// * `template` - Template of the namespaces of teams without a mapping
+/// * `team_slug` - Slug identifier for the team
+/// * `mapping` - Namespace and tag overrides for the team, if configured
+///
+/// # Returns
+///
+/// * `Vec<String>` - The mapped tags, followed by `team:{slug}` if the team's
+///   namespace comes from a template without the slug
+pub fn team_tags(
+    template: &NamespaceTemplate,
+    team_slug: &str,
+    mapping: Option<&TeamMapping>,
+) -> Vec<String> {
+    let mut tags = mapping.map(|m| m.tags.clone()).unwrap_or_default();
+    if mapping.and_then(|m| m.namespace.as_ref()).is_none() {
+        tags.extend(template.slug_tag("team", team_slug));
+    }
+    tags
+}
+
+/// Normalizes a team slug for use as a single Datadog metric name segment
+///
+/// Slugs made of ASCII alphanumerics, hyphens and underscores (every slug
+/// GitHub generates) are kept unchanged, so existing namespaces aren't
+/// renamed; Datadog already reports their hyphens as underscores. Any other
+/// slug would break the namespace into several segments or be rejected, so it
+/// is lowercased, every other character (including periods and hyphens) is
+/// replaced with an underscore, repeated underscores are collapsed and
+/// leading/trailing ones are trimmed. A slug with no usable characters
+/// becomes `unknown`.
+///
+/// # Arguments
+///
+/// * `team_slug` - Slug identifier for the team
+///
+/// # Returns
+///
+/// * `String` - The slug, sanitized if necessary, e.g. `Web.Platform` becomes
+///   `web_platform` while `data-science` is kept
+pub fn sanitize_slug(team_slug: &str) -> String {
+    let valid = team_slug.chars().any(|c| c.is_ascii_alphanumeric())
+        && team_slug
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_');
+    if valid {
+        return team_slug.to_string();
+    }
+
+    let mut sanitized = String::with_capacity(team_slug.len());
+
+    for c in team_slug.chars() {
+        if c.is_ascii_alphanumeric() {
+            sanitized.push(c.to_ascii_lowercase());
+        } else if !sanitized.is_empty() && !sanitized.ends_with('_') {
+            sanitized.push('_');
+        }
+    }
+
+    let sanitized = sanitized.trim_end_matches('_');
+    if sanitized.is_empty() {
+        "unknown".to_string()
     } else {
-        Ok(())
+        sanitized.to_string()
+    }
+}
+
+/// Finds teams whose resolved namespaces collide
+///
+/// Different slugs can end up as the same metric names in Datadog (e.g.
+/// `web.app` and `web-app`, both sent as `web_app`), and mappings can point
+/// several teams at one namespace. Namespaces are compared as normalized by
+/// `names::normalize`. Teams
+/// sharing a namespace but told apart by their tags (e.g. with a namespace
+/// template without the slug) don't collide.
+///
+/// # Arguments
+///
+/// * `datadog_namespace` - Base namespace prefix for Datadog metrics
+/// * `template` - Template of the namespaces of teams without a mapping
+/// * `team_slugs` - Array of team slug identifiers to process
+/// * `team_mappings` - Namespace and tag overrides keyed by team slug
+///
+/// # Returns
+///
+/// * `HashMap<String, Vec<String>>` - Colliding namespaces, normalized, mapped
+///   to the distinct slugs that resolve to them
+pub fn find_namespace_collisions(
+    datadog_namespace: &str,
+    template: &NamespaceTemplate,
+    team_slugs: &[String],
+    team_mappings: &HashMap<String, TeamMapping>,
+) -> HashMap<String, Vec<String>> {
+    let mut by_series: HashMap<(String, Vec<String>), Vec<String>> = HashMap::new();
+
+    for slug in team_slugs {
+        let mapping = team_mappings.get(slug);
+        let namespace =
+            names::normalize(&team_namespace(datadog_namespace, template, slug, mapping));
+        let mut tags = team_tags(template, slug, mapping);
+        tags.sort();
+        let slugs = by_series.entry((namespace, tags)).or_default();
+        if !slugs.contains(slug) {
+            slugs.push(slug.clone());
+        }
+    }
+
+    let mut by_namespace: HashMap<String, Vec<String>> = HashMap::new();
+    for ((namespace, _), slugs) in by_series {
+        if slugs.len() > 1 {
+            by_namespace.entry(namespace).or_default().extend(slugs);
+        }
     }
+    by_namespace
 }
@@ -0,0 +1,1166 @@
+//! # Processor Tests
+//!
+//! Tests for the pure helpers used by the processors, such as resolving and
+//! sanitizing team namespaces.
+
+use std::collections::HashMap;
+
+use chrono::NaiveDate;
+
+use super::adoption::Adoption;
+use super::alerts::{evaluate, process_alerts, AlertMetric, AlertRule};
+use super::anomaly::{score, to_gauges};
+use super::backfill::{backfill_scope, BackfillCheckpoint, BackfillOptions};
+use super::baseline::{comparison_gauges, weekly_aggregates, Baseline};
+use super::diff::PeriodComparison;
+use super::digest::WeeklyDigest;
+use super::distribution::{percentile, TeamDistribution};
+use super::handoff::{merge_windows, FetchedScope, MetricsBundle, BUNDLE_VERSION};
+use super::leaderboard::Leaderboard;
+use super::organization::org_namespace;
+use super::privacy::{hash_team_namespaces, team_hashes, update_mapping_file};
+use super::ranking::{RankBy, TeamRanking, TeamStanding};
+use super::report::MetricsReport;
+use super::rollups::{rollups, to_gauges as rollup_gauges, RollupPeriod};
+use super::seats::{breakdown_gauges, ActivityHistogram, SeatUsage};
+use super::summary::{RunSummary, ScopeSummary};
+use super::team::{
+    find_namespace_collisions, parent_teams, rollup_namespace, sanitize_slug, team_namespace,
+    TeamOutcome,
+};
+use crate::config::TeamMapping;
+use crate::error::GhrustError;
+use crate::models::aggregate::sum_by_date;
+use crate::models::billing::{CopilotBilling, CopilotSeat};
+use crate::services::datadog::names::NamespaceTemplate;
+use crate::services::datadog::redact::salted_hash;
+use crate::services::datadog::{SendStats, TagContext};
+use crate::services::github::{create_mock_metrics, GitHubError};
+use crate::services::notify::{Notification, NotificationSink, Notifier};
+
+#[test]
+fn test_sanitize_slug() {
+    assert_eq!(sanitize_slug("platform"), "platform");
+    // Valid slugs keep the namespaces they had before sanitization
+    assert_eq!(sanitize_slug("data-science"), "data-science");
+    assert_eq!(sanitize_slug("Platform_Ops"), "Platform_Ops");
+    assert_eq!(sanitize_slug("Web.Platform"), "web_platform");
+    assert_eq!(sanitize_slug("data-science--ml."), "data_science_ml");
+    assert_eq!(sanitize_slug("-team 42-"), "team_42");
+    assert_eq!(sanitize_slug("--"), "unknown");
+    assert_eq!(sanitize_slug("..."), "unknown");
+}
+
+#[test]
+fn test_team_namespace_uses_sanitized_slug_or_mapping() {
+    let template = NamespaceTemplate::default();
+    assert_eq!(
+        team_namespace("github.copilot", &template, "Web.App", None),
+        "github.copilot.team.web_app"
+    );
+
+    let mapping = TeamMapping {
+        namespace: Some("platform.copilot".to_string()),
+        ..TeamMapping::default()
+    };
+    assert_eq!(
+        team_namespace("github.copilot", &template, "Web.App", Some(&mapping)),
+        "platform.copilot"
+    );
+}
+
+#[test]
+fn test_org_namespace_uses_sanitized_login() {
+    assert_eq!(
+        org_namespace(
+            "github.copilot",
+            &NamespaceTemplate::default(),
+            "Acme Payments"
+        ),
+        "github.copilot.org.acme_payments"
+    );
+    assert_eq!(
+        org_namespace(
+            "github.copilot",
+            &NamespaceTemplate::default(),
+            "acme-payments"
+        ),
+        "github.copilot.org.acme-payments"
+    );
+}
+
+#[test]
+fn test_namespace_collisions_detected() {
+    let slugs = vec![
+        "web.app".to_string(),
+        "web-app".to_string(),
+        "platform".to_string(),
+        "platform".to_string(),
+    ];
+    let template = NamespaceTemplate::default();
+
+    let collisions = find_namespace_collisions("gh", &template, &slugs, &HashMap::new());
+    assert_eq!(collisions.len(), 1);
+    assert_eq!(
+        collisions["gh.team.web_app"],
+        vec!["web.app".to_string(), "web-app".to_string()]
+    );
+
+    // A mapping moves one of the teams out of the way
+    let mut mappings = HashMap::new();
+    mappings.insert(
+        "web.app".to_string(),
+        TeamMapping {
+            namespace: Some("gh.team.web_app_legacy".to_string()),
+            ..TeamMapping::default()
+        },
+    );
+    assert!(find_namespace_collisions("gh", &template, &slugs, &mappings).is_empty());
+
+    // Without the slug in the namespace, teams are told apart by their tags
+    let template = NamespaceTemplate::parse("{base}.{scope}").unwrap();
+    let slugs = vec!["web".to_string(), "api".to_string()];
+    assert!(find_namespace_collisions("gh", &template, &slugs, &HashMap::new()).is_empty());
+}
+
+#[test]
+fn test_team_outcome_threshold() {
+    let outcome = TeamOutcome {
+        succeeded: 3,
+        failed: 1,
+        ..TeamOutcome::default()
+    };
+    assert_eq!(outcome.total(), 4);
+    assert!(outcome.exceeds(0.0));
+    assert!(outcome.exceeds(0.2));
+    assert!(!outcome.exceeds(0.25));
+
+    assert!(!TeamOutcome {
+        succeeded: 2,
+        failed: 0,
+        ..TeamOutcome::default()
+    }
+    .exceeds(0.0));
+}
+
+#[test]
+fn test_run_summary_tracks_scopes() {
+    let mut run = RunSummary::new();
+
+    let ok = run.track("enterprise:acme", "gh", |summary| {
+        summary.dates_fetched = 28;
+        summary.record_send(
+            &SendStats::default(),
+            &SendStats {
+                series_prepared: 250,
+                series_sent: 200,
+                chunks_sent: 2,
+                chunks_failed: 1,
+                series_failed: 50,
+            },
+        );
+        Ok(())
+    });
+    assert!(ok.is_ok());
+
+    let failed: crate::error::Result<()> = run.track("team:acme/web", "gh.team.web", |_| {
+        Err(GhrustError::NamespaceCollision("gh.team.web".to_string()))
+    });
+    assert!(failed.is_err());
+
+    assert_eq!(run.scopes.len(), 2);
+    assert_eq!(run.scopes[0].dates_fetched, 28);
+    assert_eq!(run.scopes[0].series_prepared, 250);
+    assert_eq!(run.scopes[0].series_sent, 200);
+    assert_eq!(run.scopes[0].chunks_sent, 2);
+    assert_eq!(run.scopes[0].chunks_failed, 1);
+    assert_eq!(run.scopes[0].series_failed, 50);
+    assert_eq!(run.scopes[0].error, None);
+    assert_eq!(run.scopes[1].namespace, "gh.team.web");
+    assert_eq!(
+        run.scopes[1].error.as_deref(),
+        Some("Namespace gh.team.web is shared with other teams")
+    );
+    assert_eq!(run.scopes[1].error_kind, Some("namespace_collision"));
+    assert!(!run.scopes[1].retryable);
+}
+
+/// A scope GitHub is still generating the data of is skipped, not failed
+#[test]
+fn test_run_summary_skips_pending_scopes() {
+    let mut run = RunSummary::new();
+    let result: crate::error::Result<usize> = run.track("enterprise:acme", "gh", |_| {
+        Err(GhrustError::github("Failed to fetch enterprise metrics")(
+            GitHubError::NoDataYet("/enterprises/acme/copilot/metrics".to_string()),
+        ))
+    });
+
+    assert_eq!(result.unwrap(), 0);
+    assert_eq!(run.scopes[0].error, None);
+    assert!(run.scopes[0]
+        .skipped
+        .as_deref()
+        .unwrap()
+        .contains("/enterprises/acme/copilot/metrics"));
+    assert!(run.succeeded_namespaces(false).is_empty());
+}
+
+#[test]
+fn test_runtime_gauges() {
+    let mut run = RunSummary::new();
+    let _ = run.track("enterprise:acme", "gh", |summary| {
+        summary.record_fetch(std::time::Instant::now());
+        Ok(())
+    });
+    run.scopes[0].fetch_ms = 1200;
+    run.scopes[0].duration_ms = 1500;
+    run.duration_ms = 2000;
+    run.datadog = SendStats {
+        series_prepared: 250,
+        chunks_sent: 3,
+        ..SendStats::default()
+    };
+
+    let gauges = run.runtime_gauges("2025-03-02", None);
+    let value = |name: &str| {
+        gauges
+            .iter()
+            .find(|g| g.name == name)
+            .map(|g| (g.value, g.tags.clone()))
+    };
+    assert_eq!(value("collector.run.duration_ms"), Some((2000.0, vec![])));
+    assert_eq!(
+        value("collector.run.series_prepared"),
+        Some((250.0, vec![]))
+    );
+    assert_eq!(value("collector.run.chunks_sent"), Some((3.0, vec![])));
+    assert_eq!(value("collector.run.scopes"), Some((1.0, vec![])));
+    let scope_tags = vec!["scope:enterprise:acme".to_string()];
+    assert_eq!(
+        value("collector.scope.fetch_ms"),
+        Some((1200.0, scope_tags.clone()))
+    );
+    assert_eq!(
+        value("collector.scope.duration_ms"),
+        Some((1500.0, scope_tags))
+    );
+    assert!(gauges.iter().all(|g| g.date == "2025-03-02"));
+}
+
+/// In privacy mode no runtime gauge reveals the slug of a team
+#[test]
+fn test_runtime_gauges_hash_team_slugs() {
+    let mut run = RunSummary::new();
+    for scope in [
+        "enterprise:acme",
+        "team:acme/secret-squad",
+        "teams:acme/all",
+    ] {
+        let _ = run.track(scope, "gh", |_| Ok(()));
+    }
+
+    let gauges = run.runtime_gauges("2025-03-02", Some("pepper"));
+    for gauge in &gauges {
+        assert!(!gauge.name.contains("secret-squad"), "{}", gauge.name);
+        assert!(
+            gauge.tags.iter().all(|tag| !tag.contains("secret-squad")),
+            "{:?}",
+            gauge.tags
+        );
+    }
+    let hashed = format!("scope:team:acme/{}", salted_hash("pepper", "secret-squad"));
+    assert!(gauges.iter().any(|g| g.tags == vec![hashed.clone()]));
+    assert!(gauges
+        .iter()
+        .any(|g| g.tags == vec!["scope:teams:acme/all".to_string()]));
+
+    // Without a salt the slug is sent as is
+    let gauges = run.runtime_gauges("2025-03-02", None);
+    assert!(gauges
+        .iter()
+        .any(|g| g.tags == vec!["scope:team:acme/secret-squad".to_string()]));
+}
+
+#[test]
+fn test_succeeded_namespaces() {
+    let mut run = RunSummary::new();
+    let sent = SendStats {
+        series_prepared: 10,
+        ..SendStats::default()
+    };
+    for (scope, namespace) in [
+        ("enterprise:acme", "gh"),
+        ("premium_requests:acme", "gh"),
+        ("team:acme/web", "gh.team.web"),
+    ] {
+        let _ = run.track(scope, namespace, |summary| {
+            summary.record_send(&SendStats::default(), &sent);
+            Ok(())
+        });
+    }
+    let _: crate::error::Result<()> = run.track("team:acme/api", "gh.team.api", |summary| {
+        summary.record_send(&SendStats::default(), &sent);
+        Err(GhrustError::NamespaceCollision("gh.team.api".to_string()))
+    });
+    let _ = run.track("team:acme/idle", "gh.team.idle", |_| Ok(()));
+
+    assert_eq!(run.succeeded_namespaces(false), vec!["gh", "gh.team.web"]);
+    assert!(run.succeeded_namespaces(true).is_empty());
+}
+
+#[test]
+fn test_weekly_digest_aggregation() {
+    let metrics: Vec<_> = (1..=10)
+        .map(|day| {
+            let mut metric = create_mock_metrics(100 + day, 50);
+            metric.date = format!("2023-03-{:02}", day);
+            metric
+        })
+        .rev()
+        .collect();
+
+    let digest = WeeklyDigest::from_metrics("acme", &metrics).unwrap();
+    assert_eq!(digest.daily_active_users.len(), 7);
+    assert_eq!(
+        digest.daily_active_users[0],
+        ("2023-03-04".to_string(), 104)
+    );
+    assert_eq!(digest.average_active_users, 107.0);
+    assert_eq!(digest.previous_average_active_users, Some(102.0));
+    assert_eq!(digest.acceptance_rate, Some(0.5));
+    assert_eq!(digest.top_languages[0].name, "Rust");
+    assert_eq!(digest.top_languages[0].acceptances, 7 * 2500);
+
+    let html = digest.to_html();
+    assert!(html.contains("2023-03-04 to 2023-03-10"));
+    assert!(html.contains("50.0%"));
+
+    assert!(WeeklyDigest::from_metrics("acme", &[]).is_none());
+}
+
+#[test]
+fn test_metrics_report_aggregation() {
+    let metrics: Vec<_> = (1..=3)
+        .map(|day| {
+            let mut metric = create_mock_metrics(10 * day, 5 * day);
+            metric.date = format!("2023-03-{:02}", day);
+            metric
+        })
+        .rev()
+        .collect();
+
+    let report = MetricsReport::from_metrics("acme <&>", &metrics);
+    assert_eq!(report.days.len(), 3);
+    assert_eq!(report.days[0].date, "2023-03-01");
+    assert_eq!(report.days[2].active_users, 30);
+    assert_eq!(report.languages[0].name, "Rust");
+    assert_eq!(report.languages[0].value, 3 * 2500);
+    assert_eq!(report.editors[0].name, "VS Code");
+
+    let html = report.to_html();
+    assert!(html.contains("acme &lt;&amp;&gt;"));
+    assert!(html.contains("<polyline"));
+    assert!(html.contains("2023-03-01 to 2023-03-03"));
+
+    let empty = MetricsReport::from_metrics("acme", &[]).to_html();
+    assert!(empty.contains("No data"));
+}
+
+#[test]
+fn test_period_comparison() {
+    let metrics: Vec<_> = (1..=15)
+        .map(|day| {
+            let active = if day <= 8 { 100 } else { 150 };
+            let mut metric = create_mock_metrics(active, 50);
+            metric.date = format!("2023-03-{:02}", day);
+            metric
+        })
+        .collect();
+
+    let comparison = PeriodComparison::from_metrics(&metrics, 7).unwrap();
+    assert_eq!(comparison.previous.from, "2023-03-02");
+    assert_eq!(comparison.previous.to, "2023-03-08");
+    assert_eq!(comparison.current.from, "2023-03-09");
+    assert_eq!(comparison.current.to, "2023-03-15");
+
+    let active = &comparison.changes[0];
+    assert_eq!(active.metric, "average_active_users");
+    assert_eq!((active.previous, active.current), (100.0, 150.0));
+    assert_eq!(active.change, Some(0.5));
+
+    let rate = comparison
+        .changes
+        .iter()
+        .find(|c| c.metric == "acceptance_rate")
+        .unwrap();
+    assert_eq!(rate.change, Some(0.0));
+    assert!(comparison.to_table().contains("+50.0%"));
+
+    assert!(PeriodComparison::from_metrics(&metrics[..13], 7).is_none());
+}
+
+#[test]
+fn test_team_rollup_sums_teams_per_day() {
+    let day = |date: &str, active: i64| {
+        let mut metric = create_mock_metrics(active, active / 2);
+        metric.date = date.to_string();
+        metric
+    };
+    let rollup = sum_by_date(vec![
+        day("2023-03-02", 10),
+        day("2023-03-01", 20),
+        day("2023-03-02", 30),
+    ]);
+
+    assert_eq!(rollup.len(), 2);
+    assert_eq!(rollup[0].date, "2023-03-01");
+    assert_eq!(rollup[1].total_active_users, Some(40));
+    assert_eq!(rollup[1].total_engaged_users, Some(20));
+
+    let completions = rollup[1].copilot_ide_code_completions.as_ref().unwrap();
+    let languages = completions.languages.as_ref().unwrap();
+    assert_eq!(languages.len(), 1);
+    assert_eq!(languages[0].total_code_acceptances, Some(5000));
+
+    assert_eq!(rollup_namespace("gh.copilot"), "gh.copilot.teams.all");
+}
+
+#[test]
+fn test_parent_teams_from_hierarchy() {
+    let parent = |slug: &str| TeamMapping {
+        parent: Some(slug.to_string()),
+        ..TeamMapping::default()
+    };
+    let mut mappings = HashMap::new();
+    mappings.insert("payments".to_string(), parent("fintech"));
+    mappings.insert("billing".to_string(), parent("fintech"));
+    mappings.insert("fintech".to_string(), parent("business"));
+    mappings.insert("search".to_string(), parent("discovery"));
+
+    let slugs = vec![
+        "payments".to_string(),
+        "billing".to_string(),
+        "search".to_string(),
+        "discovery".to_string(),
+    ];
+
+    // Processed teams report their own metrics and are not aggregated
+    assert_eq!(
+        parent_teams(&slugs, &mappings),
+        vec!["business".to_string(), "fintech".to_string()]
+    );
+    assert!(parent_teams(&slugs, &HashMap::new()).is_empty());
+}
+
+#[test]
+fn test_adoption_from_latest_day() {
+    let day = |date: &str, active: i64| {
+        let mut metric = create_mock_metrics(active, active / 2);
+        metric.date = date.to_string();
+        metric
+    };
+    let metrics = vec![day("2023-03-02", 40), day("2023-03-01", 10)];
+
+    let adoption = Adoption::from_metrics(&metrics, 80).unwrap();
+    assert_eq!(adoption.date, "2023-03-02");
+    assert_eq!(adoption.active_users_rate(), 0.5);
+    assert_eq!(adoption.engaged_users_rate(), 0.25);
+
+    let gauges = adoption.to_gauges();
+    assert_eq!(gauges[0].name, "adoption.members");
+    assert_eq!(gauges[0].value, 80.0);
+
+    assert!(Adoption::from_metrics(&metrics, 0).is_none());
+    assert!(Adoption::from_metrics(&[], 80).is_none());
+}
+
+#[test]
+fn test_seat_usage_detects_inactive_seats() {
+    let seat = |login: &str, last_activity_at: Option<&str>| -> CopilotSeat {
+        serde_json::from_value(serde_json::json!({
+            "assignee": { "login": login },
+            "created_at": "2023-01-01T00:00:00Z",
+            "last_activity_at": last_activity_at,
+        }))
+        .unwrap()
+    };
+    let seats = vec![
+        seat("zoe", None),
+        seat("ann", Some("2023-02-20T12:00:00Z")),
+        seat("bob", Some("2023-03-05T08:00:00Z")),
+    ];
+
+    let since = "2023-03-01T00:00:00Z".parse().unwrap();
+    let usage = SeatUsage::from_seats(&seats, since);
+    assert_eq!(usage.total, 3);
+    assert_eq!(usage.unused, vec!["ann".to_string(), "zoe".to_string()]);
+
+    assert_eq!(usage.to_gauges("2023-03-10", false, None).len(), 2);
+    let gauges = usage.to_gauges("2023-03-10", true, Some(39.0));
+    assert_eq!(gauges[1].value, 2.0);
+    assert_eq!(gauges[2].name, "seats.estimated_spend");
+    assert_eq!(gauges[2].value, 117.0);
+    assert_eq!(gauges[3].value, 78.0);
+    assert_eq!(gauges[4].tags, vec!["user:ann".to_string()]);
+}
+
+#[test]
+fn test_activity_histogram_buckets_seats() {
+    let seat = |last_activity_at: Option<&str>| -> CopilotSeat {
+        serde_json::from_value(serde_json::json!({
+            "assignee": { "login": "octocat" },
+            "created_at": "2023-01-01T00:00:00Z",
+            "last_activity_at": last_activity_at,
+        }))
+        .unwrap()
+    };
+    let seats = vec![
+        seat(Some("2023-03-09T12:00:00Z")),
+        seat(Some("2023-03-03T00:00:00Z")),
+        seat(Some("2023-02-28T00:00:00Z")),
+        seat(Some("2022-10-01T00:00:00Z")),
+        seat(None),
+        seat(None),
+    ];
+
+    let now = "2023-03-10T00:00:00Z".parse().unwrap();
+    let histogram = ActivityHistogram::from_seats(&seats, now);
+    assert_eq!(
+        histogram.buckets,
+        vec![
+            ("0-7", 2),
+            ("8-14", 1),
+            ("15-30", 0),
+            ("31-90", 0),
+            ("91+", 1),
+            ("never", 2)
+        ]
+    );
+
+    let gauges = histogram.to_gauges("2023-03-10");
+    assert_eq!(gauges.len(), 6);
+    assert_eq!(gauges[2].value, 0.0);
+    assert_eq!(
+        gauges[5].tags,
+        vec!["days_since_activity:never".to_string()]
+    );
+}
+
+#[test]
+fn test_seat_breakdown_gauges() {
+    let billing: CopilotBilling = serde_json::from_value(serde_json::json!({
+        "seat_breakdown": {
+            "total": 12,
+            "added_this_cycle": 3,
+            "pending_cancellation": 1,
+            "pending_invitation": 2,
+            "active_this_cycle": 9,
+            "inactive_this_cycle": 3
+        },
+        "seat_management_setting": "assign_selected",
+        "public_code_suggestions": "block"
+    }))
+    .unwrap();
+
+    let gauges: Vec<(String, f64)> = breakdown_gauges(&billing.seat_breakdown, "2025-06-01")
+        .into_iter()
+        .map(|gauge| (gauge.name, gauge.value))
+        .collect();
+    assert_eq!(
+        gauges,
+        vec![
+            ("seats.total".to_string(), 12.0),
+            ("seats.added_this_cycle".to_string(), 3.0),
+            ("seats.pending_cancellation".to_string(), 1.0),
+            ("seats.pending_invitation".to_string(), 2.0),
+        ]
+    );
+}
+
+#[test]
+fn test_alert_rules_on_latest_day() {
+    let day = |date: &str, active: i64| {
+        let mut metric = create_mock_metrics(active, active / 2);
+        metric.date = date.to_string();
+        metric
+    };
+    let rule = |metric, drop_percent, below| AlertRule {
+        metric,
+        drop_percent,
+        below,
+    };
+    let metrics = vec![day("2023-03-02", 70), day("2023-03-01", 100)];
+    let rules = vec![
+        rule(AlertMetric::ActiveUsers, Some(20.0), None),
+        rule(AlertMetric::EngagedUsers, Some(40.0), None),
+        rule(AlertMetric::AcceptanceRate, None, Some(60.0)),
+        rule(AlertMetric::AcceptanceRate, None, Some(15.0)),
+    ];
+
+    let alerts = evaluate("enterprise:acme", &rules, &metrics);
+    assert_eq!(alerts.len(), 2);
+    assert_eq!(alerts[0].metric, AlertMetric::ActiveUsers);
+    assert_eq!(alerts[0].date, "2023-03-02");
+    assert_eq!(alerts[0].value, 70.0);
+    assert_eq!(alerts[1].metric, AlertMetric::AcceptanceRate);
+    assert_eq!(alerts[1].value, 50.0);
+
+    let notification = alerts[0].notification();
+    assert_eq!(notification.dedup_key, "enterprise:acme:active_users");
+    assert!(notification.message.contains("dropped 30.0%"));
+
+    // A single day can't drop, and no days fire nothing
+    assert!(evaluate("enterprise:acme", &rules[..1], &metrics[..1]).is_empty());
+    assert!(evaluate("enterprise:acme", &rules, &[]).is_empty());
+}
+
+/// Records the alerts it is notified about and the keys it resolves
+struct RecordingNotifier(std::sync::Arc<std::sync::Mutex<Vec<String>>>);
+
+impl Notifier for RecordingNotifier {
+    fn name(&self) -> &str {
+        "recording"
+    }
+
+    fn notify(&self, notification: &Notification) -> crate::services::notify::Result<()> {
+        self.0
+            .lock()
+            .unwrap()
+            .push(format!("open {}", notification.dedup_key));
+        Ok(())
+    }
+
+    fn resolve(&self, dedup_key: &str) -> crate::services::notify::Result<()> {
+        self.0.lock().unwrap().push(format!("close {}", dedup_key));
+        Ok(())
+    }
+}
+
+#[test]
+fn test_alerts_resolve_rules_that_stopped_firing() {
+    let mut metric = create_mock_metrics(70, 35);
+    metric.date = "2023-03-02".to_string();
+    let rules = vec![
+        AlertRule {
+            metric: AlertMetric::ActiveUsers,
+            drop_percent: None,
+            below: Some(100.0),
+        },
+        AlertRule {
+            metric: AlertMetric::ActiveUsers,
+            drop_percent: None,
+            below: Some(10.0),
+        },
+        AlertRule {
+            metric: AlertMetric::EngagedUsers,
+            drop_percent: None,
+            below: Some(10.0),
+        },
+        AlertRule {
+            metric: AlertMetric::EngagedUsers,
+            drop_percent: Some(50.0),
+            below: None,
+        },
+    ];
+    let received = std::sync::Arc::new(std::sync::Mutex::new(Vec::new()));
+    let notifications =
+        NotificationSink::new().with_notifier(RecordingNotifier(std::sync::Arc::clone(&received)));
+
+    let fired = process_alerts("enterprise:acme", &rules, &[metric], &notifications).unwrap();
+
+    assert_eq!(fired, 1);
+    assert_eq!(
+        *received.lock().unwrap(),
+        vec![
+            "open enterprise:acme:active_users",
+            "close enterprise:acme:engaged_users",
+        ]
+    );
+}
+
+#[test]
+fn test_anomaly_scores_latest_day() {
+    let day = |n: u32, active: i64| {
+        let mut metric = create_mock_metrics(active, 50);
+        metric.date = format!("2023-03-{:02}", n);
+        metric
+    };
+    let mut metrics: Vec<_> = (1..=10)
+        .map(|n| day(n, 100 + (n % 2) as i64 * 10))
+        .collect();
+    metrics.push(day(11, 20));
+
+    let scores = score(&metrics);
+    let active = scores
+        .iter()
+        .find(|s| s.metric == AlertMetric::ActiveUsers)
+        .unwrap();
+    assert_eq!(active.date, "2023-03-11");
+    assert_eq!(active.mean, 105.0);
+    assert_eq!(active.std_dev, 5.0);
+    assert_eq!(active.z_score, -17.0);
+    assert!(active.is_anomalous(3.0));
+    // Constant metrics have no spread to score against
+    assert!(scores.iter().all(|s| s.metric != AlertMetric::EngagedUsers));
+
+    let gauges = to_gauges(&scores, 3.0);
+    assert_eq!(gauges[0].name, "anomaly.z_score");
+    assert!(gauges[0].tags.contains(&"anomalous:true".to_string()));
+    let count = gauges.last().unwrap();
+    assert_eq!(count.name, "anomaly.count");
+    assert_eq!(count.value, 1.0);
+
+    // Too little history is not scored
+    assert!(score(&metrics[5..]).is_empty());
+}
+
+#[test]
+fn test_baseline_weeks_and_quarter_comparison() {
+    let day = |date: NaiveDate, active: i64| {
+        let mut metric = create_mock_metrics(active, active / 2);
+        metric.date = date.to_string();
+        metric
+    };
+    // Wednesday 2023-03-01 to Tuesday 2023-03-21: one partial week at each end
+    let start = NaiveDate::from_ymd_opt(2023, 3, 1).unwrap();
+    let metrics: Vec<_> = (0..21)
+        .map(|i| day(start + chrono::Duration::days(i), 100 + i))
+        .collect();
+
+    let weeks = weekly_aggregates(&metrics);
+    assert_eq!(weeks.len(), 2);
+    assert_eq!(weeks[0].week_start.to_string(), "2023-03-06");
+    assert_eq!(weeks[0].average_active_users, 108.0);
+    assert_eq!(weeks[1].week_start.to_string(), "2023-03-13");
+    assert_eq!(weeks[1].acceptance_rate(), Some(50.0));
+
+    let mut baseline = Baseline::default();
+    let mut quarter_ago = weeks[1].clone();
+    quarter_ago.week_start -= chrono::Duration::weeks(13);
+    quarter_ago.average_active_users = 50.0;
+    baseline.merge("enterprise:acme", &[quarter_ago.clone()]);
+    baseline.merge("enterprise:acme", &weeks);
+    // Merging the same weeks again replaces them
+    baseline.merge("enterprise:acme", &weeks);
+    assert_eq!(baseline.scopes["enterprise:acme"].len(), 3);
+
+    let previous = baseline.week("enterprise:acme", quarter_ago.week_start);
+    let gauges = comparison_gauges(&weeks[1], previous);
+    let gauge = |name: &str| gauges.iter().find(|g| g.name == name).unwrap();
+    assert_eq!(gauge("baseline.week.active_users").value, 115.0);
+    assert_eq!(gauge("baseline.week.active_users").date, "2023-03-19");
+    assert_eq!(gauge("baseline.quarter_ago.active_users").value, 50.0);
+    assert_eq!(gauge("baseline.change_percent.active_users").value, 130.0);
+    assert_eq!(gauge("baseline.change_percent.acceptance_rate").value, 0.0);
+
+    // Without a stored quarter-ago week only the current week is reported
+    assert_eq!(comparison_gauges(&weeks[1], None).len(), 3);
+}
+
+#[test]
+fn test_team_privacy_mode_hashes_unmapped_teams() {
+    let mut mappings = HashMap::new();
+    mappings.insert(
+        "payments".to_string(),
+        TeamMapping {
+            parent: Some("fintech".to_string()),
+            ..TeamMapping::default()
+        },
+    );
+    mappings.insert(
+        "search".to_string(),
+        TeamMapping {
+            namespace: Some("copilot.search".to_string()),
+            ..TeamMapping::default()
+        },
+    );
+    let slugs = vec!["payments".to_string(), "search".to_string()];
+    let template = NamespaceTemplate::default();
+
+    let hashed = hash_team_namespaces("github.copilot", &template, &slugs, &mappings, "salt");
+    let payments = salted_hash("salt", "payments");
+    assert_eq!(
+        team_namespace(
+            "github.copilot",
+            &template,
+            "payments",
+            hashed.get("payments")
+        ),
+        format!("github.copilot.team.{}", payments)
+    );
+    assert_eq!(hashed["payments"].parent.as_deref(), Some("fintech"));
+    assert_eq!(
+        team_namespace(
+            "github.copilot",
+            &template,
+            "fintech",
+            hashed.get("fintech")
+        ),
+        format!("github.copilot.team.{}", salted_hash("salt", "fintech"))
+    );
+    assert_eq!(
+        hashed["search"].namespace.as_deref(),
+        Some("copilot.search")
+    );
+
+    let hashes = team_hashes(&slugs, &mappings, "salt");
+    assert_eq!(hashes.len(), 2);
+    assert_eq!(hashes[&payments], "payments");
+
+    let path = std::env::temp_dir().join(format!("ghrust-team-hashes-{}.json", std::process::id()));
+    std::fs::write(&path, r#"{"0123456789abcdef": "retired-team"}"#).unwrap();
+    update_mapping_file(&path, &hashes).unwrap();
+    let recorded: HashMap<String, String> =
+        serde_json::from_slice(&std::fs::read(&path).unwrap()).unwrap();
+    std::fs::remove_file(&path).unwrap();
+    assert_eq!(recorded.len(), 3);
+    assert_eq!(recorded["0123456789abcdef"], "retired-team");
+    assert_eq!(recorded[&payments], "payments");
+}
+
+#[test]
+fn test_backfill_resumes_after_last_completed_day() {
+    let path = std::env::temp_dir().join(format!("ghrust-backfill-{}.json", std::process::id()));
+    std::fs::remove_file(&path).ok();
+    let options = BackfillOptions::new(
+        NaiveDate::from_ymd_opt(2025, 3, 1).unwrap(),
+        NaiveDate::from_ymd_opt(2025, 3, 4).unwrap(),
+    )
+    .with_day_delay(std::time::Duration::ZERO);
+    let day = |date: &str| {
+        let mut metric = create_mock_metrics(10, 5);
+        metric.date = date.to_string();
+        metric
+    };
+
+    // The first attempt fails on the third day
+    let mut checkpoint = BackfillCheckpoint::open(&path).unwrap();
+    let mut summary = ScopeSummary::new("enterprise:acme", "github.copilot");
+    let mut sent = Vec::new();
+    let result = backfill_scope(
+        &options,
+        &mut checkpoint,
+        &mut summary,
+        |date| match date {
+            "2025-03-03" => Err(GitHubError::Network("timeout".to_string()).into()),
+            _ => Ok(vec![day(date)]),
+        },
+        |metrics, _| {
+            sent.push(metrics[0].date.clone());
+            Ok(())
+        },
+    );
+    assert!(result.is_err());
+    assert_eq!(sent, vec!["2025-03-01", "2025-03-02"]);
+
+    // A new attempt with the same file starts at the failed day
+    let mut checkpoint = BackfillCheckpoint::open(&path).unwrap();
+    assert_eq!(
+        checkpoint.last_completed("enterprise:acme"),
+        NaiveDate::from_ymd_opt(2025, 3, 2)
+    );
+    let mut summary = ScopeSummary::new("enterprise:acme", "github.copilot");
+    let mut requested = Vec::new();
+    let days = backfill_scope(
+        &options,
+        &mut checkpoint,
+        &mut summary,
+        |date| {
+            requested.push(date.to_string());
+            Ok(vec![day(date)])
+        },
+        |_, _| Ok(()),
+    )
+    .unwrap();
+    std::fs::remove_file(&path).ok();
+
+    assert_eq!(days, 2);
+    assert_eq!(requested, vec!["2025-03-03", "2025-03-04"]);
+    assert_eq!(summary.dates_fetched, 2);
+    assert_eq!(checkpoint.last_completed("team:acme/platform"), None);
+}
+
+#[test]
+fn test_weekly_rollups_average_users_and_sum_activity() {
+    // Wednesday 2023-03-01 to Tuesday 2023-03-21: one partial week at each end
+    let start = NaiveDate::from_ymd_opt(2023, 3, 1).unwrap();
+    let metrics: Vec<_> = (0..21)
+        .map(|i| {
+            let mut metric = create_mock_metrics(100 + i, 50);
+            metric.date = (start + chrono::Duration::days(i)).to_string();
+            metric
+        })
+        .collect();
+
+    let weeks = rollups(&metrics, RollupPeriod::Week);
+    assert_eq!(weeks.len(), 2);
+    assert_eq!(weeks[0].start.to_string(), "2023-03-06");
+    assert_eq!(weeks[1].start.to_string(), "2023-03-13");
+
+    let gauges = rollup_gauges(&weeks, RollupPeriod::Week);
+    let gauge = |name: &str, date: &str| {
+        gauges
+            .iter()
+            .find(|g| g.name == name && g.date == date)
+            .unwrap()
+            .value
+    };
+    // 105..=111 active users per day
+    assert_eq!(gauge("weekly.average_active_users", "2023-03-06"), 108.0);
+    let daily_suggestions = metrics[0]
+        .copilot_ide_code_completions
+        .as_ref()
+        .and_then(|c| c.languages.as_ref())
+        .unwrap()
+        .iter()
+        .map(|l| l.total_code_suggestions.unwrap_or(0))
+        .sum::<i64>() as f64;
+    assert_eq!(
+        gauge("weekly.total_code_suggestions", "2023-03-13"),
+        7.0 * daily_suggestions
+    );
+    assert!(gauges.iter().all(|g| g.name.starts_with("weekly.")));
+
+    // A window without a complete week reports nothing
+    assert!(rollups(&metrics[..6], RollupPeriod::Week).is_empty());
+}
+
+#[test]
+fn test_monthly_rollups_need_complete_months() {
+    // 2024-01-15 to 2024-03-31: January is partial, leap-year February complete
+    let start = NaiveDate::from_ymd_opt(2024, 1, 15).unwrap();
+    let metrics: Vec<_> = (0..77)
+        .map(|i| {
+            let mut metric = create_mock_metrics(100, 50);
+            metric.date = (start + chrono::Duration::days(i)).to_string();
+            metric
+        })
+        .collect();
+
+    let months = rollups(&metrics, RollupPeriod::Month);
+    let starts: Vec<_> = months.iter().map(|m| m.start.to_string()).collect();
+    assert_eq!(starts, ["2024-02-01", "2024-03-01"]);
+    assert_eq!(RollupPeriod::Month.days(months[0].start), 29);
+
+    let gauges = rollup_gauges(&months, RollupPeriod::Month);
+    assert!(gauges
+        .iter()
+        .any(|g| g.name == "monthly.average_active_users" && g.value == 100.0));
+
+    // A missing day leaves the month incomplete
+    let mut gap = metrics.clone();
+    gap.retain(|m| m.date != "2024-03-10");
+    assert_eq!(rollups(&gap, RollupPeriod::Month).len(), 1);
+}
+
+#[test]
+fn test_team_distribution_percentiles() {
+    assert_eq!(percentile(&[1.0, 2.0, 3.0, 4.0, 5.0], 0.5), 3.0);
+    assert_eq!(percentile(&[10.0, 20.0], 0.9), 19.0);
+    assert_eq!(percentile(&[7.0], 0.1), 7.0);
+
+    let team = |slug: &str, engaged: i64| {
+        let mut older = create_mock_metrics(engaged, engaged);
+        older.date = "2023-03-01".to_string();
+        let mut latest = create_mock_metrics(engaged, engaged);
+        latest.date = "2023-03-02".to_string();
+        (slug.to_string(), vec![older, latest])
+    };
+    let mut teams: Vec<_> = [10, 40, 20, 30, 50]
+        .iter()
+        .enumerate()
+        .map(|(i, engaged)| team(&format!("team-{}", i), *engaged))
+        .collect();
+
+    let distribution = TeamDistribution::from_teams(&teams).unwrap();
+    assert_eq!(distribution.date, "2023-03-02");
+    assert_eq!(distribution.teams, 5);
+    let engaged = distribution
+        .metrics
+        .iter()
+        .find(|d| d.metric == AlertMetric::EngagedUsers)
+        .unwrap();
+    assert_eq!(engaged.percentiles, vec![14.0, 30.0, 46.0]);
+
+    let gauges = distribution.to_gauges();
+    let gauge = |name: &str| gauges.iter().find(|g| g.name == name).unwrap().value;
+    assert_eq!(gauge("teams.distribution.teams"), 5.0);
+    assert_eq!(gauge("teams.distribution.engaged_users.p50"), 30.0);
+    assert_eq!(gauge("teams.distribution.engaged_users.p90"), 46.0);
+
+    // Teams that didn't report the latest day are left out
+    for (_, metrics) in &mut teams[2..] {
+        metrics.pop();
+    }
+    assert!(TeamDistribution::from_teams(&teams).is_none());
+}
+
+#[test]
+fn test_team_ranking_orders_by_metric() {
+    let metrics = vec![create_mock_metrics(20, 10), create_mock_metrics(20, 30)];
+    let platform = TeamStanding::from_metrics("platform", &metrics, Some(40)).unwrap();
+    assert_eq!(platform.average_engaged_users, 20.0);
+    assert_eq!(platform.engaged_users_rate, Some(0.5));
+    assert!(platform.acceptance_rate.is_some());
+
+    let mut web = TeamStanding::from_metrics("web", &metrics, Some(25)).unwrap();
+    web.acceptance_rate = Some(0.1);
+    let data = TeamStanding::from_metrics("data", &metrics, None).unwrap();
+    assert_eq!(data.engaged_users_rate, None);
+    assert!(TeamStanding::from_metrics("empty", &[], Some(10)).is_none());
+
+    let ranking = TeamRanking::new(
+        vec![data.clone(), platform.clone(), web.clone()],
+        RankBy::EngagedUsersRate,
+    );
+    let order: Vec<_> = ranking.teams.iter().map(|t| t.team.as_str()).collect();
+    // Teams without a member count rank last
+    assert_eq!(order, ["web", "platform", "data"]);
+
+    let ranking = TeamRanking::new(vec![web, platform, data], RankBy::AcceptanceRate);
+    assert_eq!(ranking.teams.last().unwrap().team, "web");
+    let table = ranking.to_table();
+    assert!(table.lines().nth(1).unwrap().starts_with("   1 data"));
+
+    assert_eq!("chats_per_user".parse(), Ok(RankBy::ChatsPerUser));
+    assert!("engagement".parse::<RankBy>().is_err());
+}
+
+#[test]
+fn test_leaderboard_ranks_teams_by_growth() {
+    let team = |slug: &str, previous: i64, current: i64| {
+        let start = NaiveDate::from_ymd_opt(2023, 3, 1).unwrap();
+        let metrics: Vec<_> = (0..14)
+            .map(|i| {
+                let engaged = if i < 7 { previous } else { current };
+                let mut metric = create_mock_metrics(engaged, engaged);
+                metric.date = (start + chrono::Duration::days(i)).to_string();
+                metric
+            })
+            .collect();
+        (slug.to_string(), metrics)
+    };
+    let mut teams = vec![
+        team("platform", 20, 22),
+        team("web", 10, 15),
+        team("data", 0, 4),
+        team("mobile", 30, 24),
+    ];
+    // A team with less than two weeks of metrics isn't ranked
+    let (_, mut short) = team("infra", 5, 50);
+    short.drain(..5);
+    teams.push(("infra".to_string(), short));
+
+    let leaderboard = Leaderboard::from_teams("enterprise:acme", &teams).unwrap();
+    assert_eq!(leaderboard.from, "2023-03-08");
+    assert_eq!(leaderboard.to, "2023-03-14");
+    let order: Vec<_> = leaderboard
+        .entries
+        .iter()
+        .map(|e| e.team.as_str())
+        .collect();
+    assert_eq!(order, ["web", "platform", "mobile", "data"]);
+    assert_eq!(leaderboard.entries[0].growth, Some(0.5));
+
+    let markdown = leaderboard.to_markdown();
+    assert!(markdown.contains("1. web: 15.0 engaged users per day (+50.0%, from 10.0)"));
+    assert!(markdown.contains("4. data: 4.0 engaged users per day (new, from 0.0)"));
+
+    assert!(Leaderboard::from_teams("enterprise:acme", &teams[4..]).is_none());
+}
+
+#[test]
+fn test_metrics_bundle_round_trip() {
+    let path = std::env::temp_dir().join(format!("ghrust-bundle-{}.json", std::process::id()));
+    let mut bundle = MetricsBundle::new();
+    bundle.scopes.push(FetchedScope {
+        scope: "team:acme/web".to_string(),
+        namespace: "github.copilot.team.web".to_string(),
+        tags: vec!["cost_center:42".to_string()],
+        tag_context: TagContext::team("acme", "web"),
+        metrics: vec![create_mock_metrics(10, 5)],
+    });
+    bundle.write(&path).unwrap();
+
+    let read = MetricsBundle::read(&path).unwrap();
+    assert_eq!(read.version, BUNDLE_VERSION);
+    assert_eq!(read.fetched_at, bundle.fetched_at);
+    assert_eq!(read.scopes.len(), 1);
+    assert_eq!(read.scopes[0].namespace, "github.copilot.team.web");
+    assert_eq!(read.scopes[0].tags, vec!["cost_center:42"]);
+    assert_eq!(read.scopes[0].tag_context, TagContext::team("acme", "web"));
+    assert_eq!(read.scopes[0].metrics[0].total_active_users, Some(10));
+
+    // Files of a newer format are rejected instead of being half understood
+    bundle.version = BUNDLE_VERSION + 1;
+    bundle.write(&path).unwrap();
+    assert!(matches!(
+        MetricsBundle::read(&path),
+        Err(GhrustError::Bundle(..))
+    ));
+    std::fs::remove_file(&path).ok();
+}
+
+/// Replayed windows are merged by date, later fetches replacing earlier ones
+#[test]
+fn test_merge_replayed_windows() {
+    let day = |date: &str, active: i64| crate::models::github::CopilotMetrics {
+        date: date.to_string(),
+        ..create_mock_metrics(active, 1)
+    };
+    let merged = merge_windows(vec![
+        vec![day("2025-03-01", 1), day("2025-03-02", 2)],
+        vec![day("2025-03-02", 20), day("2025-03-03", 3)],
+    ]);
+    let days: Vec<(&str, Option<i64>)> = merged
+        .iter()
+        .map(|m| (m.date.as_str(), m.total_active_users))
+        .collect();
+    assert_eq!(
+        days,
+        vec![
+            ("2025-03-01", Some(1)),
+            ("2025-03-02", Some(20)),
+            ("2025-03-03", Some(3))
+        ]
+    );
+}
+
+#[test]
+fn test_unchanged_enterprise_window_is_not_sent() {
+    use super::enterprise::process_enterprise_metrics;
+    use super::persistence::Persistence;
+    use crate::services::datadog::{DatadogDestination, DatadogSettings, DatadogSink};
+    use crate::services::github::GitHubClient;
+    use crate::tests::mock_server::{self, MockResponse};
+
+    let (github_url, _) =
+        mock_server::serve_forever(|_, _| MockResponse::new("304 Not Modified", String::new()));
+    let (datadog_url, sent) =
+        mock_server::serve_forever(|_, _| MockResponse::new("202 Accepted", "{}"));
+    let github = GitHubClient::builder()
+        .token("fake_token")
+        .base_url(&github_url)
+        .build()
+        .unwrap();
+    let mut destination = DatadogDestination::new("primary", "dd_key");
+    destination.site = datadog_url;
+    let datadog = DatadogSink::new(&[destination], "gh", &DatadogSettings::default());
+
+    let mut summary = ScopeSummary::new("enterprise:acme", "gh");
+    let metrics = process_enterprise_metrics(
+        &github,
+        "acme",
+        &datadog,
+        "gh",
+        &Persistence::default(),
+        &mut summary,
+    )
+    .unwrap();
+
+    // Without a store there is no window to serve, and nothing is sent
+    assert!(metrics.is_empty());
+    assert!(summary.not_modified);
+    assert!(summary.error.is_none());
+    assert_eq!(sent.load(std::sync::atomic::Ordering::SeqCst), 0);
+}
@@ -0,0 +1,57 @@
+//! # AWS Credentials
+//!
+//! The Lambda runtime exposes the execution role's temporary credentials and
+//! the function's region as environment variables, which is all the minimal
+//! AWS clients need.
+
+use std::env;
+
+use super::error::{AwsError, Result};
+
+/// Credentials and region used to sign AWS requests
+#[derive(Clone, PartialEq)]
+pub struct AwsCredentials {
+    /// Access key ID
+    pub access_key_id: String,
+    /// Secret access key
+    pub secret_access_key: String,
+    /// Session token of temporary credentials, if any
+    pub session_token: Option<String>,
+    /// Region requests are sent to, e.g. "eu-central-1"
+    pub region: String,
+}
+
+impl AwsCredentials {
+    /// Reads credentials from `AWS_ACCESS_KEY_ID`, `AWS_SECRET_ACCESS_KEY`,
+    /// `AWS_SESSION_TOKEN` and `AWS_REGION` (or `AWS_DEFAULT_REGION`)
+    ///
+    /// # Returns
+    ///
+    /// * `Result<AwsCredentials>` - The credentials, or a `Config` error naming
+    ///   the missing variable
+    pub fn from_env() -> Result<Self> {
+        let var =
+            |name: &str| env::var(name).map_err(|_| AwsError::Config(format!("{} not set", name)));
+
+        Ok(Self {
+            access_key_id: var("AWS_ACCESS_KEY_ID")?,
+            secret_access_key: var("AWS_SECRET_ACCESS_KEY")?,
+            session_token: env::var("AWS_SESSION_TOKEN").ok(),
+            region: var("AWS_REGION").or_else(|_| var("AWS_DEFAULT_REGION"))?,
+        })
+    }
+}
+
+impl std::fmt::Debug for AwsCredentials {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("AwsCredentials")
+            .field("access_key_id", &self.access_key_id)
+            .field("secret_access_key", &"<redacted>")
+            .field(
+                "session_token",
+                &self.session_token.as_ref().map(|_| "<redacted>"),
+            )
+            .field("region", &self.region)
+            .finish()
+    }
+}
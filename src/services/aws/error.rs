@@ -0,0 +1,24 @@
+//! # AWS Error Types
+//!
+//! This module defines structured error types for the minimal AWS clients.
+
+use thiserror::Error;
+
+/// Errors that can occur when calling AWS APIs
+#[derive(Error, Debug)]
+pub enum AwsError {
+    /// Credentials or region are not configured
+    #[error("AWS configuration error: {0}")]
+    Config(String),
+
+    /// Network or transport error
+    #[error("Network error: {0}")]
+    Network(String),
+
+    /// HTTP request error with status code
+    #[error("AWS HTTP error {0}: {1}")]
+    HttpError(u16, String),
+}
+
+/// A specialized Result type for AWS operations
+pub type Result<T> = std::result::Result<T, AwsError>;
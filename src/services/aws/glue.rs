@@ -0,0 +1,173 @@
+//! # AWS Glue Client
+//!
+//! Registers partitions of a Glue Data Catalog table through the
+//! `BatchCreatePartition` API (JSON 1.1 protocol), so Athena queries see new
+//! partitions without a crawler or `MSCK REPAIR TABLE`. The Lambda execution
+//! role needs the `glue:BatchCreatePartition` permission on the catalog,
+//! database and table.
+
+use chrono::Utc;
+use serde_json::{json, Value};
+use tracing::{debug, info};
+
+use super::credentials::AwsCredentials;
+use super::error::{AwsError, Result};
+use super::sigv4::{self, Request};
+use crate::services::tls::AgentConfig;
+
+/// Partitions the API accepts per request
+const MAX_PARTITIONS: usize = 100;
+
+/// Hive input format of text (JSON Lines) files
+const TEXT_INPUT_FORMAT: &str = "org.apache.hadoop.mapred.TextInputFormat";
+
+/// Hive output format of text files
+const TEXT_OUTPUT_FORMAT: &str = "org.apache.hadoop.hive.ql.io.HiveIgnoreKeyTextOutputFormat";
+
+/// SerDe reading JSON Lines in Athena
+const JSON_SERDE: &str = "org.openx.data.jsonserde.JsonSerDe";
+
+/// A partition of a table stored as JSON Lines
+#[derive(Debug, Clone, PartialEq)]
+pub struct JsonPartition {
+    /// Values of the partition keys, in the table's key order
+    pub values: Vec<String>,
+    /// S3 location of the partition's objects, e.g. "s3://bucket/prefix/dt=2025-03-01/"
+    pub location: String,
+}
+
+/// Client for the Glue Data Catalog of the credentials' region
+pub struct GlueClient {
+    /// Credentials and region used to sign requests
+    credentials: AwsCredentials,
+    /// Glue endpoint host for the region
+    host: String,
+    /// HTTP agent using the configured TLS settings
+    agent: ureq::Agent,
+}
+
+impl GlueClient {
+    /// Creates a client for the credentials' region
+    ///
+    /// # Arguments
+    ///
+    /// * `credentials` - Credentials and region used to sign requests
+    /// * `agent` - Settings of the HTTP agent
+    pub fn new(credentials: AwsCredentials, agent: &AgentConfig) -> Self {
+        let host = format!("glue.{}.amazonaws.com", credentials.region);
+        Self {
+            credentials,
+            host,
+            agent: agent.agent_builder().build(),
+        }
+    }
+
+    /// Registers partitions of a table, skipping existing ones
+    ///
+    /// # Arguments
+    ///
+    /// * `database` - Name of the Glue database
+    /// * `table` - Name of the table
+    /// * `partitions` - The partitions to register
+    ///
+    /// # Returns
+    ///
+    /// * `Result<usize>` - Number of partitions created; partitions that
+    ///   already exist are not counted and not an error
+    pub fn create_partitions(
+        &self,
+        database: &str,
+        table: &str,
+        partitions: &[JsonPartition],
+    ) -> Result<usize> {
+        let mut created = 0;
+        for batch in partitions.chunks(MAX_PARTITIONS) {
+            let body = json!({
+                "DatabaseName": database,
+                "TableName": table,
+                "PartitionInputList": batch.iter().map(partition_input).collect::<Vec<_>>(),
+            })
+            .to_string();
+            let response = self.call("AWSGlue.BatchCreatePartition", &body)?;
+
+            let mut existing = 0;
+            for error in response["Errors"].as_array().into_iter().flatten() {
+                let code = error["ErrorDetail"]["ErrorCode"].as_str().unwrap_or("");
+                if code != "AlreadyExistsException" {
+                    return Err(AwsError::HttpError(
+                        400,
+                        format!(
+                            "Partition {} not created: {}",
+                            error["PartitionValues"], error["ErrorDetail"]
+                        ),
+                    ));
+                }
+                existing += 1;
+            }
+            debug!(
+                "{} partitions of {}.{} already exist",
+                existing, database, table
+            );
+            created += batch.len() - existing;
+        }
+
+        if created > 0 {
+            info!(
+                "Registered {} partitions of {}.{}",
+                created, database, table
+            );
+        }
+        Ok(created)
+    }
+
+    /// Sends a signed JSON 1.1 request for an operation
+    fn call(&self, target: &str, body: &str) -> Result<Value> {
+        let headers = [
+            ("content-type", "application/x-amz-json-1.1"),
+            ("x-amz-target", target),
+        ];
+        let request = Request {
+            method: "POST",
+            host: &self.host,
+            path: "/",
+            query: &[],
+            headers: &headers,
+            payload: body.as_bytes(),
+        };
+        let signed = sigv4::sign(&request, &self.credentials, "glue", Utc::now());
+
+        let mut http = self.agent.post(&format!("https://{}/", self.host));
+        for (name, value) in headers {
+            http = http.set(name, value);
+        }
+        for (name, value) in &signed {
+            http = http.set(name, value);
+        }
+
+        match http.send_string(body) {
+            Ok(response) => response
+                .into_json()
+                .map_err(|e| AwsError::Network(e.to_string())),
+            Err(ureq::Error::Status(status, response)) => {
+                let body = response
+                    .into_string()
+                    .unwrap_or_else(|_| "Could not read response body".to_string());
+                Err(AwsError::HttpError(status, body))
+            }
+            Err(ureq::Error::Transport(transport)) => Err(AwsError::Network(transport.to_string())),
+        }
+    }
+}
+
+/// Returns the `PartitionInput` of a JSON Lines partition
+pub fn partition_input(partition: &JsonPartition) -> Value {
+    json!({
+        "Values": partition.values,
+        "StorageDescriptor": {
+            "Location": partition.location,
+            "InputFormat": TEXT_INPUT_FORMAT,
+            "OutputFormat": TEXT_OUTPUT_FORMAT,
+            "SerdeInfo": { "SerializationLibrary": JSON_SERDE },
+        },
+    })
+}
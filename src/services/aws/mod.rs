@@ -0,0 +1,30 @@
+//! # AWS Services
+//!
+//! This module provides minimal clients for the AWS APIs the collector talks
+//! to directly. Like the Datadog client, they are thin wrappers around `ureq`
+//! rather than full SDKs, signing requests with AWS Signature Version 4.
+//!
+//! ## Core Components
+//!
+//! * `credentials` - Credentials and region from the Lambda environment
+//! * `sigv4` - AWS Signature Version 4 request signing
+//! * `ses` - Sending HTML email through Amazon SES
+//! * `s3` - Reading and writing single objects in Amazon S3
+//! * `glue` - Registering table partitions in the AWS Glue Data Catalog
+//! * `error` - Structured error types for AWS operations
+
+mod credentials;
+mod error;
+pub mod glue;
+pub mod s3;
+pub mod ses;
+pub mod sigv4;
+
+#[cfg(test)]
+mod tests;
+
+pub use credentials::AwsCredentials;
+pub use error::{AwsError, Result};
+pub use glue::GlueClient;
+pub use s3::S3Client;
+pub use ses::SesClient;
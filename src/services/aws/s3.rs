@@ -0,0 +1,136 @@
+//! # Amazon S3 Client
+//!
+//! Reads and writes single objects through the S3 REST API
+//! (`GetObject`/`PutObject`). The Lambda execution role needs the
+//! `s3:GetObject` and `s3:PutObject` permissions on the objects, and
+//! `s3:ListBucket` on the bucket so missing objects are reported as such
+//! rather than as access denied.
+
+use std::io::Read;
+
+use chrono::Utc;
+use tracing::info;
+
+use super::credentials::AwsCredentials;
+use super::error::{AwsError, Result};
+use super::sigv4::{self, Request};
+use crate::services::tls::AgentConfig;
+
+/// Client for the objects of one S3 bucket
+pub struct S3Client {
+    /// Credentials and region used to sign requests
+    credentials: AwsCredentials,
+    /// Name of the bucket
+    bucket: String,
+    /// Virtual-hosted endpoint of the bucket
+    host: String,
+    /// HTTP agent using the configured TLS settings
+    agent: ureq::Agent,
+}
+
+impl S3Client {
+    /// Creates a client for a bucket in the credentials' region
+    ///
+    /// # Arguments
+    ///
+    /// * `credentials` - Credentials and region used to sign requests
+    /// * `bucket` - Name of the bucket
+    /// * `agent` - Settings of the HTTP agent
+    pub fn new(credentials: AwsCredentials, bucket: &str, agent: &AgentConfig) -> Self {
+        let host = format!("{}.s3.{}.amazonaws.com", bucket, credentials.region);
+        Self {
+            credentials,
+            bucket: bucket.to_string(),
+            host,
+            agent: agent.agent_builder().build(),
+        }
+    }
+
+    /// Reads an object
+    ///
+    /// # Arguments
+    ///
+    /// * `key` - Key of the object
+    ///
+    /// # Returns
+    ///
+    /// * `Result<Option<Vec<u8>>>` - The object's contents, or `None` if the
+    ///   object doesn't exist
+    pub fn get_object(&self, key: &str) -> Result<Option<Vec<u8>>> {
+        match self.request("GET", key, &[], b"").call() {
+            Ok(response) => {
+                let mut body = Vec::new();
+                response
+                    .into_reader()
+                    .read_to_end(&mut body)
+                    .map_err(|e| AwsError::Network(e.to_string()))?;
+                Ok(Some(body))
+            }
+            Err(ureq::Error::Status(404, _)) => Ok(None),
+            Err(e) => Err(request_error(e)),
+        }
+    }
+
+    /// Writes an object, replacing any existing one
+    ///
+    /// # Arguments
+    ///
+    /// * `key` - Key of the object
+    /// * `body` - Contents of the object
+    /// * `content_type` - Media type of the contents, e.g. "application/json"
+    pub fn put_object(&self, key: &str, body: &[u8], content_type: &str) -> Result<()> {
+        self.request("PUT", key, &[("content-type", content_type)], body)
+            .send_bytes(body)
+            .map_err(request_error)?;
+        info!("Wrote s3://{}/{}", self.bucket, key);
+        Ok(())
+    }
+
+    /// Builds a signed request for an object
+    fn request(
+        &self,
+        method: &str,
+        key: &str,
+        headers: &[(&str, &str)],
+        payload: &[u8],
+    ) -> ureq::Request {
+        let path = format!("/{}", sigv4::uri_encode(key, false));
+        let payload_hash = sigv4::sha256_hex(payload);
+        let mut signed_headers = headers.to_vec();
+        signed_headers.push(("x-amz-content-sha256", &payload_hash));
+
+        let request = Request {
+            method,
+            host: &self.host,
+            path: &path,
+            query: &[],
+            headers: &signed_headers,
+            payload,
+        };
+        let signed = sigv4::sign(&request, &self.credentials, "s3", Utc::now());
+
+        let mut http = self
+            .agent
+            .request(method, &format!("https://{}{}", self.host, path));
+        for (name, value) in signed_headers.iter().copied() {
+            http = http.set(name, value);
+        }
+        for (name, value) in &signed {
+            http = http.set(name, value);
+        }
+        http
+    }
+}
+
+/// Converts a failed S3 request into an `AwsError`
+fn request_error(e: ureq::Error) -> AwsError {
+    match e {
+        ureq::Error::Status(status, response) => {
+            let body = response
+                .into_string()
+                .unwrap_or_else(|_| "Could not read response body".to_string());
+            AwsError::HttpError(status, body)
+        }
+        ureq::Error::Transport(transport) => AwsError::Network(transport.to_string()),
+    }
+}
@@ -0,0 +1,109 @@
+//! # Amazon SES Client
+//!
+//! Sends HTML email through the SES v2 `SendEmail` API. The sender address
+//! must be a verified SES identity and the Lambda execution role needs the
+//! `ses:SendEmail` permission.
+
+use chrono::Utc;
+use serde_json::json;
+use tracing::info;
+
+use super::credentials::AwsCredentials;
+use super::error::{AwsError, Result};
+use super::sigv4::{self, Request};
+use crate::services::tls::AgentConfig;
+
+/// Path of the SES v2 `SendEmail` operation
+const SEND_EMAIL_PATH: &str = "/v2/email/outbound-emails";
+
+/// Client for sending email through Amazon SES
+pub struct SesClient {
+    /// Credentials and region used to sign requests
+    credentials: AwsCredentials,
+    /// SES endpoint host for the region
+    host: String,
+    /// HTTP agent using the configured TLS settings
+    agent: ureq::Agent,
+}
+
+impl SesClient {
+    /// Creates a client for the credentials' region
+    ///
+    /// # Arguments
+    ///
+    /// * `credentials` - Credentials and region used to sign requests
+    /// * `agent` - Settings of the HTTP agent
+    pub fn new(credentials: AwsCredentials, agent: &AgentConfig) -> Self {
+        let host = format!("email.{}.amazonaws.com", credentials.region);
+        Self {
+            credentials,
+            host,
+            agent: agent.agent_builder().build(),
+        }
+    }
+
+    /// Sends an HTML email
+    ///
+    /// # Arguments
+    ///
+    /// * `from` - Verified sender address
+    /// * `to` - Recipient addresses
+    /// * `subject` - Subject line
+    /// * `html` - HTML body
+    ///
+    /// # Returns
+    ///
+    /// * `Result<()>` - Ok once SES accepted the message
+    pub fn send_html_email(
+        &self,
+        from: &str,
+        to: &[String],
+        subject: &str,
+        html: &str,
+    ) -> Result<()> {
+        let body = json!({
+            "FromEmailAddress": from,
+            "Destination": { "ToAddresses": to },
+            "Content": {
+                "Simple": {
+                    "Subject": { "Data": subject, "Charset": "UTF-8" },
+                    "Body": { "Html": { "Data": html, "Charset": "UTF-8" } }
+                }
+            }
+        })
+        .to_string();
+
+        let content_type = [("content-type", "application/json")];
+        let request = Request {
+            method: "POST",
+            host: &self.host,
+            path: SEND_EMAIL_PATH,
+            query: &[],
+            headers: &content_type,
+            payload: body.as_bytes(),
+        };
+        let signed = sigv4::sign(&request, &self.credentials, "ses", Utc::now());
+
+        let mut http = self
+            .agent
+            .post(&format!("https://{}{}", self.host, SEND_EMAIL_PATH))
+            .set("content-type", "application/json");
+        for (name, value) in &signed {
+            http = http.set(name, value);
+        }
+
+        match http.send_string(&body) {
+            Ok(_) => {
+                info!("Sent email \"{}\" to {} recipient(s)", subject, to.len());
+                Ok(())
+            }
+            Err(ureq::Error::Status(status, response)) => {
+                let body = response
+                    .into_string()
+                    .unwrap_or_else(|_| "Could not read response body".to_string());
+                Err(AwsError::HttpError(status, body))
+            }
+            Err(ureq::Error::Transport(transport)) => Err(AwsError::Network(transport.to_string())),
+        }
+    }
+}
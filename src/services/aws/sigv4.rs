@@ -0,0 +1,160 @@
+//! # AWS Signature Version 4
+//!
+//! Implements the request signing process described in the AWS documentation
+//! ("Create a signed AWS API request"). Only what the minimal clients need is
+//! supported: a single-chunk payload and header-based authorization.
+
+use chrono::{DateTime, Utc};
+use hmac::{Hmac, Mac};
+use sha2::{Digest, Sha256};
+
+use super::credentials::AwsCredentials;
+
+/// The request parts covered by the signature
+pub struct Request<'a> {
+    /// HTTP method, e.g. "POST"
+    pub method: &'a str,
+    /// Host the request is sent to, e.g. "email.eu-central-1.amazonaws.com"
+    pub host: &'a str,
+    /// URI-encoded absolute path, e.g. "/v2/email/outbound-emails"
+    pub path: &'a str,
+    /// Query parameters (unencoded)
+    pub query: &'a [(&'a str, &'a str)],
+    /// Additional headers to sign and send, e.g. the content type
+    pub headers: &'a [(&'a str, &'a str)],
+    /// Request body
+    pub payload: &'a [u8],
+}
+
+/// Signs a request, returning the headers to add to it
+///
+/// The returned headers are `x-amz-date`, `x-amz-security-token` (for
+/// temporary credentials) and `authorization`. The `host` header is signed but
+/// not returned, since the HTTP client sets it.
+///
+/// # Arguments
+///
+/// * `request` - The request to sign
+/// * `credentials` - Credentials and region to sign with
+/// * `service` - AWS service name, e.g. "ses" or "s3"
+/// * `now` - The signing time
+pub fn sign(
+    request: &Request,
+    credentials: &AwsCredentials,
+    service: &str,
+    now: DateTime<Utc>,
+) -> Vec<(String, String)> {
+    let amz_date = now.format("%Y%m%dT%H%M%SZ").to_string();
+    let date = now.format("%Y%m%d").to_string();
+
+    let mut headers: Vec<(String, String)> = request
+        .headers
+        .iter()
+        .map(|(k, v)| (k.to_ascii_lowercase(), v.trim().to_string()))
+        .collect();
+    headers.push(("host".to_string(), request.host.to_string()));
+    headers.push(("x-amz-date".to_string(), amz_date.clone()));
+    if let Some(token) = &credentials.session_token {
+        headers.push(("x-amz-security-token".to_string(), token.clone()));
+    }
+    headers.sort();
+
+    let canonical_headers: String = headers
+        .iter()
+        .map(|(k, v)| format!("{}:{}\n", k, v))
+        .collect();
+    let signed_headers = headers
+        .iter()
+        .map(|(k, _)| k.as_str())
+        .collect::<Vec<_>>()
+        .join(";");
+
+    let canonical_request = format!(
+        "{}\n{}\n{}\n{}\n{}\n{}",
+        request.method,
+        request.path,
+        canonical_query(request.query),
+        canonical_headers,
+        signed_headers,
+        sha256_hex(request.payload)
+    );
+
+    let scope = format!("{}/{}/{}/aws4_request", date, credentials.region, service);
+    let string_to_sign = format!(
+        "AWS4-HMAC-SHA256\n{}\n{}\n{}",
+        amz_date,
+        scope,
+        sha256_hex(canonical_request.as_bytes())
+    );
+
+    let key = hmac(
+        format!("AWS4{}", credentials.secret_access_key).as_bytes(),
+        date.as_bytes(),
+    );
+    let key = hmac(&key, credentials.region.as_bytes());
+    let key = hmac(&key, service.as_bytes());
+    let key = hmac(&key, b"aws4_request");
+    let signature = hex::encode(hmac(&key, string_to_sign.as_bytes()));
+
+    let mut signed = vec![("x-amz-date".to_string(), amz_date)];
+    if let Some(token) = &credentials.session_token {
+        signed.push(("x-amz-security-token".to_string(), token.clone()));
+    }
+    signed.push((
+        "authorization".to_string(),
+        format!(
+            "AWS4-HMAC-SHA256 Credential={}/{}, SignedHeaders={}, Signature={}",
+            credentials.access_key_id, scope, signed_headers, signature
+        ),
+    ));
+    signed
+}
+
+/// URI-encodes a string as required by Signature Version 4
+///
+/// Every byte except the unreserved characters `A-Z a-z 0-9 - _ . ~` is
+/// percent-encoded; `/` is kept when `encode_slash` is false.
+///
+/// # Arguments
+///
+/// * `value` - The string to encode
+/// * `encode_slash` - Whether `/` is encoded (false for paths)
+pub fn uri_encode(value: &str, encode_slash: bool) -> String {
+    let mut encoded = String::with_capacity(value.len());
+    for byte in value.bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => {
+                encoded.push(byte as char)
+            }
+            b'/' if !encode_slash => encoded.push('/'),
+            _ => encoded.push_str(&format!("%{:02X}", byte)),
+        }
+    }
+    encoded
+}
+
+/// Returns the hex-encoded SHA-256 digest of a payload
+pub fn sha256_hex(payload: &[u8]) -> String {
+    hex::encode(Sha256::digest(payload))
+}
+
+/// Builds the canonical query string: encoded and sorted by key, then value
+fn canonical_query(query: &[(&str, &str)]) -> String {
+    let mut pairs: Vec<(String, String)> = query
+        .iter()
+        .map(|(k, v)| (uri_encode(k, true), uri_encode(v, true)))
+        .collect();
+    pairs.sort();
+    pairs
+        .iter()
+        .map(|(k, v)| format!("{}={}", k, v))
+        .collect::<Vec<_>>()
+        .join("&")
+}
+
+/// Computes HMAC-SHA256 of a message
+fn hmac(key: &[u8], message: &[u8]) -> Vec<u8> {
+    let mut mac = Hmac::<Sha256>::new_from_slice(key).expect("HMAC accepts keys of any length");
+    mac.update(message);
+    mac.finalize().into_bytes().to_vec()
+}
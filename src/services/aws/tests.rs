@@ -0,0 +1,75 @@
+//! # AWS Client Tests
+//!
+//! Tests for request signing, using the `get-vanilla` case of the AWS
+//! Signature Version 4 test suite.
+
+use chrono::{TimeZone, Utc};
+
+use super::sigv4::{self, Request};
+use super::AwsCredentials;
+
+/// Credentials used by the AWS Signature Version 4 test suite
+fn test_credentials() -> AwsCredentials {
+    AwsCredentials {
+        access_key_id: "AKIDEXAMPLE".to_string(),
+        secret_access_key: "wJalrXUtnFEMI/K7MDENG+bPxRfiCYEXAMPLEKEY".to_string(),
+        session_token: None,
+        region: "us-east-1".to_string(),
+    }
+}
+
+#[test]
+fn sigv4_matches_aws_test_suite() {
+    let request = Request {
+        method: "GET",
+        host: "example.amazonaws.com",
+        path: "/",
+        query: &[],
+        headers: &[],
+        payload: b"",
+    };
+    let now = Utc.with_ymd_and_hms(2015, 8, 30, 12, 36, 0).unwrap();
+
+    let headers = sigv4::sign(&request, &test_credentials(), "service", now);
+
+    assert_eq!(
+        headers[0],
+        ("x-amz-date".to_string(), "20150830T123600Z".to_string())
+    );
+    assert_eq!(
+        headers[1].1,
+        "AWS4-HMAC-SHA256 Credential=AKIDEXAMPLE/20150830/us-east-1/service/aws4_request, \
+         SignedHeaders=host;x-amz-date, \
+         Signature=5fa00fa31553b73ebf1942676e86291e8372ff2a2260956d9b8aae1d763fbf31"
+    );
+}
+
+#[test]
+fn sigv4_signs_session_token() {
+    let mut credentials = test_credentials();
+    credentials.session_token = Some("token".to_string());
+    let request = Request {
+        method: "POST",
+        host: "email.us-east-1.amazonaws.com",
+        path: "/v2/email/outbound-emails",
+        query: &[],
+        headers: &[("content-type", "application/json")],
+        payload: b"{}",
+    };
+
+    let headers = sigv4::sign(&request, &credentials, "ses", Utc::now());
+    assert_eq!(
+        headers[1],
+        ("x-amz-security-token".to_string(), "token".to_string())
+    );
+    assert!(headers[2]
+        .1
+        .contains("SignedHeaders=content-type;host;x-amz-date;x-amz-security-token"));
+}
+
+#[test]
+fn uri_encode_keeps_unreserved_characters() {
+    assert_eq!(sigv4::uri_encode("a-b_c.d~e", true), "a-b_c.d~e");
+    assert_eq!(sigv4::uri_encode("a b/c", true), "a%20b%2Fc");
+    assert_eq!(sigv4::uri_encode("a b/c", false), "a%20b/c");
+}
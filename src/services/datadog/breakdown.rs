@@ -0,0 +1,263 @@
+//! # Breakdown Limits
+//!
+//! Every language, editor and model reported by GitHub becomes a tag value in
+//! Datadog. Large enterprises report hundreds of languages used by a single
+//! person, which multiplies the number of custom metrics without adding
+//! insight. This module provides `BreakdownLimits`, which folds such long-tail
+//! entries into a single `other` entry before series are created.
+//!
+//! The limits are configured with:
+//!
+//! - `MIN_LANGUAGE_ENGAGED_USERS`: languages with fewer engaged users are
+//!   aggregated into `other`
+//! - `TOP_N_BREAKDOWNS`: only the N entries with the most engaged users of
+//!   every language, editor and model breakdown are kept; the rest are
+//!   aggregated into `other`
+//!
+//! Counters of folded entries are summed, so totals across a breakdown stay
+//! unchanged. Nested breakdowns of folded entries (the models of an editor,
+//! the languages of a model) are merged by name, as in `models::aggregate`.
+//! Without any limit the metrics are sent as reported.
+
+use std::borrow::Cow;
+
+use tracing::debug;
+
+use crate::models::aggregate::{Absorb, Named};
+use crate::models::github::{CopilotMetrics, Editor, Language, Model};
+
+/// Name of the entry that folded entries are aggregated into
+pub const OTHER: &str = "other";
+
+/// An entry of a breakdown dimension (a language, editor or model)
+trait BreakdownEntry: Named + Absorb + Sized {
+    /// Number of engaged users, used to rank and threshold entries
+    fn engaged_users(&self) -> i64;
+
+    /// Creates an empty `other` entry
+    fn other() -> Self;
+}
+
+impl BreakdownEntry for Language {
+    fn engaged_users(&self) -> i64 {
+        self.total_engaged_users
+    }
+
+    fn other() -> Self {
+        Language {
+            name: OTHER.to_string(),
+            total_engaged_users: 0,
+            total_code_suggestions: None,
+            total_code_acceptances: None,
+            total_code_lines_suggested: None,
+            total_code_lines_accepted: None,
+            extra: Default::default(),
+        }
+    }
+}
+
+impl BreakdownEntry for Model {
+    fn engaged_users(&self) -> i64 {
+        self.total_engaged_users
+    }
+
+    fn other() -> Self {
+        Model {
+            name: OTHER.to_string(),
+            is_custom_model: false,
+            custom_model_training_date: None,
+            total_engaged_users: 0,
+            languages: None,
+            total_chats: None,
+            total_chat_insertion_events: None,
+            total_chat_copy_events: None,
+            total_pr_summaries_created: None,
+            extra: Default::default(),
+        }
+    }
+}
+
+impl BreakdownEntry for Editor {
+    fn engaged_users(&self) -> i64 {
+        self.total_engaged_users
+    }
+
+    fn other() -> Self {
+        Editor {
+            name: OTHER.to_string(),
+            total_engaged_users: 0,
+            models: None,
+            versions: None,
+            extra: Default::default(),
+        }
+    }
+}
+
+/// Limits applied to breakdown dimensions before series are created
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct BreakdownLimits {
+    /// Languages with fewer engaged users are folded into `other`
+    min_language_engaged_users: Option<i64>,
+    /// Entries ranked below the top N of a breakdown are folded into `other`
+    top_n: Option<usize>,
+}
+
+impl BreakdownLimits {
+    /// Creates limits with an explicit language threshold
+    ///
+    /// # Arguments
+    ///
+    /// * `min_language_engaged_users` - Minimum engaged users of a language to
+    ///   be reported on its own, or `None` for no threshold
+    pub fn new(min_language_engaged_users: Option<i64>) -> Self {
+        Self {
+            min_language_engaged_users: min_language_engaged_users.filter(|min| *min > 0),
+            top_n: None,
+        }
+    }
+
+    /// Keeps only the top N entries of every breakdown
+    ///
+    /// # Arguments
+    ///
+    /// * `top_n` - Number of entries kept per breakdown, or `None` for no limit
+    pub fn with_top_n(mut self, top_n: Option<usize>) -> Self {
+        self.top_n = top_n.filter(|n| *n > 0);
+        self
+    }
+
+    /// Returns whether any limit is configured
+    pub fn is_enabled(&self) -> bool {
+        self.min_language_engaged_users.is_some() || self.top_n.is_some()
+    }
+
+    /// Applies the limits to every breakdown of the given days
+    ///
+    /// # Arguments
+    ///
+    /// * `metrics` - The days about to be converted to series
+    ///
+    /// # Returns
+    ///
+    /// * `Cow<[CopilotMetrics]>` - The days unchanged if no limit is
+    ///   configured, otherwise copies with long-tail entries folded
+    pub fn apply<'a>(&self, metrics: &'a [CopilotMetrics]) -> Cow<'a, [CopilotMetrics]> {
+        if !self.is_enabled() {
+            return Cow::Borrowed(metrics);
+        }
+
+        let mut limited = metrics.to_vec();
+        for metric in &mut limited {
+            self.limit_metric(metric);
+        }
+        Cow::Owned(limited)
+    }
+
+    /// Folds the long-tail entries of every breakdown of a single day
+    fn limit_metric(&self, metric: &mut CopilotMetrics) {
+        if let Some(completions) = &mut metric.copilot_ide_code_completions {
+            self.limit_languages(&mut completions.languages);
+            self.limit_editors(&mut completions.editors);
+        }
+        if let Some(ide_chat) = &mut metric.copilot_ide_chat {
+            self.limit_editors(&mut ide_chat.editors);
+        }
+        if let Some(dotcom_chat) = &mut metric.copilot_dotcom_chat {
+            self.limit_models(&mut dotcom_chat.models);
+        }
+        if let Some(pull_requests) = &mut metric.copilot_dotcom_pull_requests {
+            for repository in pull_requests.repositories.iter_mut().flatten() {
+                let mut models = Some(std::mem::take(&mut repository.models));
+                self.limit_models(&mut models);
+                repository.models = models.unwrap_or_default();
+            }
+        }
+    }
+
+    fn limit_editors(&self, editors: &mut Option<Vec<Editor>>) {
+        if let (Some(editors), Some(n)) = (editors.as_mut(), self.top_n) {
+            fold_into_other(editors, |rank, _| rank < n);
+        }
+        for editor in editors.iter_mut().flatten() {
+            self.limit_models(&mut editor.models);
+        }
+    }
+
+    fn limit_models(&self, models: &mut Option<Vec<Model>>) {
+        if let (Some(models), Some(n)) = (models.as_mut(), self.top_n) {
+            fold_into_other(models, |rank, _| rank < n);
+        }
+        for model in models.iter_mut().flatten() {
+            self.limit_languages(&mut model.languages);
+        }
+    }
+
+    fn limit_languages(&self, languages: &mut Option<Vec<Language>>) {
+        if let Some(languages) = languages {
+            let min = self.min_language_engaged_users.unwrap_or(i64::MIN);
+            let top_n = self.top_n.unwrap_or(usize::MAX);
+            fold_into_other(languages, |rank, language| {
+                language.engaged_users() >= min && rank < top_n
+            });
+        }
+    }
+}
+
+/// Folds every entry that `keep` rejects into a single `other` entry
+///
+/// `keep` receives the entry's rank (0 for the most engaged users) and the
+/// entry. An existing entry named `other` is always folded. Entries keep their
+/// relative order; `other` is appended last.
+fn fold_into_other<T: BreakdownEntry>(entries: &mut Vec<T>, keep: impl Fn(usize, &T) -> bool) {
+    let mut ranked: Vec<usize> = (0..entries.len()).collect();
+    ranked.sort_by(|a, b| {
+        entries[*b]
+            .engaged_users()
+            .cmp(&entries[*a].engaged_users())
+            .then_with(|| entries[*a].name().cmp(entries[*b].name()))
+    });
+    let mut rank = vec![0; entries.len()];
+    for (position, index) in ranked.into_iter().enumerate() {
+        rank[index] = position;
+    }
+
+    let mut other: Option<T> = None;
+    let mut kept = Vec::with_capacity(entries.len());
+    for (index, entry) in entries.drain(..).enumerate() {
+        if entry.name() != OTHER && keep(rank[index], &entry) {
+            kept.push(entry);
+        } else {
+            other.get_or_insert_with(T::other).absorb(entry);
+        }
+    }
+
+    if let Some(other) = other {
+        debug!(
+            "Folded {} breakdown entries into {}",
+            rank.len() - kept.len(),
+            OTHER
+        );
+        kept.push(other);
+    }
+    *entries = kept;
+}
+
+/// Parses a limit that must be a positive whole number
+///
+/// # Arguments
+///
+/// * `value` - The limit, e.g. "10"
+///
+/// # Returns
+///
+/// * `Result<T, String>` - The limit, or a description of why the value is
+///   not a positive whole number
+pub fn parse_limit<T>(value: &str) -> std::result::Result<T, String>
+where
+    T: std::str::FromStr + PartialOrd + Default,
+{
+    match value.trim().parse::<T>() {
+        Ok(limit) if limit > T::default() => Ok(limit),
+        _ => Err(format!("expected a positive whole number, got {}", value)),
+    }
+}
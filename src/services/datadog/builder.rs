@@ -0,0 +1,197 @@
+//! # Datadog Client Builder
+//!
+//! `DatadogClient::new` and `DatadogClient::with_site` create a client with
+//! the neutral settings of `DatadogSettings::default()`, and the Lambda hands
+//! the settings parsed by `Config::from_env` to `DatadogClient::from_settings`.
+//! `DatadogClientBuilder` configures every aspect of the client explicitly;
+//! options that are not set keep their neutral defaults (no tag filter, no
+//! throttle, every fetched day, no breakdown limits, every repository, four
+//! chunks in flight). No option is read from the environment.
+//!
+//! ```
+//! use std::time::Duration;
+//! use ghrust::services::datadog::DatadogClient;
+//!
+//! let client = DatadogClient::builder()
+//!     .api_key("dd-api-key")
+//!     .site("datadoghq.com")
+//!     .timeout(Duration::from_secs(10))
+//!     .chunk_size(50)
+//!     .extra_tags(vec!["env:staging".to_string()])
+//!     .build()
+//!     .unwrap();
+//! ```
+
+use std::time::Duration;
+
+use super::breakdown::BreakdownLimits;
+use super::client::{series_url, DatadogClient, DEFAULT_SITE};
+use super::error::{DatadogError, Result};
+use super::languages::{LanguageGroups, LanguageNormalizer};
+use super::repos::RepositoryFilter;
+use super::settings::DatadogSettings;
+use super::tags::TagFilter;
+use super::throttle::Throttle;
+use super::window::DayWindow;
+
+/// Default number of series per submission request
+pub const DEFAULT_CHUNK_SIZE: usize = 100;
+
+/// Builder for a `DatadogClient`
+#[derive(Debug, Default)]
+pub struct DatadogClientBuilder {
+    api_key: Option<String>,
+    site: Option<String>,
+    api_url: Option<String>,
+    timeout: Option<Duration>,
+    chunk_size: Option<usize>,
+    extra_tags: Vec<String>,
+    settings: DatadogSettings,
+    language_groups: LanguageGroups,
+}
+
+impl DatadogClientBuilder {
+    /// Creates a builder with default settings
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the Datadog API key (required)
+    pub fn api_key(mut self, api_key: impl Into<String>) -> Self {
+        self.api_key = Some(api_key.into());
+        self
+    }
+
+    /// Sets the Datadog site, e.g. "datadoghq.com" (default: datadoghq.eu)
+    pub fn site(mut self, site: impl Into<String>) -> Self {
+        self.site = Some(site.into());
+        self
+    }
+
+    /// Overrides the series endpoint URL derived from the site, e.g. for a
+    /// proxy or a local mock server
+    pub fn api_url(mut self, api_url: impl Into<String>) -> Self {
+        self.api_url = Some(api_url.into());
+        self
+    }
+
+    /// Sets the overall timeout of each submission request (default: none)
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    /// Sets the number of series per submission request (default: 100)
+    pub fn chunk_size(mut self, chunk_size: usize) -> Self {
+        self.chunk_size = Some(chunk_size);
+        self
+    }
+
+    /// Sets the number of chunks submitted at the same time (default: 4)
+    pub fn concurrency(mut self, concurrency: usize) -> Self {
+        self.settings.concurrency = concurrency;
+        self
+    }
+
+    /// Sets whether unknown GitHub response fields are reported as a
+    /// `schema.unknown_fields` series (default: false)
+    pub fn report_schema_drift(mut self, report_schema_drift: bool) -> Self {
+        self.settings.report_schema_drift = report_schema_drift;
+        self
+    }
+
+    /// Sets tags added to every series the client sends
+    pub fn extra_tags(mut self, extra_tags: Vec<String>) -> Self {
+        self.extra_tags = extra_tags;
+        self
+    }
+
+    /// Sets all filters and limits at once, e.g. those of the configuration
+    pub fn settings(mut self, settings: DatadogSettings) -> Self {
+        self.settings = settings;
+        self
+    }
+
+    /// Sets the filter dropping series with excluded tag keys
+    pub fn tag_filter(mut self, tag_filter: TagFilter) -> Self {
+        self.settings.tag_filter = tag_filter;
+        self
+    }
+
+    /// Sets the pacing applied before every chunk is sent
+    pub fn throttle(mut self, throttle: Throttle) -> Self {
+        self.settings.throttle = throttle;
+        self
+    }
+
+    /// Sets the selection of the fetched days that are sent
+    pub fn day_window(mut self, day_window: DayWindow) -> Self {
+        self.settings.day_window = day_window;
+        self
+    }
+
+    /// Sets the folding of long-tail breakdown entries
+    pub fn breakdown_limits(mut self, breakdown_limits: BreakdownLimits) -> Self {
+        self.settings.breakdown_limits = breakdown_limits;
+        self
+    }
+
+    /// Sets the mapping of reported language names onto normalized ones
+    pub fn language_normalizer(mut self, language_normalizer: LanguageNormalizer) -> Self {
+        self.settings.language_normalizer = language_normalizer;
+        self
+    }
+
+    /// Sets the groups languages are additionally summed into
+    pub fn language_groups(mut self, language_groups: LanguageGroups) -> Self {
+        self.language_groups = language_groups;
+        self
+    }
+
+    /// Sets the repositories broken down into their own series
+    pub fn repository_filter(mut self, repository_filter: RepositoryFilter) -> Self {
+        self.settings.repository_filter = repository_filter;
+        self
+    }
+
+    /// Builds the client
+    ///
+    /// # Returns
+    ///
+    /// * `Result<DatadogClient>` - The client, or a `Config` error if the API
+    ///   key is missing or the chunk size or concurrency is zero
+    pub fn build(self) -> Result<DatadogClient> {
+        let api_key = self
+            .api_key
+            .filter(|key| !key.is_empty())
+            .ok_or_else(|| DatadogError::Config("an API key is required".to_string()))?;
+
+        let chunk_size = self.chunk_size.unwrap_or(DEFAULT_CHUNK_SIZE);
+        if chunk_size == 0 {
+            return Err(DatadogError::Config(
+                "the chunk size must be at least 1".to_string(),
+            ));
+        }
+
+        if self.settings.concurrency == 0 {
+            return Err(DatadogError::Config(
+                "the concurrency must be at least 1".to_string(),
+            ));
+        }
+
+        let site = self.site.unwrap_or_else(|| DEFAULT_SITE.to_string());
+        let api_url = self.api_url.unwrap_or_else(|| series_url(&site));
+
+        Ok(DatadogClient::from_parts(
+            api_key,
+            site,
+            api_url,
+            self.timeout,
+            chunk_size,
+            self.extra_tags,
+            &self.settings.agent,
+        )
+        .with_settings(self.settings)
+        .with_language_groups(self.language_groups))
+    }
+}
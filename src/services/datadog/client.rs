@@ -14,17 +14,51 @@
 //! The primary entry point is the `send_metrics` method, which takes a collection
 //! of GitHub Copilot metrics and sends them to Datadog with appropriate formatting.
 
+use super::breakdown::BreakdownLimits;
+use super::builder::{DatadogClientBuilder, DEFAULT_CHUNK_SIZE};
 use super::error::{DatadogError, Result};
-use super::models::{standard_tags, MetricPoint, MetricSeries};
+use super::failover::{Failover, FailoverEvent, FailoverState};
+use super::languages::{LanguageGroups, LanguageNormalizer};
+use super::models::{
+    append_tags, extend_tags, standard_tags, Gauge, MetricPoint, MetricSeries, Tags,
+};
+use super::names::MetricPrefix;
+use super::repos::RepositoryFilter;
+use super::settings::DatadogSettings;
+use super::stats::{SendCounters, SendStats};
+use super::tag_templates::{TagContext, TagTemplates};
+use super::tags::TagFilter;
+use super::throttle::Throttle;
+use super::window::DayWindow;
+use crate::models::billing::PremiumRequestUsage;
 use crate::models::github::{
-    CopilotDotcomChat, CopilotDotcomPullRequests, CopilotIdeChat, CopilotIdeCodeCompletions,
-    CopilotMetrics,
+    CopilotCodeReview, CopilotDotcomChat, CopilotDotcomPullRequests, CopilotIdeChat,
+    CopilotIdeCodeCompletions, CopilotMetrics, Editor, Language,
 };
+use crate::services::http_log;
+use crate::services::tls::AgentConfig;
 use serde_json::Value;
-use std::time::{SystemTime, UNIX_EPOCH};
-use tracing::info;
+use std::panic;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::thread;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tracing::{error, info, warn, Span};
+
+/// Default Datadog site (EU region)
+pub const DEFAULT_SITE: &str = "datadoghq.eu";
+
+/// Default number of chunks submitted at the same time
+pub const DEFAULT_SEND_CONCURRENCY: usize = 4;
+
+/// How often a chunk rejected with HTTP 429 is retried before giving up
+const MAX_RATE_LIMIT_RETRIES: u32 = 3;
 
-/// A Datadog client that uses the Datadog HTTP API to send metrics to EU region
+/// A Datadog client that uses the Datadog HTTP API to send metrics
+///
+/// By default the client talks to the EU region; other sites can be
+/// selected with [`DatadogClient::with_site`], and every option can be set
+/// without environment variables through [`DatadogClient::builder`].
 ///
 /// This client handles the whole process of sending metrics to Datadog:
 /// - Authentication via API key
@@ -35,8 +69,82 @@ use tracing::info;
 pub struct DatadogClient {
     /// Datadog API key for authentication
     api_key: String,
-    /// Datadog API endpoint URL (EU region)
+    /// Datadog site the client sends to (e.g. "datadoghq.eu")
+    site: String,
+    /// Datadog API endpoint URL
     api_url: String,
+    /// HTTP agent using the configured TLS settings
+    agent: ureq::Agent,
+    /// Whether request and response bodies are logged
+    log_bodies: bool,
+    /// Filter dropping series with excluded tag keys
+    tag_filter: TagFilter,
+    /// Tags with placeholders resolved against the scope of each send
+    tag_templates: TagTemplates,
+    /// Pacing of chunk submission
+    throttle: Throttle,
+    /// Fetched days that are sent
+    day_window: DayWindow,
+    /// Folding of long-tail breakdown entries
+    breakdown_limits: BreakdownLimits,
+    /// Mapping of reported language names onto normalized ones
+    language_normalizer: LanguageNormalizer,
+    /// Groups languages are additionally summed into
+    language_groups: LanguageGroups,
+    /// Repositories broken down into their own series
+    repository_filter: RepositoryFilter,
+    /// Number of series per submission request
+    chunk_size: usize,
+    /// Number of chunks submitted at the same time
+    concurrency: usize,
+    /// Whether unknown GitHub response fields are reported as a metric
+    report_schema_drift: bool,
+    /// Tags added to every series
+    extra_tags: Vec<String>,
+    /// Series and chunk counters for the end-of-run summary
+    counters: SendCounters,
+    /// Secondary site the client fails over to, if any
+    failover: Option<FailoverState>,
+    /// API key retried when Datadog rejects `api_key`, if any
+    secondary_api_key: Option<String>,
+    /// Whether Datadog rejected `api_key` and accepted the secondary key
+    secondary_key_active: AtomicBool,
+    /// Series queued for the end-of-run submission, if batching is enabled
+    batch: Option<Mutex<Vec<Value>>>,
+}
+
+/// Returns the API base URL of a Datadog site
+///
+/// A site given as a URL (starting with `http://` or `https://`) is used as
+/// the base URL, so metrics can be sent to a local mock server.
+pub(crate) fn api_base_url(site: &str) -> String {
+    if site.starts_with("http://") || site.starts_with("https://") {
+        site.trim_end_matches('/').to_string()
+    } else {
+        format!("https://api.{}", site)
+    }
+}
+
+/// Returns the series endpoint of a Datadog site
+pub(crate) fn series_url(site: &str) -> String {
+    format!("{}/api/v2/series", api_base_url(site))
+}
+
+/// Parses a number of chunks submitted at the same time
+///
+/// # Arguments
+///
+/// * `value` - The concurrency, e.g. "8"
+///
+/// # Returns
+///
+/// * `Result<usize, String>` - The concurrency, or a description of why the
+///   value is not a positive number
+pub fn parse_concurrency(value: &str) -> std::result::Result<usize, String> {
+    match value.trim().parse::<usize>() {
+        Ok(concurrency) if concurrency > 0 => Ok(concurrency),
+        _ => Err(format!("expected a positive number, got {}", value)),
+    }
 }
 
 impl DatadogClient {
@@ -52,8 +160,294 @@ impl DatadogClient {
     ///
     /// A new DatadogClient configured for the EU region API endpoint
     pub fn new(api_key: String) -> Self {
-        let api_url = "https://api.datadoghq.eu/api/v2/series".to_string();
-        Self { api_key, api_url }
+        Self::with_site(api_key, DEFAULT_SITE)
+    }
+
+    /// Create a new Datadog client for the given Datadog site
+    ///
+    /// # Arguments
+    ///
+    /// * `api_key` - Datadog API key for authentication
+    /// * `site` - Datadog site, e.g. "datadoghq.com", "datadoghq.eu" or "us5.datadoghq.com",
+    ///   or a base URL such as "http://localhost:8126" for a local mock server
+    ///
+    /// # Returns
+    ///
+    /// A new DatadogClient configured for the site's API endpoint, with the
+    /// neutral settings of `DatadogSettings::default()`
+    pub fn with_site(api_key: String, site: &str) -> Self {
+        Self::from_settings(api_key, site, DatadogSettings::default())
+    }
+
+    /// Create a new Datadog client for the given Datadog site with explicit
+    /// settings
+    ///
+    /// # Arguments
+    ///
+    /// * `api_key` - Datadog API key for authentication
+    /// * `site` - Datadog site, e.g. "datadoghq.com", or a base URL
+    /// * `settings` - Filters and limits applied to the sent series
+    ///
+    /// # Returns
+    ///
+    /// A new DatadogClient configured for the site's API endpoint
+    pub fn from_settings(api_key: String, site: &str, settings: DatadogSettings) -> Self {
+        let api_url = series_url(site);
+        Self::from_parts(
+            api_key,
+            site.to_string(),
+            api_url,
+            None,
+            DEFAULT_CHUNK_SIZE,
+            Vec::new(),
+            &settings.agent,
+        )
+        .with_settings(settings)
+    }
+
+    /// Fails over to a secondary site when the client's site keeps answering
+    /// with server errors
+    ///
+    /// # Arguments
+    ///
+    /// * `failover` - The secondary site, its API key and the threshold
+    pub fn with_failover(mut self, failover: Failover) -> Self {
+        self.failover = Some(FailoverState::new(failover));
+        self
+    }
+
+    /// Retries submissions rejected with HTTP 403 with a second API key
+    ///
+    /// Once the second key is accepted, the rest of the run uses it, so a key
+    /// can be rotated by configuring the new one as the secondary key before
+    /// revoking the old one.
+    ///
+    /// # Arguments
+    ///
+    /// * `api_key` - The secondary API key of the same organization
+    pub fn with_secondary_api_key(mut self, api_key: impl Into<String>) -> Self {
+        self.secondary_api_key = Some(api_key.into());
+        self
+    }
+
+    /// Queues every sent series instead of submitting it right away
+    ///
+    /// The queued series of all namespaces are submitted together by
+    /// [`DatadogClient::flush`], so chunks are filled across scopes instead of
+    /// each scope ending with a partial chunk.
+    pub fn with_batching(mut self) -> Self {
+        self.batch = Some(Mutex::new(Vec::new()));
+        self
+    }
+
+    /// Returns the failover to the secondary site, if the client failed over
+    pub fn failover_event(&self) -> Option<FailoverEvent> {
+        self.failover.as_ref().and_then(FailoverState::event)
+    }
+
+    /// Checks the client's API key against Datadog's validation endpoint
+    ///
+    /// # Returns
+    ///
+    /// * `Result<()>` - Ok if Datadog accepts the key, `HttpError(403, ..)` if
+    ///   it rejects it, or a network error
+    pub fn validate_api_key(&self) -> Result<()> {
+        let url = format!("{}/api/v1/validate", api_base_url(&self.site));
+        match self.agent.get(&url).set("DD-API-KEY", &self.api_key).call() {
+            Ok(_) => Ok(()),
+            Err(ureq::Error::Status(status, response)) => Err(DatadogError::HttpError(
+                status,
+                response
+                    .into_string()
+                    .unwrap_or_else(|_| "Could not read response body".to_string()),
+            )),
+            Err(ureq::Error::Transport(transport)) => {
+                Err(DatadogError::Network(transport.to_string()))
+            }
+        }
+    }
+
+    /// Returns a builder configuring the client without environment variables
+    ///
+    /// See [`DatadogClientBuilder`] for the available options.
+    pub fn builder() -> DatadogClientBuilder {
+        DatadogClientBuilder::new()
+    }
+
+    /// Creates a client with neutral filters and limits
+    ///
+    /// # Arguments
+    ///
+    /// * `api_key` - Datadog API key for authentication
+    /// * `site` - Datadog site the client sends to
+    /// * `api_url` - Series endpoint URL
+    /// * `timeout` - Overall timeout of each submission request, if any
+    /// * `chunk_size` - Number of series per submission request
+    /// * `extra_tags` - Tags added to every series
+    /// * `agent_config` - Settings of the HTTP agent
+    pub(crate) fn from_parts(
+        api_key: String,
+        site: String,
+        api_url: String,
+        timeout: Option<Duration>,
+        chunk_size: usize,
+        extra_tags: Vec<String>,
+        agent_config: &AgentConfig,
+    ) -> Self {
+        let mut agent = agent_config.agent_builder();
+        if let Some(timeout) = timeout {
+            agent = agent.timeout(timeout);
+        }
+
+        Self {
+            api_key,
+            site,
+            api_url,
+            agent: agent.build(),
+            log_bodies: agent_config.debug_log(),
+            tag_filter: TagFilter::default(),
+            tag_templates: TagTemplates::default(),
+            throttle: Throttle::default(),
+            day_window: DayWindow::default(),
+            breakdown_limits: BreakdownLimits::default(),
+            language_normalizer: LanguageNormalizer::default(),
+            language_groups: LanguageGroups::default(),
+            repository_filter: RepositoryFilter::default(),
+            chunk_size,
+            concurrency: DEFAULT_SEND_CONCURRENCY,
+            report_schema_drift: false,
+            extra_tags,
+            counters: SendCounters::default(),
+            failover: None,
+            secondary_api_key: None,
+            secondary_key_active: AtomicBool::new(false),
+            batch: None,
+        }
+    }
+
+    /// Replaces the client's filters and limits
+    ///
+    /// # Arguments
+    ///
+    /// * `settings` - Filters and limits applied to the sent series
+    pub fn with_settings(self, settings: DatadogSettings) -> Self {
+        self.with_concurrency(settings.concurrency)
+            .with_tag_filter(settings.tag_filter)
+            .with_tag_templates(settings.tag_templates)
+            .with_throttle(settings.throttle)
+            .with_day_window(settings.day_window)
+            .with_breakdown_limits(settings.breakdown_limits)
+            .with_language_normalizer(settings.language_normalizer)
+            .with_repository_filter(settings.repository_filter)
+            .with_schema_drift_reporting(settings.report_schema_drift)
+    }
+
+    /// Replaces whether unknown GitHub response fields are reported
+    ///
+    /// # Arguments
+    ///
+    /// * `report_schema_drift` - Whether a `schema.unknown_fields` series is
+    ///   sent for days with fields unknown to the models
+    pub fn with_schema_drift_reporting(mut self, report_schema_drift: bool) -> Self {
+        self.report_schema_drift = report_schema_drift;
+        self
+    }
+
+    /// Replaces the number of chunks submitted at the same time
+    ///
+    /// # Arguments
+    ///
+    /// * `concurrency` - Maximum number of chunk requests in flight; 1 sends
+    ///   the chunks one after another
+    pub fn with_concurrency(mut self, concurrency: usize) -> Self {
+        self.concurrency = concurrency.max(1);
+        self
+    }
+
+    /// Replaces the client's tag filter
+    ///
+    /// # Arguments
+    ///
+    /// * `tag_filter` - Filter dropping series with excluded tag keys
+    pub fn with_tag_filter(mut self, tag_filter: TagFilter) -> Self {
+        self.tag_filter = tag_filter;
+        self
+    }
+
+    /// Replaces the client's tag templates
+    ///
+    /// # Arguments
+    ///
+    /// * `tag_templates` - Tags with placeholders added to every series
+    pub fn with_tag_templates(mut self, tag_templates: TagTemplates) -> Self {
+        self.tag_templates = tag_templates;
+        self
+    }
+
+    /// Replaces the client's submission throttle
+    ///
+    /// # Arguments
+    ///
+    /// * `throttle` - Pacing applied before every chunk is sent
+    pub fn with_throttle(mut self, throttle: Throttle) -> Self {
+        self.throttle = throttle;
+        self
+    }
+
+    /// Replaces the client's day window
+    ///
+    /// # Arguments
+    ///
+    /// * `day_window` - Selection of the fetched days that are sent
+    pub fn with_day_window(mut self, day_window: DayWindow) -> Self {
+        self.day_window = day_window;
+        self
+    }
+
+    /// Replaces the client's breakdown limits
+    ///
+    /// # Arguments
+    ///
+    /// * `breakdown_limits` - Folding of long-tail breakdown entries
+    pub fn with_breakdown_limits(mut self, breakdown_limits: BreakdownLimits) -> Self {
+        self.breakdown_limits = breakdown_limits;
+        self
+    }
+
+    /// Replaces the client's language normalizer
+    ///
+    /// # Arguments
+    ///
+    /// * `language_normalizer` - Mapping of reported language names onto
+    ///   normalized ones
+    pub fn with_language_normalizer(mut self, language_normalizer: LanguageNormalizer) -> Self {
+        self.language_normalizer = language_normalizer;
+        self
+    }
+
+    /// Replaces the client's language groups
+    ///
+    /// # Arguments
+    ///
+    /// * `language_groups` - Groups languages are additionally summed into
+    pub fn with_language_groups(mut self, language_groups: LanguageGroups) -> Self {
+        self.language_groups = language_groups;
+        self
+    }
+
+    /// Replaces the client's repository filter
+    ///
+    /// # Arguments
+    ///
+    /// * `repository_filter` - Repositories broken down into their own series
+    pub fn with_repository_filter(mut self, repository_filter: RepositoryFilter) -> Self {
+        self.repository_filter = repository_filter;
+        self
+    }
+
+    /// Returns the series and chunk counters accumulated by this client
+    pub fn stats(&self) -> SendStats {
+        self.counters.snapshot()
     }
 
     /// Sends metrics to Datadog
@@ -64,12 +458,12 @@ impl DatadogClient {
     /// 2. Get current timestamp for the metrics
     /// 3. Format all metrics for Datadog
     /// 4. Send metrics in appropriate chunks
-    /// 5. Log completion status
     ///
     /// # Arguments
     ///
     /// * `metrics` - Array slice of GitHub Copilot metrics to send
     /// * `namespace` - Metric namespace (prefix for all metrics)
+    /// * `context` - Scope the tag templates are resolved against
     ///
     /// # Returns
     ///
@@ -78,7 +472,37 @@ impl DatadogClient {
     /// # Environment Variables
     ///
     /// * `MOCK_GITHUB_API` - If set, skips actual transmission (for testing)
-    pub fn send_metrics(&self, metrics: &[CopilotMetrics], namespace: &str) -> Result<()> {
+    pub fn send_metrics(
+        &self,
+        metrics: &[CopilotMetrics],
+        namespace: &str,
+        context: &TagContext,
+    ) -> Result<()> {
+        self.send_metrics_with_tags(metrics, namespace, context, &[])
+    }
+
+    /// Sends Copilot metrics to Datadog with additional tags on every series
+    ///
+    /// Behaves like `send_metrics`, but appends `extra_tags` to the tags of
+    /// every prepared series, e.g. the tags configured for a team.
+    ///
+    /// # Arguments
+    ///
+    /// * `metrics` - Array slice of GitHub Copilot metrics to send
+    /// * `namespace` - Metric namespace (prefix for all metrics)
+    /// * `context` - Scope the tag templates are resolved against
+    /// * `extra_tags` - Tags added to every series
+    ///
+    /// # Returns
+    ///
+    /// * `Result<()>` - Success (Ok) or error with details
+    pub fn send_metrics_with_tags(
+        &self,
+        metrics: &[CopilotMetrics],
+        namespace: &str,
+        context: &TagContext,
+        extra_tags: &[String],
+    ) -> Result<()> {
         info!(
             "Sending {} metrics to Datadog for namespace {}",
             metrics.len(),
@@ -91,41 +515,284 @@ impl DatadogClient {
             return Ok(());
         }
 
+        let metrics = self.day_window.select(metrics);
+        if metrics.is_empty() {
+            info!("No selected day to send for namespace {}", namespace);
+            return Ok(());
+        }
+
         let timestamp = self.current_timestamp()?;
-        let all_series = self.prepare_all_metrics(metrics, namespace, timestamp);
+        let mut all_series = self.prepare_all_metrics(&metrics, namespace, timestamp);
+        append_tags(&mut all_series, extra_tags);
         info!("Prepared {} series for Datadog", all_series.len());
 
-        // Send metrics in chunks to avoid oversized requests
-        for (i, chunk) in all_series.chunks(100).enumerate() {
-            info!("Sending chunk {} ({} series)", i + 1, chunk.len());
-            self.send_metrics_chunk(chunk)?;
+        self.send_series(all_series, context)
+    }
+
+    /// Sends a premium request usage report to Datadog
+    ///
+    /// Emits per-model premium request counts, amounts and multipliers under
+    /// `{namespace}.premium_requests.*`.
+    ///
+    /// # Arguments
+    ///
+    /// * `usage` - The premium request usage report to send
+    /// * `namespace` - Metric namespace (prefix for all metrics)
+    /// * `context` - Scope the tag templates are resolved against
+    /// * `extra_tags` - Tags added to every series
+    ///
+    /// # Returns
+    ///
+    /// * `Result<()>` - Success (Ok) or error with details
+    ///
+    /// # Environment Variables
+    ///
+    /// * `MOCK_GITHUB_API` - If set, skips actual transmission (for testing)
+    pub fn send_premium_request_usage(
+        &self,
+        usage: &PremiumRequestUsage,
+        namespace: &str,
+        context: &TagContext,
+        extra_tags: &[String],
+    ) -> Result<()> {
+        info!(
+            "Sending {} premium request usage items to Datadog for namespace {}",
+            usage.usage_items.len(),
+            namespace
+        );
+
+        // Skip in test mode
+        if std::env::var("MOCK_GITHUB_API").is_ok() {
+            info!("Test mode: Skipping sending metrics to Datadog");
+            return Ok(());
         }
 
-        info!("Successfully sent all metrics to Datadog EU API");
-        self.log_completion_status(namespace);
+        let timestamp = self.current_timestamp()?;
+        let mut all_series = self.prepare_premium_request_metrics(usage, namespace, timestamp);
+        append_tags(&mut all_series, extra_tags);
+        info!("Prepared {} series for Datadog", all_series.len());
+
+        self.send_series(all_series, context)
+    }
+
+    /// Sends gauges computed by a processor to Datadog
+    ///
+    /// # Arguments
+    ///
+    /// * `gauges` - The gauges to send
+    /// * `namespace` - Metric namespace (prefix for all metrics)
+    /// * `context` - Scope the tag templates are resolved against
+    ///
+    /// # Returns
+    ///
+    /// * `Result<()>` - Success (Ok) or error with details
+    ///
+    /// # Environment Variables
+    ///
+    /// * `MOCK_GITHUB_API` - If set, skips actual transmission (for testing)
+    pub fn send_gauges(
+        &self,
+        gauges: &[Gauge],
+        namespace: &str,
+        context: &TagContext,
+    ) -> Result<()> {
+        info!(
+            "Sending {} gauges to Datadog for namespace {}",
+            gauges.len(),
+            namespace
+        );
+
+        // Skip in test mode
+        if std::env::var("MOCK_GITHUB_API").is_ok() {
+            info!("Test mode: Skipping sending metrics to Datadog");
+            return Ok(());
+        }
 
-        Ok(())
+        let timestamp = self.current_timestamp()?;
+        let all_series = self.prepare_gauges(gauges, namespace, timestamp);
+        self.send_series(all_series, context)
     }
 
-    /// Logs completion status message for observability
+    /// Submits the series queued by a batching client in one chunked pass
+    ///
+    /// Does nothing if batching isn't enabled or nothing is queued.
+    ///
+    /// # Returns
     ///
-    /// Prints information about the completed metrics transmission to help
-    /// with debugging and verification. The message differs based on whether
-    /// the metrics are enterprise-wide or team-specific.
+    /// * `Result<()>` - Success (Ok), or a `ChunksFailed` error describing the
+    ///   chunks that still failed after the retry
+    pub fn flush(&self) -> Result<()> {
+        let Some(batch) = &self.batch else {
+            return Ok(());
+        };
+        let all_series = std::mem::take(&mut *batch.lock().unwrap_or_else(|e| e.into_inner()));
+        if all_series.is_empty() {
+            return Ok(());
+        }
+
+        info!(
+            "Sending {} batched series to Datadog ({})",
+            all_series.len(),
+            self.site
+        );
+        self.send_chunks(&all_series)
+    }
+
+    /// Tags prepared series and sends them, or queues them if batching is
+    /// enabled
     ///
     /// # Arguments
     ///
-    /// * `namespace` - The namespace used for the metrics, which includes
-    ///   information about whether this is enterprise or team metrics
-    fn log_completion_status(&self, namespace: &str) {
-        if !namespace.contains(".team.") {
-            println!("ENTERPRISE METRICS CALL: Next should be team metrics. If you don't see team metrics logs, there's an issue");
-        } else {
-            println!(
-                "TEAM METRICS CALL for team: {}",
-                namespace.split(".team.").last().unwrap_or("unknown")
-            );
+    /// * `all_series` - JSON Values representing metrics to send; the
+    ///   client's tag templates resolved against `context` and its extra tags
+    ///   are appended to each
+    /// * `context` - Scope the tag templates are resolved against
+    ///
+    /// # Returns
+    ///
+    /// * `Result<()>` - Success (Ok), or a `ChunksFailed` error describing the
+    ///   chunks that still failed after the retry
+    fn send_series(&self, mut all_series: Vec<Value>, context: &TagContext) -> Result<()> {
+        append_tags(&mut all_series, &self.tag_templates.resolve(context));
+        append_tags(&mut all_series, &self.extra_tags);
+        self.counters.prepared(all_series.len());
+
+        if let Some(batch) = &self.batch {
+            info!("Queued {} series for the batched send", all_series.len());
+            batch
+                .lock()
+                .unwrap_or_else(|e| e.into_inner())
+                .extend(all_series);
+            return Ok(());
+        }
+        self.send_chunks(&all_series)
+    }
+
+    /// Sends prepared series to Datadog in chunks
+    ///
+    /// Splits the series into chunks (100 series by default) to avoid
+    /// oversized requests and sends up to the client's concurrency (4 by
+    /// default) of them at the same time. Chunks are paced by the client's
+    /// throttle, and a chunk rejected with HTTP 429 is retried after the delay
+    /// Datadog asks for. A chunk that fails otherwise doesn't stop the send:
+    /// the remaining chunks are sent first, then every failed chunk is retried
+    /// once.
+    ///
+    /// # Arguments
+    ///
+    /// * `all_series` - Tagged JSON Values representing metrics to send
+    ///
+    /// # Returns
+    ///
+    /// * `Result<()>` - Success (Ok), or a `ChunksFailed` error describing the
+    ///   chunks that still failed after the retry
+    fn send_chunks(&self, all_series: &[Value]) -> Result<()> {
+        // Send metrics in chunks to avoid oversized requests
+        let chunks: Vec<&[Value]> = all_series.chunks(self.chunk_size).collect();
+        let all: Vec<usize> = (0..chunks.len()).collect();
+        let mut failed = Vec::new();
+        for (i, outcome) in self.send_chunk_indices(&chunks, &all, "Sending") {
+            let chunk = chunks[i];
+            match outcome {
+                Ok(()) => self.counters.chunk_sent(chunk.len()),
+                Err(e) => {
+                    warn!(
+                        "Chunk {} of {} failed, retrying after the remaining chunks: {}",
+                        i + 1,
+                        chunks.len(),
+                        e
+                    );
+                    failed.push(i);
+                }
+            }
         }
+
+        // Retry the failed chunks once, now that the others are delivered
+        let mut last_error = None;
+        let mut series_failed = 0;
+        let mut chunks_failed = 0;
+        for (i, outcome) in self.send_chunk_indices(&chunks, &failed, "Retrying") {
+            let chunk = chunks[i];
+            match outcome {
+                Ok(()) => self.counters.chunk_sent(chunk.len()),
+                Err(e) => {
+                    error!("Chunk {} failed again: {}", i + 1, e);
+                    self.counters.chunk_failed(chunk.len());
+                    chunks_failed += 1;
+                    series_failed += chunk.len();
+                    last_error = Some(e);
+                }
+            }
+        }
+
+        match last_error {
+            None => {
+                info!("Successfully sent all metrics to Datadog ({})", self.site);
+                Ok(())
+            }
+            Some(last) => Err(DatadogError::ChunksFailed {
+                failed: chunks_failed,
+                total: chunks.len(),
+                series: series_failed,
+                last: Box::new(last),
+            }),
+        }
+    }
+
+    /// Sends the chunks at the given indices, up to the client's concurrency
+    /// at a time
+    ///
+    /// # Arguments
+    ///
+    /// * `chunks` - All chunks of the send
+    /// * `indices` - Indices of the chunks to send
+    /// * `action` - Verb logged for every chunk, e.g. "Sending"
+    ///
+    /// # Returns
+    ///
+    /// * `Vec<(usize, Result<()>)>` - The outcome of every chunk, ordered by
+    ///   chunk index
+    fn send_chunk_indices(
+        &self,
+        chunks: &[&[Value]],
+        indices: &[usize],
+        action: &str,
+    ) -> Vec<(usize, Result<()>)> {
+        let send = |i: usize| {
+            info!("{} chunk {} ({} series)", action, i + 1, chunks[i].len());
+            (i, self.send_chunk_with_backoff(chunks[i]))
+        };
+
+        let workers = self.concurrency.min(indices.len());
+        if workers <= 1 {
+            return indices.iter().map(|&i| send(i)).collect();
+        }
+
+        // Workers take the next unsent chunk until none is left; the span
+        // keeps their logs attributed to the scope being sent
+        let next = AtomicUsize::new(0);
+        let span = Span::current();
+        let mut outcomes: Vec<(usize, Result<()>)> = thread::scope(|scope| {
+            let handles: Vec<_> = (0..workers)
+                .map(|_| {
+                    scope.spawn(|| {
+                        span.in_scope(|| {
+                            let mut outcomes = Vec::new();
+                            while let Some(&i) = indices.get(next.fetch_add(1, Ordering::Relaxed)) {
+                                outcomes.push(send(i));
+                            }
+                            outcomes
+                        })
+                    })
+                })
+                .collect();
+            handles
+                .into_iter()
+                .flat_map(|handle| handle.join().unwrap_or_else(|e| panic::resume_unwind(e)))
+                .collect()
+        });
+        outcomes.sort_by_key(|(i, _)| *i);
+        outcomes
     }
 
     /// Get current Unix timestamp
@@ -150,11 +817,21 @@ impl DatadogClient {
     /// Prepares all metrics to be sent to Datadog
     ///
     /// Converts GitHub Copilot metrics to Datadog's format by:
+    /// - Reporting unknown response fields (only when schema drift reporting
+    ///   is enabled)
     /// - Adding core metrics (active and engaged users)
     /// - Processing IDE code completions metrics
     /// - Processing IDE chat metrics
     /// - Processing GitHub.com chat metrics
     /// - Processing GitHub.com pull request metrics
+    /// - Processing Copilot code review metrics
+    /// - Dropping series with tag keys excluded by the tag filter
+    ///
+    /// Long-tail breakdown entries are folded into `other` by the breakdown
+    /// limits before any series is created.
+    ///
+    /// Metric names are built with `MetricPrefix`, so a namespace that Datadog
+    /// would reject is normalized before sending.
     ///
     /// # Arguments
     ///
@@ -165,28 +842,44 @@ impl DatadogClient {
     /// # Returns
     ///
     /// Vector of JSON Values representing the metrics in Datadog's format
-    fn prepare_all_metrics(
+    pub(crate) fn prepare_all_metrics(
         &self,
         metrics: &[CopilotMetrics],
         namespace: &str,
         timestamp: i64,
     ) -> Vec<Value> {
         let mut all_series = MetricSeries::new();
+        let names = MetricPrefix::new(namespace);
 
-        for metric in metrics {
+        let normalized = self.language_normalizer.apply(metrics);
+        let metrics = self.breakdown_limits.apply(&normalized);
+        for metric in metrics.iter() {
             let date = &metric.date;
-            let base_tags = standard_tags(date);
+            let base_tags: Tags = standard_tags(date).into();
+
+            // Report response shape drift as a metric in schema warn mode
+            if self.report_schema_drift {
+                let unknown_fields = metric.unknown_field_paths();
+                if !unknown_fields.is_empty() {
+                    all_series.add_point(MetricPoint::new(
+                        names.metric("schema.unknown_fields"),
+                        unknown_fields.len() as f64,
+                        timestamp,
+                        base_tags.clone(),
+                    ));
+                }
+            }
 
             // Add core metrics (active and engaged users)
             all_series.add_point(MetricPoint::new(
-                format!("{}.total_active_users", namespace),
+                names.metric("total_active_users"),
                 metric.total_active_users.unwrap_or(0) as f64,
                 timestamp,
                 base_tags.clone(),
             ));
 
             all_series.add_point(MetricPoint::new(
-                format!("{}.total_engaged_users", namespace),
+                names.metric("total_engaged_users"),
                 metric.total_engaged_users.unwrap_or(0) as f64,
                 timestamp,
                 base_tags.clone(),
@@ -220,11 +913,119 @@ impl DatadogClient {
                     self.prepare_dotcom_pr_metrics(dotcom_pr, namespace, date, timestamp);
                 self.merge_series(&mut all_series, &mut subseries);
             }
+
+            if let Some(ref code_review) = metric.copilot_code_review {
+                let mut subseries =
+                    self.prepare_code_review_metrics(code_review, namespace, date, timestamp);
+                self.merge_series(&mut all_series, &mut subseries);
+            }
         }
 
+        self.tag_filter.apply(&mut all_series);
         all_series.to_json()
     }
 
+    /// Prepares premium request usage metrics to be sent to Datadog
+    ///
+    /// Converts a premium request usage report to Datadog's format. Each usage
+    /// item produces gross/net quantity and amount series (plus the model
+    /// multiplier when reported), tagged with the model, SKU and billing period.
+    ///
+    /// # Arguments
+    ///
+    /// * `usage` - The premium request usage report to convert
+    /// * `namespace` - Base namespace for the metrics
+    /// * `timestamp` - Unix timestamp to use for all metrics
+    ///
+    /// # Returns
+    ///
+    /// Vector of JSON Values representing the metrics in Datadog's format
+    pub(crate) fn prepare_premium_request_metrics(
+        &self,
+        usage: &PremiumRequestUsage,
+        namespace: &str,
+        timestamp: i64,
+    ) -> Vec<Value> {
+        let mut series = MetricSeries::new();
+        let prefix = MetricPrefix::new(namespace).child("premium_requests");
+        let period = usage.time_period.label();
+        let mut base_tags = standard_tags(&period);
+        base_tags.push(format!("period:{}", period));
+
+        for item in &usage.usage_items {
+            let item_tags = extend_tags(
+                &base_tags,
+                [
+                    format!("model:{}", item.model.as_deref().unwrap_or("unknown")),
+                    format!("sku:{}", item.sku),
+                    format!("product:{}", item.product),
+                ],
+            );
+
+            for (name, value) in [
+                ("gross_quantity", item.gross_quantity),
+                ("net_quantity", item.net_quantity),
+                ("discount_quantity", item.discount_quantity),
+                ("gross_amount", item.gross_amount),
+                ("net_amount", item.net_amount),
+            ] {
+                series.add_point(MetricPoint::new(
+                    prefix.metric(name),
+                    value,
+                    timestamp,
+                    item_tags.clone(),
+                ));
+            }
+
+            if let Some(multiplier) = item.multiplier {
+                series.add_point(MetricPoint::new(
+                    prefix.metric("multiplier"),
+                    multiplier,
+                    timestamp,
+                    item_tags,
+                ));
+            }
+        }
+
+        self.tag_filter.apply(&mut series);
+        series.to_json()
+    }
+
+    /// Converts gauges to Datadog's format
+    ///
+    /// # Arguments
+    ///
+    /// * `gauges` - The gauges to convert
+    /// * `namespace` - Base namespace for the metrics
+    /// * `timestamp` - Unix timestamp to use for all metrics
+    ///
+    /// # Returns
+    ///
+    /// Vector of JSON Values representing the metrics in Datadog's format
+    pub(crate) fn prepare_gauges(
+        &self,
+        gauges: &[Gauge],
+        namespace: &str,
+        timestamp: i64,
+    ) -> Vec<Value> {
+        let mut series = MetricSeries::new();
+        let prefix = MetricPrefix::new(namespace);
+
+        for gauge in gauges {
+            let mut tags = standard_tags(&gauge.date);
+            tags.extend(gauge.tags.iter().cloned());
+            series.add_point(MetricPoint::new(
+                prefix.metric(&gauge.name),
+                gauge.value,
+                timestamp,
+                tags,
+            ));
+        }
+
+        self.tag_filter.apply(&mut series);
+        series.to_json()
+    }
+
     /// Merge one series into another
     ///
     /// Transfers all points from the source series into the target series.
@@ -241,6 +1042,49 @@ impl DatadogClient {
         }
     }
 
+    /// Sends a chunk of metrics, waiting for the throttle and retrying when
+    /// Datadog rate limits the request
+    ///
+    /// A rate limit resetting later than the throttle's maximum wait fails the
+    /// chunk right away rather than sleeping through the invocation.
+    ///
+    /// # Arguments
+    ///
+    /// * `series` - Array slice of JSON Values representing metrics to send
+    ///
+    /// # Returns
+    ///
+    /// * `Result<()>` - Success (Ok) or the error of the last attempt
+    fn send_chunk_with_backoff(&self, series: &[Value]) -> Result<()> {
+        let mut attempt = 0;
+
+        loop {
+            self.throttle.wait(series.len());
+
+            match self.send_metrics_chunk(series) {
+                Err(DatadogError::RateLimited(retry_after))
+                    if Duration::from_secs(retry_after) > self.throttle.max_rate_limit_wait() =>
+                {
+                    let max_wait = self.throttle.max_rate_limit_wait().as_secs();
+                    warn!(
+                        "Datadog rate limit resets in {}s, longer than the allowed {}s; failing the chunk",
+                        retry_after, max_wait
+                    );
+                    return Err(DatadogError::RateLimitWaitExceeded(retry_after, max_wait));
+                }
+                Err(DatadogError::RateLimited(retry_after)) if attempt < MAX_RATE_LIMIT_RETRIES => {
+                    attempt += 1;
+                    warn!(
+                        "Datadog rate limit hit; retrying chunk in {}s (attempt {}/{})",
+                        retry_after, attempt, MAX_RATE_LIMIT_RETRIES
+                    );
+                    std::thread::sleep(Duration::from_secs(retry_after));
+                }
+                result => return result,
+            }
+        }
+    }
+
     /// Sends a chunk of metrics to Datadog
     ///
     /// Transmits a batch of metrics to Datadog's API via HTTP POST.
@@ -258,21 +1102,88 @@ impl DatadogClient {
     ///
     /// Returns an error if the HTTP request fails or Datadog returns an error response
     fn send_metrics_chunk(&self, series: &[Value]) -> Result<()> {
+        if let Some((api_url, api_key)) = self.failover.as_ref().and_then(FailoverState::active) {
+            return self.post_chunk(api_url, api_key, series);
+        }
+
+        let result = self.post_chunk_with_keys(series);
+        if let Some(failover) = &self.failover {
+            failover.record(&self.site, &result);
+        }
+        result
+    }
+
+    /// Posts a chunk of metrics to the client's site, retrying a rejected
+    /// API key with the secondary key
+    ///
+    /// # Arguments
+    ///
+    /// * `series` - Array slice of JSON Values representing metrics to send
+    fn post_chunk_with_keys(&self, series: &[Value]) -> Result<()> {
+        let Some(secondary_key) = &self.secondary_api_key else {
+            return self.post_chunk(&self.api_url, &self.api_key, series);
+        };
+        if self.secondary_key_active.load(Ordering::Relaxed) {
+            return self.post_chunk(&self.api_url, secondary_key, series);
+        }
+
+        match self.post_chunk(&self.api_url, &self.api_key, series) {
+            Err(DatadogError::HttpError(403, _)) => {
+                warn!(
+                    "Datadog {} rejected the API key, retrying with the secondary key",
+                    self.site
+                );
+                let result = self.post_chunk(&self.api_url, secondary_key, series);
+                if result.is_ok() && !self.secondary_key_active.swap(true, Ordering::Relaxed) {
+                    warn!(
+                        "Using the secondary API key for {} for the rest of the run",
+                        self.site
+                    );
+                }
+                result
+            }
+            result => result,
+        }
+    }
+
+    /// Posts a chunk of metrics to a series endpoint
+    ///
+    /// # Arguments
+    ///
+    /// * `api_url` - The series endpoint
+    /// * `api_key` - API key of the endpoint's organization
+    /// * `series` - Array slice of JSON Values representing metrics to send
+    fn post_chunk(&self, api_url: &str, api_key: &str, series: &[Value]) -> Result<()> {
         info!("Sending chunk with {} series", series.len());
 
         let request_body = serde_json::json!({ "series": series });
+        if self.log_bodies {
+            http_log::log_body("request", api_url, &request_body.to_string());
+        }
 
-        match ureq::post(&self.api_url)
+        match self
+            .agent
+            .post(api_url)
             .set("Content-Type", "application/json")
-            .set("DD-API-KEY", &self.api_key)
+            .set("DD-API-KEY", api_key)
             .send_json(request_body)
         {
             Ok(_) => Ok(()),
             Err(e) => match e {
+                ureq::Error::Status(429, response) => {
+                    let retry_after = response
+                        .header("X-RateLimit-Reset")
+                        .and_then(|v| v.trim().parse::<u64>().ok())
+                        .unwrap_or(1);
+                    Err(DatadogError::RateLimited(retry_after))
+                }
                 ureq::Error::Status(status, response) => {
                     let body = response
                         .into_string()
                         .unwrap_or_else(|_| "Could not read response body".to_string());
+                    if self.log_bodies {
+                        http_log::log_body("response", api_url, &body);
+                    }
                     Err(DatadogError::HttpError(status, body))
                 }
                 ureq::Error::Transport(transport) => {
@@ -308,12 +1219,12 @@ impl DatadogClient {
         timestamp: i64,
     ) -> MetricSeries {
         let mut series = MetricSeries::new();
-        let prefix = format!("{}.ide.code_completions", namespace);
-        let base_tags = standard_tags(date);
+        let prefix = MetricPrefix::new(namespace).child("ide.code_completions");
+        let base_tags: Tags = standard_tags(date).into();
 
         // Add total engaged users
         series.add_point(MetricPoint::new(
-            format!("{}.total_engaged_users", prefix),
+            prefix.metric("total_engaged_users"),
             completions.total_engaged_users as f64,
             timestamp,
             base_tags.clone(),
@@ -321,47 +1232,31 @@ impl DatadogClient {
 
         // Process languages
         if let Some(languages) = &completions.languages {
+            let languages_prefix = prefix.child("languages");
             for language in languages {
-                let lang_name = &language.name;
-                let mut lang_tags = base_tags.clone();
-                lang_tags.push(format!("language:{}", lang_name));
-
-                // Add engaged users
-                series.add_point(MetricPoint::new(
-                    format!("{}.languages.total_engaged_users", prefix),
-                    language.total_engaged_users as f64,
-                    timestamp,
-                    lang_tags.clone(),
-                ));
-
-                // Add optional metrics
-                series.add_optional_i64_point(
-                    format!("{}.languages.total_code_suggestions", prefix),
-                    language.total_code_suggestions,
-                    timestamp,
-                    &lang_tags,
-                );
-
-                series.add_optional_i64_point(
-                    format!("{}.languages.total_code_acceptances", prefix),
-                    language.total_code_acceptances,
-                    timestamp,
-                    &lang_tags,
-                );
-
-                series.add_optional_i64_point(
-                    format!("{}.languages.total_code_lines_suggested", prefix),
-                    language.total_code_lines_suggested,
+                let lang_tags: Tags = base_tags
+                    .iter()
+                    .cloned()
+                    .chain(self.language_normalizer.tags(&language.name))
+                    .collect();
+                add_language_points(
+                    &mut series,
+                    &languages_prefix,
+                    language,
                     timestamp,
                     &lang_tags,
                 );
+            }
 
-                series.add_optional_i64_point(
-                    format!("{}.languages.total_code_lines_accepted", prefix),
-                    language.total_code_lines_accepted,
-                    timestamp,
-                    &lang_tags,
-                );
+            // Sum the languages into the configured groups
+            let groups_prefix = prefix.child("language_groups");
+            for group in self
+                .language_groups
+                .sum(languages, &self.language_normalizer)
+            {
+                let group_tags =
+                    extend_tags(&base_tags, [format!("language_group:{}", group.name)]);
+                add_language_points(&mut series, &groups_prefix, &group, timestamp, &group_tags);
             }
         }
 
@@ -369,15 +1264,15 @@ impl DatadogClient {
         if let Some(editors) = &completions.editors {
             for editor in editors {
                 let editor_name = &editor.name;
-                let mut editor_tags = base_tags.clone();
-                editor_tags.push(format!("editor:{}", editor_name));
+                let editor_tags = extend_tags(&base_tags, [format!("editor:{}", editor_name)]);
 
                 series.add_point(MetricPoint::new(
-                    format!("{}.editors.total_engaged_users", prefix),
+                    prefix.metric("editors.total_engaged_users"),
                     editor.total_engaged_users as f64,
                     timestamp,
                     editor_tags.clone(),
                 ));
+                add_editor_versions(&mut series, &prefix, editor, timestamp, &editor_tags);
             }
         }
 
@@ -415,12 +1310,12 @@ impl DatadogClient {
         timestamp: i64,
     ) -> MetricSeries {
         let mut series = MetricSeries::new();
-        let prefix = format!("{}.ide.chat", namespace);
-        let base_tags = standard_tags(date);
+        let prefix = MetricPrefix::new(namespace).child("ide.chat");
+        let base_tags: Tags = standard_tags(date).into();
 
         // Add total engaged users
         series.add_point(MetricPoint::new(
-            format!("{}.total_engaged_users", prefix),
+            prefix.metric("total_engaged_users"),
             ide_chat.total_engaged_users as f64,
             timestamp,
             base_tags.clone(),
@@ -434,15 +1329,15 @@ impl DatadogClient {
         if let Some(editors) = &ide_chat.editors {
             for editor in editors {
                 let editor_name = &editor.name;
-                let mut editor_tags = base_tags.clone();
-                editor_tags.push(format!("editor:{}", editor_name));
+                let editor_tags = extend_tags(&base_tags, [format!("editor:{}", editor_name)]);
 
                 series.add_point(MetricPoint::new(
-                    format!("{}.editors.total_engaged_users", prefix),
+                    prefix.metric("editors.total_engaged_users"),
                     editor.total_engaged_users as f64,
                     timestamp,
                     editor_tags.clone(),
                 ));
+                add_editor_versions(&mut series, &prefix, editor, timestamp, &editor_tags);
 
                 // Process models if present
                 if let Some(models) = &editor.models {
@@ -454,12 +1349,16 @@ impl DatadogClient {
                             "false"
                         };
 
-                        let mut model_tags = editor_tags.clone();
-                        model_tags.push(format!("model:{}", model_name));
-                        model_tags.push(format!("is_custom_model:{}", is_custom));
+                        let model_tags = extend_tags(
+                            &editor_tags,
+                            [
+                                format!("model:{}", model_name),
+                                format!("is_custom_model:{}", is_custom),
+                            ],
+                        );
 
                         series.add_point(MetricPoint::new(
-                            format!("{}.editors.models.total_engaged_users", prefix),
+                            prefix.metric("editors.models.total_engaged_users"),
                             model.total_engaged_users as f64,
                             timestamp,
                             model_tags.clone(),
@@ -467,7 +1366,7 @@ impl DatadogClient {
 
                         // Add PR summaries if present
                         series.add_optional_i64_point(
-                            format!("{}.editors.models.total_pr_summaries_created", prefix),
+                            prefix.metric("editors.models.total_pr_summaries_created"),
                             model.total_pr_summaries_created,
                             timestamp,
                             &model_tags,
@@ -479,25 +1378,23 @@ impl DatadogClient {
 
         // Add P7S1 specific metrics if environment variable exists
         if let Ok(p7s1_namespace) = std::env::var("DATADOG_NAMESPACE_P7S1") {
+            let p7s1 = MetricPrefix::new(&p7s1_namespace).child("copilot_ide_chat");
             series.add_point(MetricPoint::new(
-                format!("{}.copilot_ide_chat.total_chats", p7s1_namespace),
+                p7s1.metric("total_chats"),
                 total_chats as f64,
                 timestamp,
                 base_tags.clone(),
             ));
 
             series.add_point(MetricPoint::new(
-                format!("{}.copilot_ide_chat.total_chat_copy_events", p7s1_namespace),
+                p7s1.metric("total_chat_copy_events"),
                 total_copies as f64,
                 timestamp,
                 base_tags.clone(),
             ));
 
             series.add_point(MetricPoint::new(
-                format!(
-                    "{}.copilot_ide_chat.total_chat_insertion_events",
-                    p7s1_namespace
-                ),
+                p7s1.metric("total_chat_insertion_events"),
                 total_insertions as f64,
                 timestamp,
                 base_tags,
@@ -570,12 +1467,12 @@ impl DatadogClient {
         timestamp: i64,
     ) -> MetricSeries {
         let mut series = MetricSeries::new();
-        let prefix = format!("{}.dotcom.chat", namespace);
-        let base_tags = standard_tags(date);
+        let prefix = MetricPrefix::new(namespace).child("dotcom.chat");
+        let base_tags: Tags = standard_tags(date).into();
 
         // Add total engaged users
         series.add_point(MetricPoint::new(
-            format!("{}.total_engaged_users", prefix),
+            prefix.metric("total_engaged_users"),
             chat.total_engaged_users as f64,
             timestamp,
             base_tags.clone(),
@@ -591,19 +1488,23 @@ impl DatadogClient {
                     "false"
                 };
 
-                let mut model_tags = base_tags.clone();
-                model_tags.push(format!("model:{}", model_name));
-                model_tags.push(format!("is_custom_model:{}", is_custom));
+                let model_tags = extend_tags(
+                    &base_tags,
+                    [
+                        format!("model:{}", model_name),
+                        format!("is_custom_model:{}", is_custom),
+                    ],
+                );
 
                 series.add_point(MetricPoint::new(
-                    format!("{}.models.total_engaged_users", prefix),
+                    prefix.metric("models.total_engaged_users"),
                     model.total_engaged_users as f64,
                     timestamp,
                     model_tags.clone(),
                 ));
 
                 series.add_optional_i64_point(
-                    format!("{}.models.total_chats", prefix),
+                    prefix.metric("models.total_chats"),
                     model.total_chats,
                     timestamp,
                     &model_tags,
@@ -640,12 +1541,12 @@ impl DatadogClient {
         timestamp: i64,
     ) -> MetricSeries {
         let mut series = MetricSeries::new();
-        let prefix = format!("{}.dotcom.pull_requests", namespace);
-        let base_tags = standard_tags(date);
+        let prefix = MetricPrefix::new(namespace).child("dotcom.pull_requests");
+        let base_tags: Tags = standard_tags(date).into();
 
         // Add total engaged users
         series.add_point(MetricPoint::new(
-            format!("{}.total_engaged_users", prefix),
+            prefix.metric("total_engaged_users"),
             pr.total_engaged_users as f64,
             timestamp,
             base_tags.clone(),
@@ -653,13 +1554,15 @@ impl DatadogClient {
 
         // Add repository metrics if repositories are available
         if let Some(repositories) = &pr.repositories {
-            for repo in repositories {
-                let repo_name = &repo.name;
-                let mut repo_tags = base_tags.clone();
-                repo_tags.push(format!("repository:{}", repo_name));
+            for repo in repositories
+                .iter()
+                .filter(|r| self.repository_filter.keeps(&r.name))
+            {
+                let repo_name = self.repository_filter.tag_value(&repo.name);
+                let repo_tags = extend_tags(&base_tags, [format!("repository:{}", repo_name)]);
 
                 series.add_point(MetricPoint::new(
-                    format!("{}.repositories.total_engaged_users", prefix),
+                    prefix.metric("repositories.total_engaged_users"),
                     repo.total_engaged_users as f64,
                     timestamp,
                     repo_tags.clone(),
@@ -673,19 +1576,23 @@ impl DatadogClient {
                         "false"
                     };
 
-                    let mut model_tags = repo_tags.clone();
-                    model_tags.push(format!("model:{}", model_name));
-                    model_tags.push(format!("is_custom_model:{}", is_custom));
+                    let model_tags = extend_tags(
+                        &repo_tags,
+                        [
+                            format!("model:{}", model_name),
+                            format!("is_custom_model:{}", is_custom),
+                        ],
+                    );
 
                     series.add_point(MetricPoint::new(
-                        format!("{}.repositories.models.total_engaged_users", prefix),
+                        prefix.metric("repositories.models.total_engaged_users"),
                         model.total_engaged_users as f64,
                         timestamp,
                         model_tags.clone(),
                     ));
 
                     series.add_optional_i64_point(
-                        format!("{}.repositories.models.total_pr_summaries_created", prefix),
+                        prefix.metric("repositories.models.total_pr_summaries_created"),
                         model.total_pr_summaries_created,
                         timestamp,
                         &model_tags,
@@ -696,4 +1603,180 @@ impl DatadogClient {
 
         series
     }
+
+    /// Prepare metrics for Copilot code review
+    ///
+    /// Converts Copilot code review metrics from GitHub's format to Datadog's format.
+    /// This includes:
+    /// - Total engaged users, reviews, comments and applied comments
+    /// - Repository-specific metrics
+    ///
+    /// # Arguments
+    ///
+    /// * `review` - The Copilot code review metrics to convert
+    /// * `namespace` - Base namespace for the metrics
+    /// * `date` - Date string for tagging
+    /// * `timestamp` - Unix timestamp for the metrics
+    ///
+    /// # Returns
+    ///
+    /// A MetricSeries containing all the processed code review metrics
+    fn prepare_code_review_metrics(
+        &self,
+        review: &CopilotCodeReview,
+        namespace: &str,
+        date: &str,
+        timestamp: i64,
+    ) -> MetricSeries {
+        let mut series = MetricSeries::new();
+        let prefix = MetricPrefix::new(namespace).child("code_review");
+        let base_tags: Tags = standard_tags(date).into();
+
+        // Add totals
+        series.add_point(MetricPoint::new(
+            prefix.metric("total_engaged_users"),
+            review.total_engaged_users as f64,
+            timestamp,
+            base_tags.clone(),
+        ));
+
+        series.add_optional_i64_point(
+            prefix.metric("total_reviews"),
+            review.total_reviews,
+            timestamp,
+            &base_tags,
+        );
+
+        series.add_optional_i64_point(
+            prefix.metric("total_review_comments"),
+            review.total_review_comments,
+            timestamp,
+            &base_tags,
+        );
+
+        series.add_optional_i64_point(
+            prefix.metric("total_review_comments_applied"),
+            review.total_review_comments_applied,
+            timestamp,
+            &base_tags,
+        );
+
+        // Add repository metrics if repositories are available
+        if let Some(repositories) = &review.repositories {
+            for repo in repositories
+                .iter()
+                .filter(|r| self.repository_filter.keeps(&r.name))
+            {
+                let repo_name = self.repository_filter.tag_value(&repo.name);
+                let repo_tags = extend_tags(&base_tags, [format!("repository:{}", repo_name)]);
+
+                series.add_point(MetricPoint::new(
+                    prefix.metric("repositories.total_engaged_users"),
+                    repo.total_engaged_users as f64,
+                    timestamp,
+                    repo_tags.clone(),
+                ));
+
+                series.add_optional_i64_point(
+                    prefix.metric("repositories.total_reviews"),
+                    repo.total_reviews,
+                    timestamp,
+                    &repo_tags,
+                );
+
+                series.add_optional_i64_point(
+                    prefix.metric("repositories.total_review_comments"),
+                    repo.total_review_comments,
+                    timestamp,
+                    &repo_tags,
+                );
+
+                series.add_optional_i64_point(
+                    prefix.metric("repositories.total_review_comments_applied"),
+                    repo.total_review_comments_applied,
+                    timestamp,
+                    &repo_tags,
+                );
+            }
+        }
+
+        series
+    }
+}
+
+/// Adds the engaged users of every client version of an editor, tagged
+/// `editor_version:{version}`, if GitHub broke the editor down by version
+///
+/// # Arguments
+///
+/// * `series` - Series the points are added to
+/// * `prefix` - Prefix of the editor metrics (e.g. `{namespace}.ide.chat`)
+/// * `editor` - The editor
+/// * `timestamp` - Unix timestamp for the metrics
+/// * `editor_tags` - Tags of the editor's series
+fn add_editor_versions(
+    series: &mut MetricSeries,
+    prefix: &MetricPrefix,
+    editor: &Editor,
+    timestamp: i64,
+    editor_tags: &[String],
+) {
+    for version in editor.versions.iter().flatten() {
+        series.add_point(MetricPoint::new(
+            prefix.metric("editors.versions.total_engaged_users"),
+            version.total_engaged_users as f64,
+            timestamp,
+            extend_tags(editor_tags, [format!("editor_version:{}", version.version)]),
+        ));
+    }
+}
+
+/// Adds the engaged users and completion counters of a language (or a group
+/// of languages)
+///
+/// # Arguments
+///
+/// * `series` - Series the points are added to
+/// * `prefix` - Prefix of the language metrics (e.g.
+///   `{namespace}.ide.code_completions.languages`)
+/// * `language` - The language
+/// * `timestamp` - Unix timestamp for the metrics
+/// * `tags` - Tags of the language's series
+fn add_language_points(
+    series: &mut MetricSeries,
+    prefix: &MetricPrefix,
+    language: &Language,
+    timestamp: i64,
+    tags: &Tags,
+) {
+    series.add_point(MetricPoint::new(
+        prefix.metric("total_engaged_users"),
+        language.total_engaged_users as f64,
+        timestamp,
+        tags.clone(),
+    ));
+    series.add_optional_i64_point(
+        prefix.metric("total_code_suggestions"),
+        language.total_code_suggestions,
+        timestamp,
+        tags,
+    );
+    series.add_optional_i64_point(
+        prefix.metric("total_code_acceptances"),
+        language.total_code_acceptances,
+        timestamp,
+        tags,
+    );
+    series.add_optional_i64_point(
+        prefix.metric("total_code_lines_suggested"),
+        language.total_code_lines_suggested,
+        timestamp,
+        tags,
+    );
+    series.add_optional_i64_point(
+        prefix.metric("total_code_lines_accepted"),
+        language.total_code_lines_accepted,
+        timestamp,
+        tags,
+    );
 }
@@ -0,0 +1,251 @@
+//! # Dashboard Provisioning
+//!
+//! Dashboards built by hand drift from the metric names the collector sends:
+//! a renamed namespace or metric leaves widgets querying series that no longer
+//! arrive. This module builds the standard Copilot dashboard for a namespace
+//! from the same `MetricPrefix` the client uses to name its series, and
+//! creates or updates it through Datadog's dashboards API.
+//!
+//! Dashboards are matched by title, so running the provisioning again updates
+//! the existing dashboard in place; manual edits to a provisioned dashboard are
+//! overwritten. The dashboards API requires an application key in addition to
+//! the API key.
+
+use serde_json::{json, Value};
+use tracing::info;
+
+use super::client::api_base_url;
+use super::error::{DatadogError, Result};
+use super::names::MetricPrefix;
+use crate::services::tls::AgentConfig;
+
+/// Number of entries shown by the top list widgets
+const TOP_LIST_SIZE: usize = 10;
+
+/// Returns the title of the dashboard provisioned for a namespace
+///
+/// # Arguments
+///
+/// * `namespace` - The metric namespace shown on the dashboard
+pub fn dashboard_title(namespace: &str) -> String {
+    format!("GitHub Copilot ({})", namespace)
+}
+
+/// Builds the standard Copilot dashboard for a namespace
+///
+/// # Arguments
+///
+/// * `namespace` - The metric namespace the widgets query
+/// * `title` - Title of the dashboard
+///
+/// # Returns
+///
+/// * `Value` - The dashboard definition in the format of the v1 dashboards API
+pub fn dashboard_definition(namespace: &str, title: &str) -> Value {
+    let names = MetricPrefix::new(namespace);
+    let completions = names.child("ide.code_completions");
+    let ide_chat = names.child("ide.chat");
+
+    let engaged = |prefix: &str| names.child(prefix).metric("total_engaged_users");
+    let acceptance_rate = format!(
+        "100 * sum:{}{{*}} / sum:{}{{*}}",
+        completions.metric("languages.total_code_acceptances"),
+        completions.metric("languages.total_code_suggestions")
+    );
+
+    let widgets = vec![
+        timeseries(
+            "Active and engaged users",
+            &[
+                names.metric("total_active_users"),
+                names.metric("total_engaged_users"),
+            ],
+        ),
+        timeseries(
+            "Engaged users by feature",
+            &[
+                engaged("ide.code_completions"),
+                engaged("ide.chat"),
+                engaged("dotcom.chat"),
+                engaged("dotcom.pull_requests"),
+                engaged("code_review"),
+            ],
+        ),
+        widget(json!({
+            "type": "query_value",
+            "title": "Code completion acceptance rate (%)",
+            "precision": 1,
+            "requests": [{ "q": acceptance_rate, "aggregator": "last" }],
+        })),
+        top_list(
+            "Accepted completions by language",
+            &completions.metric("languages.total_code_acceptances"),
+            "language",
+        ),
+        top_list(
+            "Code completion users by editor",
+            &completions.metric("editors.total_engaged_users"),
+            "editor",
+        ),
+        top_list(
+            "IDE chat users by model",
+            &ide_chat.metric("editors.models.total_engaged_users"),
+            "model",
+        ),
+    ];
+
+    json!({
+        "title": title,
+        "description": format!(
+            "Provisioned by ghrust for the `{}` namespace; manual changes are overwritten.",
+            namespace
+        ),
+        "layout_type": "ordered",
+        "widgets": widgets,
+    })
+}
+
+/// Wraps a widget definition
+fn widget(definition: Value) -> Value {
+    json!({ "definition": definition })
+}
+
+/// A line chart with one line per metric
+fn timeseries(title: &str, metrics: &[String]) -> Value {
+    let requests: Vec<Value> = metrics
+        .iter()
+        .map(|metric| json!({ "q": format!("max:{}{{*}}", metric), "display_type": "line" }))
+        .collect();
+
+    widget(json!({
+        "type": "timeseries",
+        "title": title,
+        "requests": requests,
+    }))
+}
+
+/// A top list of a metric summed by a tag
+fn top_list(title: &str, metric: &str, tag: &str) -> Value {
+    widget(json!({
+        "type": "toplist",
+        "title": title,
+        "requests": [{
+            "q": format!(
+                "top(sum:{}{{*}} by {{{}}}, {}, 'sum', 'desc')",
+                metric, tag, TOP_LIST_SIZE
+            ),
+        }],
+    }))
+}
+
+/// Creates and updates dashboards through Datadog's dashboards API
+pub struct DashboardProvisioner {
+    /// Datadog API key
+    api_key: String,
+    /// Datadog application key, required by the dashboards API
+    app_key: String,
+    /// Dashboards endpoint URL
+    dashboards_url: String,
+    /// HTTP agent using the configured TLS settings
+    agent: ureq::Agent,
+}
+
+impl DashboardProvisioner {
+    /// Creates a provisioner for a Datadog site
+    ///
+    /// # Arguments
+    ///
+    /// * `api_key` - Datadog API key
+    /// * `app_key` - Datadog application key
+    /// * `site` - Datadog site, e.g. "datadoghq.eu", or a base URL
+    /// * `agent` - Settings of the HTTP agent
+    pub fn new(
+        api_key: impl Into<String>,
+        app_key: impl Into<String>,
+        site: &str,
+        agent: &AgentConfig,
+    ) -> Self {
+        Self {
+            api_key: api_key.into(),
+            app_key: app_key.into(),
+            dashboards_url: format!("{}/api/v1/dashboard", api_base_url(site)),
+            agent: agent.agent_builder().build(),
+        }
+    }
+
+    /// Creates the standard dashboard for a namespace, or updates it if a
+    /// dashboard with the same title exists
+    ///
+    /// # Arguments
+    ///
+    /// * `namespace` - The metric namespace the widgets query
+    ///
+    /// # Returns
+    ///
+    /// * `Result<String>` - The ID of the created or updated dashboard
+    pub fn provision(&self, namespace: &str) -> Result<String> {
+        let title = dashboard_title(namespace);
+        let definition = dashboard_definition(namespace, &title);
+
+        let response = match self.find(&title)? {
+            Some(id) => {
+                info!("Updating Datadog dashboard {} ({})", title, id);
+                self.request("PUT", &format!("{}/{}", self.dashboards_url, id))
+                    .send_json(definition)
+            }
+            None => {
+                info!("Creating Datadog dashboard {}", title);
+                self.request("POST", &self.dashboards_url)
+                    .send_json(definition)
+            }
+        }
+        .map_err(request_error)?;
+
+        let body: Value = response
+            .into_json()
+            .map_err(|e| DatadogError::Network(e.to_string()))?;
+        body["id"]
+            .as_str()
+            .map(str::to_string)
+            .ok_or_else(|| DatadogError::HttpError(200, "response has no dashboard id".to_string()))
+    }
+
+    /// Looks up the ID of the dashboard with the given title
+    fn find(&self, title: &str) -> Result<Option<String>> {
+        let body: Value = self
+            .request("GET", &self.dashboards_url)
+            .call()
+            .map_err(request_error)?
+            .into_json()
+            .map_err(|e| DatadogError::Network(e.to_string()))?;
+
+        Ok(body["dashboards"]
+            .as_array()
+            .into_iter()
+            .flatten()
+            .find(|d| d["title"].as_str() == Some(title))
+            .and_then(|d| d["id"].as_str())
+            .map(str::to_string))
+    }
+
+    /// Builds an authenticated request
+    fn request(&self, method: &str, url: &str) -> ureq::Request {
+        self.agent
+            .request(method, url)
+            .set("DD-API-KEY", &self.api_key)
+            .set("DD-APPLICATION-KEY", &self.app_key)
+    }
+}
+
+/// Converts a failed dashboards API request into a `DatadogError`
+fn request_error(e: ureq::Error) -> DatadogError {
+    match e {
+        ureq::Error::Status(status, response) => {
+            let body = response
+                .into_string()
+                .unwrap_or_else(|_| "Could not read response body".to_string());
+            DatadogError::HttpError(status, body)
+        }
+        ureq::Error::Transport(transport) => DatadogError::Network(transport.to_string()),
+    }
+}
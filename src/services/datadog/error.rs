@@ -21,6 +21,10 @@ pub enum DatadogError {
     #[error("Time error: {0}")]
     TimeError(String),
 
+    /// The client is misconfigured
+    #[error("Datadog client configuration error: {0}")]
+    Config(String),
+
     /// Network or transport error
     #[error("Network error: {0}")]
     Network(String),
@@ -28,6 +32,34 @@ pub enum DatadogError {
     /// HTTP request error with status code
     #[error("HTTP error {0}: {1}")]
     HttpError(u16, String),
+
+    /// Datadog rejected the request with HTTP 429; retry after the given seconds
+    #[error("Rate limited by Datadog, retry after {0}s")]
+    RateLimited(u64),
+
+    /// Datadog asked to wait longer than the configured maximum before
+    /// retrying a rate-limited request
+    #[error("Rate limited by Datadog for {0}s, longer than the allowed {1}s")]
+    RateLimitWaitExceeded(u64, u64),
+
+    /// Some chunks could not be delivered, even after retrying them at the end
+    /// of the send; the other chunks were delivered
+    #[error("{failed} of {total} chunks ({series} series) could not be delivered: {last}")]
+    ChunksFailed {
+        /// Chunks that could not be delivered
+        failed: usize,
+        /// Chunks of the send
+        total: usize,
+        /// Series in the undelivered chunks
+        series: usize,
+        /// Error of the last failed attempt
+        #[source]
+        last: Box<DatadogError>,
+    },
+
+    /// Sending failed for one or more fan-out destinations
+    #[error("Failed to send to Datadog destination(s): {}", .0.join("; "))]
+    Destinations(Vec<String>),
 }
 
 /// A specialized Result type for Datadog operations
@@ -35,3 +67,17 @@ pub enum DatadogError {
 /// This type alias is used throughout the Datadog client for consistent
 /// error handling and to avoid repeating the error type.
 pub type Result<T> = std::result::Result<T, DatadogError>;
+
+impl DatadogError {
+    /// Returns whether sending again later may succeed
+    ///
+    /// Network errors, rate limits and server errors (5xx) are transient.
+    pub fn is_retryable(&self) -> bool {
+        match self {
+            Self::Network(_) | Self::RateLimited(_) => true,
+            Self::HttpError(status, _) => *status >= 500,
+            Self::ChunksFailed { last, .. } => last.is_retryable(),
+            _ => false,
+        }
+    }
+}
@@ -0,0 +1,148 @@
+//! # Multi-region Failover
+//!
+//! A destination can name a secondary Datadog site (with its own API key) that
+//! the client fails over to when the primary intake keeps answering with
+//! server errors. After `threshold` consecutive 5xx responses from the primary
+//! site, every later chunk of the run goes to the secondary site; chunks that
+//! failed on the way are delivered there by the client's end-of-send retry.
+//!
+//! Failing over is recorded as a `FailoverEvent` and reported with the run, so
+//! dashboards reading the primary site can be checked for the gap. A new run
+//! starts on the primary site again.
+
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::Mutex;
+
+use serde::Serialize;
+use tracing::{error, warn};
+
+use super::client::series_url;
+use super::error::{DatadogError, Result};
+
+/// Consecutive 5xx responses after which a client fails over by default
+pub const DEFAULT_FAILOVER_THRESHOLD: u32 = 3;
+
+/// Secondary Datadog site a destination fails over to
+#[derive(Debug, Clone, PartialEq)]
+pub struct Failover {
+    /// Datadog site, e.g. "datadoghq.com", or a base URL
+    pub site: String,
+    /// API key for the organization on the secondary site
+    pub api_key: String,
+    /// Consecutive 5xx responses of the primary site that trigger the failover
+    pub threshold: u32,
+}
+
+impl Failover {
+    /// Creates a failover to a site after the default number of server errors
+    ///
+    /// # Arguments
+    ///
+    /// * `site` - The secondary Datadog site
+    /// * `api_key` - API key for the organization on the secondary site
+    pub fn new(site: impl Into<String>, api_key: impl Into<String>) -> Self {
+        Self {
+            site: site.into(),
+            api_key: api_key.into(),
+            threshold: DEFAULT_FAILOVER_THRESHOLD,
+        }
+    }
+}
+
+/// A client switching from its primary to its secondary site
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct FailoverEvent {
+    /// Name of the destination that failed over, if known
+    #[serde(skip_serializing_if = "String::is_empty")]
+    pub destination: String,
+    /// The primary site
+    pub from_site: String,
+    /// The secondary site every later chunk was sent to
+    pub to_site: String,
+    /// The last error of the primary site
+    pub reason: String,
+    /// Unix time of the failover
+    pub timestamp: i64,
+}
+
+/// Failover configuration of a client with its state for the current run
+#[derive(Debug)]
+pub(crate) struct FailoverState {
+    /// The secondary site
+    failover: Failover,
+    /// Series endpoint of the secondary site
+    api_url: String,
+    /// Consecutive 5xx responses of the primary site
+    server_errors: AtomicU32,
+    /// The failover, once it happened
+    event: Mutex<Option<FailoverEvent>>,
+}
+
+impl FailoverState {
+    /// Creates the state of a client that hasn't failed over yet
+    pub(crate) fn new(failover: Failover) -> Self {
+        Self {
+            api_url: series_url(&failover.site),
+            failover,
+            server_errors: AtomicU32::new(0),
+            event: Mutex::new(None),
+        }
+    }
+
+    /// Returns the series endpoint and API key of the secondary site if the
+    /// client has failed over
+    pub(crate) fn active(&self) -> Option<(&str, &str)> {
+        self.event()
+            .map(|_| (self.api_url.as_str(), self.failover.api_key.as_str()))
+    }
+
+    /// Returns the failover event, if the client has failed over
+    pub(crate) fn event(&self) -> Option<FailoverEvent> {
+        self.event.lock().unwrap_or_else(|e| e.into_inner()).clone()
+    }
+
+    /// Records the result of a request to the primary site, failing over
+    /// once the threshold of consecutive server errors is reached
+    ///
+    /// A success resets the count; other errors (rate limits, network
+    /// errors) leave it unchanged.
+    ///
+    /// # Arguments
+    ///
+    /// * `primary_site` - The client's primary site
+    /// * `result` - Result of the request
+    pub(crate) fn record(&self, primary_site: &str, result: &Result<()>) {
+        let message = match result {
+            Ok(()) => {
+                self.server_errors.store(0, Ordering::Relaxed);
+                return;
+            }
+            Err(e @ DatadogError::HttpError(500..=599, _)) => e.to_string(),
+            Err(_) => return,
+        };
+
+        let errors = self.server_errors.fetch_add(1, Ordering::Relaxed) + 1;
+        if errors < self.failover.threshold {
+            warn!(
+                "Datadog {} server error {}/{} before failing over to {}",
+                primary_site, errors, self.failover.threshold, self.failover.site
+            );
+            return;
+        }
+
+        let mut event = self.event.lock().unwrap_or_else(|e| e.into_inner());
+        if event.is_none() {
+            error!(
+                "Datadog {} failed {} times in a row, failing over to {}",
+                primary_site, errors, self.failover.site
+            );
+            *event = Some(FailoverEvent {
+                destination: String::new(),
+                from_site: primary_site.to_string(),
+                to_site: self.failover.site.clone(),
+                reason: message,
+                timestamp: chrono::Utc::now().timestamp(),
+            });
+        }
+    }
+}
@@ -0,0 +1,275 @@
+//! # Language Normalization
+//!
+//! GitHub reports languages by editor language ID, so one stack shows up
+//! under several names: `typescriptreact` next to `typescript`,
+//! `javascriptreact` next to `javascript`, `vue-html` next to `vue`. This
+//! module provides `LanguageNormalizer`, which maps such names onto one
+//! language before series are created.
+//!
+//! The built-in aliases (`BUILTIN_ALIASES`) are extended or overridden with
+//! `LANGUAGE_ALIASES` (`typescriptreact=typescript,svelte=frontend`). Entries
+//! that map onto the same language are merged, summing their counters as in
+//! `models::aggregate`. With `LANGUAGE_RAW_TAG` set, they are kept apart
+//! instead and their series carry the reported name as `raw_language:{name}`
+//! next to `language:{normalized}`.
+//!
+//! `LanguageGroups` sums the normalized languages into configured groups
+//! (e.g. "jvm", "frontend", "infra"), which are reported as additional
+//! series tagged `language_group:{group}`.
+
+use std::borrow::Cow;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+
+use crate::models::aggregate::{merge_by_name, Absorb};
+use crate::models::github::{CopilotMetrics, Editor, Language};
+
+/// Language aliases applied unless overridden, as (reported, normalized)
+pub const BUILTIN_ALIASES: &[(&str, &str)] = &[
+    ("typescriptreact", "typescript"),
+    ("javascriptreact", "javascript"),
+    ("vue-html", "vue"),
+    ("jsonc", "json"),
+    ("shellscript", "shell"),
+];
+
+/// Maps reported language names onto normalized ones
+#[derive(Debug, Clone, PartialEq)]
+pub struct LanguageNormalizer {
+    /// Normalized name keyed by reported name
+    aliases: HashMap<String, String>,
+    /// Whether the reported name is kept as a `raw_language` tag
+    raw_tag: bool,
+}
+
+impl Default for LanguageNormalizer {
+    fn default() -> Self {
+        Self::new(HashMap::new())
+    }
+}
+
+impl LanguageNormalizer {
+    /// Creates a normalizer with the built-in aliases and custom ones
+    ///
+    /// # Arguments
+    ///
+    /// * `custom` - Aliases keyed by reported name; they override built-in
+    ///   aliases of the same name
+    pub fn new(custom: HashMap<String, String>) -> Self {
+        let mut aliases: HashMap<String, String> = BUILTIN_ALIASES
+            .iter()
+            .map(|(raw, normalized)| (raw.to_string(), normalized.to_string()))
+            .collect();
+        aliases.extend(custom);
+        aliases.retain(|raw, normalized| raw != normalized);
+        Self {
+            aliases,
+            raw_tag: false,
+        }
+    }
+
+    /// Keeps the reported name of aliased languages as a `raw_language` tag
+    ///
+    /// # Arguments
+    ///
+    /// * `raw_tag` - Whether aliased languages keep their own series, tagged
+    ///   with the reported name
+    pub fn with_raw_tag(mut self, raw_tag: bool) -> Self {
+        self.raw_tag = raw_tag;
+        self
+    }
+
+    /// Returns the normalized name of a language
+    pub fn normalize<'a>(&'a self, name: &'a str) -> &'a str {
+        self.aliases.get(name).map_or(name, String::as_str)
+    }
+
+    /// Returns the tags of a language's series
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - The language name as it appears in the (applied) metrics
+    ///
+    /// # Returns
+    ///
+    /// * `Vec<String>` - `language:{normalized}`, followed by
+    ///   `raw_language:{name}` if the raw tag is enabled and the name is
+    ///   an alias
+    pub fn tags(&self, name: &str) -> Vec<String> {
+        let normalized = self.normalize(name);
+        let mut tags = vec![format!("language:{}", normalized)];
+        if self.raw_tag && normalized != name {
+            tags.push(format!("raw_language:{}", name));
+        }
+        tags
+    }
+
+    /// Renames and merges the aliased languages of every breakdown
+    ///
+    /// With the raw tag enabled, the days are returned unchanged, since
+    /// aliased languages keep their own series and are renamed in `tags`.
+    ///
+    /// # Arguments
+    ///
+    /// * `metrics` - The days about to be converted to series
+    ///
+    /// # Returns
+    ///
+    /// * `Cow<[CopilotMetrics]>` - The days unchanged if no reported language
+    ///   is aliased, otherwise copies with aliased languages merged
+    pub fn apply<'a>(&self, metrics: &'a [CopilotMetrics]) -> Cow<'a, [CopilotMetrics]> {
+        if self.raw_tag || !metrics.iter().any(|m| self.has_alias(m)) {
+            return Cow::Borrowed(metrics);
+        }
+
+        let mut normalized = metrics.to_vec();
+        for metric in &mut normalized {
+            if let Some(completions) = &mut metric.copilot_ide_code_completions {
+                self.merge_languages(&mut completions.languages);
+                self.merge_editors(&mut completions.editors);
+            }
+            if let Some(ide_chat) = &mut metric.copilot_ide_chat {
+                self.merge_editors(&mut ide_chat.editors);
+            }
+        }
+        Cow::Owned(normalized)
+    }
+
+    /// Returns whether any language reported on a day is aliased
+    fn has_alias(&self, metric: &CopilotMetrics) -> bool {
+        let is_aliased = |languages: &Option<Vec<Language>>| {
+            languages
+                .iter()
+                .flatten()
+                .any(|language| self.aliases.contains_key(&language.name))
+        };
+        let editors_aliased = |editors: &Option<Vec<Editor>>| {
+            editors
+                .iter()
+                .flatten()
+                .flat_map(|editor| editor.models.iter().flatten())
+                .any(|model| is_aliased(&model.languages))
+        };
+
+        metric
+            .copilot_ide_code_completions
+            .as_ref()
+            .is_some_and(|c| is_aliased(&c.languages) || editors_aliased(&c.editors))
+            || metric
+                .copilot_ide_chat
+                .as_ref()
+                .is_some_and(|c| editors_aliased(&c.editors))
+    }
+
+    fn merge_editors(&self, editors: &mut Option<Vec<Editor>>) {
+        for editor in editors.iter_mut().flatten() {
+            for model in editor.models.iter_mut().flatten() {
+                self.merge_languages(&mut model.languages);
+            }
+        }
+    }
+
+    fn merge_languages(&self, languages: &mut Option<Vec<Language>>) {
+        let Some(languages) = languages.as_mut() else {
+            return;
+        };
+        let mut merged = Vec::with_capacity(languages.len());
+        let renamed = std::mem::take(languages).into_iter().map(|mut language| {
+            if let Some(normalized) = self.aliases.get(&language.name) {
+                language.name = normalized.clone();
+            }
+            language
+        });
+        merge_by_name(&mut merged, renamed.collect());
+        *languages = merged;
+    }
+}
+
+/// Languages summed into configured groups
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct LanguageGroups {
+    /// Normalized language names keyed by group
+    groups: BTreeMap<String, BTreeSet<String>>,
+}
+
+impl LanguageGroups {
+    /// Creates groups from their member languages
+    ///
+    /// # Arguments
+    ///
+    /// * `groups` - Normalized language names keyed by group name; a language
+    ///   may belong to several groups
+    pub fn new(groups: BTreeMap<String, Vec<String>>) -> Self {
+        Self {
+            groups: groups
+                .into_iter()
+                .map(|(group, languages)| (group, languages.into_iter().collect()))
+                .collect(),
+        }
+    }
+
+    /// Returns whether no group is configured
+    pub fn is_empty(&self) -> bool {
+        self.groups.is_empty()
+    }
+
+    /// Sums a language breakdown into the groups
+    ///
+    /// Engaged users are summed like every other counter, so a user of
+    /// several languages of a group is counted once per language.
+    ///
+    /// # Arguments
+    ///
+    /// * `languages` - The language breakdown of a day
+    /// * `normalizer` - Normalizer mapping reported names onto the names the
+    ///   groups are configured with
+    ///
+    /// # Returns
+    ///
+    /// * `Vec<Language>` - One entry per group with at least one reported
+    ///   language, named after the group, in group order
+    pub fn sum(&self, languages: &[Language], normalizer: &LanguageNormalizer) -> Vec<Language> {
+        self.groups
+            .iter()
+            .filter_map(|(group, members)| {
+                let mut sum: Option<Language> = None;
+                for language in languages
+                    .iter()
+                    .filter(|l| members.contains(normalizer.normalize(&l.name)))
+                {
+                    let mut language = language.clone();
+                    language.name = group.clone();
+                    match &mut sum {
+                        Some(sum) => sum.absorb(language),
+                        None => sum = Some(language),
+                    }
+                }
+                sum
+            })
+            .collect()
+    }
+}
+
+/// Parses aliases of the form `reported=normalized,reported=normalized`
+///
+/// # Arguments
+///
+/// * `value` - The comma-separated aliases, e.g. "hcl=terraform"
+///
+/// # Returns
+///
+/// * `Result<HashMap<String, String>, String>` - The aliases by reported
+///   name, or a description of the first entry without `=` or with an empty
+///   side
+pub fn parse_aliases(value: &str) -> std::result::Result<HashMap<String, String>, String> {
+    value
+        .split(',')
+        .map(str::trim)
+        .filter(|entry| !entry.is_empty())
+        .map(|entry| match entry.split_once('=') {
+            Some((raw, normalized)) if !raw.trim().is_empty() && !normalized.trim().is_empty() => {
+                Ok((raw.trim().to_string(), normalized.trim().to_string()))
+            }
+            _ => Err(format!("expected reported=normalized, got {}", entry)),
+        })
+        .collect()
+}
@@ -0,0 +1,277 @@
+//! # Metric Metadata
+//!
+//! Datadog shows series without metadata as unitless numbers, so dashboards
+//! can't label their axes ("users", "lines"). This module keeps the unit and
+//! description of every standard Copilot metric of a namespace in sync
+//! through Datadog's metric metadata API.
+//!
+//! Metadata is only written when it differs from what Datadog has, and
+//! metrics that were updated or found up to date are remembered for the life
+//! of the process, so warm Lambda invocations don't repeat the requests.
+//! Metrics Datadog hasn't received yet (e.g. code review without any review)
+//! are skipped and picked up on a later run. The metadata API requires an
+//! application key in addition to the API key.
+
+use std::collections::HashSet;
+use std::sync::{Mutex, OnceLock};
+
+use serde_json::{json, Value};
+use tracing::{debug, info};
+
+use super::client::api_base_url;
+use super::error::{DatadogError, Result};
+use super::names::MetricPrefix;
+use crate::services::tls::AgentConfig;
+
+/// Unit and description of a metric, keyed by its suffix below the namespace
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct MetricMetadata {
+    /// Metric suffix, e.g. "ide.chat.total_engaged_users"
+    pub suffix: &'static str,
+    /// Datadog unit name, e.g. "user"
+    pub unit: &'static str,
+    /// Description shown in the metric summary
+    pub description: &'static str,
+}
+
+/// Shorthand for the entries of `STANDARD_METADATA`
+const fn metadata(
+    suffix: &'static str,
+    unit: &'static str,
+    description: &'static str,
+) -> MetricMetadata {
+    MetricMetadata {
+        suffix,
+        unit,
+        description,
+    }
+}
+
+/// Metadata of the standard Copilot metrics
+pub const STANDARD_METADATA: &[MetricMetadata] = &[
+    metadata(
+        "total_active_users",
+        "user",
+        "Users with any Copilot activity",
+    ),
+    metadata(
+        "total_engaged_users",
+        "user",
+        "Users who engaged with a Copilot feature",
+    ),
+    metadata(
+        "ide.code_completions.total_engaged_users",
+        "user",
+        "Users who accepted a code completion in an IDE",
+    ),
+    metadata(
+        "ide.code_completions.languages.total_engaged_users",
+        "user",
+        "Code completion users per language",
+    ),
+    metadata(
+        "ide.code_completions.languages.total_code_suggestions",
+        "event",
+        "Code completions suggested per language",
+    ),
+    metadata(
+        "ide.code_completions.languages.total_code_acceptances",
+        "event",
+        "Code completions accepted per language",
+    ),
+    metadata(
+        "ide.code_completions.languages.total_code_lines_suggested",
+        "line",
+        "Lines of code suggested per language",
+    ),
+    metadata(
+        "ide.code_completions.languages.total_code_lines_accepted",
+        "line",
+        "Lines of code accepted per language",
+    ),
+    metadata(
+        "ide.code_completions.editors.total_engaged_users",
+        "user",
+        "Code completion users per editor",
+    ),
+    metadata(
+        "ide.chat.total_engaged_users",
+        "user",
+        "Users of Copilot chat in an IDE",
+    ),
+    metadata(
+        "ide.chat.editors.total_engaged_users",
+        "user",
+        "IDE chat users per editor",
+    ),
+    metadata(
+        "ide.chat.editors.models.total_engaged_users",
+        "user",
+        "IDE chat users per editor and model",
+    ),
+    metadata(
+        "dotcom.chat.total_engaged_users",
+        "user",
+        "Users of Copilot chat on GitHub.com",
+    ),
+    metadata(
+        "dotcom.chat.models.total_chats",
+        "event",
+        "Chats on GitHub.com per model",
+    ),
+    metadata(
+        "dotcom.pull_requests.total_engaged_users",
+        "user",
+        "Users of Copilot pull request summaries",
+    ),
+    metadata(
+        "dotcom.pull_requests.repositories.models.total_pr_summaries_created",
+        "event",
+        "Pull request summaries created per repository and model",
+    ),
+    metadata(
+        "code_review.total_reviews",
+        "event",
+        "Pull request reviews by Copilot",
+    ),
+    metadata(
+        "code_review.total_review_comments",
+        "event",
+        "Review comments left by Copilot",
+    ),
+];
+
+/// Metric names whose metadata was written or found up to date in this process
+static SYNCED: OnceLock<Mutex<HashSet<String>>> = OnceLock::new();
+
+/// Number of metrics by what happened to their metadata
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct MetadataOutcome {
+    /// Metrics whose metadata was written
+    pub updated: usize,
+    /// Metrics whose metadata was already up to date
+    pub unchanged: usize,
+    /// Metrics Datadog hasn't received yet
+    pub missing: usize,
+}
+
+/// Writes metric metadata through Datadog's metric metadata API
+pub struct MetadataUpdater {
+    /// Datadog API key
+    api_key: String,
+    /// Datadog application key, required by the metadata API
+    app_key: String,
+    /// Metrics endpoint URL
+    metrics_url: String,
+    /// HTTP agent using the configured TLS settings
+    agent: ureq::Agent,
+}
+
+impl MetadataUpdater {
+    /// Creates an updater for a Datadog site
+    ///
+    /// # Arguments
+    ///
+    /// * `api_key` - Datadog API key
+    /// * `app_key` - Datadog application key
+    /// * `site` - Datadog site, e.g. "datadoghq.eu", or a base URL
+    /// * `agent` - Settings of the HTTP agent
+    pub fn new(
+        api_key: impl Into<String>,
+        app_key: impl Into<String>,
+        site: &str,
+        agent: &AgentConfig,
+    ) -> Self {
+        Self {
+            api_key: api_key.into(),
+            app_key: app_key.into(),
+            metrics_url: format!("{}/api/v1/metrics", api_base_url(site)),
+            agent: agent.agent_builder().build(),
+        }
+    }
+
+    /// Brings the metadata of the standard metrics of a namespace up to date
+    ///
+    /// # Arguments
+    ///
+    /// * `namespace` - The metric namespace
+    ///
+    /// # Returns
+    ///
+    /// * `Result<MetadataOutcome>` - How many metrics were updated, already
+    ///   up to date or not received yet, or the first failed request
+    pub fn update(&self, namespace: &str) -> Result<MetadataOutcome> {
+        let names = MetricPrefix::new(namespace);
+        let synced = SYNCED.get_or_init(Default::default);
+        let mut outcome = MetadataOutcome::default();
+
+        for entry in STANDARD_METADATA {
+            let name = names.metric(entry.suffix);
+            if synced
+                .lock()
+                .unwrap_or_else(|e| e.into_inner())
+                .contains(&name)
+            {
+                outcome.unchanged += 1;
+                continue;
+            }
+
+            let url = format!("{}/{}", self.metrics_url, name);
+            let current: Value = match self.request("GET", &url).call() {
+                Ok(response) => response
+                    .into_json()
+                    .map_err(|e| DatadogError::Network(e.to_string()))?,
+                Err(ureq::Error::Status(404, _)) => {
+                    debug!("Metric {} not received yet, skipping its metadata", name);
+                    outcome.missing += 1;
+                    continue;
+                }
+                Err(e) => return Err(request_error(e)),
+            };
+
+            if current["unit"] == entry.unit && current["description"] == entry.description {
+                outcome.unchanged += 1;
+            } else {
+                self.request("PUT", &url)
+                    .send_json(json!({
+                        "type": "gauge",
+                        "unit": entry.unit,
+                        "description": entry.description,
+                    }))
+                    .map_err(request_error)?;
+                outcome.updated += 1;
+            }
+            synced
+                .lock()
+                .unwrap_or_else(|e| e.into_inner())
+                .insert(name);
+        }
+
+        info!(
+            "Metric metadata of {}: {} updated, {} up to date, {} not received yet",
+            namespace, outcome.updated, outcome.unchanged, outcome.missing
+        );
+        Ok(outcome)
+    }
+
+    /// Builds an authenticated request
+    fn request(&self, method: &str, url: &str) -> ureq::Request {
+        self.agent
+            .request(method, url)
+            .set("DD-API-KEY", &self.api_key)
+            .set("DD-APPLICATION-KEY", &self.app_key)
+    }
+}
+
+/// Converts a failed metadata API request into a `DatadogError`
+fn request_error(e: ureq::Error) -> DatadogError {
+    match e {
+        ureq::Error::Status(status, response) => {
+            let body = response
+                .into_string()
+                .unwrap_or_else(|_| "Could not read response body".to_string());
+            DatadogError::HttpError(status, body)
+        }
+        ureq::Error::Transport(transport) => DatadogError::Network(transport.to_string()),
+    }
+}
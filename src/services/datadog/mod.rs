@@ -5,18 +5,68 @@
 //!
 //! ## Core Components
 //!
+//! * `breakdown` - Folding of long-tail breakdown entries into `other`
+//! * `builder` - Explicit configuration of a client without environment variables
 //! * `client` - The main Datadog API client for sending metrics
-//! * `models` - Data structures for representing Datadog metrics
+//! * `languages` - Normalization of language names and grouping of languages
+//! * `dashboard` - Provisioning of the standard Copilot dashboard
+//! * `metadata` - Units and descriptions of the standard metrics
+//! * `models` - Data structures for representing Datadog metrics and derived gauges
 //! * `error` - Structured error types for Datadog operations
+//! * `failover` - Failover to a secondary Datadog site on repeated server errors
+//! * `names` - Validated construction of Datadog metric names
+//! * `redact` - Salted hashing of identifying tag values
+//! * `repos` - Repository allow/deny patterns and name hashing for per-repository series
+//! * `settings` - Filters and limits of a client, read once by the configuration
+//! * `sink` - Fan-out of the same metrics to several Datadog organizations
+//! * `stats` - Series and chunk counters for run summaries
+//! * `tag_templates` - Configured tags with placeholders resolved per scope
+//! * `tags` - Tag key allow/deny lists for cardinality control
+//! * `throttle` - Pacing of chunk submission to respect intake rate limits
+//! * `window` - Selection of the fetched days that are sent
 //!
 //! ## Usage
 //!
 //! The main entry point is the `DatadogClient` which handles authentication,
 //! metric formatting, and transmission to Datadog's API.
 
+pub mod breakdown;
+pub mod builder;
 pub mod client;
+pub mod dashboard;
 mod error;
+pub mod failover;
+pub mod languages;
+pub mod metadata;
 mod models;
+pub mod names;
+pub mod redact;
+pub mod repos;
+pub mod settings;
+pub mod sink;
+pub mod stats;
+pub mod tag_templates;
+pub mod tags;
+pub mod throttle;
+pub mod window;
 
+#[cfg(test)]
+mod tests;
+
+pub use breakdown::BreakdownLimits;
+pub use builder::DatadogClientBuilder;
 pub use client::DatadogClient;
-// pub use error::{DatadogError, Result as DatadogResult};
+pub use dashboard::DashboardProvisioner;
+pub use error::DatadogError;
+pub use failover::{Failover, FailoverEvent};
+pub use languages::{LanguageGroups, LanguageNormalizer};
+pub use metadata::MetadataUpdater;
+pub use models::Gauge;
+pub use repos::RepositoryFilter;
+pub use settings::DatadogSettings;
+pub use sink::{DatadogDestination, DatadogSink, DatadogSinkBuilder};
+pub use stats::SendStats;
+pub use tag_templates::{TagContext, TagTemplates};
+pub use tags::TagFilter;
+pub use throttle::Throttle;
+pub use window::DayWindow;
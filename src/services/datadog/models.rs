@@ -7,13 +7,24 @@
 //! The module provides:
 //! - `MetricPoint`: Represents a single metric data point with timestamp, value, and tags
 //! - `MetricSeries`: Collects multiple metric points for batch submission
+//! - `Gauge`: A single value computed by a processor rather than read from the
+//!   Copilot metrics models
 //! - Helper functions for creating standardized tags
 //!
+//! Points share their tag sets (`Tags`): a breakdown entry's tags are built
+//! once and every point of the entry holds a reference to them, instead of a
+//! copy per point.
+//!
 //! These models support the Datadog client by handling serialization to the specific
 //! JSON format expected by the Datadog API.
 
+use std::sync::Arc;
+
 use serde_json::{json, Value};
 
+/// An immutable tag set shared by the points carrying it
+pub type Tags = Arc<[String]>;
+
 /// Represents a metric series point to be sent to Datadog
 ///
 /// A MetricPoint contains all the information needed to record a single metric
@@ -26,7 +37,7 @@ pub struct MetricPoint {
     pub name: String,
     pub value: f64,
     pub timestamp: i64,
-    pub tags: Vec<String>,
+    pub tags: Tags,
 }
 
 impl MetricPoint {
@@ -39,17 +50,18 @@ impl MetricPoint {
     /// * `name` - Name of the metric (e.g., "github.copilot.active_users")
     /// * `value` - Numeric value of the metric
     /// * `timestamp` - Unix timestamp in seconds
-    /// * `tags` - Vector of tag strings (e.g., ["date:2023-03-01", "source:github"])
+    /// * `tags` - Tag strings (e.g., ["date:2023-03-01", "source:github"]);
+    ///   passing a shared `Tags` doesn't copy them
     ///
     /// # Returns
     ///
     /// A new `MetricPoint` instance with the provided values
-    pub fn new(name: impl Into<String>, value: f64, timestamp: i64, tags: Vec<String>) -> Self {
+    pub fn new(name: impl Into<String>, value: f64, timestamp: i64, tags: impl Into<Tags>) -> Self {
         Self {
             name: name.into(),
             value,
             timestamp,
-            tags,
+            tags: tags.into(),
         }
     }
 
@@ -75,7 +87,7 @@ impl MetricPoint {
                     "value": self.value
                 }
             ],
-            "tags": self.tags
+            "tags": &*self.tags
         })
     }
 }
@@ -122,7 +134,7 @@ impl MetricSeries {
     /// * `name` - Name of the metric
     /// * `value` - Optional i64 value for the metric
     /// * `timestamp` - Unix timestamp in seconds
-    /// * `tags` - Slice of tag strings to apply to the metric
+    /// * `tags` - Shared tag set to apply to the metric
     ///
     /// # Note
     ///
@@ -133,10 +145,10 @@ impl MetricSeries {
         name: impl Into<String>,
         value: Option<i64>,
         timestamp: i64,
-        tags: &[String],
+        tags: &Tags,
     ) {
         if let Some(val) = value {
-            self.add_point(MetricPoint::new(name, val as f64, timestamp, tags.to_vec()));
+            self.add_point(MetricPoint::new(name, val as f64, timestamp, tags.clone()));
         }
     }
 
@@ -159,6 +171,51 @@ impl Default for MetricSeries {
     }
 }
 
+/// A daily gauge computed by a processor
+///
+/// Processors use gauges for values that are derived from several sources,
+/// e.g. adoption rates combining usage with team sizes. The client prefixes
+/// the name with the namespace and adds the standard tags for the date.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Gauge {
+    /// Metric suffix below the namespace, e.g. "adoption.engaged_users_rate"
+    pub name: String,
+    /// Value of the gauge
+    pub value: f64,
+    /// Date (YYYY-MM-DD) the value applies to
+    pub date: String,
+    /// Tags in addition to the standard tags
+    pub tags: Vec<String>,
+}
+
+impl Gauge {
+    /// Creates a gauge without additional tags
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - Metric suffix below the namespace
+    /// * `value` - Value of the gauge
+    /// * `date` - Date (YYYY-MM-DD) the value applies to
+    pub fn new(name: impl Into<String>, value: f64, date: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            value,
+            date: date.into(),
+            tags: Vec::new(),
+        }
+    }
+
+    /// Adds tags to the gauge
+    ///
+    /// # Arguments
+    ///
+    /// * `tags` - Tags in addition to the standard tags
+    pub fn with_tags(mut self, tags: Vec<String>) -> Self {
+        self.tags.extend(tags);
+        self
+    }
+}
+
 /// Helper to create standard tags
 ///
 /// Creates a vector of standard tags that should be included with all metrics.
@@ -179,3 +236,39 @@ pub fn standard_tags(date: &str) -> Vec<String> {
         "source:github-copilot-metrics".to_string(),
     ]
 }
+
+/// Creates a tag set from a base set and additional tags
+///
+/// # Arguments
+///
+/// * `base` - Tags shared with the parent breakdown, e.g. the standard tags
+/// * `extra` - Tags identifying the breakdown entry, e.g. `language:rust`
+///
+/// # Returns
+///
+/// A new shared tag set containing `base` followed by `extra`
+pub fn extend_tags<const N: usize>(base: &[String], extra: [String; N]) -> Tags {
+    base.iter().cloned().chain(extra).collect()
+}
+
+/// Appends extra tags to already prepared series
+///
+/// Used for tags that apply to every series of a scope, such as the tags
+/// configured for a team, so they don't have to be threaded through every
+/// metric preparation function.
+///
+/// # Arguments
+///
+/// * `series` - Mutable slice of series in Datadog's JSON format
+/// * `tags` - Tags to append to each series
+pub fn append_tags(series: &mut [Value], tags: &[String]) {
+    if tags.is_empty() {
+        return;
+    }
+
+    for s in series.iter_mut() {
+        if let Some(existing) = s["tags"].as_array_mut() {
+            existing.extend(tags.iter().map(|t| Value::String(t.clone())));
+        }
+    }
+}
@@ -0,0 +1,250 @@
+//! # Metric Names
+//!
+//! Datadog metric names must start with a letter, may only contain ASCII
+//! alphanumerics, underscores and periods, and are limited to 200 characters.
+//! Datadog silently rewrites names that break these rules, which leaves
+//! dashboards querying metrics that never arrive.
+//!
+//! This module provides `MetricPrefix`, the single place where namespaces and
+//! metric suffixes are joined into full metric names. Every name it builds is
+//! normalized to satisfy Datadog's constraints, and any rewrite is logged so
+//! misconfigured namespaces are noticed.
+//!
+//! It also provides `NamespaceTemplate`, which builds the namespaces of teams
+//! and organizations from the template configured in
+//! `DATADOG_NAMESPACE_TEMPLATE`. The template may use
+//! three placeholders:
+//!
+//! - `{base}`: the enterprise's namespace, e.g. `github.copilot`
+//! - `{scope}`: the kind of scope, `team` or `org`
+//! - `{slug}`: the sanitized team slug or organization login
+//!
+//! The default `{base}.{scope}.{slug}` gives `github.copilot.team.web`. A
+//! template without `{slug}`, e.g. `{base}`, sends every team under the same
+//! metric names and identifies it by a `team:{slug}` (or `org:{login}`) tag.
+
+use tracing::warn;
+
+/// Maximum length of a Datadog metric name
+pub const MAX_METRIC_NAME_LEN: usize = 200;
+
+/// Template of the namespaces of teams and organizations used by default
+pub const DEFAULT_NAMESPACE_TEMPLATE: &str = "{base}.{scope}.{slug}";
+
+/// Placeholders a namespace template may use
+const PLACEHOLDERS: &[&str] = &["base", "scope", "slug"];
+
+/// Template the namespaces of teams and organizations are built from
+#[derive(Debug, Clone, PartialEq)]
+pub struct NamespaceTemplate {
+    template: String,
+}
+
+impl Default for NamespaceTemplate {
+    fn default() -> Self {
+        Self {
+            template: DEFAULT_NAMESPACE_TEMPLATE.to_string(),
+        }
+    }
+}
+
+impl NamespaceTemplate {
+    /// Parses a template
+    ///
+    /// # Arguments
+    ///
+    /// * `template` - The template, e.g. "{base}.{scope}.{slug}"
+    ///
+    /// # Returns
+    ///
+    /// * `Result<Self, String>` - The template, or a description of why it is
+    ///   invalid: an unknown or unclosed placeholder, or no `{base}`
+    pub fn parse(template: &str) -> Result<Self, String> {
+        let template = template.trim();
+        let mut rest = template;
+        while let Some(open) = rest.find('{') {
+            let Some(close) = rest[open..].find('}') else {
+                return Err(format!("unclosed placeholder in {:?}", template));
+            };
+            let name = &rest[open + 1..open + close];
+            if !PLACEHOLDERS.contains(&name) {
+                return Err(format!(
+                    "unknown placeholder {{{}}} (expected {{base}}, {{scope}} or {{slug}})",
+                    name
+                ));
+            }
+            rest = &rest[open + close + 1..];
+        }
+        if !template.contains("{base}") {
+            return Err(format!("{:?} doesn't contain {{base}}", template));
+        }
+        Ok(Self {
+            template: template.to_string(),
+        })
+    }
+
+    /// Builds the namespace of a scope
+    ///
+    /// # Arguments
+    ///
+    /// * `base` - The enterprise's namespace
+    /// * `scope` - The kind of scope, e.g. "team"
+    /// * `slug` - The sanitized slug of the scope
+    ///
+    /// # Returns
+    ///
+    /// * `String` - The namespace, with empty segments left out
+    pub fn render(&self, base: &str, scope: &str, slug: &str) -> String {
+        let rendered = self
+            .template
+            .replace("{base}", base)
+            .replace("{scope}", scope)
+            .replace("{slug}", slug);
+        rendered
+            .split('.')
+            .filter(|segment| !segment.is_empty())
+            .collect::<Vec<_>>()
+            .join(".")
+    }
+
+    /// Returns whether the slug is part of the namespace
+    pub fn has_slug(&self) -> bool {
+        self.template.contains("{slug}")
+    }
+
+    /// Returns the tag identifying a scope whose slug isn't in its namespace
+    ///
+    /// # Arguments
+    ///
+    /// * `scope` - The kind of scope, e.g. "team"
+    /// * `slug` - The slug of the scope
+    ///
+    /// # Returns
+    ///
+    /// * `Option<String>` - `{scope}:{slug}`, or `None` if the template
+    ///   contains the slug
+    pub fn slug_tag(&self, scope: &str, slug: &str) -> Option<String> {
+        (!self.has_slug()).then(|| format!("{}:{}", scope, slug))
+    }
+}
+
+/// A namespace prefix that full metric names are built from
+#[derive(Debug, Clone, PartialEq)]
+pub struct MetricPrefix {
+    prefix: String,
+}
+
+impl MetricPrefix {
+    /// Creates a prefix from a metric namespace
+    ///
+    /// # Arguments
+    ///
+    /// * `namespace` - The namespace, e.g. "github.copilot.team.web"
+    pub fn new(namespace: &str) -> Self {
+        Self {
+            prefix: namespace.trim_matches('.').to_string(),
+        }
+    }
+
+    /// Returns a prefix extended by one or more dot-separated segments
+    ///
+    /// # Arguments
+    ///
+    /// * `segment` - The segment(s) to append, e.g. "ide.code_completions"
+    pub fn child(&self, segment: &str) -> Self {
+        Self::new(&join(&self.prefix, segment))
+    }
+
+    /// Builds the full, validated metric name for a suffix
+    ///
+    /// # Arguments
+    ///
+    /// * `suffix` - The metric suffix, e.g. "total_engaged_users"
+    ///
+    /// # Returns
+    ///
+    /// * `String` - `{prefix}.{suffix}`, normalized with `normalize` if it
+    ///   would otherwise be rejected or rewritten by Datadog
+    pub fn metric(&self, suffix: &str) -> String {
+        let name = join(&self.prefix, suffix);
+        if is_valid(&name) {
+            return name;
+        }
+
+        let normalized = normalize(&name);
+        warn!(
+            "Metric name {} is not valid in Datadog; sending as {}",
+            name, normalized
+        );
+        normalized
+    }
+}
+
+/// Joins a prefix and a suffix with a period, skipping empty parts
+fn join(prefix: &str, suffix: &str) -> String {
+    let suffix = suffix.trim_matches('.');
+    match (prefix.is_empty(), suffix.is_empty()) {
+        (true, _) => suffix.to_string(),
+        (false, true) => prefix.to_string(),
+        (false, false) => format!("{}.{}", prefix, suffix),
+    }
+}
+
+/// Returns whether a metric name satisfies Datadog's naming constraints
+///
+/// # Arguments
+///
+/// * `name` - The full metric name
+pub fn is_valid(name: &str) -> bool {
+    name.len() <= MAX_METRIC_NAME_LEN
+        && name.starts_with(|c: char| c.is_ascii_alphabetic())
+        && !name.ends_with('.')
+        && !name.contains("..")
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '.')
+}
+
+/// Rewrites a metric name so it satisfies Datadog's naming constraints
+///
+/// Disallowed characters become underscores, empty segments are dropped,
+/// leading characters up to the first letter are removed and the name is
+/// truncated to `MAX_METRIC_NAME_LEN` characters. A name with no letters at
+/// all becomes `unknown`.
+///
+/// # Arguments
+///
+/// * `name` - The metric name to normalize
+///
+/// # Returns
+///
+/// * `String` - The normalized name, e.g. `github.copilot.team.web-app`
+///   becomes `github.copilot.team.web_app`
+pub fn normalize(name: &str) -> String {
+    let replaced: String = name
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || c == '_' || c == '.' {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect();
+
+    let joined = replaced
+        .split('.')
+        .filter(|segment| !segment.is_empty())
+        .collect::<Vec<_>>()
+        .join(".");
+
+    let start = match joined.find(|c: char| c.is_ascii_alphabetic()) {
+        Some(start) => start,
+        None => return "unknown".to_string(),
+    };
+
+    // Every character is ASCII at this point, so byte offsets are char offsets
+    let mut normalized = joined[start..].to_string();
+    normalized.truncate(MAX_METRIC_NAME_LEN);
+    normalized.trim_end_matches('.').to_string()
+}
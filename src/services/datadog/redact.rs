@@ -0,0 +1,33 @@
+//! # Redaction
+//!
+//! Some deployments may not send identifying names, such as repository names,
+//! to Datadog. This module replaces such values with a salted hash: the same
+//! name and salt always give the same hash, so series stay continuous across
+//! runs, while the name can't be recovered by hashing guessed names without
+//! knowing the salt.
+
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
+
+/// Number of hex characters of a hash that are kept
+pub const HASH_LENGTH: usize = 16;
+
+/// Returns the salted hash of a value
+///
+/// # Arguments
+///
+/// * `salt` - Secret salt of the deployment
+/// * `value` - The value to hash, e.g. a repository name
+///
+/// # Returns
+///
+/// * `String` - The first `HASH_LENGTH` hex characters of the HMAC-SHA256 of
+///   the value keyed with the salt
+pub fn salted_hash(salt: &str, value: &str) -> String {
+    let mut mac =
+        Hmac::<Sha256>::new_from_slice(salt.as_bytes()).expect("HMAC accepts keys of any length");
+    mac.update(value.as_bytes());
+    let mut hash = hex::encode(mac.finalize().into_bytes());
+    hash.truncate(HASH_LENGTH);
+    hash
+}
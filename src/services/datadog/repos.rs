@@ -0,0 +1,128 @@
+//! # Repository Filtering
+//!
+//! Pull request and code review metrics are broken down per repository, which
+//! leaks internal repository names into shared dashboards and adds one series
+//! per repository and day. This module provides `RepositoryFilter`, which
+//! selects the repositories that get their own series before the series are
+//! created.
+//!
+//! The filter is configured with two comma-separated lists of patterns, where
+//! `*` matches any sequence of characters (e.g. `acme/public-*`):
+//!
+//! - `DATADOG_REPOSITORY_DENYLIST`: repositories to exclude
+//! - `DATADOG_REPOSITORY_ALLOWLIST`: if set, only matching repositories are kept
+//!
+//! Patterns are matched case-insensitively against the repository name as
+//! reported by GitHub. The totals across all repositories are unaffected.
+//!
+//! With `DATADOG_REPOSITORY_HASH_SALT` set, the `repository` tag of the kept
+//! repositories carries a salted hash of the name instead of the name itself
+//! (see `redact`). Patterns still match the real names.
+
+use std::borrow::Cow;
+
+use super::redact::salted_hash;
+
+/// Selects the repositories broken down into their own series
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct RepositoryFilter {
+    /// If set, only repositories matching one of these patterns are kept
+    allow: Option<Vec<String>>,
+    /// Repositories matching one of these patterns are excluded
+    deny: Vec<String>,
+    /// If set, repository names are replaced with their hash salted with it
+    hash_salt: Option<String>,
+}
+
+impl RepositoryFilter {
+    /// Creates a filter from explicit allow and deny patterns
+    ///
+    /// # Arguments
+    ///
+    /// * `allow` - Patterns of repositories to keep, or `None` to keep every
+    ///   repository not denied
+    /// * `deny` - Patterns of repositories to exclude
+    pub fn new(allow: Option<Vec<String>>, deny: Vec<String>) -> Self {
+        Self {
+            allow,
+            deny,
+            hash_salt: None,
+        }
+    }
+
+    /// Replaces repository names in tags with a salted hash
+    ///
+    /// # Arguments
+    ///
+    /// * `hash_salt` - Secret salt, or `None` to send the names as reported
+    pub fn with_hash_salt(mut self, hash_salt: Option<String>) -> Self {
+        self.hash_salt = hash_salt.filter(|salt| !salt.is_empty());
+        self
+    }
+
+    /// Returns whether a repository gets its own series
+    ///
+    /// # Arguments
+    ///
+    /// * `repository` - The repository name, e.g. "acme/website"
+    pub fn keeps(&self, repository: &str) -> bool {
+        if self.deny.iter().any(|p| matches(p, repository)) {
+            return false;
+        }
+        match &self.allow {
+            Some(allow) => allow.iter().any(|p| matches(p, repository)),
+            None => true,
+        }
+    }
+
+    /// Returns the value of a repository's `repository` tag
+    ///
+    /// # Arguments
+    ///
+    /// * `repository` - The repository name, e.g. "acme/website"
+    ///
+    /// # Returns
+    ///
+    /// * `Cow<str>` - The salted hash of the name if a salt is set, otherwise
+    ///   the name itself
+    pub fn tag_value<'a>(&self, repository: &'a str) -> Cow<'a, str> {
+        match &self.hash_salt {
+            Some(salt) => Cow::Owned(salted_hash(salt, repository)),
+            None => Cow::Borrowed(repository),
+        }
+    }
+}
+
+/// Returns whether a name matches a pattern where `*` matches any sequence
+fn matches(pattern: &str, name: &str) -> bool {
+    let pattern = pattern.to_lowercase();
+    let name = name.to_lowercase();
+
+    let mut parts = pattern.split('*');
+    let first = parts.next().unwrap_or_default();
+    let Some(mut rest) = name.strip_prefix(first) else {
+        return false;
+    };
+
+    let mut parts: Vec<&str> = parts.collect();
+    let Some(last) = parts.pop() else {
+        // No wildcard: the whole name must match
+        return rest.is_empty();
+    };
+    for part in parts {
+        match rest.find(part) {
+            Some(index) => rest = &rest[index + part.len()..],
+            None => return false,
+        }
+    }
+    rest.ends_with(last)
+}
+
+/// Parses a comma-separated list of patterns
+pub fn parse_patterns(value: &str) -> Vec<String> {
+    value
+        .split(',')
+        .map(|p| p.trim().to_string())
+        .filter(|p| !p.is_empty())
+        .collect()
+}
@@ -0,0 +1,77 @@
+//! # Datadog Client Settings
+//!
+//! The filters and limits a `DatadogClient` applies to the series it sends,
+//! grouped so the configuration can read them once and hand the same values to
+//! every client of a run. `DatadogSettings::default()` holds the neutral
+//! settings of `DatadogClientBuilder`; `Config::from_env` parses the
+//! environment variables documented on each setting and rejects invalid
+//! values.
+
+use super::breakdown::BreakdownLimits;
+use super::client::DEFAULT_SEND_CONCURRENCY;
+use super::languages::LanguageNormalizer;
+use super::repos::RepositoryFilter;
+use super::tag_templates::TagTemplates;
+use super::tags::TagFilter;
+use super::throttle::Throttle;
+use super::window::DayWindow;
+use crate::services::tls::AgentConfig;
+
+/// Filters and limits applied by a Datadog client
+#[derive(Debug, Clone, PartialEq)]
+pub struct DatadogSettings {
+    /// Filter dropping series with excluded tag keys
+    /// (`DATADOG_TAG_ALLOWLIST` / `DATADOG_TAG_DENYLIST`)
+    pub tag_filter: TagFilter,
+
+    /// Tags with placeholders added to every series
+    /// (`DATADOG_TAG_TEMPLATES` / `DD_ENV`)
+    pub tag_templates: TagTemplates,
+
+    /// Pacing of chunk submission
+    /// (`DATADOG_MAX_CHUNKS_PER_SECOND` / `DATADOG_MAX_POINTS_PER_SECOND`)
+    pub throttle: Throttle,
+
+    /// Fetched days that are sent (`SEND_LATEST_DAY_ONLY`)
+    pub day_window: DayWindow,
+
+    /// Folding of long-tail breakdown entries
+    /// (`MIN_LANGUAGE_ENGAGED_USERS` / `TOP_N_BREAKDOWNS`)
+    pub breakdown_limits: BreakdownLimits,
+
+    /// Mapping of reported language names onto normalized ones
+    /// (`LANGUAGE_ALIASES` / `LANGUAGE_RAW_TAG`)
+    pub language_normalizer: LanguageNormalizer,
+
+    /// Repositories broken down into their own series
+    /// (`DATADOG_REPOSITORY_ALLOWLIST` / `DATADOG_REPOSITORY_DENYLIST` /
+    /// `DATADOG_REPOSITORY_HASH_SALT`)
+    pub repository_filter: RepositoryFilter,
+
+    /// Number of chunks submitted at the same time (`DATADOG_SEND_CONCURRENCY`)
+    pub concurrency: usize,
+
+    /// Whether unknown GitHub response fields are reported as a metric
+    /// (`STRICT_SCHEMA=warn`)
+    pub report_schema_drift: bool,
+
+    /// Settings of the HTTP agent (`GHRUST_CA_BUNDLE`)
+    pub agent: AgentConfig,
+}
+
+impl Default for DatadogSettings {
+    fn default() -> Self {
+        Self {
+            tag_filter: TagFilter::default(),
+            tag_templates: TagTemplates::default(),
+            throttle: Throttle::default(),
+            day_window: DayWindow::default(),
+            breakdown_limits: BreakdownLimits::default(),
+            language_normalizer: LanguageNormalizer::default(),
+            repository_filter: RepositoryFilter::default(),
+            concurrency: DEFAULT_SEND_CONCURRENCY,
+            report_schema_drift: false,
+            agent: AgentConfig::default(),
+        }
+    }
+}
@@ -0,0 +1,401 @@
+//! # Datadog Fan-out Sink
+//!
+//! Some organizations run several Datadog organizations (e.g. one for security
+//! and one for engineering) that all need the same Copilot metrics. This module
+//! provides `DatadogSink`, which sends the same series to every configured
+//! destination, each with its own site, API key and namespace.
+//!
+//! Every destination is attempted independently: a failure sending to one
+//! organization is logged and reported, but does not prevent delivery to the
+//! others.
+//!
+//! A sink carries the `TagContext` its series are tagged for. The run creates
+//! one sink and hands each scope a [`DatadogSink::scoped`] copy, which shares
+//! the clients (and their counters, batch and failover state) but resolves
+//! the tag templates against the scope's enterprise, team or organization.
+//! Options that change the clients (the day window, language groups and
+//! batching) are set on a [`DatadogSinkBuilder`] before the clients exist, so
+//! no scoped copy can observe a change.
+
+use std::sync::Arc;
+
+use tracing::{error, info};
+
+use super::client::{DatadogClient, DEFAULT_SITE};
+use super::error::{DatadogError, Result};
+use super::failover::{Failover, FailoverEvent};
+use super::languages::LanguageGroups;
+use super::models::Gauge;
+use super::settings::DatadogSettings;
+use super::stats::SendStats;
+use super::tag_templates::TagContext;
+use super::window::DayWindow;
+use crate::models::billing::PremiumRequestUsage;
+use crate::models::github::CopilotMetrics;
+
+/// A Datadog organization to send metrics to
+#[derive(Debug, Clone, PartialEq)]
+pub struct DatadogDestination {
+    /// Name used to identify the destination in logs and errors
+    pub name: String,
+
+    /// Datadog site, e.g. "datadoghq.eu"
+    pub site: String,
+
+    /// API key for the destination's organization
+    pub api_key: String,
+
+    /// Namespace replacing the base namespace for this destination, if any
+    pub namespace: Option<String>,
+
+    /// API key retried when the organization rejects `api_key`, if any
+    pub secondary_api_key: Option<String>,
+
+    /// Secondary site the destination fails over to, if any
+    pub failover: Option<Failover>,
+}
+
+impl DatadogDestination {
+    /// Creates a destination for the default site using the base namespace
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - Name used to identify the destination in logs and errors
+    /// * `api_key` - API key for the destination's organization
+    pub fn new(name: impl Into<String>, api_key: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            site: DEFAULT_SITE.to_string(),
+            api_key: api_key.into(),
+            namespace: None,
+            secondary_api_key: None,
+            failover: None,
+        }
+    }
+
+    /// Maps a namespace built from the base namespace to this destination
+    ///
+    /// If the destination has its own namespace, the base namespace prefix is
+    /// replaced with it, so `github.copilot.team.web` becomes
+    /// `security.copilot.team.web` for a destination namespace of
+    /// `security.copilot`. Namespaces that don't start with the base namespace
+    /// are returned unchanged.
+    ///
+    /// # Arguments
+    ///
+    /// * `base_namespace` - The configured base namespace
+    /// * `namespace` - The scope namespace to map
+    pub fn map_namespace(&self, base_namespace: &str, namespace: &str) -> String {
+        match &self.namespace {
+            Some(own) => match namespace.strip_prefix(base_namespace) {
+                Some(rest) if rest.is_empty() || rest.starts_with('.') => {
+                    format!("{}{}", own, rest)
+                }
+                _ => namespace.to_string(),
+            },
+            None => namespace.to_string(),
+        }
+    }
+}
+
+/// Sends the same metrics to one or more Datadog destinations
+pub struct DatadogSink {
+    /// The base namespace that destination namespaces replace
+    base_namespace: String,
+    /// Destinations paired with their clients, shared with scoped copies
+    targets: Arc<Vec<(DatadogDestination, DatadogClient)>>,
+    /// Scope the tag templates of sent series are resolved against
+    tag_context: TagContext,
+}
+
+/// Builder for a `DatadogSink`
+///
+/// Created with [`DatadogSink::builder`]; the clients of the destinations are
+/// created by `build`.
+#[derive(Debug, Clone)]
+pub struct DatadogSinkBuilder {
+    destinations: Vec<DatadogDestination>,
+    base_namespace: String,
+    settings: DatadogSettings,
+    language_groups: Option<LanguageGroups>,
+    batching: bool,
+}
+
+impl DatadogSinkBuilder {
+    /// Selects the fetched days every destination sends
+    ///
+    /// # Arguments
+    ///
+    /// * `day_window` - Selection of the fetched days that are sent
+    pub fn day_window(mut self, day_window: DayWindow) -> Self {
+        self.settings.day_window = day_window;
+        self
+    }
+
+    /// Sums languages into the given groups at every destination
+    ///
+    /// # Arguments
+    ///
+    /// * `language_groups` - Groups languages are additionally summed into
+    pub fn language_groups(mut self, language_groups: LanguageGroups) -> Self {
+        self.language_groups = Some(language_groups);
+        self
+    }
+
+    /// Queues the series sent to every destination until [`DatadogSink::flush`]
+    ///
+    /// The send methods then only prepare and queue series, so their results
+    /// no longer reflect delivery; the result of `flush` does.
+    pub fn batching(mut self) -> Self {
+        self.batching = true;
+        self
+    }
+
+    /// Creates the client of every destination and the sink sharing them
+    pub fn build(self) -> DatadogSink {
+        let targets = self
+            .destinations
+            .iter()
+            .map(|d| {
+                let mut client =
+                    DatadogClient::from_settings(d.api_key.clone(), &d.site, self.settings.clone());
+                if let Some(api_key) = &d.secondary_api_key {
+                    client = client.with_secondary_api_key(api_key.clone());
+                }
+                if let Some(failover) = &d.failover {
+                    client = client.with_failover(failover.clone());
+                }
+                if let Some(groups) = &self.language_groups {
+                    client = client.with_language_groups(groups.clone());
+                }
+                if self.batching {
+                    client = client.with_batching();
+                }
+                (d.clone(), client)
+            })
+            .collect();
+
+        DatadogSink {
+            base_namespace: self.base_namespace,
+            targets: Arc::new(targets),
+            tag_context: TagContext::default(),
+        }
+    }
+}
+
+impl DatadogSink {
+    /// Creates a sink fanning out to the given destinations
+    ///
+    /// # Arguments
+    ///
+    /// * `destinations` - The Datadog organizations to send to
+    /// * `base_namespace` - The configured base namespace
+    /// * `settings` - Filters and limits every destination applies
+    pub fn new(
+        destinations: &[DatadogDestination],
+        base_namespace: &str,
+        settings: &DatadogSettings,
+    ) -> Self {
+        Self::builder(destinations, base_namespace, settings).build()
+    }
+
+    /// Returns a builder for a sink with further client options
+    ///
+    /// # Arguments
+    ///
+    /// * `destinations` - The Datadog organizations to send to
+    /// * `base_namespace` - The configured base namespace
+    /// * `settings` - Filters and limits every destination applies
+    pub fn builder(
+        destinations: &[DatadogDestination],
+        base_namespace: &str,
+        settings: &DatadogSettings,
+    ) -> DatadogSinkBuilder {
+        DatadogSinkBuilder {
+            destinations: destinations.to_vec(),
+            base_namespace: base_namespace.to_string(),
+            settings: settings.clone(),
+            language_groups: None,
+            batching: false,
+        }
+    }
+
+    /// Returns a sink sending to the same destinations, with the tag templates
+    /// resolved against a scope
+    ///
+    /// The clients are shared, so series, counters and failovers of the copy
+    /// count towards this sink and a batched copy queues into the same batch.
+    ///
+    /// # Arguments
+    ///
+    /// * `tag_context` - The enterprise, team or organization of the series
+    pub fn scoped(&self, tag_context: TagContext) -> Self {
+        Self {
+            base_namespace: self.base_namespace.clone(),
+            targets: Arc::clone(&self.targets),
+            tag_context,
+        }
+    }
+
+    /// Returns the names of the configured destinations
+    pub fn destination_names(&self) -> Vec<&str> {
+        self.targets.iter().map(|(d, _)| d.name.as_str()).collect()
+    }
+
+    /// Returns the series and chunk counters summed over every destination
+    pub fn stats(&self) -> SendStats {
+        let mut stats = SendStats::default();
+        for (_, client) in self.targets.iter() {
+            stats.add(&client.stats());
+        }
+        stats
+    }
+
+    /// Returns the destinations that failed over to their secondary site
+    pub fn failovers(&self) -> Vec<FailoverEvent> {
+        self.targets
+            .iter()
+            .filter_map(|(destination, client)| {
+                client.failover_event().map(|event| FailoverEvent {
+                    destination: destination.name.clone(),
+                    ..event
+                })
+            })
+            .collect()
+    }
+
+    /// Sends Copilot metrics to every destination
+    ///
+    /// # Arguments
+    ///
+    /// * `metrics` - Array slice of GitHub Copilot metrics to send
+    /// * `namespace` - Metric namespace built from the base namespace
+    ///
+    /// # Returns
+    ///
+    /// * `Result<()>` - Ok if every destination succeeded, otherwise a
+    ///   `Destinations` error listing each failed destination
+    pub fn send_metrics(&self, metrics: &[CopilotMetrics], namespace: &str) -> Result<()> {
+        self.send_metrics_with_tags(metrics, namespace, &[])
+    }
+
+    /// Sends Copilot metrics to every destination with additional tags
+    ///
+    /// # Arguments
+    ///
+    /// * `metrics` - Array slice of GitHub Copilot metrics to send
+    /// * `namespace` - Metric namespace built from the base namespace
+    /// * `extra_tags` - Tags added to every series
+    ///
+    /// # Returns
+    ///
+    /// * `Result<()>` - Ok if every destination succeeded, otherwise a
+    ///   `Destinations` error listing each failed destination
+    pub fn send_metrics_with_tags(
+        &self,
+        metrics: &[CopilotMetrics],
+        namespace: &str,
+        extra_tags: &[String],
+    ) -> Result<()> {
+        self.fan_out(namespace, |client, ns| {
+            client.send_metrics_with_tags(metrics, ns, &self.tag_context, extra_tags)
+        })
+    }
+
+    /// Sends a premium request usage report to every destination
+    ///
+    /// # Arguments
+    ///
+    /// * `usage` - The premium request usage report to send
+    /// * `namespace` - Metric namespace built from the base namespace
+    /// * `extra_tags` - Tags added to every series
+    ///
+    /// # Returns
+    ///
+    /// * `Result<()>` - Ok if every destination succeeded, otherwise a
+    ///   `Destinations` error listing each failed destination
+    pub fn send_premium_request_usage(
+        &self,
+        usage: &PremiumRequestUsage,
+        namespace: &str,
+        extra_tags: &[String],
+    ) -> Result<()> {
+        self.fan_out(namespace, |client, ns| {
+            client.send_premium_request_usage(usage, ns, &self.tag_context, extra_tags)
+        })
+    }
+
+    /// Sends gauges computed by a processor to every destination
+    ///
+    /// # Arguments
+    ///
+    /// * `gauges` - The gauges to send
+    /// * `namespace` - Metric namespace built from the base namespace
+    ///
+    /// # Returns
+    ///
+    /// * `Result<()>` - Ok if every destination succeeded, otherwise a
+    ///   `Destinations` error listing each failed destination
+    pub fn send_gauges(&self, gauges: &[Gauge], namespace: &str) -> Result<()> {
+        self.fan_out(namespace, |client, ns| {
+            client.send_gauges(gauges, ns, &self.tag_context)
+        })
+    }
+
+    /// Submits the queued series of every destination in one chunked pass
+    ///
+    /// # Returns
+    ///
+    /// * `Result<()>` - Ok if every destination succeeded, otherwise a
+    ///   `Destinations` error listing each failed destination
+    pub fn flush(&self) -> Result<()> {
+        let mut failures = Vec::new();
+
+        for (destination, client) in self.targets.iter() {
+            if let Err(e) = client.flush() {
+                error!(
+                    "Failed to send the batched series to Datadog destination {}: {}",
+                    destination.name, e
+                );
+                failures.push(format!("{}: {}", destination.name, e));
+            }
+        }
+
+        if failures.is_empty() {
+            Ok(())
+        } else {
+            Err(DatadogError::Destinations(failures))
+        }
+    }
+
+    /// Runs `send` against every destination, collecting failures
+    fn fan_out<F>(&self, namespace: &str, send: F) -> Result<()>
+    where
+        F: Fn(&DatadogClient, &str) -> Result<()>,
+    {
+        let mut failures = Vec::new();
+
+        for (destination, client) in self.targets.iter() {
+            let target_namespace = destination.map_namespace(&self.base_namespace, namespace);
+            match send(client, &target_namespace) {
+                Ok(()) => info!(
+                    "Sent {} to Datadog destination {}",
+                    target_namespace, destination.name
+                ),
+                Err(e) => {
+                    error!(
+                        "Failed to send {} to Datadog destination {}: {}",
+                        target_namespace, destination.name, e
+                    );
+                    failures.push(format!("{}: {}", destination.name, e));
+                }
+            }
+        }
+
+        if failures.is_empty() {
+            Ok(())
+        } else {
+            Err(DatadogError::Destinations(failures))
+        }
+    }
+}
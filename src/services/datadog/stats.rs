@@ -0,0 +1,95 @@
+//! # Submission Statistics
+//!
+//! Counters kept by the Datadog client so callers can report how many series
+//! were prepared and delivered, and how many chunks failed, without every send
+//! method having to return them. Counters only ever grow; callers take a
+//! snapshot before and after a send and report the difference.
+
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use serde::Serialize;
+
+/// A snapshot of submission counters
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+pub struct SendStats {
+    /// Series prepared for submission
+    pub series_prepared: usize,
+    /// Series accepted by Datadog
+    pub series_sent: usize,
+    /// Chunks accepted by Datadog
+    pub chunks_sent: usize,
+    /// Chunks that could not be delivered
+    pub chunks_failed: usize,
+    /// Series in the chunks that could not be delivered
+    pub series_failed: usize,
+}
+
+impl SendStats {
+    /// Returns the counters accumulated since an earlier snapshot
+    ///
+    /// # Arguments
+    ///
+    /// * `earlier` - The snapshot taken before the operation
+    pub fn since(&self, earlier: &SendStats) -> SendStats {
+        SendStats {
+            series_prepared: self.series_prepared - earlier.series_prepared,
+            series_sent: self.series_sent - earlier.series_sent,
+            chunks_sent: self.chunks_sent - earlier.chunks_sent,
+            chunks_failed: self.chunks_failed - earlier.chunks_failed,
+            series_failed: self.series_failed - earlier.series_failed,
+        }
+    }
+
+    /// Adds another snapshot to this one
+    ///
+    /// # Arguments
+    ///
+    /// * `other` - The counters to add
+    pub fn add(&mut self, other: &SendStats) {
+        self.series_prepared += other.series_prepared;
+        self.series_sent += other.series_sent;
+        self.chunks_sent += other.chunks_sent;
+        self.chunks_failed += other.chunks_failed;
+        self.series_failed += other.series_failed;
+    }
+}
+
+/// Thread-safe submission counters owned by a client
+#[derive(Debug, Default)]
+pub(crate) struct SendCounters {
+    series_prepared: AtomicUsize,
+    series_sent: AtomicUsize,
+    chunks_sent: AtomicUsize,
+    chunks_failed: AtomicUsize,
+    series_failed: AtomicUsize,
+}
+
+impl SendCounters {
+    /// Records series prepared for submission
+    pub(crate) fn prepared(&self, series: usize) {
+        self.series_prepared.fetch_add(series, Ordering::Relaxed);
+    }
+
+    /// Records a chunk accepted by Datadog
+    pub(crate) fn chunk_sent(&self, series: usize) {
+        self.series_sent.fetch_add(series, Ordering::Relaxed);
+        self.chunks_sent.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Records a chunk that could not be delivered
+    pub(crate) fn chunk_failed(&self, series: usize) {
+        self.series_failed.fetch_add(series, Ordering::Relaxed);
+        self.chunks_failed.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Returns a snapshot of the counters
+    pub(crate) fn snapshot(&self) -> SendStats {
+        SendStats {
+            series_prepared: self.series_prepared.load(Ordering::Relaxed),
+            series_sent: self.series_sent.load(Ordering::Relaxed),
+            chunks_sent: self.chunks_sent.load(Ordering::Relaxed),
+            chunks_failed: self.chunks_failed.load(Ordering::Relaxed),
+            series_failed: self.series_failed.load(Ordering::Relaxed),
+        }
+    }
+}
@@ -0,0 +1,194 @@
+//! # Tag Templates
+//!
+//! Tagging conventions differ between Datadog organizations: one wants every
+//! series tagged `enterprise:acme`, another `env:prod` and `team:web`. This
+//! module provides `TagTemplates`, a comma-separated list of tags with
+//! placeholders configured in `DATADOG_TAG_TEMPLATES`, e.g.
+//! `enterprise:{enterprise_id},team:{team_slug},env:{env}`.
+//!
+//! The templates are read once with the rest of the Datadog settings and
+//! resolved when series are sent, against the `TagContext` of the sink or
+//! send call (see `DatadogSink::scoped`):
+//!
+//! - `{enterprise_id}`: the enterprise of the scope
+//! - `{team_slug}`: the team, for team scopes
+//! - `{org}`: the organization login, for organization scopes
+//! - `{env}`: the `DD_ENV` environment variable
+//!
+//! A tag with a placeholder that has no value in the scope (e.g. `{team_slug}`
+//! for the enterprise metrics) is left out.
+
+use serde::{Deserialize, Serialize};
+
+/// Placeholders a tag template may use
+const PLACEHOLDERS: &[&str] = &["enterprise_id", "team_slug", "org", "env"];
+
+/// Values of the scope placeholders for the series being sent
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct TagContext {
+    /// ID of the enterprise
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub enterprise_id: Option<String>,
+    /// Slug of the team, for team scopes
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub team_slug: Option<String>,
+    /// Login of the organization, for organization scopes
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub org: Option<String>,
+}
+
+impl TagContext {
+    /// Creates the context of an enterprise and the series derived from its
+    /// metrics, such as its seats, rollups or team distribution
+    ///
+    /// # Arguments
+    ///
+    /// * `enterprise_id` - ID of the enterprise
+    pub fn enterprise(enterprise_id: &str) -> Self {
+        Self {
+            enterprise_id: Some(enterprise_id.to_string()),
+            ..Self::default()
+        }
+    }
+
+    /// Creates the context of a team of an enterprise
+    ///
+    /// # Arguments
+    ///
+    /// * `enterprise_id` - ID of the enterprise
+    /// * `team_slug` - Slug of the team
+    pub fn team(enterprise_id: &str, team_slug: &str) -> Self {
+        Self {
+            team_slug: Some(team_slug.to_string()),
+            ..Self::enterprise(enterprise_id)
+        }
+    }
+
+    /// Creates the context of an organization of an enterprise
+    ///
+    /// # Arguments
+    ///
+    /// * `enterprise_id` - ID of the enterprise
+    /// * `org` - Login of the organization
+    pub fn org(enterprise_id: &str, org: &str) -> Self {
+        Self {
+            org: Some(org.to_string()),
+            ..Self::enterprise(enterprise_id)
+        }
+    }
+}
+
+/// Additional tags with placeholders added to every series
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct TagTemplates {
+    templates: Vec<String>,
+    /// Value of the `{env}` placeholder
+    env: Option<String>,
+}
+
+impl TagTemplates {
+    /// Parses a comma-separated list of tag templates
+    ///
+    /// # Arguments
+    ///
+    /// * `list` - The templates, e.g. "enterprise:{enterprise_id},env:{env}"
+    ///
+    /// # Returns
+    ///
+    /// * `Result<Self, String>` - The templates, or a description of the first
+    ///   invalid one: not `key:value`, or with an unknown or unclosed
+    ///   placeholder
+    pub fn parse(list: &str) -> Result<Self, String> {
+        let mut templates = Vec::new();
+        for template in list.split(',').map(str::trim).filter(|t| !t.is_empty()) {
+            match template.split_once(':') {
+                Some((key, value)) if !key.is_empty() && !value.is_empty() => {}
+                _ => return Err(format!("{:?} is not a key:value tag", template)),
+            }
+            for placeholder in placeholders(template)? {
+                if !PLACEHOLDERS.contains(&placeholder) {
+                    return Err(format!(
+                        "unknown placeholder {{{}}} in {:?} (expected {})",
+                        placeholder,
+                        template,
+                        PLACEHOLDERS
+                            .iter()
+                            .map(|p| format!("{{{}}}", p))
+                            .collect::<Vec<_>>()
+                            .join(", ")
+                    ));
+                }
+            }
+            templates.push(template.to_string());
+        }
+        Ok(Self {
+            templates,
+            env: None,
+        })
+    }
+
+    /// Returns the templates with a value for the `{env}` placeholder
+    ///
+    /// # Arguments
+    ///
+    /// * `env` - The deployment environment, e.g. "prod"; empty values are
+    ///   treated as unset
+    pub fn with_env(mut self, env: Option<String>) -> Self {
+        self.env = env.filter(|env| !env.is_empty());
+        self
+    }
+
+    /// Returns whether any template uses a placeholder
+    ///
+    /// # Arguments
+    ///
+    /// * `placeholder` - The placeholder name, e.g. "team_slug"
+    pub fn uses(&self, placeholder: &str) -> bool {
+        let placeholder = format!("{{{}}}", placeholder);
+        self.templates.iter().any(|t| t.contains(&placeholder))
+    }
+
+    /// Resolves the templates against a context
+    ///
+    /// # Arguments
+    ///
+    /// * `context` - Values of the scope placeholders
+    ///
+    /// # Returns
+    ///
+    /// * `Vec<String>` - The resolved tags, without those using a placeholder
+    ///   the context has no value for
+    pub fn resolve(&self, context: &TagContext) -> Vec<String> {
+        self.templates
+            .iter()
+            .filter_map(|template| {
+                let mut tag = template.clone();
+                for placeholder in placeholders(template).ok()? {
+                    let value = match placeholder {
+                        "enterprise_id" => context.enterprise_id.as_deref(),
+                        "team_slug" => context.team_slug.as_deref(),
+                        "org" => context.org.as_deref(),
+                        "env" => self.env.as_deref(),
+                        _ => None,
+                    }?;
+                    tag = tag.replace(&format!("{{{}}}", placeholder), value);
+                }
+                Some(tag)
+            })
+            .collect()
+    }
+}
+
+/// Returns the placeholder names of a template
+fn placeholders(template: &str) -> Result<Vec<&str>, String> {
+    let mut names = Vec::new();
+    let mut rest = template;
+    while let Some(open) = rest.find('{') {
+        let Some(close) = rest[open..].find('}') else {
+            return Err(format!("unclosed placeholder in {:?}", template));
+        };
+        names.push(&rest[open + 1..open + close]);
+        rest = &rest[open + close + 1..];
+    }
+    Ok(names)
+}
@@ -0,0 +1,98 @@
+//! # Tag Filtering
+//!
+//! Repository- and model-level tags multiply the number of custom metrics
+//! reported to Datadog. This module provides `TagFilter`, which lets a
+//! deployment keep or drop specific tag keys to tune cardinality.
+//!
+//! Removing a tag from a breakdown series (e.g. a per-repository series) would
+//! leave several series with identical names and tags, and Datadog would keep
+//! only one of them. The filter therefore drops every series that carries an
+//! excluded tag key; the aggregated parent series are unaffected.
+//!
+//! The filter is configured with two comma-separated lists of tag keys:
+//!
+//! - `DATADOG_TAG_DENYLIST`: tag keys to exclude, e.g. `repository,model`
+//! - `DATADOG_TAG_ALLOWLIST`: if set, only these tag keys are kept
+//!
+//! The standard `date` and `source` tags are always kept.
+
+use tracing::debug;
+
+use super::models::MetricSeries;
+
+/// Tag keys present on every series that are never filtered
+const ALWAYS_KEPT: &[&str] = &["date", "source"];
+
+/// Keeps or drops series based on their tag keys
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct TagFilter {
+    /// If set, only these tag keys are kept
+    allow: Option<Vec<String>>,
+    /// Tag keys to exclude
+    deny: Vec<String>,
+}
+
+impl TagFilter {
+    /// Creates a filter from explicit allow and deny lists
+    ///
+    /// # Arguments
+    ///
+    /// * `allow` - Tag keys to keep, or `None` to keep every key not denied
+    /// * `deny` - Tag keys to exclude
+    pub fn new(allow: Option<Vec<String>>, deny: Vec<String>) -> Self {
+        Self { allow, deny }
+    }
+
+    /// Returns whether series carrying the given tag key are kept
+    ///
+    /// # Arguments
+    ///
+    /// * `key` - The tag key, i.e. the part of a tag before the first `:`
+    pub fn keeps(&self, key: &str) -> bool {
+        if ALWAYS_KEPT.contains(&key) {
+            return true;
+        }
+        if self.deny.iter().any(|k| k == key) {
+            return false;
+        }
+        match &self.allow {
+            Some(allow) => allow.iter().any(|k| k == key),
+            None => true,
+        }
+    }
+
+    /// Removes every series carrying a tag key the filter doesn't keep
+    ///
+    /// # Arguments
+    ///
+    /// * `series` - The series to filter in place
+    pub fn apply(&self, series: &mut MetricSeries) {
+        if self.allow.is_none() && self.deny.is_empty() {
+            return;
+        }
+
+        let before = series.points.len();
+        series
+            .points
+            .retain(|point| point.tags.iter().all(|tag| self.keeps(tag_key(tag))));
+
+        let dropped = before - series.points.len();
+        if dropped > 0 {
+            debug!("Tag filter dropped {} of {} series", dropped, before);
+        }
+    }
+}
+
+/// Returns the key of a tag, i.e. the part before the first `:`
+fn tag_key(tag: &str) -> &str {
+    tag.split_once(':').map_or(tag, |(key, _)| key)
+}
+
+/// Parses a comma-separated list of tag keys, accepting a trailing `:`
+pub fn parse_keys(value: &str) -> Vec<String> {
+    value
+        .split(',')
+        .map(|k| k.trim().trim_end_matches(':').to_string())
+        .filter(|k| !k.is_empty())
+        .collect()
+}
@@ -0,0 +1,1378 @@
+//! # Datadog Payload Golden Tests
+//!
+//! These tests feed known `CopilotMetrics` fixtures through `prepare_all_metrics`
+//! and compare the serialized series against golden JSON files committed under
+//! `src/services/datadog/testdata/golden/`. Any change to metric names, tags or
+//! values shows up as a test failure before it can corrupt Datadog dashboards.
+//!
+//! To regenerate the golden files after an intentional change, run:
+//!
+//! ```text
+//! UPDATE_GOLDEN=1 cargo test golden
+//! ```
+//!
+//! and review the resulting diff like any other code change.
+
+use std::borrow::Cow;
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
+
+use chrono::NaiveDate;
+use serde_json::Value;
+
+use super::dashboard::{dashboard_definition, dashboard_title};
+use super::languages::parse_aliases;
+use super::models::append_tags;
+use super::names::{self, MetricPrefix, NamespaceTemplate};
+use super::tag_templates::{TagContext, TagTemplates};
+use super::{
+    BreakdownLimits, DatadogClient, DatadogDestination, DayWindow, LanguageGroups,
+    LanguageNormalizer, RepositoryFilter, TagFilter, Throttle,
+};
+use crate::models::github::{CopilotMetrics, Editor, Language, Model};
+use crate::services::github::test_helpers::{
+    arb_copilot_metrics, create_chat_metrics, create_code_review_metrics, create_mock_api_response,
+    create_premium_request_usage, create_test_metrics, create_test_team_metrics,
+    create_window_fixture,
+};
+use crate::tests::alloc_counter;
+use crate::tests::mock_server::{self, MockRequest, MockResponse};
+
+/// Fixed timestamp used for every golden payload so the output is deterministic
+const GOLDEN_TIMESTAMP: i64 = 1_677_628_800;
+
+/// Returns the path of a golden file by name
+fn golden_path(name: &str) -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+        .join("src/services/datadog/testdata/golden")
+        .join(format!("{}.json", name))
+}
+
+/// Prepares the Datadog series for the given metrics with a fixed timestamp
+fn prepare(metrics: &[CopilotMetrics], namespace: &str) -> Value {
+    let client = DatadogClient::new("golden_api_key".to_string());
+    Value::Array(client.prepare_all_metrics(metrics, namespace, GOLDEN_TIMESTAMP))
+}
+
+/// Compares the prepared payload against the named golden file
+///
+/// When `UPDATE_GOLDEN` is set, the golden file is (re)written instead.
+fn assert_golden(name: &str, actual: Value) {
+    let path = golden_path(name);
+
+    if std::env::var("UPDATE_GOLDEN").is_ok() {
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        let rendered = serde_json::to_string_pretty(&actual).unwrap();
+        std::fs::write(&path, rendered + "\n").unwrap();
+        return;
+    }
+
+    let expected = std::fs::read_to_string(&path).unwrap_or_else(|e| {
+        panic!(
+            "Missing golden file {} ({}); run with UPDATE_GOLDEN=1 to create it",
+            path.display(),
+            e
+        )
+    });
+    let expected: Value = serde_json::from_str(&expected).unwrap();
+
+    assert_eq!(
+        actual,
+        expected,
+        "Datadog payload does not match golden file {}; run with UPDATE_GOLDEN=1 if the change is intended",
+        path.display()
+    );
+}
+
+/// Runs the given test body with the P7S1 namespace configured (or not)
+fn with_p7s1_namespace<F: FnOnce()>(namespace: Option<&str>, f: F) {
+    temp_env::with_var("DATADOG_NAMESPACE_P7S1", namespace, f);
+}
+
+#[test]
+fn golden_enterprise_metrics() {
+    with_p7s1_namespace(None, || {
+        let payload = prepare(&[create_test_metrics()], "github.copilot");
+        assert_golden("enterprise", payload);
+    });
+}
+
+#[test]
+fn golden_team_metrics() {
+    with_p7s1_namespace(None, || {
+        let payload = prepare(
+            &[create_test_team_metrics()],
+            "github.copilot.team.platform",
+        );
+        assert_golden("team", payload);
+    });
+}
+
+#[test]
+fn golden_ide_chat_metrics_with_p7s1_namespace() {
+    with_p7s1_namespace(Some("gh.p7s1"), || {
+        let payload = prepare(&[create_chat_metrics()], "github.copilot");
+        assert_golden("ide_chat_p7s1", payload);
+    });
+}
+
+#[test]
+fn golden_code_review_metrics() {
+    with_p7s1_namespace(None, || {
+        let payload = prepare(&[create_code_review_metrics()], "github.copilot");
+        assert_golden("code_review", payload);
+    });
+}
+
+#[test]
+fn golden_premium_request_metrics() {
+    let client = DatadogClient::new("golden_api_key".to_string());
+    let payload = Value::Array(client.prepare_premium_request_metrics(
+        &create_premium_request_usage(),
+        "github.copilot",
+        GOLDEN_TIMESTAMP,
+    ));
+    assert_golden("premium_requests", payload);
+}
+
+#[test]
+fn golden_api_response_metrics() {
+    with_p7s1_namespace(None, || {
+        let mut metrics = create_mock_api_response().unwrap();
+        // The mock response uses today's date; pin it for a stable payload
+        for metric in &mut metrics {
+            metric.date = "2023-03-02".to_string();
+        }
+        let payload = prepare(&metrics, "github.copilot");
+        assert_golden("api_response", payload);
+    });
+}
+
+#[test]
+fn golden_dashboard_definition() {
+    let definition = dashboard_definition("github.copilot", &dashboard_title("github.copilot"));
+    assert_golden("dashboard", definition);
+}
+
+#[test]
+fn dashboard_queries_metrics_that_are_sent() {
+    with_p7s1_namespace(None, || {
+        let metrics = [
+            create_test_metrics(),
+            create_chat_metrics(),
+            create_code_review_metrics(),
+        ];
+        let payload = prepare(&metrics, "github.copilot");
+        let sent: Vec<&str> = payload
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|s| s["metric"].as_str().unwrap())
+            .collect();
+
+        let definition = dashboard_definition("github.copilot", "Copilot");
+        let queries = definition["widgets"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .flat_map(|w| w["definition"]["requests"].as_array().unwrap())
+            .map(|r| r["q"].as_str().unwrap());
+        for query in queries {
+            for metric in query.split(':').skip(1) {
+                let metric = &metric[..metric.find('{').unwrap()];
+                assert!(sent.contains(&metric), "{} is not sent", metric);
+            }
+        }
+    });
+}
+
+#[test]
+fn schema_warn_mode_reports_unknown_fields_metric() {
+    let mut metric = create_test_metrics();
+    metric
+        .extra
+        .insert("copilot_new_feature".to_string(), serde_json::json!({}));
+
+    let find = |payload: &Value| {
+        payload
+            .as_array()
+            .unwrap()
+            .iter()
+            .find(|s| s["metric"] == "github.copilot.schema.unknown_fields")
+            .cloned()
+    };
+
+    temp_env::with_var("DATADOG_NAMESPACE_P7S1", None::<&str>, || {
+        let client = DatadogClient::builder()
+            .api_key("golden_api_key")
+            .report_schema_drift(true)
+            .build()
+            .unwrap();
+        let payload = Value::Array(client.prepare_all_metrics(
+            std::slice::from_ref(&metric),
+            "github.copilot",
+            GOLDEN_TIMESTAMP,
+        ));
+        let series = find(&payload).expect("Missing schema drift metric");
+        assert_eq!(series["points"][0]["value"], 1.0);
+
+        // The environment is only read by the configuration
+        temp_env::with_var("STRICT_SCHEMA", Some("warn"), || {
+            let client = DatadogClient::builder()
+                .api_key("golden_api_key")
+                .build()
+                .unwrap();
+            let payload = Value::Array(client.prepare_all_metrics(
+                std::slice::from_ref(&metric),
+                "github.copilot",
+                GOLDEN_TIMESTAMP,
+            ));
+            assert!(find(&payload).is_none());
+        });
+    });
+}
+
+#[test]
+fn destination_namespace_replaces_base_prefix() {
+    let mut destination = DatadogDestination::new("security", "dd_key");
+    assert_eq!(
+        destination.map_namespace("github.copilot", "github.copilot.team.web"),
+        "github.copilot.team.web"
+    );
+
+    destination.namespace = Some("sec.copilot".to_string());
+    assert_eq!(
+        destination.map_namespace("github.copilot", "github.copilot"),
+        "sec.copilot"
+    );
+    assert_eq!(
+        destination.map_namespace("github.copilot", "github.copilot.team.web"),
+        "sec.copilot.team.web"
+    );
+    assert_eq!(
+        destination.map_namespace("github.copilot", "github.copilotx.team"),
+        "github.copilotx.team"
+    );
+}
+
+#[test]
+fn extra_tags_are_appended_to_every_series() {
+    let mut payload = prepare(&[create_test_team_metrics()], "github.copilot.team.web");
+    let series = payload.as_array_mut().unwrap();
+    append_tags(series, &["cost_center:42".to_string()]);
+
+    assert!(!series.is_empty());
+    for s in series.iter() {
+        let tags = s["tags"].as_array().unwrap();
+        assert_eq!(tags.last().unwrap(), "cost_center:42");
+    }
+}
+
+#[test]
+fn editor_versions_are_tagged() {
+    let mut metric = create_chat_metrics();
+    let editor = &mut metric
+        .copilot_ide_chat
+        .as_mut()
+        .unwrap()
+        .editors
+        .as_mut()
+        .unwrap()[0];
+    editor.versions = Some(
+        serde_json::from_value(serde_json::json!([
+            { "version": "1.250.0", "total_engaged_users": 12 },
+            { "version": "1.180.2", "total_engaged_users": 3 }
+        ]))
+        .unwrap(),
+    );
+
+    let payload = prepare(&[metric], "github.copilot");
+    let versions: Vec<(f64, Vec<&str>)> = payload
+        .as_array()
+        .unwrap()
+        .iter()
+        .filter(|s| s["metric"] == "github.copilot.ide.chat.editors.versions.total_engaged_users")
+        .map(|s| {
+            (
+                s["points"][0]["value"].as_f64().unwrap(),
+                s["tags"]
+                    .as_array()
+                    .unwrap()
+                    .iter()
+                    .map(|t| t.as_str().unwrap())
+                    .filter(|t| t.starts_with("editor"))
+                    .collect(),
+            )
+        })
+        .collect();
+    assert_eq!(
+        versions,
+        vec![
+            (12.0, vec!["editor:VS Code", "editor_version:1.250.0"]),
+            (3.0, vec!["editor:VS Code", "editor_version:1.180.2"]),
+        ]
+    );
+}
+
+/// Series of `ide.code_completions.languages.total_engaged_users` as
+/// (value, language tags)
+fn language_series(client: &DatadogClient, metric: &CopilotMetrics) -> Vec<(f64, Vec<String>)> {
+    client
+        .prepare_all_metrics(
+            std::slice::from_ref(metric),
+            "github.copilot",
+            GOLDEN_TIMESTAMP,
+        )
+        .iter()
+        .filter(|s| {
+            s["metric"] == "github.copilot.ide.code_completions.languages.total_engaged_users"
+        })
+        .map(|s| {
+            (
+                s["points"][0]["value"].as_f64().unwrap(),
+                s["tags"]
+                    .as_array()
+                    .unwrap()
+                    .iter()
+                    .map(|t| t.as_str().unwrap().to_string())
+                    .filter(|t| t.contains("language:"))
+                    .collect(),
+            )
+        })
+        .collect()
+}
+
+#[test]
+fn language_aliases_merge_or_keep_raw_names() {
+    let language = |name: &str, engaged: i64| Language {
+        name: name.to_string(),
+        total_engaged_users: engaged,
+        total_code_suggestions: Some(engaged * 10),
+        total_code_acceptances: None,
+        total_code_lines_suggested: None,
+        total_code_lines_accepted: None,
+        extra: Default::default(),
+    };
+    let mut metric = create_test_metrics();
+    metric
+        .copilot_ide_code_completions
+        .as_mut()
+        .unwrap()
+        .languages = Some(vec![
+        language("typescript", 20),
+        language("typescriptreact", 5),
+        language("hcl", 3),
+    ]);
+
+    assert_eq!(
+        parse_aliases("hcl = terraform, , pcl=terraform "),
+        Ok([
+            ("hcl".to_string(), "terraform".to_string()),
+            ("pcl".to_string(), "terraform".to_string())
+        ]
+        .into())
+    );
+    assert!(parse_aliases("hcl=terraform,invalid").is_err());
+    assert!(parse_aliases("=x").is_err());
+    let normalizer = LanguageNormalizer::new(parse_aliases("hcl=terraform").unwrap());
+
+    let merged = DatadogClient::new("golden_api_key".to_string())
+        .with_language_normalizer(normalizer.clone());
+    assert_eq!(
+        language_series(&merged, &metric),
+        vec![
+            (25.0, vec!["language:typescript".to_string()]),
+            (3.0, vec!["language:terraform".to_string()]),
+        ]
+    );
+
+    let raw = DatadogClient::new("golden_api_key".to_string())
+        .with_language_normalizer(normalizer.with_raw_tag(true));
+    assert_eq!(
+        language_series(&raw, &metric),
+        vec![
+            (20.0, vec!["language:typescript".to_string()]),
+            (
+                5.0,
+                vec![
+                    "language:typescript".to_string(),
+                    "raw_language:typescriptreact".to_string()
+                ]
+            ),
+            (
+                3.0,
+                vec![
+                    "language:terraform".to_string(),
+                    "raw_language:hcl".to_string()
+                ]
+            ),
+        ]
+    );
+}
+
+#[test]
+fn language_groups_sum_normalized_languages() {
+    let language = |name: &str, engaged: i64, suggestions: i64| Language {
+        name: name.to_string(),
+        total_engaged_users: engaged,
+        total_code_suggestions: Some(suggestions),
+        total_code_acceptances: None,
+        total_code_lines_suggested: None,
+        total_code_lines_accepted: None,
+        extra: Default::default(),
+    };
+    let languages = vec![
+        language("java", 10, 100),
+        language("kotlin", 4, 30),
+        language("typescriptreact", 6, 50),
+        language("rust", 2, 10),
+    ];
+    let groups = LanguageGroups::new(
+        [
+            (
+                "jvm".to_string(),
+                vec!["java".to_string(), "kotlin".to_string()],
+            ),
+            ("frontend".to_string(), vec!["typescript".to_string()]),
+            ("infra".to_string(), vec!["terraform".to_string()]),
+        ]
+        .into(),
+    );
+
+    let sums: Vec<(String, i64, Option<i64>)> = groups
+        .sum(&languages, &LanguageNormalizer::default())
+        .into_iter()
+        .map(|l| (l.name, l.total_engaged_users, l.total_code_suggestions))
+        .collect();
+    assert_eq!(
+        sums,
+        vec![
+            ("frontend".to_string(), 6, Some(50)),
+            ("jvm".to_string(), 14, Some(130)),
+        ]
+    );
+
+    let client = DatadogClient::new("golden_api_key".to_string()).with_language_groups(groups);
+    let mut metric = create_test_metrics();
+    metric
+        .copilot_ide_code_completions
+        .as_mut()
+        .unwrap()
+        .languages = Some(languages);
+    let series = client.prepare_all_metrics(&[metric], "github.copilot", GOLDEN_TIMESTAMP);
+    let jvm = series
+        .iter()
+        .find(|s| {
+            s["metric"]
+                == "github.copilot.ide.code_completions.language_groups.total_code_suggestions"
+                && s["tags"]
+                    .as_array()
+                    .unwrap()
+                    .contains(&Value::from("language_group:jvm"))
+        })
+        .unwrap();
+    assert_eq!(jvm["points"][0]["value"], 130.0);
+}
+
+#[test]
+fn tag_denylist_drops_breakdown_series() {
+    let client = DatadogClient::new("golden_api_key".to_string())
+        .with_tag_filter(TagFilter::new(None, vec!["repository".to_string()]));
+    let series = client.prepare_all_metrics(
+        &[create_code_review_metrics()],
+        "github.copilot",
+        GOLDEN_TIMESTAMP,
+    );
+
+    assert!(series
+        .iter()
+        .any(|s| s["metric"] == "github.copilot.code_review.total_reviews"));
+    assert!(series.iter().all(|s| s["tags"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .all(|t| !t.as_str().unwrap().starts_with("repository:"))));
+}
+
+#[test]
+fn tag_allowlist_keeps_only_listed_keys() {
+    let filter = TagFilter::new(Some(vec!["editor".to_string()]), Vec::new());
+    assert!(filter.keeps("date"));
+    assert!(filter.keeps("source"));
+    assert!(filter.keeps("editor"));
+    assert!(!filter.keeps("model"));
+
+    let client = DatadogClient::new("golden_api_key".to_string()).with_tag_filter(filter);
+    let series =
+        client.prepare_all_metrics(&[create_chat_metrics()], "github.copilot", GOLDEN_TIMESTAMP);
+    assert!(!series.is_empty());
+    assert!(series.iter().all(|s| s["tags"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .all(|t| !t.as_str().unwrap().starts_with("model:"))));
+}
+
+#[test]
+fn repository_patterns_match_wildcards() {
+    let filter = RepositoryFilter::new(
+        Some(vec!["acme/public-*".to_string(), "*-docs".to_string()]),
+        vec!["*secret*".to_string()],
+    );
+    assert!(filter.keeps("acme/public-website"));
+    assert!(filter.keeps("ACME/Public-API"));
+    assert!(filter.keeps("acme/api-docs"));
+    assert!(!filter.keeps("acme/internal"));
+    assert!(!filter.keeps("acme/public-secret-keys"));
+    assert!(RepositoryFilter::default().keeps("acme/internal"));
+}
+
+#[test]
+fn repository_filter_drops_repository_series_only() {
+    let repositories = |series: &[Value]| -> Vec<String> {
+        series
+            .iter()
+            .flat_map(|s| s["tags"].as_array().unwrap().clone())
+            .filter_map(|t| t.as_str()?.strip_prefix("repository:").map(String::from))
+            .collect()
+    };
+    let metrics = [create_test_metrics(), create_code_review_metrics()];
+
+    let client = DatadogClient::new("golden_api_key".to_string())
+        .with_repository_filter(RepositoryFilter::new(None, vec!["test-*".to_string()]));
+    let series = client.prepare_all_metrics(&metrics, "github.copilot", GOLDEN_TIMESTAMP);
+    assert!(repositories(&series).iter().all(|r| r == "docs"));
+    assert!(series
+        .iter()
+        .any(|s| s["metric"] == "github.copilot.dotcom.pull_requests.total_engaged_users"));
+    assert!(series
+        .iter()
+        .any(|s| s["metric"] == "github.copilot.code_review.total_reviews"));
+
+    let client = DatadogClient::new("golden_api_key".to_string())
+        .with_repository_filter(RepositoryFilter::new(Some(Vec::new()), Vec::new()));
+    let series = client.prepare_all_metrics(&metrics, "github.copilot", GOLDEN_TIMESTAMP);
+    assert!(repositories(&series).is_empty());
+}
+
+#[test]
+fn repository_names_are_replaced_with_stable_hashes() {
+    let filter = RepositoryFilter::default().with_hash_salt(Some("deployment-secret".to_string()));
+    let hash = filter.tag_value("test-repo").into_owned();
+    assert_eq!(hash.len(), 16);
+    assert_eq!(filter.tag_value("test-repo"), hash);
+    assert_ne!(filter.tag_value("docs"), hash);
+    let other_salt = RepositoryFilter::default().with_hash_salt(Some("other".to_string()));
+    assert_ne!(other_salt.tag_value("test-repo"), hash);
+
+    let client = DatadogClient::new("golden_api_key".to_string()).with_repository_filter(filter);
+    let series =
+        client.prepare_all_metrics(&[create_test_metrics()], "github.copilot", GOLDEN_TIMESTAMP);
+    let tags: Vec<&str> = series
+        .iter()
+        .flat_map(|s| s["tags"].as_array().unwrap())
+        .filter_map(|t| t.as_str())
+        .collect();
+    assert!(tags.contains(&format!("repository:{}", hash).as_str()));
+    assert!(!tags.contains(&"repository:test-repo"));
+    assert!(series.iter().any(|s| s["metric"]
+        == "github.copilot.dotcom.pull_requests.total_engaged_users"
+        && s["points"][0]["value"] == 200.0));
+}
+
+#[test]
+fn metric_prefix_builds_valid_names() {
+    let prefix = MetricPrefix::new("github.copilot").child("ide.chat");
+    assert_eq!(
+        prefix.metric("total_engaged_users"),
+        "github.copilot.ide.chat.total_engaged_users"
+    );
+
+    // Invalid namespaces are normalized instead of being rewritten by Datadog
+    let prefix = MetricPrefix::new("9github.copilot.team.web-app.");
+    assert_eq!(
+        prefix.metric("total_active_users"),
+        "github.copilot.team.web_app.total_active_users"
+    );
+}
+
+#[test]
+fn metric_name_normalization() {
+    assert!(names::is_valid("github.copilot.total_active_users"));
+    assert!(!names::is_valid("github..copilot"));
+    assert!(!names::is_valid("_github.copilot"));
+    assert!(!names::is_valid("github.cöpilot"));
+
+    assert_eq!(names::normalize("github..cöpilot"), "github.c_pilot");
+    assert_eq!(names::normalize("123"), "unknown");
+
+    let long = format!("github.{}", "a".repeat(300));
+    let normalized = names::normalize(&long);
+    assert_eq!(normalized.len(), names::MAX_METRIC_NAME_LEN);
+    assert!(names::is_valid(&normalized));
+}
+
+#[test]
+fn namespace_template_renders_scopes() {
+    let template = NamespaceTemplate::default();
+    assert_eq!(
+        template.render("github.copilot", "team", "web"),
+        "github.copilot.team.web"
+    );
+    assert_eq!(template.slug_tag("team", "web"), None);
+
+    let template = NamespaceTemplate::parse("{base}.teams.{slug}").unwrap();
+    assert_eq!(template.render("gh", "team", "web"), "gh.teams.web");
+
+    // Without the slug, teams share the namespace and are told apart by a tag
+    let template = NamespaceTemplate::parse("{base}.{scope}").unwrap();
+    assert_eq!(template.render("gh", "org", "acme"), "gh.org");
+    assert_eq!(
+        template.slug_tag("org", "acme").as_deref(),
+        Some("org:acme")
+    );
+    let template = NamespaceTemplate::parse("{base}").unwrap();
+    assert_eq!(template.render("gh", "team", "web"), "gh");
+
+    assert!(NamespaceTemplate::parse("{scope}.{slug}").is_err());
+    assert!(NamespaceTemplate::parse("{base}.{team}").is_err());
+    assert!(NamespaceTemplate::parse("{base}.{slug").is_err());
+}
+
+#[test]
+fn tag_templates_resolve_against_the_scope() {
+    let templates =
+        TagTemplates::parse("enterprise:{enterprise_id}, team:{team_slug}, env:{env}").unwrap();
+
+    let with_env = templates.clone().with_env(Some("prod".to_string()));
+    assert_eq!(
+        with_env.resolve(&TagContext::team("acme", "web")),
+        vec!["enterprise:acme", "team:web", "env:prod"]
+    );
+    assert_eq!(with_env.resolve(&TagContext::default()), vec!["env:prod"]);
+
+    // Placeholders without a value in the scope leave their tag out
+    assert_eq!(
+        templates.resolve(&TagContext::enterprise("acme")),
+        vec!["enterprise:acme"]
+    );
+    assert_eq!(
+        TagTemplates::parse("org:{org}")
+            .unwrap()
+            .resolve(&TagContext::org("acme", "acme-web")),
+        vec!["org:acme-web"]
+    );
+    assert!(templates.resolve(&TagContext::default()).is_empty());
+    assert!(templates
+        .clone()
+        .with_env(Some(String::new()))
+        .resolve(&TagContext::default())
+        .is_empty());
+
+    assert!(templates.uses("team_slug"));
+    assert!(TagTemplates::parse("team:{team}").is_err());
+    assert!(TagTemplates::parse("{env}").is_err());
+    assert!(TagTemplates::parse("env:{env").is_err());
+}
+
+#[test]
+fn throttle_cost_uses_stricter_limit() {
+    let throttle = Throttle::new(Some(10.0), Some(500.0));
+    assert!(throttle.is_enabled());
+    assert_eq!(throttle.cost(1), Duration::from_millis(100));
+    assert_eq!(throttle.cost(100), Duration::from_millis(200));
+
+    let unlimited = Throttle::new(None, Some(0.0));
+    assert!(!unlimited.is_enabled());
+    assert_eq!(unlimited.cost(100), Duration::ZERO);
+}
+
+#[test]
+fn throttle_spaces_consecutive_chunks() {
+    let throttle = Throttle::new(Some(20.0), None);
+    let start = Instant::now();
+    throttle.wait(100);
+    throttle.wait(100);
+    throttle.wait(100);
+    assert!(start.elapsed() >= Duration::from_millis(100));
+}
+
+#[test]
+fn day_window_selects_latest_complete_day() {
+    let metrics: Vec<CopilotMetrics> = ["2023-03-02", "2023-03-04", "2023-03-03", "bogus"]
+        .iter()
+        .map(|date| {
+            let mut metric = create_test_metrics();
+            metric.date = date.to_string();
+            metric
+        })
+        .collect();
+    let today = NaiveDate::from_ymd_opt(2023, 3, 4).unwrap();
+
+    assert_eq!(DayWindow::All.select_as_of(&metrics, today).len(), 4);
+
+    let latest = DayWindow::LatestCompleteDay.select_as_of(&metrics, today);
+    assert_eq!(latest.len(), 1);
+    assert_eq!(latest[0].date, "2023-03-03");
+
+    let first_day = NaiveDate::from_ymd_opt(2023, 3, 2).unwrap();
+    assert!(DayWindow::LatestCompleteDay
+        .select_as_of(&metrics, first_day)
+        .is_empty());
+
+    let range = DayWindow::Range {
+        since: first_day,
+        until: NaiveDate::from_ymd_opt(2023, 3, 3).unwrap(),
+    };
+    let selected = range.select_as_of(&metrics, today);
+    assert_eq!(selected.len(), 2);
+    assert!(selected.iter().all(|m| m.date != "2023-03-04"));
+}
+
+#[test]
+fn breakdown_limits_fold_languages_below_threshold() {
+    let language = |name: &str, engaged: i64, suggestions: Option<i64>| Language {
+        name: name.to_string(),
+        total_engaged_users: engaged,
+        total_code_suggestions: suggestions,
+        total_code_acceptances: None,
+        total_code_lines_suggested: None,
+        total_code_lines_accepted: None,
+        extra: Default::default(),
+    };
+    let mut metric = create_test_metrics();
+    metric
+        .copilot_ide_code_completions
+        .as_mut()
+        .unwrap()
+        .languages = Some(vec![
+        language("Rust", 300, Some(5000)),
+        language("COBOL", 1, Some(10)),
+        language("Zig", 2, None),
+        language("Go", 40, Some(700)),
+    ]);
+    let metrics = vec![metric];
+
+    let unlimited = BreakdownLimits::new(None);
+    assert!(matches!(unlimited.apply(&metrics), Cow::Borrowed(_)));
+
+    let limited = BreakdownLimits::new(Some(5)).apply(&metrics);
+    let languages = limited[0]
+        .copilot_ide_code_completions
+        .as_ref()
+        .unwrap()
+        .languages
+        .as_ref()
+        .unwrap();
+    let names: Vec<&str> = languages.iter().map(|l| l.name.as_str()).collect();
+    assert_eq!(names, vec!["Rust", "Go", "other"]);
+    assert_eq!(languages[2].total_engaged_users, 3);
+    assert_eq!(languages[2].total_code_suggestions, Some(10));
+    assert_eq!(languages[2].total_code_acceptances, None);
+}
+
+#[test]
+fn breakdown_limits_keep_top_n_editors_and_models() {
+    let model = |name: &str, engaged: i64, chats: i64| Model {
+        name: name.to_string(),
+        is_custom_model: false,
+        custom_model_training_date: None,
+        total_engaged_users: engaged,
+        languages: None,
+        total_chats: Some(chats),
+        total_chat_insertion_events: None,
+        total_chat_copy_events: None,
+        total_pr_summaries_created: None,
+        extra: Default::default(),
+    };
+    let editor = |name: &str, engaged: i64, models: Vec<Model>| Editor {
+        name: name.to_string(),
+        total_engaged_users: engaged,
+        models: Some(models),
+        versions: None,
+        extra: Default::default(),
+    };
+
+    let mut metric = create_chat_metrics();
+    metric.copilot_ide_chat.as_mut().unwrap().editors = Some(vec![
+        editor("Vim", 5, vec![model("default", 5, 20)]),
+        editor(
+            "VS Code",
+            100,
+            vec![model("default", 80, 400), model("gpt-4o", 20, 50)],
+        ),
+        editor("Emacs", 3, vec![model("default", 3, 7)]),
+    ]);
+
+    let limited = BreakdownLimits::new(None)
+        .with_top_n(Some(1))
+        .apply(std::slice::from_ref(&metric));
+    let editors = limited[0]
+        .copilot_ide_chat
+        .as_ref()
+        .unwrap()
+        .editors
+        .as_ref()
+        .unwrap();
+
+    let names: Vec<&str> = editors.iter().map(|e| e.name.as_str()).collect();
+    assert_eq!(names, vec!["VS Code", "other"]);
+    assert_eq!(editors[1].total_engaged_users, 8);
+
+    let vscode_models = editors[0].models.as_ref().unwrap();
+    assert_eq!(vscode_models.len(), 2);
+    assert_eq!(vscode_models[1].name, "other");
+    assert_eq!(vscode_models[1].total_chats, Some(50));
+
+    let other_models = editors[1].models.as_ref().unwrap();
+    assert_eq!(other_models.len(), 1);
+    assert_eq!(other_models[0].name, "default");
+    assert_eq!(other_models[0].total_chats, Some(27));
+}
+
+#[test]
+fn builder_validates_settings() {
+    assert!(DatadogClient::builder().build().is_err());
+    assert!(DatadogClient::builder()
+        .api_key("golden_api_key")
+        .chunk_size(0)
+        .build()
+        .is_err());
+    assert!(DatadogClient::builder()
+        .api_key("golden_api_key")
+        .concurrency(0)
+        .build()
+        .is_err());
+
+    // Options that are not set keep their neutral defaults, whatever the
+    // environment says
+    let client = DatadogClient::builder()
+        .api_key("golden_api_key")
+        .site("datadoghq.com")
+        .timeout(Duration::from_secs(5))
+        .chunk_size(10)
+        .build()
+        .unwrap();
+    let series = client.prepare_all_metrics(
+        &[create_code_review_metrics()],
+        "github.copilot",
+        GOLDEN_TIMESTAMP,
+    );
+    assert!(series.iter().any(|s| s["tags"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .any(|t| t.as_str().unwrap().starts_with("repository:"))));
+}
+
+/// Starts a local server answering Datadog submissions
+///
+/// `fails(n)` decides whether the n-th request (starting at 0) is answered with
+/// HTTP 500 instead of 202. Returns the series URL and the request counter.
+fn mock_datadog(
+    fails: impl Fn(usize) -> bool + Send + 'static,
+) -> (String, std::sync::Arc<std::sync::atomic::AtomicUsize>) {
+    mock_datadog_with(move |n, _| {
+        if fails(n) {
+            MockResponse::new("500 Internal Server Error", "{}")
+        } else {
+            MockResponse::new("202 Accepted", "{}")
+        }
+    })
+}
+
+/// Starts a local server answering the n-th Datadog submission with
+/// `respond(n, request)`, and returns the series URL and the request counter
+fn mock_datadog_with(
+    respond: impl Fn(usize, &MockRequest) -> MockResponse + Send + 'static,
+) -> (String, std::sync::Arc<std::sync::atomic::AtomicUsize>) {
+    let (base_url, requests) = mock_server::serve_forever(respond);
+    (format!("{}/api/v2/series", base_url), requests)
+}
+
+/// Metrics of the chunks a mock Datadog site received, with the API key they
+/// were sent with
+type Received = std::sync::Arc<std::sync::Mutex<Vec<(String, String)>>>;
+
+/// Starts a local server like `mock_datadog_with` that also records the
+/// metric of every single-series chunk it receives
+fn recording_datadog(
+    respond: impl Fn(&MockRequest) -> MockResponse + Send + 'static,
+) -> (String, Received) {
+    let received = Received::default();
+    let recorded = std::sync::Arc::clone(&received);
+    let (url, _) = mock_datadog_with(move |_, request| {
+        let metric = request.json()["series"][0]["metric"]
+            .as_str()
+            .unwrap()
+            .to_string();
+        let key = request.header("dd-api-key").unwrap_or_default().to_string();
+        recorded.lock().unwrap().push((key, metric));
+        respond(request)
+    });
+    (url, received)
+}
+
+/// Sorted metrics of the received chunks sent with an API key
+fn metrics_with_key(received: &Received, key: &str) -> Vec<String> {
+    let mut metrics: Vec<String> = received
+        .lock()
+        .unwrap()
+        .iter()
+        .filter(|(k, _)| k == key)
+        .map(|(_, metric)| metric.clone())
+        .collect();
+    metrics.sort();
+    metrics
+}
+
+fn gauges(count: usize) -> Vec<super::Gauge> {
+    (0..count)
+        .map(|i| super::Gauge::new(format!("gauge_{}", i), i as f64, "2025-06-01"))
+        .collect()
+}
+
+#[test]
+fn rate_limit_wait_beyond_the_maximum_fails_the_chunk() {
+    // Datadog asks to wait an hour; the chunk fails instead of sleeping
+    let (url, requests) = mock_datadog_with(|_, _| {
+        MockResponse::new("429 Too Many Requests", "{}").with_header("X-RateLimit-Reset", "3600")
+    });
+    let client = DatadogClient::builder()
+        .api_key("key")
+        .api_url(url)
+        .throttle(Throttle::new(None, None).with_max_rate_limit_wait(Duration::from_secs(5)))
+        .build()
+        .unwrap();
+
+    let started = Instant::now();
+    let err = client
+        .send_gauges(&gauges(1), "gh", &TagContext::default())
+        .unwrap_err();
+    assert!(started.elapsed() < Duration::from_secs(5));
+    // Sent once and retried once at the end of the send, without waiting
+    assert_eq!(requests.load(std::sync::atomic::Ordering::SeqCst), 2);
+    assert!(matches!(
+        err,
+        super::DatadogError::ChunksFailed { ref last, .. }
+            if matches!(**last, super::DatadogError::RateLimitWaitExceeded(3600, 5))
+    ));
+}
+
+#[test]
+fn failed_chunks_are_retried_after_the_remaining_chunks() {
+    // The second chunk fails once; the retry at the end succeeds
+    let (url, requests) = mock_datadog(|n| n == 1);
+    let client = DatadogClient::builder()
+        .api_key("key")
+        .api_url(url)
+        .chunk_size(1)
+        .build()
+        .unwrap();
+
+    client
+        .send_gauges(&gauges(3), "gh", &TagContext::default())
+        .unwrap();
+
+    let stats = client.stats();
+    assert_eq!(requests.load(std::sync::atomic::Ordering::SeqCst), 4);
+    assert_eq!(stats.chunks_sent, 3);
+    assert_eq!(stats.series_sent, 3);
+    assert_eq!(stats.chunks_failed, 0);
+}
+
+#[test]
+fn residual_chunk_failures_are_reported() {
+    // The second chunk fails on both attempts; the third is still delivered
+    let (url, _) = mock_datadog(|n| n == 1 || n == 3);
+    let client = DatadogClient::builder()
+        .api_key("key")
+        .api_url(url)
+        .chunk_size(2)
+        .concurrency(1)
+        .build()
+        .unwrap();
+
+    let err = client
+        .send_gauges(&gauges(5), "gh", &TagContext::default())
+        .unwrap_err();
+    assert!(matches!(
+        err,
+        super::DatadogError::ChunksFailed {
+            failed: 1,
+            total: 3,
+            series: 2,
+            ..
+        }
+    ));
+    assert!(err.is_retryable());
+
+    let stats = client.stats();
+    assert_eq!(stats.series_prepared, 5);
+    assert_eq!(stats.series_sent, 3);
+    assert_eq!(stats.chunks_failed, 1);
+    assert_eq!(stats.series_failed, 2);
+}
+
+#[test]
+fn chunks_are_sent_concurrently_with_failures_attributed() {
+    // Two of eight chunks fail on both attempts, whichever chunks they are
+    let (url, requests) = mock_datadog(|n| !(2..8).contains(&n));
+    let client = DatadogClient::builder()
+        .api_key("key")
+        .api_url(url)
+        .chunk_size(1)
+        .concurrency(4)
+        .build()
+        .unwrap();
+
+    let err = client
+        .send_gauges(&gauges(8), "gh", &TagContext::default())
+        .unwrap_err();
+    assert!(matches!(
+        err,
+        super::DatadogError::ChunksFailed {
+            failed: 2,
+            total: 8,
+            series: 2,
+            ..
+        }
+    ));
+    assert_eq!(requests.load(std::sync::atomic::Ordering::SeqCst), 10);
+    let stats = client.stats();
+    assert_eq!(stats.chunks_sent, 6);
+    assert_eq!(stats.chunks_failed, 2);
+}
+
+#[test]
+fn repeated_server_errors_fail_over_to_the_secondary_site() {
+    let (primary, primary_requests) = mock_datadog(|_| true);
+    let (secondary, secondary_requests) = mock_datadog(|_| false);
+    let secondary_site = secondary.trim_end_matches("/api/v2/series").to_string();
+    let mut failover = super::Failover::new(secondary_site.clone(), "secondary_key");
+    failover.threshold = 2;
+    let client = DatadogClient::builder()
+        .api_key("key")
+        .api_url(primary)
+        .chunk_size(1)
+        .concurrency(1)
+        .build()
+        .unwrap()
+        .with_failover(failover);
+
+    client
+        .send_gauges(&gauges(3), "gh", &TagContext::default())
+        .unwrap();
+
+    // Two chunks fail on the primary; the third and both retries go to the secondary
+    let ordering = std::sync::atomic::Ordering::SeqCst;
+    assert_eq!(primary_requests.load(ordering), 2);
+    assert_eq!(secondary_requests.load(ordering), 3);
+    assert_eq!(client.stats().chunks_sent, 3);
+    let event = client.failover_event().unwrap();
+    assert_eq!(event.to_site, secondary_site);
+    assert!(event.reason.contains("500"));
+}
+
+#[test]
+fn concurrent_chunks_fail_over_once_without_duplicates_or_losses() {
+    let (primary, primary_received) =
+        recording_datadog(|_| MockResponse::new("500 Internal Server Error", "{}"));
+    let (secondary, secondary_received) =
+        recording_datadog(|_| MockResponse::new("202 Accepted", "{}"));
+    let secondary_site = secondary.trim_end_matches("/api/v2/series").to_string();
+    let mut failover = super::Failover::new(secondary_site.clone(), "secondary_key");
+    failover.threshold = 2;
+    let client = DatadogClient::builder()
+        .api_key("key")
+        .api_url(primary)
+        .chunk_size(1)
+        .build()
+        .unwrap()
+        .with_failover(failover);
+
+    client
+        .send_gauges(&gauges(12), "gh", &TagContext::default())
+        .unwrap();
+
+    // Chunks in flight when the threshold is reached fail on the primary
+    // once; every chunk reaches the secondary exactly once
+    let mut expected: Vec<String> = (0..12).map(|i| format!("gh.gauge_{}", i)).collect();
+    expected.sort();
+    assert_eq!(
+        metrics_with_key(&secondary_received, "secondary_key"),
+        expected
+    );
+    let failed = metrics_with_key(&primary_received, "key");
+    assert!(failed.len() >= 2);
+    assert!(failed.windows(2).all(|pair| pair[0] != pair[1]));
+    assert_eq!(client.stats().chunks_sent, 12);
+    assert_eq!(client.stats().chunks_failed, 0);
+    assert_eq!(client.failover_event().unwrap().to_site, secondary_site);
+}
+
+#[test]
+fn rejected_api_key_is_retried_with_the_secondary_key() {
+    let (url, requests) = mock_datadog_with(|_, request| match request.header("dd-api-key") {
+        Some("new_key") => MockResponse::new("202 Accepted", "{}"),
+        _ => MockResponse::new("403 Forbidden", "{}"),
+    });
+    let client = DatadogClient::builder()
+        .api_key("old_key")
+        .api_url(url)
+        .chunk_size(1)
+        .concurrency(1)
+        .build()
+        .unwrap()
+        .with_secondary_api_key("new_key");
+
+    client
+        .send_gauges(&gauges(3), "gh", &TagContext::default())
+        .unwrap();
+
+    // Only the first chunk tries the rejected key; the rest use the secondary key
+    assert_eq!(requests.load(std::sync::atomic::Ordering::SeqCst), 4);
+    assert_eq!(client.stats().chunks_sent, 3);
+    assert_eq!(client.stats().chunks_failed, 0);
+}
+
+#[test]
+fn concurrent_chunks_switch_to_the_secondary_key_without_duplicates_or_losses() {
+    let (url, received) = recording_datadog(|request| match request.header("dd-api-key") {
+        Some("new_key") => MockResponse::new("202 Accepted", "{}"),
+        _ => MockResponse::new("403 Forbidden", "{}"),
+    });
+    let client = DatadogClient::builder()
+        .api_key("old_key")
+        .api_url(url)
+        .chunk_size(1)
+        .build()
+        .unwrap()
+        .with_secondary_api_key("new_key");
+
+    client
+        .send_gauges(&gauges(12), "gh", &TagContext::default())
+        .unwrap();
+
+    // Chunks in flight before the switch try the rejected key once; every
+    // chunk is accepted with the secondary key exactly once
+    let mut expected: Vec<String> = (0..12).map(|i| format!("gh.gauge_{}", i)).collect();
+    expected.sort();
+    assert_eq!(metrics_with_key(&received, "new_key"), expected);
+    let rejected = metrics_with_key(&received, "old_key");
+    assert!(!rejected.is_empty());
+    assert!(rejected.windows(2).all(|pair| pair[0] != pair[1]));
+    assert_eq!(client.stats().chunks_sent, 12);
+    assert_eq!(client.stats().chunks_failed, 0);
+}
+
+#[test]
+fn batched_series_of_all_namespaces_are_sent_in_one_pass() {
+    let (url, requests) = mock_datadog(|_| false);
+    let client = DatadogClient::builder()
+        .api_key("key")
+        .api_url(url)
+        .chunk_size(5)
+        .build()
+        .unwrap()
+        .with_batching();
+
+    client
+        .send_gauges(&gauges(2), "gh.team.web", &TagContext::default())
+        .unwrap();
+    client
+        .send_gauges(&gauges(3), "gh.team.api", &TagContext::default())
+        .unwrap();
+    let ordering = std::sync::atomic::Ordering::SeqCst;
+    assert_eq!(requests.load(ordering), 0);
+    assert_eq!(client.stats().series_prepared, 5);
+
+    client.flush().unwrap();
+    assert_eq!(requests.load(ordering), 1);
+    assert_eq!(client.stats().series_sent, 5);
+
+    // Nothing is left to send
+    client.flush().unwrap();
+    assert_eq!(requests.load(ordering), 1);
+}
+
+/// Scoped sinks share the clients of the run, and each resolves the tag
+/// templates against its own enterprise, team or organization
+#[test]
+fn scoped_sinks_resolve_tag_templates_for_their_scope() {
+    use super::{DatadogSettings, DatadogSink};
+    use std::sync::{Arc, Mutex};
+
+    let received = Arc::new(Mutex::new(Vec::new()));
+    let recorded = Arc::clone(&received);
+    let (base_url, _) = mock_server::serve_forever(move |_, request| {
+        let tags = request.json()["series"][0]["tags"].clone();
+        recorded.lock().unwrap().push(tags);
+        MockResponse::new("202 Accepted", "{}")
+    });
+    let mut destination = DatadogDestination::new("primary", "dd_key");
+    destination.site = base_url;
+    let templates = TagTemplates::parse("enterprise:{enterprise_id},team:{team_slug},env:{env}")
+        .unwrap()
+        .with_env(Some("prod".to_string()));
+    let settings = DatadogSettings {
+        tag_templates: templates,
+        ..DatadogSettings::default()
+    };
+    let sink = DatadogSink::new(&[destination], "gh", &settings);
+
+    let gauge = [super::Gauge::new("heartbeat", 1.0, "2025-06-01")];
+    sink.send_gauges(&gauge, "gh").unwrap();
+    let enterprise = sink.scoped(TagContext::enterprise("acme"));
+    enterprise.send_gauges(&gauge, "gh").unwrap();
+    let team = enterprise.scoped(TagContext::team("acme", "web"));
+    team.send_gauges(&gauge, "gh.team.web").unwrap();
+
+    let tags: Vec<Vec<String>> = received
+        .lock()
+        .unwrap()
+        .iter()
+        .map(|tags| serde_json::from_value(tags.clone()).unwrap())
+        .collect();
+    let standard = ["date:2025-06-01", "source:github-copilot-metrics"];
+    assert_eq!(tags[0], [&standard[..], &["env:prod"]].concat());
+    assert_eq!(
+        tags[1],
+        [&standard[..], &["enterprise:acme", "env:prod"]].concat()
+    );
+    assert_eq!(
+        tags[2],
+        [&standard[..], &["enterprise:acme", "team:web", "env:prod"]].concat()
+    );
+    // Every send counts towards the sink of the run
+    assert_eq!(sink.stats().series_sent, 3);
+}
+
+/// Client options set on the builder apply to every scoped copy of the sink
+#[test]
+fn sink_builder_options_apply_to_scoped_copies() {
+    use super::{DatadogSettings, DatadogSink};
+    use std::sync::atomic::Ordering;
+
+    let (base_url, requests) =
+        mock_server::serve_forever(|_, _| MockResponse::new("202 Accepted", "{}"));
+    let mut destination = DatadogDestination::new("primary", "dd_key");
+    destination.site = base_url;
+    let sink = DatadogSink::builder(&[destination], "gh", &DatadogSettings::default())
+        .day_window(DayWindow::All)
+        .batching()
+        .build();
+    let team = sink.scoped(TagContext::team("acme", "web"));
+
+    // Both queue into the shared batch until it is flushed
+    let gauge = [super::Gauge::new("heartbeat", 1.0, "2025-06-01")];
+    sink.send_gauges(&gauge, "gh").unwrap();
+    team.send_gauges(&gauge, "gh.team.web").unwrap();
+    assert_eq!(requests.load(Ordering::SeqCst), 0);
+
+    team.flush().unwrap();
+    assert_eq!(requests.load(Ordering::SeqCst), 1);
+    assert_eq!(sink.stats().series_sent, 2);
+}
+
+/// Premium request usage of an organization keeps the fallback tag
+#[test]
+fn test_premium_request_usage_keeps_extra_tags() {
+    use super::{DatadogSettings, DatadogSink};
+    use crate::processors::enterprise::ORG_FALLBACK_TAG;
+
+    let (base_url, server) = mock_server::serve(vec![MockResponse::new("202 Accepted", "{}")]);
+    let mut destination = DatadogDestination::new("primary", "dd_key");
+    destination.site = base_url;
+    let sink = DatadogSink::new(&[destination], "gh", &DatadogSettings::default());
+
+    sink.send_premium_request_usage(
+        &create_premium_request_usage(),
+        "gh",
+        &[ORG_FALLBACK_TAG.to_string()],
+    )
+    .unwrap();
+
+    let requests = server.join().unwrap();
+    let series = requests[0].json()["series"].clone();
+    assert!(!series.as_array().unwrap().is_empty());
+    for series in series.as_array().unwrap() {
+        assert!(series["tags"]
+            .as_array()
+            .unwrap()
+            .contains(&Value::from(ORG_FALLBACK_TAG)));
+    }
+}
+
+/// Allocations and time of preparing a 28-day window of three enterprises
+///
+/// Not run by default; see `alloc_counter` for how to run it on its own in a
+/// release build.
+#[test]
+#[ignore]
+fn prepare_all_metrics_benchmark() {
+    const RUNS: u32 = 20;
+    let metrics = create_window_fixture(3, 28);
+    let client = DatadogClient::new("key".to_string());
+
+    // Warm up, and check the size of the fixture
+    let series = client.prepare_all_metrics(&metrics, "github.copilot", GOLDEN_TIMESTAMP);
+    assert_eq!(metrics.len(), 84);
+
+    let before = alloc_counter::allocations();
+    let started = Instant::now();
+    for _ in 0..RUNS {
+        std::hint::black_box(client.prepare_all_metrics(
+            std::hint::black_box(&metrics),
+            "github.copilot",
+            GOLDEN_TIMESTAMP,
+        ));
+    }
+    let elapsed = started.elapsed() / RUNS;
+    let allocations = (alloc_counter::allocations() - before) / RUNS as usize;
+
+    println!(
+        "prepare_all_metrics: {} days, {} series, {} allocations, {:.1} ms per run",
+        metrics.len(),
+        series.len(),
+        allocations,
+        elapsed.as_secs_f64() * 1000.0
+    );
+}
+
+proptest::proptest! {
+    /// Every prepared point is a finite number and every series carries the
+    /// date of its day and the source tag, whatever the API returned
+    #[test]
+    fn prop_prepared_series_are_finite_and_tagged(
+        metrics in proptest::collection::vec(arb_copilot_metrics(), 1..3)
+    ) {
+        let client = DatadogClient::new("key".to_string());
+        let series = client.prepare_all_metrics(&metrics, "github.copilot", GOLDEN_TIMESTAMP);
+        proptest::prop_assert!(!series.is_empty());
+
+        let dates: Vec<String> = metrics.iter().map(|m| format!("date:{}", m.date)).collect();
+        for series in &series {
+            let tags: Vec<&str> = series["tags"]
+                .as_array()
+                .unwrap()
+                .iter()
+                .filter_map(Value::as_str)
+                .collect();
+            proptest::prop_assert!(
+                tags.iter().any(|tag| dates.iter().any(|date| date == tag)),
+                "no date tag on {}",
+                series
+            );
+            proptest::prop_assert!(tags.contains(&"source:github-copilot-metrics"));
+            for point in series["points"].as_array().unwrap() {
+                let value = point["value"].as_f64();
+                proptest::prop_assert!(
+                    value.is_some_and(f64::is_finite),
+                    "non-finite value in {}",
+                    series
+                );
+            }
+        }
+    }
+}
@@ -0,0 +1,167 @@
+//! # Submission Throttling
+//!
+//! Very large runs (many enterprises, teams and destinations) send hundreds of
+//! chunks in quick succession and can trip Datadog's intake rate limits. This
+//! module provides `Throttle`, which paces chunk submission so the collector
+//! slows down instead of failing.
+//!
+//! The throttle is configured with two optional limits:
+//!
+//! - `DATADOG_MAX_CHUNKS_PER_SECOND`: maximum number of requests per second
+//! - `DATADOG_MAX_POINTS_PER_SECOND`: maximum number of series per second
+//!
+//! Without either variable chunks are sent as fast as possible. With a limit,
+//! the throttle serializes all senders of a client while it waits (see
+//! `Throttle::wait`).
+//!
+//! Chunks Datadog rejects with HTTP 429 are retried after the delay Datadog
+//! asks for, unless it exceeds `DATADOG_MAX_RATE_LIMIT_WAIT_SECS` (default:
+//! 60); the chunk then fails instead of blocking the invocation.
+
+use std::sync::Mutex;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use tracing::debug;
+
+/// Longest delay a rate-limited chunk is retried after, by default
+pub const DEFAULT_MAX_RATE_LIMIT_WAIT: Duration = Duration::from_secs(60);
+
+/// Paces chunk submission to stay below configured rates
+#[derive(Debug)]
+pub struct Throttle {
+    /// Maximum number of chunks sent per second
+    chunks_per_second: Option<f64>,
+    /// Maximum number of series sent per second
+    points_per_second: Option<f64>,
+    /// Longest delay a rate-limited chunk is retried after
+    max_rate_limit_wait: Duration,
+    /// Earliest time the next chunk may be sent
+    next_allowed: Mutex<Option<Instant>>,
+}
+
+impl Default for Throttle {
+    fn default() -> Self {
+        Self::new(None, None)
+    }
+}
+
+impl Clone for Throttle {
+    /// Returns a throttle with the same limits that paces its own submissions
+    fn clone(&self) -> Self {
+        Self::new(self.chunks_per_second, self.points_per_second)
+            .with_max_rate_limit_wait(self.max_rate_limit_wait)
+    }
+}
+
+impl PartialEq for Throttle {
+    /// Compares the limits of two throttles
+    fn eq(&self, other: &Self) -> bool {
+        self.chunks_per_second == other.chunks_per_second
+            && self.points_per_second == other.points_per_second
+            && self.max_rate_limit_wait == other.max_rate_limit_wait
+    }
+}
+
+impl Throttle {
+    /// Creates a throttle with explicit limits
+    ///
+    /// Limits that are not positive are ignored.
+    ///
+    /// # Arguments
+    ///
+    /// * `chunks_per_second` - Maximum chunks per second, or `None` for no limit
+    /// * `points_per_second` - Maximum series per second, or `None` for no limit
+    pub fn new(chunks_per_second: Option<f64>, points_per_second: Option<f64>) -> Self {
+        Self {
+            chunks_per_second: chunks_per_second.filter(|r| *r > 0.0),
+            points_per_second: points_per_second.filter(|r| *r > 0.0),
+            max_rate_limit_wait: DEFAULT_MAX_RATE_LIMIT_WAIT,
+            next_allowed: Mutex::new(None),
+        }
+    }
+
+    /// Replaces the longest delay a rate-limited chunk is retried after
+    ///
+    /// # Arguments
+    ///
+    /// * `max_wait` - Longest delay to wait; a chunk Datadog asks to wait
+    ///   longer for fails instead
+    pub fn with_max_rate_limit_wait(mut self, max_wait: Duration) -> Self {
+        self.max_rate_limit_wait = max_wait;
+        self
+    }
+
+    /// Returns the longest delay a rate-limited chunk is retried after
+    pub fn max_rate_limit_wait(&self) -> Duration {
+        self.max_rate_limit_wait
+    }
+
+    /// Returns whether any limit is configured
+    pub fn is_enabled(&self) -> bool {
+        self.chunks_per_second.is_some() || self.points_per_second.is_some()
+    }
+
+    /// Returns how long a chunk of the given size occupies the rate budget
+    ///
+    /// # Arguments
+    ///
+    /// * `points` - Number of series in the chunk
+    pub fn cost(&self, points: usize) -> Duration {
+        let by_chunks = self.chunks_per_second.map_or(0.0, |rate| 1.0 / rate);
+        let by_points = self
+            .points_per_second
+            .map_or(0.0, |rate| points as f64 / rate);
+        Duration::from_secs_f64(by_chunks.max(by_points))
+    }
+
+    /// Blocks until a chunk of the given size may be sent
+    ///
+    /// The lock on the next allowed time is held while sleeping, so
+    /// concurrent senders queue up behind it and a throttled client starts
+    /// one chunk at a time regardless of `DATADOG_SEND_CONCURRENCY`. This
+    /// keeps the configured rates exact; only requests already started still
+    /// overlap.
+    ///
+    /// # Arguments
+    ///
+    /// * `points` - Number of series in the chunk about to be sent
+    pub fn wait(&self, points: usize) {
+        if !self.is_enabled() {
+            return;
+        }
+
+        let mut next_allowed = self
+            .next_allowed
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+
+        if let Some(at) = *next_allowed {
+            let now = Instant::now();
+            if at > now {
+                let delay = at - now;
+                debug!("Throttling Datadog submission for {:?}", delay);
+                thread::sleep(delay);
+            }
+        }
+
+        *next_allowed = Some(Instant::now() + self.cost(points));
+    }
+}
+
+/// Parses a rate or delay that must be a positive number
+///
+/// # Arguments
+///
+/// * `value` - The number, e.g. "2.5"
+///
+/// # Returns
+///
+/// * `Result<f64, String>` - The number, or a description of why the value
+///   is not a positive number
+pub fn parse_rate(value: &str) -> std::result::Result<f64, String> {
+    match value.trim().parse::<f64>() {
+        Ok(rate) if rate.is_finite() && rate > 0.0 => Ok(rate),
+        _ => Err(format!("expected a positive number, got {}", value)),
+    }
+}
@@ -0,0 +1,94 @@
+//! # Day Window
+//!
+//! The GitHub metrics API always returns a window of up to 28 days, but a
+//! collector running once a day only needs the newest day. This module
+//! provides `DayWindow`, which selects the days of a fetched window that are
+//! turned into series, so scheduled runs don't rewrite the same points every
+//! day.
+//!
+//! The window is configured with `SEND_LATEST_DAY_ONLY`: when set, only the
+//! most recent complete day (the newest date before today, UTC) is sent.
+//! A date range can be selected instead, e.g. to re-send specific days.
+
+use std::borrow::Cow;
+
+use chrono::{NaiveDate, Utc};
+use tracing::debug;
+
+use crate::models::github::CopilotMetrics;
+
+/// Selection of the fetched days that are sent to Datadog
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum DayWindow {
+    /// Send every fetched day
+    #[default]
+    All,
+    /// Send only the most recent complete day
+    LatestCompleteDay,
+    /// Send the days from `since` to `until`, both inclusive
+    Range {
+        /// First day to send
+        since: NaiveDate,
+        /// Last day to send
+        until: NaiveDate,
+    },
+}
+
+impl DayWindow {
+    /// Selects the days to send, relative to the current UTC date
+    ///
+    /// # Arguments
+    ///
+    /// * `metrics` - The fetched days in any order
+    pub fn select<'a>(&self, metrics: &'a [CopilotMetrics]) -> Cow<'a, [CopilotMetrics]> {
+        self.select_as_of(metrics, Utc::now().date_naive())
+    }
+
+    /// Selects the days to send, relative to a given date
+    ///
+    /// # Arguments
+    ///
+    /// * `metrics` - The fetched days in any order
+    /// * `today` - The current date; this day and later ones are incomplete
+    ///
+    /// # Returns
+    ///
+    /// * `Cow<[CopilotMetrics]>` - All days, the latest complete day (nothing
+    ///   if no day is complete), or the days within the range
+    pub fn select_as_of<'a>(
+        &self,
+        metrics: &'a [CopilotMetrics],
+        today: NaiveDate,
+    ) -> Cow<'a, [CopilotMetrics]> {
+        match self {
+            Self::All => Cow::Borrowed(metrics),
+            Self::Range { since, until } => Cow::Owned(
+                metrics
+                    .iter()
+                    .filter(|m| {
+                        NaiveDate::parse_from_str(&m.date, "%Y-%m-%d")
+                            .is_ok_and(|date| (*since..=*until).contains(&date))
+                    })
+                    .cloned()
+                    .collect(),
+            ),
+            Self::LatestCompleteDay => {
+                let latest = metrics
+                    .iter()
+                    .filter_map(|m| {
+                        let date = NaiveDate::parse_from_str(&m.date, "%Y-%m-%d").ok()?;
+                        (date < today).then_some((date, m))
+                    })
+                    .max_by_key(|(date, _)| *date);
+
+                match latest {
+                    Some((date, metric)) => {
+                        debug!("Sending only the latest complete day {}", date);
+                        Cow::Borrowed(std::slice::from_ref(metric))
+                    }
+                    None => Cow::Borrowed(&[]),
+                }
+            }
+        }
+    }
+}
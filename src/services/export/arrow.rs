@@ -0,0 +1,128 @@
+//! # Arrow Serialization
+//!
+//! Serializes metrics rows as Arrow record batches and writes them in the
+//! Arrow IPC file format (Feather v2), so downstream tooling such as pandas,
+//! Polars or DataFusion reads the fetched days without parsing JSON:
+//!
+//! ```python
+//! import pyarrow.feather as feather
+//! df = feather.read_table("metrics.arrow").to_pandas()
+//! ```
+//!
+//! The schema follows `COLUMNS`: `date` is a `Date32`, `scope` a `Utf8` and
+//! every count an `Int64`.
+
+use std::io::Write;
+use std::sync::Arc;
+
+use arrow_array::{ArrayRef, Date32Array, Int64Array, RecordBatch, StringArray};
+use arrow_ipc::writer::FileWriter;
+use arrow_schema::{ArrowError, DataType, Field, Schema, SchemaRef};
+use chrono::NaiveDate;
+
+use super::error::{ExportError, Result};
+use super::rows::{MetricsRow, COLUMNS};
+use crate::models::github::CopilotMetrics;
+
+/// Returns the Arrow type of a column of `COLUMNS`
+fn column_type(column: &str) -> DataType {
+    match column {
+        "date" => DataType::Date32,
+        "scope" => DataType::Utf8,
+        _ => DataType::Int64,
+    }
+}
+
+/// Returns the schema of the record batches
+pub fn schema() -> SchemaRef {
+    let fields: Vec<Field> = COLUMNS
+        .iter()
+        .map(|column| Field::new(*column, column_type(column), false))
+        .collect();
+    Arc::new(Schema::new(fields))
+}
+
+/// Converts metrics rows into a record batch
+///
+/// # Arguments
+///
+/// * `rows` - The rows, in the order they should appear in the batch
+///
+/// # Returns
+///
+/// * `Result<RecordBatch>` - The batch, or an error if a row's date isn't a
+///   valid YYYY-MM-DD date
+pub fn record_batch(rows: &[MetricsRow]) -> Result<RecordBatch> {
+    let epoch = NaiveDate::from_ymd_opt(1970, 1, 1).unwrap_or_default();
+    let dates = rows
+        .iter()
+        .map(|row| {
+            NaiveDate::parse_from_str(&row.date, "%Y-%m-%d")
+                .map(|date| (date - epoch).num_days() as i32)
+                .map_err(|e| {
+                    ExportError::Serialization(format!("Invalid date {}: {}", row.date, e))
+                })
+        })
+        .collect::<Result<Vec<i32>>>()?;
+    let counts = |value: fn(&MetricsRow) -> i64| -> ArrayRef {
+        Arc::new(Int64Array::from_iter_values(rows.iter().map(value)))
+    };
+
+    let columns: Vec<ArrayRef> = vec![
+        Arc::new(Date32Array::from(dates)),
+        Arc::new(StringArray::from_iter_values(
+            rows.iter().map(|row| row.scope.as_str()),
+        )),
+        counts(|r| r.total_active_users),
+        counts(|r| r.total_engaged_users),
+        counts(|r| r.code_completions_engaged_users),
+        counts(|r| r.ide_chat_engaged_users),
+        counts(|r| r.dotcom_chat_engaged_users),
+        counts(|r| r.pull_requests_engaged_users),
+        counts(|r| r.code_suggestions),
+        counts(|r| r.code_acceptances),
+        counts(|r| r.code_lines_suggested),
+        counts(|r| r.code_lines_accepted),
+    ];
+    RecordBatch::try_new(schema(), columns).map_err(arrow_error)
+}
+
+/// Writes record batches in the Arrow IPC file format
+///
+/// # Arguments
+///
+/// * `writer` - Destination of the file, e.g. a `File`
+/// * `batches` - The batches, all with the schema of `schema()`
+pub fn write_ipc<W: Write>(writer: W, batches: &[RecordBatch]) -> Result<()> {
+    let mut file = FileWriter::try_new(writer, &schema()).map_err(arrow_error)?;
+    for batch in batches {
+        file.write(batch).map_err(arrow_error)?;
+    }
+    file.finish().map_err(arrow_error)
+}
+
+/// Writes the days of a scope as one record batch in the Arrow IPC file format
+///
+/// # Arguments
+///
+/// * `writer` - Destination of the file
+/// * `scope` - Scope the days belong to, e.g. "enterprise:acme"
+/// * `metrics` - The days, written in the given order
+///
+/// # Returns
+///
+/// * `Result<usize>` - Number of rows written
+pub fn write_metrics<W: Write>(
+    writer: W,
+    scope: &str,
+    metrics: &[CopilotMetrics],
+) -> Result<usize> {
+    let batch = record_batch(&MetricsRow::from_metrics(scope, metrics))?;
+    write_ipc(writer, std::slice::from_ref(&batch))?;
+    Ok(batch.num_rows())
+}
+
+/// Converts an Arrow error into an `ExportError`
+fn arrow_error(e: ArrowError) -> ExportError {
+    ExportError::Serialization(format!("Arrow: {}", e))
+}
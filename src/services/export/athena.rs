@@ -0,0 +1,134 @@
+//! # Partitioned S3 Exporter
+//!
+//! Writes metrics rows to S3 in the Hive partition layout Athena (and Spark,
+//! Trino, DuckDB) understand, one JSON Lines object per day and scope:
+//!
+//! ```text
+//! s3://bucket/copilot_metrics/dt=2025-03-01/scope=enterprise%3Aacme/metrics.json
+//! ```
+//!
+//! Partition values are percent-encoded like Hive does, since scopes contain
+//! `:` and `/`. An object written again replaces the previous copy, so
+//! re-runs don't duplicate rows. With a Glue database and table configured,
+//! the partitions of the written objects are registered in the Data Catalog.
+
+use tracing::info;
+
+use super::error::{ExportError, Result};
+use super::rows::MetricsRow;
+use super::Exporter;
+use crate::services::aws::glue::{GlueClient, JsonPartition};
+use crate::services::aws::sigv4::uri_encode;
+use crate::services::aws::{AwsCredentials, S3Client};
+use crate::services::store::s3::parse_location;
+use crate::services::tls::AgentConfig;
+
+/// Name of the object holding the row of a partition
+const OBJECT_NAME: &str = "metrics.json";
+
+/// Glue table the partitions are registered in
+#[derive(Debug, Clone, PartialEq)]
+pub struct GlueTable {
+    /// Name of the Glue database
+    pub database: String,
+    /// Name of the table, partitioned by `dt` and `scope`
+    pub table: String,
+}
+
+/// Writes metrics rows as date-partitioned JSON Lines to S3
+pub struct PartitionedS3Exporter {
+    /// Client for the bucket
+    s3: S3Client,
+    /// Name of the bucket
+    bucket: String,
+    /// Key prefix of the table, without trailing slash
+    prefix: String,
+    /// Glue client and table, if partitions are registered
+    glue: Option<(GlueClient, GlueTable)>,
+}
+
+impl PartitionedS3Exporter {
+    /// Creates an exporter for a location
+    ///
+    /// # Arguments
+    ///
+    /// * `location` - `s3://<bucket>/<prefix>` location of the table
+    /// * `credentials` - Credentials and region used to sign requests
+    /// * `agent` - Settings of the HTTP agent
+    pub fn new(location: &str, credentials: AwsCredentials, agent: &AgentConfig) -> Result<Self> {
+        let (bucket, prefix) = parse_location(location)
+            .ok_or_else(|| ExportError::Config(format!("Invalid S3 location: {}", location)))?;
+        Ok(Self {
+            s3: S3Client::new(credentials, bucket, agent),
+            bucket: bucket.to_string(),
+            prefix: prefix.to_string(),
+            glue: None,
+        })
+    }
+
+    /// Registers the partitions of written objects in a Glue table
+    ///
+    /// # Arguments
+    ///
+    /// * `glue` - Client for the Data Catalog
+    /// * `table` - The Glue table, partitioned by `dt` and `scope`
+    pub fn with_glue(mut self, glue: GlueClient, table: GlueTable) -> Self {
+        self.glue = Some((glue, table));
+        self
+    }
+
+    /// Returns the partition of a row
+    pub fn partition(&self, row: &MetricsRow) -> JsonPartition {
+        JsonPartition {
+            values: vec![row.date.clone(), row.scope.clone()],
+            location: format!("s3://{}/{}", self.bucket, partition_path(&self.prefix, row)),
+        }
+    }
+}
+
+impl Exporter for PartitionedS3Exporter {
+    fn name(&self) -> &str {
+        "athena"
+    }
+
+    fn export(&self, rows: &[MetricsRow]) -> Result<usize> {
+        for row in rows {
+            let mut line =
+                serde_json::to_vec(row).map_err(|e| ExportError::Serialization(e.to_string()))?;
+            line.push(b'\n');
+            let key = format!("{}{}", partition_path(&self.prefix, row), OBJECT_NAME);
+            self.s3.put_object(&key, &line, "application/x-ndjson")?;
+        }
+
+        if let Some((glue, table)) = &self.glue {
+            let partitions: Vec<JsonPartition> =
+                rows.iter().map(|row| self.partition(row)).collect();
+            glue.create_partitions(&table.database, &table.table, &partitions)?;
+        }
+
+        if !rows.is_empty() {
+            info!(
+                "Wrote {} partitions to s3://{}/{}",
+                rows.len(),
+                self.bucket,
+                self.prefix
+            );
+        }
+        Ok(rows.len())
+    }
+}
+
+/// Returns the key prefix of a row's partition, with a trailing slash
+///
+/// # Arguments
+///
+/// * `prefix` - Key prefix of the table, may be empty
+/// * `row` - The row
+pub fn partition_path(prefix: &str, row: &MetricsRow) -> String {
+    let partition = format!("dt={}/scope={}/", row.date, uri_encode(&row.scope, true));
+    if prefix.is_empty() {
+        partition
+    } else {
+        format!("{}/{}", prefix, partition)
+    }
+}
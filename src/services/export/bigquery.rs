@@ -0,0 +1,321 @@
+//! # BigQuery Exporter
+//!
+//! Streams metrics rows into a BigQuery table through the `insertAll` API,
+//! authenticated as a service account with the BigQuery Data Editor and Job
+//! User roles on the dataset.
+//!
+//! The table is managed by the exporter: it is created from `schema` on the
+//! first export if it doesn't exist, and columns added to `COLUMNS` by later
+//! versions are appended to an existing table. Like the Sheets exporter, the
+//! days of a scope already in the table (by date) are skipped, so every run
+//! only inserts the new days of the window.
+
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use serde_json::{json, Value};
+use tracing::info;
+
+use super::error::{request_error, ExportError, Result};
+use super::google::ServiceAccount;
+use super::rows::{MetricsRow, COLUMNS};
+use super::sheets::new_rows;
+use super::Exporter;
+use crate::services::tls::AgentConfig;
+
+/// BigQuery API base URL
+pub const BIGQUERY_API_URL: &str = "https://bigquery.googleapis.com";
+
+/// OAuth scope needed to create tables and insert rows
+pub const BIGQUERY_SCOPE: &str = "https://www.googleapis.com/auth/bigquery";
+
+/// Rows sent per `insertAll` request
+const INSERT_BATCH: usize = 500;
+
+/// Returns the BigQuery type of a column of `COLUMNS`
+fn column_type(column: &str) -> &'static str {
+    match column {
+        "date" => "DATE",
+        "scope" => "STRING",
+        _ => "INTEGER",
+    }
+}
+
+/// Returns the table schema of the metrics rows, in the shape of the
+/// BigQuery API's `TableSchema`
+pub fn schema() -> Value {
+    let fields: Vec<Value> = COLUMNS
+        .iter()
+        .map(|column| {
+            json!({
+                "name": column,
+                "type": column_type(column),
+                "mode": "REQUIRED",
+            })
+        })
+        .collect();
+    json!({ "fields": fields })
+}
+
+/// Streams metrics rows into a BigQuery table
+pub struct BigQueryExporter {
+    /// Google Cloud project of the dataset
+    project_id: String,
+    /// Dataset the table lives in
+    dataset: String,
+    /// Name of the table
+    table: String,
+    /// Service account the requests are authorized as
+    account: ServiceAccount,
+    /// BigQuery API base URL
+    base_url: String,
+    /// Whether the table was found or created in this process
+    table_ready: AtomicBool,
+    /// HTTP agent using the configured TLS settings
+    agent: ureq::Agent,
+}
+
+impl BigQueryExporter {
+    /// Creates an exporter for a table
+    ///
+    /// # Arguments
+    ///
+    /// * `project_id` - Google Cloud project of the dataset
+    /// * `dataset` - Dataset the table lives in; it must exist
+    /// * `table` - Name of the table; it is created if missing
+    /// * `account` - Service account with access to the dataset
+    /// * `agent` - Settings of the HTTP agent
+    pub fn new(
+        project_id: &str,
+        dataset: &str,
+        table: &str,
+        account: ServiceAccount,
+        agent: &AgentConfig,
+    ) -> Self {
+        Self {
+            project_id: project_id.to_string(),
+            dataset: dataset.to_string(),
+            table: table.to_string(),
+            account,
+            base_url: BIGQUERY_API_URL.to_string(),
+            table_ready: AtomicBool::new(false),
+            agent: agent.agent_builder().build(),
+        }
+    }
+
+    /// Sends requests to another BigQuery API base URL, e.g. a mock server
+    pub fn with_base_url(mut self, base_url: &str) -> Self {
+        self.base_url = base_url.trim_end_matches('/').to_string();
+        self
+    }
+
+    /// Returns the URL of the project's API, with a path below it
+    fn project_url(&self, path: &str) -> String {
+        format!(
+            "{}/bigquery/v2/projects/{}/{}",
+            self.base_url, self.project_id, path
+        )
+    }
+
+    /// Returns the URL of the table, with a suffix
+    fn table_url(&self, suffix: &str) -> String {
+        self.project_url(&format!(
+            "datasets/{}/tables/{}{}",
+            self.dataset, self.table, suffix
+        ))
+    }
+
+    /// Creates the table, or adds the columns it is missing
+    fn ensure_table(&self, auth: &str) -> Result<()> {
+        if self.table_ready.load(Ordering::Relaxed) {
+            return Ok(());
+        }
+
+        match self
+            .agent
+            .get(&self.table_url(""))
+            .set("Authorization", auth)
+            .call()
+        {
+            Ok(response) => {
+                let table: Value = response
+                    .into_json()
+                    .map_err(|e| ExportError::Serialization(e.to_string()))?;
+                let mut fields = table["schema"]["fields"]
+                    .as_array()
+                    .cloned()
+                    .unwrap_or_default();
+                let missing: Vec<Value> = schema()["fields"]
+                    .as_array()
+                    .into_iter()
+                    .flatten()
+                    .filter(|field| !fields.iter().any(|f| f["name"] == field["name"]))
+                    // Columns added to an existing table must be nullable
+                    .map(|field| {
+                        json!({
+                            "name": field["name"],
+                            "type": field["type"],
+                            "mode": "NULLABLE",
+                        })
+                    })
+                    .collect();
+                if !missing.is_empty() {
+                    info!(
+                        "Adding {} columns to BigQuery table {}.{}",
+                        missing.len(),
+                        self.dataset,
+                        self.table
+                    );
+                    fields.extend(missing);
+                    self.agent
+                        .request("PATCH", &self.table_url(""))
+                        .set("Authorization", auth)
+                        .send_json(json!({ "schema": { "fields": fields } }))
+                        .map_err(request_error)?;
+                }
+            }
+            Err(ureq::Error::Status(404, _)) => {
+                info!("Creating BigQuery table {}.{}", self.dataset, self.table);
+                self.agent
+                    .post(&self.project_url(&format!("datasets/{}/tables", self.dataset)))
+                    .set("Authorization", auth)
+                    .send_json(json!({
+                        "tableReference": {
+                            "projectId": self.project_id,
+                            "datasetId": self.dataset,
+                            "tableId": self.table,
+                        },
+                        "schema": schema(),
+                        "timePartitioning": { "type": "DAY", "field": "date" },
+                    }))
+                    .map_err(request_error)?;
+            }
+            Err(e) => return Err(request_error(e)),
+        }
+
+        self.table_ready.store(true, Ordering::Relaxed);
+        Ok(())
+    }
+
+    /// Returns the date and scope of the table's rows of a scope since a day
+    fn existing_keys(&self, auth: &str, scope: &str, since: &str) -> Result<Vec<Vec<Value>>> {
+        let response: Value = self
+            .agent
+            .post(&self.project_url("queries"))
+            .set("Authorization", auth)
+            .send_json(json!({
+                "query": format!(
+                    "SELECT CAST(date AS STRING), scope FROM `{}.{}` \
+                     WHERE scope = @scope AND date >= @since",
+                    self.dataset, self.table
+                ),
+                "useLegacySql": false,
+                "parameterMode": "NAMED",
+                "queryParameters": [{
+                    "name": "scope",
+                    "parameterType": { "type": "STRING" },
+                    "parameterValue": { "value": scope },
+                }, {
+                    "name": "since",
+                    "parameterType": { "type": "DATE" },
+                    "parameterValue": { "value": since },
+                }],
+            }))
+            .map_err(request_error)?
+            .into_json()
+            .map_err(|e| ExportError::Serialization(e.to_string()))?;
+
+        if response["jobComplete"] == false {
+            return Err(ExportError::Network(
+                "BigQuery query did not complete in time".to_string(),
+            ));
+        }
+        Ok(query_rows(&response))
+    }
+}
+
+impl Exporter for BigQueryExporter {
+    fn name(&self) -> &str {
+        "bigquery"
+    }
+
+    fn export(&self, rows: &[MetricsRow]) -> Result<usize> {
+        let Some(scope) = rows.first().map(|row| row.scope.as_str()) else {
+            return Ok(0);
+        };
+        let token = self.account.access_token()?;
+        let auth = format!("Bearer {}", token);
+
+        self.ensure_table(&auth)?;
+        let since = rows
+            .iter()
+            .map(|row| row.date.as_str())
+            .min()
+            .unwrap_or_default();
+        let existing = self.existing_keys(&auth, scope, since)?;
+        let rows = new_rows(&existing, rows);
+
+        for batch in rows.chunks(INSERT_BATCH) {
+            let body = json!({
+                "rows": batch
+                    .iter()
+                    .map(|row| json!({
+                        "insertId": format!("{}|{}", row.date, row.scope),
+                        "json": row,
+                    }))
+                    .collect::<Vec<_>>(),
+            });
+            let response: Value = self
+                .agent
+                .post(&self.table_url("/insertAll"))
+                .set("Authorization", &auth)
+                .send_json(body)
+                .map_err(request_error)?
+                .into_json()
+                .map_err(|e| ExportError::Serialization(e.to_string()))?;
+
+            if let Some(errors) = response["insertErrors"]
+                .as_array()
+                .filter(|e| !e.is_empty())
+            {
+                return Err(ExportError::Rejected(format!(
+                    "BigQuery rejected {} rows: {}",
+                    errors.len(),
+                    errors[0]
+                )));
+            }
+        }
+
+        if !rows.is_empty() {
+            info!(
+                "Inserted {} rows of {} into BigQuery table {}.{}",
+                rows.len(),
+                scope,
+                self.dataset,
+                self.table
+            );
+        }
+        Ok(rows.len())
+    }
+}
+
+/// Flattens the rows of a query response into their cell values
+///
+/// # Arguments
+///
+/// * `response` - Response of the `queries` API, with rows of the form
+///   `{"f": [{"v": "2025-03-01"}, {"v": "enterprise:acme"}]}`
+pub fn query_rows(response: &Value) -> Vec<Vec<Value>> {
+    response["rows"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .map(|row| {
+            row["f"]
+                .as_array()
+                .into_iter()
+                .flatten()
+                .map(|cell| cell["v"].clone())
+                .collect()
+        })
+        .collect()
+}
@@ -0,0 +1,244 @@
+//! # DuckDB Exporter
+//!
+//! Keeps the fetched days in a local DuckDB file, split into the normalized
+//! tables of `NormalizedDay` (`days`, `languages`, `editors`, `models` and
+//! `repositories`), for ad-hoc SQL analysis on the collector box:
+//!
+//! ```sql
+//! SELECT language, sum(code_acceptances) FROM languages
+//! WHERE editor IS NULL GROUP BY language ORDER BY 2 DESC;
+//! ```
+//!
+//! The tables are created on open. Every run replaces the rows of the days it
+//! exports, so the file holds each day of a scope once, as last fetched.
+
+use std::sync::Mutex;
+
+use duckdb::{params, Connection, Transaction};
+
+use super::error::{ExportError, Result};
+use super::rows::MetricsRow;
+use super::tables::NormalizedDay;
+use super::Exporter;
+use crate::models::github::CopilotMetrics;
+
+/// Tables of the file, created on open
+const SCHEMA: &str = "
+CREATE TABLE IF NOT EXISTS days (
+    date DATE NOT NULL,
+    scope VARCHAR NOT NULL,
+    total_active_users BIGINT NOT NULL,
+    total_engaged_users BIGINT NOT NULL,
+    code_completions_engaged_users BIGINT NOT NULL,
+    ide_chat_engaged_users BIGINT NOT NULL,
+    dotcom_chat_engaged_users BIGINT NOT NULL,
+    pull_requests_engaged_users BIGINT NOT NULL,
+    code_suggestions BIGINT NOT NULL,
+    code_acceptances BIGINT NOT NULL,
+    code_lines_suggested BIGINT NOT NULL,
+    code_lines_accepted BIGINT NOT NULL
+);
+CREATE TABLE IF NOT EXISTS languages (
+    date DATE NOT NULL,
+    scope VARCHAR NOT NULL,
+    editor VARCHAR,
+    model VARCHAR,
+    language VARCHAR NOT NULL,
+    engaged_users BIGINT NOT NULL,
+    code_suggestions BIGINT,
+    code_acceptances BIGINT,
+    code_lines_suggested BIGINT,
+    code_lines_accepted BIGINT
+);
+CREATE TABLE IF NOT EXISTS editors (
+    date DATE NOT NULL,
+    scope VARCHAR NOT NULL,
+    feature VARCHAR NOT NULL,
+    editor VARCHAR NOT NULL,
+    engaged_users BIGINT NOT NULL
+);
+CREATE TABLE IF NOT EXISTS models (
+    date DATE NOT NULL,
+    scope VARCHAR NOT NULL,
+    feature VARCHAR NOT NULL,
+    editor VARCHAR,
+    repository VARCHAR,
+    model VARCHAR NOT NULL,
+    is_custom_model BOOLEAN NOT NULL,
+    engaged_users BIGINT NOT NULL,
+    total_chats BIGINT,
+    total_chat_insertion_events BIGINT,
+    total_chat_copy_events BIGINT,
+    total_pr_summaries_created BIGINT
+);
+CREATE TABLE IF NOT EXISTS repositories (
+    date DATE NOT NULL,
+    scope VARCHAR NOT NULL,
+    feature VARCHAR NOT NULL,
+    repository VARCHAR NOT NULL,
+    engaged_users BIGINT NOT NULL,
+    pr_summaries_created BIGINT,
+    reviews BIGINT,
+    review_comments BIGINT,
+    review_comments_applied BIGINT
+);
+";
+
+/// Tables holding rows of a day, cleared before the day is written again
+const TABLES: [&str; 5] = ["days", "languages", "editors", "models", "repositories"];
+
+/// Exports days into the normalized tables of a DuckDB file
+pub struct DuckDbExporter {
+    conn: Mutex<Connection>,
+}
+
+impl DuckDbExporter {
+    /// Opens (and creates if needed) a DuckDB file
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - Path of the DuckDB file
+    pub fn open(path: &str) -> Result<Self> {
+        let conn = Connection::open(path)
+            .map_err(|e| ExportError::Config(format!("Cannot open {}: {}", path, e)))?;
+        conn.execute_batch(SCHEMA).map_err(database_error)?;
+        Ok(Self {
+            conn: Mutex::new(conn),
+        })
+    }
+
+    /// Deletes the rows of a day from the given tables
+    fn clear_day(tx: &Transaction, tables: &[&str], row: &MetricsRow) -> duckdb::Result<()> {
+        for table in tables {
+            tx.execute(
+                &format!(
+                    "DELETE FROM {} WHERE date = CAST(? AS DATE) AND scope = ?",
+                    table
+                ),
+                params![row.date, row.scope],
+            )?;
+        }
+        Ok(())
+    }
+
+    /// Inserts the totals of a day
+    fn insert_totals(tx: &Transaction, d: &MetricsRow) -> duckdb::Result<()> {
+        tx.execute(
+            "INSERT INTO days VALUES (CAST(? AS DATE), ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)",
+            params![
+                d.date,
+                d.scope,
+                d.total_active_users,
+                d.total_engaged_users,
+                d.code_completions_engaged_users,
+                d.ide_chat_engaged_users,
+                d.dotcom_chat_engaged_users,
+                d.pull_requests_engaged_users,
+                d.code_suggestions,
+                d.code_acceptances,
+                d.code_lines_suggested,
+                d.code_lines_accepted,
+            ],
+        )?;
+        Ok(())
+    }
+
+    /// Replaces the rows of a day in every table
+    fn write_day(tx: &Transaction, day: &NormalizedDay) -> duckdb::Result<()> {
+        Self::clear_day(tx, &TABLES, &day.day)?;
+        Self::insert_totals(tx, &day.day)?;
+        for l in &day.languages {
+            tx.execute(
+                "INSERT INTO languages VALUES (CAST(? AS DATE), ?, ?, ?, ?, ?, ?, ?, ?, ?)",
+                params![
+                    l.date,
+                    l.scope,
+                    l.editor,
+                    l.model,
+                    l.language,
+                    l.engaged_users,
+                    l.code_suggestions,
+                    l.code_acceptances,
+                    l.code_lines_suggested,
+                    l.code_lines_accepted,
+                ],
+            )?;
+        }
+        for e in &day.editors {
+            tx.execute(
+                "INSERT INTO editors VALUES (CAST(? AS DATE), ?, ?, ?, ?)",
+                params![e.date, e.scope, e.feature, e.editor, e.engaged_users],
+            )?;
+        }
+        for m in &day.models {
+            tx.execute(
+                "INSERT INTO models VALUES (CAST(? AS DATE), ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)",
+                params![
+                    m.date,
+                    m.scope,
+                    m.feature,
+                    m.editor,
+                    m.repository,
+                    m.model,
+                    m.is_custom_model,
+                    m.engaged_users,
+                    m.total_chats,
+                    m.total_chat_insertion_events,
+                    m.total_chat_copy_events,
+                    m.total_pr_summaries_created,
+                ],
+            )?;
+        }
+        for r in &day.repositories {
+            tx.execute(
+                "INSERT INTO repositories VALUES (CAST(? AS DATE), ?, ?, ?, ?, ?, ?, ?, ?)",
+                params![
+                    r.date,
+                    r.scope,
+                    r.feature,
+                    r.repository,
+                    r.engaged_users,
+                    r.pr_summaries_created,
+                    r.reviews,
+                    r.review_comments,
+                    r.review_comments_applied,
+                ],
+            )?;
+        }
+        Ok(())
+    }
+}
+
+impl Exporter for DuckDbExporter {
+    fn name(&self) -> &str {
+        "duckdb"
+    }
+
+    /// Replaces the totals of the days only; `export_days` fills every table
+    fn export(&self, rows: &[MetricsRow]) -> Result<usize> {
+        let mut conn = self.conn.lock().unwrap_or_else(|e| e.into_inner());
+        let tx = conn.transaction().map_err(database_error)?;
+        for row in rows {
+            Self::clear_day(&tx, &TABLES[..1], row)
+                .and_then(|_| Self::insert_totals(&tx, row))
+                .map_err(database_error)?;
+        }
+        tx.commit().map_err(database_error)?;
+        Ok(rows.len())
+    }
+
+    fn export_days(&self, scope: &str, metrics: &[CopilotMetrics]) -> Result<usize> {
+        let mut conn = self.conn.lock().unwrap_or_else(|e| e.into_inner());
+        let tx = conn.transaction().map_err(database_error)?;
+        for metric in metrics {
+            Self::write_day(&tx, &NormalizedDay::new(scope, metric)).map_err(database_error)?;
+        }
+        tx.commit().map_err(database_error)?;
+        Ok(metrics.len())
+    }
+}
+
+/// Converts a DuckDB error into an `ExportError`
+fn database_error(e: duckdb::Error) -> ExportError {
+    ExportError::Rejected(format!("DuckDB: {}", e))
+}
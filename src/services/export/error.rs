@@ -0,0 +1,68 @@
+//! # Export Error Types
+//!
+//! This module defines structured error types for the metrics exporters.
+
+use thiserror::Error;
+
+use crate::services::aws::AwsError;
+
+/// Errors that can occur when exporting metrics rows
+#[derive(Error, Debug)]
+pub enum ExportError {
+    /// The exporter is misconfigured, e.g. unreadable credentials
+    #[error("Export configuration error: {0}")]
+    Config(String),
+
+    /// Obtaining an access token failed
+    #[error("Export authentication error: {0}")]
+    Authentication(String),
+
+    /// Network or transport error
+    #[error("Network error: {0}")]
+    Network(String),
+
+    /// HTTP request error with status code
+    #[error("Export HTTP error {0}: {1}")]
+    HttpError(u16, String),
+
+    /// The destination rejected some of the rows
+    #[error("Export rejected: {0}")]
+    Rejected(String),
+
+    /// An AWS request (S3, Glue) failed
+    #[error(transparent)]
+    Aws(#[from] AwsError),
+
+    /// A response or request body could not be (de)serialized
+    #[error("Export serialization error: {0}")]
+    Serialization(String),
+}
+
+impl ExportError {
+    /// Returns whether retrying the export later may succeed
+    pub fn is_retryable(&self) -> bool {
+        match self {
+            Self::Network(_) => true,
+            Self::HttpError(status, _) => *status == 429 || *status >= 500,
+            Self::Aws(AwsError::Network(_)) => true,
+            Self::Aws(AwsError::HttpError(status, _)) => *status == 429 || *status >= 500,
+            _ => false,
+        }
+    }
+}
+
+/// Converts a failed request into an `ExportError`
+pub(crate) fn request_error(e: ureq::Error) -> ExportError {
+    match e {
+        ureq::Error::Status(status, response) => {
+            let body = response
+                .into_string()
+                .unwrap_or_else(|_| "Could not read response body".to_string());
+            ExportError::HttpError(status, body)
+        }
+        ureq::Error::Transport(transport) => ExportError::Network(transport.to_string()),
+    }
+}
+
+/// A specialized Result type for export operations
+pub type Result<T> = std::result::Result<T, ExportError>;
@@ -0,0 +1,141 @@
+//! # Google Service Accounts
+//!
+//! Google APIs (Sheets, BigQuery) authenticate service accounts with OAuth
+//! access tokens obtained by signing a JWT with the account's private key
+//! (RS256) and exchanging it at the token endpoint. `ServiceAccount` reads
+//! the JSON key file downloaded from the Google Cloud console and caches the
+//! access token until shortly before it expires.
+
+use std::fs;
+use std::sync::Mutex;
+
+use chrono::Utc;
+use serde::Deserialize;
+use serde_json::json;
+use tracing::debug;
+
+use super::error::{request_error, ExportError, Result};
+use super::jwt::RsaSigner;
+use crate::services::tls::AgentConfig;
+
+/// Token endpoint used if the key file doesn't name one
+pub const DEFAULT_TOKEN_URI: &str = "https://oauth2.googleapis.com/token";
+
+/// Lifetime requested for access tokens, in seconds
+const TOKEN_LIFETIME: i64 = 3600;
+
+/// Seconds before expiry a cached token is replaced
+const TOKEN_MARGIN: i64 = 60;
+
+/// Fields of a service account key file
+#[derive(Deserialize)]
+struct KeyFile {
+    client_email: String,
+    private_key: String,
+    #[serde(default)]
+    token_uri: Option<String>,
+}
+
+/// Response of the token endpoint
+#[derive(Deserialize)]
+struct TokenResponse {
+    access_token: String,
+    #[serde(default)]
+    expires_in: Option<i64>,
+}
+
+/// A service account authorized for one OAuth scope
+pub struct ServiceAccount {
+    /// E-mail address of the service account
+    client_email: String,
+    /// Token endpoint
+    token_uri: String,
+    /// OAuth scope requested, e.g. "https://www.googleapis.com/auth/spreadsheets"
+    scope: String,
+    /// Private key signing the token requests
+    signer: RsaSigner,
+    /// Cached access token and its expiry (Unix seconds)
+    token: Mutex<Option<(String, i64)>>,
+    /// HTTP agent using the configured TLS settings
+    agent: ureq::Agent,
+}
+
+impl ServiceAccount {
+    /// Reads a service account key file
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - Path of the JSON key file
+    /// * `scope` - OAuth scope the access tokens are requested for
+    /// * `agent` - Settings of the HTTP agent
+    pub fn from_file(path: &str, scope: &str, agent: &AgentConfig) -> Result<Self> {
+        let contents = fs::read_to_string(path).map_err(|e| {
+            ExportError::Config(format!("Cannot read service account key {}: {}", path, e))
+        })?;
+        Self::from_json(&contents, scope, agent)
+    }
+
+    /// Parses the contents of a service account key file
+    ///
+    /// # Arguments
+    ///
+    /// * `json` - Contents of the JSON key file
+    /// * `scope` - OAuth scope the access tokens are requested for
+    /// * `agent` - Settings of the HTTP agent
+    pub fn from_json(json: &str, scope: &str, agent: &AgentConfig) -> Result<Self> {
+        let key: KeyFile = serde_json::from_str(json)
+            .map_err(|e| ExportError::Config(format!("Invalid service account key: {}", e)))?;
+        let signer = RsaSigner::from_pem(&key.private_key).map_err(|e| {
+            ExportError::Config(format!("Invalid service account private key: {}", e))
+        })?;
+
+        Ok(Self {
+            client_email: key.client_email,
+            token_uri: key
+                .token_uri
+                .unwrap_or_else(|| DEFAULT_TOKEN_URI.to_string()),
+            scope: scope.to_string(),
+            signer,
+            token: Mutex::new(None),
+            agent: agent.agent_builder().build(),
+        })
+    }
+
+    /// Returns an access token, requesting a new one if needed
+    pub fn access_token(&self) -> Result<String> {
+        let now = Utc::now().timestamp();
+        let mut cached = self.token.lock().unwrap_or_else(|e| e.into_inner());
+        if let Some((token, expires_at)) = cached.as_ref() {
+            if *expires_at - TOKEN_MARGIN > now {
+                return Ok(token.clone());
+            }
+        }
+
+        debug!("Requesting an access token for {}", self.client_email);
+        let response: TokenResponse = self
+            .agent
+            .post(&self.token_uri)
+            .send_form(&[
+                ("grant_type", "urn:ietf:params:oauth:grant-type:jwt-bearer"),
+                ("assertion", &self.assertion(now)?),
+            ])
+            .map_err(|e| ExportError::Authentication(request_error(e).to_string()))?
+            .into_json()
+            .map_err(|e| ExportError::Authentication(e.to_string()))?;
+
+        let expires_at = now + response.expires_in.unwrap_or(TOKEN_LIFETIME);
+        *cached = Some((response.access_token.clone(), expires_at));
+        Ok(response.access_token)
+    }
+
+    /// Builds the signed JWT exchanged for an access token
+    fn assertion(&self, now: i64) -> Result<String> {
+        self.signer.sign(&json!({
+            "iss": self.client_email,
+            "scope": self.scope,
+            "aud": self.token_uri,
+            "iat": now,
+            "exp": now + TOKEN_LIFETIME,
+        }))
+    }
+}
@@ -0,0 +1,384 @@
+//! # Metrics Export
+//!
+//! Optional exports of the fetched days as flat rows (`MetricsRow`) to
+//! destinations outside Datadog, such as spreadsheets that stakeholders track
+//! adoption in or the data warehouse engineering metrics are joined in.
+//! Exports are configured as a list in the configuration file (`exports`) or
+//! in `EXPORT_SINKS`, each entry selecting its exporter by `type`:
+//!
+//! ```json
+//! [{ "type": "google_sheets", "spreadsheet_id": "1AbC...", "sheet": "Copilot",
+//!    "credentials_file": "/opt/ghrust/service-account.json" },
+//!  { "type": "bigquery", "project_id": "analytics", "dataset": "engineering",
+//!    "credentials_file": "/opt/ghrust/service-account.json" },
+//!  { "type": "snowflake", "account": "myorg-myaccount", "user": "GHRUST",
+//!    "private_key_file": "/opt/ghrust/snowflake.p8", "database": "ANALYTICS",
+//!    "schema": "ENGINEERING", "warehouse": "LOADING" },
+//!  { "type": "athena", "location": "s3://metrics-bucket/copilot_metrics",
+//!    "glue_database": "engineering", "glue_table": "copilot_metrics" },
+//!  { "type": "duckdb", "path": "/var/lib/ghrust/copilot.duckdb" }]
+//! ```
+//!
+//! Like the metrics store, exporting is best effort: a failing exporter is
+//! logged and never fails the scope.
+//!
+//! ## Core Components
+//!
+//! * `Exporter` - Trait implemented by the export destinations
+//! * `ExportSink` - Fan-out of the rows of a scope to every exporter
+//! * `rows` - The flat row schema shared by the exporters
+//! * `google` - Service account authentication for Google APIs
+//! * `sheets` - Google Sheets exporter
+//! * `bigquery` - BigQuery exporter with a crate-managed table schema
+//! * `snowflake` - Snowflake exporter using the SQL API
+//! * `athena` - Date-partitioned JSON Lines in S3, optionally registered in
+//!   Glue
+//! * `duckdb` - Normalized tables in a local DuckDB file (requires the
+//!   `duckdb` feature)
+//! * `tables` - The normalized table rows of a day
+//! * `arrow` - Arrow record batches and IPC files of the rows (requires the
+//!   `arrow` feature)
+//! * `jwt` - RS256 signing shared by the Google and Snowflake exporters
+
+#[cfg(feature = "arrow")]
+pub mod arrow;
+pub mod athena;
+pub mod bigquery;
+#[cfg(feature = "duckdb")]
+pub mod duckdb;
+mod error;
+pub mod google;
+pub mod jwt;
+pub mod rows;
+pub mod sheets;
+pub mod snowflake;
+pub mod tables;
+
+#[cfg(test)]
+mod tests;
+
+use std::fs;
+
+use serde::Deserialize;
+use tracing::{debug, warn};
+
+use crate::models::github::CopilotMetrics;
+use crate::services::aws::{AwsCredentials, GlueClient};
+use crate::services::tls::AgentConfig;
+pub use athena::{GlueTable, PartitionedS3Exporter};
+pub use bigquery::BigQueryExporter;
+pub use error::{ExportError, Result};
+pub use google::ServiceAccount;
+pub use rows::MetricsRow;
+pub use sheets::GoogleSheetsExporter;
+pub use snowflake::{SnowflakeExporter, SnowflakeTarget};
+
+/// Sheet rows are appended to unless configured otherwise
+pub const DEFAULT_SHEET: &str = "Copilot";
+
+/// Warehouse table rows are loaded into unless configured otherwise
+pub const DEFAULT_TABLE: &str = "copilot_metrics";
+
+/// An export destination, as configured in `exports` or `EXPORT_SINKS`
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ExportConfig {
+    /// Appends rows to a Google Sheet
+    GoogleSheets {
+        /// ID of the spreadsheet, as in its URL
+        spreadsheet_id: String,
+        /// Name of the sheet (tab), "Copilot" by default
+        #[serde(default)]
+        sheet: Option<String>,
+        /// Path of the service account's JSON key file
+        credentials_file: String,
+    },
+
+    /// Streams rows into a BigQuery table
+    #[serde(rename = "bigquery")]
+    BigQuery {
+        /// Google Cloud project of the dataset
+        project_id: String,
+        /// Dataset the table lives in
+        dataset: String,
+        /// Name of the table, "copilot_metrics" by default
+        #[serde(default)]
+        table: Option<String>,
+        /// Path of the service account's JSON key file
+        credentials_file: String,
+    },
+
+    /// Loads rows into a Snowflake table through the SQL API
+    Snowflake {
+        /// Account identifier, e.g. "myorg-myaccount"
+        account: String,
+        /// User the statements run as
+        user: String,
+        /// Path of the user's unencrypted PKCS#8 private key (PEM)
+        private_key_file: String,
+        /// Database of the table
+        database: String,
+        /// Schema of the table
+        schema: String,
+        /// Warehouse running the statements
+        warehouse: String,
+        /// Role the statements run as, the user's default role if unset
+        #[serde(default)]
+        role: Option<String>,
+        /// Name of the table, "copilot_metrics" by default
+        #[serde(default)]
+        table: Option<String>,
+    },
+
+    /// Writes rows as date-partitioned JSON Lines to S3, for Athena
+    Athena {
+        /// `s3://<bucket>/<prefix>` location of the table
+        location: String,
+        /// Glue database the partitions are registered in, if any
+        #[serde(default)]
+        glue_database: Option<String>,
+        /// Glue table the partitions are registered in, "copilot_metrics" by
+        /// default if a database is set
+        #[serde(default)]
+        glue_table: Option<String>,
+    },
+
+    /// Keeps the days in normalized tables of a local DuckDB file
+    #[serde(rename = "duckdb")]
+    DuckDb {
+        /// Path of the DuckDB file, created if missing
+        path: String,
+    },
+}
+
+impl ExportConfig {
+    /// Returns the `type` of the entry, e.g. "bigquery"
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Self::GoogleSheets { .. } => "google_sheets",
+            Self::BigQuery { .. } => "bigquery",
+            Self::Snowflake { .. } => "snowflake",
+            Self::Athena { .. } => "athena",
+            Self::DuckDb { .. } => "duckdb",
+        }
+    }
+
+    /// Creates the exporter the entry describes
+    ///
+    /// # Arguments
+    ///
+    /// * `agent` - Settings of the exporter's HTTP agents
+    pub fn exporter(&self, agent: &AgentConfig) -> Result<Box<dyn Exporter>> {
+        match self {
+            Self::GoogleSheets {
+                spreadsheet_id,
+                sheet,
+                credentials_file,
+            } => {
+                let account =
+                    ServiceAccount::from_file(credentials_file, sheets::SHEETS_SCOPE, agent)?;
+                Ok(Box::new(GoogleSheetsExporter::new(
+                    spreadsheet_id,
+                    sheet.as_deref().unwrap_or(DEFAULT_SHEET),
+                    account,
+                    agent,
+                )))
+            }
+            Self::BigQuery {
+                project_id,
+                dataset,
+                table,
+                credentials_file,
+            } => {
+                let account =
+                    ServiceAccount::from_file(credentials_file, bigquery::BIGQUERY_SCOPE, agent)?;
+                Ok(Box::new(BigQueryExporter::new(
+                    project_id,
+                    dataset,
+                    table.as_deref().unwrap_or(DEFAULT_TABLE),
+                    account,
+                    agent,
+                )))
+            }
+            Self::Snowflake {
+                account,
+                user,
+                private_key_file,
+                database,
+                schema,
+                warehouse,
+                role,
+                table,
+            } => {
+                let table = table.as_deref().unwrap_or(DEFAULT_TABLE);
+                snowflake::validate_table(table)?;
+                let pem = fs::read_to_string(private_key_file).map_err(|e| {
+                    ExportError::Config(format!(
+                        "Cannot read Snowflake private key {}: {}",
+                        private_key_file, e
+                    ))
+                })?;
+                let target = SnowflakeTarget {
+                    database: database.clone(),
+                    schema: schema.clone(),
+                    warehouse: warehouse.clone(),
+                    role: role.clone(),
+                    table: table.to_string(),
+                };
+                Ok(Box::new(SnowflakeExporter::new(
+                    account,
+                    user,
+                    jwt::RsaSigner::from_pem(&pem)?,
+                    target,
+                    agent,
+                )))
+            }
+            Self::Athena {
+                location,
+                glue_database,
+                glue_table,
+            } => {
+                let credentials = AwsCredentials::from_env()?;
+                let mut exporter =
+                    PartitionedS3Exporter::new(location, credentials.clone(), agent)?;
+                if let Some(database) = glue_database {
+                    let table = GlueTable {
+                        database: database.clone(),
+                        table: glue_table.as_deref().unwrap_or(DEFAULT_TABLE).to_string(),
+                    };
+                    exporter = exporter.with_glue(GlueClient::new(credentials, agent), table);
+                }
+                Ok(Box::new(exporter))
+            }
+            Self::DuckDb { path } => open_duckdb(path),
+        }
+    }
+}
+
+/// Opens the DuckDB exporter for a file
+#[cfg(feature = "duckdb")]
+fn open_duckdb(path: &str) -> Result<Box<dyn Exporter>> {
+    Ok(Box::new(duckdb::DuckDbExporter::open(path)?))
+}
+
+/// Opens the DuckDB exporter for a file
+#[cfg(not(feature = "duckdb"))]
+fn open_duckdb(path: &str) -> Result<Box<dyn Exporter>> {
+    Err(ExportError::Config(format!(
+        "{} requires a build with the `duckdb` feature",
+        path
+    )))
+}
+
+/// A destination metrics rows are exported to
+pub trait Exporter: Send + Sync {
+    /// Name used to identify the exporter in logs and errors
+    fn name(&self) -> &str;
+
+    /// Exports rows
+    ///
+    /// # Arguments
+    ///
+    /// * `rows` - The rows of one scope
+    ///
+    /// # Returns
+    ///
+    /// * `Result<usize>` - Number of rows written; rows the destination
+    ///   already has may be skipped
+    fn export(&self, rows: &[MetricsRow]) -> Result<usize>;
+
+    /// Exports the days of a scope
+    ///
+    /// Flattens the days into rows for `export` unless the exporter keeps
+    /// more than the totals.
+    ///
+    /// # Arguments
+    ///
+    /// * `scope` - Scope the days belong to, e.g. "enterprise:acme"
+    /// * `metrics` - The fetched days
+    ///
+    /// # Returns
+    ///
+    /// * `Result<usize>` - Number of days written
+    fn export_days(&self, scope: &str, metrics: &[CopilotMetrics]) -> Result<usize> {
+        self.export(&MetricsRow::from_metrics(scope, metrics))
+    }
+}
+
+/// Exports rows to every configured exporter
+#[derive(Default)]
+pub struct ExportSink {
+    exporters: Vec<Box<dyn Exporter>>,
+}
+
+impl ExportSink {
+    /// Creates a sink without exporters
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Creates a sink with an exporter for every configured destination
+    ///
+    /// # Arguments
+    ///
+    /// * `configs` - The configured export destinations
+    /// * `agent` - Settings of the exporters' HTTP agents
+    ///
+    /// # Returns
+    ///
+    /// * `Result<Self>` - The sink, or an error if an exporter can't be
+    ///   created, e.g. because its credentials can't be read
+    pub fn open(configs: &[ExportConfig], agent: &AgentConfig) -> Result<Self> {
+        let mut sink = Self::new();
+        for config in configs {
+            sink = sink.with_exporter(config.exporter(agent)?);
+        }
+        Ok(sink)
+    }
+
+    /// Adds an exporter to the sink
+    pub fn with_exporter(mut self, exporter: Box<dyn Exporter>) -> Self {
+        self.exporters.push(exporter);
+        self
+    }
+
+    /// Returns whether no exporter is configured
+    pub fn is_empty(&self) -> bool {
+        self.exporters.is_empty()
+    }
+
+    /// Exports the days of a scope to every exporter
+    ///
+    /// Failures are logged per exporter and don't stop the others.
+    ///
+    /// # Arguments
+    ///
+    /// * `scope` - Scope the days belong to, e.g. "enterprise:acme"
+    /// * `metrics` - The fetched days
+    ///
+    /// # Returns
+    ///
+    /// * `usize` - Number of exporters that failed
+    pub fn export(&self, scope: &str, metrics: &[CopilotMetrics]) -> usize {
+        if self.exporters.is_empty() || metrics.is_empty() {
+            return 0;
+        }
+
+        let mut failed = 0;
+        for exporter in &self.exporters {
+            match exporter.export_days(scope, metrics) {
+                Ok(written) => debug!(
+                    "Exported {} rows of {} to {}",
+                    written,
+                    scope,
+                    exporter.name()
+                ),
+                Err(e) => {
+                    warn!("Failed to export {} to {}: {}", scope, exporter.name(), e);
+                    failed += 1;
+                }
+            }
+        }
+        failed
+    }
+}
@@ -0,0 +1,124 @@
+//! # Metrics Rows
+//!
+//! Spreadsheets and warehouses want one flat row per scope and day rather
+//! than the nested breakdowns of the metrics API. `MetricsRow` flattens a
+//! day into its totals; the column order of `COLUMNS` is the schema every
+//! exporter writes.
+
+use serde::Serialize;
+use serde_json::{json, Value};
+
+use crate::models::github::{CopilotMetrics, Language};
+
+/// Column names of a metrics row, in order
+pub const COLUMNS: [&str; 12] = [
+    "date",
+    "scope",
+    "total_active_users",
+    "total_engaged_users",
+    "code_completions_engaged_users",
+    "ide_chat_engaged_users",
+    "dotcom_chat_engaged_users",
+    "pull_requests_engaged_users",
+    "code_suggestions",
+    "code_acceptances",
+    "code_lines_suggested",
+    "code_lines_accepted",
+];
+
+/// Totals of one scope on one day
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct MetricsRow {
+    /// Date (YYYY-MM-DD)
+    pub date: String,
+    /// Scope the day belongs to, e.g. "enterprise:acme"
+    pub scope: String,
+    pub total_active_users: i64,
+    pub total_engaged_users: i64,
+    pub code_completions_engaged_users: i64,
+    pub ide_chat_engaged_users: i64,
+    pub dotcom_chat_engaged_users: i64,
+    pub pull_requests_engaged_users: i64,
+    /// Code suggestions summed over all languages
+    pub code_suggestions: i64,
+    /// Accepted code suggestions summed over all languages
+    pub code_acceptances: i64,
+    /// Suggested lines summed over all languages
+    pub code_lines_suggested: i64,
+    /// Accepted lines summed over all languages
+    pub code_lines_accepted: i64,
+}
+
+impl MetricsRow {
+    /// Flattens a day of a scope into a row
+    ///
+    /// # Arguments
+    ///
+    /// * `scope` - Scope the day belongs to, e.g. "enterprise:acme"
+    /// * `metric` - The day's metrics
+    pub fn new(scope: &str, metric: &CopilotMetrics) -> Self {
+        let languages = metric
+            .copilot_ide_code_completions
+            .as_ref()
+            .and_then(|c| c.languages.as_deref())
+            .unwrap_or_default();
+        let sum = |value: fn(&Language) -> Option<i64>| -> i64 {
+            languages.iter().map(|l| value(l).unwrap_or(0)).sum()
+        };
+
+        Self {
+            date: metric.date.clone(),
+            scope: scope.to_string(),
+            total_active_users: metric.total_active_users.unwrap_or(0),
+            total_engaged_users: metric.total_engaged_users.unwrap_or(0),
+            code_completions_engaged_users: metric
+                .copilot_ide_code_completions
+                .as_ref()
+                .map_or(0, |c| c.total_engaged_users),
+            ide_chat_engaged_users: metric
+                .copilot_ide_chat
+                .as_ref()
+                .map_or(0, |c| c.total_engaged_users),
+            dotcom_chat_engaged_users: metric
+                .copilot_dotcom_chat
+                .as_ref()
+                .map_or(0, |c| c.total_engaged_users),
+            pull_requests_engaged_users: metric
+                .copilot_dotcom_pull_requests
+                .as_ref()
+                .map_or(0, |c| c.total_engaged_users),
+            code_suggestions: sum(|l| l.total_code_suggestions),
+            code_acceptances: sum(|l| l.total_code_acceptances),
+            code_lines_suggested: sum(|l| l.total_code_lines_suggested),
+            code_lines_accepted: sum(|l| l.total_code_lines_accepted),
+        }
+    }
+
+    /// Flattens every day of a scope
+    ///
+    /// # Arguments
+    ///
+    /// * `scope` - Scope the days belong to
+    /// * `metrics` - The days, in any order
+    pub fn from_metrics(scope: &str, metrics: &[CopilotMetrics]) -> Vec<Self> {
+        metrics.iter().map(|m| Self::new(scope, m)).collect()
+    }
+
+    /// Returns the row's values in the order of `COLUMNS`
+    pub fn values(&self) -> Vec<Value> {
+        vec![
+            json!(self.date),
+            json!(self.scope),
+            json!(self.total_active_users),
+            json!(self.total_engaged_users),
+            json!(self.code_completions_engaged_users),
+            json!(self.ide_chat_engaged_users),
+            json!(self.dotcom_chat_engaged_users),
+            json!(self.pull_requests_engaged_users),
+            json!(self.code_suggestions),
+            json!(self.code_acceptances),
+            json!(self.code_lines_suggested),
+            json!(self.code_lines_accepted),
+        ]
+    }
+}
@@ -0,0 +1,155 @@
+//! # Google Sheets Exporter
+//!
+//! Appends metrics rows to a sheet of a Google spreadsheet through the
+//! Sheets API, authenticated as a service account. Share the spreadsheet with
+//! the service account's e-mail address (as editor) before the first run.
+//!
+//! The first two columns (date and scope) identify a row: days already in the
+//! sheet are not appended again, so every run only adds the new days of the
+//! 30-day window. An empty sheet gets a header row of `COLUMNS` first.
+
+use std::collections::HashSet;
+
+use serde::Deserialize;
+use serde_json::{json, Value};
+use tracing::info;
+
+use super::error::{request_error, ExportError, Result};
+use super::google::ServiceAccount;
+use super::rows::{MetricsRow, COLUMNS};
+use super::Exporter;
+use crate::services::aws::sigv4::uri_encode;
+use crate::services::tls::AgentConfig;
+
+/// Sheets API base URL
+pub const SHEETS_API_URL: &str = "https://sheets.googleapis.com";
+
+/// OAuth scope needed to edit spreadsheets
+pub const SHEETS_SCOPE: &str = "https://www.googleapis.com/auth/spreadsheets";
+
+/// Values of a range, as returned by the Sheets API
+#[derive(Deserialize)]
+struct ValueRange {
+    /// Rows of the range; missing if the range is empty
+    #[serde(default)]
+    values: Vec<Vec<Value>>,
+}
+
+/// Appends metrics rows to a Google Sheet
+pub struct GoogleSheetsExporter {
+    /// ID of the spreadsheet, as in its URL
+    spreadsheet_id: String,
+    /// Name of the sheet (tab) rows are appended to
+    sheet: String,
+    /// Service account the requests are authorized as
+    account: ServiceAccount,
+    /// Sheets API base URL
+    base_url: String,
+    /// HTTP agent using the configured TLS settings
+    agent: ureq::Agent,
+}
+
+impl GoogleSheetsExporter {
+    /// Creates an exporter for a sheet of a spreadsheet
+    ///
+    /// # Arguments
+    ///
+    /// * `spreadsheet_id` - ID of the spreadsheet
+    /// * `sheet` - Name of the sheet (tab) rows are appended to
+    /// * `account` - Service account with edit access to the spreadsheet
+    /// * `agent` - Settings of the HTTP agent
+    pub fn new(
+        spreadsheet_id: &str,
+        sheet: &str,
+        account: ServiceAccount,
+        agent: &AgentConfig,
+    ) -> Self {
+        Self {
+            spreadsheet_id: spreadsheet_id.to_string(),
+            sheet: sheet.to_string(),
+            account,
+            base_url: SHEETS_API_URL.to_string(),
+            agent: agent.agent_builder().build(),
+        }
+    }
+
+    /// Sends requests to another Sheets API base URL, e.g. a mock server
+    pub fn with_base_url(mut self, base_url: &str) -> Self {
+        self.base_url = base_url.trim_end_matches('/').to_string();
+        self
+    }
+
+    /// Returns the URL of a range of the sheet, with an optional suffix
+    fn range_url(&self, range: &str, suffix: &str) -> String {
+        format!(
+            "{}/v4/spreadsheets/{}/values/{}{}",
+            self.base_url,
+            self.spreadsheet_id,
+            uri_encode(&format!("'{}'!{}", self.sheet, range), true),
+            suffix
+        )
+    }
+}
+
+impl Exporter for GoogleSheetsExporter {
+    fn name(&self) -> &str {
+        "google_sheets"
+    }
+
+    fn export(&self, rows: &[MetricsRow]) -> Result<usize> {
+        let token = self.account.access_token()?;
+        let auth = format!("Bearer {}", token);
+
+        let existing: ValueRange = self
+            .agent
+            .get(&self.range_url("A:B", ""))
+            .set("Authorization", &auth)
+            .call()
+            .map_err(request_error)?
+            .into_json()
+            .map_err(|e| ExportError::Serialization(e.to_string()))?;
+
+        let rows = new_rows(&existing.values, rows);
+        if rows.is_empty() {
+            return Ok(0);
+        }
+
+        let mut values: Vec<Vec<Value>> = Vec::with_capacity(rows.len() + 1);
+        if existing.values.is_empty() {
+            values.push(COLUMNS.iter().map(|c| json!(c)).collect());
+        }
+        values.extend(rows.iter().map(|row| row.values()));
+
+        self.agent
+            .post(&self.range_url("A1", ":append"))
+            .query("valueInputOption", "RAW")
+            .query("insertDataOption", "INSERT_ROWS")
+            .set("Authorization", &auth)
+            .send_json(json!({ "values": values }))
+            .map_err(request_error)?;
+
+        info!(
+            "Appended {} rows to sheet {} of spreadsheet {}",
+            rows.len(),
+            self.sheet,
+            self.spreadsheet_id
+        );
+        Ok(rows.len())
+    }
+}
+
+/// Returns the rows whose date and scope aren't in the sheet yet
+///
+/// # Arguments
+///
+/// * `existing` - The sheet's date and scope columns, header included
+/// * `rows` - The rows to export
+pub fn new_rows<'a>(existing: &[Vec<Value>], rows: &'a [MetricsRow]) -> Vec<&'a MetricsRow> {
+    let keys: HashSet<(&str, &str)> = existing
+        .iter()
+        .filter_map(|row| Some((row.first()?.as_str()?, row.get(1)?.as_str()?)))
+        .collect();
+    rows.iter()
+        .filter(|row| !keys.contains(&(row.date.as_str(), row.scope.as_str())))
+        .collect()
+}
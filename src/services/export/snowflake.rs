@@ -0,0 +1,342 @@
+//! # Snowflake Exporter
+//!
+//! Loads metrics rows into a Snowflake table through the SQL API, with
+//! key-pair authentication: every request carries a JWT signed with the
+//! user's RSA private key, whose public key is registered on the user
+//! (`ALTER USER ... SET RSA_PUBLIC_KEY = '...'`).
+//!
+//! The table is created with `create_table_sql` on the first export if it
+//! doesn't exist. Rows are loaded with a `MERGE` on date and scope, so re-runs
+//! don't duplicate rows and days GitHub restated overwrite the stored ones.
+//! The table name is interpolated into the statements, so it must be a plain
+//! (optionally qualified) identifier; see `validate_table`.
+
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::thread;
+use std::time::Duration;
+
+use chrono::Utc;
+use serde_json::{json, Map, Value};
+use tracing::{debug, info};
+
+use super::error::{request_error, ExportError, Result};
+use super::jwt::RsaSigner;
+use super::rows::{MetricsRow, COLUMNS};
+use super::Exporter;
+use crate::services::tls::AgentConfig;
+
+/// Rows loaded per `MERGE` statement
+const MERGE_BATCH: usize = 200;
+
+/// Seconds the SQL API waits for a statement before answering asynchronously
+const STATEMENT_TIMEOUT: u64 = 45;
+
+/// Polls of a statement that is still running before giving up
+const MAX_POLLS: usize = 30;
+
+/// Lifetime of the JWTs, in seconds; Snowflake accepts at most one hour
+const JWT_LIFETIME: i64 = 3600;
+
+/// Where the rows are loaded
+#[derive(Debug, Clone, PartialEq)]
+pub struct SnowflakeTarget {
+    /// Database of the table
+    pub database: String,
+    /// Schema of the table
+    pub schema: String,
+    /// Warehouse running the statements
+    pub warehouse: String,
+    /// Role the statements run as, the user's default role if unset
+    pub role: Option<String>,
+    /// Name of the table
+    pub table: String,
+}
+
+/// Checks that a table name is a plain Snowflake identifier
+///
+/// The name is interpolated into the SQL statements, so only unquoted
+/// identifiers (letters, digits, `_` and `$`, not starting with a digit or
+/// `$`), optionally qualified with a schema and database, are accepted.
+///
+/// # Arguments
+///
+/// * `table` - Name of the table, e.g. "copilot_metrics" or
+///   "ANALYTICS.ENGINEERING.copilot_metrics"
+///
+/// # Returns
+///
+/// * `Result<()>` - A `Config` error naming the table if it isn't a plain
+///   identifier
+pub fn validate_table(table: &str) -> Result<()> {
+    let parts: Vec<&str> = table.split('.').collect();
+    let plain = |part: &&str| {
+        let mut chars = part.chars();
+        chars
+            .next()
+            .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+            && chars.all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '$')
+    };
+    if parts.len() > 3 || !parts.iter().all(plain) {
+        return Err(ExportError::Config(format!(
+            "Snowflake table {:?} is not a plain identifier",
+            table
+        )));
+    }
+    Ok(())
+}
+
+/// Returns the Snowflake type of a column of `COLUMNS`
+fn column_type(column: &str) -> &'static str {
+    match column {
+        "date" => "DATE",
+        "scope" => "VARCHAR",
+        _ => "NUMBER(38, 0)",
+    }
+}
+
+/// Returns the statement creating the metrics table if it doesn't exist
+///
+/// # Arguments
+///
+/// * `table` - Name of the table, optionally qualified
+pub fn create_table_sql(table: &str) -> String {
+    let columns: Vec<String> = COLUMNS
+        .iter()
+        .map(|column| format!("{} {} NOT NULL", column, column_type(column)))
+        .collect();
+    format!(
+        "CREATE TABLE IF NOT EXISTS {} ({}, PRIMARY KEY (date, scope))",
+        table,
+        columns.join(", ")
+    )
+}
+
+/// Returns the statement loading `rows` rows into the table, updating the
+/// dates and scopes it already has
+///
+/// The values are bound positionally, row by row in the order of `COLUMNS`.
+pub fn merge_sql(table: &str, rows: usize) -> String {
+    let selected: Vec<String> = COLUMNS
+        .iter()
+        .enumerate()
+        .map(|(i, column)| match *column {
+            "date" => format!("TO_DATE(column{}) AS date", i + 1),
+            _ => format!("column{} AS {}", i + 1, column),
+        })
+        .collect();
+    let placeholders = format!("({})", vec!["?"; COLUMNS.len()].join(", "));
+    let inserted: Vec<String> = COLUMNS.iter().map(|c| format!("s.{}", c)).collect();
+    let updated: Vec<String> = COLUMNS
+        .iter()
+        .filter(|column| !matches!(**column, "date" | "scope"))
+        .map(|column| format!("{} = s.{}", column, column))
+        .collect();
+    format!(
+        "MERGE INTO {} t USING (SELECT {} FROM VALUES {}) s \
+         ON t.date = s.date AND t.scope = s.scope \
+         WHEN MATCHED THEN UPDATE SET {} \
+         WHEN NOT MATCHED THEN INSERT ({}) VALUES ({})",
+        table,
+        selected.join(", "),
+        vec![placeholders; rows].join(", "),
+        updated.join(", "),
+        COLUMNS.join(", "),
+        inserted.join(", ")
+    )
+}
+
+/// Returns the positional bindings of rows, for `merge_sql`
+pub fn bindings(rows: &[&MetricsRow]) -> Value {
+    let mut bindings = Map::new();
+    for (i, value) in rows.iter().flat_map(|row| row.values()).enumerate() {
+        let binding = match value {
+            Value::String(text) => json!({ "type": "TEXT", "value": text }),
+            other => json!({ "type": "FIXED", "value": other.to_string() }),
+        };
+        bindings.insert((i + 1).to_string(), binding);
+    }
+    Value::Object(bindings)
+}
+
+/// Loads metrics rows into a Snowflake table
+pub struct SnowflakeExporter {
+    /// Account identifier, e.g. "myorg-myaccount"
+    account: String,
+    /// User the statements run as
+    user: String,
+    /// Private key of the user
+    signer: RsaSigner,
+    /// Where the rows are loaded
+    target: SnowflakeTarget,
+    /// SQL API base URL
+    base_url: String,
+    /// Whether the table was created or found in this process
+    table_ready: AtomicBool,
+    /// HTTP agent using the configured TLS settings
+    agent: ureq::Agent,
+}
+
+impl SnowflakeExporter {
+    /// Creates an exporter for a table
+    ///
+    /// # Arguments
+    ///
+    /// * `account` - Account identifier, e.g. "myorg-myaccount"
+    /// * `user` - User the statements run as
+    /// * `signer` - The user's private key
+    /// * `target` - Where the rows are loaded
+    /// * `agent` - Settings of the HTTP agent
+    pub fn new(
+        account: &str,
+        user: &str,
+        signer: RsaSigner,
+        target: SnowflakeTarget,
+        agent: &AgentConfig,
+    ) -> Self {
+        Self {
+            account: account.to_string(),
+            user: user.to_string(),
+            signer,
+            target,
+            base_url: format!("https://{}.snowflakecomputing.com", account.to_lowercase()),
+            table_ready: AtomicBool::new(false),
+            agent: agent.agent_builder().build(),
+        }
+    }
+
+    /// Sends requests to another SQL API base URL, e.g. a mock server
+    pub fn with_base_url(mut self, base_url: &str) -> Self {
+        self.base_url = base_url.trim_end_matches('/').to_string();
+        self
+    }
+
+    /// Returns a JWT authenticating the user
+    fn token(&self) -> Result<String> {
+        // The JWT names the account without region or cloud suffixes
+        let account = self
+            .account
+            .split('.')
+            .next()
+            .unwrap_or_default()
+            .to_uppercase();
+        let subject = format!("{}.{}", account, self.user.to_uppercase());
+        let now = Utc::now().timestamp();
+        self.signer.sign(&json!({
+            "iss": format!("{}.{}", subject, self.signer.public_key_fingerprint()),
+            "sub": subject,
+            "iat": now,
+            "exp": now + JWT_LIFETIME,
+        }))
+    }
+
+    /// Runs a statement and returns its result
+    fn execute(&self, token: &str, statement: &str, bindings: Option<Value>) -> Result<Value> {
+        let mut body = json!({
+            "statement": statement,
+            "timeout": STATEMENT_TIMEOUT,
+            "database": self.target.database,
+            "schema": self.target.schema,
+            "warehouse": self.target.warehouse,
+        });
+        if let Some(role) = &self.target.role {
+            body["role"] = json!(role);
+        }
+        if let Some(bindings) = bindings {
+            body["bindings"] = bindings;
+        }
+
+        let response = self
+            .request(
+                "POST",
+                &format!("{}/api/v2/statements", self.base_url),
+                token,
+            )
+            .send_json(body)
+            .map_err(request_error)?;
+        let mut status = response.status();
+        let mut result: Value = response
+            .into_json()
+            .map_err(|e| ExportError::Serialization(e.to_string()))?;
+
+        // 202: the statement is still running; poll until it completes
+        let mut polls = 0;
+        while status == 202 {
+            polls += 1;
+            if polls > MAX_POLLS {
+                return Err(ExportError::Network(
+                    "Snowflake statement did not complete in time".to_string(),
+                ));
+            }
+            let handle = result["statementHandle"].as_str().unwrap_or_default();
+            debug!("Waiting for Snowflake statement {}", handle);
+            thread::sleep(Duration::from_secs(1));
+            let response = self
+                .request(
+                    "GET",
+                    &format!("{}/api/v2/statements/{}", self.base_url, handle),
+                    token,
+                )
+                .call()
+                .map_err(request_error)?;
+            status = response.status();
+            result = response
+                .into_json()
+                .map_err(|e| ExportError::Serialization(e.to_string()))?;
+        }
+        Ok(result)
+    }
+
+    /// Builds an authenticated request
+    fn request(&self, method: &str, url: &str, token: &str) -> ureq::Request {
+        self.agent
+            .request(method, url)
+            .set("Authorization", &format!("Bearer {}", token))
+            .set("X-Snowflake-Authorization-Token-Type", "KEYPAIR_JWT")
+            .set("Accept", "application/json")
+    }
+}
+
+impl Exporter for SnowflakeExporter {
+    fn name(&self) -> &str {
+        "snowflake"
+    }
+
+    fn export(&self, rows: &[MetricsRow]) -> Result<usize> {
+        if rows.is_empty() {
+            return Ok(0);
+        }
+        let token = self.token()?;
+
+        if !self.table_ready.load(Ordering::Relaxed) {
+            self.execute(&token, &create_table_sql(&self.target.table), None)?;
+            self.table_ready.store(true, Ordering::Relaxed);
+        }
+
+        let rows: Vec<&MetricsRow> = rows.iter().collect();
+        let (mut inserted, mut updated) = (0, 0);
+        for batch in rows.chunks(MERGE_BATCH) {
+            let result = self.execute(
+                &token,
+                &merge_sql(&self.target.table, batch.len()),
+                Some(bindings(batch)),
+            )?;
+            // MERGE returns the numbers of inserted and updated rows
+            let count = |column: usize| {
+                result["data"][0][column]
+                    .as_str()
+                    .and_then(|count| count.parse::<usize>().ok())
+                    .unwrap_or(0)
+            };
+            inserted += count(0);
+            updated += count(1);
+        }
+
+        if inserted + updated > 0 {
+            info!(
+                "Inserted {} and updated {} rows in Snowflake table {}.{}.{}",
+                inserted, updated, self.target.database, self.target.schema, self.target.table
+            );
+        }
+        Ok(inserted + updated)
+    }
+}
@@ -0,0 +1,226 @@
+//! # Normalized Tables
+//!
+//! `MetricsRow` keeps only the totals of a day. For ad-hoc SQL analysis the
+//! breakdowns matter as well, so `NormalizedDay` splits a day into one row
+//! per table: the day's totals, its languages, editors, models and
+//! repositories. Every row carries the date and scope of its day, which
+//! together identify the rows a day replaces.
+
+use crate::models::github::{CopilotMetrics, Language, Model};
+
+use super::rows::MetricsRow;
+
+/// Code completion counts of a language, overall or in an editor and model
+#[derive(Debug, Clone, PartialEq)]
+pub struct LanguageRow {
+    pub date: String,
+    pub scope: String,
+    /// Editor of a per-editor breakdown, `None` for the day's totals
+    pub editor: Option<String>,
+    /// Model of a per-editor breakdown, `None` for the day's totals
+    pub model: Option<String>,
+    pub language: String,
+    pub engaged_users: i64,
+    pub code_suggestions: Option<i64>,
+    pub code_acceptances: Option<i64>,
+    pub code_lines_suggested: Option<i64>,
+    pub code_lines_accepted: Option<i64>,
+}
+
+/// Engaged users of an editor for a feature
+#[derive(Debug, Clone, PartialEq)]
+pub struct EditorRow {
+    pub date: String,
+    pub scope: String,
+    /// "code_completions" or "ide_chat"
+    pub feature: String,
+    pub editor: String,
+    pub engaged_users: i64,
+}
+
+/// Counts of a model for a feature, in an editor or repository if the
+/// feature is broken down by them
+#[derive(Debug, Clone, PartialEq)]
+pub struct ModelRow {
+    pub date: String,
+    pub scope: String,
+    /// "code_completions", "ide_chat", "dotcom_chat" or "pull_requests"
+    pub feature: String,
+    pub editor: Option<String>,
+    pub repository: Option<String>,
+    pub model: String,
+    pub is_custom_model: bool,
+    pub engaged_users: i64,
+    pub total_chats: Option<i64>,
+    pub total_chat_insertion_events: Option<i64>,
+    pub total_chat_copy_events: Option<i64>,
+    pub total_pr_summaries_created: Option<i64>,
+}
+
+/// Activity of a repository for a feature
+#[derive(Debug, Clone, PartialEq)]
+pub struct RepositoryRow {
+    pub date: String,
+    pub scope: String,
+    /// "pull_requests" or "code_review"
+    pub feature: String,
+    pub repository: String,
+    pub engaged_users: i64,
+    /// Pull request summaries, summed over the repository's models
+    pub pr_summaries_created: Option<i64>,
+    pub reviews: Option<i64>,
+    pub review_comments: Option<i64>,
+    pub review_comments_applied: Option<i64>,
+}
+
+/// A day split into the rows of the normalized tables
+#[derive(Debug, Clone, PartialEq)]
+pub struct NormalizedDay {
+    pub day: MetricsRow,
+    pub languages: Vec<LanguageRow>,
+    pub editors: Vec<EditorRow>,
+    pub models: Vec<ModelRow>,
+    pub repositories: Vec<RepositoryRow>,
+}
+
+impl NormalizedDay {
+    /// Splits a day of a scope into table rows
+    ///
+    /// # Arguments
+    ///
+    /// * `scope` - Scope the day belongs to, e.g. "enterprise:acme"
+    /// * `metric` - The day's metrics
+    pub fn new(scope: &str, metric: &CopilotMetrics) -> Self {
+        let mut normalized = Self {
+            day: MetricsRow::new(scope, metric),
+            languages: Vec::new(),
+            editors: Vec::new(),
+            models: Vec::new(),
+            repositories: Vec::new(),
+        };
+
+        if let Some(completions) = &metric.copilot_ide_code_completions {
+            for language in completions.languages.iter().flatten() {
+                normalized.push_language(None, None, language);
+            }
+            for editor in completions.editors.iter().flatten() {
+                normalized.push_editor(
+                    "code_completions",
+                    &editor.name,
+                    editor.total_engaged_users,
+                );
+                for model in editor.models.iter().flatten() {
+                    normalized.push_model("code_completions", Some(&editor.name), None, model);
+                    for language in model.languages.iter().flatten() {
+                        normalized.push_language(Some(&editor.name), Some(&model.name), language);
+                    }
+                }
+            }
+        }
+
+        if let Some(chat) = &metric.copilot_ide_chat {
+            for editor in chat.editors.iter().flatten() {
+                normalized.push_editor("ide_chat", &editor.name, editor.total_engaged_users);
+                for model in editor.models.iter().flatten() {
+                    normalized.push_model("ide_chat", Some(&editor.name), None, model);
+                }
+            }
+        }
+
+        if let Some(chat) = &metric.copilot_dotcom_chat {
+            for model in chat.models.iter().flatten() {
+                normalized.push_model("dotcom_chat", None, None, model);
+            }
+        }
+
+        if let Some(pull_requests) = &metric.copilot_dotcom_pull_requests {
+            for repository in pull_requests.repositories.iter().flatten() {
+                for model in &repository.models {
+                    normalized.push_model("pull_requests", None, Some(&repository.name), model);
+                }
+                let summaries = repository
+                    .models
+                    .iter()
+                    .filter_map(|m| m.total_pr_summaries_created)
+                    .reduce(|a, b| a + b);
+                normalized.repositories.push(RepositoryRow {
+                    date: metric.date.clone(),
+                    scope: scope.to_string(),
+                    feature: "pull_requests".to_string(),
+                    repository: repository.name.clone(),
+                    engaged_users: repository.total_engaged_users,
+                    pr_summaries_created: summaries,
+                    reviews: None,
+                    review_comments: None,
+                    review_comments_applied: None,
+                });
+            }
+        }
+
+        if let Some(review) = &metric.copilot_code_review {
+            for repository in review.repositories.iter().flatten() {
+                normalized.repositories.push(RepositoryRow {
+                    date: metric.date.clone(),
+                    scope: scope.to_string(),
+                    feature: "code_review".to_string(),
+                    repository: repository.name.clone(),
+                    engaged_users: repository.total_engaged_users,
+                    pr_summaries_created: None,
+                    reviews: repository.total_reviews,
+                    review_comments: repository.total_review_comments,
+                    review_comments_applied: repository.total_review_comments_applied,
+                });
+            }
+        }
+
+        normalized
+    }
+
+    fn push_language(&mut self, editor: Option<&str>, model: Option<&str>, language: &Language) {
+        self.languages.push(LanguageRow {
+            date: self.day.date.clone(),
+            scope: self.day.scope.clone(),
+            editor: editor.map(str::to_string),
+            model: model.map(str::to_string),
+            language: language.name.clone(),
+            engaged_users: language.total_engaged_users,
+            code_suggestions: language.total_code_suggestions,
+            code_acceptances: language.total_code_acceptances,
+            code_lines_suggested: language.total_code_lines_suggested,
+            code_lines_accepted: language.total_code_lines_accepted,
+        });
+    }
+
+    fn push_editor(&mut self, feature: &str, editor: &str, engaged_users: i64) {
+        self.editors.push(EditorRow {
+            date: self.day.date.clone(),
+            scope: self.day.scope.clone(),
+            feature: feature.to_string(),
+            editor: editor.to_string(),
+            engaged_users,
+        });
+    }
+
+    fn push_model(
+        &mut self,
+        feature: &str,
+        editor: Option<&str>,
+        repository: Option<&str>,
+        model: &Model,
+    ) {
+        self.models.push(ModelRow {
+            date: self.day.date.clone(),
+            scope: self.day.scope.clone(),
+            feature: feature.to_string(),
+            editor: editor.map(str::to_string),
+            repository: repository.map(str::to_string),
+            model: model.name.clone(),
+            is_custom_model: model.is_custom_model,
+            engaged_users: model.total_engaged_users,
+            total_chats: model.total_chats,
+            total_chat_insertion_events: model.total_chat_insertion_events,
+            total_chat_copy_events: model.total_chat_copy_events,
+            total_pr_summaries_created: model.total_pr_summaries_created,
+        });
+    }
+}
@@ -0,0 +1,372 @@
+//! # Metrics Export Tests
+
+use serde_json::json;
+
+use super::rows::COLUMNS;
+use super::sheets::new_rows;
+use super::tables::NormalizedDay;
+use super::{athena, bigquery, jwt, snowflake};
+use super::{ExportConfig, ExportError, MetricsRow};
+use crate::services::aws::glue::{self, JsonPartition};
+use crate::services::github::create_mock_metrics;
+use crate::services::tls::AgentConfig;
+
+#[test]
+fn test_row_flattens_a_day() {
+    let metric = create_mock_metrics(120, 90);
+    let row = MetricsRow::new("enterprise:acme", &metric);
+
+    let languages = metric
+        .copilot_ide_code_completions
+        .as_ref()
+        .and_then(|c| c.languages.clone())
+        .unwrap();
+    let suggestions: i64 = languages
+        .iter()
+        .map(|l| l.total_code_suggestions.unwrap_or(0))
+        .sum();
+
+    assert_eq!(row.date, metric.date);
+    assert_eq!(row.scope, "enterprise:acme");
+    assert_eq!(row.total_active_users, 120);
+    assert_eq!(row.total_engaged_users, 90);
+    assert_eq!(row.code_suggestions, suggestions);
+
+    let values = row.values();
+    assert_eq!(values.len(), COLUMNS.len());
+    assert_eq!(values[0], json!(metric.date));
+    assert_eq!(values[2], json!(120));
+}
+
+#[test]
+fn test_new_rows_skips_exported_days() {
+    let mut first = create_mock_metrics(10, 5);
+    first.date = "2025-03-01".to_string();
+    let mut second = create_mock_metrics(20, 10);
+    second.date = "2025-03-02".to_string();
+    let rows = MetricsRow::from_metrics("team:acme/platform", &[first, second]);
+
+    let existing = vec![
+        vec![json!("date"), json!("scope")],
+        vec![json!("2025-03-01"), json!("team:acme/platform")],
+        vec![json!("2025-03-02"), json!("enterprise:acme")],
+    ];
+    let new = new_rows(&existing, &rows);
+    assert_eq!(new.len(), 1);
+    assert_eq!(new[0].date, "2025-03-02");
+
+    assert_eq!(new_rows(&[], &rows).len(), 2);
+}
+
+#[test]
+fn test_export_config_parses_google_sheets() {
+    let configs: Vec<ExportConfig> = serde_json::from_str(
+        r#"[{"type": "google_sheets", "spreadsheet_id": "1AbC", "credentials_file": "/tmp/sa.json"}]"#,
+    )
+    .unwrap();
+    assert_eq!(
+        configs,
+        vec![ExportConfig::GoogleSheets {
+            spreadsheet_id: "1AbC".to_string(),
+            sheet: None,
+            credentials_file: "/tmp/sa.json".to_string(),
+        }]
+    );
+
+    assert!(serde_json::from_str::<Vec<ExportConfig>>(r#"[{"type": "excel"}]"#).is_err());
+}
+
+#[test]
+fn test_bigquery_schema_covers_columns() {
+    let schema = bigquery::schema();
+    let fields = schema["fields"].as_array().unwrap();
+    let names: Vec<&str> = fields.iter().map(|f| f["name"].as_str().unwrap()).collect();
+    assert_eq!(names, COLUMNS);
+    assert_eq!(fields[0]["type"], "DATE");
+    assert_eq!(fields[1]["type"], "STRING");
+    assert_eq!(fields[2]["type"], "INTEGER");
+}
+
+#[test]
+fn test_bigquery_query_rows_feed_dedup() {
+    let response = json!({
+        "jobComplete": true,
+        "rows": [
+            { "f": [{ "v": "2025-03-01" }, { "v": "enterprise:acme" }] },
+        ],
+    });
+    let existing = bigquery::query_rows(&response);
+    assert_eq!(
+        existing,
+        vec![vec![json!("2025-03-01"), json!("enterprise:acme")]]
+    );
+
+    let mut day = create_mock_metrics(10, 5);
+    day.date = "2025-03-01".to_string();
+    let rows = MetricsRow::from_metrics("enterprise:acme", &[day]);
+    assert!(new_rows(&existing, &rows).is_empty());
+    assert!(bigquery::query_rows(&json!({ "jobComplete": true })).is_empty());
+}
+
+#[test]
+fn test_export_config_parses_bigquery() {
+    let config: ExportConfig = serde_json::from_str(
+        r#"{"type": "bigquery", "project_id": "analytics", "dataset": "engineering",
+            "credentials_file": "/tmp/sa.json"}"#,
+    )
+    .unwrap();
+    assert_eq!(
+        config,
+        ExportConfig::BigQuery {
+            project_id: "analytics".to_string(),
+            dataset: "engineering".to_string(),
+            table: None,
+            credentials_file: "/tmp/sa.json".to_string(),
+        }
+    );
+}
+
+#[test]
+fn test_snowflake_statements() {
+    let create = snowflake::create_table_sql("copilot_metrics");
+    assert!(create.starts_with("CREATE TABLE IF NOT EXISTS copilot_metrics (date DATE NOT NULL"));
+    assert!(create.ends_with("PRIMARY KEY (date, scope))"));
+
+    let merge = snowflake::merge_sql("copilot_metrics", 2);
+    assert_eq!(merge.matches('?').count(), 2 * COLUMNS.len());
+    assert!(merge.contains("TO_DATE(column1) AS date, column2 AS scope"));
+    assert!(merge.contains("WHEN NOT MATCHED THEN INSERT"));
+    // Restated days overwrite every column but the key
+    assert!(
+        merge.contains("WHEN MATCHED THEN UPDATE SET total_active_users = s.total_active_users, ")
+    );
+    assert!(!merge.contains("date = s.date,"));
+    assert!(!merge.contains("scope = s.scope,"));
+}
+
+#[test]
+fn test_snowflake_table_must_be_plain_identifier() {
+    for table in [
+        "copilot_metrics",
+        "ANALYTICS.ENGINEERING.copilot_metrics",
+        "_t$1",
+    ] {
+        assert!(snowflake::validate_table(table).is_ok(), "{}", table);
+    }
+    for table in [
+        "",
+        "1metrics",
+        "metrics; DROP TABLE users",
+        "\"quoted\"",
+        "a.b.c.d",
+        "analytics..metrics",
+    ] {
+        assert!(
+            matches!(
+                snowflake::validate_table(table),
+                Err(ExportError::Config(_))
+            ),
+            "{}",
+            table
+        );
+    }
+}
+
+#[test]
+fn test_snowflake_bindings_follow_columns() {
+    let metric = create_mock_metrics(120, 90);
+    let row = MetricsRow::new("enterprise:acme", &metric);
+    let bindings = snowflake::bindings(&[&row, &row]);
+
+    assert_eq!(bindings.as_object().unwrap().len(), 2 * COLUMNS.len());
+    assert_eq!(
+        bindings["1"],
+        json!({ "type": "TEXT", "value": metric.date })
+    );
+    assert_eq!(bindings["2"]["value"], "enterprise:acme");
+    assert_eq!(bindings["3"], json!({ "type": "FIXED", "value": "120" }));
+    assert_eq!(bindings["14"]["value"], "enterprise:acme");
+}
+
+#[test]
+fn test_subject_public_key_info_wraps_key() {
+    let key = vec![0x30; 270];
+    let info = jwt::subject_public_key_info(&key);
+    // SEQUENCE with a two-byte length, then the rsaEncryption identifier
+    assert_eq!(&info[..2], &[0x30, 0x82]);
+    assert_eq!(
+        usize::from(info[2]) << 8 | usize::from(info[3]),
+        info.len() - 4
+    );
+    assert_eq!(&info[4..6], &[0x30, 0x0d]);
+    assert!(info.ends_with(&key));
+}
+
+#[test]
+fn test_export_config_parses_snowflake() {
+    let config: ExportConfig = serde_json::from_str(
+        r#"{"type": "snowflake", "account": "myorg-myaccount", "user": "GHRUST",
+            "private_key_file": "/tmp/sf.p8", "database": "ANALYTICS",
+            "schema": "ENGINEERING", "warehouse": "LOADING"}"#,
+    )
+    .unwrap();
+    match config {
+        ExportConfig::Snowflake {
+            account,
+            role,
+            table,
+            ..
+        } => {
+            assert_eq!(account, "myorg-myaccount");
+            assert_eq!(role, None);
+            assert_eq!(table, None);
+        }
+        other => panic!("Expected a Snowflake export, got {:?}", other),
+    }
+}
+
+#[test]
+fn test_partition_path_encodes_scope() {
+    let mut metric = create_mock_metrics(10, 5);
+    metric.date = "2025-03-01".to_string();
+    let row = MetricsRow::new("team:acme/platform", &metric);
+
+    assert_eq!(
+        athena::partition_path("copilot_metrics", &row),
+        "copilot_metrics/dt=2025-03-01/scope=team%3Aacme%2Fplatform/"
+    );
+    assert_eq!(
+        athena::partition_path("", &row),
+        "dt=2025-03-01/scope=team%3Aacme%2Fplatform/"
+    );
+}
+
+#[test]
+fn test_glue_partition_input() {
+    let partition = JsonPartition {
+        values: vec!["2025-03-01".to_string(), "enterprise:acme".to_string()],
+        location: "s3://bucket/copilot_metrics/dt=2025-03-01/scope=enterprise%3Aacme/".to_string(),
+    };
+    let input = glue::partition_input(&partition);
+    assert_eq!(input["Values"], json!(["2025-03-01", "enterprise:acme"]));
+    assert_eq!(
+        input["StorageDescriptor"]["SerdeInfo"]["SerializationLibrary"],
+        "org.openx.data.jsonserde.JsonSerDe"
+    );
+}
+
+#[test]
+fn test_normalized_day_splits_breakdowns() {
+    let metric = create_mock_metrics(120, 90);
+    let day = NormalizedDay::new("enterprise:acme", &metric);
+    let completions = metric.copilot_ide_code_completions.as_ref().unwrap();
+
+    assert_eq!(day.day, MetricsRow::new("enterprise:acme", &metric));
+    let totals: Vec<_> = day
+        .languages
+        .iter()
+        .filter(|l| l.editor.is_none())
+        .collect();
+    assert_eq!(
+        totals.len(),
+        completions.languages.as_ref().map_or(0, Vec::len)
+    );
+    assert!(day
+        .languages
+        .iter()
+        .all(|l| l.editor.is_some() == l.model.is_some()));
+    assert!(day.editors.iter().any(|e| e.feature == "code_completions"));
+    assert!(day
+        .repositories
+        .iter()
+        .any(|r| r.feature == "pull_requests" && r.repository == "test-repo"));
+    assert!(day
+        .models
+        .iter()
+        .all(|m| m.date == metric.date && m.scope == "enterprise:acme"));
+}
+
+#[cfg(not(feature = "duckdb"))]
+#[test]
+fn test_duckdb_export_needs_feature() {
+    let config = ExportConfig::DuckDb {
+        path: "/tmp/copilot.duckdb".to_string(),
+    };
+    assert!(matches!(
+        config.exporter(&AgentConfig::default()),
+        Err(ExportError::Config(_))
+    ));
+}
+
+#[cfg(feature = "arrow")]
+#[test]
+fn test_arrow_ipc_round_trip() {
+    use arrow_array::{Array, Date32Array, Int64Array, StringArray};
+    use std::io::Cursor;
+
+    let metrics = vec![create_mock_metrics(120, 90), create_mock_metrics(80, 60)];
+    let mut file = Vec::new();
+    let written = super::arrow::write_metrics(&mut file, "enterprise:acme", &metrics).unwrap();
+    assert_eq!(written, 2);
+
+    let reader = arrow_ipc::reader::FileReader::try_new(Cursor::new(file), None).unwrap();
+    assert_eq!(reader.schema(), super::arrow::schema());
+    let batches: Vec<_> = reader.map(|batch| batch.unwrap()).collect();
+    assert_eq!(batches.len(), 1);
+
+    let batch = &batches[0];
+    assert_eq!(batch.num_rows(), 2);
+    let dates = batch
+        .column(0)
+        .as_any()
+        .downcast_ref::<Date32Array>()
+        .unwrap();
+    assert_eq!(
+        dates
+            .value_as_date(0)
+            .unwrap()
+            .format("%Y-%m-%d")
+            .to_string(),
+        metrics[0].date
+    );
+    let scopes = batch
+        .column(1)
+        .as_any()
+        .downcast_ref::<StringArray>()
+        .unwrap();
+    assert_eq!(scopes.value(1), "enterprise:acme");
+    let active = batch
+        .column(2)
+        .as_any()
+        .downcast_ref::<Int64Array>()
+        .unwrap();
+    assert_eq!(active.values().to_vec(), vec![120, 80]);
+    assert_eq!(active.null_count(), 0);
+}
+
+#[cfg(feature = "arrow")]
+#[test]
+fn test_arrow_rejects_invalid_dates() {
+    let mut row = MetricsRow::new("enterprise:acme", &create_mock_metrics(1, 1));
+    row.date = "yesterday".to_string();
+    assert!(matches!(
+        super::arrow::record_batch(&[row]),
+        Err(ExportError::Serialization(_))
+    ));
+}
+
+#[test]
+fn test_export_sink_opens_every_configured_exporter() {
+    use super::ExportSink;
+
+    let sink = ExportSink::open(&[], &AgentConfig::default()).unwrap();
+    assert!(sink.is_empty());
+
+    // An exporter that can't be created fails the whole sink
+    let configs = [ExportConfig::GoogleSheets {
+        spreadsheet_id: "1AbC".to_string(),
+        sheet: None,
+        credentials_file: "/nonexistent/service-account.json".to_string(),
+    }];
+    assert!(ExportSink::open(&configs, &AgentConfig::default()).is_err());
+}
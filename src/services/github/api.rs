@@ -6,15 +6,49 @@
 //! - Authenticating with the GitHub API using personal access tokens
 //! - Fetching enterprise-wide Copilot usage metrics
 //! - Fetching team-specific Copilot usage metrics
+//! - Fetching organization-specific Copilot usage metrics
+//! - Fetching premium request usage reports
+//! - Fetching Copilot seat assignments
+//! - Listing an enterprise's teams
+//! - Fetching an organization's Copilot seat breakdown
 //! - Handling API errors and rate limiting
 //! - Logging metric summaries for observability
 //!
 //! The client uses the `ureq` library for making HTTP requests and handles JSON
 //! serialization/deserialization of the GitHub API responses.
 
+use super::archive::RawArchive;
+use super::budget;
+use super::builder::{GitHubClientBuilder, PendingPolicy, RetryPolicy, DEFAULT_BASE_URL};
 use super::error::{GitHubError, Result};
+use super::etag::EtagCache;
+use super::graphql::AccountKind;
+use super::schema::SchemaMode;
+use super::settings::GitHubSettings;
+use super::source::MetricsSource;
+use crate::models::billing::{CopilotBilling, CopilotSeat, CopilotSeats, PremiumRequestUsage};
 use crate::models::github::CopilotMetrics;
-use tracing::{debug, error, info};
+use crate::models::usage::CopilotUsageDay;
+use crate::services::aws::sigv4::uri_encode;
+use crate::services::http_log;
+use crate::services::tls::AgentConfig;
+use serde::Deserialize;
+use std::collections::{BTreeSet, HashMap};
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::{Arc, Mutex};
+use tracing::{debug, error, info, warn};
+
+/// Percent-encodes a value for use as a single URL path segment
+///
+/// Slugs and logins come from the configuration, so characters such as `/`,
+/// `?`, `#` or spaces must not change the requested path.
+///
+/// # Arguments
+///
+/// * `value` - The enterprise ID, team slug or organization login
+fn segment(value: &str) -> String {
+    uri_encode(value, true)
+}
 
 /// Client for interacting with the GitHub API
 ///
@@ -32,7 +66,53 @@ pub struct GitHubClient {
     /// This token must have the appropriate scopes to access Copilot metrics:
     /// - For enterprise metrics: `admin:enterprise` scope
     /// - For team metrics: `admin:enterprise` and `read:org` scopes
-    token: String,
+    pub(super) token: String,
+
+    /// Base URL of the REST API, without a trailing slash
+    pub(super) base_url: String,
+
+    /// HTTP agent with the configured timeouts and user agent
+    pub(super) agent: ureq::Agent,
+
+    /// Whether request and response bodies are logged
+    pub(super) log_bodies: bool,
+
+    /// How requests failing with a transient error are retried
+    retry_policy: RetryPolicy,
+
+    /// How requests are retried while GitHub is still generating their data
+    pending_policy: PendingPolicy,
+
+    /// Retries made so far while GitHub was generating data, shared by clones
+    /// so `pending_policy.max_total_retries` bounds a whole run
+    pending_retries: Arc<AtomicU32>,
+
+    /// How strictly responses are validated against the models
+    schema_mode: SchemaMode,
+
+    /// ETags of earlier responses for conditional metrics requests, if any
+    etags: Option<Arc<EtagCache>>,
+
+    /// REST API version sent as `X-GitHub-Api-Version`
+    api_version: String,
+
+    /// Which endpoint metrics are read from
+    metrics_source: MetricsSource,
+
+    /// Archive metrics responses are kept in, if configured
+    archive: Option<Arc<RawArchive>>,
+
+    /// Accounts configured IDs resolved to, shared by clones so a run looks
+    /// each ID up once
+    pub(super) accounts: Arc<Mutex<HashMap<String, AccountKind>>>,
+}
+
+/// A successful response
+struct Response {
+    /// The response body
+    body: String,
+    /// The response's `ETag` header, if any
+    etag: Option<String>,
 }
 
 impl GitHubClient {
@@ -45,17 +125,267 @@ impl GitHubClient {
     ///
     /// # Returns
     ///
-    /// A new `GitHubClient` instance configured with the provided token
+    /// A new `GitHubClient` instance configured with the provided token.
+    /// The schema validation mode is read from the `STRICT_SCHEMA` environment variable
+    /// and the CA bundle from `GHRUST_CA_BUNDLE`.
     ///
     /// # Example
     ///
     /// ```
     /// use ghrust::services::github::GitHubClient;
-    /// let client = GitHubClient::new("ghp_your_personal_access_token");
+    /// let client = GitHubClient::builder()
+    ///     .token("ghp_your_personal_access_token")
+    ///     .build()
+    ///     .unwrap();
     /// ```
+    #[deprecated(note = "use `GitHubClient::builder()` instead")]
     pub fn new(token: &str) -> Self {
+        let agent = AgentConfig::from_env().unwrap_or_else(|e| {
+            error!("{}; falling back to the default trust store", e);
+            AgentConfig::default()
+        });
+        let settings = GitHubSettings {
+            agent,
+            ..GitHubSettings::from_env()
+        };
+        let agent = GitHubClientBuilder::new()
+            .settings(settings.clone())
+            .agent();
+        Self::from_parts(
+            token.to_string(),
+            DEFAULT_BASE_URL.to_string(),
+            agent,
+            settings.retry_policy,
+            settings.schema_mode,
+            None,
+            settings.api_version,
+        )
+        .with_metrics_source(settings.metrics_source)
+        .with_pending_policy(settings.pending_policy)
+        .with_body_logging(settings.agent.debug_log())
+    }
+
+    /// Returns a builder configuring the token source, base URL, timeouts,
+    /// retry policy, user agent and API version
+    ///
+    /// See [`GitHubClientBuilder`] for the available options.
+    pub fn builder() -> GitHubClientBuilder {
+        GitHubClientBuilder::new()
+    }
+
+    /// Creates a client from resolved settings
+    pub(super) fn from_parts(
+        token: String,
+        base_url: String,
+        agent: ureq::Agent,
+        retry_policy: RetryPolicy,
+        schema_mode: SchemaMode,
+        etags: Option<Arc<EtagCache>>,
+        api_version: String,
+    ) -> Self {
         Self {
-            token: token.to_string(),
+            token,
+            base_url,
+            agent,
+            log_bodies: false,
+            retry_policy,
+            pending_policy: PendingPolicy::default(),
+            pending_retries: Arc::new(AtomicU32::new(0)),
+            schema_mode,
+            etags,
+            api_version,
+            metrics_source: MetricsSource::default(),
+            archive: None,
+            accounts: Arc::default(),
+        }
+    }
+
+    /// Returns a copy of this client logging request and response bodies, or not
+    ///
+    /// # Arguments
+    ///
+    /// * `log_bodies` - Whether bodies are logged, see `http_log`
+    pub fn with_body_logging(mut self, log_bodies: bool) -> Self {
+        self.log_bodies = log_bodies;
+        self
+    }
+
+    /// Returns a copy of this client using the given schema validation mode
+    ///
+    /// # Arguments
+    ///
+    /// * `schema_mode` - How strictly responses are validated against the models
+    pub fn with_schema_mode(mut self, schema_mode: SchemaMode) -> Self {
+        self.schema_mode = schema_mode;
+        self
+    }
+
+    /// Returns a copy of this client reading metrics from the given endpoint
+    ///
+    /// # Arguments
+    ///
+    /// * `metrics_source` - Whether the metrics or the usage summary API is used
+    pub fn with_metrics_source(mut self, metrics_source: MetricsSource) -> Self {
+        self.metrics_source = metrics_source;
+        self
+    }
+
+    /// Returns a copy of this client retrying requests according to the given
+    /// policy while GitHub is still generating their data
+    ///
+    /// # Arguments
+    ///
+    /// * `pending_policy` - Retries and delay while the data is generated
+    pub fn with_pending_policy(mut self, pending_policy: PendingPolicy) -> Self {
+        self.pending_policy = pending_policy;
+        self
+    }
+
+    /// Returns a copy of this client keeping metrics responses in an archive
+    ///
+    /// # Arguments
+    ///
+    /// * `archive` - The archive
+    pub fn with_archive(mut self, archive: Arc<RawArchive>) -> Self {
+        self.archive = Some(archive);
+        self
+    }
+
+    /// Reads the enterprise-wide metrics window archived on a day
+    ///
+    /// # Arguments
+    ///
+    /// * `archive` - Archive the responses were kept in
+    /// * `day` - Day the window was fetched (YYYY-MM-DD)
+    /// * `enterprise_id` - ID of the GitHub Enterprise organization
+    ///
+    /// # Returns
+    ///
+    /// * `Result<Option<Vec<CopilotMetrics>>>` - The parsed window, `None` if
+    ///   nothing was archived that day, or an error if it can't be read or parsed
+    pub fn archived_enterprise_metrics(
+        &self,
+        archive: &RawArchive,
+        day: &str,
+        enterprise_id: &str,
+    ) -> Result<Option<Vec<CopilotMetrics>>> {
+        let url = format!(
+            "{}/enterprises/{}/copilot/metrics",
+            self.base_url,
+            segment(enterprise_id)
+        );
+        self.archived_metrics(archive, day, &url, "enterprise")
+    }
+
+    /// Reads the metrics window of a team archived on a day
+    ///
+    /// # Arguments
+    ///
+    /// * `archive` - Archive the responses were kept in
+    /// * `day` - Day the window was fetched (YYYY-MM-DD)
+    /// * `enterprise_id` - ID of the GitHub Enterprise organization
+    /// * `team_slug` - Slug of the team
+    ///
+    /// # Returns
+    ///
+    /// * `Result<Option<Vec<CopilotMetrics>>>` - The parsed window, `None` if
+    ///   nothing was archived that day, or an error if it can't be read or parsed
+    pub fn archived_team_metrics(
+        &self,
+        archive: &RawArchive,
+        day: &str,
+        enterprise_id: &str,
+        team_slug: &str,
+    ) -> Result<Option<Vec<CopilotMetrics>>> {
+        let url = format!(
+            "{}/enterprises/{}/team/{}/copilot/metrics",
+            self.base_url,
+            segment(enterprise_id),
+            segment(team_slug)
+        );
+        self.archived_metrics(archive, day, &url, "team")
+    }
+
+    /// Reads the metrics window of an organization archived on a day
+    ///
+    /// # Arguments
+    ///
+    /// * `archive` - Archive the responses were kept in
+    /// * `day` - Day the window was fetched (YYYY-MM-DD)
+    /// * `org` - Login of the organization
+    ///
+    /// # Returns
+    ///
+    /// * `Result<Option<Vec<CopilotMetrics>>>` - The parsed window, `None` if
+    ///   nothing was archived that day, or an error if it can't be read or parsed
+    pub fn archived_org_metrics(
+        &self,
+        archive: &RawArchive,
+        day: &str,
+        org: &str,
+    ) -> Result<Option<Vec<CopilotMetrics>>> {
+        let url = format!("{}/orgs/{}/copilot/metrics", self.base_url, segment(org));
+        self.archived_metrics(archive, day, &url, "organization")
+    }
+
+    /// Reads the metrics window of an organization's team archived on a day
+    ///
+    /// # Arguments
+    ///
+    /// * `archive` - Archive the responses were kept in
+    /// * `day` - Day the window was fetched (YYYY-MM-DD)
+    /// * `org` - Login of the organization
+    /// * `team_slug` - Slug of the team
+    ///
+    /// # Returns
+    ///
+    /// * `Result<Option<Vec<CopilotMetrics>>>` - The parsed window, `None` if
+    ///   nothing was archived that day, or an error if it can't be read or parsed
+    pub fn archived_org_team_metrics(
+        &self,
+        archive: &RawArchive,
+        day: &str,
+        org: &str,
+        team_slug: &str,
+    ) -> Result<Option<Vec<CopilotMetrics>>> {
+        let url = format!(
+            "{}/orgs/{}/team/{}/copilot/metrics",
+            self.base_url,
+            segment(org),
+            segment(team_slug)
+        );
+        self.archived_metrics(archive, day, &url, "team")
+    }
+
+    /// Reads and parses an archived metrics response of an endpoint
+    fn archived_metrics(
+        &self,
+        archive: &RawArchive,
+        day: &str,
+        url: &str,
+        context: &str,
+    ) -> Result<Option<Vec<CopilotMetrics>>> {
+        let url = self.metrics_source.endpoint(url);
+        let body = archive
+            .read(day, self.api_path(&url))
+            .map_err(|e| GitHubError::Archive(archive.location().to_string(), e.to_string()))?;
+        body.map(|body| self.parse_window(&body, context))
+            .transpose()
+    }
+
+    /// Returns the API path of a URL of this client, e.g.
+    /// "enterprises/acme/copilot/metrics"
+    fn api_path<'a>(&self, url: &'a str) -> &'a str {
+        url.strip_prefix(self.base_url.as_str())
+            .unwrap_or(url)
+            .trim_start_matches('/')
+    }
+
+    /// Parses a metrics window response of the configured endpoint
+    fn parse_window(&self, body: &str, context: &str) -> Result<Vec<CopilotMetrics>> {
+        match self.metrics_source {
+            MetricsSource::Metrics => self.parse_metrics(body, context),
+            MetricsSource::Usage => self.parse_usage(body, context),
         }
     }
 
@@ -66,7 +396,7 @@ impl GitHubClient {
     ///
     /// # Arguments
     ///
-    /// * `enterprise_id` - ID of the GitHub Enterprise organization (e.g., "123456")
+    /// * `enterprise_id` - Slug of the GitHub Enterprise (e.g., "acme"), not its numeric ID
     /// * `since_date` - ISO 8601 date string for filtering metrics (e.g., "2023-01-01")
     ///   Only metrics from this date onward will be returned
     ///
@@ -84,12 +414,13 @@ impl GitHubClient {
         since_date: &str,
     ) -> Result<Vec<CopilotMetrics>> {
         let url = format!(
-            "https://api.github.com/enterprises/{}/copilot/metrics",
-            enterprise_id
+            "{}/enterprises/{}/copilot/metrics",
+            self.base_url,
+            segment(enterprise_id)
         );
 
         info!("Fetching enterprise metrics for {}", enterprise_id);
-        self.fetch_metrics(&url, since_date, "enterprise")
+        self.fetch_metrics(&url, since_date, None, "enterprise")
     }
 
     /// Fetches team-specific Copilot metrics
@@ -100,7 +431,7 @@ impl GitHubClient {
     ///
     /// # Arguments
     ///
-    /// * `enterprise_id` - ID of the GitHub Enterprise organization (e.g., "123456")
+    /// * `enterprise_id` - Slug of the GitHub Enterprise (e.g., "acme"), not its numeric ID
     /// * `team_slug` - Slug of the team to fetch metrics for (e.g., "engineering")
     /// * `since_date` - ISO 8601 date string for filtering metrics (e.g., "2023-01-01")
     ///   Only metrics from this date onward will be returned
@@ -120,12 +451,472 @@ impl GitHubClient {
         since_date: &str,
     ) -> Result<Vec<CopilotMetrics>> {
         let url = format!(
-            "https://api.github.com/enterprises/{}/team/{}/copilot/metrics",
-            enterprise_id, team_slug
+            "{}/enterprises/{}/team/{}/copilot/metrics",
+            self.base_url,
+            segment(enterprise_id),
+            segment(team_slug)
         );
 
         info!("Fetching team metrics for {}/{}", enterprise_id, team_slug);
-        self.fetch_metrics(&url, since_date, "team")
+        self.fetch_metrics(&url, since_date, None, "team")
+    }
+
+    /// Fetches enterprise-wide Copilot metrics for a date range
+    ///
+    /// Used by backfills, which request one day at a time (`since_date` equal
+    /// to `until_date`) so a failure only loses that day.
+    ///
+    /// # Arguments
+    ///
+    /// * `enterprise_id` - ID of the GitHub Enterprise organization
+    /// * `since_date` - First day to return (YYYY-MM-DD)
+    /// * `until_date` - Last day to return, inclusive (YYYY-MM-DD)
+    ///
+    /// # Returns
+    ///
+    /// * `Result<Vec<CopilotMetrics>>` - The days of the range, or an error
+    pub fn fetch_enterprise_metrics_between(
+        &self,
+        enterprise_id: &str,
+        since_date: &str,
+        until_date: &str,
+    ) -> Result<Vec<CopilotMetrics>> {
+        let url = format!(
+            "{}/enterprises/{}/copilot/metrics",
+            self.base_url,
+            segment(enterprise_id)
+        );
+
+        info!(
+            "Fetching enterprise metrics for {} from {} to {}",
+            enterprise_id, since_date, until_date
+        );
+        self.fetch_metrics(&url, since_date, Some(until_date), "enterprise")
+    }
+
+    /// Fetches team-specific Copilot metrics for a date range
+    ///
+    /// # Arguments
+    ///
+    /// * `enterprise_id` - ID of the GitHub Enterprise organization
+    /// * `team_slug` - Slug of the team to fetch metrics for
+    /// * `since_date` - First day to return (YYYY-MM-DD)
+    /// * `until_date` - Last day to return, inclusive (YYYY-MM-DD)
+    ///
+    /// # Returns
+    ///
+    /// * `Result<Vec<CopilotMetrics>>` - The days of the range, or an error
+    pub fn fetch_team_metrics_between(
+        &self,
+        enterprise_id: &str,
+        team_slug: &str,
+        since_date: &str,
+        until_date: &str,
+    ) -> Result<Vec<CopilotMetrics>> {
+        let url = format!(
+            "{}/enterprises/{}/team/{}/copilot/metrics",
+            self.base_url,
+            segment(enterprise_id),
+            segment(team_slug)
+        );
+
+        info!(
+            "Fetching team metrics for {}/{} from {} to {}",
+            enterprise_id, team_slug, since_date, until_date
+        );
+        self.fetch_metrics(&url, since_date, Some(until_date), "team")
+    }
+
+    /// Fetches organization-specific Copilot metrics
+    ///
+    /// Retrieves Copilot usage metrics for a single organization, e.g. one of
+    /// the organizations of an enterprise.
+    ///
+    /// # Arguments
+    ///
+    /// * `org` - Login of the organization (e.g., "acme-payments")
+    /// * `since_date` - ISO 8601 date string for filtering metrics (e.g., "2023-01-01")
+    ///   Only metrics from this date onward will be returned
+    ///
+    /// # Returns
+    ///
+    /// * `Result<Vec<CopilotMetrics>>` - Collection of metrics data points on success,
+    ///   or an error if the API request fails or returns invalid data
+    ///
+    /// # API Endpoint
+    ///
+    /// `GET /orgs/{org}/copilot/metrics`
+    pub fn fetch_org_metrics(&self, org: &str, since_date: &str) -> Result<Vec<CopilotMetrics>> {
+        let url = format!("{}/orgs/{}/copilot/metrics", self.base_url, segment(org));
+
+        info!("Fetching organization metrics for {}", org);
+        self.fetch_metrics(&url, since_date, None, "organization")
+    }
+
+    /// Fetches organization-specific Copilot metrics for a date range
+    ///
+    /// # Arguments
+    ///
+    /// * `org` - Login of the organization
+    /// * `since_date` - First day to return (YYYY-MM-DD)
+    /// * `until_date` - Last day to return, inclusive (YYYY-MM-DD)
+    ///
+    /// # Returns
+    ///
+    /// * `Result<Vec<CopilotMetrics>>` - The days of the range, or an error
+    pub fn fetch_org_metrics_between(
+        &self,
+        org: &str,
+        since_date: &str,
+        until_date: &str,
+    ) -> Result<Vec<CopilotMetrics>> {
+        let url = format!("{}/orgs/{}/copilot/metrics", self.base_url, segment(org));
+
+        info!(
+            "Fetching organization metrics for {} from {} to {}",
+            org, since_date, until_date
+        );
+        self.fetch_metrics(&url, since_date, Some(until_date), "organization")
+    }
+
+    /// Fetches the Copilot metrics of a team of an organization
+    ///
+    /// # Arguments
+    ///
+    /// * `org` - Login of the organization
+    /// * `team_slug` - Slug of the team to fetch metrics for
+    /// * `since_date` - ISO 8601 date string for filtering metrics (e.g., "2023-01-01")
+    ///
+    /// # Returns
+    ///
+    /// * `Result<Vec<CopilotMetrics>>` - Collection of metrics data points on success,
+    ///   or an error if the API request fails or returns invalid data
+    ///
+    /// # API Endpoint
+    ///
+    /// `GET /orgs/{org}/team/{team_slug}/copilot/metrics`
+    pub fn fetch_org_team_metrics(
+        &self,
+        org: &str,
+        team_slug: &str,
+        since_date: &str,
+    ) -> Result<Vec<CopilotMetrics>> {
+        let url = format!(
+            "{}/orgs/{}/team/{}/copilot/metrics",
+            self.base_url,
+            segment(org),
+            segment(team_slug)
+        );
+
+        info!("Fetching team metrics for {}/{}", org, team_slug);
+        self.fetch_metrics(&url, since_date, None, "team")
+    }
+
+    /// Fetches the Copilot metrics of a team of an organization for a date range
+    ///
+    /// # Arguments
+    ///
+    /// * `org` - Login of the organization
+    /// * `team_slug` - Slug of the team to fetch metrics for
+    /// * `since_date` - First day to return (YYYY-MM-DD)
+    /// * `until_date` - Last day to return, inclusive (YYYY-MM-DD)
+    ///
+    /// # Returns
+    ///
+    /// * `Result<Vec<CopilotMetrics>>` - The days of the range, or an error
+    pub fn fetch_org_team_metrics_between(
+        &self,
+        org: &str,
+        team_slug: &str,
+        since_date: &str,
+        until_date: &str,
+    ) -> Result<Vec<CopilotMetrics>> {
+        let url = format!(
+            "{}/orgs/{}/team/{}/copilot/metrics",
+            self.base_url,
+            segment(org),
+            segment(team_slug)
+        );
+
+        info!(
+            "Fetching team metrics for {}/{} from {} to {}",
+            org, team_slug, since_date, until_date
+        );
+        self.fetch_metrics(&url, since_date, Some(until_date), "team")
+    }
+
+    /// Fetches the enterprise's premium request usage report
+    ///
+    /// Retrieves per-model premium request consumption (request counts,
+    /// multipliers and amounts) for the given billing period.
+    ///
+    /// # Arguments
+    ///
+    /// * `enterprise_id` - Slug of the GitHub Enterprise (e.g., "acme"), not its numeric ID
+    /// * `year` - Billing year to report on
+    /// * `month` - Billing month (1-12) to report on
+    ///
+    /// # Returns
+    ///
+    /// * `Result<PremiumRequestUsage>` - The usage report on success, or an error
+    ///   if the API request fails or returns invalid data
+    ///
+    /// # API Endpoint
+    ///
+    /// `GET /enterprises/{enterprise_id}/settings/billing/premium_request/usage`
+    pub fn fetch_premium_request_usage(
+        &self,
+        enterprise_id: &str,
+        year: i32,
+        month: u32,
+    ) -> Result<PremiumRequestUsage> {
+        let url = format!(
+            "{}/enterprises/{}/settings/billing/premium_request/usage",
+            self.base_url,
+            segment(enterprise_id)
+        );
+
+        info!(
+            "Fetching premium request usage for {} ({}-{:02})",
+            enterprise_id, year, month
+        );
+        self.fetch_premium_usage(&url, year, month)
+    }
+
+    /// Fetches an organization's premium request usage report
+    ///
+    /// # Arguments
+    ///
+    /// * `org` - Login of the organization
+    /// * `year` - Billing year to report on
+    /// * `month` - Billing month (1-12) to report on
+    ///
+    /// # Returns
+    ///
+    /// * `Result<PremiumRequestUsage>` - The usage report on success, or an error
+    ///   if the API request fails or returns invalid data
+    ///
+    /// # API Endpoint
+    ///
+    /// `GET /organizations/{org}/settings/billing/premium_request/usage`
+    pub fn fetch_org_premium_request_usage(
+        &self,
+        org: &str,
+        year: i32,
+        month: u32,
+    ) -> Result<PremiumRequestUsage> {
+        let url = format!(
+            "{}/organizations/{}/settings/billing/premium_request/usage",
+            self.base_url,
+            segment(org)
+        );
+
+        info!(
+            "Fetching premium request usage for organization {} ({}-{:02})",
+            org, year, month
+        );
+        self.fetch_premium_usage(&url, year, month)
+    }
+
+    /// Requests and parses a premium request usage report of a billing month
+    fn fetch_premium_usage(&self, url: &str, year: i32, month: u32) -> Result<PremiumRequestUsage> {
+        let body = self.get(
+            url,
+            &[("year", &year.to_string()), ("month", &month.to_string())],
+        )?;
+
+        let usage = serde_json::from_str::<PremiumRequestUsage>(&body).map_err(|e| {
+            GitHubError::ParseError("premium request usage".to_string(), e.to_string())
+        })?;
+        info!(
+            "Received {} premium request usage items",
+            usage.usage_items.len()
+        );
+
+        Ok(usage)
+    }
+
+    /// Fetches the Copilot billing information of an organization
+    ///
+    /// # Arguments
+    ///
+    /// * `org` - Login of the organization
+    ///
+    /// # Returns
+    ///
+    /// * `Result<CopilotBilling>` - The billing settings and seat breakdown on
+    ///   success, or an error if the API request fails or returns invalid data
+    ///
+    /// # API Endpoint
+    ///
+    /// `GET /orgs/{org}/copilot/billing`
+    pub fn fetch_org_billing(&self, org: &str) -> Result<CopilotBilling> {
+        let url = format!("{}/orgs/{}/copilot/billing", self.base_url, segment(org));
+
+        info!("Fetching Copilot billing for {}", org);
+        let body = self.get(&url, &[])?;
+        serde_json::from_str::<CopilotBilling>(&body)
+            .map_err(|e| GitHubError::ParseError("billing".to_string(), e.to_string()))
+    }
+
+    /// Fetches all Copilot seat assignments of an enterprise
+    ///
+    /// The seat list is paginated; pages of 100 seats are requested until the
+    /// reported total is reached or a page comes back empty.
+    ///
+    /// # Arguments
+    ///
+    /// * `enterprise_id` - Slug of the GitHub Enterprise (e.g., "acme"), not its numeric ID
+    ///
+    /// # Returns
+    ///
+    /// * `Result<Vec<CopilotSeat>>` - Every seat assignment on success, or an
+    ///   error if an API request fails or returns invalid data
+    ///
+    /// # API Endpoint
+    ///
+    /// `GET /enterprises/{enterprise_id}/copilot/billing/seats`
+    pub fn fetch_enterprise_seats(&self, enterprise_id: &str) -> Result<Vec<CopilotSeat>> {
+        let url = format!(
+            "{}/enterprises/{}/copilot/billing/seats",
+            self.base_url,
+            segment(enterprise_id)
+        );
+
+        info!("Fetching Copilot seats for {}", enterprise_id);
+        self.fetch_seats(&url)
+    }
+
+    /// Fetches all Copilot seat assignments of an organization
+    ///
+    /// # Arguments
+    ///
+    /// * `org` - Login of the organization
+    ///
+    /// # Returns
+    ///
+    /// * `Result<Vec<CopilotSeat>>` - Every seat assignment on success, or an
+    ///   error if an API request fails or returns invalid data
+    ///
+    /// # API Endpoint
+    ///
+    /// `GET /orgs/{org}/copilot/billing/seats`
+    pub fn fetch_org_seats(&self, org: &str) -> Result<Vec<CopilotSeat>> {
+        let url = format!(
+            "{}/orgs/{}/copilot/billing/seats",
+            self.base_url,
+            segment(org)
+        );
+
+        info!("Fetching Copilot seats for organization {}", org);
+        self.fetch_seats(&url)
+    }
+
+    /// Requests the pages of a seat list until all seats are read
+    fn fetch_seats(&self, url: &str) -> Result<Vec<CopilotSeat>> {
+        let mut seats = Vec::new();
+        for page in 1.. {
+            let body = self.get(url, &[("per_page", "100"), ("page", &page.to_string())])?;
+            let response = serde_json::from_str::<CopilotSeats>(&body)
+                .map_err(|e| GitHubError::ParseError("seats".to_string(), e.to_string()))?;
+
+            let received = response.seats.len();
+            seats.extend(response.seats);
+            if received == 0 || seats.len() >= response.total_seats {
+                break;
+            }
+        }
+        info!("Received {} Copilot seats", seats.len());
+
+        Ok(seats)
+    }
+
+    /// Lists the slugs of an enterprise's teams
+    ///
+    /// Follows the pages until one has fewer than the requested 100 teams.
+    ///
+    /// # Arguments
+    ///
+    /// * `enterprise_id` - Slug of the GitHub Enterprise (e.g., "acme")
+    ///
+    /// # Returns
+    ///
+    /// * `Result<Vec<String>>` - The team slugs, or an error if a request
+    ///   fails or returns invalid data
+    ///
+    /// # API Endpoint
+    ///
+    /// `GET /enterprises/{enterprise_id}/teams`
+    pub fn list_enterprise_teams(&self, enterprise_id: &str) -> Result<Vec<String>> {
+        #[derive(Deserialize)]
+        struct Team {
+            slug: String,
+        }
+
+        let url = format!(
+            "{}/enterprises/{}/teams",
+            self.base_url,
+            segment(enterprise_id)
+        );
+        info!("Listing teams of enterprise {}", enterprise_id);
+        let mut slugs = Vec::new();
+        for page in 1.. {
+            let body = self.get(&url, &[("per_page", "100"), ("page", &page.to_string())])?;
+            let teams = serde_json::from_str::<Vec<Team>>(&body)
+                .map_err(|e| GitHubError::ParseError("teams".to_string(), e.to_string()))?;
+
+            let received = teams.len();
+            slugs.extend(teams.into_iter().map(|team| team.slug));
+            if received < 100 {
+                break;
+            }
+        }
+        info!(
+            "Found {} teams in enterprise {}",
+            slugs.len(),
+            enterprise_id
+        );
+
+        Ok(slugs)
+    }
+
+    /// Fetches the OAuth scopes granted to the client's token
+    ///
+    /// GitHub lists the scopes of classic personal access tokens and OAuth
+    /// tokens in the `X-OAuth-Scopes` header; fine-grained tokens and GitHub
+    /// App tokens have permissions instead and don't send the header.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<Option<Vec<String>>>` - The scopes, `None` for tokens without
+    ///   scopes, or an error if the token is rejected or GitHub unreachable
+    ///
+    /// # API Endpoint
+    ///
+    /// `GET /user`
+    pub fn fetch_token_scopes(&self) -> Result<Option<Vec<String>>> {
+        let url = format!("{}/user", self.base_url);
+        let resp = match self
+            .agent
+            .get(&url)
+            .set("Accept", "application/vnd.github+json")
+            .set("Authorization", &format!("Bearer {}", self.token))
+            .set("X-GitHub-Api-Version", &self.api_version)
+            .call()
+        {
+            Ok(resp) => resp,
+            Err(e) => return self.handle_api_error(e),
+        };
+        budget::record(&resp);
+
+        Ok(resp.header("X-OAuth-Scopes").map(|scopes| {
+            scopes
+                .split(',')
+                .map(str::trim)
+                .filter(|scope| !scope.is_empty())
+                .map(str::to_string)
+                .collect()
+        }))
     }
 
     /// Core fetch metrics function used by both enterprise and team fetching
@@ -134,10 +925,19 @@ impl GitHubClient {
     /// different endpoints. It configures the HTTP request, handles authorization,
     /// processes the response, and parses the JSON data into CopilotMetrics objects.
     ///
+    /// With the usage summary source, the matching `/copilot/usage` endpoint
+    /// is requested instead and its days are mapped onto the metrics models.
+    ///
+    /// With an ETag cache, the request carries the ETag of the previous run's
+    /// response; if GitHub answers `304 Not Modified`, a `NotModified` error
+    /// is returned so the unchanged window is not sent again.
+    ///
     /// # Arguments
     ///
     /// * `url` - The complete GitHub API URL to fetch metrics from
     /// * `since_date` - ISO 8601 date string for filtering metrics
+    /// * `until_date` - Last day to return (inclusive), or `None` for the
+    ///   most recent day
     /// * `context` - String describing the context ("enterprise" or "team") for logging
     ///
     /// # Returns
@@ -154,49 +954,258 @@ impl GitHubClient {
         &self,
         url: &str,
         since_date: &str,
+        until_date: Option<&str>,
         context: &str,
     ) -> Result<Vec<CopilotMetrics>> {
+        let url = &self.metrics_source.endpoint(url);
         debug!("Requesting {} metrics from URL: {}", context, url);
 
-        let agent = ureq::AgentBuilder::new()
-            .timeout_connect(std::time::Duration::from_secs(5))
-            .timeout_read(std::time::Duration::from_secs(30))
-            .build();
+        let mut query = vec![("since", since_date)];
+        let mut key = format!("{}?since={}", url, since_date);
+        if let Some(until_date) = until_date {
+            query.push(("until", until_date));
+            key.push_str(&format!("&until={}", until_date));
+        }
+        let etag = self.etags.as_ref().and_then(|etags| etags.get(&key));
+        let Some(response) = self.get_conditional(url, &query, etag.as_deref(), true)? else {
+            return Err(GitHubError::NotModified(context.to_string()));
+        };
+
+        // Keep the full window as received, before it is parsed
+        if let (Some(archive), None) = (&self.archive, until_date) {
+            let today = chrono::Utc::now().format("%Y-%m-%d").to_string();
+            if let Err(e) = archive.write(&today, self.api_path(url), &response.body) {
+                warn!("Error archiving the {} metrics response: {}", context, e);
+            }
+        }
+
+        let metrics = self.parse_window(&response.body, context)?;
+        if let (Some(etags), Some(etag)) = (&self.etags, &response.etag) {
+            etags.record(&key, etag);
+        }
+        Ok(metrics)
+    }
+
+    /// Performs an authenticated GET request against the GitHub API
+    ///
+    /// Sets the standard GitHub headers, retries transient failures according
+    /// to the client's retry policy, and returns the raw response body so
+    /// callers can parse it into the appropriate model.
+    ///
+    /// # Arguments
+    ///
+    /// * `url` - The complete GitHub API URL
+    /// * `query` - Query parameters to append to the URL
+    ///
+    /// # Returns
+    ///
+    /// * `Result<String>` - The response body or an error
+    fn get(&self, url: &str, query: &[(&str, &str)]) -> Result<String> {
+        match self.get_conditional(url, query, None, false)? {
+            Some(response) => Ok(response.body),
+            None => Err(GitHubError::HttpError(
+                304,
+                "Not Modified without a conditional request".to_string(),
+            )),
+        }
+    }
 
-        let response = match agent
+    /// Performs an authenticated GET request, optionally conditional on an ETag
+    ///
+    /// # Arguments
+    ///
+    /// * `url` - The complete GitHub API URL
+    /// * `query` - Query parameters to append to the URL
+    /// * `etag` - ETag sent as `If-None-Match`, if any
+    /// * `metrics` - Whether this is a metrics endpoint, which answers
+    ///   `202 Accepted` or with an empty body while the data is generated
+    ///
+    /// # Returns
+    ///
+    /// * `Result<Option<Response>>` - The response, `None` if GitHub answered
+    ///   `304 Not Modified`, or an error
+    fn get_conditional(
+        &self,
+        url: &str,
+        query: &[(&str, &str)],
+        etag: Option<&str>,
+        metrics: bool,
+    ) -> Result<Option<Response>> {
+        let mut retry = 0;
+        let mut pending = 0;
+        loop {
+            match self.get_once(url, query, etag, metrics) {
+                Err(GitHubError::NoDataYet(_))
+                    if pending < self.pending_policy.max_retries && self.take_pending_retry() =>
+                {
+                    pending += 1;
+                    info!(
+                        "GitHub is still generating the data of {}, retry {}/{} in {:?}",
+                        url, pending, self.pending_policy.max_retries, self.pending_policy.delay
+                    );
+                    std::thread::sleep(self.pending_policy.delay);
+                }
+                Err(e @ GitHubError::NoDataYet(_)) => {
+                    warn!(
+                        "GitHub was still generating the data of {} after {} retries \
+                         ({} retries used by this run)",
+                        url,
+                        pending,
+                        self.pending_retries.load(Ordering::SeqCst)
+                    );
+                    return Err(e);
+                }
+                Err(e) if e.is_retryable() && retry < self.retry_policy.max_retries => {
+                    retry += 1;
+                    let delay = self.retry_policy.delay(retry);
+                    warn!(
+                        "GitHub request to {} failed ({}), retry {}/{} in {:?}",
+                        url, e, retry, self.retry_policy.max_retries, delay
+                    );
+                    std::thread::sleep(delay);
+                }
+                // Report how often the server failed once the retries are used up
+                Err(GitHubError::ServerError(status, _, body)) => {
+                    return Err(GitHubError::ServerError(status, retry + 1, body));
+                }
+                result => return result,
+            }
+        }
+    }
+
+    /// Takes one retry from the run's budget for data still being generated
+    ///
+    /// # Returns
+    ///
+    /// * `bool` - Whether the budget allowed another retry
+    fn take_pending_retry(&self) -> bool {
+        let budget = self.pending_policy.max_total_retries;
+        self.pending_retries
+            .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |used| {
+                (used < budget).then_some(used + 1)
+            })
+            .is_ok()
+    }
+
+    /// Performs a single authenticated GET request
+    ///
+    /// A `202 Accepted` or an empty body is reported as `NoDataYet` only for
+    /// metrics endpoints; other endpoints return the body as received.
+    fn get_once(
+        &self,
+        url: &str,
+        query: &[(&str, &str)],
+        etag: Option<&str>,
+        metrics: bool,
+    ) -> Result<Option<Response>> {
+        let mut request = self
+            .agent
             .get(url)
-            .query("since", since_date)
             .set("Accept", "application/vnd.github+json")
             .set("Authorization", &format!("Bearer {}", self.token))
-            .set("X-GitHub-Api-Version", "2022-11-28")
-            .call()
-        {
-            Ok(resp) => match resp.into_string() {
-                Ok(body) => body,
-                Err(e) => {
-                    return Err(GitHubError::Network(format!(
-                        "Failed to read response: {}",
-                        e
-                    )))
+            .set("X-GitHub-Api-Version", &self.api_version);
+        if let Some(etag) = etag {
+            request = request.set("If-None-Match", etag);
+        }
+        for (key, value) in query {
+            request = request.query(key, value);
+        }
+
+        let resp = match request.call() {
+            Ok(resp) => {
+                budget::record(&resp);
+                match resp.status() {
+                    304 => return Ok(None),
+                    // Accepted: the data is still being generated
+                    202 if metrics => return Err(GitHubError::NoDataYet(url.to_string())),
+                    _ => resp,
                 }
-            },
-            Err(e) => return self.handle_api_error(e),
+            }
+            Err(e) => {
+                if let ureq::Error::Status(_, response) = &e {
+                    budget::record(response);
+                }
+                return self.handle_api_error(e);
+            }
         };
+        let etag = resp.header("ETag").map(str::to_string);
+        let body = resp
+            .into_string()
+            .map_err(|e| GitHubError::Network(format!("Failed to read response: {}", e)))?;
 
-        debug!("Received API response ({} bytes)", response.len());
+        debug!("Received API response ({} bytes)", body.len());
+        if self.log_bodies {
+            http_log::log_body("response", url, &body);
+        }
+        if metrics && body.trim().is_empty() {
+            return Err(GitHubError::NoDataYet(url.to_string()));
+        }
+        Ok(Some(Response { body, etag }))
+    }
 
-        match serde_json::from_str::<Vec<CopilotMetrics>>(&response) {
-            Ok(metrics) => {
-                if metrics.is_empty() {
-                    info!("No metrics data available");
-                } else {
-                    info!("Received {} data points", metrics.len());
-                    self.log_metrics_summary(&metrics);
-                }
-                Ok(metrics)
-            }
-            Err(e) => Err(GitHubError::ParseError(context.to_string(), e.to_string())),
+    /// Parses a metrics response body and validates it against the models
+    ///
+    /// # Arguments
+    ///
+    /// * `body` - The raw JSON response body
+    /// * `context` - String describing the context ("enterprise" or "team") for logging
+    ///
+    /// # Returns
+    ///
+    /// * `Result<Vec<CopilotMetrics>>` - The parsed metrics or an error
+    ///
+    /// # Errors
+    ///
+    /// - `ParseError` if the body is not a valid metrics response
+    /// - `SchemaDrift` if the response contains unknown fields in strict schema mode
+    pub(crate) fn parse_metrics(&self, body: &str, context: &str) -> Result<Vec<CopilotMetrics>> {
+        let metrics = serde_json::from_str::<Vec<CopilotMetrics>>(body)
+            .map_err(|e| GitHubError::ParseError(context.to_string(), e.to_string()))?;
+
+        if metrics.is_empty() {
+            info!("No metrics data available");
+            return Ok(metrics);
         }
+
+        info!("Received {} data points", metrics.len());
+        self.log_metrics_summary(&metrics);
+
+        let unknown = self.report_unknown_fields(&metrics, context);
+        if self.schema_mode == SchemaMode::Strict && !unknown.is_empty() {
+            error!(
+                "Strict schema mode: rejecting {} metrics response with unknown fields",
+                context
+            );
+            return Err(GitHubError::SchemaDrift(context.to_string(), unknown));
+        }
+
+        Ok(metrics)
+    }
+
+    /// Parses a usage summary response and maps its days onto the metrics models
+    ///
+    /// # Arguments
+    ///
+    /// * `body` - The JSON response body of a `/copilot/usage` endpoint
+    /// * `context` - String describing the context for logging and errors
+    ///
+    /// # Returns
+    ///
+    /// * `Result<Vec<CopilotMetrics>>` - One metrics entry per usage day, or a
+    ///   parse error
+    pub(crate) fn parse_usage(&self, body: &str, context: &str) -> Result<Vec<CopilotMetrics>> {
+        let days = serde_json::from_str::<Vec<CopilotUsageDay>>(body)
+            .map_err(|e| GitHubError::ParseError(context.to_string(), e.to_string()))?;
+
+        if days.is_empty() {
+            info!("No usage data available");
+            return Ok(Vec::new());
+        }
+
+        info!("Received {} usage days", days.len());
+        let metrics: Vec<CopilotMetrics> = days.iter().map(CopilotUsageDay::to_metrics).collect();
+        self.log_metrics_summary(&metrics);
+        Ok(metrics)
     }
 
     /// Helper function to handle API errors
@@ -211,7 +1220,7 @@ impl GitHubClient {
     ///
     /// # Returns
     ///
-    /// * `Result<Vec<CopilotMetrics>>` - Always returns an Err with a contextualized message
+    /// * `Result<T>` - Always returns an Err with a contextualized message
     ///
     /// # Error Handling
     ///
@@ -221,13 +1230,18 @@ impl GitHubClient {
     /// - 404: Resource not found
     /// - 422: Validation errors
     /// - 429: Rate limit exceeded
-    fn handle_api_error(&self, e: ureq::Error) -> Result<Vec<CopilotMetrics>> {
+    /// - 500-504: Server errors, retried by the retry policy
+    pub(super) fn handle_api_error<T>(&self, e: ureq::Error) -> Result<T> {
         match e {
             ureq::Error::Status(status, response) => {
+                let url = response.get_url().to_string();
                 let body = response
                     .into_string()
                     .unwrap_or_else(|_| "Could not read response body".to_string());
                 error!("HTTP error {}: {}", status, body);
+                if self.log_bodies {
+                    http_log::log_body("response", &url, &body);
+                }
 
                 match status {
                     401 => Err(GitHubError::Authentication(body)),
@@ -235,6 +1249,7 @@ impl GitHubClient {
                     404 => Err(GitHubError::NotFound(body)),
                     422 => Err(GitHubError::Validation(body)),
                     429 => Err(GitHubError::RateLimit(body)),
+                    500..=504 => Err(GitHubError::ServerError(status, 1, body)),
                     _ => Err(GitHubError::HttpError(status, body)),
                 }
             }
@@ -245,11 +1260,49 @@ impl GitHubClient {
         }
     }
 
+    /// Reports fields in the API response that the models do not know about
+    ///
+    /// GitHub keeps adding fields to the metrics API. They are preserved in the
+    /// models' `extra` maps, and this helper lists their paths once per response
+    /// so new data gets noticed. The report is logged at `warn` level when the
+    /// `REPORT_UNKNOWN_FIELDS` environment variable is set or a non-lenient
+    /// schema mode is active, otherwise at `debug`.
+    ///
+    /// # Arguments
+    ///
+    /// * `metrics` - Collection of CopilotMetrics objects to inspect
+    /// * `context` - String describing the context ("enterprise" or "team") for logging
+    ///
+    /// # Returns
+    ///
+    /// The sorted, de-duplicated list of unknown field paths
+    fn report_unknown_fields(&self, metrics: &[CopilotMetrics], context: &str) -> Vec<String> {
+        let paths: BTreeSet<String> = metrics
+            .iter()
+            .flat_map(|m| m.unknown_field_paths())
+            .collect();
+        let paths: Vec<String> = paths.into_iter().collect();
+
+        if paths.is_empty() {
+            return paths;
+        }
+
+        let listed = paths.join(", ");
+        if std::env::var("REPORT_UNKNOWN_FIELDS").is_ok() || self.schema_mode != SchemaMode::Lenient
+        {
+            warn!("Unknown fields in {} metrics response: {}", context, listed);
+        } else {
+            debug!("Unknown fields in {} metrics response: {}", context, listed);
+        }
+
+        paths
+    }
+
     /// Helper function to log metrics summary
     ///
     /// Provides a concise log of the metrics retrieved, including counts of
     /// active and engaged users for each feature area (IDE code completions,
-    /// IDE chat, GitHub.com chat, and GitHub.com pull requests).
+    /// IDE chat, GitHub.com chat, GitHub.com pull requests and code review).
     ///
     /// This is useful for quick verification that the metrics are being
     /// retrieved correctly and contain the expected data.
@@ -294,6 +1347,13 @@ impl GitHubClient {
                         .as_ref()
                         .map(|c| c.total_engaged_users),
                 ),
+                (
+                    "Code Review",
+                    metric
+                        .copilot_code_review
+                        .as_ref()
+                        .map(|c| c.total_engaged_users),
+                ),
             ]
             .iter()
             .filter_map(|(name, users)| users.map(|u| (name, u)))
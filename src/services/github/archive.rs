@@ -0,0 +1,153 @@
+//! # Raw Response Archive
+//!
+//! With an archive configured (`GITHUB_RAW_ARCHIVE`), the body of every
+//! metrics window response is kept exactly as GitHub sent it, in a local
+//! directory or an S3 location, under the day it was fetched and the API path:
+//!
+//! ```text
+//! {location}/2025-03-02/enterprises/acme/copilot/metrics.json
+//! {location}/2025-03-02/enterprises/acme/team/web/copilot/metrics.json
+//! ```
+//!
+//! `ghrust replay` reads the responses of a day back and sends them through
+//! the current transformation and sinks, e.g. after a Datadog outage or to
+//! fill a new namespace after a migration. A response is archived before it
+//! is parsed, so a window the models couldn't parse can be replayed after a
+//! fix. Archiving is best effort and never fails a fetch.
+
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+
+use crate::services::aws::{AwsCredentials, S3Client};
+use crate::services::store::s3::parse_location;
+use crate::services::tls::AgentConfig;
+
+/// Where archived responses are kept
+enum Location {
+    /// A local directory
+    Local(PathBuf),
+    /// A bucket and key prefix
+    S3 { client: S3Client, prefix: String },
+}
+
+/// Raw metrics responses, keyed by the day they were fetched and API path
+pub struct RawArchive {
+    /// The configured location, for logs and errors
+    location: String,
+    /// Where the responses are kept
+    backend: Location,
+}
+
+impl std::fmt::Debug for RawArchive {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("RawArchive")
+            .field("location", &self.location)
+            .finish()
+    }
+}
+
+impl RawArchive {
+    /// Opens an archive in a local directory or at `s3://<bucket>/<prefix>`
+    ///
+    /// # Arguments
+    ///
+    /// * `location` - Local directory or S3 location of the archive
+    /// * `agent` - Settings of the HTTP agent of an S3 location
+    ///
+    /// # Returns
+    ///
+    /// * `io::Result<RawArchive>` - The archive, or an error if the AWS
+    ///   credentials of an S3 location can't be loaded
+    pub fn open(location: &str, agent: &AgentConfig) -> io::Result<Self> {
+        let backend = match parse_location(location) {
+            Some((bucket, prefix)) => Location::S3 {
+                client: S3Client::new(
+                    AwsCredentials::from_env().map_err(io::Error::other)?,
+                    bucket,
+                    agent,
+                ),
+                prefix: prefix.to_string(),
+            },
+            None => Location::Local(PathBuf::from(location)),
+        };
+        Ok(Self {
+            location: location.to_string(),
+            backend,
+        })
+    }
+
+    /// The configured location
+    pub fn location(&self) -> &str {
+        &self.location
+    }
+
+    /// Stores a response body
+    ///
+    /// # Arguments
+    ///
+    /// * `day` - Day the response was fetched (YYYY-MM-DD)
+    /// * `api_path` - API path of the request, e.g. "enterprises/acme/copilot/metrics"
+    /// * `body` - The response body
+    pub fn write(&self, day: &str, api_path: &str, body: &str) -> io::Result<()> {
+        let name = object_name(day, api_path);
+        match &self.backend {
+            Location::Local(dir) => {
+                let path = dir.join(&name);
+                if let Some(parent) = path.parent() {
+                    fs::create_dir_all(parent)?;
+                }
+                fs::write(path, body)
+            }
+            Location::S3 { client, prefix } => client
+                .put_object(&join(prefix, &name), body.as_bytes(), "application/json")
+                .map_err(io::Error::other),
+        }
+    }
+
+    /// Reads a response body stored on a day
+    ///
+    /// # Arguments
+    ///
+    /// * `day` - Day the response was fetched (YYYY-MM-DD)
+    /// * `api_path` - API path of the request
+    ///
+    /// # Returns
+    ///
+    /// * `io::Result<Option<String>>` - The body, or `None` if no response of
+    ///   the path was archived that day
+    pub fn read(&self, day: &str, api_path: &str) -> io::Result<Option<String>> {
+        let name = object_name(day, api_path);
+        let contents = match &self.backend {
+            Location::Local(dir) => match fs::read(dir.join(&name)) {
+                Ok(contents) => Some(contents),
+                Err(e) if e.kind() == io::ErrorKind::NotFound => None,
+                Err(e) => return Err(e),
+            },
+            Location::S3 { client, prefix } => client
+                .get_object(&join(prefix, &name))
+                .map_err(io::Error::other)?,
+        };
+        contents
+            .map(|contents| {
+                String::from_utf8(contents)
+                    .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+            })
+            .transpose()
+    }
+}
+
+/// Returns the object name of a response, e.g.
+/// "2025-03-02/enterprises/acme/copilot/metrics.json"
+fn object_name(day: &str, api_path: &str) -> String {
+    format!("{}/{}.json", day, api_path.trim_matches('/'))
+}
+
+/// Joins an S3 key prefix and an object name
+fn join(prefix: &str, name: &str) -> String {
+    if prefix.is_empty() {
+        name.to_string()
+    } else {
+        format!("{}/{}", prefix, name)
+    }
+}
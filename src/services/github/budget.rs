@@ -0,0 +1,147 @@
+//! # Rate Limit Budget
+//!
+//! Counts the REST requests GitHub answered and remembers the rate limit it
+//! last reported in the `X-RateLimit-*` headers, so a run can report how much
+//! of the token's hourly budget it used and how much is left. GraphQL queries
+//! have a separate, point-based limit and aren't counted.
+//!
+//! A process can build several clients, so the counters are kept per
+//! process; callers take a snapshot before a run and report the difference,
+//! like `SendStats`.
+
+use std::sync::atomic::{AtomicI64, AtomicU64, Ordering};
+
+use serde::Serialize;
+
+use crate::services::datadog::Gauge;
+
+/// Counters of every GitHub client of the process
+static COUNTERS: RateLimitCounters = RateLimitCounters::new();
+
+/// Marks a rate limit header that hasn't been seen yet
+const UNKNOWN: i64 = -1;
+
+/// Requests made and the rate limit GitHub last reported
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+pub struct RateLimitBudget {
+    /// REST requests answered by GitHub
+    pub requests: u64,
+    /// Requests left in the current rate limit window, if reported
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub remaining: Option<u64>,
+    /// Requests allowed per rate limit window, if reported
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub limit: Option<u64>,
+    /// Unix time the current window resets at, if reported
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reset: Option<i64>,
+}
+
+impl RateLimitBudget {
+    /// Returns the counters of every GitHub client of the process
+    pub fn snapshot() -> Self {
+        COUNTERS.snapshot()
+    }
+
+    /// Returns the requests made since an earlier snapshot, with the latest
+    /// reported rate limit
+    ///
+    /// # Arguments
+    ///
+    /// * `earlier` - The snapshot taken before the run
+    pub fn since(&self, earlier: &RateLimitBudget) -> RateLimitBudget {
+        RateLimitBudget {
+            requests: self.requests.saturating_sub(earlier.requests),
+            ..*self
+        }
+    }
+
+    /// Returns the budget as collector gauges
+    ///
+    /// The rate limit gauges are omitted if GitHub didn't report them, e.g.
+    /// when no request was made.
+    ///
+    /// # Arguments
+    ///
+    /// * `date` - Date (YYYY-MM-DD) of the run
+    pub fn gauges(&self, date: &str) -> Vec<Gauge> {
+        let mut gauges = vec![Gauge::new(
+            "collector.github.requests",
+            self.requests as f64,
+            date,
+        )];
+        if let Some(remaining) = self.remaining {
+            gauges.push(Gauge::new(
+                "collector.github.rate_limit.remaining",
+                remaining as f64,
+                date,
+            ));
+        }
+        if let Some(limit) = self.limit {
+            gauges.push(Gauge::new(
+                "collector.github.rate_limit.limit",
+                limit as f64,
+                date,
+            ));
+        }
+        gauges
+    }
+}
+
+/// Thread-safe request and rate limit counters
+#[derive(Debug)]
+pub(crate) struct RateLimitCounters {
+    requests: AtomicU64,
+    remaining: AtomicI64,
+    limit: AtomicI64,
+    reset: AtomicI64,
+}
+
+impl RateLimitCounters {
+    /// Creates counters without requests or a reported rate limit
+    pub(crate) const fn new() -> Self {
+        Self {
+            requests: AtomicU64::new(0),
+            remaining: AtomicI64::new(UNKNOWN),
+            limit: AtomicI64::new(UNKNOWN),
+            reset: AtomicI64::new(UNKNOWN),
+        }
+    }
+
+    /// Records a request answered by GitHub and the rate limit headers of
+    /// its response
+    ///
+    /// # Arguments
+    ///
+    /// * `header` - Looks up a response header by name
+    pub(crate) fn record<'a>(&self, header: impl Fn(&str) -> Option<&'a str>) {
+        self.requests.fetch_add(1, Ordering::Relaxed);
+        for (name, counter) in [
+            ("X-RateLimit-Remaining", &self.remaining),
+            ("X-RateLimit-Limit", &self.limit),
+            ("X-RateLimit-Reset", &self.reset),
+        ] {
+            if let Some(value) = header(name).and_then(|v| v.trim().parse::<i64>().ok()) {
+                counter.store(value, Ordering::Relaxed);
+            }
+        }
+    }
+
+    /// Returns the current counters
+    pub(crate) fn snapshot(&self) -> RateLimitBudget {
+        let known = |counter: &AtomicI64| {
+            Some(counter.load(Ordering::Relaxed)).filter(|value| *value != UNKNOWN)
+        };
+        RateLimitBudget {
+            requests: self.requests.load(Ordering::Relaxed),
+            remaining: known(&self.remaining).map(|value| value as u64),
+            limit: known(&self.limit).map(|value| value as u64),
+            reset: known(&self.reset),
+        }
+    }
+}
+
+/// Records a response of the GitHub API in the process counters
+pub(crate) fn record(response: &ureq::Response) {
+    COUNTERS.record(|name| response.header(name));
+}
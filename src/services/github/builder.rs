@@ -0,0 +1,440 @@
+//! # GitHub Client Builder
+//!
+//! `GitHubClientBuilder` configures a `GitHubClient` explicitly: where the
+//! token comes from, the API base URL (for GitHub Enterprise Server), timeouts,
+//! the retry policy for transient failures, the user agent and the REST API
+//! version. Transient failures are retried according to a `RetryPolicy`;
+//! requests GitHub answers with "still generating" are retried according to a
+//! `PendingPolicy`. The builder never reads the environment: unset options
+//! keep the neutral defaults of `GitHubSettings::default()`, and the
+//! configuration passes the values it read once with `settings`.
+//!
+//! ```
+//! use std::time::Duration;
+//! use ghrust::services::github::{GitHubClient, RetryPolicy, TokenSource};
+//!
+//! let client = GitHubClient::builder()
+//!     .token_source(TokenSource::Static("ghp_your_personal_access_token".to_string()))
+//!     .base_url("https://github.example.com/api/v3")
+//!     .read_timeout(Duration::from_secs(60))
+//!     .retry_policy(RetryPolicy::new(3, Duration::from_millis(500)))
+//!     .user_agent("my-service/1.0")
+//!     .api_version("2022-11-28")
+//!     .build()
+//!     .unwrap();
+//! ```
+
+use std::env;
+use std::sync::Arc;
+use std::time::Duration;
+
+use tracing::warn;
+
+use super::api::GitHubClient;
+use super::error::{GitHubError, Result};
+use super::etag::EtagCache;
+use super::schema::SchemaMode;
+use super::settings::GitHubSettings;
+use super::source::MetricsSource;
+
+/// Base URL of the public GitHub API
+pub const DEFAULT_BASE_URL: &str = "https://api.github.com";
+
+/// User agent sent with every request unless configured otherwise
+pub const DEFAULT_USER_AGENT: &str = concat!("ghrust/", env!("CARGO_PKG_VERSION"));
+
+/// REST API version requested with `X-GitHub-Api-Version` unless configured
+/// otherwise
+pub const DEFAULT_API_VERSION: &str = "2022-11-28";
+
+/// Retries of a request failing with a transient error, by default
+pub const DEFAULT_MAX_RETRIES: u32 = 3;
+
+/// Delay before the first retry of a request failing with a transient error,
+/// by default
+pub const DEFAULT_RETRY_BACKOFF: Duration = Duration::from_millis(500);
+
+/// Retries of a request GitHub is still generating the data of, by default
+pub const DEFAULT_PENDING_RETRIES: u32 = 3;
+
+/// Delay before retrying a request GitHub is still generating the data of,
+/// by default
+pub const DEFAULT_PENDING_DELAY: Duration = Duration::from_secs(10);
+
+/// Retries of requests GitHub is still generating the data of that a client
+/// makes in total, by default
+pub const DEFAULT_PENDING_RETRY_BUDGET: u32 = 6;
+
+/// Where the client's access token comes from
+#[derive(Debug, Clone, PartialEq)]
+pub enum TokenSource {
+    /// A token given directly
+    Static(String),
+    /// The name of an environment variable holding the token
+    Env(String),
+}
+
+impl TokenSource {
+    /// Resolves the token
+    ///
+    /// # Returns
+    ///
+    /// * `Result<String>` - The token, or a `Config` error if it is empty or
+    ///   the environment variable is not set
+    pub fn resolve(&self) -> Result<String> {
+        let token = match self {
+            Self::Static(token) => token.clone(),
+            Self::Env(name) => env::var(name).map_err(|_| {
+                GitHubError::Config(format!("{} environment variable not set", name))
+            })?,
+        };
+
+        let token = token.trim().to_string();
+        if token.is_empty() {
+            return Err(GitHubError::Config("the token is empty".to_string()));
+        }
+        Ok(token)
+    }
+}
+
+/// How requests failing with a transient error are retried
+///
+/// Rate limits, network errors and server errors (5xx) are retried with
+/// exponential backoff; other errors are returned immediately.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RetryPolicy {
+    /// Retries after the first attempt
+    pub max_retries: u32,
+    /// Delay before the first retry; doubled for every further retry
+    pub backoff: Duration,
+}
+
+impl RetryPolicy {
+    /// Creates a retry policy
+    ///
+    /// # Arguments
+    ///
+    /// * `max_retries` - Retries after the first attempt
+    /// * `backoff` - Delay before the first retry, doubled for every further retry
+    pub fn new(max_retries: u32, backoff: Duration) -> Self {
+        Self {
+            max_retries,
+            backoff,
+        }
+    }
+
+    /// A policy that never retries
+    pub fn none() -> Self {
+        Self::new(0, Duration::ZERO)
+    }
+
+    /// Reads the policy from `GITHUB_MAX_RETRIES` and
+    /// `GITHUB_RETRY_BACKOFF_MS`
+    ///
+    /// # Returns
+    ///
+    /// The configured policy; unset or invalid values fall back to
+    /// `DEFAULT_MAX_RETRIES` retries starting `DEFAULT_RETRY_BACKOFF` apart
+    pub fn from_env() -> Self {
+        Self::new(
+            u32_var("GITHUB_MAX_RETRIES").unwrap_or(DEFAULT_MAX_RETRIES),
+            u32_var("GITHUB_RETRY_BACKOFF_MS")
+                .map(|ms| Duration::from_millis(ms.into()))
+                .unwrap_or(DEFAULT_RETRY_BACKOFF),
+        )
+    }
+
+    /// Delay before the given retry (1 for the first retry)
+    pub fn delay(&self, retry: u32) -> Duration {
+        self.backoff
+            .saturating_mul(2u32.saturating_pow(retry.saturating_sub(1)))
+    }
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self::none()
+    }
+}
+
+/// Parses a retry count or delay that must be a non-negative whole number
+///
+/// # Arguments
+///
+/// * `value` - The number, e.g. "3"
+///
+/// # Returns
+///
+/// * `Result<u32, String>` - The number, or a description of why the value is
+///   not a non-negative whole number
+pub fn parse_count(value: &str) -> std::result::Result<u32, String> {
+    value
+        .trim()
+        .parse()
+        .map_err(|_| format!("expected a non-negative whole number, got {}", value))
+}
+
+/// Reads a non-negative integer environment variable, ignoring invalid values
+fn u32_var(name: &str) -> Option<u32> {
+    let value = env::var(name).ok()?;
+    parse_count(&value)
+        .map_err(|e| warn!("Ignoring invalid {}: {}", name, e))
+        .ok()
+}
+
+/// How requests are retried while GitHub is still generating their data
+///
+/// The metrics endpoints answer `202 Accepted` or with an empty body until the
+/// data of a window has been generated. Such requests are retried after a
+/// fixed delay; once the retries are used up the request fails with a
+/// `NoDataYet` error rather than a parse error. Other endpoints are never
+/// retried this way.
+///
+/// A run builds one client, so `max_total_retries` bounds the time a run
+/// spends waiting for data however many scopes are still pending.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PendingPolicy {
+    /// Retries after the first attempt
+    pub max_retries: u32,
+    /// Delay before every retry
+    pub delay: Duration,
+    /// Retries a client and its clones make in total, over all requests
+    pub max_total_retries: u32,
+}
+
+impl PendingPolicy {
+    /// Creates a pending policy
+    ///
+    /// # Arguments
+    ///
+    /// * `max_retries` - Retries after the first attempt
+    /// * `delay` - Delay before every retry
+    pub fn new(max_retries: u32, delay: Duration) -> Self {
+        Self {
+            max_retries,
+            delay,
+            max_total_retries: DEFAULT_PENDING_RETRY_BUDGET,
+        }
+    }
+
+    /// Returns the policy with a budget of retries over all requests
+    ///
+    /// # Arguments
+    ///
+    /// * `max_total_retries` - Retries a client and its clones make in total
+    pub fn with_max_total_retries(mut self, max_total_retries: u32) -> Self {
+        self.max_total_retries = max_total_retries;
+        self
+    }
+
+    /// A policy that never waits for the data
+    pub fn none() -> Self {
+        Self::new(0, Duration::ZERO).with_max_total_retries(0)
+    }
+
+    /// Reads the policy from `GITHUB_PENDING_RETRIES`,
+    /// `GITHUB_PENDING_DELAY_SECS` and `GITHUB_PENDING_RETRY_BUDGET`
+    ///
+    /// # Returns
+    ///
+    /// The configured policy; unset or invalid values keep their defaults
+    pub fn from_env() -> Self {
+        let defaults = Self::default();
+        Self::new(
+            u32_var("GITHUB_PENDING_RETRIES").unwrap_or(defaults.max_retries),
+            u32_var("GITHUB_PENDING_DELAY_SECS")
+                .map(|secs| Duration::from_secs(secs.into()))
+                .unwrap_or(defaults.delay),
+        )
+        .with_max_total_retries(
+            u32_var("GITHUB_PENDING_RETRY_BUDGET").unwrap_or(defaults.max_total_retries),
+        )
+    }
+}
+
+impl Default for PendingPolicy {
+    fn default() -> Self {
+        Self::new(DEFAULT_PENDING_RETRIES, DEFAULT_PENDING_DELAY)
+    }
+}
+
+/// Builder for a `GitHubClient`
+#[derive(Debug, Clone)]
+pub struct GitHubClientBuilder {
+    token: Option<TokenSource>,
+    base_url: String,
+    connect_timeout: Duration,
+    read_timeout: Duration,
+    settings: GitHubSettings,
+    etags: Option<Arc<EtagCache>>,
+}
+
+impl Default for GitHubClientBuilder {
+    fn default() -> Self {
+        Self {
+            token: None,
+            base_url: DEFAULT_BASE_URL.to_string(),
+            connect_timeout: Duration::from_secs(5),
+            read_timeout: Duration::from_secs(30),
+            settings: GitHubSettings::default(),
+            etags: None,
+        }
+    }
+}
+
+impl GitHubClientBuilder {
+    /// Creates a builder with default settings
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Uses the given access token
+    pub fn token(self, token: impl Into<String>) -> Self {
+        self.token_source(TokenSource::Static(token.into()))
+    }
+
+    /// Sets where the access token comes from (required)
+    pub fn token_source(mut self, token: TokenSource) -> Self {
+        self.token = Some(token);
+        self
+    }
+
+    /// Sets the API base URL, e.g. `https://github.example.com/api/v3` for
+    /// GitHub Enterprise Server (default: https://api.github.com)
+    pub fn base_url(mut self, base_url: impl Into<String>) -> Self {
+        self.base_url = base_url.into().trim_end_matches('/').to_string();
+        self
+    }
+
+    /// Sets the connection timeout (default: 5 seconds)
+    pub fn connect_timeout(mut self, timeout: Duration) -> Self {
+        self.connect_timeout = timeout;
+        self
+    }
+
+    /// Sets the response read timeout (default: 30 seconds)
+    pub fn read_timeout(mut self, timeout: Duration) -> Self {
+        self.read_timeout = timeout;
+        self
+    }
+
+    /// Sets all request policies and the identification at once, e.g. those
+    /// of the configuration
+    pub fn settings(mut self, settings: GitHubSettings) -> Self {
+        self.settings = settings;
+        self
+    }
+
+    /// Sets how transient failures are retried (default: 3 retries starting
+    /// 500 ms apart)
+    pub fn retry_policy(mut self, retry_policy: RetryPolicy) -> Self {
+        self.settings.retry_policy = retry_policy;
+        self
+    }
+
+    /// Sets how requests are retried while GitHub is still generating their
+    /// data (default: 3 retries 10 seconds apart)
+    pub fn pending_policy(mut self, pending_policy: PendingPolicy) -> Self {
+        self.settings.pending_policy = pending_policy;
+        self
+    }
+
+    /// Sets the user agent sent with every request (default: ghrust/{version})
+    pub fn user_agent(mut self, user_agent: impl Into<String>) -> Self {
+        self.settings.user_agent = user_agent.into();
+        self
+    }
+
+    /// Sets the REST API version sent as `X-GitHub-Api-Version`
+    /// (default: 2022-11-28)
+    pub fn api_version(mut self, api_version: impl Into<String>) -> Self {
+        self.settings.api_version = api_version.into();
+        self
+    }
+
+    /// Sets how strictly responses are validated against the models
+    /// (default: lenient)
+    pub fn schema_mode(mut self, schema_mode: SchemaMode) -> Self {
+        self.settings.schema_mode = schema_mode;
+        self
+    }
+
+    /// Sets which endpoint metrics are read from (default: the metrics API)
+    pub fn metrics_source(mut self, metrics_source: MetricsSource) -> Self {
+        self.settings.metrics_source = metrics_source;
+        self
+    }
+
+    /// Sets the ETag cache used for conditional metrics requests
+    /// (default: none, requests are unconditional)
+    pub fn etag_cache(mut self, etags: Arc<EtagCache>) -> Self {
+        self.etags = Some(etags);
+        self
+    }
+
+    /// Builds the client
+    ///
+    /// # Returns
+    ///
+    /// * `Result<GitHubClient>` - The client, or a `Config` error if no token
+    ///   is configured or it can't be resolved
+    pub fn build(self) -> Result<GitHubClient> {
+        let token = self
+            .token
+            .as_ref()
+            .ok_or_else(|| GitHubError::Config("a token is required".to_string()))?
+            .resolve()?;
+
+        let agent = self.agent();
+        let settings = self.settings;
+
+        Ok(GitHubClient::from_parts(
+            token,
+            self.base_url,
+            agent,
+            settings.retry_policy,
+            settings.schema_mode,
+            self.etags,
+            settings.api_version,
+        )
+        .with_metrics_source(settings.metrics_source)
+        .with_pending_policy(settings.pending_policy)
+        .with_body_logging(settings.agent.debug_log()))
+    }
+
+    /// Builds a client that only reads archived responses
+    ///
+    /// No token is required: the client locates and parses archived
+    /// responses but never sends a request.
+    ///
+    /// # Returns
+    ///
+    /// * `GitHubClient` - The client, without credentials
+    pub fn build_offline(self) -> GitHubClient {
+        let agent = self.agent();
+        let settings = self.settings;
+
+        GitHubClient::from_parts(
+            String::new(),
+            self.base_url,
+            agent,
+            settings.retry_policy,
+            settings.schema_mode,
+            None,
+            settings.api_version,
+        )
+        .with_metrics_source(settings.metrics_source)
+        .with_body_logging(settings.agent.debug_log())
+    }
+
+    /// Builds the HTTP agent with the configured timeouts and user agent
+    pub(super) fn agent(&self) -> ureq::Agent {
+        self.settings
+            .agent
+            .agent_builder()
+            .timeout_connect(self.connect_timeout)
+            .timeout_read(self.read_timeout)
+            .user_agent(&self.settings.user_agent)
+            .build()
+    }
+}
@@ -45,6 +45,35 @@ pub enum GitHubError {
     #[error("Error parsing GitHub {0} metrics: {1}")]
     ParseError(String, String),
 
+    /// Response contained fields unknown to the models while `STRICT_SCHEMA` is enabled
+    #[error("Schema drift in GitHub {0} metrics: unknown fields {1:?}")]
+    SchemaDrift(String, Vec<String>),
+
+    /// The client is missing required settings
+    #[error("Configuration error: {0}")]
+    Config(String),
+
+    /// The GraphQL API answered with errors
+    #[error("GraphQL error: {0}")]
+    GraphQl(String),
+
+    /// GitHub is still generating the requested data (`202` or an empty body)
+    #[error("No data yet: GitHub is still generating {0}")]
+    NoDataYet(String),
+
+    /// The metrics are unchanged since the ETag of the last run (`304`)
+    #[error("GitHub {0} metrics not modified since the last run")]
+    NotModified(String),
+
+    /// An archived response could not be read
+    #[error("Error reading the raw response archive {0}: {1}")]
+    Archive(String, String),
+
+    /// GitHub answered with a server error (500-504), also after the retries
+    /// of the client's retry policy
+    #[error("Server error {0} after {1} attempt(s): {2}")]
+    ServerError(u16, u32, String),
+
     /// Error from HTTP response that couldn't be further classified
     #[error("HTTP error {0}: {1}")]
     HttpError(u16, String),
@@ -55,3 +84,18 @@ pub enum GitHubError {
 /// This type alias is used throughout the GitHub client for consistent
 /// error handling and to avoid repeating the error type.
 pub type Result<T> = std::result::Result<T, GitHubError>;
+
+impl GitHubError {
+    /// Returns whether retrying the request later may succeed
+    ///
+    /// Network errors, rate limits, server errors (5xx) and data that is
+    /// still being generated are transient.
+    pub fn is_retryable(&self) -> bool {
+        match self {
+            Self::RateLimit(_) | Self::Network(_) | Self::NoDataYet(_) => true,
+            Self::ServerError(..) => true,
+            Self::HttpError(status, _) => *status >= 500,
+            _ => false,
+        }
+    }
+}
@@ -0,0 +1,120 @@
+//! # Conditional Requests
+//!
+//! GitHub answers a request carrying the `ETag` of the previous response in
+//! `If-None-Match` with `304 Not Modified` when nothing changed, and such
+//! responses don't count against the rate limit. This module keeps the ETags
+//! of the metrics endpoints in a JSON file between runs; the client sends them
+//! and reports a 304 as `GitHubError::NotModified`, so unchanged windows are
+//! not sent to Datadog again.
+//!
+//! ETags received during a run are only staged. They are written to the file
+//! once the whole run succeeded (`commit`), so a window whose submission
+//! failed is fetched and sent again by the next run instead of being skipped.
+//!
+//! ## Usage
+//!
+//! The pipeline opens the cache of `GITHUB_ETAG_CACHE` for every run and
+//! passes it to its client with `GitHubClientBuilder::etag_cache`; without a
+//! cache requests are unconditional.
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+use std::sync::Mutex;
+
+use tracing::{debug, info, warn};
+
+/// ETags of previously fetched responses, keyed by request URL
+#[derive(Debug)]
+pub struct EtagCache {
+    /// Path of the JSON file the ETags are kept in
+    path: PathBuf,
+    /// ETags of the last successful run
+    stored: Mutex<BTreeMap<String, String>>,
+    /// ETags received during this run, not yet written
+    pending: Mutex<BTreeMap<String, String>>,
+}
+
+impl EtagCache {
+    /// Opens the cache file, starting empty if it doesn't exist or can't be
+    /// parsed
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - Path of the JSON file the ETags are kept in
+    pub fn open(path: impl Into<PathBuf>) -> Self {
+        let path = path.into();
+        let stored = match fs::read(&path) {
+            Ok(contents) => serde_json::from_slice(&contents).unwrap_or_else(|e| {
+                warn!("Ignoring invalid ETag cache {}: {}", path.display(), e);
+                BTreeMap::new()
+            }),
+            Err(e) => {
+                if e.kind() != io::ErrorKind::NotFound {
+                    warn!("Could not read ETag cache {}: {}", path.display(), e);
+                }
+                BTreeMap::new()
+            }
+        };
+        debug!("Loaded {} ETags from {}", stored.len(), path.display());
+
+        Self {
+            path,
+            stored: Mutex::new(stored),
+            pending: Mutex::new(BTreeMap::new()),
+        }
+    }
+
+    /// Returns the ETag of the last successful run for a request
+    ///
+    /// # Arguments
+    ///
+    /// * `key` - The request URL including its query
+    pub fn get(&self, key: &str) -> Option<String> {
+        lock(&self.stored).get(key).cloned()
+    }
+
+    /// Stages the ETag of a response until the run is committed
+    ///
+    /// # Arguments
+    ///
+    /// * `key` - The request URL including its query
+    /// * `etag` - The response's `ETag` header
+    pub fn record(&self, key: &str, etag: &str) {
+        lock(&self.pending).insert(key.to_string(), etag.to_string());
+    }
+
+    /// Writes the staged ETags to the cache file
+    ///
+    /// # Returns
+    ///
+    /// * `io::Result<usize>` - Number of ETags written, or an error if the
+    ///   file can't be written
+    pub fn commit(&self) -> io::Result<usize> {
+        let pending = std::mem::take(&mut *lock(&self.pending));
+        if pending.is_empty() {
+            return Ok(0);
+        }
+
+        let mut stored = lock(&self.stored);
+        stored.extend(pending);
+        let contents = serde_json::to_vec_pretty(&*stored)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+        fs::write(&self.path, contents)?;
+        info!("Saved {} ETags to {}", stored.len(), self.path.display());
+        Ok(stored.len())
+    }
+
+    /// Drops the staged ETags, so the next run fetches the same data again
+    pub fn discard(&self) {
+        lock(&self.pending).clear();
+    }
+}
+
+/// Locks a map, recovering it if another thread panicked while holding it
+fn lock(
+    map: &Mutex<BTreeMap<String, String>>,
+) -> std::sync::MutexGuard<'_, BTreeMap<String, String>> {
+    map.lock().unwrap_or_else(|e| e.into_inner())
+}
@@ -0,0 +1,360 @@
+//! # GitHub GraphQL API
+//!
+//! Some data isn't exposed by the REST metrics endpoints: the organizations
+//! of an enterprise, the number of members of a team or an organization. This
+//! module adds a lightweight GraphQL client to `GitHubClient` for these
+//! queries. Queries are plain documents with variables; responses are parsed
+//! into small structs local to each query.
+//!
+//! Member counts are used to compute adoption percentages (engaged users
+//! relative to the size of a team or organization).
+
+use serde::Deserialize;
+use serde_json::{json, Value};
+use tracing::{debug, info};
+
+use super::api::GitHubClient;
+use super::error::{GitHubError, Result};
+use crate::services::http_log;
+
+/// What a slug or login names on GitHub
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AccountKind {
+    /// The slug of an enterprise account
+    Enterprise,
+    /// The login of an organization
+    Organization,
+    /// Neither an enterprise nor an organization the token can see
+    Unknown,
+}
+
+/// A connection's `totalCount`
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct TotalCount {
+    total_count: i64,
+}
+
+impl GitHubClient {
+    /// GraphQL endpoint matching the client's REST base URL
+    ///
+    /// GitHub Enterprise Server serves REST under `/api/v3` and GraphQL under
+    /// `/api/graphql`; github.com serves GraphQL under `/graphql`.
+    pub(super) fn graphql_url(&self) -> String {
+        match self.base_url.strip_suffix("/api/v3") {
+            Some(host) => format!("{}/api/graphql", host),
+            None => format!("{}/graphql", self.base_url),
+        }
+    }
+
+    /// Lists the logins of all organizations in an enterprise
+    ///
+    /// The REST API has no endpoint listing an enterprise's organizations, so
+    /// they are queried through the GraphQL API, following the cursor until
+    /// all pages are read.
+    ///
+    /// # Arguments
+    ///
+    /// * `enterprise_id` - Slug of the GitHub Enterprise (e.g., "acme")
+    ///
+    /// # Returns
+    ///
+    /// * `Result<Vec<String>>` - The organization logins on success, or an error
+    ///   if the query fails
+    ///
+    /// # API Endpoint
+    ///
+    /// `POST /graphql` (`enterprise.organizations`)
+    pub fn list_enterprise_organizations(&self, enterprise_id: &str) -> Result<Vec<String>> {
+        const QUERY: &str = "query($slug: String!, $cursor: String) { \
+            enterprise(slug: $slug) { \
+                organizations(first: 100, after: $cursor) { \
+                    nodes { login } \
+                    pageInfo { hasNextPage endCursor } \
+                } \
+            } \
+        }";
+
+        #[derive(Deserialize)]
+        struct Data {
+            enterprise: Option<Enterprise>,
+        }
+        #[derive(Deserialize)]
+        struct Enterprise {
+            organizations: Connection,
+        }
+        #[derive(Deserialize)]
+        #[serde(rename_all = "camelCase")]
+        struct Connection {
+            nodes: Vec<Node>,
+            page_info: PageInfo,
+        }
+        #[derive(Deserialize)]
+        struct Node {
+            login: String,
+        }
+        #[derive(Deserialize)]
+        #[serde(rename_all = "camelCase")]
+        struct PageInfo {
+            has_next_page: bool,
+            end_cursor: Option<String>,
+        }
+
+        info!("Listing organizations of enterprise {}", enterprise_id);
+        let mut logins = Vec::new();
+        let mut cursor: Option<String> = None;
+        loop {
+            let data: Data = self.graphql(
+                QUERY,
+                json!({ "slug": enterprise_id, "cursor": cursor }),
+                "enterprise organizations",
+            )?;
+            let organizations = data
+                .enterprise
+                .ok_or_else(|| GitHubError::NotFound(format!("enterprise {}", enterprise_id)))?
+                .organizations;
+
+            logins.extend(organizations.nodes.into_iter().map(|node| node.login));
+            match organizations.page_info {
+                PageInfo {
+                    has_next_page: true,
+                    end_cursor: Some(next),
+                } => cursor = Some(next),
+                _ => break,
+            }
+        }
+
+        info!(
+            "Found {} organizations in enterprise {}",
+            logins.len(),
+            enterprise_id
+        );
+        Ok(logins)
+    }
+
+    /// Resolves a configured enterprise ID to the account it names
+    ///
+    /// # Arguments
+    ///
+    /// * `slug` - The configured ID, e.g. "acme"
+    ///
+    /// # Returns
+    ///
+    /// * `Result<AccountKind>` - Whether the ID is an enterprise slug, an
+    ///   organization login or neither, or an error if the query fails
+    ///
+    /// # API Endpoint
+    ///
+    /// `POST /graphql` (`enterprise`, `organization`)
+    pub fn resolve_account(&self, slug: &str) -> Result<AccountKind> {
+        const QUERY: &str = concat!(
+            "query($slug: String!) { ",
+            "enterprise(slug: $slug) { slug } ",
+            "organization(login: $slug) { login } ",
+            "}"
+        );
+
+        #[derive(Deserialize)]
+        struct Data {
+            enterprise: Option<Value>,
+            organization: Option<Value>,
+        }
+
+        let data: Data = self.graphql(QUERY, json!({ "slug": slug }), "account")?;
+        let kind = match (data.enterprise, data.organization) {
+            (Some(_), _) => AccountKind::Enterprise,
+            (None, Some(_)) => AccountKind::Organization,
+            (None, None) => AccountKind::Unknown,
+        };
+        debug!("{} resolves to {:?}", slug, kind);
+        Ok(kind)
+    }
+
+    /// Resolves a configured enterprise ID, reusing an earlier resolution
+    ///
+    /// IDs that resolved to an enterprise or organization are remembered by
+    /// the client and its clones, so the steps of a run look each ID up once.
+    /// A client is built per run and token, so another run or tenant resolves
+    /// its IDs again.
+    ///
+    /// # Arguments
+    ///
+    /// * `slug` - The configured ID, e.g. "acme"
+    ///
+    /// # Returns
+    ///
+    /// * `Result<AccountKind>` - As for `resolve_account`
+    pub fn account_kind(&self, slug: &str) -> Result<AccountKind> {
+        let accounts = || self.accounts.lock().unwrap_or_else(|e| e.into_inner());
+        if let Some(kind) = accounts().get(slug) {
+            return Ok(*kind);
+        }
+
+        let kind = self.resolve_account(slug)?;
+        if kind != AccountKind::Unknown {
+            accounts().insert(slug.to_string(), kind);
+        }
+        Ok(kind)
+    }
+
+    /// Counts the members of a team, including members of child teams
+    ///
+    /// # Arguments
+    ///
+    /// * `org` - Login of the organization owning the team
+    /// * `team_slug` - Slug of the team
+    ///
+    /// # Returns
+    ///
+    /// * `Result<i64>` - The number of members, or an error if the query fails
+    ///   or the team doesn't exist
+    ///
+    /// # API Endpoint
+    ///
+    /// `POST /graphql` (`organization.team.members.totalCount`)
+    pub fn fetch_team_member_count(&self, org: &str, team_slug: &str) -> Result<i64> {
+        const QUERY: &str = "query($org: String!, $slug: String!) { \
+            organization(login: $org) { \
+                team(slug: $slug) { members { totalCount } } \
+            } \
+        }";
+
+        #[derive(Deserialize)]
+        struct Data {
+            organization: Option<Organization>,
+        }
+        #[derive(Deserialize)]
+        struct Organization {
+            team: Option<Team>,
+        }
+        #[derive(Deserialize)]
+        struct Team {
+            members: TotalCount,
+        }
+
+        let data: Data = self.graphql(
+            QUERY,
+            json!({ "org": org, "slug": team_slug }),
+            "team members",
+        )?;
+        let count = data
+            .organization
+            .and_then(|o| o.team)
+            .ok_or_else(|| GitHubError::NotFound(format!("team {}/{}", org, team_slug)))?
+            .members
+            .total_count;
+
+        debug!("Team {}/{} has {} members", org, team_slug, count);
+        Ok(count)
+    }
+
+    /// Counts the members of an organization
+    ///
+    /// # Arguments
+    ///
+    /// * `org` - Login of the organization
+    ///
+    /// # Returns
+    ///
+    /// * `Result<i64>` - The number of members, or an error if the query fails
+    ///   or the organization doesn't exist
+    ///
+    /// # API Endpoint
+    ///
+    /// `POST /graphql` (`organization.membersWithRole.totalCount`)
+    pub fn fetch_org_member_count(&self, org: &str) -> Result<i64> {
+        const QUERY: &str = "query($org: String!) { \
+            organization(login: $org) { membersWithRole { totalCount } } \
+        }";
+
+        #[derive(Deserialize)]
+        #[serde(rename_all = "camelCase")]
+        struct Data {
+            organization: Option<Organization>,
+        }
+        #[derive(Deserialize)]
+        #[serde(rename_all = "camelCase")]
+        struct Organization {
+            members_with_role: TotalCount,
+        }
+
+        let data: Data = self.graphql(QUERY, json!({ "org": org }), "organization members")?;
+        let count = data
+            .organization
+            .ok_or_else(|| GitHubError::NotFound(format!("organization {}", org)))?
+            .members_with_role
+            .total_count;
+
+        debug!("Organization {} has {} members", org, count);
+        Ok(count)
+    }
+
+    /// Runs a GraphQL query against the GitHub API
+    ///
+    /// # Arguments
+    ///
+    /// * `query` - The GraphQL query document
+    /// * `variables` - Values of the query's variables
+    /// * `context` - String describing the queried data, for errors
+    ///
+    /// # Returns
+    ///
+    /// * `Result<T>` - The response's `data` parsed into `T`, or an error if
+    ///   the request fails or the response contains GraphQL errors. Fields
+    ///   that can't be resolved (`NOT_FOUND` errors) are `null` in `data`, so
+    ///   they are left to the caller.
+    pub(super) fn graphql<T: serde::de::DeserializeOwned>(
+        &self,
+        query: &str,
+        variables: Value,
+        context: &str,
+    ) -> Result<T> {
+        #[derive(Deserialize)]
+        struct Response<T> {
+            data: Option<T>,
+            #[serde(default)]
+            errors: Vec<GraphQlError>,
+        }
+        #[derive(Deserialize)]
+        struct GraphQlError {
+            message: String,
+            #[serde(default, rename = "type")]
+            kind: Option<String>,
+        }
+
+        let request_body = json!({ "query": query, "variables": variables });
+        if self.log_bodies {
+            http_log::log_body("request", &self.graphql_url(), &request_body.to_string());
+        }
+        let response = self
+            .agent
+            .post(&self.graphql_url())
+            .set("Authorization", &format!("Bearer {}", self.token))
+            .send_json(request_body);
+        let body = match response {
+            Ok(resp) => resp
+                .into_string()
+                .map_err(|e| GitHubError::Network(format!("Failed to read response: {}", e)))?,
+            Err(e) => return self.handle_api_error(e),
+        };
+        if self.log_bodies {
+            http_log::log_body("response", &self.graphql_url(), &body);
+        }
+
+        let response = serde_json::from_str::<Response<T>>(&body)
+            .map_err(|e| GitHubError::ParseError(context.to_string(), e.to_string()))?;
+        let not_found = |e: &GraphQlError| e.kind.as_deref() == Some("NOT_FOUND");
+        if response.data.is_some() && response.errors.iter().all(not_found) {
+            for error in &response.errors {
+                debug!("GraphQL {}: {}", context, error.message);
+            }
+        } else if !response.errors.is_empty() {
+            let messages: Vec<String> = response.errors.into_iter().map(|e| e.message).collect();
+            return Err(GitHubError::GraphQl(messages.join("; ")));
+        }
+
+        response.data.ok_or_else(|| {
+            GitHubError::ParseError(context.to_string(), "response has no data".to_string())
+        })
+    }
+}
@@ -7,19 +7,26 @@
 //! The module offers:
 //! - Enterprise-wide metrics collection
 //! - Team-specific metrics collection
+//! - Organization metrics collection for every organization of an enterprise
+//! - Premium request usage for the current billing month
+//! - Copilot seat assignments
 //! - Default date range calculation (last 30 days)
 //!
 //! This module abstracts away some of the complexity of the raw API client,
 //! making it easier to fetch metrics in common scenarios.
 
 // GitHub metrics processing functions
-use anyhow::Result;
-use chrono::{Duration, Utc};
+use chrono::{Datelike, Duration, Utc};
 use tracing::info;
 
 use super::api::GitHubClient;
+use super::error::Result;
+use crate::models::billing::{CopilotSeat, PremiumRequestUsage};
 use crate::models::github::CopilotMetrics;
 
+/// Number of days the metrics window reaches back
+pub const REPORTING_WINDOW_DAYS: i64 = 30;
+
 /// Fetches enterprise-wide Copilot metrics from GitHub
 ///
 /// This function retrieves Copilot usage metrics for an entire GitHub Enterprise organization.
@@ -42,7 +49,7 @@ use crate::models::github::CopilotMetrics;
 /// use ghrust::services::github::{GitHubClient, get_enterprise_metrics};
 /// # use anyhow::Result;
 /// # fn example() -> Result<()> {
-/// let client = GitHubClient::new("your-github-token");
+/// let client = GitHubClient::builder().token("your-github-token").build()?;
 /// let metrics = get_enterprise_metrics(&client, "12345")?;
 /// # Ok(())
 /// # }
@@ -106,6 +113,141 @@ pub fn get_team_metrics(
     Ok(metrics)
 }
 
+/// Fetches organization-specific Copilot metrics from GitHub
+///
+/// # Arguments
+///
+/// * `client` - A reference to an authenticated GitHubClient instance
+/// * `org` - Login of the organization to fetch metrics for
+///
+/// # Returns
+///
+/// * `Result<Vec<CopilotMetrics>>` - A collection of organization metrics on
+///   success, or an error if the API request fails
+pub fn get_org_metrics(client: &GitHubClient, org: &str) -> Result<Vec<CopilotMetrics>> {
+    let since_date = calculate_default_since_date();
+
+    let metrics = client.fetch_org_metrics(org, &since_date)?;
+
+    info!(
+        "Retrieved {} organization metric entries for {}",
+        metrics.len(),
+        org
+    );
+
+    Ok(metrics)
+}
+
+/// Lists the organizations of an enterprise
+///
+/// # Arguments
+///
+/// * `client` - A reference to an authenticated GitHubClient instance
+/// * `enterprise_id` - Slug of the GitHub Enterprise
+///
+/// # Returns
+///
+/// * `Result<Vec<String>>` - The organization logins, or an error if the
+///   query fails
+pub fn get_enterprise_organizations(
+    client: &GitHubClient,
+    enterprise_id: &str,
+) -> Result<Vec<String>> {
+    client.list_enterprise_organizations(enterprise_id)
+}
+
+/// Fetches all Copilot seat assignments of an enterprise
+///
+/// # Arguments
+///
+/// * `client` - A reference to an authenticated GitHubClient instance
+/// * `enterprise_id` - ID of the GitHub Enterprise organization
+///
+/// # Returns
+///
+/// * `Result<Vec<CopilotSeat>>` - The seat assignments, or an error if the
+///   API request fails
+pub fn get_enterprise_seats(
+    client: &GitHubClient,
+    enterprise_id: &str,
+) -> Result<Vec<CopilotSeat>> {
+    client.fetch_enterprise_seats(enterprise_id)
+}
+
+/// Fetches all Copilot seat assignments of an organization
+///
+/// # Arguments
+///
+/// * `client` - A reference to an authenticated GitHubClient instance
+/// * `org` - Login of the organization
+///
+/// # Returns
+///
+/// * `Result<Vec<CopilotSeat>>` - The seat assignments, or an error if the
+///   API request fails
+pub fn get_org_seats(client: &GitHubClient, org: &str) -> Result<Vec<CopilotSeat>> {
+    client.fetch_org_seats(org)
+}
+
+/// Fetches the enterprise's premium request usage for the current billing month
+///
+/// Premium request usage is reported per billing period rather than per day,
+/// so this function always requests the month containing today's date (UTC).
+///
+/// # Arguments
+///
+/// * `client` - A reference to an authenticated GitHubClient instance
+/// * `enterprise_id` - ID of the GitHub Enterprise organization
+///
+/// # Returns
+///
+/// * `Result<PremiumRequestUsage>` - The usage report on success, or an error
+///   if the API request fails
+pub fn get_premium_request_usage(
+    client: &GitHubClient,
+    enterprise_id: &str,
+) -> Result<PremiumRequestUsage> {
+    let today = Utc::now();
+
+    let usage = client.fetch_premium_request_usage(enterprise_id, today.year(), today.month())?;
+
+    info!(
+        "Retrieved {} premium request usage items for {}",
+        usage.usage_items.len(),
+        usage.time_period.label()
+    );
+
+    Ok(usage)
+}
+
+/// Fetches an organization's premium request usage for the current billing month
+///
+/// # Arguments
+///
+/// * `client` - A reference to an authenticated GitHubClient instance
+/// * `org` - Login of the organization
+///
+/// # Returns
+///
+/// * `Result<PremiumRequestUsage>` - The usage report on success, or an error
+///   if the API request fails
+pub fn get_org_premium_request_usage(
+    client: &GitHubClient,
+    org: &str,
+) -> Result<PremiumRequestUsage> {
+    let today = Utc::now();
+
+    let usage = client.fetch_org_premium_request_usage(org, today.year(), today.month())?;
+
+    info!(
+        "Retrieved {} premium request usage items for {}",
+        usage.usage_items.len(),
+        usage.time_period.label()
+    );
+
+    Ok(usage)
+}
+
 /// Calculate a default "since" date (30 days back from today)
 ///
 /// This helper function generates an ISO 8601 date string (YYYY-MM-DD format)
@@ -120,7 +262,7 @@ pub fn get_team_metrics(
 ///
 /// The 30-day window is a balance between getting enough historical data
 /// and keeping API response sizes manageable.
-fn calculate_default_since_date() -> String {
-    let thirty_days_ago = Utc::now() - Duration::days(30);
+pub fn calculate_default_since_date() -> String {
+    let thirty_days_ago = Utc::now() - Duration::days(REPORTING_WINDOW_DAYS);
     thirty_days_ago.format("%Y-%m-%d").to_string()
 }
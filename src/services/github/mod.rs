@@ -6,7 +6,16 @@
 //! ## Core Components
 //!
 //! * `api` - The main GitHub API client for fetching metrics
+//! * `archive` - Raw metrics responses kept for `ghrust replay`
+//! * `budget` - Requests made and the rate limit left, for run reports
+//! * `builder` - Explicit configuration of the client (token source, base URL, retries)
 //! * `error` - Structured error types for GitHub API operations
+//! * `etag` - ETags kept between runs for conditional metrics requests
+//! * `graphql` - GraphQL queries for data the REST endpoints don't expose
+//! * `schema` - Opt-in validation of API responses against the models
+//! * `settings` - Request policies and identification read once from the environment
+//! * `source` - Selection of the metrics or usage summary endpoint
+//! * `teams` - Discovery of an enterprise's teams, cached between runs
 //!
 //! ## Usage
 //!
@@ -14,16 +23,43 @@
 //! request formation when interacting with GitHub's API.
 
 pub mod api;
+pub mod archive;
+mod budget;
+mod builder;
 mod error;
+pub mod etag;
+mod graphql;
 mod metrics;
+mod schema;
+mod settings;
+mod source;
+pub mod teams;
 
 #[cfg(test)]
-mod test_helpers;
+pub(crate) mod test_helpers;
 #[cfg(test)]
 mod tests;
 
 // Re-export public items
 pub use api::GitHubClient;
-pub use metrics::{get_enterprise_metrics, get_team_metrics};
+pub use archive::RawArchive;
+pub use budget::RateLimitBudget;
+pub use builder::{
+    parse_count, GitHubClientBuilder, PendingPolicy, RetryPolicy, TokenSource, DEFAULT_API_VERSION,
+    DEFAULT_BASE_URL, DEFAULT_MAX_RETRIES, DEFAULT_PENDING_DELAY, DEFAULT_PENDING_RETRIES,
+    DEFAULT_RETRY_BACKOFF, DEFAULT_USER_AGENT,
+};
+pub use error::GitHubError;
+pub use etag::EtagCache;
+pub use graphql::AccountKind;
+pub use metrics::{
+    calculate_default_since_date, get_enterprise_metrics, get_enterprise_organizations,
+    get_enterprise_seats, get_org_metrics, get_org_premium_request_usage, get_org_seats,
+    get_premium_request_usage, get_team_metrics, REPORTING_WINDOW_DAYS,
+};
+pub use schema::SchemaMode;
+pub use settings::GitHubSettings;
+pub use source::MetricsSource;
+pub use teams::TeamCache;
 #[cfg(test)]
 pub use test_helpers::create_test_metrics_with_params as create_mock_metrics;
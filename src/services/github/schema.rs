@@ -0,0 +1,62 @@
+//! # Schema Validation Mode
+//!
+//! GitHub's Copilot metrics API evolves over time. By default unknown fields are
+//! preserved and only reported in the logs, but a canary deployment may want to
+//! find out about response shape drift as early as possible. This module defines
+//! the opt-in validation modes selected through the `STRICT_SCHEMA` environment
+//! variable.
+
+/// How strictly GitHub API responses are validated against the models
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SchemaMode {
+    /// Unknown fields are preserved and logged (default)
+    #[default]
+    Lenient,
+
+    /// Unknown fields are logged at warn level and reported as a
+    /// `{namespace}.schema.unknown_fields` metric, but data is still processed
+    Warn,
+
+    /// Any unknown field fails the request with a `SchemaDrift` error
+    Strict,
+}
+
+impl SchemaMode {
+    /// Parses a schema mode from its configuration value
+    ///
+    /// `warn` selects [`SchemaMode::Warn`]; `0`, `false`, `off` and the empty
+    /// string select [`SchemaMode::Lenient`]; `1`, `true`, `on` and `strict`
+    /// enable [`SchemaMode::Strict`].
+    ///
+    /// # Arguments
+    ///
+    /// * `value` - The raw configuration value
+    ///
+    /// # Returns
+    ///
+    /// The selected mode, or `None` if the value is none of the above
+    pub fn parse(value: &str) -> Option<Self> {
+        match value.trim().to_ascii_lowercase().as_str() {
+            "" | "0" | "false" | "off" => Some(SchemaMode::Lenient),
+            "warn" => Some(SchemaMode::Warn),
+            "1" | "true" | "on" | "strict" => Some(SchemaMode::Strict),
+            _ => None,
+        }
+    }
+
+    /// Reads the schema mode from the `STRICT_SCHEMA` environment variable
+    ///
+    /// # Returns
+    ///
+    /// The configured mode, or [`SchemaMode::Lenient`] when the variable is
+    /// unset or invalid; `Config::from_env` rejects an invalid value instead
+    pub fn from_env() -> Self {
+        let Ok(value) = std::env::var("STRICT_SCHEMA") else {
+            return Self::default();
+        };
+        Self::parse(&value).unwrap_or_else(|| {
+            tracing::warn!("Ignoring invalid STRICT_SCHEMA: {}", value);
+            Self::default()
+        })
+    }
+}
@@ -0,0 +1,80 @@
+//! # GitHub Client Settings
+//!
+//! The request policies and identification a `GitHubClient` uses, grouped so
+//! the configuration can read them once and hand the same values to every
+//! client of a run. `GitHubSettings::default()` holds the neutral settings of
+//! `GitHubClientBuilder`. `Config::from_env` parses the environment variables
+//! documented on each setting and rejects invalid values;
+//! `GitHubSettings::from_env()` reads them leniently for the deprecated
+//! `GitHubClient::new` and the test binaries.
+
+use std::env;
+
+use super::builder::{
+    PendingPolicy, RetryPolicy, DEFAULT_API_VERSION, DEFAULT_MAX_RETRIES, DEFAULT_RETRY_BACKOFF,
+    DEFAULT_USER_AGENT,
+};
+use super::schema::SchemaMode;
+use super::source::MetricsSource;
+use crate::services::tls::AgentConfig;
+
+/// Request policies and identification used by a GitHub client
+#[derive(Debug, Clone, PartialEq)]
+pub struct GitHubSettings {
+    /// Retries of transient failures
+    /// (`GITHUB_MAX_RETRIES` / `GITHUB_RETRY_BACKOFF_MS`)
+    pub retry_policy: RetryPolicy,
+
+    /// Retries while GitHub is still generating the data
+    /// (`GITHUB_PENDING_RETRIES` / `GITHUB_PENDING_DELAY_SECS` /
+    /// `GITHUB_PENDING_RETRY_BUDGET`)
+    pub pending_policy: PendingPolicy,
+
+    /// Validation of responses against the models (`STRICT_SCHEMA`)
+    pub schema_mode: SchemaMode,
+
+    /// Endpoint metrics are read from (`GITHUB_METRICS_SOURCE`)
+    pub metrics_source: MetricsSource,
+
+    /// User agent sent with every request (`GITHUB_USER_AGENT`)
+    pub user_agent: String,
+
+    /// REST API version sent as `X-GitHub-Api-Version` (`GITHUB_API_VERSION`)
+    pub api_version: String,
+
+    /// Settings of the HTTP agent (`GHRUST_CA_BUNDLE`)
+    pub agent: AgentConfig,
+}
+
+impl Default for GitHubSettings {
+    fn default() -> Self {
+        Self {
+            retry_policy: RetryPolicy::new(DEFAULT_MAX_RETRIES, DEFAULT_RETRY_BACKOFF),
+            pending_policy: PendingPolicy::default(),
+            schema_mode: SchemaMode::default(),
+            metrics_source: MetricsSource::default(),
+            user_agent: DEFAULT_USER_AGENT.to_string(),
+            api_version: DEFAULT_API_VERSION.to_string(),
+            agent: AgentConfig::default(),
+        }
+    }
+}
+
+impl GitHubSettings {
+    /// Reads the settings from their environment variables
+    ///
+    /// Unset or invalid values keep their defaults. The agent keeps the
+    /// default roots; `Config::from_env` loads the CA bundle.
+    pub fn from_env() -> Self {
+        let defaults = Self::default();
+        Self {
+            retry_policy: RetryPolicy::from_env(),
+            pending_policy: PendingPolicy::from_env(),
+            schema_mode: SchemaMode::from_env(),
+            metrics_source: MetricsSource::from_env(),
+            user_agent: env::var("GITHUB_USER_AGENT").unwrap_or(defaults.user_agent),
+            api_version: env::var("GITHUB_API_VERSION").unwrap_or(defaults.api_version),
+            agent: defaults.agent,
+        }
+    }
+}
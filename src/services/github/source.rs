@@ -0,0 +1,61 @@
+//! # Metrics Source
+//!
+//! Some tenants can't access GitHub's Copilot metrics API but still have the
+//! older usage summary API (`/copilot/usage`). This module defines which of
+//! the two endpoints the client reads, selected through the
+//! `GITHUB_METRICS_SOURCE` environment variable. Usage days are mapped onto
+//! the metrics models, so the rest of the pipeline is the same for both.
+
+/// Which GitHub endpoint Copilot metrics are read from
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum MetricsSource {
+    /// The Copilot metrics API, `/copilot/metrics` (default)
+    #[default]
+    Metrics,
+
+    /// The Copilot usage summary API, `/copilot/usage`
+    Usage,
+}
+
+impl MetricsSource {
+    /// Parses a metrics source from its configuration value
+    ///
+    /// `usage` selects [`MetricsSource::Usage`]; any other value selects
+    /// [`MetricsSource::Metrics`].
+    ///
+    /// # Arguments
+    ///
+    /// * `value` - The raw configuration value
+    pub fn parse(value: &str) -> Self {
+        match value.trim().to_ascii_lowercase().as_str() {
+            "usage" => MetricsSource::Usage,
+            _ => MetricsSource::Metrics,
+        }
+    }
+
+    /// Reads the metrics source from the `GITHUB_METRICS_SOURCE` environment variable
+    ///
+    /// # Returns
+    ///
+    /// The configured source, or [`MetricsSource::Metrics`] when the variable is unset
+    pub fn from_env() -> Self {
+        std::env::var("GITHUB_METRICS_SOURCE")
+            .map(|v| Self::parse(&v))
+            .unwrap_or_default()
+    }
+
+    /// Returns the URL of this source's endpoint for a metrics API URL
+    ///
+    /// # Arguments
+    ///
+    /// * `metrics_url` - URL ending in `/copilot/metrics`
+    pub fn endpoint(&self, metrics_url: &str) -> String {
+        match self {
+            MetricsSource::Metrics => metrics_url.to_string(),
+            MetricsSource::Usage => match metrics_url.strip_suffix("/copilot/metrics") {
+                Some(base) => format!("{}/copilot/usage", base),
+                None => metrics_url.to_string(),
+            },
+        }
+    }
+}
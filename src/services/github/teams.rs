@@ -0,0 +1,180 @@
+//! # Team Discovery
+//!
+//! With team discovery enabled, the teams of every enterprise are listed
+//! through the API in addition to the configured slugs. Listing the teams of
+//! a large enterprise takes many paginated requests, and the list rarely
+//! changes between runs, so `TeamCache` keeps each enterprise's list in a JSON
+//! file with the time it was listed and reuses it until it is older than the
+//! TTL. A refresh (`--refresh-teams`) lists the teams again regardless, e.g.
+//! right after a reorganization.
+//!
+//! If listing fails, an expired cached list is used rather than none.
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+use std::sync::Mutex;
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+use tracing::{debug, info, warn};
+
+use super::api::GitHubClient;
+use super::error::Result;
+
+/// Team list of an enterprise with the time it was listed
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+struct CachedTeams {
+    /// Unix time the teams were listed at
+    listed_at: i64,
+    /// Slugs of the teams
+    teams: Vec<String>,
+}
+
+/// Team lists of previous runs, keyed by enterprise
+#[derive(Debug)]
+pub struct TeamCache {
+    /// Path of the JSON file the lists are kept in
+    path: PathBuf,
+    /// How long a list is used before the teams are listed again
+    ttl: Duration,
+    /// The cached lists
+    entries: Mutex<BTreeMap<String, CachedTeams>>,
+}
+
+impl TeamCache {
+    /// Opens the cache file, starting empty if it doesn't exist or can't be
+    /// parsed
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - Path of the JSON file the lists are kept in
+    /// * `ttl` - How long a list is used before the teams are listed again
+    pub fn open(path: impl Into<PathBuf>, ttl: Duration) -> Self {
+        let path = path.into();
+        let entries = match fs::read(&path) {
+            Ok(contents) => serde_json::from_slice(&contents).unwrap_or_else(|e| {
+                warn!("Ignoring invalid team cache {}: {}", path.display(), e);
+                BTreeMap::new()
+            }),
+            Err(e) => {
+                if e.kind() != io::ErrorKind::NotFound {
+                    warn!("Could not read team cache {}: {}", path.display(), e);
+                }
+                BTreeMap::new()
+            }
+        };
+
+        Self {
+            path,
+            ttl,
+            entries: Mutex::new(entries),
+        }
+    }
+
+    /// Returns the cached teams of an enterprise if they haven't expired
+    ///
+    /// # Arguments
+    ///
+    /// * `enterprise_id` - Slug of the enterprise
+    /// * `now` - Current Unix time
+    pub fn get(&self, enterprise_id: &str, now: i64) -> Option<Vec<String>> {
+        let entries = self.entries.lock().unwrap_or_else(|e| e.into_inner());
+        let cached = entries.get(enterprise_id)?;
+        let age = now.saturating_sub(cached.listed_at);
+        (age >= 0 && (age as u64) < self.ttl.as_secs()).then(|| cached.teams.clone())
+    }
+
+    /// Returns the cached teams of an enterprise, expired or not
+    ///
+    /// # Arguments
+    ///
+    /// * `enterprise_id` - Slug of the enterprise
+    pub fn get_expired(&self, enterprise_id: &str) -> Option<Vec<String>> {
+        let entries = self.entries.lock().unwrap_or_else(|e| e.into_inner());
+        entries
+            .get(enterprise_id)
+            .map(|cached| cached.teams.clone())
+    }
+
+    /// Stores the teams of an enterprise and writes the cache file
+    ///
+    /// # Arguments
+    ///
+    /// * `enterprise_id` - Slug of the enterprise
+    /// * `teams` - Slugs of the teams
+    /// * `now` - Unix time the teams were listed at
+    pub fn store(&self, enterprise_id: &str, teams: &[String], now: i64) -> io::Result<()> {
+        let mut entries = self.entries.lock().unwrap_or_else(|e| e.into_inner());
+        entries.insert(
+            enterprise_id.to_string(),
+            CachedTeams {
+                listed_at: now,
+                teams: teams.to_vec(),
+            },
+        );
+        let contents = serde_json::to_vec_pretty(&*entries)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+        fs::write(&self.path, contents)?;
+        debug!(
+            "Saved the teams of {} to {}",
+            enterprise_id,
+            self.path.display()
+        );
+        Ok(())
+    }
+}
+
+/// Returns the teams of an enterprise, from the cache if possible
+///
+/// # Arguments
+///
+/// * `client` - Client the teams are listed with
+/// * `enterprise_id` - Slug of the enterprise
+/// * `cache` - Cache of earlier lists, if any
+/// * `refresh` - Whether to list the teams even if the cached list is fresh
+///
+/// # Returns
+///
+/// * `Result<Vec<String>>` - The team slugs, or an error if listing fails
+///   and no cached list exists
+pub fn discover_teams(
+    client: &GitHubClient,
+    enterprise_id: &str,
+    cache: Option<&TeamCache>,
+    refresh: bool,
+) -> Result<Vec<String>> {
+    let now = chrono::Utc::now().timestamp();
+    if let (Some(cache), false) = (cache, refresh) {
+        if let Some(teams) = cache.get(enterprise_id, now) {
+            info!(
+                "Using {} cached teams of enterprise {}",
+                teams.len(),
+                enterprise_id
+            );
+            return Ok(teams);
+        }
+    }
+
+    match client.list_enterprise_teams(enterprise_id) {
+        Ok(teams) => {
+            if let Some(cache) = cache {
+                if let Err(e) = cache.store(enterprise_id, &teams, now) {
+                    warn!("Error saving the team cache: {}", e);
+                }
+            }
+            Ok(teams)
+        }
+        Err(e) => match cache.and_then(|cache| cache.get_expired(enterprise_id)) {
+            Some(teams) => {
+                warn!(
+                    "Listing the teams of {} failed ({}), using the expired cached list",
+                    enterprise_id, e
+                );
+                Ok(teams)
+            }
+            None => Err(e),
+        },
+    }
+}
@@ -9,6 +9,7 @@
 //! - Functions for creating team-level test metrics
 //! - Specialized metrics for testing chat-related functionality
 //! - Functions for simulating GitHub API responses
+//! - Proptest strategies generating arbitrary metrics for property tests
 //!
 //! These test helpers are particularly useful for:
 //! - Unit testing processors and services without calling the real GitHub API
@@ -16,12 +17,16 @@
 //! - Ensuring consistent test data across test suites
 
 // Helper functions for tests that simplify test data creation
+use crate::models::billing::{PremiumRequestUsage, PremiumRequestUsageItem, TimePeriod};
 use crate::models::github::{
-    CopilotDotcomChat, CopilotDotcomPullRequests, CopilotIdeChat, CopilotIdeCodeCompletions,
-    CopilotMetrics, Editor, Language, Model, Repository,
+    CodeReviewRepository, CopilotCodeReview, CopilotDotcomChat, CopilotDotcomPullRequests,
+    CopilotIdeChat, CopilotIdeCodeCompletions, CopilotMetrics, Editor, EditorVersion, Language,
+    Model, Repository,
 };
 use anyhow::Result;
-use chrono::Utc;
+use chrono::{NaiveDate, Utc};
+use proptest::prelude::*;
+use serde_json::{Map, Value};
 
 /// Create test metrics suitable for enterprise testing
 ///
@@ -53,12 +58,16 @@ pub fn create_test_metrics() -> CopilotMetrics {
                 total_code_acceptances: Some(2500),
                 total_code_lines_suggested: Some(10000),
                 total_code_lines_accepted: Some(5000),
+                extra: Default::default(),
             }]),
             editors: Some(vec![Editor {
                 name: "VS Code".to_string(),
                 total_engaged_users: 550,
                 models: None,
+                versions: None,
+                extra: Default::default(),
             }]),
+            extra: Default::default(),
         }),
         copilot_ide_chat: Some(CopilotIdeChat {
             total_engaged_users: 400,
@@ -66,7 +75,10 @@ pub fn create_test_metrics() -> CopilotMetrics {
                 name: "VS Code".to_string(),
                 total_engaged_users: 375,
                 models: None,
+                versions: None,
+                extra: Default::default(),
             }]),
+            extra: Default::default(),
         }),
         copilot_dotcom_chat: Some(CopilotDotcomChat {
             total_engaged_users: 300,
@@ -80,7 +92,9 @@ pub fn create_test_metrics() -> CopilotMetrics {
                 total_chat_insertion_events: Some(300),
                 total_chat_copy_events: Some(200),
                 total_pr_summaries_created: None,
+                extra: Default::default(),
             }]),
+            extra: Default::default(),
         }),
         copilot_dotcom_pull_requests: Some(CopilotDotcomPullRequests {
             total_engaged_users: 200,
@@ -97,9 +111,14 @@ pub fn create_test_metrics() -> CopilotMetrics {
                     total_chat_insertion_events: None,
                     total_chat_copy_events: None,
                     total_pr_summaries_created: Some(50),
+                    extra: Default::default(),
                 }],
+                extra: Default::default(),
             }]),
+            extra: Default::default(),
         }),
+        copilot_code_review: None,
+        extra: Default::default(),
     }
 }
 
@@ -133,12 +152,16 @@ pub fn create_test_team_metrics() -> CopilotMetrics {
                 total_code_acceptances: Some(375),
                 total_code_lines_suggested: Some(1500),
                 total_code_lines_accepted: Some(750),
+                extra: Default::default(),
             }]),
             editors: Some(vec![Editor {
                 name: "VS Code".to_string(),
                 total_engaged_users: 82,
                 models: None,
+                versions: None,
+                extra: Default::default(),
             }]),
+            extra: Default::default(),
         }),
         copilot_ide_chat: Some(CopilotIdeChat {
             total_engaged_users: 60,
@@ -146,7 +169,10 @@ pub fn create_test_team_metrics() -> CopilotMetrics {
                 name: "VS Code".to_string(),
                 total_engaged_users: 56,
                 models: None,
+                versions: None,
+                extra: Default::default(),
             }]),
+            extra: Default::default(),
         }),
         copilot_dotcom_chat: Some(CopilotDotcomChat {
             total_engaged_users: 45,
@@ -160,7 +186,9 @@ pub fn create_test_team_metrics() -> CopilotMetrics {
                 total_chat_insertion_events: Some(45),
                 total_chat_copy_events: Some(30),
                 total_pr_summaries_created: None,
+                extra: Default::default(),
             }]),
+            extra: Default::default(),
         }),
         copilot_dotcom_pull_requests: Some(CopilotDotcomPullRequests {
             total_engaged_users: 30,
@@ -177,9 +205,14 @@ pub fn create_test_team_metrics() -> CopilotMetrics {
                     total_chat_insertion_events: None,
                     total_chat_copy_events: None,
                     total_pr_summaries_created: Some(10),
+                    extra: Default::default(),
                 }],
+                extra: Default::default(),
             }]),
+            extra: Default::default(),
         }),
+        copilot_code_review: None,
+        extra: Default::default(),
     }
 }
 
@@ -224,6 +257,7 @@ pub fn create_chat_metrics() -> CopilotMetrics {
                             total_chat_insertion_events: Some(39),
                             total_chat_copy_events: Some(44),
                             total_pr_summaries_created: None,
+                            extra: Default::default(),
                         },
                         Model {
                             name: "GPT-3.5".to_string(),
@@ -235,8 +269,11 @@ pub fn create_chat_metrics() -> CopilotMetrics {
                             total_chat_insertion_events: Some(0),
                             total_chat_copy_events: Some(0),
                             total_pr_summaries_created: None,
+                            extra: Default::default(),
                         },
                     ]),
+                    versions: None,
+                    extra: Default::default(),
                 },
                 Editor {
                     name: "IntelliJ".to_string(),
@@ -252,6 +289,7 @@ pub fn create_chat_metrics() -> CopilotMetrics {
                             total_chat_insertion_events: Some(0),
                             total_chat_copy_events: Some(0),
                             total_pr_summaries_created: None,
+                            extra: Default::default(),
                         },
                         Model {
                             name: "GPT-3.5".to_string(),
@@ -263,13 +301,19 @@ pub fn create_chat_metrics() -> CopilotMetrics {
                             total_chat_insertion_events: Some(0),
                             total_chat_copy_events: Some(0),
                             total_pr_summaries_created: None,
+                            extra: Default::default(),
                         },
                     ]),
+                    versions: None,
+                    extra: Default::default(),
                 },
             ]),
+            extra: Default::default(),
         }),
         copilot_dotcom_chat: None,
         copilot_dotcom_pull_requests: None,
+        copilot_code_review: None,
+        extra: Default::default(),
     }
 }
 
@@ -311,8 +355,10 @@ pub fn create_mock_api_response() -> Result<Vec<CopilotMetrics>> {
                 total_code_acceptances: Some(800),
                 total_code_lines_suggested: Some(5000),
                 total_code_lines_accepted: Some(4000),
+                extra: Default::default(),
             }]),
             editors: None,
+            extra: Default::default(),
         }),
         copilot_ide_chat: Some(CopilotIdeChat {
             total_engaged_users: 20,
@@ -329,8 +375,12 @@ pub fn create_mock_api_response() -> Result<Vec<CopilotMetrics>> {
                     is_custom_model: false,
                     custom_model_training_date: None,
                     languages: None,
+                    extra: Default::default(),
                 }]),
+                versions: None,
+                extra: Default::default(),
             }]),
+            extra: Default::default(),
         }),
         copilot_dotcom_chat: Some(CopilotDotcomChat {
             total_engaged_users: 10,
@@ -344,9 +394,13 @@ pub fn create_mock_api_response() -> Result<Vec<CopilotMetrics>> {
                 is_custom_model: false,
                 custom_model_training_date: None,
                 languages: None,
+                extra: Default::default(),
             }]),
+            extra: Default::default(),
         }),
         copilot_dotcom_pull_requests: None,
+        copilot_code_review: None,
+        extra: Default::default(),
     }])
 }
 
@@ -379,3 +433,474 @@ pub fn create_test_metrics_with_params(active_users: i64, engaged_users: i64) ->
     metrics.total_engaged_users = Some(engaged_users);
     metrics
 }
+
+/// Create test metrics containing Copilot code review activity
+///
+/// Generates a `CopilotMetrics` object with a populated code review section
+/// (including per-repository breakdowns) and no other feature data, for testing
+/// the code review metrics path in isolation.
+///
+/// # Returns
+///
+/// A `CopilotMetrics` instance with code review metrics for two repositories
+pub fn create_code_review_metrics() -> CopilotMetrics {
+    CopilotMetrics {
+        date: "2023-03-01".to_string(),
+        total_active_users: Some(100),
+        total_engaged_users: Some(80),
+        copilot_ide_code_completions: None,
+        copilot_ide_chat: None,
+        copilot_dotcom_chat: None,
+        copilot_dotcom_pull_requests: None,
+        copilot_code_review: Some(CopilotCodeReview {
+            total_engaged_users: 25,
+            total_reviews: Some(140),
+            total_review_comments: Some(410),
+            total_review_comments_applied: Some(96),
+            repositories: Some(vec![
+                CodeReviewRepository {
+                    name: "test-repo".to_string(),
+                    total_engaged_users: 20,
+                    total_reviews: Some(120),
+                    total_review_comments: Some(350),
+                    total_review_comments_applied: Some(80),
+                    extra: Default::default(),
+                },
+                CodeReviewRepository {
+                    name: "docs".to_string(),
+                    total_engaged_users: 5,
+                    total_reviews: Some(20),
+                    total_review_comments: Some(60),
+                    total_review_comments_applied: None,
+                    extra: Default::default(),
+                },
+            ]),
+            extra: Default::default(),
+        }),
+        extra: Default::default(),
+    }
+}
+
+/// Create a large metrics window for benchmarks
+///
+/// Generates `days` consecutive days (from 2025-02-01) for each of `scopes`
+/// scopes, every day broken down by 12 languages, 4 editors with 3 models
+/// each, 3 GitHub.com chat models, 8 pull request repositories and 8 code
+/// review repositories, roughly the breakdowns of a large enterprise. The
+/// numbers are derived from the day and scope, so the window is the same on
+/// every run.
+///
+/// # Arguments
+///
+/// * `scopes` - Number of enterprises or teams the days are repeated for
+/// * `days` - Number of days per scope
+///
+/// # Returns
+///
+/// `scopes * days` metrics days, scope by scope
+pub fn create_window_fixture(scopes: usize, days: usize) -> Vec<CopilotMetrics> {
+    const LANGUAGES: &[&str] = &[
+        "rust",
+        "typescript",
+        "typescriptreact",
+        "python",
+        "go",
+        "java",
+        "kotlin",
+        "csharp",
+        "javascript",
+        "yaml",
+        "markdown",
+        "shell",
+    ];
+    const EDITORS: &[&str] = &["vscode", "jetbrains", "neovim", "visualstudio"];
+    const MODELS: &[&str] = &["default", "gpt-4o", "claude-sonnet"];
+
+    let first = NaiveDate::from_ymd_opt(2025, 2, 1).unwrap();
+    let language = |name: &str, users: i64| Language {
+        name: name.to_string(),
+        total_engaged_users: users,
+        total_code_suggestions: Some(users * 40),
+        total_code_acceptances: Some(users * 12),
+        total_code_lines_suggested: Some(users * 90),
+        total_code_lines_accepted: Some(users * 25),
+        extra: Default::default(),
+    };
+    let model = |name: &str, users: i64, languages: Option<Vec<Language>>| Model {
+        name: name.to_string(),
+        is_custom_model: false,
+        custom_model_training_date: None,
+        total_engaged_users: users,
+        languages,
+        total_chats: Some(users * 6),
+        total_chat_insertion_events: Some(users * 2),
+        total_chat_copy_events: Some(users * 3),
+        total_pr_summaries_created: Some(users),
+        extra: Default::default(),
+    };
+    let languages = |users: i64| -> Vec<Language> {
+        LANGUAGES
+            .iter()
+            .enumerate()
+            .map(|(i, name)| language(name, users / (i as i64 + 2)))
+            .collect()
+    };
+    let editors = |users: i64, with_languages: bool| -> Vec<Editor> {
+        EDITORS
+            .iter()
+            .enumerate()
+            .map(|(i, name)| Editor {
+                name: name.to_string(),
+                total_engaged_users: users / (i as i64 + 1),
+                models: Some(
+                    MODELS
+                        .iter()
+                        .map(|m| model(m, users / 4, with_languages.then(|| languages(users / 4))))
+                        .collect(),
+                ),
+                versions: None,
+                extra: Default::default(),
+            })
+            .collect()
+    };
+
+    (0..scopes)
+        .flat_map(|scope| (0..days).map(move |day| (scope, day)))
+        .map(|(scope, day)| {
+            let users = 2000 + (scope as i64) * 300 + (day as i64 % 7) * 40;
+            let repositories = |prefix: &str| -> Vec<String> {
+                (0..8).map(|i| format!("acme/{}-{}", prefix, i)).collect()
+            };
+            CopilotMetrics {
+                date: (first + chrono::Days::new(day as u64))
+                    .format("%Y-%m-%d")
+                    .to_string(),
+                total_active_users: Some(users),
+                total_engaged_users: Some(users * 4 / 5),
+                copilot_ide_code_completions: Some(CopilotIdeCodeCompletions {
+                    total_engaged_users: users / 2,
+                    languages: Some(languages(users / 2)),
+                    editors: Some(editors(users / 2, true)),
+                    extra: Default::default(),
+                }),
+                copilot_ide_chat: Some(CopilotIdeChat {
+                    total_engaged_users: users / 3,
+                    editors: Some(editors(users / 3, false)),
+                    extra: Default::default(),
+                }),
+                copilot_dotcom_chat: Some(CopilotDotcomChat {
+                    total_engaged_users: users / 5,
+                    models: Some(MODELS.iter().map(|m| model(m, users / 15, None)).collect()),
+                    extra: Default::default(),
+                }),
+                copilot_dotcom_pull_requests: Some(CopilotDotcomPullRequests {
+                    total_engaged_users: users / 10,
+                    repositories: Some(
+                        repositories("service")
+                            .into_iter()
+                            .map(|name| Repository {
+                                name,
+                                total_engaged_users: users / 40,
+                                models: vec![model("default", users / 40, None)],
+                                extra: Default::default(),
+                            })
+                            .collect(),
+                    ),
+                    extra: Default::default(),
+                }),
+                copilot_code_review: Some(CopilotCodeReview {
+                    total_engaged_users: users / 20,
+                    total_reviews: Some(users / 4),
+                    total_review_comments: Some(users / 2),
+                    total_review_comments_applied: Some(users / 8),
+                    repositories: Some(
+                        repositories("library")
+                            .into_iter()
+                            .map(|name| CodeReviewRepository {
+                                name,
+                                total_engaged_users: users / 80,
+                                total_reviews: Some(users / 32),
+                                total_review_comments: Some(users / 16),
+                                total_review_comments_applied: Some(users / 64),
+                                extra: Default::default(),
+                            })
+                            .collect(),
+                    ),
+                    extra: Default::default(),
+                }),
+                extra: Default::default(),
+            }
+        })
+        .collect()
+}
+
+/// Create a premium request usage report for testing
+///
+/// Generates a `PremiumRequestUsage` report for March 2023 with two premium
+/// models, one of which reports its request multiplier.
+///
+/// # Returns
+///
+/// A `PremiumRequestUsage` instance with two usage items
+pub fn create_premium_request_usage() -> PremiumRequestUsage {
+    let item = |model: &str, quantity: f64, multiplier: Option<f64>| PremiumRequestUsageItem {
+        product: "Copilot".to_string(),
+        sku: "Copilot Premium Request".to_string(),
+        model: Some(model.to_string()),
+        unit_type: "requests".to_string(),
+        price_per_unit: 0.04,
+        gross_quantity: quantity,
+        gross_amount: quantity * 0.04,
+        discount_quantity: quantity / 2.0,
+        discount_amount: quantity * 0.02,
+        net_quantity: quantity / 2.0,
+        net_amount: quantity * 0.02,
+        multiplier,
+        extra: Default::default(),
+    };
+
+    PremiumRequestUsage {
+        time_period: TimePeriod {
+            year: 2023,
+            month: Some(3),
+            day: None,
+        },
+        enterprise: Some("test-enterprise".to_string()),
+        usage_items: vec![
+            item("Claude Opus 4", 500.0, Some(10.0)),
+            item("GPT-4.5", 200.0, None),
+        ],
+        extra: Default::default(),
+    }
+}
+
+/// Strategy for counts as the API reports them, up to far beyond real values
+fn arb_count() -> impl Strategy<Value = i64> {
+    0i64..1 << 40
+}
+
+/// Strategy for optional counts
+fn arb_opt_count() -> impl Strategy<Value = Option<i64>> {
+    proptest::option::of(arb_count())
+}
+
+/// Strategy for breakdown names (languages, editors, models, repositories),
+/// including characters that need normalizing in tags
+fn arb_name() -> impl Strategy<Value = String> {
+    "[a-zA-Z0-9][a-zA-Z0-9 ._+#/-]{0,15}"
+}
+
+/// Strategy for fields unknown to the models
+///
+/// Keys are prefixed with `x_` so they never shadow a modelled field.
+fn arb_extra() -> impl Strategy<Value = Map<String, Value>> {
+    let value = prop_oneof![
+        Just(Value::Null),
+        any::<bool>().prop_map(Value::from),
+        any::<i64>().prop_map(Value::from),
+        "[a-z ]{0,8}".prop_map(Value::from),
+    ];
+    proptest::collection::btree_map("x_[a-z]{1,8}", value, 0..2)
+        .prop_map(|fields| fields.into_iter().collect())
+}
+
+/// Strategy for a list of up to `max` breakdown entries, or none at all
+fn arb_list<T: std::fmt::Debug>(
+    entry: impl Strategy<Value = T>,
+    max: usize,
+) -> impl Strategy<Value = Option<Vec<T>>> {
+    proptest::option::of(proptest::collection::vec(entry, 0..=max))
+}
+
+prop_compose! {
+    /// Strategy for language breakdown entries
+    pub fn arb_language()(
+        name in arb_name(),
+        total_engaged_users in arb_count(),
+        total_code_suggestions in arb_opt_count(),
+        total_code_acceptances in arb_opt_count(),
+        total_code_lines_suggested in arb_opt_count(),
+        total_code_lines_accepted in arb_opt_count(),
+        extra in arb_extra(),
+    ) -> Language {
+        Language {
+            name,
+            total_engaged_users,
+            total_code_suggestions,
+            total_code_acceptances,
+            total_code_lines_suggested,
+            total_code_lines_accepted,
+            extra,
+        }
+    }
+}
+
+prop_compose! {
+    /// Strategy for model breakdown entries
+    pub fn arb_model()(
+        name in arb_name(),
+        is_custom_model in any::<bool>(),
+        custom_model_training_date in proptest::option::of(arb_date()),
+        total_engaged_users in arb_count(),
+        languages in arb_list(arb_language(), 3),
+        total_chats in arb_opt_count(),
+        total_chat_insertion_events in arb_opt_count(),
+        total_chat_copy_events in arb_opt_count(),
+        total_pr_summaries_created in arb_opt_count(),
+        extra in arb_extra(),
+    ) -> Model {
+        Model {
+            name,
+            is_custom_model,
+            custom_model_training_date,
+            total_engaged_users,
+            languages,
+            total_chats,
+            total_chat_insertion_events,
+            total_chat_copy_events,
+            total_pr_summaries_created,
+            extra,
+        }
+    }
+}
+
+prop_compose! {
+    /// Strategy for editor breakdown entries
+    pub fn arb_editor()(
+        name in arb_name(),
+        total_engaged_users in arb_count(),
+        models in arb_list(arb_model(), 2),
+        versions in arb_list(
+            ("[0-9]{1,2}\\.[0-9]{1,2}\\.[0-9]{1,2}", arb_count(), arb_extra()).prop_map(
+                |(version, total_engaged_users, extra)| EditorVersion {
+                    version,
+                    total_engaged_users,
+                    extra,
+                },
+            ),
+            2,
+        ),
+        extra in arb_extra(),
+    ) -> Editor {
+        Editor {
+            name,
+            total_engaged_users,
+            models,
+            versions,
+            extra,
+        }
+    }
+}
+
+prop_compose! {
+    /// Strategy for code review repositories
+    fn arb_code_review_repository()(
+        name in arb_name(),
+        total_engaged_users in arb_count(),
+        total_reviews in arb_opt_count(),
+        total_review_comments in arb_opt_count(),
+        total_review_comments_applied in arb_opt_count(),
+        extra in arb_extra(),
+    ) -> CodeReviewRepository {
+        CodeReviewRepository {
+            name,
+            total_engaged_users,
+            total_reviews,
+            total_review_comments,
+            total_review_comments_applied,
+            extra,
+        }
+    }
+}
+
+/// Strategy for metric dates (YYYY-MM-DD)
+pub fn arb_date() -> impl Strategy<Value = String> {
+    (0i64..3650).prop_map(|days| {
+        (NaiveDate::from_ymd_opt(2020, 1, 1).unwrap() + chrono::Duration::days(days))
+            .format("%Y-%m-%d")
+            .to_string()
+    })
+}
+
+prop_compose! {
+    /// Strategy for a day of Copilot metrics with any combination of
+    /// sections, breakdowns and unknown fields
+    pub fn arb_copilot_metrics()(
+        date in arb_date(),
+        total_active_users in arb_opt_count(),
+        total_engaged_users in arb_opt_count(),
+        completions in proptest::option::of(
+            (arb_count(), arb_list(arb_language(), 3), arb_list(arb_editor(), 2), arb_extra())
+        ),
+        ide_chat in proptest::option::of((arb_count(), arb_list(arb_editor(), 2), arb_extra())),
+        dotcom_chat in proptest::option::of((arb_count(), arb_list(arb_model(), 2), arb_extra())),
+        pull_requests in proptest::option::of((
+            arb_count(),
+            arb_list((arb_name(), arb_count(), proptest::collection::vec(arb_model(), 0..2), arb_extra()), 2),
+            arb_extra(),
+        )),
+        code_review in proptest::option::of((
+            arb_count(),
+            (arb_opt_count(), arb_opt_count(), arb_opt_count()),
+            arb_list(arb_code_review_repository(), 2),
+            arb_extra(),
+        )),
+        extra in arb_extra(),
+    ) -> CopilotMetrics {
+        CopilotMetrics {
+            date,
+            total_active_users,
+            total_engaged_users,
+            copilot_ide_code_completions: completions.map(
+                |(total_engaged_users, languages, editors, extra)| CopilotIdeCodeCompletions {
+                    total_engaged_users,
+                    languages,
+                    editors,
+                    extra,
+                },
+            ),
+            copilot_ide_chat: ide_chat.map(|(total_engaged_users, editors, extra)| CopilotIdeChat {
+                total_engaged_users,
+                editors,
+                extra,
+            }),
+            copilot_dotcom_chat: dotcom_chat.map(|(total_engaged_users, models, extra)| {
+                CopilotDotcomChat {
+                    total_engaged_users,
+                    models,
+                    extra,
+                }
+            }),
+            copilot_dotcom_pull_requests: pull_requests.map(
+                |(total_engaged_users, repositories, extra)| CopilotDotcomPullRequests {
+                    total_engaged_users,
+                    repositories: repositories.map(|repositories| {
+                        repositories
+                            .into_iter()
+                            .map(|(name, total_engaged_users, models, extra)| Repository {
+                                name,
+                                total_engaged_users,
+                                models,
+                                extra,
+                            })
+                            .collect()
+                    }),
+                    extra,
+                },
+            ),
+            copilot_code_review: code_review.map(
+                |(total_engaged_users, (total_reviews, total_review_comments, total_review_comments_applied), repositories, extra)| {
+                    CopilotCodeReview {
+                        total_engaged_users,
+                        total_reviews,
+                        total_review_comments,
+                        total_review_comments_applied,
+                        repositories,
+                        extra,
+                    }
+                },
+            ),
+            extra,
+        }
+    }
+}
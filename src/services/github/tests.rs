@@ -18,7 +18,12 @@ use super::test_helpers::{
     create_chat_metrics, create_mock_api_response, create_test_metrics, create_test_team_metrics,
 };
 use crate::models::github::CopilotMetrics;
-use crate::services::github::{get_enterprise_metrics, get_team_metrics};
+use crate::services::github::{
+    get_enterprise_metrics, get_team_metrics, GitHubClient, GitHubError, GitHubSettings,
+    MetricsSource, SchemaMode, DEFAULT_API_VERSION, DEFAULT_USER_AGENT,
+};
+use crate::services::tls::AgentConfig;
+use crate::tests::mock_server::{self, MockResponse};
 
 /// Core test for mock metrics functionality
 ///
@@ -137,7 +142,10 @@ fn test_github_api_direct() {
     let enterprise_id =
         std::env::var("GITHUB_ENTERPRISE_ID").expect("GITHUB_ENTERPRISE_ID not set");
 
-    let client = super::api::GitHubClient::new(&github_token);
+    let client = super::api::GitHubClient::builder()
+        .token(&github_token)
+        .build()
+        .unwrap();
     let result = get_enterprise_metrics(&client, &enterprise_id);
     println!("\nAPI Call Result: {:?}", result);
 }
@@ -164,7 +172,10 @@ fn test_github_team_metrics_direct() {
     let enterprise_id =
         std::env::var("GITHUB_ENTERPRISE_ID").expect("GITHUB_ENTERPRISE_ID not set");
 
-    let client = super::api::GitHubClient::new(&github_token);
+    let client = super::api::GitHubClient::builder()
+        .token(&github_token)
+        .build()
+        .unwrap();
     let team_slug = "pts";
 
     let result = get_team_metrics(&client, &enterprise_id, team_slug);
@@ -207,15 +218,18 @@ fn test_github_team_metrics_direct() {
 #[test]
 fn test_ide_chat_metrics_calculation() {
     // This test requires access to the Datadog client, which may not be available in all test environments
-    if let Ok(_) = std::env::var("SKIP_DATADOG_TESTS") {
+    if std::env::var("SKIP_DATADOG_TESTS").is_ok() {
         println!("Skipping Datadog test as SKIP_DATADOG_TESTS is set");
         return;
     }
 
-    std::env::set_var("DATADOG_NAMESPACE_P7S1", "gh.p7s1.test");
-
-    let metrics = create_chat_metrics();
+    temp_env::with_var("DATADOG_NAMESPACE_P7S1", Some("gh.p7s1.test"), || {
+        check_ide_chat_metrics_calculation(create_chat_metrics())
+    });
+}
 
+/// Body of `test_ide_chat_metrics_calculation`, run with the P7S1 namespace set
+fn check_ide_chat_metrics_calculation(metrics: CopilotMetrics) {
     // Mock the Datadog functionality or skip if not available
     #[cfg(feature = "datadog_tests")]
     {
@@ -318,7 +332,7 @@ mod mock_client_tests {
     /// - Verifies the structure and values of the returned metrics
     #[test]
     fn test_github_api_with_mock() {
-        let client = GitHubClient::new("fake_token");
+        let client = GitHubClient::builder().token("fake_token").build().unwrap();
         let metrics = client.mock_response().unwrap();
         let metric = &metrics[0];
 
@@ -350,3 +364,1102 @@ mod mock_client_tests {
         );
     }
 }
+
+/// Unknown API fields are preserved and reported
+///
+/// Deserializes a response containing fields the models do not know about
+/// and verifies that they are kept in the `extra` maps, listed by
+/// `unknown_field_paths`, and written back out on serialization.
+#[test]
+fn test_unknown_fields_are_preserved() {
+    let body = r#"[{
+        "date": "2024-06-01",
+        "total_active_users": 10,
+        "total_engaged_users": 8,
+        "copilot_new_feature": {"total_engaged_users": 3},
+        "copilot_ide_chat": {
+            "total_engaged_users": 5,
+            "editors": [{
+                "name": "vscode",
+                "total_engaged_users": 5,
+                "models": [{
+                    "name": "default",
+                    "is_custom_model": false,
+                    "custom_model_training_date": null,
+                    "total_engaged_users": 5,
+                    "total_chat_turns": 12
+                }]
+            }]
+        }
+    }]"#;
+
+    let metrics: Vec<CopilotMetrics> = serde_json::from_str(body).unwrap();
+    let metric = &metrics[0];
+
+    assert!(metric.extra.contains_key("copilot_new_feature"));
+    assert_eq!(
+        metric.unknown_field_paths(),
+        vec![
+            "copilot_ide_chat.editors[].models[].total_chat_turns".to_string(),
+            "copilot_new_feature".to_string(),
+        ]
+    );
+
+    let round_trip = serde_json::to_value(metric).unwrap();
+    assert_eq!(round_trip["copilot_new_feature"]["total_engaged_users"], 3);
+    assert_eq!(
+        round_trip["copilot_ide_chat"]["editors"][0]["models"][0]["total_chat_turns"],
+        12
+    );
+}
+
+proptest::proptest! {
+    /// Any metrics survive a serialize/deserialize round trip unchanged,
+    /// including absent sections, empty breakdowns and unknown fields
+    #[test]
+    fn prop_metrics_round_trip(metrics in super::test_helpers::arb_copilot_metrics()) {
+        let json = serde_json::to_value(&metrics).unwrap();
+        let parsed: CopilotMetrics = serde_json::from_value(json.clone()).unwrap();
+        proptest::prop_assert_eq!(serde_json::to_value(&parsed).unwrap(), json);
+        proptest::prop_assert_eq!(parsed.unknown_field_paths(), metrics.unknown_field_paths());
+    }
+}
+
+/// `null`s and values of an unexpected type degrade to defaults instead of
+/// failing the whole response, and breakdown entries without a name are
+/// dropped
+#[test]
+fn test_unexpected_values_are_tolerated() {
+    let body = r#"[{
+        "date": "2024-06-01",
+        "total_active_users": "12",
+        "total_engaged_users": null,
+        "copilot_ide_code_completions": {
+            "total_engaged_users": null,
+            "languages": null,
+            "editors": [
+                {"name": "vscode", "total_engaged_users": 7.0, "models": {"name": "default"}},
+                "jetbrains",
+                {"name": null, "total_engaged_users": 3},
+                {"total_engaged_users": 1},
+                {"name": "", "total_engaged_users": 1},
+                {"name": "neovim", "total_engaged_users": 2, "models": [{
+                    "name": "default",
+                    "is_custom_model": null,
+                    "custom_model_training_date": 20240101,
+                    "total_engaged_users": 2,
+                    "languages": [
+                        {"name": "rust", "total_engaged_users": 2, "total_code_suggestions": "n/a"},
+                        {"name": null, "total_engaged_users": 1}
+                    ]
+                }]}
+            ]
+        },
+        "copilot_ide_chat": "unavailable",
+        "copilot_dotcom_pull_requests": {"total_engaged_users": 1, "repositories": [
+            {"name": "web", "total_engaged_users": 1, "models": null}
+        ]}
+    }]"#;
+
+    let metrics: Vec<CopilotMetrics> = serde_json::from_str(body).unwrap();
+    let metric = &metrics[0];
+    assert_eq!(metric.total_active_users, Some(12));
+    assert_eq!(metric.total_engaged_users, None);
+    assert!(metric.copilot_ide_chat.is_none());
+
+    let completions = metric.copilot_ide_code_completions.as_ref().unwrap();
+    assert_eq!(completions.total_engaged_users, 0);
+    assert!(completions.languages.is_none());
+    // The entry that isn't an editor and the unnamed ones are dropped, the
+    // others are kept
+    let editors = completions.editors.as_ref().unwrap();
+    assert_eq!(editors.len(), 2);
+    assert_eq!(editors[0].total_engaged_users, 7);
+    assert!(editors[0].models.is_none());
+    assert_eq!(editors[1].name, "neovim");
+    let model = &editors[1].models.as_ref().unwrap()[0];
+    assert!(!model.is_custom_model);
+    assert_eq!(model.custom_model_training_date, None);
+    let languages = model.languages.as_ref().unwrap();
+    assert_eq!(languages.len(), 1);
+    assert_eq!(languages[0].total_code_suggestions, None);
+
+    let prs = metric.copilot_dotcom_pull_requests.as_ref().unwrap();
+    assert!(prs.repositories.as_ref().unwrap()[0].models.is_empty());
+}
+
+/// Known-only responses report no unknown fields
+#[test]
+fn test_no_unknown_fields_in_fixtures() {
+    assert!(create_test_metrics().unknown_field_paths().is_empty());
+}
+
+/// A response with a field unknown to the models
+const DRIFTED_RESPONSE: &str = r#"[{
+    "date": "2024-06-01",
+    "total_active_users": 10,
+    "total_engaged_users": 8,
+    "copilot_new_feature": {"total_engaged_users": 3}
+}]"#;
+
+/// Schema modes are parsed from their configuration values
+#[test]
+fn test_parse_schema_mode() {
+    assert_eq!(SchemaMode::parse(""), Some(SchemaMode::Lenient));
+    assert_eq!(SchemaMode::parse("0"), Some(SchemaMode::Lenient));
+    assert_eq!(SchemaMode::parse("false"), Some(SchemaMode::Lenient));
+    assert_eq!(SchemaMode::parse("WARN"), Some(SchemaMode::Warn));
+    assert_eq!(SchemaMode::parse("1"), Some(SchemaMode::Strict));
+    assert_eq!(SchemaMode::parse("true"), Some(SchemaMode::Strict));
+    assert_eq!(SchemaMode::parse("strict"), Some(SchemaMode::Strict));
+    assert_eq!(SchemaMode::parse("warning"), None);
+    assert_eq!(SchemaMode::parse("yes please"), None);
+}
+
+/// Strict schema mode rejects responses with unknown fields
+#[test]
+fn test_strict_schema_rejects_unknown_fields() {
+    let client = GitHubClient::builder()
+        .token("fake_token")
+        .build()
+        .unwrap()
+        .with_schema_mode(SchemaMode::Strict);
+
+    match client.parse_metrics(DRIFTED_RESPONSE, "enterprise") {
+        Err(super::error::GitHubError::SchemaDrift(context, fields)) => {
+            assert_eq!(context, "enterprise");
+            assert_eq!(fields, vec!["copilot_new_feature".to_string()]);
+        }
+        other => panic!("Expected schema drift error, got {:?}", other),
+    }
+}
+
+/// Lenient and warn modes keep processing responses with unknown fields
+#[test]
+fn test_non_strict_schema_accepts_unknown_fields() {
+    for mode in [SchemaMode::Lenient, SchemaMode::Warn] {
+        let client = GitHubClient::builder()
+            .token("fake_token")
+            .build()
+            .unwrap()
+            .with_schema_mode(mode);
+        let metrics = client.parse_metrics(DRIFTED_RESPONSE, "team").unwrap();
+        assert_eq!(metrics.len(), 1);
+    }
+}
+
+/// Copilot code review sections are deserialized into the model
+#[test]
+fn test_code_review_section_deserializes() {
+    let body = r#"[{
+        "date": "2024-06-01",
+        "total_active_users": 10,
+        "total_engaged_users": 8,
+        "copilot_code_review": {
+            "total_engaged_users": 4,
+            "total_reviews": 12,
+            "repositories": [{
+                "name": "api",
+                "total_engaged_users": 4,
+                "total_review_comments": 30
+            }]
+        }
+    }]"#;
+
+    let metrics: Vec<CopilotMetrics> = serde_json::from_str(body).unwrap();
+    let review = metrics[0].copilot_code_review.as_ref().unwrap();
+    assert_eq!(review.total_engaged_users, 4);
+    assert_eq!(review.total_reviews, Some(12));
+    let repos = review.repositories.as_ref().unwrap();
+    assert_eq!(repos[0].name, "api");
+    assert_eq!(repos[0].total_review_comments, Some(30));
+    assert!(metrics[0].unknown_field_paths().is_empty());
+}
+
+/// Premium request usage reports are deserialized from GitHub's camelCase shape
+#[test]
+fn test_premium_request_usage_deserializes() {
+    let body = r#"{
+        "timePeriod": {"year": 2025, "month": 6},
+        "enterprise": "acme",
+        "usageItems": [{
+            "product": "Copilot",
+            "sku": "Copilot Premium Request",
+            "model": "Claude Sonnet 4",
+            "unitType": "requests",
+            "pricePerUnit": 0.04,
+            "grossQuantity": 120,
+            "grossAmount": 4.8,
+            "discountQuantity": 100,
+            "discountAmount": 4.0,
+            "netQuantity": 20,
+            "netAmount": 0.8
+        }]
+    }"#;
+
+    let usage: crate::models::billing::PremiumRequestUsage = serde_json::from_str(body).unwrap();
+    assert_eq!(usage.time_period.label(), "2025-06");
+    assert_eq!(usage.usage_items.len(), 1);
+    let item = &usage.usage_items[0];
+    assert_eq!(item.model.as_deref(), Some("Claude Sonnet 4"));
+    assert_eq!(item.gross_quantity, 120.0);
+    assert_eq!(item.net_amount, 0.8);
+    assert_eq!(item.multiplier, None);
+}
+
+/// The builder requires a token and derives the GraphQL endpoint from the base URL
+#[test]
+fn test_client_builder() {
+    use crate::services::github::{GitHubError, RetryPolicy, TokenSource};
+    use std::time::Duration;
+
+    assert!(matches!(
+        GitHubClient::builder().build(),
+        Err(GitHubError::Config(_))
+    ));
+    assert!(matches!(
+        GitHubClient::builder().token("  ").build(),
+        Err(GitHubError::Config(_))
+    ));
+    assert!(matches!(
+        TokenSource::Env("GHRUST_TEST_UNSET_TOKEN".to_string()).resolve(),
+        Err(GitHubError::Config(_))
+    ));
+
+    let client = GitHubClient::builder().token("fake_token").build().unwrap();
+    assert_eq!(client.graphql_url(), "https://api.github.com/graphql");
+
+    let client = GitHubClient::builder()
+        .token("fake_token")
+        .base_url("https://github.example.com/api/v3/")
+        .build()
+        .unwrap();
+    assert_eq!(client.base_url, "https://github.example.com/api/v3");
+    assert_eq!(
+        client.graphql_url(),
+        "https://github.example.com/api/graphql"
+    );
+
+    let policy = RetryPolicy::new(3, Duration::from_millis(100));
+    assert_eq!(policy.delay(1), Duration::from_millis(100));
+    assert_eq!(policy.delay(3), Duration::from_millis(400));
+}
+
+/// ETags are only persisted on commit and report unchanged windows as not
+/// modified
+#[test]
+fn test_etag_conditional_requests() {
+    use crate::services::github::{EtagCache, GitHubError};
+    use std::sync::Arc;
+
+    let path = std::env::temp_dir().join(format!("ghrust-etags-{}.json", std::process::id()));
+    std::fs::remove_file(&path).ok();
+    let body = serde_json::to_string(&vec![create_test_metrics()]).unwrap();
+    let (base_url, server) = mock_server::serve(vec![
+        MockResponse::new("200 OK", body.clone()).with_header("ETag", "\"v1\""),
+        MockResponse::new("200 OK", body).with_header("ETag", "\"v1\""),
+        MockResponse::new("304 Not Modified", String::new()),
+    ]);
+    let client = |etags: &Arc<EtagCache>| {
+        GitHubClient::builder()
+            .token("fake_token")
+            .base_url(&base_url)
+            .etag_cache(Arc::clone(etags))
+            .build()
+            .unwrap()
+    };
+
+    // Without a commit, the next run sends no ETag
+    let etags = Arc::new(EtagCache::open(&path));
+    assert_eq!(
+        client(&etags)
+            .fetch_enterprise_metrics("acme", "2025-06-01")
+            .unwrap()
+            .len(),
+        1
+    );
+    etags.discard();
+    assert_eq!(etags.commit().unwrap(), 0);
+    let etags = Arc::new(EtagCache::open(&path));
+    client(&etags)
+        .fetch_enterprise_metrics("acme", "2025-06-01")
+        .unwrap();
+    assert_eq!(etags.commit().unwrap(), 1);
+
+    // The committed ETag is sent by the next run, which gets no new data
+    let etags = Arc::new(EtagCache::open(&path));
+    assert!(matches!(
+        client(&etags).fetch_enterprise_metrics("acme", "2025-06-01"),
+        Err(GitHubError::NotModified(_))
+    ));
+    std::fs::remove_file(&path).ok();
+
+    let requests = server.join().unwrap();
+    let conditions: Vec<Option<&str>> = requests
+        .iter()
+        .map(|request| request.header("if-none-match"))
+        .collect();
+    assert_eq!(conditions, vec![None, None, Some("\"v1\"")]);
+}
+
+/// Server errors are retried, and the attempts are reported once the retries
+/// are used up
+#[test]
+fn test_server_errors_are_retried() {
+    use crate::services::github::{GitHubError, RetryPolicy};
+    use std::time::Duration;
+
+    let body = serde_json::to_string(&vec![create_test_metrics()]).unwrap();
+    let (base_url, server) = mock_server::serve(vec![
+        MockResponse::new("502 Bad Gateway", String::new()),
+        MockResponse::new("503 Service Unavailable", String::new()),
+        MockResponse::new("200 OK", body),
+        MockResponse::new("500 Internal Server Error", "boom".to_string()),
+        MockResponse::new("504 Gateway Timeout", "boom".to_string()),
+    ]);
+    let client = |retries| {
+        GitHubClient::builder()
+            .token("fake_token")
+            .base_url(&base_url)
+            .retry_policy(RetryPolicy::new(retries, Duration::from_millis(1)))
+            .build()
+            .unwrap()
+    };
+
+    let metrics = client(2)
+        .fetch_team_metrics("acme", "web", "2025-06-01")
+        .unwrap();
+    assert_eq!(metrics.len(), 1);
+
+    let err = client(1)
+        .fetch_team_metrics("acme", "web", "2025-06-01")
+        .unwrap_err();
+    assert!(
+        matches!(err, GitHubError::ServerError(504, 2, _)),
+        "{}",
+        err
+    );
+    assert!(err.is_retryable());
+    assert_eq!(server.join().unwrap().len(), 5);
+}
+
+/// `202 Accepted` and empty responses are retried while GitHub generates the
+/// data, and reported as "no data yet" once the retries are used up
+#[test]
+fn test_pending_responses_are_retried() {
+    use crate::error::GhrustError;
+    use crate::services::github::{GitHubError, PendingPolicy};
+    use std::time::Duration;
+
+    let body = serde_json::to_string(&vec![create_test_metrics()]).unwrap();
+    let (base_url, server) = mock_server::serve(vec![
+        MockResponse::new("202 Accepted", String::new()),
+        MockResponse::new("200 OK", " ".to_string()),
+        MockResponse::new("200 OK", body),
+        MockResponse::new("202 Accepted", String::new()),
+        MockResponse::new("202 Accepted", String::new()),
+    ]);
+    let client = |retries| {
+        GitHubClient::builder()
+            .token("fake_token")
+            .base_url(&base_url)
+            .pending_policy(PendingPolicy::new(retries, Duration::from_millis(1)))
+            .build()
+            .unwrap()
+    };
+
+    let metrics = client(2)
+        .fetch_enterprise_metrics("acme", "2025-06-01")
+        .unwrap();
+    assert_eq!(metrics.len(), 1);
+
+    let err = client(1)
+        .fetch_enterprise_metrics("acme", "2025-06-01")
+        .unwrap_err();
+    assert!(matches!(err, GitHubError::NoDataYet(_)));
+    assert!(err.is_retryable());
+    let err = GhrustError::github("Failed to fetch enterprise metrics")(err);
+    assert_eq!(err.kind(), "github_no_data_yet");
+    assert_eq!(server.join().unwrap().len(), 5);
+}
+
+/// The retries while GitHub generates data are bounded per run, over all
+/// clones of a client, and only metrics endpoints are retried at all
+#[test]
+fn test_pending_retry_budget() {
+    use crate::services::github::{GitHubError, PendingPolicy};
+    use std::time::Duration;
+
+    let (base_url, server) = mock_server::serve(vec![
+        MockResponse::new("202 Accepted", String::new()),
+        MockResponse::new("202 Accepted", String::new()),
+        MockResponse::new("202 Accepted", String::new()),
+        MockResponse::new("202 Accepted", String::new()),
+        MockResponse::new("200 OK", String::new()),
+    ]);
+    let client = GitHubClient::builder()
+        .token("fake_token")
+        .base_url(&base_url)
+        .pending_policy(PendingPolicy::new(5, Duration::from_millis(1)).with_max_total_retries(2))
+        .build()
+        .unwrap();
+
+    // Two retries use up the budget of the run...
+    let err = client
+        .fetch_enterprise_metrics("acme", "2025-06-01")
+        .unwrap_err();
+    assert!(matches!(err, GitHubError::NoDataYet(_)));
+    // ...so a clone gives up after the first attempt
+    let err = client
+        .clone()
+        .fetch_team_metrics("acme", "web", "2025-06-01")
+        .unwrap_err();
+    assert!(matches!(err, GitHubError::NoDataYet(_)));
+
+    // An empty body of another endpoint is not mistaken for pending data
+    let err = client.fetch_org_billing("acme").unwrap_err();
+    assert!(!matches!(err, GitHubError::NoDataYet(_)), "{}", err);
+    assert_eq!(server.join().unwrap().len(), 5);
+}
+
+/// Metrics responses are archived under the fetch day and read back for replays
+#[test]
+fn test_raw_response_archive() {
+    use crate::services::github::RawArchive;
+    use std::sync::Arc;
+
+    let dir = std::env::temp_dir().join(format!("ghrust-archive-{}", std::process::id()));
+    std::fs::remove_dir_all(&dir).ok();
+    let body = serde_json::to_string(&vec![create_test_metrics()]).unwrap();
+    let (base_url, server) = mock_server::serve(vec![MockResponse::new("200 OK", body.clone())]);
+    let archive =
+        Arc::new(RawArchive::open(dir.to_str().unwrap(), &AgentConfig::default()).unwrap());
+    let client = GitHubClient::builder()
+        .token("fake_token")
+        .base_url(&base_url)
+        .build()
+        .unwrap()
+        .with_archive(Arc::clone(&archive));
+
+    client
+        .fetch_team_metrics("acme", "web", "2025-06-01")
+        .unwrap();
+    server.join().unwrap();
+
+    let today = chrono::Utc::now().format("%Y-%m-%d").to_string();
+    assert_eq!(
+        std::fs::read_to_string(
+            dir.join(&today)
+                .join("enterprises/acme/team/web/copilot/metrics.json")
+        )
+        .unwrap(),
+        body
+    );
+    // Reading the archive back needs no token
+    let client = GitHubClient::builder().base_url(&base_url).build_offline();
+    let replayed = client
+        .archived_team_metrics(&archive, &today, "acme", "web")
+        .unwrap()
+        .unwrap();
+    assert_eq!(replayed.len(), 1);
+    assert_eq!(replayed[0].date, create_test_metrics().date);
+
+    // Nothing was archived for the enterprise or on other days
+    assert!(client
+        .archived_enterprise_metrics(&archive, &today, "acme")
+        .unwrap()
+        .is_none());
+    assert!(client
+        .archived_team_metrics(&archive, "2000-01-01", "acme", "web")
+        .unwrap()
+        .is_none());
+    std::fs::remove_dir_all(&dir).ok();
+}
+
+/// The user agent and API version can be configured per client or read from
+/// `GITHUB_USER_AGENT` and `GITHUB_API_VERSION` into `GitHubSettings`
+#[test]
+fn test_user_agent_and_api_version_headers() {
+    let body = serde_json::to_string(&vec![create_test_metrics()]).unwrap();
+    let (base_url, server) = mock_server::serve(vec![
+        MockResponse::new("200 OK", body.clone()),
+        MockResponse::new("200 OK", body.clone()),
+        MockResponse::new("200 OK", body),
+    ]);
+
+    temp_env::with_vars(
+        [
+            ("GITHUB_USER_AGENT", Some("acme-gateway/2.0")),
+            ("GITHUB_API_VERSION", Some("2026-03-10")),
+        ],
+        || {
+            // The builder never reads the environment
+            let client = GitHubClient::builder()
+                .token("fake_token")
+                .base_url(&base_url)
+                .build()
+                .unwrap();
+            client
+                .fetch_enterprise_metrics("acme", "2025-06-01")
+                .unwrap();
+
+            let client = GitHubClient::builder()
+                .token("fake_token")
+                .base_url(&base_url)
+                .settings(GitHubSettings::from_env())
+                .build()
+                .unwrap();
+            client
+                .fetch_enterprise_metrics("acme", "2025-06-01")
+                .unwrap();
+
+            // Explicit options win over the settings
+            let client = GitHubClient::builder()
+                .token("fake_token")
+                .base_url(&base_url)
+                .settings(GitHubSettings::from_env())
+                .user_agent("explicit/1.0")
+                .api_version("2022-11-28")
+                .build()
+                .unwrap();
+            client
+                .fetch_enterprise_metrics("acme", "2025-06-01")
+                .unwrap();
+        },
+    );
+
+    let requests = server.join().unwrap();
+    let headers: Vec<(&str, &str)> = requests
+        .iter()
+        .map(|request| {
+            (
+                request.header("user-agent").unwrap(),
+                request.header("x-github-api-version").unwrap(),
+            )
+        })
+        .collect();
+    assert_eq!(
+        headers,
+        vec![
+            (DEFAULT_USER_AGENT, DEFAULT_API_VERSION),
+            ("acme-gateway/2.0", "2026-03-10"),
+            ("explicit/1.0", "2022-11-28"),
+        ]
+    );
+}
+
+/// The usage summary source requests `/copilot/usage` and maps its days
+/// onto the metrics models
+#[test]
+fn test_usage_summary_source() {
+    assert_eq!(MetricsSource::parse(" Usage "), MetricsSource::Usage);
+    assert_eq!(MetricsSource::parse("metrics"), MetricsSource::Metrics);
+    assert_eq!(MetricsSource::parse(""), MetricsSource::Metrics);
+
+    let body = r#"[{
+        "day": "2025-06-01",
+        "total_suggestions_count": 30,
+        "total_acceptances_count": 12,
+        "total_lines_suggested": 60,
+        "total_lines_accepted": 20,
+        "total_active_users": 7,
+        "total_chat_acceptances": 4,
+        "total_chat_turns": 9,
+        "total_active_chat_users": 3,
+        "breakdown": [
+            {"language": "rust", "editor": "vscode", "suggestions_count": 10,
+             "acceptances_count": 5, "lines_suggested": 20, "lines_accepted": 8,
+             "active_users": 4},
+            {"language": "rust", "editor": "jetbrains", "suggestions_count": 15,
+             "acceptances_count": 6, "lines_suggested": 30, "lines_accepted": 10,
+             "active_users": 2},
+            {"language": "python", "editor": "vscode", "suggestions_count": 5,
+             "acceptances_count": 1, "lines_suggested": 10, "lines_accepted": 2,
+             "active_users": 3}
+        ]
+    }]"#;
+    let (base_url, server) =
+        mock_server::serve(vec![MockResponse::new("200 OK", body.to_string())]);
+
+    let client = GitHubClient::builder()
+        .token("fake_token")
+        .base_url(&base_url)
+        .metrics_source(MetricsSource::Usage)
+        .build()
+        .unwrap();
+    let metrics = client
+        .fetch_team_metrics("acme", "platform", "2025-06-01")
+        .unwrap();
+
+    let requests = server.join().unwrap();
+    assert_eq!(
+        requests[0].path,
+        "/enterprises/acme/team/platform/copilot/usage?since=2025-06-01"
+    );
+
+    assert_eq!(metrics.len(), 1);
+    let day = &metrics[0];
+    assert_eq!(day.date, "2025-06-01");
+    assert_eq!(day.total_active_users, Some(7));
+    let completions = day.copilot_ide_code_completions.as_ref().unwrap();
+    let languages = completions.languages.as_ref().unwrap();
+    let rust = languages.iter().find(|l| l.name == "rust").unwrap();
+    assert_eq!(rust.total_engaged_users, 4);
+    assert_eq!(rust.total_code_suggestions, Some(25));
+    assert_eq!(rust.total_code_lines_accepted, Some(18));
+    let editors = completions.editors.as_ref().unwrap();
+    let vscode = editors.iter().find(|e| e.name == "vscode").unwrap();
+    assert_eq!(vscode.total_engaged_users, 4);
+    let model = &vscode.models.as_ref().unwrap()[0];
+    assert_eq!(model.languages.as_ref().unwrap().len(), 2);
+    assert_eq!(
+        day.copilot_ide_chat.as_ref().unwrap().total_engaged_users,
+        3
+    );
+}
+
+/// Request counts are reported per run, with the latest reported rate limit
+#[test]
+fn test_rate_limit_budget() {
+    use super::budget::{RateLimitBudget, RateLimitCounters};
+
+    let counters = RateLimitCounters::new();
+    let start = counters.snapshot();
+    assert_eq!(start, RateLimitBudget::default());
+    assert_eq!(start.gauges("2025-06-01").len(), 1);
+
+    counters.record(|name| match name {
+        "X-RateLimit-Remaining" => Some("4999"),
+        "X-RateLimit-Limit" => Some("5000"),
+        "X-RateLimit-Reset" => Some("1750000000"),
+        _ => None,
+    });
+    let earlier = counters.snapshot();
+    counters.record(|name| (name == "X-RateLimit-Remaining").then_some("4997"));
+    counters.record(|_| None);
+
+    let run = counters.snapshot().since(&earlier);
+    assert_eq!(
+        run,
+        RateLimitBudget {
+            requests: 2,
+            remaining: Some(4997),
+            limit: Some(5000),
+            reset: Some(1750000000),
+        }
+    );
+    let gauges: Vec<(String, f64)> = run
+        .gauges("2025-06-01")
+        .into_iter()
+        .map(|gauge| (gauge.name, gauge.value))
+        .collect();
+    assert_eq!(
+        gauges,
+        vec![
+            ("collector.github.requests".to_string(), 2.0),
+            ("collector.github.rate_limit.remaining".to_string(), 4997.0),
+            ("collector.github.rate_limit.limit".to_string(), 5000.0),
+        ]
+    );
+}
+
+/// Discovered teams are cached until the TTL expires or a refresh is requested
+#[test]
+fn test_team_discovery_cache() {
+    use crate::services::github::teams::{discover_teams, TeamCache};
+    use crate::services::github::RetryPolicy;
+    use std::time::Duration;
+
+    let path = std::env::temp_dir().join(format!("ghrust-teams-{}.json", std::process::id()));
+    std::fs::remove_file(&path).ok();
+    let teams = |slugs: &[&str]| {
+        let teams: Vec<_> = slugs
+            .iter()
+            .map(|slug| serde_json::json!({ "slug": slug, "name": slug }))
+            .collect();
+        serde_json::to_string(&teams).unwrap()
+    };
+    let (base_url, server) = mock_server::serve(vec![
+        MockResponse::new("200 OK", teams(&["platform", "web"])),
+        MockResponse::new("200 OK", teams(&["platform", "web", "data"])),
+        MockResponse::new("500 Internal Server Error", String::new()),
+    ]);
+    let client = GitHubClient::builder()
+        .token("fake_token")
+        .base_url(&base_url)
+        .retry_policy(RetryPolicy::none())
+        .build()
+        .unwrap();
+    let ttl = Duration::from_secs(3600);
+
+    // The first run lists the teams and caches them
+    let cache = TeamCache::open(&path, ttl);
+    let discovered = discover_teams(&client, "acme", Some(&cache), false).unwrap();
+    assert_eq!(discovered, vec!["platform", "web"]);
+
+    // The next run uses the cached list without a request
+    let cache = TeamCache::open(&path, ttl);
+    let discovered = discover_teams(&client, "acme", Some(&cache), false).unwrap();
+    assert_eq!(discovered, vec!["platform", "web"]);
+
+    // A refresh lists them again and updates the cache
+    let discovered = discover_teams(&client, "acme", Some(&cache), true).unwrap();
+    assert_eq!(discovered, vec!["platform", "web", "data"]);
+
+    // If listing fails, the expired list is used
+    let expired = TeamCache::open(&path, Duration::ZERO);
+    assert_eq!(expired.get("acme", chrono::Utc::now().timestamp()), None);
+    let discovered = discover_teams(&client, "acme", Some(&expired), false).unwrap();
+    assert_eq!(discovered, vec!["platform", "web", "data"]);
+
+    let requests = server.join().unwrap();
+    assert_eq!(requests.len(), 3);
+    assert_eq!(
+        requests[0].path,
+        "/enterprises/acme/teams?per_page=100&page=1"
+    );
+    std::fs::remove_file(&path).ok();
+}
+
+/// An enterprise the API doesn't know is fetched as an organization
+#[test]
+fn test_enterprise_falls_back_to_org_endpoint() {
+    use crate::processors::enterprise::{fetch_with_org_fallback, ORG_FALLBACK_TAG};
+
+    let body = serde_json::to_string(&vec![create_test_metrics()]).unwrap();
+    let not_found = r#"{"message": "Not Found"}"#.to_string();
+    let organization =
+        r#"{"data": {"enterprise": null, "organization": {"login": "acme"}}}"#.to_string();
+    let (base_url, server) = mock_server::serve(vec![
+        MockResponse::new("404 Not Found", not_found.clone()),
+        MockResponse::new("200 OK", organization),
+        MockResponse::new("200 OK", body),
+        MockResponse::new("404 Not Found", not_found.clone()),
+        MockResponse::new("404 Not Found", not_found),
+    ]);
+    let client = GitHubClient::builder()
+        .token("fake_token")
+        .base_url(&base_url)
+        .build()
+        .unwrap();
+
+    let (metrics, tags) = fetch_with_org_fallback(&client, "acme", "2025-06-01").unwrap();
+    assert_eq!(metrics.len(), 1);
+    assert_eq!(tags, vec![ORG_FALLBACK_TAG.to_string()]);
+
+    // The enterprise endpoint's error is kept if the organization is unknown too
+    assert!(matches!(
+        fetch_with_org_fallback(&client, "acme", "2025-06-01"),
+        Err(GitHubError::NotFound(_))
+    ));
+
+    let paths: Vec<String> = server
+        .join()
+        .unwrap()
+        .iter()
+        .map(|request| request.path.split('?').next().unwrap().to_string())
+        .collect();
+    assert_eq!(
+        paths,
+        vec![
+            "/enterprises/acme/copilot/metrics",
+            "/graphql",
+            "/orgs/acme/copilot/metrics",
+            "/enterprises/acme/copilot/metrics",
+            "/orgs/acme/copilot/metrics",
+        ]
+    );
+}
+
+/// A 404 of an enterprise's endpoint is not retried as an organization
+#[test]
+fn test_enterprise_not_found_does_not_fall_back() {
+    use crate::processors::enterprise::{fallback_tags, fetch_team_with_org_fallback};
+
+    let (base_url, server) = mock_server::serve(vec![
+        MockResponse::new("404 Not Found", r#"{"message": "Not Found"}"#.to_string()),
+        MockResponse::new(
+            "200 OK",
+            r#"{"data": {"enterprise": {"slug": "acme"}, "organization": null}}"#.to_string(),
+        ),
+    ]);
+    let client = GitHubClient::builder()
+        .token("fake_token")
+        .base_url(&base_url)
+        .build()
+        .unwrap();
+
+    assert!(matches!(
+        fetch_team_with_org_fallback(&client, "acme", "gone", "2025-06-01"),
+        Err(GitHubError::NotFound(_))
+    ));
+    // Stored days of an enterprise are sent without the fallback tag
+    assert!(fallback_tags(&client, "acme").is_empty());
+
+    let paths: Vec<String> = server
+        .join()
+        .unwrap()
+        .iter()
+        .map(|request| request.path.split('?').next().unwrap().to_string())
+        .collect();
+    assert_eq!(
+        paths,
+        vec!["/enterprises/acme/team/gone/copilot/metrics", "/graphql"]
+    );
+}
+
+/// Team metrics and backfilled days of an unknown enterprise are fetched from
+/// the organization endpoints
+#[test]
+fn test_team_and_backfill_fall_back_to_org_endpoints() {
+    use crate::processors::backfill::{backfill_scope, BackfillCheckpoint, BackfillOptions};
+    use crate::processors::enterprise::{
+        fetch_team_with_org_fallback, with_org_fallback, ORG_FALLBACK_TAG,
+    };
+    use crate::processors::summary::ScopeSummary;
+    use chrono::NaiveDate;
+
+    let body = serde_json::to_string(&vec![create_test_metrics()]).unwrap();
+    let not_found = r#"{"message": "Not Found"}"#.to_string();
+    let (base_url, server) = mock_server::serve_with(7, move |_, request| {
+        if request.path == "/graphql" {
+            MockResponse::new(
+                "200 OK",
+                r#"{"data": {"enterprise": null, "organization": {"login": "acme"}}}"#.to_string(),
+            )
+        } else if request.path.starts_with("/enterprises/") {
+            MockResponse::new("404 Not Found", not_found.clone())
+        } else {
+            MockResponse::new("200 OK", body.clone())
+        }
+    });
+    let client = GitHubClient::builder()
+        .token("fake_token")
+        .base_url(&base_url)
+        .build()
+        .unwrap();
+
+    let (metrics, tags) =
+        fetch_team_with_org_fallback(&client, "acme", "web", "2025-06-01").unwrap();
+    assert_eq!(metrics.len(), 1);
+    assert_eq!(tags, vec![ORG_FALLBACK_TAG.to_string()]);
+
+    // A backfill of two days falls back for each of them
+    let path = std::env::temp_dir().join(format!(
+        "ghrust-backfill-fallback-{}.json",
+        std::process::id()
+    ));
+    std::fs::remove_file(&path).ok();
+    let options = BackfillOptions::new(
+        NaiveDate::from_ymd_opt(2025, 6, 1).unwrap(),
+        NaiveDate::from_ymd_opt(2025, 6, 2).unwrap(),
+    )
+    .with_day_delay(std::time::Duration::ZERO);
+    let mut checkpoint = BackfillCheckpoint::open(&path).unwrap();
+    let mut summary = ScopeSummary::new("enterprise:acme", "github.copilot");
+    let mut sent_tags = Vec::new();
+    let fallback = std::cell::RefCell::new(Vec::new());
+    let days = backfill_scope(
+        &options,
+        &mut checkpoint,
+        &mut summary,
+        |day| {
+            with_org_fallback(
+                &client,
+                "acme",
+                || client.fetch_enterprise_metrics_between("acme", day, day),
+                || client.fetch_org_metrics_between("acme", day, day),
+            )
+            .map(|(metrics, tags)| {
+                *fallback.borrow_mut() = tags;
+                metrics
+            })
+            .map_err(Into::into)
+        },
+        |_, _| {
+            sent_tags.push(fallback.borrow().clone());
+            Ok(())
+        },
+    )
+    .unwrap();
+    std::fs::remove_file(&path).ok();
+    assert_eq!(days, 2);
+    assert_eq!(sent_tags, vec![vec![ORG_FALLBACK_TAG.to_string()]; 2]);
+
+    // The ID is resolved once, after the first 404
+    let paths: Vec<String> = server
+        .join()
+        .unwrap()
+        .iter()
+        .map(|request| request.path.clone())
+        .collect();
+    assert_eq!(paths[1], "/graphql");
+    let paths: Vec<String> = paths.into_iter().filter(|p| p != "/graphql").collect();
+    assert_eq!(
+        paths[0],
+        "/enterprises/acme/team/web/copilot/metrics?since=2025-06-01"
+    );
+    assert_eq!(
+        paths[1],
+        "/orgs/acme/team/web/copilot/metrics?since=2025-06-01"
+    );
+    assert!(paths[2].starts_with("/enterprises/acme/copilot/metrics?since=2025-06-01"));
+    assert_eq!(
+        paths[3],
+        "/orgs/acme/copilot/metrics?since=2025-06-01&until=2025-06-01"
+    );
+    assert_eq!(
+        paths[5],
+        "/orgs/acme/copilot/metrics?since=2025-06-02&until=2025-06-02"
+    );
+}
+
+/// Configured enterprise IDs resolve to enterprises, organizations or neither
+#[test]
+fn test_resolve_account() {
+    use crate::processors::enterprise::check_enterprise_id;
+    use crate::services::github::AccountKind;
+
+    let not_found = |field: &str| {
+        format!(
+            r#"{{"type": "NOT_FOUND", "path": ["{}"], "message": "Could not resolve"}}"#,
+            field
+        )
+    };
+    let (base_url, server) = mock_server::serve(vec![
+        MockResponse::new(
+            "200 OK",
+            format!(
+                r#"{{"data": {{"enterprise": {{"slug": "acme"}}, "organization": null}},
+                    "errors": [{}]}}"#,
+                not_found("organization")
+            ),
+        ),
+        MockResponse::new(
+            "200 OK",
+            format!(
+                r#"{{"data": {{"enterprise": null, "organization": {{"login": "acme-web"}}}},
+                    "errors": [{}]}}"#,
+                not_found("enterprise")
+            ),
+        ),
+        MockResponse::new(
+            "200 OK",
+            format!(
+                r#"{{"data": {{"enterprise": null, "organization": null}}, "errors": [{}, {}]}}"#,
+                not_found("enterprise"),
+                not_found("organization")
+            ),
+        ),
+        MockResponse::new(
+            "200 OK",
+            r#"{"data": null, "errors": [{"type": "FORBIDDEN", "message": "No access"}]}"#
+                .to_string(),
+        ),
+    ]);
+    let client = GitHubClient::builder()
+        .token("fake_token")
+        .base_url(&base_url)
+        .build()
+        .unwrap();
+
+    assert_eq!(
+        client.resolve_account("acme").unwrap(),
+        AccountKind::Enterprise
+    );
+    assert_eq!(
+        client.resolve_account("acme-web").unwrap(),
+        AccountKind::Organization
+    );
+    // A numeric ID is rejected with an explanation of the expected slug
+    let error = check_enterprise_id(&client, "123456")
+        .unwrap_err()
+        .to_string();
+    assert!(error.contains("numeric enterprise ID"), "{}", error);
+    assert!(error.contains("github.com/enterprises/<slug>"), "{}", error);
+    // A lookup the token isn't allowed to make doesn't fail the run
+    assert!(check_enterprise_id(&client, "acme").is_ok());
+
+    assert!(server
+        .join()
+        .unwrap()
+        .iter()
+        .all(|request| request.path == "/graphql"));
+}
+
+/// An enterprise ID is resolved once per client
+#[test]
+fn test_enterprise_id_check_is_cached() {
+    use crate::processors::enterprise::check_enterprise_id;
+
+    let (base_url, requests) = mock_server::serve_forever(|_, _| {
+        MockResponse::new(
+            "200 OK",
+            r#"{"data": {"enterprise": {"slug": "acme-cached"}, "organization": null}}"#,
+        )
+    });
+    let client = GitHubClient::builder()
+        .token("fake_token")
+        .base_url(&base_url)
+        .build()
+        .unwrap();
+
+    assert!(check_enterprise_id(&client, "acme-cached").is_ok());
+    assert!(check_enterprise_id(&client.clone(), "acme-cached").is_ok());
+    assert_eq!(requests.load(std::sync::atomic::Ordering::SeqCst), 1);
+
+    // Another client, e.g. of a tenant with its own token, looks it up again
+    let tenant = GitHubClient::builder()
+        .token("tenant_token")
+        .base_url(&base_url)
+        .build()
+        .unwrap();
+    assert!(check_enterprise_id(&tenant, "acme-cached").is_ok());
+    assert_eq!(requests.load(std::sync::atomic::Ordering::SeqCst), 2);
+}
+
+/// Slugs with reserved characters stay within their path segment
+#[test]
+fn test_path_segments_are_percent_encoded() {
+    let body = serde_json::to_string(&vec![create_test_team_metrics()]).unwrap();
+    let (base_url, server) = mock_server::serve(vec![
+        MockResponse::new("200 OK", body.clone()),
+        MockResponse::new("200 OK", body.clone()),
+        MockResponse::new("200 OK", body.clone()),
+        MockResponse::new("200 OK", body),
+    ]);
+    let client = GitHubClient::builder()
+        .token("fake_token")
+        .base_url(&base_url)
+        .build()
+        .unwrap();
+
+    for (enterprise, team) in [
+        ("acme", "web/app"),
+        ("acme", "data science?"),
+        ("acme", "ops#1 & co"),
+    ] {
+        client
+            .fetch_team_metrics(enterprise, team, "2025-06-01")
+            .unwrap();
+    }
+    client
+        .fetch_enterprise_metrics("acme corp", "2025-06-01")
+        .unwrap();
+
+    let paths: Vec<String> = server
+        .join()
+        .unwrap()
+        .iter()
+        .map(|request| request.path.split('?').next().unwrap().to_string())
+        .collect();
+    assert_eq!(
+        paths,
+        vec![
+            "/enterprises/acme/team/web%2Fapp/copilot/metrics",
+            "/enterprises/acme/team/data%20science%3F/copilot/metrics",
+            "/enterprises/acme/team/ops%231%20%26%20co/copilot/metrics",
+            "/enterprises/acme%20corp/copilot/metrics",
+        ]
+    );
+}
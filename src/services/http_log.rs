@@ -0,0 +1,123 @@
+//! # HTTP Debug Logging
+//!
+//! Troubleshooting a deployment often comes down to what was actually sent to
+//! GitHub or Datadog and what came back. With `HTTP_DEBUG_LOG` set, the
+//! configuration's `AgentConfig` enables logging: every HTTP agent built from
+//! it logs the method, URL and headers of its requests and the status and
+//! headers of the responses, and the clients log the bodies they send and
+//! receive, truncated to `MAX_BODY_LENGTH` bytes.
+//!
+//! Credentials never reach the log: the values of `Authorization`,
+//! `DD-API-KEY`, `DD-APPLICATION-KEY` and the other `SECRET_HEADERS` are
+//! replaced before anything is written, so the output can be shared safely.
+
+use std::borrow::Cow;
+
+use tracing::info;
+
+/// Headers whose values are never logged (compared case-insensitively)
+pub const SECRET_HEADERS: &[&str] = &[
+    "authorization",
+    "dd-api-key",
+    "dd-application-key",
+    "x-vault-token",
+    "x-amz-security-token",
+];
+
+/// Number of body bytes logged at most
+pub const MAX_BODY_LENGTH: usize = 2048;
+
+/// Agent middleware logging the headers of every request and response
+///
+/// Installed by `AgentConfig::agent_builder` when logging is enabled. Responses with
+/// an error status are logged as well, before ureq turns them into errors.
+pub struct HttpLogger;
+
+impl ureq::Middleware for HttpLogger {
+    fn handle(
+        &self,
+        request: ureq::Request,
+        next: ureq::MiddlewareNext,
+    ) -> Result<ureq::Response, ureq::Error> {
+        let headers: Vec<String> = request
+            .header_names()
+            .iter()
+            .map(|name| format_header(name, request.header(name).unwrap_or_default()))
+            .collect();
+        info!(
+            "HTTP request: {} {} [{}]",
+            request.method(),
+            request.url(),
+            headers.join(", ")
+        );
+
+        let response = next.handle(request)?;
+        let headers: Vec<String> = response
+            .headers_names()
+            .iter()
+            .map(|name| format_header(name, response.header(name).unwrap_or_default()))
+            .collect();
+        info!(
+            "HTTP response: {} {} from {} [{}]",
+            response.status(),
+            response.status_text(),
+            response.get_url(),
+            headers.join(", ")
+        );
+        Ok(response)
+    }
+}
+
+/// Logs a request or response body
+///
+/// The clients call it only when their `AgentConfig` enables logging.
+///
+/// # Arguments
+///
+/// * `direction` - "request" or "response"
+/// * `url` - URL the body was sent to or received from
+/// * `body` - The body
+pub fn log_body(direction: &str, url: &str, body: &str) {
+    info!(
+        "HTTP {} body for {} ({} bytes): {}",
+        direction,
+        url,
+        body.len(),
+        truncate(body)
+    );
+}
+
+/// Formats a header for the log, hiding the value of secret headers
+///
+/// # Arguments
+///
+/// * `name` - Name of the header
+/// * `value` - Value of the header
+///
+/// # Returns
+///
+/// * `String` - "name: value", with the value of a secret header replaced
+///   by `<redacted>` (keeping the scheme of `Authorization`, e.g. "Bearer")
+pub fn format_header(name: &str, value: &str) -> String {
+    if !SECRET_HEADERS.iter().any(|h| h.eq_ignore_ascii_case(name)) {
+        return format!("{}: {}", name, value);
+    }
+    match value.split_once(' ') {
+        Some((scheme, _)) if name.eq_ignore_ascii_case("authorization") => {
+            format!("{}: {} <redacted>", name, scheme)
+        }
+        _ => format!("{}: <redacted>", name),
+    }
+}
+
+/// Truncates a body to `MAX_BODY_LENGTH` bytes at a character boundary
+pub fn truncate(body: &str) -> Cow<'_, str> {
+    if body.len() <= MAX_BODY_LENGTH {
+        return Cow::Borrowed(body);
+    }
+    let mut end = MAX_BODY_LENGTH;
+    while !body.is_char_boundary(end) {
+        end -= 1;
+    }
+    Cow::Owned(format!("{}... [truncated]", &body[..end]))
+}
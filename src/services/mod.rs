@@ -6,14 +6,35 @@
 //!
 //! ## Submodules
 //!
+//! * `aws` - Minimal, signed clients for the AWS APIs used directly by the
+//!   collector, such as Amazon SES for email digests.
+//!
 //! * `datadog` - Client and utilities for sending metrics to Datadog's monitoring service.
 //!   This module provides functionality to format, batch, and transmit GitHub Copilot
 //!   usage metrics to Datadog for visualization and analysis.
 //!
+//! * `export` - Optional exports of the fetched days as rows to destinations
+//!   outside Datadog, such as Google Sheets.
+//!
 //! * `github` - Client and utilities for fetching data from the GitHub API.
 //!   This module handles authentication, request formation, error handling, and response
 //!   parsing when communicating with GitHub's Copilot metrics endpoints.
 //!
+//! * `http_log` - Opt-in logging of HTTP requests and responses with the
+//!   credentials redacted, for troubleshooting production deployments.
+//!
+//! * `notify` - Notification channels (e.g. chat webhooks) for alerts that
+//!   don't rely on Datadog monitors.
+//!
+//! * `store` - Optional local persistence of fetched metrics days, with an
+//!   embedded SQLite backend behind the `sqlite` feature.
+//!
+//! * `vault` - Optional HashiCorp Vault secrets provider for the GitHub token
+//!   and the Datadog API key, with token renewal for the long-running server.
+//!
+//! * `tls` - Shared TLS configuration for the HTTP agents of both services,
+//!   including custom CA bundles for internal certificate authorities.
+//!
 //! ## Architecture
 //!
 //! The services in this module are designed to be:
@@ -26,6 +47,13 @@
 //! and report processed metrics to Datadog.
 
 // Generated by Github Copilot
+pub mod aws;
 pub mod datadog;
+pub mod export;
 pub mod github;
+pub mod http_log;
+pub mod notify;
+pub mod store;
+pub mod tls;
+pub mod vault;
 // Generated Code by Github Copilot ends here
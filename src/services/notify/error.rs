@@ -0,0 +1,24 @@
+//! # Notification Error Types
+//!
+//! This module defines structured error types for the notification sinks.
+
+use thiserror::Error;
+
+/// Errors that can occur when sending notifications
+#[derive(Error, Debug)]
+pub enum NotifyError {
+    /// Network or transport error
+    #[error("Network error: {0}")]
+    Network(String),
+
+    /// HTTP request error with status code
+    #[error("Notification HTTP error {0}: {1}")]
+    HttpError(u16, String),
+
+    /// Sending failed for one or more notifiers
+    #[error("Failed to notify: {}", .0.join("; "))]
+    Notifiers(Vec<String>),
+}
+
+/// A specialized Result type for notification operations
+pub type Result<T> = std::result::Result<T, NotifyError>;
@@ -0,0 +1,100 @@
+//! # Healthcheck Pinger
+//!
+//! Pings a dead man's switch such as healthchecks.io when a run starts and
+//! when it ends, so a missed or failed scheduled run raises an alert without
+//! any Datadog monitor. The URL is pinged the healthchecks.io way:
+//!
+//! * `{url}/start` when the run starts
+//! * `{url}` when every scope succeeded
+//! * `{url}/fail` when the run or any scope failed
+//!
+//! The end pings carry a short summary of the run as their body.
+
+use tracing::info;
+
+use super::error::{NotifyError, Result};
+use crate::services::tls::AgentConfig;
+
+/// Pings a healthcheck URL at the start and end of a run
+pub struct HealthcheckPinger {
+    /// Ping URL, without a trailing slash
+    url: String,
+    /// HTTP agent using the configured TLS settings
+    agent: ureq::Agent,
+}
+
+impl HealthcheckPinger {
+    /// Creates a pinger for a ping URL
+    ///
+    /// # Arguments
+    ///
+    /// * `url` - The ping URL, e.g. "https://hc-ping.com/<uuid>"
+    /// * `agent` - Settings of the HTTP agent
+    pub fn new(url: impl Into<String>, agent: &AgentConfig) -> Self {
+        Self {
+            url: url.into().trim_end_matches('/').to_string(),
+            agent: agent.agent_builder().build(),
+        }
+    }
+
+    /// Signals that the run started
+    pub fn start(&self) -> Result<()> {
+        self.ping(Some("start"), "")
+    }
+
+    /// Signals that the run succeeded
+    ///
+    /// # Arguments
+    ///
+    /// * `message` - Summary of the run
+    pub fn success(&self, message: &str) -> Result<()> {
+        self.ping(None, message)
+    }
+
+    /// Signals that the run failed
+    ///
+    /// # Arguments
+    ///
+    /// * `message` - What failed
+    pub fn failure(&self, message: &str) -> Result<()> {
+        self.ping(Some("fail"), message)
+    }
+
+    /// Posts a ping with a plain text body
+    ///
+    /// The URL isn't logged, since it is all it takes to send pings.
+    ///
+    /// # Arguments
+    ///
+    /// * `signal` - Path appended to the URL, or `None` for a success ping
+    /// * `body` - Body of the ping
+    fn ping(&self, signal: Option<&str>, body: &str) -> Result<()> {
+        let url = match signal {
+            Some(signal) => format!("{}/{}", self.url, signal),
+            None => self.url.clone(),
+        };
+        match self
+            .agent
+            .post(&url)
+            .set("Content-Type", "text/plain")
+            .send_string(body)
+        {
+            Ok(_) => {
+                info!(
+                    "Sent {} ping to the healthcheck",
+                    signal.unwrap_or("success")
+                );
+                Ok(())
+            }
+            Err(ureq::Error::Status(status, response)) => {
+                let body = response
+                    .into_string()
+                    .unwrap_or_else(|_| "Could not read response body".to_string());
+                Err(NotifyError::HttpError(status, body))
+            }
+            Err(ureq::Error::Transport(transport)) => {
+                Err(NotifyError::Network(transport.to_string()))
+            }
+        }
+    }
+}
@@ -0,0 +1,176 @@
+//! # Notifications
+//!
+//! Messages for the people operating the collector, such as threshold
+//! alerts, delivered without relying on Datadog monitors.
+//!
+//! ## Core Components
+//!
+//! * `Notifier` - Trait implemented by the notification channels
+//! * `NotificationSink` - Fan-out of a notification to every configured channel
+//! * `webhook` - Slack/Teams compatible incoming webhooks
+//! * `opsgenie` - Opsgenie alerts, created and closed by deduplication key
+//! * `healthcheck` - Start, success and failure pings to a dead man's switch
+//!   such as healthchecks.io
+
+mod error;
+pub mod healthcheck;
+pub mod opsgenie;
+pub mod webhook;
+
+#[cfg(test)]
+mod tests;
+
+use tracing::warn;
+
+pub use error::{NotifyError, Result};
+pub use healthcheck::HealthcheckPinger;
+pub use opsgenie::OpsgenieNotifier;
+pub use webhook::WebhookNotifier;
+
+/// What a notification is about, so channels can pick the ones they handle
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NotificationKind {
+    /// A condition that needs attention, e.g. a threshold alert
+    Alert,
+    /// A periodic report, e.g. the team leaderboard
+    Report,
+}
+
+/// A message for the people operating the collector
+#[derive(Debug, Clone, PartialEq)]
+pub struct Notification {
+    /// What the notification is about
+    pub kind: NotificationKind,
+    /// Short summary, e.g. "Copilot alert: active users dropped"
+    pub title: String,
+    /// Details of the notification
+    pub message: String,
+    /// Identifies the condition, so channels can deduplicate repeated
+    /// notifications about it
+    pub dedup_key: String,
+}
+
+/// A channel notifications are delivered through
+pub trait Notifier: Send + Sync {
+    /// Name used to identify the channel in logs and errors
+    fn name(&self) -> &str;
+
+    /// Delivers a notification
+    ///
+    /// # Arguments
+    ///
+    /// * `notification` - The notification to deliver
+    fn notify(&self, notification: &Notification) -> Result<()>;
+
+    /// Returns whether the channel delivers notifications of a kind
+    ///
+    /// Channels deliver every kind unless they override this.
+    ///
+    /// # Arguments
+    ///
+    /// * `kind` - Kind of the notification
+    fn accepts(&self, _kind: NotificationKind) -> bool {
+        true
+    }
+
+    /// Resolves an earlier notification whose condition no longer holds
+    ///
+    /// Channels without a notion of open alerts ignore this.
+    ///
+    /// # Arguments
+    ///
+    /// * `dedup_key` - Deduplication key of the earlier notification
+    fn resolve(&self, _dedup_key: &str) -> Result<()> {
+        Ok(())
+    }
+}
+
+/// Delivers notifications to every configured channel
+#[derive(Default)]
+pub struct NotificationSink {
+    notifiers: Vec<Box<dyn Notifier>>,
+}
+
+impl NotificationSink {
+    /// Creates a sink without channels
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a channel to the sink
+    ///
+    /// # Arguments
+    ///
+    /// * `notifier` - The channel to deliver to
+    pub fn with_notifier(mut self, notifier: impl Notifier + 'static) -> Self {
+        self.notifiers.push(Box::new(notifier));
+        self
+    }
+
+    /// Returns whether no channel is configured
+    pub fn is_empty(&self) -> bool {
+        self.notifiers.is_empty()
+    }
+
+    /// Delivers a notification to every channel
+    ///
+    /// Every channel is attempted, even if an earlier one fails.
+    ///
+    /// # Arguments
+    ///
+    /// * `notification` - The notification to deliver
+    ///
+    /// # Returns
+    ///
+    /// * `Result<()>` - Ok, or a `Notifiers` error listing the failed channels
+    pub fn notify(&self, notification: &Notification) -> Result<()> {
+        let mut failures = Vec::new();
+        for notifier in &self.notifiers {
+            if !notifier.accepts(notification.kind) {
+                continue;
+            }
+            if let Err(e) = notifier.notify(notification) {
+                warn!("Failed to notify {}: {}", notifier.name(), e);
+                failures.push(format!("{}: {}", notifier.name(), e));
+            }
+        }
+
+        if failures.is_empty() {
+            Ok(())
+        } else {
+            Err(NotifyError::Notifiers(failures))
+        }
+    }
+
+    /// Resolves an earlier notification on every channel
+    ///
+    /// Every channel is attempted, even if an earlier one fails.
+    ///
+    /// # Arguments
+    ///
+    /// * `dedup_key` - Deduplication key of the earlier notification
+    ///
+    /// # Returns
+    ///
+    /// * `Result<()>` - Ok, or a `Notifiers` error listing the failed channels
+    pub fn resolve(&self, dedup_key: &str) -> Result<()> {
+        let mut failures = Vec::new();
+        for notifier in &self.notifiers {
+            if let Err(e) = notifier.resolve(dedup_key) {
+                warn!(
+                    "Failed to resolve {} on {}: {}",
+                    dedup_key,
+                    notifier.name(),
+                    e
+                );
+                failures.push(format!("{}: {}", notifier.name(), e));
+            }
+        }
+
+        if failures.is_empty() {
+            Ok(())
+        } else {
+            Err(NotifyError::Notifiers(failures))
+        }
+    }
+}
@@ -0,0 +1,124 @@
+//! # Opsgenie Notifier
+//!
+//! Creates an Opsgenie alert for every fired threshold alert and closes it
+//! once its rule no longer fires. The notification's deduplication key is used
+//! as the Opsgenie alias, so repeated runs add to the same open alert instead
+//! of creating new ones. Reports such as the team leaderboard are not sent.
+
+use serde_json::{json, Value};
+use tracing::{debug, info};
+
+use super::error::{NotifyError, Result};
+use super::{Notification, NotificationKind, Notifier};
+use crate::services::aws::sigv4::uri_encode;
+use crate::services::tls::AgentConfig;
+
+/// Default Opsgenie API URL (US instance)
+pub const DEFAULT_OPSGENIE_API_URL: &str = "https://api.opsgenie.com";
+
+/// Maximum length of an Opsgenie alert message
+const MAX_MESSAGE_CHARS: usize = 130;
+
+/// Source reported with every alert
+const SOURCE: &str = "ghrust";
+
+/// Creates and closes Opsgenie alerts
+pub struct OpsgenieNotifier {
+    /// API key of an Opsgenie API integration
+    api_key: String,
+    /// Base URL of the Opsgenie API, without a trailing slash
+    api_url: String,
+    /// HTTP agent using the configured TLS settings
+    agent: ureq::Agent,
+}
+
+impl OpsgenieNotifier {
+    /// Creates a notifier for the US Opsgenie instance
+    ///
+    /// # Arguments
+    ///
+    /// * `api_key` - API key of an Opsgenie API integration
+    /// * `agent` - Settings of the HTTP agent
+    pub fn new(api_key: impl Into<String>, agent: &AgentConfig) -> Self {
+        Self {
+            api_key: api_key.into(),
+            api_url: DEFAULT_OPSGENIE_API_URL.to_string(),
+            agent: agent.agent_builder().build(),
+        }
+    }
+
+    /// Sets the API URL, e.g. "https://api.eu.opsgenie.com" for the EU instance
+    ///
+    /// # Arguments
+    ///
+    /// * `api_url` - Base URL of the Opsgenie API
+    pub fn with_api_url(mut self, api_url: impl Into<String>) -> Self {
+        self.api_url = api_url.into().trim_end_matches('/').to_string();
+        self
+    }
+
+    /// Posts a JSON request to the alerts API
+    fn post(&self, path: &str, body: Value) -> Result<()> {
+        match self
+            .agent
+            .post(&format!("{}{}", self.api_url, path))
+            .set("Authorization", &format!("GenieKey {}", self.api_key))
+            .send_json(body)
+        {
+            Ok(_) => Ok(()),
+            Err(ureq::Error::Status(status, response)) => {
+                let body = response
+                    .into_string()
+                    .unwrap_or_else(|_| "Could not read response body".to_string());
+                Err(NotifyError::HttpError(status, body))
+            }
+            Err(ureq::Error::Transport(transport)) => {
+                Err(NotifyError::Network(transport.to_string()))
+            }
+        }
+    }
+}
+
+impl Notifier for OpsgenieNotifier {
+    fn name(&self) -> &str {
+        "opsgenie"
+    }
+
+    fn accepts(&self, kind: NotificationKind) -> bool {
+        kind == NotificationKind::Alert
+    }
+
+    fn notify(&self, notification: &Notification) -> Result<()> {
+        let message: String = notification.title.chars().take(MAX_MESSAGE_CHARS).collect();
+        self.post(
+            "/v2/alerts",
+            json!({
+                "message": message,
+                "alias": notification.dedup_key,
+                "description": notification.message,
+                "source": SOURCE,
+            }),
+        )?;
+        info!(
+            "Created Opsgenie alert \"{}\" ({})",
+            notification.title, notification.dedup_key
+        );
+        Ok(())
+    }
+
+    fn resolve(&self, dedup_key: &str) -> Result<()> {
+        let path = format!(
+            "/v2/alerts/{}/close?identifierType=alias",
+            uri_encode(dedup_key, true)
+        );
+        match self.post(&path, json!({ "source": SOURCE })) {
+            // Nothing to close if no alert is open for the key
+            Err(NotifyError::HttpError(404, _)) => Ok(()),
+            Err(e) => Err(e),
+            Ok(()) => {
+                debug!("Closed Opsgenie alert {}, if open", dedup_key);
+                Ok(())
+            }
+        }
+    }
+}
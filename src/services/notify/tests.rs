@@ -0,0 +1,164 @@
+//! # Notification Tests
+//!
+//! Tests for the notification fan-out and the webhook payload.
+
+use std::sync::{Arc, Mutex};
+
+use super::{
+    HealthcheckPinger, Notification, NotificationKind, NotificationSink, Notifier, NotifyError,
+    OpsgenieNotifier, Result, WebhookNotifier,
+};
+use crate::services::tls::AgentConfig;
+use crate::tests::mock_server::{self, MockResponse};
+
+/// Records the titles it is notified about, or fails every notification
+struct TestNotifier {
+    name: &'static str,
+    fail: bool,
+    received: Arc<Mutex<Vec<String>>>,
+}
+
+impl Notifier for TestNotifier {
+    fn name(&self) -> &str {
+        self.name
+    }
+
+    fn notify(&self, notification: &Notification) -> Result<()> {
+        if self.fail {
+            return Err(NotifyError::HttpError(500, "down".to_string()));
+        }
+        self.received
+            .lock()
+            .unwrap()
+            .push(notification.title.clone());
+        Ok(())
+    }
+}
+
+fn notification() -> Notification {
+    Notification {
+        kind: NotificationKind::Alert,
+        title: "Copilot alert for enterprise:acme: active_users".to_string(),
+        message: "active_users was 10.0 on 2025-06-02 (below 50)".to_string(),
+        dedup_key: "enterprise:acme:active_users".to_string(),
+    }
+}
+
+#[test]
+fn failing_notifier_does_not_stop_the_others() {
+    let received = Arc::new(Mutex::new(Vec::new()));
+    let sink = NotificationSink::new()
+        .with_notifier(TestNotifier {
+            name: "broken",
+            fail: true,
+            received: Arc::clone(&received),
+        })
+        .with_notifier(TestNotifier {
+            name: "chat",
+            fail: false,
+            received: Arc::clone(&received),
+        });
+
+    match sink.notify(&notification()) {
+        Err(NotifyError::Notifiers(failures)) => {
+            assert_eq!(failures.len(), 1);
+            assert!(failures[0].starts_with("broken: "));
+        }
+        other => panic!("expected a Notifiers error, got {:?}", other),
+    }
+    assert_eq!(received.lock().unwrap().len(), 1);
+}
+
+#[test]
+fn webhook_posts_text_payload() {
+    let (base_url, server) = mock_server::serve(vec![MockResponse::new("200 OK", "ok")]);
+    let url = format!("{}/hook", base_url);
+
+    WebhookNotifier::new(url, &AgentConfig::default())
+        .notify(&notification())
+        .unwrap();
+
+    let body = server.join().unwrap()[0].json();
+    assert_eq!(
+        body["text"],
+        "Copilot alert for enterprise:acme: active_users\nactive_users was 10.0 on 2025-06-02 (below 50)"
+    );
+}
+
+#[test]
+fn healthcheck_pings_start_success_and_failure() {
+    let (base_url, server) = mock_server::serve(vec![MockResponse::new("200 OK", "OK"); 3]);
+    let url = format!("{}/ping/abc/", base_url);
+
+    let pinger = HealthcheckPinger::new(url, &AgentConfig::default());
+    pinger.start().unwrap();
+    pinger.success("3 scope(s) processed").unwrap();
+    pinger.failure("team:acme/web: down").unwrap();
+
+    let requests: Vec<(String, String)> = server
+        .join()
+        .unwrap()
+        .iter()
+        .map(|request| (request.line(), request.text()))
+        .collect();
+    assert_eq!(
+        requests,
+        vec![
+            ("POST /ping/abc/start HTTP/1.1".to_string(), String::new()),
+            (
+                "POST /ping/abc HTTP/1.1".to_string(),
+                "3 scope(s) processed".to_string()
+            ),
+            (
+                "POST /ping/abc/fail HTTP/1.1".to_string(),
+                "team:acme/web: down".to_string()
+            ),
+        ]
+    );
+}
+
+#[test]
+fn opsgenie_creates_and_closes_alerts_by_alias() {
+    let (url, server) = mock_server::serve(vec![
+        MockResponse::new("202 Accepted", "{}"),
+        MockResponse::new("404 Not Found", "{}"),
+    ]);
+
+    let notifier =
+        OpsgenieNotifier::new("og-key", &AgentConfig::default()).with_api_url(format!("{}/", url));
+    assert!(notifier.accepts(NotificationKind::Alert));
+    assert!(!notifier.accepts(NotificationKind::Report));
+    notifier.notify(&notification()).unwrap();
+    // Closing an alert that isn't open is not an error
+    notifier.resolve("team:acme/web:active_users").unwrap();
+
+    let requests = server.join().unwrap();
+    let alert = requests[0].json();
+    assert_eq!(requests[0].line(), "POST /v2/alerts HTTP/1.1");
+    assert_eq!(requests[0].header("authorization"), Some("GenieKey og-key"));
+    assert_eq!(alert["alias"], "enterprise:acme:active_users");
+    assert_eq!(
+        alert["message"],
+        "Copilot alert for enterprise:acme: active_users"
+    );
+    assert_eq!(
+        alert["description"],
+        "active_users was 10.0 on 2025-06-02 (below 50)"
+    );
+    assert_eq!(
+        requests[1].line(),
+        "POST /v2/alerts/team%3Aacme%2Fweb%3Aactive_users/close?identifierType=alias HTTP/1.1"
+    );
+}
+
+#[test]
+fn reports_skip_channels_that_only_take_alerts() {
+    let sink = NotificationSink::new().with_notifier(
+        OpsgenieNotifier::new("og-key", &AgentConfig::default()).with_api_url("http://127.0.0.1:9"),
+    );
+    let report = Notification {
+        kind: NotificationKind::Report,
+        ..notification()
+    };
+    assert!(sink.notify(&report).is_ok());
+}
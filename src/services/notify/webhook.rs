@@ -0,0 +1,64 @@
+//! # Webhook Notifier
+//!
+//! Posts notifications as `{"text": "..."}` JSON, the format accepted by
+//! Slack and Microsoft Teams incoming webhooks and most chat tools.
+
+use serde_json::json;
+use tracing::info;
+
+use super::error::{NotifyError, Result};
+use super::{Notification, Notifier};
+use crate::services::tls::AgentConfig;
+
+/// Sends notifications to an incoming webhook
+pub struct WebhookNotifier {
+    /// Webhook URL
+    url: String,
+    /// HTTP agent using the configured TLS settings
+    agent: ureq::Agent,
+}
+
+impl WebhookNotifier {
+    /// Creates a notifier for a webhook URL
+    ///
+    /// # Arguments
+    ///
+    /// * `url` - The incoming webhook URL
+    /// * `agent` - Settings of the HTTP agent
+    pub fn new(url: impl Into<String>, agent: &AgentConfig) -> Self {
+        Self {
+            url: url.into(),
+            agent: agent.agent_builder().build(),
+        }
+    }
+}
+
+impl Notifier for WebhookNotifier {
+    fn name(&self) -> &str {
+        "webhook"
+    }
+
+    fn notify(&self, notification: &Notification) -> Result<()> {
+        let text = format!("{}\n{}", notification.title, notification.message);
+
+        match self
+            .agent
+            .post(&self.url)
+            .send_json(json!({ "text": text }))
+        {
+            Ok(_) => {
+                info!("Sent notification \"{}\" to webhook", notification.title);
+                Ok(())
+            }
+            Err(ureq::Error::Status(status, response)) => {
+                let body = response
+                    .into_string()
+                    .unwrap_or_else(|_| "Could not read response body".to_string());
+                Err(NotifyError::HttpError(status, body))
+            }
+            Err(ureq::Error::Transport(transport)) => {
+                Err(NotifyError::Network(transport.to_string()))
+            }
+        }
+    }
+}
@@ -0,0 +1,28 @@
+//! # Store Error Types
+//!
+//! This module defines structured error types for the metrics store.
+
+use thiserror::Error;
+
+/// Errors that can occur when persisting or loading metrics
+#[derive(Error, Debug)]
+pub enum StoreError {
+    /// The store could not be opened
+    #[error("Error opening metrics store {0}: {1}")]
+    Open(String, String),
+
+    /// A query failed
+    #[error("Metrics store error: {0}")]
+    Database(String),
+
+    /// A stored day could not be serialized or deserialized
+    #[error("Metrics serialization error: {0}")]
+    Serialization(String),
+
+    /// The store backend is not compiled in
+    #[error("Metrics store not supported: {0}")]
+    Unsupported(String),
+}
+
+/// A specialized Result type for store operations
+pub type Result<T> = std::result::Result<T, StoreError>;
@@ -0,0 +1,389 @@
+//! # Metrics Store
+//!
+//! Optional local persistence of every fetched `CopilotMetrics` day. Keeping
+//! the raw days around allows offline analysis, deduplication decisions and
+//! comparisons between runs without fetching the window from GitHub again.
+//!
+//! Days are keyed by scope (the same identifiers as the run summary, e.g.
+//! "enterprise:acme" or "team:acme/platform") and date; storing a day again
+//! replaces the earlier copy.
+//!
+//! With reuse enabled the store also acts as a cache of GitHub responses:
+//! `fetch_missing` serves the days of the window that are already stored and
+//! only asks GitHub for the window from the first missing day on, so re-runs
+//! and backfills don't fetch complete days again.
+//!
+//! ## Core Components
+//!
+//! * `MetricsStore` - Trait implemented by the store backends
+//! * `sqlite` - Embedded SQLite backend (requires the `sqlite` feature)
+//! * `s3` - Backend keeping the days in an S3 bucket (`s3://<bucket>/<prefix>`)
+//! * `Store` - Handle of the configured backend, passed to the processors by
+//!   the pipeline
+//! * `load_range` - Stored days of a scope within a date range, for the
+//!   query endpoints of the server
+//! * `sent_this_week` / `mark_sent_this_week` - Markers keeping weekly
+//!   deliveries to one per week
+
+mod error;
+pub mod s3;
+#[cfg(feature = "sqlite")]
+pub mod sqlite;
+
+#[cfg(test)]
+mod tests;
+
+use std::collections::{BTreeMap, HashSet};
+use std::fmt;
+use std::sync::Arc;
+
+use chrono::{Datelike, Duration, NaiveDate, Utc};
+use tracing::{debug, info, warn};
+
+use crate::models::github::CopilotMetrics;
+use crate::services::tls::AgentConfig;
+pub use error::{Result, StoreError};
+
+/// Persistence of fetched metrics days
+pub trait MetricsStore: Send + Sync {
+    /// Stores metrics days of a scope, replacing days already stored
+    ///
+    /// # Arguments
+    ///
+    /// * `scope` - Scope the metrics belong to, e.g. "enterprise:acme"
+    /// * `metrics` - The days to store
+    ///
+    /// # Returns
+    ///
+    /// * `Result<usize>` - Number of days stored
+    fn save(&self, scope: &str, metrics: &[CopilotMetrics]) -> Result<usize>;
+
+    /// Loads all stored days of a scope, oldest first
+    fn load(&self, scope: &str) -> Result<Vec<CopilotMetrics>>;
+
+    /// Returns the most recent stored date of a scope, if any
+    fn latest_date(&self, scope: &str) -> Result<Option<String>>;
+
+    /// Lists the scopes with stored days, sorted
+    fn scopes(&self) -> Result<Vec<String>>;
+
+    /// Returns the value of a marker, if it was set
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - Name of the marker, e.g. "digest:acme"
+    fn marker(&self, name: &str) -> Result<Option<String>>;
+
+    /// Sets the value of a marker, replacing the earlier value
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - Name of the marker, e.g. "digest:acme"
+    /// * `value` - The new value
+    fn set_marker(&self, name: &str, value: &str) -> Result<()>;
+}
+
+/// The metrics store of a run, if one is configured
+///
+/// Cloning the handle shares the backend, so a server can keep one open
+/// store for every run and query.
+#[derive(Clone, Default)]
+pub struct Store {
+    /// The backend, `None` if persistence is disabled
+    backend: Option<Arc<dyn MetricsStore>>,
+    /// Whether `fetch_missing` serves stored days instead of fetching them
+    /// again
+    reuse: bool,
+}
+
+impl fmt::Debug for Store {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Store")
+            .field("configured", &self.backend.is_some())
+            .field("reuse", &self.reuse)
+            .finish()
+    }
+}
+
+impl Store {
+    /// Opens the store at a path or S3 location
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - Path of the SQLite database file or `s3://<bucket>/<prefix>`
+    ///   location, or `None` to disable persistence
+    /// * `reuse` - Whether `fetch_missing` serves stored days instead of
+    ///   fetching them again
+    /// * `agent` - Settings of the HTTP agent of an S3 location
+    ///
+    /// # Returns
+    ///
+    /// * `Result<Self>` - The store, or an error if it can't be opened
+    pub fn open(path: Option<&str>, reuse: bool, agent: &AgentConfig) -> Result<Self> {
+        let backend = match path {
+            Some(path) => Some(Arc::from(open(path, agent)?)),
+            None => None,
+        };
+        Ok(Self { backend, reuse })
+    }
+
+    /// Creates a store with an already opened backend
+    ///
+    /// # Arguments
+    ///
+    /// * `backend` - The store backend
+    /// * `reuse` - Whether `fetch_missing` serves stored days instead of
+    ///   fetching them again
+    pub fn new(backend: Arc<dyn MetricsStore>, reuse: bool) -> Self {
+        Self {
+            backend: Some(backend),
+            reuse,
+        }
+    }
+
+    /// Returns the backend, if persistence is enabled
+    pub fn backend(&self) -> Option<&dyn MetricsStore> {
+        self.backend.as_deref()
+    }
+
+    /// Stores fetched metrics days, logging failures
+    ///
+    /// # Arguments
+    ///
+    /// * `scope` - Scope the metrics belong to, e.g. "enterprise:acme"
+    /// * `metrics` - The fetched days
+    pub fn persist(&self, scope: &str, metrics: &[CopilotMetrics]) {
+        let Some(store) = self.backend() else {
+            return;
+        };
+        match store.save(scope, metrics) {
+            Ok(saved) => debug!("Stored {} days of metrics for {}", saved, scope),
+            Err(e) => warn!("Failed to store metrics for {}: {}", scope, e),
+        }
+    }
+
+    /// Loads the stored days of a scope from a day on
+    ///
+    /// Serves the window of a scope GitHub reported as unchanged. Without a
+    /// backend, or if it can't be read, the window is empty.
+    ///
+    /// # Arguments
+    ///
+    /// * `scope` - Scope the metrics belong to, e.g. "enterprise:acme"
+    /// * `since` - First day of the window (YYYY-MM-DD)
+    pub fn window(&self, scope: &str, since: &str) -> Vec<CopilotMetrics> {
+        let Some(store) = self.backend() else {
+            return Vec::new();
+        };
+        match store.load(scope) {
+            Ok(days) => days
+                .into_iter()
+                .filter(|day| day.date.as_str() >= since)
+                .collect(),
+            Err(e) => {
+                warn!("Could not read stored days of {}: {}", scope, e);
+                Vec::new()
+            }
+        }
+    }
+
+    /// Fetches the metrics window of a scope, serving the days already stored
+    ///
+    /// Without a backend or with reuse disabled, the whole window is fetched.
+    /// With reuse enabled, `fetch` is called from the first day between
+    /// `since` and yesterday (UTC) that isn't stored, and not at all if every
+    /// day is stored; a store that can't be read falls back to fetching the
+    /// whole window.
+    ///
+    /// # Arguments
+    ///
+    /// * `scope` - Scope the metrics belong to, e.g. "enterprise:acme"
+    /// * `since` - First day of the window (YYYY-MM-DD)
+    /// * `fetch` - Fetches the window from a given day on from GitHub
+    ///
+    /// # Returns
+    ///
+    /// * `Result<Vec<CopilotMetrics>, E>` - The days of the window, oldest
+    ///   first, or the error of `fetch`
+    pub fn fetch_missing<E>(
+        &self,
+        scope: &str,
+        since: &str,
+        fetch: impl FnOnce(&str) -> std::result::Result<Vec<CopilotMetrics>, E>,
+    ) -> std::result::Result<Vec<CopilotMetrics>, E> {
+        match self.backend().filter(|_| self.reuse) {
+            Some(store) => {
+                let yesterday = Utc::now().date_naive() - Duration::days(1);
+                fetch_missing_from(store, scope, since, yesterday, fetch)
+            }
+            None => fetch(since),
+        }
+    }
+}
+
+/// Returns the ISO week of a day, e.g. "2025-W23"
+fn iso_week(day: NaiveDate) -> String {
+    let week = day.iso_week();
+    format!("{}-W{:02}", week.year(), week.week())
+}
+
+/// Returns whether a weekly delivery was already sent in the week of a day
+///
+/// The collector runs several times a day, so deliveries scheduled for a
+/// weekday (the digest, the leaderboard) record the ISO week they were sent
+/// in as a marker of the store, which `ghrust validate` therefore requires.
+/// Without a store every run of the weekday sends; a store that can't be
+/// read is treated as not sent.
+///
+/// # Arguments
+///
+/// * `store` - Store holding the markers, if configured
+/// * `name` - Name of the delivery's marker, e.g. "digest:acme"
+/// * `today` - Day of the run
+pub fn sent_this_week(store: Option<&dyn MetricsStore>, name: &str, today: NaiveDate) -> bool {
+    let Some(store) = store else {
+        return false;
+    };
+    match store.marker(name) {
+        Ok(week) => week.as_deref() == Some(iso_week(today).as_str()),
+        Err(e) => {
+            warn!("Could not read marker {}: {}", name, e);
+            false
+        }
+    }
+}
+
+/// Records that a weekly delivery was sent in the week of a day
+///
+/// Failures are logged and never fail the delivery.
+///
+/// # Arguments
+///
+/// * `store` - Store holding the markers, if configured
+/// * `name` - Name of the delivery's marker, e.g. "digest:acme"
+/// * `today` - Day of the run
+pub fn mark_sent_this_week(store: Option<&dyn MetricsStore>, name: &str, today: NaiveDate) {
+    let Some(store) = store else {
+        return;
+    };
+    if let Err(e) = store.set_marker(name, &iso_week(today)) {
+        warn!("Could not record marker {}: {}", name, e);
+    }
+}
+
+/// Loads the stored days of a scope within a date range
+///
+/// # Arguments
+///
+/// * `store` - Store holding the days
+/// * `scope` - Scope the metrics belong to, e.g. "enterprise:acme"
+/// * `from` - First day (inclusive), unbounded if `None`
+/// * `to` - Last day (inclusive), unbounded if `None`
+///
+/// # Returns
+///
+/// * `Result<Vec<CopilotMetrics>>` - The stored days of the range, oldest
+///   first
+pub fn load_range(
+    store: &dyn MetricsStore,
+    scope: &str,
+    from: Option<NaiveDate>,
+    to: Option<NaiveDate>,
+) -> Result<Vec<CopilotMetrics>> {
+    let from = from.map(|date| date.format("%Y-%m-%d").to_string());
+    let to = to.map(|date| date.format("%Y-%m-%d").to_string());
+    Ok(store
+        .load(scope)?
+        .into_iter()
+        .filter(|day| from.as_ref().is_none_or(|from| &day.date >= from))
+        .filter(|day| to.as_ref().is_none_or(|to| &day.date <= to))
+        .collect())
+}
+
+/// Fetches the days of a window missing from a store
+///
+/// # Arguments
+///
+/// * `store` - Store holding the previously fetched days
+/// * `scope` - Scope the metrics belong to
+/// * `since` - First day of the window (YYYY-MM-DD)
+/// * `until` - Last day of the window that should be stored
+/// * `fetch` - Fetches the window from a given day on
+pub(crate) fn fetch_missing_from<E>(
+    store: &dyn MetricsStore,
+    scope: &str,
+    since: &str,
+    until: NaiveDate,
+    fetch: impl FnOnce(&str) -> std::result::Result<Vec<CopilotMetrics>, E>,
+) -> std::result::Result<Vec<CopilotMetrics>, E> {
+    let (Ok(start), Ok(stored)) = (
+        NaiveDate::parse_from_str(since, "%Y-%m-%d"),
+        store.load(scope),
+    ) else {
+        warn!(
+            "Could not read stored days of {}, fetching the window",
+            scope
+        );
+        return fetch(since);
+    };
+
+    let mut days: BTreeMap<String, CopilotMetrics> = stored
+        .into_iter()
+        .filter(|day| day.date.as_str() >= since)
+        .map(|day| (day.date.clone(), day))
+        .collect();
+    let stored_dates: HashSet<NaiveDate> = days
+        .keys()
+        .filter_map(|date| NaiveDate::parse_from_str(date, "%Y-%m-%d").ok())
+        .collect();
+
+    let Some(first_missing) = start
+        .iter_days()
+        .take_while(|day| *day <= until)
+        .find(|day| !stored_dates.contains(day))
+    else {
+        info!(
+            "All {} days of {} are stored, not fetching them again",
+            days.len(),
+            scope
+        );
+        return Ok(days.into_values().collect());
+    };
+
+    let first_missing = first_missing.format("%Y-%m-%d").to_string();
+    if first_missing != since {
+        info!(
+            "Serving {} stored days of {}, fetching from {}",
+            days.range(..first_missing.clone()).count(),
+            scope,
+            first_missing
+        );
+    }
+    for day in fetch(&first_missing)? {
+        days.insert(day.date.clone(), day);
+    }
+    Ok(days.into_values().collect())
+}
+
+/// Opens the store backend for a database path or S3 location
+fn open(path: &str, agent: &AgentConfig) -> Result<Box<dyn MetricsStore>> {
+    if path.starts_with("s3://") {
+        return Ok(Box::new(s3::S3Store::open(path, agent)?));
+    }
+    open_file(path)
+}
+
+/// Opens the store backend for a database path
+#[cfg(feature = "sqlite")]
+fn open_file(path: &str) -> Result<Box<dyn MetricsStore>> {
+    Ok(Box::new(sqlite::SqliteStore::open(path)?))
+}
+
+/// Opens the store backend for a database path
+#[cfg(not(feature = "sqlite"))]
+fn open_file(path: &str) -> Result<Box<dyn MetricsStore>> {
+    Err(StoreError::Unsupported(format!(
+        "{} requires a build with the `sqlite` feature",
+        path
+    )))
+}
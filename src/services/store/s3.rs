@@ -0,0 +1,180 @@
+//! # S3 Store
+//!
+//! `MetricsStore` backend keeping the days of every scope in one JSON object
+//! of an S3 bucket, so the stored days survive Lambda invocations without a
+//! mounted file system. The store is selected with a path of the form
+//! `s3://<bucket>/<prefix>`; the days of a scope live at
+//! `<prefix>/<scope>.json` (with `:` in the scope replaced by `/`, e.g.
+//! `<prefix>/team/acme/platform.json`), keyed by date, and
+//! `<prefix>/scopes.json` lists the stored scopes. Markers are kept in
+//! `<prefix>/markers.json`.
+//!
+//! The execution role needs `s3:GetObject` and `s3:PutObject` on the prefix
+//! and `s3:ListBucket` on the bucket.
+
+use std::collections::BTreeMap;
+use std::sync::Mutex;
+
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use serde_json::Value;
+
+use super::{MetricsStore, Result, StoreError};
+use crate::models::github::CopilotMetrics;
+use crate::services::aws::{AwsCredentials, AwsError, S3Client};
+use crate::services::tls::AgentConfig;
+
+/// Name of the object listing the stored scopes
+const SCOPES_OBJECT: &str = "scopes.json";
+
+/// Name of the object holding the markers, keyed by name
+const MARKERS_OBJECT: &str = "markers.json";
+
+/// Metrics store backed by objects in an S3 bucket
+pub struct S3Store {
+    client: S3Client,
+    /// Key prefix of the objects, without trailing slash
+    prefix: String,
+    /// Serializes the read-modify-write cycles of `save` and `set_marker`
+    writes: Mutex<()>,
+}
+
+impl S3Store {
+    /// Opens a store at an `s3://<bucket>/<prefix>` location
+    ///
+    /// No request is made until days are loaded or saved.
+    ///
+    /// # Arguments
+    ///
+    /// * `location` - Bucket and key prefix of the store
+    /// * `agent` - Settings of the HTTP agent
+    ///
+    /// # Returns
+    ///
+    /// * `Result<S3Store>` - The store, or an error if the location is invalid
+    ///   or the AWS credentials are not available
+    pub fn open(location: &str, agent: &AgentConfig) -> Result<Self> {
+        let (bucket, prefix) = parse_location(location)
+            .ok_or_else(|| StoreError::Open(location.to_string(), "no bucket".to_string()))?;
+        let credentials = AwsCredentials::from_env()
+            .map_err(|e| StoreError::Open(location.to_string(), e.to_string()))?;
+        Ok(Self {
+            client: S3Client::new(credentials, bucket, agent),
+            prefix: prefix.to_string(),
+            writes: Mutex::new(()),
+        })
+    }
+
+    /// Returns the key of an object below the prefix
+    fn key(&self, name: &str) -> String {
+        if self.prefix.is_empty() {
+            name.to_string()
+        } else {
+            format!("{}/{}", self.prefix, name)
+        }
+    }
+
+    /// Reads a JSON object, or `None` if it doesn't exist
+    fn read<T: DeserializeOwned>(&self, key: &str) -> Result<Option<T>> {
+        let Some(body) = self.client.get_object(key).map_err(request_error)? else {
+            return Ok(None);
+        };
+        serde_json::from_slice(&body)
+            .map(Some)
+            .map_err(|e| StoreError::Serialization(format!("{}: {}", key, e)))
+    }
+
+    /// Writes a JSON object, replacing any existing one
+    fn write<T: Serialize>(&self, key: &str, value: &T) -> Result<()> {
+        let body =
+            serde_json::to_vec(value).map_err(|e| StoreError::Serialization(e.to_string()))?;
+        self.client
+            .put_object(key, &body, "application/json")
+            .map_err(request_error)
+    }
+
+    /// Reads the stored days of a scope, keyed by date
+    fn days(&self, scope: &str) -> Result<BTreeMap<String, Value>> {
+        Ok(self
+            .read(&self.key(&scope_object(scope)))?
+            .unwrap_or_default())
+    }
+}
+
+impl MetricsStore for S3Store {
+    fn save(&self, scope: &str, metrics: &[CopilotMetrics]) -> Result<usize> {
+        let _guard = self.writes.lock().unwrap_or_else(|e| e.into_inner());
+
+        let mut days = self.days(scope)?;
+        let is_new = days.is_empty();
+        for metric in metrics {
+            let payload = serde_json::to_value(metric)
+                .map_err(|e| StoreError::Serialization(e.to_string()))?;
+            days.insert(metric.date.clone(), payload);
+        }
+        self.write(&self.key(&scope_object(scope)), &days)?;
+
+        if is_new {
+            let mut scopes = self.scopes()?;
+            if !scopes.iter().any(|s| s == scope) {
+                scopes.push(scope.to_string());
+                scopes.sort();
+                self.write(&self.key(SCOPES_OBJECT), &scopes)?;
+            }
+        }
+        Ok(metrics.len())
+    }
+
+    fn load(&self, scope: &str) -> Result<Vec<CopilotMetrics>> {
+        self.days(scope)?
+            .into_values()
+            .map(|payload| {
+                serde_json::from_value(payload)
+                    .map_err(|e| StoreError::Serialization(e.to_string()))
+            })
+            .collect()
+    }
+
+    fn latest_date(&self, scope: &str) -> Result<Option<String>> {
+        Ok(self.days(scope)?.into_keys().next_back())
+    }
+
+    fn scopes(&self) -> Result<Vec<String>> {
+        Ok(self.read(&self.key(SCOPES_OBJECT))?.unwrap_or_default())
+    }
+
+    fn marker(&self, name: &str) -> Result<Option<String>> {
+        let markers: BTreeMap<String, String> =
+            self.read(&self.key(MARKERS_OBJECT))?.unwrap_or_default();
+        Ok(markers.get(name).cloned())
+    }
+
+    fn set_marker(&self, name: &str, value: &str) -> Result<()> {
+        let _guard = self.writes.lock().unwrap_or_else(|e| e.into_inner());
+
+        let key = self.key(MARKERS_OBJECT);
+        let mut markers: BTreeMap<String, String> = self.read(&key)?.unwrap_or_default();
+        markers.insert(name.to_string(), value.to_string());
+        self.write(&key, &markers)
+    }
+}
+
+/// Splits an `s3://<bucket>/<prefix>` location into bucket and prefix
+pub(crate) fn parse_location(location: &str) -> Option<(&str, &str)> {
+    let rest = location.strip_prefix("s3://")?;
+    let (bucket, prefix) = rest.split_once('/').unwrap_or((rest, ""));
+    if bucket.is_empty() {
+        return None;
+    }
+    Some((bucket, prefix.trim_matches('/')))
+}
+
+/// Returns the object name of a scope's days, e.g. "team/acme/platform.json"
+pub(super) fn scope_object(scope: &str) -> String {
+    format!("{}.json", scope.replace(':', "/"))
+}
+
+/// Converts a failed S3 request into a `StoreError`
+fn request_error(e: AwsError) -> StoreError {
+    StoreError::Database(e.to_string())
+}
@@ -0,0 +1,156 @@
+//! # SQLite Store
+//!
+//! Embedded `MetricsStore` backend keeping each day as a JSON document in a
+//! single SQLite table, keyed by scope and date. The JSON round-trip keeps
+//! fields the models don't know yet, so stored days can be re-processed once
+//! the models catch up.
+
+use std::sync::Mutex;
+
+use chrono::Utc;
+use rusqlite::{params, Connection, OptionalExtension};
+
+use super::{MetricsStore, Result, StoreError};
+use crate::models::github::CopilotMetrics;
+
+/// Schema of the store, created on open
+const SCHEMA: &str = "CREATE TABLE IF NOT EXISTS copilot_metrics (
+    scope TEXT NOT NULL,
+    date TEXT NOT NULL,
+    payload TEXT NOT NULL,
+    fetched_at TEXT NOT NULL,
+    PRIMARY KEY (scope, date)
+)";
+
+/// Table of the markers, created on open
+const MARKERS_SCHEMA: &str = "CREATE TABLE IF NOT EXISTS markers (
+    name TEXT PRIMARY KEY,
+    value TEXT NOT NULL
+)";
+
+/// Metrics store backed by a SQLite database
+pub struct SqliteStore {
+    conn: Mutex<Connection>,
+}
+
+impl SqliteStore {
+    /// Opens (and creates if needed) a store at a database path
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - Path of the SQLite database file
+    ///
+    /// # Returns
+    ///
+    /// * `Result<SqliteStore>` - The store, or an error if the database can't
+    ///   be opened or initialized
+    pub fn open(path: &str) -> Result<Self> {
+        let conn = Connection::open(path)
+            .map_err(|e| StoreError::Open(path.to_string(), e.to_string()))?;
+        Self::with_connection(conn)
+    }
+
+    /// Opens a store that lives in memory only, mainly for tests
+    pub fn open_in_memory() -> Result<Self> {
+        let conn = Connection::open_in_memory()
+            .map_err(|e| StoreError::Open(":memory:".to_string(), e.to_string()))?;
+        Self::with_connection(conn)
+    }
+
+    fn with_connection(conn: Connection) -> Result<Self> {
+        conn.execute(SCHEMA, []).map_err(database_error)?;
+        conn.execute(MARKERS_SCHEMA, []).map_err(database_error)?;
+        Ok(Self {
+            conn: Mutex::new(conn),
+        })
+    }
+
+    fn conn(&self) -> std::sync::MutexGuard<'_, Connection> {
+        self.conn.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+impl MetricsStore for SqliteStore {
+    fn save(&self, scope: &str, metrics: &[CopilotMetrics]) -> Result<usize> {
+        let fetched_at = Utc::now().to_rfc3339();
+        let mut conn = self.conn();
+        let tx = conn.transaction().map_err(database_error)?;
+        for metric in metrics {
+            let payload = serde_json::to_string(metric)
+                .map_err(|e| StoreError::Serialization(e.to_string()))?;
+            tx.execute(
+                "INSERT OR REPLACE INTO copilot_metrics (scope, date, payload, fetched_at)
+                 VALUES (?1, ?2, ?3, ?4)",
+                params![scope, metric.date, payload, fetched_at],
+            )
+            .map_err(database_error)?;
+        }
+        tx.commit().map_err(database_error)?;
+        Ok(metrics.len())
+    }
+
+    fn load(&self, scope: &str) -> Result<Vec<CopilotMetrics>> {
+        let conn = self.conn();
+        let mut stmt = conn
+            .prepare("SELECT payload FROM copilot_metrics WHERE scope = ?1 ORDER BY date")
+            .map_err(database_error)?;
+        let payloads = stmt
+            .query_map(params![scope], |row| row.get::<_, String>(0))
+            .map_err(database_error)?;
+
+        payloads
+            .map(|payload| {
+                let payload = payload.map_err(database_error)?;
+                serde_json::from_str(&payload).map_err(|e| StoreError::Serialization(e.to_string()))
+            })
+            .collect()
+    }
+
+    fn latest_date(&self, scope: &str) -> Result<Option<String>> {
+        self.conn()
+            .query_row(
+                "SELECT MAX(date) FROM copilot_metrics WHERE scope = ?1",
+                params![scope],
+                |row| row.get::<_, Option<String>>(0),
+            )
+            .optional()
+            .map(Option::flatten)
+            .map_err(database_error)
+    }
+
+    fn scopes(&self) -> Result<Vec<String>> {
+        let conn = self.conn();
+        let mut stmt = conn
+            .prepare("SELECT DISTINCT scope FROM copilot_metrics ORDER BY scope")
+            .map_err(database_error)?;
+        let scopes = stmt
+            .query_map([], |row| row.get::<_, String>(0))
+            .map_err(database_error)?;
+        scopes.map(|s| s.map_err(database_error)).collect()
+    }
+
+    fn marker(&self, name: &str) -> Result<Option<String>> {
+        self.conn()
+            .query_row(
+                "SELECT value FROM markers WHERE name = ?1",
+                params![name],
+                |row| row.get::<_, String>(0),
+            )
+            .optional()
+            .map_err(database_error)
+    }
+
+    fn set_marker(&self, name: &str, value: &str) -> Result<()> {
+        self.conn()
+            .execute(
+                "INSERT OR REPLACE INTO markers (name, value) VALUES (?1, ?2)",
+                params![name, value],
+            )
+            .map(|_| ())
+            .map_err(database_error)
+    }
+}
+
+fn database_error(e: rusqlite::Error) -> StoreError {
+    StoreError::Database(e.to_string())
+}
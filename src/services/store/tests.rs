@@ -0,0 +1,340 @@
+//! # Metrics Store Tests
+
+use crate::models::github::CopilotMetrics;
+use crate::services::github::create_mock_metrics;
+
+#[cfg(feature = "sqlite")]
+mod sqlite {
+    use super::super::sqlite::SqliteStore;
+    use super::super::MetricsStore;
+    use crate::services::github::create_mock_metrics;
+
+    fn day(date: &str, active_users: i64) -> crate::models::github::CopilotMetrics {
+        let mut metric = create_mock_metrics(active_users, active_users / 2);
+        metric.date = date.to_string();
+        metric
+    }
+
+    #[test]
+    fn test_markers_round_trip() {
+        let store = SqliteStore::open_in_memory().unwrap();
+        assert_eq!(store.marker("digest:acme").unwrap(), None);
+
+        store.set_marker("digest:acme", "2025-W22").unwrap();
+        store.set_marker("digest:acme", "2025-W23").unwrap();
+        assert_eq!(
+            store.marker("digest:acme").unwrap(),
+            Some("2025-W23".to_string())
+        );
+    }
+
+    #[test]
+    fn test_save_and_load_round_trip() {
+        let store = SqliteStore::open_in_memory().unwrap();
+        let saved = store
+            .save(
+                "enterprise:acme",
+                &[day("2023-03-02", 20), day("2023-03-01", 10)],
+            )
+            .unwrap();
+        assert_eq!(saved, 2);
+
+        let loaded = store.load("enterprise:acme").unwrap();
+        assert_eq!(loaded.len(), 2);
+        assert_eq!(loaded[0].date, "2023-03-01");
+        assert_eq!(loaded[1].total_active_users, Some(20));
+        assert_eq!(
+            store.latest_date("enterprise:acme").unwrap().as_deref(),
+            Some("2023-03-02")
+        );
+        assert!(store.load("team:acme/platform").unwrap().is_empty());
+        assert_eq!(store.latest_date("team:acme/platform").unwrap(), None);
+    }
+
+    #[test]
+    fn test_save_replaces_existing_days() {
+        let store = SqliteStore::open_in_memory().unwrap();
+        store
+            .save("enterprise:acme", &[day("2023-03-01", 10)])
+            .unwrap();
+        store
+            .save("enterprise:acme", &[day("2023-03-01", 15)])
+            .unwrap();
+        store
+            .save("team:acme/platform", &[day("2023-03-01", 3)])
+            .unwrap();
+
+        let loaded = store.load("enterprise:acme").unwrap();
+        assert_eq!(loaded.len(), 1);
+        assert_eq!(loaded[0].total_active_users, Some(15));
+        assert_eq!(
+            store.scopes().unwrap(),
+            vec![
+                "enterprise:acme".to_string(),
+                "team:acme/platform".to_string()
+            ]
+        );
+    }
+}
+
+#[cfg(not(feature = "sqlite"))]
+#[test]
+fn test_sqlite_requires_feature() {
+    assert!(matches!(
+        super::open("metrics.db", &Default::default()),
+        Err(super::StoreError::Unsupported(_))
+    ));
+}
+
+/// Store keeping the days in memory, for tests of the backend-independent
+/// logic
+#[derive(Default)]
+struct MemoryStore {
+    days: std::sync::Mutex<std::collections::BTreeMap<(String, String), CopilotMetrics>>,
+    markers: std::sync::Mutex<std::collections::BTreeMap<String, String>>,
+}
+
+impl super::MetricsStore for MemoryStore {
+    fn save(&self, scope: &str, metrics: &[CopilotMetrics]) -> super::Result<usize> {
+        let mut days = self.days.lock().unwrap();
+        for metric in metrics {
+            days.insert((scope.to_string(), metric.date.clone()), metric.clone());
+        }
+        Ok(metrics.len())
+    }
+
+    fn load(&self, scope: &str) -> super::Result<Vec<CopilotMetrics>> {
+        let days = self.days.lock().unwrap();
+        Ok(days
+            .iter()
+            .filter(|((s, _), _)| s == scope)
+            .map(|(_, metric)| metric.clone())
+            .collect())
+    }
+
+    fn latest_date(&self, scope: &str) -> super::Result<Option<String>> {
+        Ok(self.load(scope)?.pop().map(|metric| metric.date))
+    }
+
+    fn scopes(&self) -> super::Result<Vec<String>> {
+        let days = self.days.lock().unwrap();
+        let mut scopes: Vec<String> = days.keys().map(|(scope, _)| scope.clone()).collect();
+        scopes.dedup();
+        Ok(scopes)
+    }
+
+    fn marker(&self, name: &str) -> super::Result<Option<String>> {
+        Ok(self.markers.lock().unwrap().get(name).cloned())
+    }
+
+    fn set_marker(&self, name: &str, value: &str) -> super::Result<()> {
+        self.markers
+            .lock()
+            .unwrap()
+            .insert(name.to_string(), value.to_string());
+        Ok(())
+    }
+}
+
+fn mock_day(date: &str, active_users: i64) -> CopilotMetrics {
+    let mut metric = create_mock_metrics(active_users, active_users / 2);
+    metric.date = date.to_string();
+    metric
+}
+
+#[test]
+fn test_fetch_missing_only_fetches_from_first_missing_day() {
+    use super::{fetch_missing_from, MetricsStore};
+    use chrono::NaiveDate;
+
+    let store = MemoryStore::default();
+    let until = NaiveDate::from_ymd_opt(2023, 3, 4).unwrap();
+    store
+        .save(
+            "enterprise:acme",
+            &[
+                mock_day("2023-02-28", 1),
+                mock_day("2023-03-01", 10),
+                mock_day("2023-03-02", 20),
+            ],
+        )
+        .unwrap();
+
+    // Only the days from the first missing one on are requested
+    let mut requested = Vec::new();
+    let metrics = fetch_missing_from(&store, "enterprise:acme", "2023-03-01", until, |since| {
+        requested.push(since.to_string());
+        Ok::<_, ()>(vec![mock_day("2023-03-03", 30), mock_day("2023-03-04", 40)])
+    })
+    .unwrap();
+    assert_eq!(requested, vec!["2023-03-03".to_string()]);
+    let dates: Vec<&str> = metrics.iter().map(|m| m.date.as_str()).collect();
+    assert_eq!(
+        dates,
+        vec!["2023-03-01", "2023-03-02", "2023-03-03", "2023-03-04"]
+    );
+
+    // Once every day is stored, GitHub isn't called at all
+    store.save("enterprise:acme", &metrics).unwrap();
+    let metrics = fetch_missing_from(&store, "enterprise:acme", "2023-03-01", until, |_| {
+        Err::<Vec<CopilotMetrics>, _>("GitHub must not be called")
+    })
+    .unwrap();
+    assert_eq!(metrics.len(), 4);
+    assert_eq!(metrics[3].total_active_users, Some(40));
+
+    // A scope without stored days fetches the whole window
+    let mut requested = Vec::new();
+    fetch_missing_from(&store, "team:acme/platform", "2023-03-01", until, |since| {
+        requested.push(since.to_string());
+        Ok::<_, ()>(Vec::new())
+    })
+    .unwrap();
+    assert_eq!(requested, vec!["2023-03-01".to_string()]);
+}
+
+#[test]
+fn test_load_range_filters_stored_days() {
+    use super::{load_range, MetricsStore};
+    use chrono::NaiveDate;
+
+    let store = MemoryStore::default();
+    store
+        .save(
+            "enterprise:acme",
+            &[
+                mock_day("2023-03-01", 10),
+                mock_day("2023-03-02", 20),
+                mock_day("2023-03-03", 30),
+            ],
+        )
+        .unwrap();
+    let date = |day| NaiveDate::from_ymd_opt(2023, 3, day);
+
+    let dates = |metrics: Vec<CopilotMetrics>| -> Vec<String> {
+        metrics.into_iter().map(|m| m.date).collect()
+    };
+    assert_eq!(
+        dates(load_range(&store, "enterprise:acme", date(2), None).unwrap()),
+        vec!["2023-03-02", "2023-03-03"]
+    );
+    assert_eq!(
+        dates(load_range(&store, "enterprise:acme", None, date(1)).unwrap()),
+        vec!["2023-03-01"]
+    );
+    assert_eq!(
+        load_range(&store, "enterprise:acme", date(2), date(2))
+            .unwrap()
+            .len(),
+        1
+    );
+    assert_eq!(
+        load_range(&store, "enterprise:acme", None, None)
+            .unwrap()
+            .len(),
+        3
+    );
+    assert!(load_range(&store, "team:acme/platform", None, None)
+        .unwrap()
+        .is_empty());
+}
+
+#[test]
+fn test_weekly_delivery_sent_once_per_week() {
+    use super::{mark_sent_this_week, sent_this_week, MetricsStore};
+
+    let store = MemoryStore::default();
+    let monday = chrono::NaiveDate::from_ymd_opt(2025, 6, 2).unwrap();
+
+    // The first run of the day sends and records the week
+    assert!(!sent_this_week(Some(&store), "digest:acme", monday));
+    mark_sent_this_week(Some(&store), "digest:acme", monday);
+    assert_eq!(
+        store.marker("digest:acme").unwrap(),
+        Some("2025-W23".to_string())
+    );
+
+    // A second run the same day doesn't send again, other deliveries do
+    assert!(sent_this_week(Some(&store), "digest:acme", monday));
+    assert!(!sent_this_week(Some(&store), "digest:globex", monday));
+    assert!(!sent_this_week(Some(&store), "leaderboard:acme", monday));
+
+    // The next week sends again
+    let next_monday = monday + chrono::Duration::days(7);
+    assert!(!sent_this_week(Some(&store), "digest:acme", next_monday));
+
+    // Without a store every run sends
+    assert!(!sent_this_week(None, "digest:acme", monday));
+}
+
+#[test]
+fn test_s3_locations() {
+    use super::s3::{parse_location, scope_object};
+
+    assert_eq!(
+        parse_location("s3://metrics-bucket/ghrust/days/"),
+        Some(("metrics-bucket", "ghrust/days"))
+    );
+    assert_eq!(
+        parse_location("s3://metrics-bucket"),
+        Some(("metrics-bucket", ""))
+    );
+    assert_eq!(parse_location("s3:///days"), None);
+    assert_eq!(parse_location("/mnt/efs/metrics.db"), None);
+    assert_eq!(
+        scope_object("team:acme/platform"),
+        "team/acme/platform.json"
+    );
+}
+
+#[test]
+fn test_store_handles_are_independent() {
+    use super::{MetricsStore, Store};
+    use std::sync::Arc;
+
+    let acme = Arc::new(MemoryStore::default());
+    let globex = Arc::new(MemoryStore::default());
+    let reusing = Store::new(acme.clone(), true);
+    let fetching = Store::new(globex.clone(), false);
+
+    reusing.persist("enterprise:acme", &[mock_day("2023-03-01", 10)]);
+    assert_eq!(acme.load("enterprise:acme").unwrap().len(), 1);
+    assert!(globex.load("enterprise:acme").unwrap().is_empty());
+
+    // Without reuse the whole window is fetched even if days are stored
+    fetching.persist("enterprise:acme", &[mock_day("2023-03-01", 10)]);
+    let mut requested = Vec::new();
+    fetching
+        .fetch_missing("enterprise:acme", "2023-03-01", |since| {
+            requested.push(since.to_string());
+            Ok::<_, ()>(Vec::new())
+        })
+        .unwrap();
+    assert_eq!(requested, vec!["2023-03-01".to_string()]);
+
+    // Without a backend nothing is kept
+    let disabled = Store::default();
+    assert!(disabled.backend().is_none());
+    disabled.persist("enterprise:acme", &[mock_day("2023-03-02", 20)]);
+}
+
+#[test]
+fn test_store_window_serves_stored_days() {
+    use super::{MetricsStore, Store};
+    use std::sync::Arc;
+
+    let backend = Arc::new(MemoryStore::default());
+    backend
+        .save(
+            "enterprise:acme",
+            &[mock_day("2023-02-28", 1), mock_day("2023-03-01", 10)],
+        )
+        .unwrap();
+
+    let window = Store::new(backend, false).window("enterprise:acme", "2023-03-01");
+    assert_eq!(window.len(), 1);
+    assert_eq!(window[0].date, "2023-03-01");
+    assert!(Store::default()
+        .window("enterprise:acme", "2023-03-01")
+        .is_empty());
+}
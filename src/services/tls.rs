@@ -0,0 +1,174 @@
+//! # TLS Configuration
+//!
+//! GitHub Enterprise Server instances and corporate proxies often present
+//! certificates signed by an internal CA that is not part of the default trust
+//! store. This module builds the HTTP agents used by both the GitHub and the
+//! Datadog clients and lets a deployment add a PEM CA bundle to the trusted
+//! roots.
+//!
+//! The bundle path is read from the configuration file's `ca_bundle` or the
+//! `GHRUST_CA_BUNDLE` environment variable. Certificates in the bundle are
+//! trusted in addition to the default Mozilla roots.
+//!
+//! The configuration loads the bundle into an `AgentConfig` before it builds
+//! any client, and every client takes the `AgentConfig` it builds its agent
+//! from, so a reloaded configuration trusts the bundle it names.
+
+use std::env;
+use std::fmt;
+use std::fs::File;
+use std::io::BufReader;
+use std::sync::Arc;
+
+use rustls::{ClientConfig, RootCertStore};
+use thiserror::Error;
+use tracing::info;
+
+use super::http_log;
+
+/// Errors that can occur while loading the TLS configuration
+#[derive(Error, Debug)]
+pub enum TlsError {
+    /// The CA bundle could not be read or contains no usable certificate
+    #[error("Error loading CA bundle {0}: {1}")]
+    Bundle(String, String),
+}
+
+/// Settings of the HTTP agents built by the clients of one configuration
+///
+/// The default trusts the default roots only and logs nothing. Two
+/// configurations are equal when they name the same CA bundle and agree on
+/// logging.
+#[derive(Clone, Default)]
+pub struct AgentConfig {
+    /// Path of the CA bundle, if any
+    ca_bundle: Option<String>,
+    /// TLS client configuration trusting the bundle, if any
+    tls: Option<Arc<ClientConfig>>,
+    /// Whether requests and responses are logged (see `http_log`)
+    debug_log: bool,
+}
+
+impl AgentConfig {
+    /// Loads the agent settings
+    ///
+    /// # Arguments
+    ///
+    /// * `ca_bundle` - Path of a PEM CA bundle, or `None` for the default roots
+    ///
+    /// # Returns
+    ///
+    /// * `Result<AgentConfig, TlsError>` - The settings, or an error if the
+    ///   bundle can't be loaded
+    pub fn load(ca_bundle: Option<&str>) -> Result<Self, TlsError> {
+        let tls = match ca_bundle {
+            Some(path) => Some(Arc::new(load_client_config(path)?)),
+            None => None,
+        };
+        Ok(Self {
+            ca_bundle: ca_bundle.map(str::to_string),
+            tls,
+            debug_log: false,
+        })
+    }
+
+    /// Enables or disables the logging of requests and responses
+    ///
+    /// # Arguments
+    ///
+    /// * `debug_log` - Whether the agents log the headers of every request
+    ///   and response and the clients log the bodies
+    pub fn with_debug_log(mut self, debug_log: bool) -> Self {
+        self.debug_log = debug_log;
+        self
+    }
+
+    /// Returns whether requests and responses are logged
+    pub fn debug_log(&self) -> bool {
+        self.debug_log
+    }
+
+    /// Loads the agent settings from `GHRUST_CA_BUNDLE` and `HTTP_DEBUG_LOG`,
+    /// for standalone tools
+    ///
+    /// # Returns
+    ///
+    /// * `Result<AgentConfig, TlsError>` - The settings, or an error if the
+    ///   bundle can't be loaded
+    pub fn from_env() -> Result<Self, TlsError> {
+        Ok(Self::load(env::var("GHRUST_CA_BUNDLE").ok().as_deref())?
+            .with_debug_log(env::var("HTTP_DEBUG_LOG").is_ok()))
+    }
+
+    /// Returns the path of the trusted CA bundle, if any
+    pub fn ca_bundle(&self) -> Option<&str> {
+        self.ca_bundle.as_deref()
+    }
+
+    /// Returns an agent builder using these settings
+    ///
+    /// With debug logging enabled, the agent logs the headers of every
+    /// request and response.
+    pub fn agent_builder(&self) -> ureq::AgentBuilder {
+        let builder = match &self.tls {
+            Some(config) => ureq::AgentBuilder::new().tls_config(config.clone()),
+            None => ureq::AgentBuilder::new(),
+        };
+        if self.debug_log {
+            builder.middleware(http_log::HttpLogger)
+        } else {
+            builder
+        }
+    }
+}
+
+impl PartialEq for AgentConfig {
+    fn eq(&self, other: &Self) -> bool {
+        self.ca_bundle == other.ca_bundle && self.debug_log == other.debug_log
+    }
+}
+
+impl fmt::Debug for AgentConfig {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("AgentConfig")
+            .field("ca_bundle", &self.ca_bundle)
+            .field("debug_log", &self.debug_log)
+            .finish()
+    }
+}
+
+/// Builds a TLS client configuration trusting the default roots and a CA bundle
+///
+/// # Arguments
+///
+/// * `path` - Path of a PEM file with one or more CA certificates
+///
+/// # Returns
+///
+/// * `Result<ClientConfig, TlsError>` - The configuration, or an error if the
+///   file can't be read or contains no valid certificate
+pub fn load_client_config(path: &str) -> Result<ClientConfig, TlsError> {
+    let bundle_error = |msg: String| TlsError::Bundle(path.to_string(), msg);
+
+    let file = File::open(path).map_err(|e| bundle_error(e.to_string()))?;
+    let mut reader = BufReader::new(file);
+
+    let mut roots = RootCertStore::empty();
+    roots.extend(webpki_roots::TLS_SERVER_ROOTS.iter().cloned());
+
+    let mut added = 0;
+    for cert in rustls_pemfile::certs(&mut reader) {
+        let cert = cert.map_err(|e| bundle_error(e.to_string()))?;
+        roots.add(cert).map_err(|e| bundle_error(e.to_string()))?;
+        added += 1;
+    }
+
+    if added == 0 {
+        return Err(bundle_error("no certificates found".to_string()));
+    }
+
+    info!("Trusting {} certificate(s) from CA bundle {}", added, path);
+    Ok(ClientConfig::builder()
+        .with_root_certificates(roots)
+        .with_no_client_auth())
+}
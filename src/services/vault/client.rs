@@ -0,0 +1,313 @@
+//! # Vault Client
+//!
+//! Reads KV secrets (version 1 and 2) through the Vault HTTP API. The client
+//! token is obtained on first use, either the configured token or an AppRole
+//! login, and kept together with its lease. Before every request the lease is
+//! checked: once less than a third of its TTL (or less than a minute) is
+//! left, the token is renewed with `auth/token/renew-self`; if renewal fails
+//! and AppRole credentials are available, the client logs in again.
+//! `keep_alive` runs the same check without a request, so a background
+//! renewer can keep the lease alive between reads.
+
+use std::collections::HashMap;
+use std::env;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use serde_json::{json, Value};
+use tracing::{debug, info, warn};
+
+use super::error::{Result, VaultError};
+use crate::services::tls::AgentConfig;
+
+/// Remaining lifetime below which a token is always renewed
+const MIN_REMAINING: Duration = Duration::from_secs(60);
+
+/// Shortest delay `keep_alive` asks for, so a lease shorter than
+/// `MIN_REMAINING` isn't renewed in a busy loop
+const MIN_RENEWAL_DELAY: Duration = Duration::from_secs(1);
+
+/// How the client authenticates with Vault
+#[derive(Clone, PartialEq)]
+pub enum VaultAuth {
+    /// A Vault token, e.g. from `vault login` or a Kubernetes agent
+    Token(String),
+    /// AppRole credentials exchanged for a token
+    AppRole {
+        /// Role ID of the AppRole
+        role_id: String,
+        /// Secret ID of the AppRole
+        secret_id: String,
+    },
+}
+
+impl VaultAuth {
+    /// Reads the credentials from `VAULT_TOKEN`, or `VAULT_ROLE_ID` and
+    /// `VAULT_SECRET_ID`
+    ///
+    /// # Returns
+    ///
+    /// * `Result<VaultAuth>` - The credentials, or a `Config` error if neither
+    ///   is set
+    pub fn from_env() -> Result<Self> {
+        if let Ok(token) = env::var("VAULT_TOKEN") {
+            return Ok(Self::Token(token));
+        }
+        match (env::var("VAULT_ROLE_ID"), env::var("VAULT_SECRET_ID")) {
+            (Ok(role_id), Ok(secret_id)) => Ok(Self::AppRole { role_id, secret_id }),
+            _ => Err(VaultError::Config(
+                "VAULT_TOKEN or VAULT_ROLE_ID and VAULT_SECRET_ID must be set".to_string(),
+            )),
+        }
+    }
+}
+
+impl std::fmt::Debug for VaultAuth {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Token(_) => f.debug_tuple("Token").field(&"<redacted>").finish(),
+            Self::AppRole { role_id, .. } => f
+                .debug_struct("AppRole")
+                .field("role_id", role_id)
+                .field("secret_id", &"<redacted>")
+                .finish(),
+        }
+    }
+}
+
+/// A client token and its lease
+struct Lease {
+    /// The client token
+    token: String,
+    /// Whether the token can be renewed
+    renewable: bool,
+    /// Lifetime granted by the last login or renewal, `None` if it never expires
+    ttl: Option<Duration>,
+    /// When the lease was granted
+    granted: Instant,
+}
+
+impl Lease {
+    /// Creates a lease from the `auth` (or lookup `data`) block of a response
+    fn from_auth(token: String, auth: &Value, ttl_field: &str) -> Self {
+        let ttl = auth[ttl_field].as_u64().filter(|ttl| *ttl > 0);
+        Self {
+            token,
+            renewable: auth["renewable"].as_bool().unwrap_or(false),
+            ttl: ttl.map(Duration::from_secs),
+            granted: Instant::now(),
+        }
+    }
+
+    /// Returns whether the token should be renewed before it is used
+    fn needs_renewal(&self) -> bool {
+        self.renewal_in() == Some(Duration::ZERO)
+    }
+
+    /// Returns the time until the token should be renewed, `None` if it
+    /// never expires
+    fn renewal_in(&self) -> Option<Duration> {
+        let ttl = self.ttl?;
+        let remaining = ttl.saturating_sub(self.granted.elapsed());
+        let threshold = MIN_REMAINING.max(ttl / 3);
+        Some(remaining.saturating_sub(threshold))
+    }
+}
+
+/// Client for a Vault server
+pub struct VaultClient {
+    /// Base URL of the server, e.g. "https://vault.example.com:8200"
+    addr: String,
+    /// Vault Enterprise namespace, if any
+    namespace: Option<String>,
+    /// Credentials used to obtain the client token
+    auth: VaultAuth,
+    /// HTTP agent using the configured TLS settings
+    agent: ureq::Agent,
+    /// The current client token, once obtained
+    lease: Mutex<Option<Lease>>,
+}
+
+impl VaultClient {
+    /// Creates a client for a Vault server
+    ///
+    /// No request is made until the first secret is read.
+    ///
+    /// # Arguments
+    ///
+    /// * `addr` - Base URL of the server
+    /// * `auth` - Credentials used to obtain the client token
+    /// * `agent` - Settings of the HTTP agent
+    pub fn new(addr: impl Into<String>, auth: VaultAuth, agent: &AgentConfig) -> Self {
+        Self {
+            addr: addr.into().trim_end_matches('/').to_string(),
+            namespace: None,
+            auth,
+            agent: agent.agent_builder().build(),
+            lease: Mutex::new(None),
+        }
+    }
+
+    /// Sends requests to a Vault Enterprise namespace
+    ///
+    /// # Arguments
+    ///
+    /// * `namespace` - The namespace, e.g. "platform/ghrust"
+    pub fn with_namespace(mut self, namespace: impl Into<String>) -> Self {
+        self.namespace = Some(namespace.into());
+        self
+    }
+
+    /// Renews the client token if it is due and returns when to check again
+    ///
+    /// Called ahead of expiry by a background renewer, so a token that isn't
+    /// used for longer than its TTL (e.g. between runs of the server) is
+    /// still valid at the next read.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<Option<Duration>>` - The delay until the lease is due for
+    ///   renewal, `None` if the token never expires or can't be renewed
+    pub fn keep_alive(&self) -> Result<Option<Duration>> {
+        self.token()?;
+        let lease = self.lease.lock().unwrap_or_else(|e| e.into_inner());
+        let Some(lease) = lease.as_ref() else {
+            return Ok(None);
+        };
+        // A static token that can't be renewed expires regardless
+        if !lease.renewable && matches!(self.auth, VaultAuth::Token(_)) {
+            return Ok(None);
+        }
+        Ok(lease.renewal_in().map(|delay| delay.max(MIN_RENEWAL_DELAY)))
+    }
+
+    /// Reads a KV secret
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - API path of the secret, e.g. "secret/data/ghrust" (KV
+    ///   version 2) or "kv/ghrust" (KV version 1)
+    ///
+    /// # Returns
+    ///
+    /// * `Result<HashMap<String, String>>` - The string values of the secret
+    pub fn read_secret(&self, path: &str) -> Result<HashMap<String, String>> {
+        let token = self.token()?;
+        let response = self.get(&format!("v1/{}", path.trim_start_matches('/')), &token)?;
+
+        // KV version 2 nests the values in `data.data`
+        let data = match response["data"].get("data") {
+            Some(data) if data.is_object() && response["data"].get("metadata").is_some() => data,
+            _ => &response["data"],
+        };
+        let values = data
+            .as_object()
+            .ok_or_else(|| VaultError::InvalidResponse(format!("secret {} has no data", path)))?;
+
+        debug!("Read Vault secret {}", path);
+        Ok(values
+            .iter()
+            .filter_map(|(key, value)| Some((key.clone(), value.as_str()?.to_string())))
+            .collect())
+    }
+
+    /// Returns a valid client token, logging in or renewing as needed
+    fn token(&self) -> Result<String> {
+        let mut lease = self.lease.lock().unwrap_or_else(|e| e.into_inner());
+
+        if let Some(current) = lease.as_ref().filter(|l| l.needs_renewal()) {
+            if current.renewable {
+                match self.renew(&current.token) {
+                    Ok(renewed) => *lease = Some(renewed),
+                    Err(e) => {
+                        warn!("Renewing the Vault token failed: {}", e);
+                        *lease = None;
+                    }
+                }
+            } else if matches!(self.auth, VaultAuth::AppRole { .. }) {
+                *lease = None;
+            }
+        }
+
+        if lease.is_none() {
+            *lease = Some(self.login()?);
+        }
+        Ok(lease.as_ref().map(|l| l.token.clone()).unwrap_or_default())
+    }
+
+    /// Obtains a client token with the configured credentials
+    fn login(&self) -> Result<Lease> {
+        match &self.auth {
+            VaultAuth::Token(token) => {
+                let response = self.get("v1/auth/token/lookup-self", token)?;
+                Ok(Lease::from_auth(token.clone(), &response["data"], "ttl"))
+            }
+            VaultAuth::AppRole { role_id, secret_id } => {
+                let response = self.post(
+                    "v1/auth/approle/login",
+                    None,
+                    json!({ "role_id": role_id, "secret_id": secret_id }),
+                )?;
+                let auth = &response["auth"];
+                let token = auth["client_token"].as_str().ok_or_else(|| {
+                    VaultError::InvalidResponse("AppRole login returned no token".to_string())
+                })?;
+                info!("Logged in to Vault with AppRole");
+                Ok(Lease::from_auth(token.to_string(), auth, "lease_duration"))
+            }
+        }
+    }
+
+    /// Extends the lease of a client token
+    fn renew(&self, token: &str) -> Result<Lease> {
+        let response = self.post("v1/auth/token/renew-self", Some(token), json!({}))?;
+        info!("Renewed the Vault token");
+        Ok(Lease::from_auth(
+            token.to_string(),
+            &response["auth"],
+            "lease_duration",
+        ))
+    }
+
+    /// Sends a GET request with a client token
+    fn get(&self, path: &str, token: &str) -> Result<Value> {
+        let request = self.request("GET", path, Some(token));
+        read_response(request.call())
+    }
+
+    /// Sends a POST request with a JSON body
+    fn post(&self, path: &str, token: Option<&str>, body: Value) -> Result<Value> {
+        let request = self.request("POST", path, token);
+        read_response(request.send_json(body))
+    }
+
+    /// Builds a request with the token and namespace headers
+    fn request(&self, method: &str, path: &str, token: Option<&str>) -> ureq::Request {
+        let mut request = self
+            .agent
+            .request(method, &format!("{}/{}", self.addr, path));
+        if let Some(token) = token {
+            request = request.set("X-Vault-Token", token);
+        }
+        if let Some(namespace) = &self.namespace {
+            request = request.set("X-Vault-Namespace", namespace);
+        }
+        request
+    }
+}
+
+/// Parses a Vault response, converting failures into a `VaultError`
+fn read_response(response: std::result::Result<ureq::Response, ureq::Error>) -> Result<Value> {
+    match response {
+        Ok(response) => response
+            .into_json()
+            .map_err(|e| VaultError::InvalidResponse(e.to_string())),
+        Err(ureq::Error::Status(status, response)) => {
+            let body = response
+                .into_string()
+                .unwrap_or_else(|_| "Could not read response body".to_string());
+            Err(VaultError::HttpError(status, body))
+        }
+        Err(ureq::Error::Transport(transport)) => Err(VaultError::Network(transport.to_string())),
+    }
+}
@@ -0,0 +1,32 @@
+//! # Vault Error Types
+//!
+//! This module defines structured error types for the HashiCorp Vault client.
+
+use thiserror::Error;
+
+/// Errors that can occur when reading secrets from Vault
+#[derive(Error, Debug)]
+pub enum VaultError {
+    /// The Vault address or credentials are not configured
+    #[error("Vault configuration error: {0}")]
+    Config(String),
+
+    /// Network or transport error
+    #[error("Network error: {0}")]
+    Network(String),
+
+    /// HTTP request error with status code
+    #[error("Vault HTTP error {0}: {1}")]
+    HttpError(u16, String),
+
+    /// A response could not be parsed
+    #[error("Invalid Vault response: {0}")]
+    InvalidResponse(String),
+
+    /// The secret doesn't contain the requested key
+    #[error("Vault secret {0} has no key {1}")]
+    MissingKey(String, String),
+}
+
+/// A specialized Result type for Vault operations
+pub type Result<T> = std::result::Result<T, VaultError>;
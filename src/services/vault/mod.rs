@@ -0,0 +1,192 @@
+//! # HashiCorp Vault
+//!
+//! Optional secrets provider reading the GitHub token and the Datadog API key
+//! from a Vault KV secret instead of environment variables. The collector
+//! authenticates with a Vault token or through AppRole, and a background
+//! thread renews its token ahead of expiry, so the long-running server keeps
+//! working across token TTLs even when runs are further apart than the TTL.
+//!
+//! Vault is used when `VAULT_ADDR` and `VAULT_SECRET_PATH` are set:
+//!
+//! * `VAULT_TOKEN` - Token auth, or
+//! * `VAULT_ROLE_ID` / `VAULT_SECRET_ID` - AppRole auth
+//! * `VAULT_NAMESPACE` - Vault Enterprise namespace (optional)
+//! * `VAULT_SECRET_PATH` - API path of the secret, e.g. `secret/data/ghrust`
+//!   for KV version 2
+//!
+//! The secret holds the keys `github_token` and `datadog_api_key`.
+//!
+//! ## Core Components
+//!
+//! * `client` - Authentication, token renewal and secret reads
+//! * `configured` / `secret` - The client used by the configuration loader,
+//!   reused across loads with the same settings, and the secret body it read
+//!   last
+//! * `error` - Structured error types for Vault operations
+
+pub mod client;
+mod error;
+
+#[cfg(test)]
+mod tests;
+
+use std::collections::HashMap;
+use std::env;
+use std::sync::{Arc, Mutex, Once, Weak};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use tracing::warn;
+
+use crate::services::tls::AgentConfig;
+pub use client::{VaultAuth, VaultClient};
+pub use error::{Result, VaultError};
+
+/// Client of the last configuration load, reused while its settings match
+static VAULT: Mutex<Option<Arc<Vault>>> = Mutex::new(None);
+
+/// How long a secret body is reused, so a configuration load reads it once
+/// while the server's next run still picks up rotated values
+const SECRET_MAX_AGE: Duration = Duration::from_secs(60);
+
+/// Delay before the renewer retries after a failed renewal
+const RENEWAL_RETRY: Duration = Duration::from_secs(30);
+
+/// A secret body, with when it was read
+type CachedSecret = (Instant, HashMap<String, String>);
+
+/// A client together with the settings it was created from
+pub struct Vault {
+    /// Base URL of the server
+    addr: String,
+    /// Credentials used to obtain the client token
+    auth: VaultAuth,
+    /// Vault Enterprise namespace, if any
+    namespace: Option<String>,
+    /// Settings of the HTTP agent
+    agent: AgentConfig,
+    /// API path of the secret
+    pub path: String,
+    /// The client
+    pub client: VaultClient,
+    /// Secret bodies read per path
+    secrets: Mutex<HashMap<String, CachedSecret>>,
+    /// Starts the background renewer once the first secret was read
+    renewer: Once,
+}
+
+impl Vault {
+    /// Reads the secret, reusing a body read less than `SECRET_MAX_AGE` ago
+    fn read_secret(&self) -> Result<HashMap<String, String>> {
+        let mut secrets = self.secrets.lock().unwrap_or_else(|e| e.into_inner());
+        if let Some((_, secret)) = secrets
+            .get(&self.path)
+            .filter(|(read, _)| read.elapsed() < SECRET_MAX_AGE)
+        {
+            return Ok(secret.clone());
+        }
+        let secret = self.client.read_secret(&self.path)?;
+        secrets.insert(self.path.clone(), (Instant::now(), secret.clone()));
+        Ok(secret)
+    }
+}
+
+/// Renews the token of a client ahead of expiry until the client is dropped
+///
+/// The thread holds only a weak reference, so it ends once a configuration
+/// load with other settings replaces the client.
+fn spawn_renewer(vault: &Arc<Vault>) {
+    let vault = Arc::downgrade(vault);
+    thread::spawn(move || loop {
+        let Some(delay) = next_renewal(&vault) else {
+            return;
+        };
+        thread::sleep(delay);
+    });
+}
+
+/// Renews the token if it is due and returns the delay until the next check,
+/// `None` once the client is dropped or its token doesn't need renewal
+fn next_renewal(vault: &Weak<Vault>) -> Option<Duration> {
+    let vault = vault.upgrade()?;
+    match vault.client.keep_alive() {
+        Ok(delay) => delay,
+        Err(e) => {
+            warn!("Renewing the Vault token failed: {}", e);
+            Some(RENEWAL_RETRY)
+        }
+    }
+}
+
+/// Returns the client and secret path, if Vault is configured
+///
+/// The client is created from the environment and kept for later
+/// configuration loads with the same address, credentials and agent
+/// settings, so its token is reused and renewed instead of logging in for
+/// every load.
+///
+/// # Arguments
+///
+/// * `agent` - Settings of the HTTP agent, e.g. the configured CA bundle
+pub fn configured(agent: &AgentConfig) -> Result<Option<Arc<Vault>>> {
+    let (Ok(addr), Ok(path)) = (env::var("VAULT_ADDR"), env::var("VAULT_SECRET_PATH")) else {
+        return Ok(None);
+    };
+    let auth = VaultAuth::from_env()?;
+    let namespace = env::var("VAULT_NAMESPACE").ok();
+
+    let mut cached = VAULT.lock().unwrap_or_else(|e| e.into_inner());
+    if let Some(vault) = cached.as_ref().filter(|v| {
+        v.addr == addr
+            && v.auth == auth
+            && v.namespace == namespace
+            && &v.agent == agent
+            && v.path == path
+    }) {
+        return Ok(Some(Arc::clone(vault)));
+    }
+
+    let mut client = VaultClient::new(addr.clone(), auth.clone(), agent);
+    if let Some(namespace) = &namespace {
+        client = client.with_namespace(namespace);
+    }
+    let vault = Arc::new(Vault {
+        client,
+        addr,
+        auth,
+        namespace,
+        agent: agent.clone(),
+        path,
+        secrets: Mutex::new(HashMap::new()),
+        renewer: Once::new(),
+    });
+    *cached = Some(Arc::clone(&vault));
+    Ok(Some(vault))
+}
+
+/// Reads a key of the configured secret
+///
+/// The secret body is read once for the keys of a configuration load, and
+/// the first read starts the background renewer of the client token.
+///
+/// # Arguments
+///
+/// * `key` - Key within the secret, e.g. "github_token"
+/// * `agent` - Settings of the HTTP agent, e.g. the configured CA bundle
+///
+/// # Returns
+///
+/// * `Result<Option<String>>` - The value, `None` if Vault is not configured,
+///   or an error if the secret can't be read or lacks the key
+pub fn secret(key: &str, agent: &AgentConfig) -> Result<Option<String>> {
+    let Some(vault) = configured(agent)? else {
+        return Ok(None);
+    };
+    let secret = vault.read_secret()?;
+    vault.renewer.call_once(|| spawn_renewer(&vault));
+    secret
+        .get(key)
+        .cloned()
+        .map(Some)
+        .ok_or_else(|| VaultError::MissingKey(vault.path.clone(), key.to_string()))
+}
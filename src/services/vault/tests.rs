@@ -0,0 +1,183 @@
+//! # Vault Client Tests
+//!
+//! Tests for AppRole login, token renewal, namespaces and KV secret reads
+//! against a local mock server.
+
+use std::time::Duration;
+
+use serde_json::{json, Value};
+
+use super::{VaultAuth, VaultClient, VaultError};
+use crate::services::tls::AgentConfig;
+use crate::tests::mock_server::{self, MockResponse};
+
+fn kv2_secret() -> Value {
+    json!({
+        "data": {
+            "data": { "github_token": "ghp_vault", "datadog_api_key": "dd_vault" },
+            "metadata": { "version": 3 }
+        }
+    })
+}
+
+#[test]
+fn approle_login_reads_and_renews() {
+    let (addr, server) = mock_server::serve(vec![
+        MockResponse::json(
+            "200 OK",
+            &json!({ "auth": { "client_token": "s.first", "lease_duration": 30, "renewable": true } }),
+        ),
+        MockResponse::json("200 OK", &kv2_secret()),
+        MockResponse::json(
+            "200 OK",
+            &json!({ "auth": { "client_token": "s.first", "lease_duration": 3600, "renewable": true } }),
+        ),
+        MockResponse::json("200 OK", &kv2_secret()),
+        MockResponse::json("200 OK", &kv2_secret()),
+    ]);
+    let client = VaultClient::new(
+        addr,
+        VaultAuth::AppRole {
+            role_id: "role".to_string(),
+            secret_id: "secret".to_string(),
+        },
+        &AgentConfig::default(),
+    );
+
+    let secret = client.read_secret("secret/data/ghrust").unwrap();
+    assert_eq!(secret["github_token"], "ghp_vault");
+    assert_eq!(secret["datadog_api_key"], "dd_vault");
+    // A lease of 30 seconds is renewed before the next use
+    client.read_secret("secret/data/ghrust").unwrap();
+    // A fresh lease of an hour is used as is
+    client.read_secret("secret/data/ghrust").unwrap();
+
+    let requests = server.join().unwrap();
+    let lines: Vec<String> = requests.iter().map(|request| request.line()).collect();
+    assert_eq!(
+        lines,
+        vec![
+            "POST /v1/auth/approle/login HTTP/1.1",
+            "GET /v1/secret/data/ghrust HTTP/1.1",
+            "POST /v1/auth/token/renew-self HTTP/1.1",
+            "GET /v1/secret/data/ghrust HTTP/1.1",
+            "GET /v1/secret/data/ghrust HTTP/1.1",
+        ]
+    );
+    assert_eq!(requests[0].header("x-vault-token"), None);
+    assert!(requests[1..]
+        .iter()
+        .all(|request| request.header("x-vault-token") == Some("s.first")));
+}
+
+#[test]
+fn token_auth_reads_kv1_secret() {
+    let (addr, server) = mock_server::serve(vec![
+        MockResponse::json(
+            "200 OK",
+            &json!({ "data": { "ttl": 0, "renewable": false } }),
+        ),
+        MockResponse::json("200 OK", &json!({ "data": { "github_token": "ghp_kv1" } })),
+        MockResponse::json("403 Forbidden", &json!({ "errors": ["permission denied"] })),
+    ]);
+    let client = VaultClient::new(
+        addr,
+        VaultAuth::Token("s.static".to_string()),
+        &AgentConfig::default(),
+    );
+
+    let secret = client.read_secret("kv/ghrust").unwrap();
+    assert_eq!(secret["github_token"], "ghp_kv1");
+    assert!(matches!(
+        client.read_secret("kv/other"),
+        Err(VaultError::HttpError(403, _))
+    ));
+
+    let requests = server.join().unwrap();
+    assert_eq!(
+        requests[0].line(),
+        "GET /v1/auth/token/lookup-self HTTP/1.1"
+    );
+    assert!(requests
+        .iter()
+        .all(|request| request.header("x-vault-token") == Some("s.static")));
+}
+
+#[test]
+fn keep_alive_renews_ahead_of_expiry() {
+    let (addr, server) = mock_server::serve(vec![
+        MockResponse::json(
+            "200 OK",
+            &json!({ "auth": { "client_token": "s.first", "lease_duration": 30, "renewable": true } }),
+        ),
+        MockResponse::json(
+            "200 OK",
+            &json!({ "auth": { "client_token": "s.first", "lease_duration": 3600, "renewable": true } }),
+        ),
+    ]);
+    let client = VaultClient::new(
+        addr,
+        VaultAuth::AppRole {
+            role_id: "role".to_string(),
+            secret_id: "secret".to_string(),
+        },
+        &AgentConfig::default(),
+    );
+
+    // A lease of 30 seconds is due at once, but not checked again in a busy
+    // loop
+    assert_eq!(client.keep_alive().unwrap(), Some(Duration::from_secs(1)));
+    // It is renewed without waiting for the next read, and the renewed one is
+    // due once a third of its hour is left
+    let delay = client.keep_alive().unwrap().unwrap();
+    assert!(delay > Duration::from_secs(2390) && delay <= Duration::from_secs(2400));
+    let delay = client.keep_alive().unwrap().unwrap();
+    assert!(delay > Duration::from_secs(2390));
+
+    let requests = server.join().unwrap();
+    let lines: Vec<String> = requests.iter().map(|request| request.line()).collect();
+    assert_eq!(
+        lines,
+        vec![
+            "POST /v1/auth/approle/login HTTP/1.1",
+            "POST /v1/auth/token/renew-self HTTP/1.1",
+        ]
+    );
+}
+
+#[test]
+fn configured_secret_is_read_once_per_load() {
+    let (addr, server) = mock_server::serve(vec![
+        MockResponse::json("200 OK", &json!({ "data": { "ttl": 0 } })),
+        MockResponse::json("200 OK", &kv2_secret()),
+    ]);
+    temp_env::with_vars(
+        [
+            ("VAULT_ADDR", Some(addr.as_str())),
+            ("VAULT_SECRET_PATH", Some("secret/data/ghrust")),
+            ("VAULT_TOKEN", Some("s.static")),
+            ("VAULT_NAMESPACE", Some("platform/ghrust")),
+        ],
+        || {
+            let agent = AgentConfig::default();
+            assert_eq!(
+                super::secret("github_token", &agent).unwrap().as_deref(),
+                Some("ghp_vault")
+            );
+            assert_eq!(
+                super::secret("datadog_api_key", &agent).unwrap().as_deref(),
+                Some("dd_vault")
+            );
+            assert!(matches!(
+                super::secret("opsgenie_api_key", &agent),
+                Err(VaultError::MissingKey(_, _))
+            ));
+        },
+    );
+
+    let requests = server.join().unwrap();
+    assert_eq!(requests.len(), 2);
+    assert!(requests
+        .iter()
+        .all(|request| request.header("x-vault-namespace") == Some("platform/ghrust")));
+}
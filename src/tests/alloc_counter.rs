@@ -0,0 +1,43 @@
+//! # Allocation Counter
+//!
+//! The global allocator of the test binary. It forwards to the system
+//! allocator and counts every allocation, so benchmarks marked `#[ignore]` can
+//! report how many allocations a code path makes. The count covers every
+//! thread, so a benchmark is run on its own:
+//!
+//! ```text
+//! cargo test --release --lib prepare_all_metrics_benchmark -- --ignored --nocapture
+//! ```
+
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+/// Allocations made since the test binary started
+static ALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+
+/// The system allocator, counting allocations
+struct CountingAllocator;
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        System.realloc(ptr, layout, new_size)
+    }
+}
+
+#[global_allocator]
+static ALLOCATOR: CountingAllocator = CountingAllocator;
+
+/// Returns the number of allocations made since the test binary started
+pub fn allocations() -> usize {
+    ALLOCATIONS.load(Ordering::Relaxed)
+}
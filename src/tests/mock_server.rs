@@ -0,0 +1,195 @@
+//! # Mock HTTP Server
+//!
+//! A minimal HTTP/1.1 server on a local port, shared by the tests of the
+//! GitHub, Datadog, Vault and notification clients. Every connection carries
+//! one request, which is read completely and answered with
+//! `Connection: close`.
+
+use std::collections::HashMap;
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::thread::JoinHandle;
+
+use serde_json::Value;
+
+/// A request received by the mock server
+#[derive(Debug, Clone)]
+pub(crate) struct MockRequest {
+    /// Method, e.g. "POST"
+    pub method: String,
+    /// Path including the query string
+    pub path: String,
+    /// Headers keyed by lowercase name
+    pub headers: HashMap<String, String>,
+    /// Raw request body
+    pub body: Vec<u8>,
+}
+
+impl MockRequest {
+    /// The request line, e.g. "GET /user HTTP/1.1"
+    pub fn line(&self) -> String {
+        format!("{} {} HTTP/1.1", self.method, self.path)
+    }
+
+    /// The value of a header, looked up case-insensitively
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .get(&name.to_ascii_lowercase())
+            .map(String::as_str)
+    }
+
+    /// The body as text
+    pub fn text(&self) -> String {
+        String::from_utf8_lossy(&self.body).into_owned()
+    }
+
+    /// The body parsed as JSON, panicking if it isn't
+    pub fn json(&self) -> Value {
+        serde_json::from_slice(&self.body).unwrap()
+    }
+}
+
+/// A response of the mock server
+#[derive(Debug, Clone)]
+pub(crate) struct MockResponse {
+    status: String,
+    headers: Vec<(String, String)>,
+    body: String,
+}
+
+impl MockResponse {
+    /// Creates a response with a status line, e.g. "202 Accepted", and a body
+    pub fn new(status: &str, body: impl Into<String>) -> Self {
+        Self {
+            status: status.to_string(),
+            headers: Vec::new(),
+            body: body.into(),
+        }
+    }
+
+    /// Creates a response with a JSON body
+    pub fn json(status: &str, body: &Value) -> Self {
+        Self::new(status, body.to_string()).with_header("Content-Type", "application/json")
+    }
+
+    /// Adds a response header
+    pub fn with_header(mut self, name: &str, value: &str) -> Self {
+        self.headers.push((name.to_string(), value.to_string()));
+        self
+    }
+}
+
+/// Serves the responses in order, one per connection
+///
+/// # Returns
+///
+/// * `(String, JoinHandle<Vec<MockRequest>>)` - The base URL of the server,
+///   and a handle returning the received requests once every response was
+///   served
+pub(crate) fn serve(responses: Vec<MockResponse>) -> (String, JoinHandle<Vec<MockRequest>>) {
+    let count = responses.len();
+    let mut responses = responses.into_iter();
+    serve_with(count, move |_, _| responses.next().unwrap())
+}
+
+/// Serves `count` connections, answering the n-th request (starting at 0)
+/// with `respond(n, request)`
+///
+/// # Returns
+///
+/// * `(String, JoinHandle<Vec<MockRequest>>)` - The base URL of the server,
+///   and a handle returning the received requests
+pub(crate) fn serve_with(
+    count: usize,
+    mut respond: impl FnMut(usize, &MockRequest) -> MockResponse + Send + 'static,
+) -> (String, JoinHandle<Vec<MockRequest>>) {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let base_url = format!("http://{}", listener.local_addr().unwrap());
+    let server = std::thread::spawn(move || {
+        let mut requests = Vec::new();
+        for n in 0..count {
+            let (mut stream, _) = listener.accept().unwrap();
+            let request = read_request(&stream);
+            write_response(&mut stream, &respond(n, &request));
+            requests.push(request);
+        }
+        requests
+    });
+    (base_url, server)
+}
+
+/// Serves connections until the test process exits, answering the n-th
+/// request (starting at 0) with `respond(n, request)`
+///
+/// # Returns
+///
+/// * `(String, Arc<AtomicUsize>)` - The base URL of the server and the number
+///   of requests received so far
+pub(crate) fn serve_forever(
+    respond: impl Fn(usize, &MockRequest) -> MockResponse + Send + 'static,
+) -> (String, Arc<AtomicUsize>) {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let base_url = format!("http://{}", listener.local_addr().unwrap());
+    let requests = Arc::new(AtomicUsize::new(0));
+    let counter = Arc::clone(&requests);
+    std::thread::spawn(move || {
+        for stream in listener.incoming() {
+            let mut stream = stream.unwrap();
+            let request = read_request(&stream);
+            let response = respond(counter.fetch_add(1, Ordering::SeqCst), &request);
+            write_response(&mut stream, &response);
+        }
+    });
+    (base_url, requests)
+}
+
+/// Reads the request line, headers and `Content-Length` body of a request
+fn read_request(stream: &TcpStream) -> MockRequest {
+    let mut reader = BufReader::new(stream.try_clone().unwrap());
+    let mut request_line = String::new();
+    reader.read_line(&mut request_line).unwrap();
+    let mut parts = request_line.split_whitespace();
+    let method = parts.next().unwrap_or_default().to_string();
+    let path = parts.next().unwrap_or_default().to_string();
+
+    let mut headers = HashMap::new();
+    loop {
+        let mut line = String::new();
+        reader.read_line(&mut line).unwrap();
+        if line.trim().is_empty() {
+            break;
+        }
+        if let Some((name, value)) = line.split_once(':') {
+            headers.insert(name.to_ascii_lowercase(), value.trim().to_string());
+        }
+    }
+    let content_length = headers
+        .get("content-length")
+        .map_or(0, |length| length.parse().unwrap());
+    let mut body = vec![0; content_length];
+    reader.read_exact(&mut body).unwrap();
+
+    MockRequest {
+        method,
+        path,
+        headers,
+        body,
+    }
+}
+
+/// Writes a response and its `Content-Length`
+fn write_response(stream: &mut TcpStream, response: &MockResponse) {
+    let mut raw = format!(
+        "HTTP/1.1 {}\r\nContent-Length: {}\r\nConnection: close\r\n",
+        response.status,
+        response.body.len()
+    );
+    for (name, value) in &response.headers {
+        raw.push_str(&format!("{}: {}\r\n", name, value));
+    }
+    raw.push_str("\r\n");
+    raw.push_str(&response.body);
+    stream.write_all(raw.as_bytes()).unwrap();
+}
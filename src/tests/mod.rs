@@ -7,10 +7,21 @@
 //!
 //! Most tests use mock data rather than making real API calls, allowing
 //! for fast and reliable test execution without external dependencies.
+//! The clients of external services are tested against `mock_server`.
 
 #[cfg(test)]
+pub(crate) mod alloc_counter;
+pub(crate) mod mock_server;
+
+#[cfg(test)]
+#[allow(clippy::module_inception)]
 mod tests {
-    use crate::services::github::create_mock_metrics;
+    use super::mock_server::{self, MockResponse};
+    use crate::config::Config;
+    use crate::error::GhrustError;
+    use crate::services::datadog::{DatadogDestination, DatadogError};
+    use crate::services::github::{create_mock_metrics, GitHubError};
+    use crate::MetricsPipeline;
 
     /// Test the create_mock_metrics function
     ///
@@ -29,4 +40,103 @@ mod tests {
         assert_eq!(metrics.total_active_users, Some(100));
         assert_eq!(metrics.total_engaged_users, Some(80));
     }
+
+    /// Test that wrapped service errors keep their kind
+    ///
+    /// Retry and alerting decisions depend on the original error, so wrapping
+    /// it in `GhrustError` must not turn it into a plain message.
+    #[test]
+    fn test_error_kinds_are_preserved() {
+        let rate_limited = GhrustError::github("Failed to fetch team metrics")(
+            GitHubError::RateLimit("slow down".to_string()),
+        );
+        assert_eq!(rate_limited.kind(), "github_rate_limit");
+        assert!(rate_limited.is_retryable());
+        assert_eq!(
+            rate_limited.to_string(),
+            "Failed to fetch team metrics: Rate limit exceeded: slow down"
+        );
+
+        let unauthorized = GhrustError::from(GitHubError::Authentication("bad".to_string()));
+        assert_eq!(unauthorized.kind(), "github_auth");
+        assert!(!unauthorized.is_retryable());
+
+        let server_error = GhrustError::from(DatadogError::HttpError(503, String::new()));
+        assert_eq!(server_error.kind(), "datadog");
+        assert!(server_error.is_retryable());
+        assert!(!GhrustError::from(DatadogError::HttpError(400, String::new())).is_retryable());
+    }
+
+    /// Test that a run report exposes failures and serializes every section
+    #[test]
+    fn test_run_report() {
+        let mut report = crate::RunReport::default();
+        assert!(!report.has_failures());
+        assert_eq!(report.run_id.len(), 16);
+        assert!(report.run_id.chars().all(|c| c.is_ascii_hexdigit()));
+        assert_ne!(report.run_id, crate::RunReport::default().run_id);
+
+        report
+            .enterprises
+            .push(serde_json::json!({ "enterprise_id": "acme" }));
+        report.failures.push("acme seats: boom".to_string());
+        assert!(report.has_failures());
+
+        let value = serde_json::to_value(&report).unwrap();
+        assert_eq!(value["enterprises"][0]["enterprise_id"], "acme");
+        assert_eq!(value["failures"][0], "acme seats: boom");
+        assert_eq!(value["run_id"], report.run_id.as_str());
+        assert!(value["summary"]["scopes"].as_array().unwrap().is_empty());
+    }
+
+    /// Test that the heartbeat is sent before the run is set up
+    ///
+    /// A store that can't be opened fails the run, but the heartbeat must
+    /// already have reached Datadog so the run isn't mistaken for a collector
+    /// that didn't run at all.
+    #[test]
+    fn test_heartbeat_sent_before_setup() {
+        let (base_url, server) = mock_server::serve(vec![MockResponse::new("202 Accepted", "{}")]);
+
+        assert!(MetricsPipeline::new(unopenable_store_config(base_url))
+            .run_blocking()
+            .is_err());
+        let requests = server.join().unwrap();
+        assert_eq!(requests.len(), 1);
+        let series = &requests[0].json()["series"];
+        assert!(series[0]["metric"]
+            .as_str()
+            .unwrap()
+            .ends_with("collector.heartbeat"));
+    }
+
+    /// Test that the async entry point returns the outcome of the run
+    #[cfg(feature = "async")]
+    #[tokio::test]
+    async fn test_async_run_returns_run_errors() {
+        let (base_url, server) = mock_server::serve(vec![MockResponse::new("202 Accepted", "{}")]);
+
+        let result = MetricsPipeline::new(unopenable_store_config(base_url))
+            .run()
+            .await;
+        assert_eq!(result.unwrap_err().kind(), "store");
+        assert_eq!(server.join().unwrap().len(), 1);
+    }
+
+    /// Returns a configuration sending to `site` whose store can't be opened
+    fn unopenable_store_config(site: String) -> Config {
+        let mut config = temp_env::with_vars(
+            [
+                ("GITHUB_TOKEN", Some("ghp_test")),
+                ("DATADOG_API_KEY", Some("dd_test")),
+                ("GITHUB_ENTERPRISE_ID", Some("acme")),
+            ],
+            || Config::from_env().unwrap(),
+        );
+        let mut destination = DatadogDestination::new("primary", "dd_test");
+        destination.site = site;
+        config.datadog_destinations = vec![destination];
+        config.store_path = Some("/nonexistent/ghrust/store.db".to_string());
+        config
+    }
 }